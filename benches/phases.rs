@@ -0,0 +1,67 @@
+//! Criterion benchmarks for the parse, resolve, and emit phases, over the
+//! synthetic protocol at several sizes.
+//!
+//! Run with `cargo bench --features bench`.
+
+use std::path::Path;
+
+use avdl::bench::{emit, parse, resolve, synthetic_protocol};
+use criterion::{BatchSize, BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+/// Copies of the synthetic declarations per fixture; each copy is about
+/// 60 lines, so the largest fixture is about 6,000 lines.
+const SIZES: &[usize] = &[1, 10, 100];
+
+fn phases(c: &mut Criterion) {
+    let fixtures: Vec<(usize, &'static str)> = SIZES
+        .iter()
+        .map(|&copies| (copies, &*synthetic_protocol(copies).leak()))
+        .collect();
+    let input_dir = Path::new(".");
+
+    let mut group = c.benchmark_group("parse");
+    for &(copies, source) in &fixtures {
+        group.throughput(Throughput::Bytes(source.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(copies),
+            &source,
+            |b, &source| {
+                b.iter(|| parse(source).expect("fixture parses"));
+            },
+        );
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("resolve");
+    for &(copies, source) in &fixtures {
+        group.throughput(Throughput::Bytes(source.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(copies),
+            &source,
+            |b, &source| {
+                b.iter(|| resolve(source, input_dir).expect("fixture resolves"));
+            },
+        );
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("emit");
+    for &(copies, source) in &fixtures {
+        group.throughput(Throughput::Bytes(source.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(copies),
+            &source,
+            |b, &source| {
+                b.iter_batched(
+                    || resolve(source, input_dir).expect("fixture resolves"),
+                    |resolved| emit(resolved).expect("fixture emits"),
+                    BatchSize::SmallInput,
+                );
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, phases);
+criterion_main!(benches);
@@ -0,0 +1,190 @@
+//! Entry points for timing the compilation phases separately, and a
+//! synthetic fixture generator. Available with the `bench` feature.
+
+// ==============================================================================
+// Benchmarking Support (the `bench` feature)
+// ==============================================================================
+//
+// Entry points for the three compilation phases, so that benchmarks can time
+// them separately, plus a synthetic fixture generator. Used by the criterion
+// benchmarks in `benches/` and by `avdl bench`.
+//
+// The phases mirror `Idl::convert`:
+//
+//   - parse   — the ANTLR parse and tree walk of the input file alone
+//   - resolve — parse again, then resolve imports and validate references
+//   - emit    — serialize the resolved protocol or schema to JSON
+//
+// `resolve` includes its own parse because the compiler has no way to start
+// from an already-parsed file; `PhaseTimings` subtracts it back out.
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use crate::Idl;
+use crate::compiler::Compiled;
+use crate::reader::{ReaderOptions, parse_idl_named};
+
+/// The output of [`resolve`], consumed by [`emit`].
+pub struct Resolved(Compiled);
+
+/// Parse `source` without resolving imports or references.
+pub fn parse(source: &'static str) -> miette::Result<()> {
+    parse_idl_named(source, "<bench>", &ReaderOptions::default()).map(|_| ())
+}
+
+/// Parse `source` and resolve its imports (relative to `input_dir`) and type
+/// references.
+pub fn resolve(source: &'static str, input_dir: &Path) -> miette::Result<Resolved> {
+    Idl::new()
+        .compile_phase(source, "<bench>", input_dir)
+        .map(Resolved)
+}
+
+/// Serialize a resolved file to its `.avpr` or `.avsc` JSON.
+pub fn emit(resolved: Resolved) -> miette::Result<Value> {
+    Idl::new().emit_phase(resolved.0).map(|output| output.json)
+}
+
+/// Median wall-clock time of each phase over a number of runs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PhaseTimings {
+    pub parse: Duration,
+    /// Import and reference resolution, excluding the parse.
+    pub resolve: Duration,
+    pub emit: Duration,
+}
+
+impl PhaseTimings {
+    /// The sum of all phases.
+    #[must_use]
+    pub fn total(&self) -> Duration {
+        self.parse + self.resolve + self.emit
+    }
+}
+
+/// Time each phase `iterations` times (at least once) and return the medians.
+pub fn measure(
+    source: &'static str,
+    input_dir: &Path,
+    iterations: usize,
+) -> miette::Result<PhaseTimings> {
+    let iterations = iterations.max(1);
+    let mut parses = Vec::with_capacity(iterations);
+    let mut resolves = Vec::with_capacity(iterations);
+    let mut emits = Vec::with_capacity(iterations);
+    for _ in 0..iterations {
+        let start = Instant::now();
+        parse(source)?;
+        parses.push(start.elapsed());
+
+        let start = Instant::now();
+        let resolved = resolve(source, input_dir)?;
+        resolves.push(start.elapsed());
+
+        let start = Instant::now();
+        std::hint::black_box(emit(resolved)?);
+        emits.push(start.elapsed());
+    }
+    let parse = median(&mut parses);
+    Ok(PhaseTimings {
+        parse,
+        resolve: median(&mut resolves).saturating_sub(parse),
+        emit: median(&mut emits),
+    })
+}
+
+fn median(samples: &mut [Duration]) -> Duration {
+    samples.sort_unstable();
+    samples[samples.len() / 2]
+}
+
+/// A self-contained protocol with `copies` copies of a representative set of
+/// declarations: nested records with defaults, enums, fixed types, logical
+/// types, unions, arrays, maps, doc comments, annotations, and messages.
+/// Each copy is about 60 lines.
+#[must_use]
+pub fn synthetic_protocol(copies: usize) -> String {
+    let mut idl = String::from(
+        "/** Synthetic benchmark protocol. */\n\
+         @namespace(\"bench.synthetic\")\n\
+         protocol Synthetic {\n",
+    );
+    for i in 0..copies {
+        idl.push_str(&format!(
+            r#"
+  /** Status of an order, copy {i}. */
+  enum Status{i} {{ PENDING, PAID, SHIPPED, DELIVERED, CANCELLED }} = PENDING;
+
+  fixed Hash{i}(32);
+
+  /** A monetary amount. */
+  record Money{i} {{
+    @java-class("java.math.BigDecimal") decimal(18, 4) amount;
+    string currency = "USD";
+  }}
+
+  record Address{i} {{
+    string street;
+    string city;
+    string? region = null;
+    string postalCode;
+    string country = "US";
+  }}
+
+  record LineItem{i} {{
+    string sku;
+    int quantity = 1;
+    Money{i} unitPrice;
+    map<string> attributes = {{}};
+  }}
+
+  /** An order, copy {i}. */
+  record Order{i} {{
+    string @aliases(["OrderV1_{i}"]) id;
+    Status{i} status = "PENDING";
+    timestamp_ms createdAt;
+    date? deliveryDate = null;
+    uuid customerId;
+    Address{i} shippingAddress;
+    union {{ null, Address{i} }} billingAddress = null;
+    array<LineItem{i}> items = [];
+    map<array<string>> tags = {{}};
+    Hash{i} checksum;
+    bytes @order("ignore") payload = "";
+  }}
+
+  error OrderNotFound{i} {{
+    string id;
+    string message = "not found";
+  }}
+
+  Order{i} getOrder{i}(string id) throws OrderNotFound{i};
+  array<Order{i}> listOrders{i}(Status{i} status, int limit = 100);
+  void cancelOrder{i}(string id, string reason = "customer request");
+  void ping{i}() oneway;
+"#
+        ));
+    }
+    idl.push_str("}\n");
+    idl
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn synthetic_protocol_compiles() {
+        let source: &'static str = synthetic_protocol(3).leak();
+        let timings = measure(source, Path::new("."), 1).expect("fixture should compile");
+        assert!(timings.total() > Duration::ZERO);
+
+        let json =
+            emit(resolve(source, Path::new(".")).expect("resolves")).expect("fixture should emit");
+        assert_eq!(json["types"].as_array().map(Vec::len), Some(3 * 7));
+        assert_eq!(json["messages"].as_object().map(|m| m.len()), Some(3 * 4));
+    }
+}
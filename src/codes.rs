@@ -0,0 +1,609 @@
+// ==============================================================================
+// Diagnostic Codes
+// ==============================================================================
+//
+// Every error from compiling a file and every warning carries a stable code
+// such as `AVDL0004`, shown in the diagnostic header and explained in full by
+// `avdl explain AVDL0004`. Codes are never renumbered or reused, so they can be
+// referenced from suppression rules, documentation, and issue reports.
+//
+// The code is attached where each diagnostic is built, never derived from its
+// message, so rewording a message cannot change it. Errors take their code
+// from the `ErrorKind` of their `ParseDiagnostic`, or, without a source
+// location, from `miette!(code = ...)`. Warnings take theirs from their
+// `WarningKind`.
+//
+// Errors 0001-0099, warnings 0101-0199.
+
+/// A stable diagnostic code with a one-line title and an extended
+/// explanation, as printed by `avdl explain`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiagnosticCode {
+    /// The code, e.g. `"AVDL0004"`.
+    pub code: &'static str,
+    /// A short description, e.g. `"duplicate field or parameter"`.
+    pub title: &'static str,
+    /// What causes the diagnostic and how to fix it, with examples.
+    pub explanation: &'static str,
+}
+
+/// Every diagnostic code, errors first, in numeric order.
+pub const DIAGNOSTIC_CODES: &[DiagnosticCode] = &[
+    DiagnosticCode {
+        code: "AVDL0001",
+        title: "syntax error",
+        explanation: "\
+The source does not follow the Avro IDL grammar: a token is missing, extra,
+or misspelled. The message names the line and column and, where it can,
+what was expected there.
+
+    protocol Shop {
+        record Order { string id }    // missing `;` after `id`
+    }
+
+Fix the source at the reported position. When several syntax errors are
+found, all of them are reported, but later ones may be caused by the first.",
+    },
+    DiagnosticCode {
+        code: "AVDL0002",
+        title: "undefined name",
+        explanation: "\
+A type is referenced by a name that is neither a primitive type nor a named
+type declared in the file or its imports. Names without a dot are looked up
+in the enclosing namespace.
+
+    @namespace(\"ex\") protocol Shop {
+        record Order { Customer buyer; }    // no type `ex.Customer`
+    }
+
+Declare the type, import the file that declares it, fix the spelling, or
+use its full name if it is in another namespace (`com.example.Customer`).",
+    },
+    DiagnosticCode {
+        code: "AVDL0003",
+        title: "duplicate type name",
+        explanation: "\
+Two named types (records, errors, enums, or fixed) have the same full name,
+either in one file or across a file and its imports.
+
+    protocol Shop {
+        record Order { string id; }
+        record Order { long id; }
+    }
+
+Rename one of them, or move one into another namespace with `@namespace`.",
+    },
+    DiagnosticCode {
+        code: "AVDL0004",
+        title: "duplicate field or parameter",
+        explanation: "\
+A record declares two fields with the same name, or a message declares two
+parameters with the same name.
+
+    record Order {
+        string id;
+        long id;
+    }
+
+Rename or remove one of them. To rename a field without breaking readers of
+old data, give the new field the old name as an alias:
+`string @aliases([\"id\"]) order_id;`.",
+    },
+    DiagnosticCode {
+        code: "AVDL0005",
+        title: "duplicate enum symbol",
+        explanation: "\
+An enum lists the same symbol twice.
+
+    enum Status { NEW, SHIPPED, NEW }
+
+Remove the repeated symbol.",
+    },
+    DiagnosticCode {
+        code: "AVDL0006",
+        title: "duplicate type in union",
+        explanation: "\
+A union contains the same type more than once, which the Avro specification
+forbids because a value could not be matched to a single branch. This
+includes unnamed types of the same kind, such as two arrays.
+
+    union { null, string, null } note;
+
+Remove the repeated branch.",
+    },
+    DiagnosticCode {
+        code: "AVDL0007",
+        title: "nested union",
+        explanation: "\
+A union contains another union directly, which the Avro specification
+forbids.
+
+    union { null, union { int, long } } count;
+
+Flatten the branches into one union: `union { null, int, long } count;`, or
+pass `--flatten-nested-unions` to have avdl do so with a warning.",
+    },
+    DiagnosticCode {
+        code: "AVDL0008",
+        title: "invalid name",
+        explanation: "\
+A protocol, type, or namespace name is not a valid Avro name. Names
+must start with a letter or `_` and contain only letters, digits, and `_`,
+and a named type may not be called like a primitive type.
+
+    record int { string value; }
+
+Choose another name. Keywords can be used as field names by escaping them
+with backticks: `string `error`;`.",
+    },
+    DiagnosticCode {
+        code: "AVDL0009",
+        title: "invalid default value",
+        explanation: "\
+A field's default value does not match the field's type. For a union, the
+default must match the first branch.
+
+    record Order {
+        int quantity = \"one\";
+        union { null, string } note = \"none\";
+    }
+
+Use a value of the right type (`int quantity = 1;`), or reorder the union so
+the default's type comes first (`union { string, null } note = \"none\";`).",
+    },
+    DiagnosticCode {
+        code: "AVDL0010",
+        title: "enum default is not a symbol",
+        explanation: "\
+An enum's default is not one of its symbols.
+
+    enum Status { NEW, SHIPPED } = UNKNOWN;
+
+Add the symbol to the enum, or use an existing one as the default.",
+    },
+    DiagnosticCode {
+        code: "AVDL0011",
+        title: "annotated type reference",
+        explanation: "\
+An annotation is placed on a reference to a named type. It is unclear
+whether it should apply to the type or to the field, so it is rejected.
+
+    record Order { @deprecated(true) Customer buyer; }
+
+Annotate the field name instead (`Customer @deprecated(true) buyer;`), or
+annotate the type's declaration.",
+    },
+    DiagnosticCode {
+        code: "AVDL0012",
+        title: "reserved property",
+        explanation: "\
+An annotation sets a property that Avro itself defines, such as `doc`,
+`type`, `fields`, or `default`.
+
+    @doc(\"An order.\") record Order { string id; }
+
+Use the IDL syntax for the property instead: a `/** ... */` doc comment, or
+`= value` for a default.",
+    },
+    DiagnosticCode {
+        code: "AVDL0013",
+        title: "invalid annotation value",
+        explanation: "\
+An annotation that Avro interprets has a value of the wrong form:
+`@order` must be \"ascending\", \"descending\", or \"ignore\", and
+`@aliases` must be an array of strings. Annotation handlers registered with
+`Idl::annotation_handler` can reject custom annotations in the same way.
+
+    string @order(\"down\") id;
+    @aliases(\"Purchase\") record Order { string id; }
+
+Use a value of the expected form, e.g. `@aliases([\"Purchase\"])`.",
+    },
+    DiagnosticCode {
+        code: "AVDL0014",
+        title: "one-way message returns a value",
+        explanation: "\
+A message marked `oneway` has a response type other than `void`. The sender
+of a one-way message does not wait for a response, so it cannot have one.
+
+    Receipt submit(Order order) oneway;
+
+Declare the response as `void`, or remove `oneway`.",
+    },
+    DiagnosticCode {
+        code: "AVDL0015",
+        title: "invalid fixed size",
+        explanation: "\
+The size of a `fixed` type is not a non-negative integer that fits in 32
+bits.
+
+    fixed Hash(-16);
+
+Give the size in bytes as a plain integer: `fixed Hash(16);`.",
+    },
+    DiagnosticCode {
+        code: "AVDL0016",
+        title: "invalid decimal",
+        explanation: "\
+A `decimal(precision, scale)` type has a precision that is not positive, or
+a scale that is negative or greater than the precision.
+
+    decimal(4, 6) price;
+
+Use a precision of at least 1 and a scale between 0 and the precision:
+`decimal(9, 2) price;`.",
+    },
+    DiagnosticCode {
+        code: "AVDL0017",
+        title: "no protocol or schema",
+        explanation: "\
+`avdl idl` compiles a file to a single protocol or schema, but the file
+declares neither a protocol nor a `schema <type>;`.
+
+    namespace ex;
+    record Order { string id; }
+
+Wrap the declarations in `protocol Name { ... }`, add `schema Order;`, or
+use `avdl idl2schemata` to extract each named type to its own file.",
+    },
+    DiagnosticCode {
+        code: "AVDL0018",
+        title: "import not found",
+        explanation: "\
+An imported file does not exist relative to the importing file or in any
+import directory.
+
+    import schema \"Customer.avsc\";
+
+Fix the path, which is relative to the importing file, or pass the
+directory that contains it with `--import-dir`.",
+    },
+    DiagnosticCode {
+        code: "AVDL0019",
+        title: "invalid import",
+        explanation: "\
+An imported file was found but could not be used: it could not be read, it
+is not valid JSON or IDL, or it is of the wrong kind for the import
+statement. The cause is shown below the message.
+
+    import schema \"Shop.avpr\";    // a protocol, not a schema
+
+Fix the imported file, or use the import kind that matches it: `import idl`
+for `.avdl`, `import protocol` for `.avpr`, and `import schema` for `.avsc`.",
+    },
+    DiagnosticCode {
+        code: "AVDL0020",
+        title: "public type references internal type",
+        explanation: "\
+A type or message that is part of the public API references a type marked
+`@visibility(\"internal\")`, so the internal type would be exposed through it.
+
+    @visibility(\"internal\") record Cost { long cents; }
+    record Order { Cost cost; }
+
+Mark the referencing type or message internal too, or make the referenced
+type public.",
+    },
+    DiagnosticCode {
+        code: "AVDL0021",
+        title: "cannot read file",
+        explanation: "\
+The input file, or an imported file that was found, could not be read: it
+does not exist, is a directory, or is not readable by the current user.
+
+Check the path and its permissions. The operating system's reason is shown
+after the file name.",
+    },
+    DiagnosticCode {
+        code: "AVDL0022",
+        title: "warnings are denied",
+        explanation: "\
+Compilation produced warnings, and `--deny-warnings` (or
+`Idl::deny_warnings`) turns them into an error. The warnings are printed
+before this error.
+
+Fix the warnings, suppress the ones you accept with `--allow KIND`, or stop
+denying warnings.",
+    },
+    DiagnosticCode {
+        code: "AVDL0023",
+        title: "unsupported encoding",
+        explanation: "\
+The input file, or an imported file, is neither UTF-8 nor UTF-16. Avro IDL
+and JSON sources are UTF-8; UTF-16 (with or without a byte order mark) is
+transcoded automatically, but single-byte encodings such as Latin-1 or
+Windows-1252 cannot be told apart reliably and are rejected rather than
+misread. The message shows the first byte that is not valid UTF-8.
+
+Re-save the file as UTF-8, e.g. with `iconv -f latin1 -t utf-8`.",
+    },
+    DiagnosticCode {
+        code: "AVDL0024",
+        title: "nesting too deep",
+        explanation: "\
+The input nests more deeply than the limit, 128 levels by default: either
+brackets (`{`, `[`, `(`, `<`) nest too deeply in the source, or a type
+nests too deeply once the types it references, which the JSON output
+inlines at their first use, are counted too. The limit keeps deeply nested
+or generated input from overflowing the stack.
+
+    record A1 { A2 a; }    record A2 { A3 a; }    ...    // 200 records deep
+
+Flatten the nesting, or raise the limit with `--max-nesting-depth N` (or
+`Idl::max_nesting_depth`).",
+    },
+    DiagnosticCode {
+        code: "AVDL0101",
+        title: "out-of-place doc comment",
+        explanation: "\
+A `/** ... */` doc comment is somewhere no declaration can take it, so it is
+ignored. Doc comments belong directly before a protocol, type, field,
+enum, or message.
+
+    record /** ignored */ Order { string id; }
+
+Move the comment before the declaration, or use `/* ... */` for an
+ordinary comment. Suppress with `--allow out-of-place-doc`.",
+    },
+    DiagnosticCode {
+        code: "AVDL0102",
+        title: "unrecognized token",
+        explanation: "\
+The source contains a character the IDL lexer does not recognize, such as a
+control character. It is skipped.
+
+Remove the character. Suppress with `--allow unrecognized-token`.",
+    },
+    DiagnosticCode {
+        code: "AVDL0103",
+        title: "annotation on union",
+        explanation: "\
+An annotation is placed on a union type. Avro unions cannot carry
+properties, so the annotation is ignored.
+
+    @java-class(\"Note\") union { null, string } note;
+
+Annotate the field name or one of the branches instead. Suppress with
+`--allow union-annotation`.",
+    },
+    DiagnosticCode {
+        code: "AVDL0104",
+        title: "invalid alias name",
+        explanation: "\
+An alias is not a valid Avro name. Any string is accepted so that aliases
+can map old, illegal names, but other tools may reject it.
+
+    @aliases([\"old-order\"]) record Order { string id; }
+
+Use a valid name if the alias is not needed for old data. Suppress with
+`--allow alias-name`.",
+    },
+    DiagnosticCode {
+        code: "AVDL0105",
+        title: "unknown declaration skipped",
+        explanation: "\
+With `--skip-unknown-declarations`, a declaration from a newer IDL grammar
+was skipped instead of failing the build.
+
+Upgrade avdl to compile the declaration. Suppress with
+`--allow unknown-declaration`.",
+    },
+    DiagnosticCode {
+        code: "AVDL0106",
+        title: "unused type",
+        explanation: "\
+With `--warn-unused-types`, a named type is declared but no other type and
+no message references it.
+
+Remove the type, or reference it. Suppress with `--allow unused-type`.",
+    },
+    DiagnosticCode {
+        code: "AVDL0107",
+        title: "annotation warning",
+        explanation: "\
+An annotation handler registered with `Idl::annotation_handler` reported a
+problem with a custom annotation, such as `@pii(\"email\")`, that does not
+fail the build.
+
+The message comes from the handler. Suppress with `--allow annotation`.",
+    },
+    DiagnosticCode {
+        code: "AVDL0108",
+        title: "record always contains itself",
+        explanation: "\
+A record requires itself, directly or through other records, with nothing
+along the way that can end the recursion, so no value of it can ever be
+written. The schema is valid Avro, but it is always a modeling mistake.
+
+    record Node { string label; Node next; }
+
+Make one field on the cycle optional, e.g. `Node? next`, or use an array or
+map, e.g. `array<Node> children`. Suppress with `--allow recursive-type`.",
+    },
+    DiagnosticCode {
+        code: "AVDL0109",
+        title: "nested union flattened",
+        explanation: "\
+With `--flatten-nested-unions`, a union directly inside another union was
+merged into it. Without the option this is an error (`AVDL0007`), since the
+Avro specification forbids nested unions.
+
+    union { null, union { int, string } } value;
+
+Write the branches in one union instead: `union { null, int, string }`.
+Suppress with `--allow nested-union`.",
+    },
+    DiagnosticCode {
+        code: "AVDL0110",
+        title: "namespace outside the required prefix",
+        explanation: "\
+With `--require-namespace-prefix`, a namespace was declared outside the
+required prefix, or a protocol or named type has no namespace at all. The
+prefix is compared by component, so `com.mycorp` accepts `com.mycorp` and
+`com.mycorp.billing`, but not `com.mycorpse`.
+
+    @namespace(\"org.rogue\") record Stray { int x; }
+
+The warning points at the `@namespace` annotation, `namespace` statement, or
+dotted name that declared the namespace. Move the type into a namespace under
+the prefix. Suppress with `--allow namespace-prefix`.",
+    },
+    DiagnosticCode {
+        code: "AVDL0111",
+        title: "name breaks a naming convention",
+        explanation: "\
+With `--lint-names`, a name breaks the convention its rule checks:
+`type-names` wants PascalCase record, error, enum, and fixed names,
+`field-names` and `message-names` want camelCase, and `enum-symbols` wants
+SCREAMING_SNAKE_CASE.
+
+    record user_info { string UserId; }
+
+The warning suggests a name that follows the convention, here `UserInfo` and
+`userId`. Renaming a field or symbol changes the schema; add the old name to
+`@aliases` to keep reading old data. Suppress with
+`--allow naming-convention`.",
+    },
+    DiagnosticCode {
+        code: "AVDL0112",
+        title: "union has too many branches",
+        explanation: "\
+With `--max-union-branches N`, an explicit union has more than N branches.
+Large unions are hard to evolve and to handle in generated code.
+
+    union { null, int, long, string, bytes } id;
+
+Consider a record with a field per alternative, or an enum and a payload.
+The `T?` shorthand always has two branches and is not checked. Suppress with
+`--allow union-size`.",
+    },
+    DiagnosticCode {
+        code: "AVDL0113",
+        title: "nullable type not in the required style",
+        explanation: "\
+With `--nullable-style shorthand`, a `union { null, T }` could be written as
+`T?`; with `--nullable-style union`, a `T?` should be written out as a union.
+Both forms compile to the same schema.
+
+    union { null, string } name;   // shorthand style wants: string? name;
+
+Unions whose second branch is an array, map, union, or annotated type have no
+shorthand and are not reported, nor are those on fields with a non-null
+default (`T? x = value` puts `null` last). Suppress with
+`--allow nullable-style`.",
+    },
+    DiagnosticCode {
+        code: "AVDL0114",
+        title: "declaration has no doc comment",
+        explanation: "\
+With `--require-docs`, a named type, field, or message has no doc comment.
+With `--require-docs public`, types and messages marked
+`@visibility(\"internal\")`, and the fields of such types, are exempt.
+
+    record User { string name; }
+
+Add a `/** ... */` comment right before the declaration; only whitespace may
+separate them. A `//` or `/* ... */` comment is not a doc comment. Suppress
+with `--allow missing-doc`.",
+    },
+    DiagnosticCode {
+        code: "AVDL0115",
+        title: "invalid default accepted",
+        explanation: "\
+With `--no-validate-defaults`, a field default does not match the field's
+type. Without the option this is an error. The default is emitted as
+written, so readers that apply it may fail.
+
+    int count = \"many\";
+
+Fix the default, or drop it. Suppress with `--allow invalid-default`.",
+    },
+];
+
+/// Look up a diagnostic code, ignoring case (`avdl0004` finds `AVDL0004`).
+#[must_use]
+pub fn explain(code: &str) -> Option<&'static DiagnosticCode> {
+    DIAGNOSTIC_CODES
+        .iter()
+        .find(|entry| entry.code.eq_ignore_ascii_case(code))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ErrorKind, WarningKind};
+
+    #[test]
+    fn codes_are_unique_and_explained() {
+        let mut seen = std::collections::HashSet::new();
+        for entry in DIAGNOSTIC_CODES {
+            assert!(seen.insert(entry.code), "duplicate code {}", entry.code);
+        }
+        for kind in ErrorKind::ALL {
+            assert!(
+                explain(kind.code()).is_some(),
+                "{kind:?} has no explanation"
+            );
+            assert_eq!(ErrorKind::from_code(kind.code()), Some(kind));
+        }
+        for kind in WarningKind::ALL {
+            assert!(
+                explain(kind.code()).is_some(),
+                "{kind:?} has no explanation"
+            );
+        }
+        assert_eq!(
+            explain("avdl0004").map(|entry| entry.code),
+            Some("AVDL0004")
+        );
+        assert_eq!(explain("AVDL9999"), None);
+    }
+
+    /// A `ParseDiagnostic` cannot be built without a kind, so the errors to
+    /// check are the ones built with `miette!` and `bail!` in the modules that
+    /// compile a file: each must take its code from an [`ErrorKind`] before
+    /// its message, so that no code is spelled out twice.
+    #[test]
+    fn every_error_site_has_a_code() {
+        // Errors that are not about the input, and so have no code.
+        const UNCODED: &[&str] = &["\"{e}: write JSON\""];
+        let sources = [
+            ("compiler.rs", include_str!("compiler.rs")),
+            ("defaults.rs", include_str!("defaults.rs")),
+            ("import.rs", include_str!("import.rs")),
+            ("reader.rs", include_str!("reader.rs")),
+            ("resolve.rs", include_str!("resolve.rs")),
+            ("visibility.rs", include_str!("visibility.rs")),
+        ];
+        let mut uncoded = Vec::new();
+        for (file, source) in sources {
+            let source = source
+                .split("\n#[cfg(test)]\nmod tests")
+                .next()
+                .unwrap_or(source);
+            let calls = source
+                .match_indices("miette!(")
+                .chain(source.match_indices("bail!("));
+            for (at, call) in calls {
+                let line = source[..at].matches('\n').count() + 1;
+                let args = source[at + call.len()..].trim_start();
+                if UNCODED.iter().any(|message| args.starts_with(message)) {
+                    continue;
+                }
+                let Some(quote) = args.find('"') else {
+                    uncoded.push(format!("{file}:{line}"));
+                    continue;
+                };
+                let kind = args[..quote]
+                    .strip_prefix("code = ErrorKind::")
+                    .and_then(|rest| rest.split_once(".code()"))
+                    .map(|(kind, _)| kind);
+                if !kind.is_some_and(|kind| ErrorKind::ALL.iter().any(|k| format!("{k:?}") == kind))
+                {
+                    uncoded.push(format!("{file}:{line}"));
+                }
+            }
+        }
+        assert!(uncoded.is_empty(), "errors without a code at {uncoded:?}");
+    }
+}
@@ -0,0 +1,123 @@
+// ==============================================================================
+// `bench` Subcommand
+// ==============================================================================
+//
+// `avdl bench [INPUT...]` -- time each compilation phase.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use lexopt::prelude::*;
+
+use crate::{expand_avdl_inputs, write_output};
+
+const HELP: &str = "\
+Usage: avdl bench [OPTIONS] [INPUT...]
+
+Compiles each INPUT (an .avdl file, or a directory searched recursively for
+.avdl files) repeatedly and prints the median time of the parse, resolve, and
+emit phases.
+
+Options:
+      --self-test         Also time the built-in synthetic protocol at 1, 10, and 100 copies
+      --iterations <N>    Runs per input (default: 10)
+  -h, --help              Print help";
+
+/// Parsed CLI arguments for the `bench` subcommand.
+pub(crate) struct Args {
+    inputs: Vec<PathBuf>,
+    self_test: bool,
+    iterations: usize,
+}
+
+/// Parse options and positional inputs for the `bench` subcommand.
+pub(crate) fn parse_args(parser: &mut lexopt::Parser) -> Result<Args, lexopt::Error> {
+    let mut inputs = Vec::new();
+    let mut self_test = false;
+    let mut iterations = 10;
+
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Long("self-test") => {
+                self_test = true;
+            }
+            Long("iterations") => {
+                iterations = parser.value()?.parse()?;
+            }
+            Short('h') | Long("help") => {
+                println!("{HELP}");
+                std::process::exit(0);
+            }
+            Value(val) => {
+                inputs.push(PathBuf::from(val));
+            }
+            _ => return Err(arg.unexpected()),
+        }
+    }
+
+    if inputs.is_empty() && !self_test {
+        return Err(lexopt::Error::MissingValue {
+            option: Some("INPUT (or --self-test)".to_string()),
+        });
+    }
+
+    Ok(Args {
+        inputs,
+        self_test,
+        iterations,
+    })
+}
+
+/// Time each compilation phase for the built-in fixtures and every input
+/// file, printing one row per file.
+pub(crate) fn run(args: Args) -> miette::Result<()> {
+    use avdl::bench::{PhaseTimings, measure, synthetic_protocol};
+
+    let mut rows: Vec<(String, PhaseTimings)> = Vec::new();
+    if args.self_test {
+        for copies in [1, 10, 100] {
+            let source: &'static str = synthetic_protocol(copies).leak();
+            let timings = measure(source, Path::new("."), args.iterations)
+                .map_err(|e| e.wrap_err("built-in fixture failed to compile"))?;
+            rows.push((format!("<synthetic {copies}x>"), timings));
+        }
+    }
+
+    for file in expand_avdl_inputs(&args.inputs) {
+        let source: &'static str = fs::read_to_string(&file)
+            .map_err(|e| miette::miette!("{e}: read {}", file.display()))?
+            .leak();
+        let input_dir = file.parent().unwrap_or(Path::new("."));
+        let timings = measure(source, input_dir, args.iterations)
+            .map_err(|e| e.wrap_err(format!("compile {}", file.display())))?;
+        rows.push((file.display().to_string(), timings));
+    }
+
+    let width = rows
+        .iter()
+        .map(|(name, _)| name.len())
+        .chain(["input".len()])
+        .max()
+        .unwrap_or_default();
+    let mut text = format!(
+        "{:<width$}  {:>10}  {:>10}  {:>10}  {:>10}\n",
+        "input", "parse", "resolve", "emit", "total"
+    );
+    for (name, timings) in &rows {
+        let cells = [
+            timings.parse,
+            timings.resolve,
+            timings.emit,
+            timings.total(),
+        ]
+        .map(|d| format!("{d:.2?}"));
+        writeln!(
+            text,
+            "{name:<width$}  {:>10}  {:>10}  {:>10}  {:>10}",
+            cells[0], cells[1], cells[2], cells[3]
+        )
+        .expect("write to String is infallible");
+    }
+    write_output(None, &text)
+}
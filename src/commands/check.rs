@@ -0,0 +1,99 @@
+// ==============================================================================
+// `check` Subcommand
+// ==============================================================================
+//
+// `avdl check INPUT` -- check that shared types agree across files.
+
+use std::path::PathBuf;
+
+use avdl::Idl2Schemata;
+use lexopt::prelude::*;
+
+use crate::print_warnings;
+
+const HELP: &str = "\
+Usage: avdl check [OPTIONS] INPUT
+
+Compiles INPUT (an .avdl file, or a directory searched recursively for .avdl
+files, each compiled on its own) and fails if the same full type name is
+defined with structurally different schemas. Docs, aliases, and custom
+properties are ignored.
+
+Options:
+      --import-dir <DIR>  Additional directories to search for imports (repeatable)
+  -h, --help              Print help";
+
+/// Parsed CLI arguments for the `check` subcommand.
+pub(crate) struct Args {
+    input: String,
+    import_dirs: Vec<PathBuf>,
+}
+
+/// Parse options and the positional input for the `check` subcommand.
+pub(crate) fn parse_args(parser: &mut lexopt::Parser) -> Result<Args, lexopt::Error> {
+    let mut import_dirs = Vec::new();
+    let mut input = None;
+
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Long("import-dir") => {
+                let val: String = parser.value()?.string()?;
+                import_dirs.push(PathBuf::from(val));
+            }
+            Short('h') | Long("help") => {
+                println!("{HELP}");
+                std::process::exit(0);
+            }
+            Value(val) if input.is_none() => {
+                input = Some(val.string()?);
+            }
+            _ => return Err(arg.unexpected()),
+        }
+    }
+
+    let input = input.ok_or_else(|| lexopt::Error::MissingValue {
+        option: Some("INPUT".to_string()),
+    })?;
+
+    Ok(Args { input, import_dirs })
+}
+
+/// Compile every input file and report named types whose definitions differ
+/// between files.
+pub(crate) fn run(args: Args) -> miette::Result<()> {
+    let mut builder = Idl2Schemata::new();
+    for dir in &args.import_dirs {
+        builder.import_dir(dir);
+    }
+
+    let output = match builder.extract(&args.input) {
+        Ok(output) => output,
+        Err(e) => {
+            print_warnings(&builder.drain_warnings(), &[]);
+            return Err(e);
+        }
+    };
+    print_warnings(&output.warnings, output.imports());
+
+    let conflicts = output.conflicts();
+    if conflicts.is_empty() {
+        return Ok(());
+    }
+    for conflict in &conflicts {
+        eprintln!(
+            "{:?}",
+            miette::miette!(
+                help = format!(
+                    "defined in `{}` and differently in `{}`",
+                    conflict.first_source, conflict.second_source
+                ),
+                "conflicting definitions of `{}`",
+                conflict.full_name
+            )
+        );
+    }
+    Err(miette::miette!(
+        "{} type(s) have conflicting definitions",
+        conflicts.len()
+    ))
+}
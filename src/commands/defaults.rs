@@ -0,0 +1,92 @@
+// ==============================================================================
+// `defaults` Subcommand
+// ==============================================================================
+//
+// `avdl defaults INPUT` -- print the effective default of every field.
+
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use avdl::Idl;
+use lexopt::prelude::*;
+
+use crate::{print_warnings, write_output};
+
+const HELP: &str = "\
+Usage: avdl defaults [OPTIONS] INPUT
+
+Prints, for every record, the value each field takes when it is absent from
+the data. Record-typed defaults are shown with the nested records' own field
+defaults filled in.
+
+Options:
+      --import-dir <DIR>  Additional directories to search for imports (repeatable)
+  -h, --help              Print help";
+
+/// Parsed CLI arguments for the `defaults` subcommand.
+pub(crate) struct Args {
+    input: String,
+    import_dirs: Vec<PathBuf>,
+}
+
+/// Parse options and the positional input for the `defaults` subcommand.
+pub(crate) fn parse_args(parser: &mut lexopt::Parser) -> Result<Args, lexopt::Error> {
+    let mut import_dirs = Vec::new();
+    let mut input = None;
+
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Long("import-dir") => {
+                let val: String = parser.value()?.string()?;
+                import_dirs.push(PathBuf::from(val));
+            }
+            Short('h') | Long("help") => {
+                println!("{HELP}");
+                std::process::exit(0);
+            }
+            Value(val) if input.is_none() => {
+                input = Some(val.string()?);
+            }
+            _ => return Err(arg.unexpected()),
+        }
+    }
+
+    let input = input.ok_or_else(|| lexopt::Error::MissingValue {
+        option: Some("INPUT".to_string()),
+    })?;
+
+    Ok(Args { input, import_dirs })
+}
+
+/// Compile the input and print the effective default of every record field.
+pub(crate) fn run(args: Args) -> miette::Result<()> {
+    let mut builder = Idl::new();
+    for dir in &args.import_dirs {
+        builder.import_dir(dir);
+    }
+
+    let output = match builder.convert(&args.input) {
+        Ok(output) => output,
+        Err(e) => {
+            print_warnings(&builder.drain_warnings(), &[]);
+            return Err(e);
+        }
+    };
+    print_warnings(&output.warnings, output.imports());
+
+    let mut text = String::new();
+    for (i, record) in output.effective_defaults().iter().enumerate() {
+        if i > 0 {
+            text.push('\n');
+        }
+        writeln!(text, "{}", record.record).expect("write to String is infallible");
+        for field in &record.fields {
+            match &field.value {
+                Some(value) => writeln!(text, "  {} = {value}", field.name),
+                None => writeln!(text, "  {} (no default)", field.name),
+            }
+            .expect("write to String is infallible");
+        }
+    }
+    write_output(None, &text)
+}
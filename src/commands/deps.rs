@@ -0,0 +1,176 @@
+// ==============================================================================
+// `deps` Subcommand
+// ==============================================================================
+//
+// `avdl deps INPUT` -- print the transitive import graph.
+
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use avdl::{Idl2Schemata, ImportEdge};
+use lexopt::prelude::*;
+
+use crate::{print_warnings, write_output};
+
+const HELP: &str = "\
+Usage: avdl deps [OPTIONS] INPUT
+
+Lists every import of INPUT and the file it resolved to, with the overlay or
+import directory that satisfied it.
+
+Options:
+      --import-dir <DIR>   Additional directories to search for imports (repeatable)
+      --overlay-dir <DIR>  Layer DIR into one import tree with the other overlay
+                           directories, earlier ones shadowing later ones (repeatable)
+      --format <FORMAT>    Output format: text (default) or json
+  -h, --help               Print help";
+
+/// Output format for the `deps` subcommand.
+enum Format {
+    Text,
+    Json,
+}
+
+/// Parsed CLI arguments for the `deps` subcommand.
+pub(crate) struct Args {
+    input: String,
+    import_dirs: Vec<PathBuf>,
+    overlay_dirs: Vec<PathBuf>,
+    format: Format,
+}
+
+/// Parse options and the positional input for the `deps` subcommand.
+pub(crate) fn parse_args(parser: &mut lexopt::Parser) -> Result<Args, lexopt::Error> {
+    let mut import_dirs = Vec::new();
+    let mut overlay_dirs = Vec::new();
+    let mut format = Format::Text;
+    let mut input = None;
+
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Long("import-dir") => {
+                let val: String = parser.value()?.string()?;
+                import_dirs.push(PathBuf::from(val));
+            }
+            Long("overlay-dir") => {
+                let val: String = parser.value()?.string()?;
+                overlay_dirs.push(PathBuf::from(val));
+            }
+            Long("format") => {
+                let val: String = parser.value()?.string()?;
+                format = match val.as_str() {
+                    "text" => Format::Text,
+                    "json" => Format::Json,
+                    _ => {
+                        return Err(lexopt::Error::UnexpectedValue {
+                            option: "--format".to_string(),
+                            value: val.into(),
+                        });
+                    }
+                };
+            }
+            Short('h') | Long("help") => {
+                println!("{HELP}");
+                std::process::exit(0);
+            }
+            Value(val) if input.is_none() => {
+                input = Some(val.string()?);
+            }
+            _ => return Err(arg.unexpected()),
+        }
+    }
+
+    let input = input.ok_or_else(|| lexopt::Error::MissingValue {
+        option: Some("INPUT".to_string()),
+    })?;
+
+    Ok(Args {
+        input,
+        import_dirs,
+        overlay_dirs,
+        format,
+    })
+}
+
+/// Compile the input and print every import statement it (transitively)
+/// contains. Uses `Idl2Schemata` so that files without a protocol or `schema`
+/// declaration are accepted too.
+pub(crate) fn run(args: Args) -> miette::Result<()> {
+    let mut builder = Idl2Schemata::new();
+    for dir in &args.import_dirs {
+        builder.import_dir(dir);
+    }
+    for dir in &args.overlay_dirs {
+        builder.overlay_dir(dir);
+    }
+
+    let output = match builder.extract(&args.input) {
+        Ok(output) => output,
+        Err(e) => {
+            print_warnings(&builder.drain_warnings(), &[]);
+            return Err(e);
+        }
+    };
+    print_warnings(&output.warnings, output.imports());
+
+    let rendered = match args.format {
+        Format::Json => {
+            let edges: Vec<serde_json::Value> = output
+                .imports()
+                .iter()
+                .map(|edge| {
+                    serde_json::json!({
+                        "importer": edge.importer,
+                        "kind": edge.kind.as_str(),
+                        "path": edge.path,
+                        "resolved": edge.resolved.display().to_string(),
+                        "root": edge.root.as_ref().map(|root| root.display().to_string()),
+                    })
+                })
+                .collect();
+            serde_json::to_string_pretty(&edges)
+                .map_err(|e| miette::miette!("serialize JSON: {e}"))?
+        }
+        Format::Text => {
+            // Group edges under their importing file, keeping the order in
+            // which importers were first seen. Nested `import idl` edges are
+            // resolved depth-first, so an importer's edges are not
+            // necessarily contiguous.
+            let mut groups: Vec<(&str, Vec<&ImportEdge>)> = Vec::new();
+            for edge in output.imports() {
+                match groups.iter_mut().find(|(f, _)| *f == edge.importer) {
+                    Some((_, edges)) => edges.push(edge),
+                    None => groups.push((edge.importer.as_str(), vec![edge])),
+                }
+            }
+
+            let mut text = String::new();
+            for (i, (importer, edges)) in groups.iter().enumerate() {
+                if i > 0 {
+                    text.push('\n');
+                }
+                writeln!(text, "{importer}").expect("write to String is infallible");
+                for edge in edges {
+                    write!(
+                        text,
+                        "  import {} \"{}\" -> {}",
+                        edge.kind.as_str(),
+                        edge.path,
+                        edge.resolved.display()
+                    )
+                    .expect("write to String is infallible");
+                    if let Some(root) = &edge.root {
+                        write!(text, " (from {})", root.display())
+                            .expect("write to String is infallible");
+                    }
+                    text.push('\n');
+                }
+            }
+            text.trim_end().to_string()
+        }
+    };
+
+    // Unlike `idl`, there is no Java output to match, so end with a newline
+    // like any other line-oriented tool.
+    write_output(None, &format!("{rendered}\n"))
+}
@@ -0,0 +1,99 @@
+// ==============================================================================
+// `diff` Subcommand
+// ==============================================================================
+//
+// `avdl diff OLD NEW` -- print the semantic differences between two files.
+
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use avdl::Idl;
+use lexopt::prelude::*;
+
+use crate::{print_warnings, write_output};
+
+const HELP: &str = "\
+Usage: avdl diff [OPTIONS] OLD NEW
+
+Compiles both files and prints how NEW differs from OLD, one change per line:
+added (+), removed (-), and changed (~) types, fields, defaults, logical types,
+and messages. Types and messages are matched by name, so reordering is not a
+change. Prints nothing if the files are equivalent.
+
+Options:
+      --import-dir <DIR>  Additional directories to search for imports (repeatable)
+  -h, --help              Print help";
+
+/// Parsed CLI arguments for the `diff` subcommand.
+pub(crate) struct Args {
+    old: String,
+    new: String,
+    import_dirs: Vec<PathBuf>,
+}
+
+/// Parse options and the two positional inputs for the `diff` subcommand.
+pub(crate) fn parse_args(parser: &mut lexopt::Parser) -> Result<Args, lexopt::Error> {
+    let mut import_dirs = Vec::new();
+    let mut old = None;
+    let mut new = None;
+
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Long("import-dir") => {
+                let val: String = parser.value()?.string()?;
+                import_dirs.push(PathBuf::from(val));
+            }
+            Short('h') | Long("help") => {
+                println!("{HELP}");
+                std::process::exit(0);
+            }
+            Value(val) if old.is_none() => {
+                old = Some(val.string()?);
+            }
+            Value(val) if new.is_none() => {
+                new = Some(val.string()?);
+            }
+            _ => return Err(arg.unexpected()),
+        }
+    }
+
+    let old = old.ok_or_else(|| lexopt::Error::MissingValue {
+        option: Some("OLD".to_string()),
+    })?;
+    let new = new.ok_or_else(|| lexopt::Error::MissingValue {
+        option: Some("NEW".to_string()),
+    })?;
+
+    Ok(Args {
+        old,
+        new,
+        import_dirs,
+    })
+}
+
+/// Compile both inputs and print the semantic changes from the first to the
+/// second.
+pub(crate) fn run(args: Args) -> miette::Result<()> {
+    let mut outputs = Vec::with_capacity(2);
+    for input in [&args.old, &args.new] {
+        let mut builder = Idl::new();
+        for dir in &args.import_dirs {
+            builder.import_dir(dir);
+        }
+        let output = match builder.convert(input) {
+            Ok(output) => output,
+            Err(e) => {
+                print_warnings(&builder.drain_warnings(), &[]);
+                return Err(e.wrap_err(format!("compile {input}")));
+            }
+        };
+        print_warnings(&output.warnings, output.imports());
+        outputs.push(output);
+    }
+
+    let mut text = String::new();
+    for change in outputs[0].diff(&outputs[1]) {
+        writeln!(text, "{change}").expect("write to String is infallible");
+    }
+    write_output(None, &text)
+}
@@ -0,0 +1,136 @@
+// ==============================================================================
+// `doc` Subcommand
+// ==============================================================================
+//
+// `avdl doc INPUT [OUTDIR]` -- generate HTML, Markdown, or JSON documentation.
+
+use std::fs;
+use std::path::PathBuf;
+
+use avdl::Idl;
+use lexopt::prelude::*;
+
+use crate::print_warnings;
+
+const HELP: &str = "\
+Usage: avdl doc [OPTIONS] INPUT [OUTDIR]
+
+Writes a static HTML site for INPUT to OUTDIR (default: the current
+directory): index.html with the named types and message signatures, and one
+<full name>.html page per named type with its doc comment, fields, symbols,
+or size. Type references link to the referenced type's page. With
+`--format markdown`, writes a single <protocol full name>.md instead. With
+`--format json`, writes <protocol full name>.docs.json, an object mapping the
+full name of each documented protocol, type, field (<type>.<field>), message
+(<protocol>.<message>), and parameter to its doc comment.
+
+Options:
+      --import-dir <DIR>  Additional directories to search for imports (repeatable)
+      --format <FORMAT>   Output format: html (default), markdown, or json
+  -h, --help              Print help";
+
+/// Output format for the `doc` subcommand.
+enum Format {
+    Html,
+    Markdown,
+    Json,
+}
+
+/// Parsed CLI arguments for the `doc` subcommand.
+pub(crate) struct Args {
+    input: String,
+    outdir: Option<PathBuf>,
+    import_dirs: Vec<PathBuf>,
+    format: Format,
+}
+
+/// Parse options and positional arguments for the `doc` subcommand.
+pub(crate) fn parse_args(parser: &mut lexopt::Parser) -> Result<Args, lexopt::Error> {
+    let mut import_dirs = Vec::new();
+    let mut format = Format::Html;
+    let mut input = None;
+    let mut outdir = None;
+
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Long("import-dir") => {
+                let val: String = parser.value()?.string()?;
+                import_dirs.push(PathBuf::from(val));
+            }
+            Long("format") => {
+                let val: String = parser.value()?.string()?;
+                format = match val.as_str() {
+                    "html" => Format::Html,
+                    "markdown" => Format::Markdown,
+                    "json" => Format::Json,
+                    _ => {
+                        return Err(lexopt::Error::UnexpectedValue {
+                            option: "--format".to_string(),
+                            value: val.into(),
+                        });
+                    }
+                };
+            }
+            Short('h') | Long("help") => {
+                println!("{HELP}");
+                std::process::exit(0);
+            }
+            Value(val) if input.is_none() => {
+                input = Some(val.string()?);
+            }
+            Value(val) if outdir.is_none() => {
+                outdir = Some(PathBuf::from(val));
+            }
+            _ => return Err(arg.unexpected()),
+        }
+    }
+
+    let input = input.ok_or_else(|| lexopt::Error::MissingValue {
+        option: Some("INPUT".to_string()),
+    })?;
+
+    Ok(Args {
+        input,
+        outdir,
+        import_dirs,
+        format,
+    })
+}
+
+/// Compile the input and write its HTML pages or Markdown file to the output
+/// directory.
+pub(crate) fn run(args: Args) -> miette::Result<()> {
+    let mut builder = Idl::new();
+    for dir in &args.import_dirs {
+        builder.import_dir(dir);
+    }
+    let output = match builder.convert(&args.input) {
+        Ok(output) => output,
+        Err(e) => {
+            print_warnings(&builder.drain_warnings(), &[]);
+            return Err(e);
+        }
+    };
+    print_warnings(&output.warnings, output.imports());
+
+    let output_dir = args.outdir.unwrap_or_else(|| PathBuf::from("."));
+    if output_dir.exists() && !output_dir.is_dir() {
+        return Err(miette::miette!(
+            "output path `{}` exists and is not a directory",
+            output_dir.display()
+        ));
+    }
+    fs::create_dir_all(&output_dir).map_err(|e| miette::miette!("{e}: create output directory"))?;
+
+    let pages = match args.format {
+        Format::Html => output.html_docs(),
+        Format::Markdown => vec![output.markdown_docs()],
+        Format::Json => vec![output.json_docs()],
+    };
+    for page in pages {
+        let file_path = output_dir.join(&page.path);
+        fs::write(&file_path, page.content)
+            .map_err(|e| miette::miette!("{e}: write {}", file_path.display()))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,113 @@
+// ==============================================================================
+// `equal` Subcommand
+// ==============================================================================
+//
+// `avdl equal A B` -- check that two schemas are canonically equal.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use avdl::{Idl, canonical_eq};
+use lexopt::prelude::*;
+
+use crate::print_warnings;
+
+const HELP: &str = "\
+Usage: avdl equal [OPTIONS] A B
+
+Succeeds if A and B describe the same schema, and fails otherwise. Each is an
+.avdl file in schema mode, which is compiled first, or an .avsc file. Schemas
+are compared by Parsing Canonical Form, so docs, aliases, defaults, logical
+types, and custom properties are ignored.
+
+Options:
+      --import-dir <DIR>  Additional directories to search for imports (repeatable)
+  -h, --help              Print help";
+
+/// Parsed CLI arguments for the `equal` subcommand.
+pub(crate) struct Args {
+    a: String,
+    b: String,
+    import_dirs: Vec<PathBuf>,
+}
+
+/// Parse options and the two positional inputs for the `equal` subcommand.
+pub(crate) fn parse_args(parser: &mut lexopt::Parser) -> Result<Args, lexopt::Error> {
+    let mut import_dirs = Vec::new();
+    let mut a = None;
+    let mut b = None;
+
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Long("import-dir") => {
+                let val: String = parser.value()?.string()?;
+                import_dirs.push(PathBuf::from(val));
+            }
+            Short('h') | Long("help") => {
+                println!("{HELP}");
+                std::process::exit(0);
+            }
+            Value(val) if a.is_none() => {
+                a = Some(val.string()?);
+            }
+            Value(val) if b.is_none() => {
+                b = Some(val.string()?);
+            }
+            _ => return Err(arg.unexpected()),
+        }
+    }
+
+    let a = a.ok_or_else(|| lexopt::Error::MissingValue {
+        option: Some("A".to_string()),
+    })?;
+    let b = b.ok_or_else(|| lexopt::Error::MissingValue {
+        option: Some("B".to_string()),
+    })?;
+
+    Ok(Args { a, b, import_dirs })
+}
+
+pub(crate) fn run(args: Args) -> miette::Result<()> {
+    let a = load_schema(&args.a, &args.import_dirs)?;
+    let b = load_schema(&args.b, &args.import_dirs)?;
+    if canonical_eq(&a, &b) {
+        Ok(())
+    } else {
+        Err(miette::miette!(
+            "{} and {} are not the same schema",
+            args.a,
+            args.b
+        ))
+    }
+}
+
+/// Read the schema in an `.avsc` file, or compile it from a schema-mode
+/// `.avdl` file.
+fn load_schema(path: &str, import_dirs: &[PathBuf]) -> miette::Result<serde_json::Value> {
+    let json = if Path::new(path).extension().is_some_and(|ext| ext == "avdl") {
+        let mut builder = Idl::new();
+        for dir in import_dirs {
+            builder.import_dir(dir);
+        }
+        let output = match builder.convert(path) {
+            Ok(output) => output,
+            Err(e) => {
+                print_warnings(&builder.drain_warnings(), &[]);
+                return Err(e.wrap_err(format!("compile {path}")));
+            }
+        };
+        print_warnings(&output.warnings, output.imports());
+        output.json
+    } else {
+        let text = fs::read_to_string(path).map_err(|e| miette::miette!("{e}: read {path}"))?;
+        serde_json::from_str(&text).map_err(|e| miette::miette!("{e}: parse {path}"))?
+    };
+
+    if json.get("protocol").is_some() {
+        return Err(miette::miette!(
+            help = "`avdl equal` compares schemas; use `avdl diff` to compare protocols",
+            "{path} is a protocol, not a schema"
+        ));
+    }
+    Ok(json)
+}
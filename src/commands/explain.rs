@@ -0,0 +1,58 @@
+// ==============================================================================
+// `explain` Subcommand
+// ==============================================================================
+//
+// `avdl explain [CODE]` -- explain a diagnostic code such as AVDL0004.
+
+use lexopt::prelude::*;
+
+const HELP: &str = "\
+Usage: avdl explain [CODE]
+
+Prints what causes the diagnostic with the given code (e.g., AVDL0004, as
+shown in the header of every error and warning) and how to fix it. Without a
+code, lists every code.
+
+Options:
+  -h, --help              Print help";
+
+pub(crate) struct Args {
+    code: Option<String>,
+}
+
+/// Parse the optional code for the `explain` subcommand.
+pub(crate) fn parse_args(parser: &mut lexopt::Parser) -> Result<Args, lexopt::Error> {
+    let mut code = None;
+
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Short('h') | Long("help") => {
+                println!("{HELP}");
+                std::process::exit(0);
+            }
+            Value(val) if code.is_none() => {
+                code = Some(val.string()?);
+            }
+            _ => return Err(arg.unexpected()),
+        }
+    }
+
+    Ok(Args { code })
+}
+
+pub(crate) fn run(args: Args) -> miette::Result<()> {
+    let Some(code) = args.code else {
+        for entry in avdl::DIAGNOSTIC_CODES {
+            println!("{}  {}", entry.code, entry.title);
+        }
+        return Ok(());
+    };
+    let entry = avdl::explain(&code).ok_or_else(|| {
+        miette::miette!(
+            help = "run `avdl explain` to list every code",
+            "unknown diagnostic code `{code}`"
+        )
+    })?;
+    println!("{}: {}\n\n{}", entry.code, entry.title, entry.explanation);
+    Ok(())
+}
@@ -0,0 +1,85 @@
+// ==============================================================================
+// `fix` Subcommand
+// ==============================================================================
+//
+// `avdl fix INPUT` -- apply the safe fixes for the errors in a file, in place.
+
+use std::fs;
+use std::path::PathBuf;
+
+use avdl::Idl;
+use lexopt::prelude::*;
+
+const HELP: &str = "\
+Usage: avdl fix [OPTIONS] INPUT
+
+Rewrites INPUT in place to fix the errors that have exactly one reasonable
+repair, printing each fix:
+  - an undefined name that a file under an import directory defines: insert
+    the `import` statement for that file
+  - an identifier where a string is expected, such as a bare enum default:
+    quote it
+  - a type repeated in a union: remove the repeat
+
+Fails, after writing the fixes made, if errors without a safe fix remain.
+
+Options:
+      --import-dir <DIR>  Additional directories to search for imports (repeatable)
+  -h, --help              Print help";
+
+/// Parsed CLI arguments for the `fix` subcommand.
+pub(crate) struct Args {
+    input: String,
+    import_dirs: Vec<PathBuf>,
+}
+
+/// Parse options and the positional input for the `fix` subcommand.
+pub(crate) fn parse_args(parser: &mut lexopt::Parser) -> Result<Args, lexopt::Error> {
+    let mut import_dirs = Vec::new();
+    let mut input = None;
+
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Long("import-dir") => {
+                let val: String = parser.value()?.string()?;
+                import_dirs.push(PathBuf::from(val));
+            }
+            Short('h') | Long("help") => {
+                println!("{HELP}");
+                std::process::exit(0);
+            }
+            Value(val) if input.is_none() => {
+                input = Some(val.string()?);
+            }
+            _ => return Err(arg.unexpected()),
+        }
+    }
+
+    let input = input.ok_or_else(|| lexopt::Error::MissingValue {
+        option: Some("INPUT".to_string()),
+    })?;
+
+    Ok(Args { input, import_dirs })
+}
+
+/// Apply the safe fixes for the errors in the input, writing it back if any
+/// were made, and fail with the first error left.
+pub(crate) fn run(args: Args) -> miette::Result<()> {
+    let mut builder = Idl::new();
+    for dir in &args.import_dirs {
+        builder.import_dir(dir);
+    }
+
+    let output = builder.fix(&args.input)?;
+    for fix in &output.fixes {
+        eprintln!("{}: {} ({})", args.input, fix.description, fix.code);
+    }
+    if !output.fixes.is_empty() {
+        fs::write(&args.input, &output.content)
+            .map_err(|e| miette::miette!("write {}: {e}", args.input))?;
+    }
+    match output.remaining_error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,90 @@
+// ==============================================================================
+// `graph` Subcommand
+// ==============================================================================
+//
+// `avdl graph INPUT` -- print the type dependency graph.
+
+use std::path::PathBuf;
+
+use avdl::Idl;
+use lexopt::prelude::*;
+
+use crate::{print_warnings, write_output};
+
+const HELP: &str = "\
+Usage: avdl graph [OPTIONS] INPUT
+
+Options:
+      --import-dir <DIR>  Additional directories to search for imports (repeatable)
+      --format <FORMAT>   Output format: dot (default, Graphviz)
+  -h, --help              Print help";
+
+/// Parsed CLI arguments for the `graph` subcommand. DOT is the only output
+/// format, but `--format dot` is accepted so scripts can be explicit.
+pub(crate) struct Args {
+    input: String,
+    import_dirs: Vec<PathBuf>,
+}
+
+/// Parse options and the positional input for the `graph` subcommand.
+pub(crate) fn parse_args(parser: &mut lexopt::Parser) -> Result<Args, lexopt::Error> {
+    let mut import_dirs = Vec::new();
+    let mut input = None;
+
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Long("import-dir") => {
+                let val: String = parser.value()?.string()?;
+                import_dirs.push(PathBuf::from(val));
+            }
+            Long("format") => {
+                let val: String = parser.value()?.string()?;
+                if val != "dot" {
+                    return Err(lexopt::Error::UnexpectedValue {
+                        option: "--format".to_string(),
+                        value: val.into(),
+                    });
+                }
+            }
+            Short('h') | Long("help") => {
+                println!("{HELP}");
+                std::process::exit(0);
+            }
+            Value(val) if input.is_none() => {
+                input = Some(val.string()?);
+            }
+            _ => return Err(arg.unexpected()),
+        }
+    }
+
+    let input = input.ok_or_else(|| lexopt::Error::MissingValue {
+        option: Some("INPUT".to_string()),
+    })?;
+
+    Ok(Args { input, import_dirs })
+}
+
+/// Compile the input and print its type dependency graph in DOT format.
+pub(crate) fn run(args: Args) -> miette::Result<()> {
+    let mut builder = Idl::new();
+    for dir in &args.import_dirs {
+        builder.import_dir(dir);
+    }
+
+    let output = match builder.convert(&args.input) {
+        Ok(output) => output,
+        Err(e) => {
+            print_warnings(&builder.drain_warnings(), &[]);
+            return Err(e);
+        }
+    };
+    print_warnings(&output.warnings, output.imports());
+
+    let graph_name = output
+        .json
+        .get("protocol")
+        .and_then(|p| p.as_str())
+        .unwrap_or("schema");
+    let dot = output.type_graph().to_dot(graph_name);
+    write_output(None, &format!("{dot}\n"))
+}
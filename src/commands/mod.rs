@@ -0,0 +1,20 @@
+// ==============================================================================
+// Tooling Subcommands
+// ==============================================================================
+//
+// One module per subcommand with no Java equivalent. Each defines its `HELP`
+// text, its parsed `Args`, a `parse_args` function, and a `run` function that
+// `main` dispatches to.
+
+#[cfg(feature = "bench")]
+pub(crate) mod bench;
+pub(crate) mod check;
+pub(crate) mod defaults;
+pub(crate) mod deps;
+pub(crate) mod diff;
+pub(crate) mod doc;
+pub(crate) mod equal;
+pub(crate) mod explain;
+pub(crate) mod fix;
+pub(crate) mod graph;
+pub(crate) mod verify;
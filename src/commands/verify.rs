@@ -0,0 +1,216 @@
+// ==============================================================================
+// `verify` Subcommand
+// ==============================================================================
+//
+// `avdl verify --against JAR INPUT...` -- compare the output with Java avro-tools.
+
+use std::path::{Path, PathBuf};
+
+use avdl::Idl;
+use lexopt::prelude::*;
+
+use crate::expand_avdl_inputs;
+
+const HELP: &str = "\
+Usage: avdl verify [OPTIONS] --against <JAR> INPUT...
+
+Compiles each INPUT (an .avdl file, or a directory searched recursively for
+.avdl files) with both avdl and `java -cp JAR org.apache.avro.tool.Main idl`,
+and reports every place where the JSON differs, ignoring key order and
+number formatting, and any difference in the warnings printed. Fails if any
+file differs. Requires `java` on the PATH.
+
+Options:
+      --against <JAR>     The avro-tools JAR to compare with
+      --import-dir <DIR>  Additional directories to search for imports (repeatable);
+                          also added to the Java classpath
+  -h, --help              Print help";
+
+/// Parsed CLI arguments for the `verify` subcommand.
+pub(crate) struct Args {
+    inputs: Vec<PathBuf>,
+    jar: PathBuf,
+    import_dirs: Vec<PathBuf>,
+}
+
+/// Parse options and positional inputs for the `verify` subcommand.
+pub(crate) fn parse_args(parser: &mut lexopt::Parser) -> Result<Args, lexopt::Error> {
+    let mut import_dirs = Vec::new();
+    let mut jar = None;
+    let mut inputs = Vec::new();
+
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Long("against") => {
+                jar = Some(PathBuf::from(parser.value()?));
+            }
+            Long("import-dir") => {
+                let val: String = parser.value()?.string()?;
+                import_dirs.push(PathBuf::from(val));
+            }
+            Short('h') | Long("help") => {
+                println!("{HELP}");
+                std::process::exit(0);
+            }
+            Value(val) => {
+                inputs.push(PathBuf::from(val));
+            }
+            _ => return Err(arg.unexpected()),
+        }
+    }
+
+    let jar = jar.ok_or_else(|| lexopt::Error::MissingValue {
+        option: Some("--against".to_string()),
+    })?;
+    if inputs.is_empty() {
+        return Err(lexopt::Error::MissingValue {
+            option: Some("INPUT".to_string()),
+        });
+    }
+
+    Ok(Args {
+        inputs,
+        jar,
+        import_dirs,
+    })
+}
+
+/// Compile every input with both avdl and Java avro-tools and report where
+/// the outputs or warnings differ.
+pub(crate) fn run(args: Args) -> miette::Result<()> {
+    let mut classpath = vec![args.jar.clone()];
+    classpath.extend(args.import_dirs.iter().cloned());
+    let classpath = std::env::join_paths(&classpath)
+        .map_err(|e| miette::miette!("{e}: build Java classpath"))?;
+
+    let files = expand_avdl_inputs(&args.inputs);
+    let mut failed = 0;
+    for file in &files {
+        let problems = verify_one(file, &args.import_dirs, &classpath)?;
+        if problems.is_empty() {
+            println!("ok    {}", file.display());
+        } else {
+            failed += 1;
+            println!("FAIL  {}", file.display());
+            for problem in problems {
+                println!("      {problem}");
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(miette::miette!(
+            "{failed} of {} file(s) differ from avro-tools",
+            files.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Compare one file, returning a description of each difference.
+fn verify_one(
+    file: &Path,
+    import_dirs: &[PathBuf],
+    classpath: &std::ffi::OsStr,
+) -> miette::Result<Vec<String>> {
+    let mut builder = Idl::new();
+    for dir in import_dirs {
+        builder.import_dir(dir);
+    }
+    let ours = builder.convert(file);
+
+    let java = std::process::Command::new("java")
+        .arg("-cp")
+        .arg(classpath)
+        .args(["org.apache.avro.tool.Main", "idl"])
+        .arg(file)
+        .output()
+        .map_err(|e| {
+            miette::miette!(
+                help = "install a Java runtime, or put `java` on the PATH",
+                "{e}: run java"
+            )
+        })?;
+    let java_stderr = String::from_utf8_lossy(&java.stderr);
+
+    let ours = match (ours, java.status.success()) {
+        (Ok(ours), true) => ours,
+        (Ok(_), false) => {
+            let reason = java_stderr.lines().next().unwrap_or("no output");
+            return Ok(vec![format!(
+                "avro-tools failed but avdl succeeded: {reason}"
+            )]);
+        }
+        (Err(e), true) => {
+            return Ok(vec![format!("avdl failed but avro-tools succeeded: {e}")]);
+        }
+        // Both reject the file; the error messages are not comparable.
+        (Err(_), false) => return Ok(Vec::new()),
+    };
+
+    let theirs: serde_json::Value = serde_json::from_slice(&java.stdout)
+        .map_err(|e| miette::miette!("{e}: parse avro-tools output for {}", file.display()))?;
+    let mut problems = Vec::new();
+    json_differences("", &theirs, &ours.json, &mut problems);
+
+    // avro-tools prints each warning as a `Warning: ...` line on stderr.
+    let their_warnings: Vec<&str> = java_stderr
+        .lines()
+        .filter_map(|line| line.strip_prefix("Warning: "))
+        .collect();
+    let our_warnings: Vec<String> = ours.warnings.iter().map(ToString::to_string).collect();
+    for warning in &their_warnings {
+        if !our_warnings.iter().any(|w| w == warning) {
+            problems.push(format!("warning only from avro-tools: {warning}"));
+        }
+    }
+    for warning in &our_warnings {
+        if !their_warnings.contains(&warning.as_str()) {
+            problems.push(format!("warning only from avdl: {warning}"));
+        }
+    }
+    Ok(problems)
+}
+
+/// Record the JSON pointer of every place where `actual` differs from
+/// `expected`. Object key order is irrelevant, and numbers compare by value,
+/// so Java's `1.0` equals `1`.
+fn json_differences(
+    path: &str,
+    expected: &serde_json::Value,
+    actual: &serde_json::Value,
+    out: &mut Vec<String>,
+) {
+    use serde_json::Value as Json;
+
+    let location = if path.is_empty() { "/" } else { path };
+    match (expected, actual) {
+        (Json::Object(expected), Json::Object(actual)) => {
+            for (key, expected_value) in expected {
+                let child = format!("{path}/{}", key.replace('~', "~0").replace('/', "~1"));
+                match actual.get(key) {
+                    Some(actual_value) => {
+                        json_differences(&child, expected_value, actual_value, out);
+                    }
+                    None => out.push(format!("{child}: missing (avro-tools: {expected_value})")),
+                }
+            }
+            for (key, actual_value) in actual {
+                if !expected.contains_key(key) {
+                    let child = format!("{path}/{}", key.replace('~', "~0").replace('/', "~1"));
+                    out.push(format!("{child}: unexpected (avdl: {actual_value})"));
+                }
+            }
+        }
+        (Json::Array(expected), Json::Array(actual)) if expected.len() == actual.len() => {
+            for (i, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+                json_differences(&format!("{path}/{i}"), expected, actual, out);
+            }
+        }
+        (Json::Number(expected), Json::Number(actual)) if expected.as_f64() == actual.as_f64() => {}
+        (expected, actual) if expected == actual => {}
+        (expected, actual) => {
+            out.push(format!("{location}: avro-tools {expected}, avdl {actual}"));
+        }
+    }
+}
@@ -0,0 +1,245 @@
+// ==============================================================================
+// Comments by Declaration
+// ==============================================================================
+//
+// Only `/** ... */` doc comments end up in the schema. Tools that migrate
+// legacy `//` and `/* ... */` comments into doc comments need to know which
+// declaration each comment belongs to, which is decided here by position,
+// on the lossless syntax tree:
+//
+// - A comment that ends a line with earlier code on it trails the innermost
+//   declaration that code is in (`int a; // why`).
+// - Otherwise it leads the declaration that starts at the next token.
+// - Otherwise (before a `}`, say, or between annotations and the keyword) it
+//   is inside the innermost declaration around it.
+//
+// Comments outside every declaration are not reported.
+
+use std::ops::Range;
+
+use crate::syntax::{SyntaxElement, SyntaxNode, syntax_tree};
+
+/// A declaration and the comments attached to it. See
+/// [`declaration_comments`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommentedDeclaration {
+    /// The grammar rule of the declaration: `protocolDeclaration`,
+    /// `recordDeclaration`, `enumDeclaration`, `fixedDeclaration`,
+    /// `enumSymbol`, `fieldDeclaration`, or `messageDeclaration`.
+    pub kind: &'static str,
+    /// The name of the declaration, after the names of the declarations
+    /// around it, e.g. `["Service", "User", "email"]`. A field declaration
+    /// that declares several fields is named after the first.
+    pub path: Vec<String>,
+    /// The byte range of the declaration, from its first annotation.
+    pub span: Range<usize>,
+    /// The comments attached to the declaration, in source order.
+    pub comments: Vec<Comment>,
+}
+
+/// A comment in the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    pub kind: CommentKind,
+    pub placement: CommentPlacement,
+    pub span: Range<usize>,
+    /// The comment as written, delimiters included.
+    pub text: String,
+}
+
+/// The syntax of a [`Comment`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentKind {
+    /// `/** ... */`
+    Doc,
+    /// `/* ... */`
+    Block,
+    /// `// ...`
+    Line,
+}
+
+/// Where a [`Comment`] is relative to its declaration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentPlacement {
+    /// Before the declaration.
+    Leading,
+    /// At the end of a line of the declaration, after code.
+    Trailing,
+    /// Inside the declaration, on a line of its own.
+    Inner,
+}
+
+/// Find every declaration in `source` and the comments attached to it, by
+/// position. Declarations are in source order, outer ones before the ones
+/// inside them, including those without comments.
+///
+/// This only lexes and parses `source` itself, and never fails; for source
+/// with syntax errors, declarations are whatever the parser recovered.
+#[must_use]
+pub fn declaration_comments(source: &str) -> Vec<CommentedDeclaration> {
+    let tree = syntax_tree(source);
+    let mut declarations = Vec::new();
+    collect_declarations(source, &tree, &mut Vec::new(), &mut declarations);
+
+    let tokens = tree.tokens();
+    for (i, token) in tokens.iter().enumerate() {
+        let kind = match token.kind {
+            "DocComment" => CommentKind::Doc,
+            "MultiLineComment" | "EmptyComment" => CommentKind::Block,
+            "SingleLineComment" => CommentKind::Line,
+            _ => continue,
+        };
+        // A line comment's token runs through its line break; the comment
+        // itself ends before it.
+        let span = if kind == CommentKind::Line {
+            let text = source[token.span.clone()].trim_end_matches(['\n', '\r']);
+            token.span.start..token.span.start + text.len()
+        } else {
+            token.span.clone()
+        };
+        let previous = tokens[..i].iter().rev().find(|t| !t.is_trivia());
+        let next = tokens[i + 1..].iter().find(|t| !t.is_trivia());
+        let innermost = |offset: usize| {
+            declarations
+                .iter()
+                .rposition(|d: &CommentedDeclaration| d.span.contains(&offset))
+        };
+
+        let ends_line = next.is_none_or(|next| source[span.end..next.span.start].contains('\n'));
+        let trailing = previous
+            .filter(|previous| ends_line && !source[previous.span.end..span.start].contains('\n'))
+            .and_then(|previous| innermost(previous.span.start));
+        let (index, placement) = if let Some(index) = trailing {
+            (index, CommentPlacement::Trailing)
+        } else if let Some(index) = next.and_then(|next| {
+            declarations
+                .iter()
+                .position(|d| d.span.start == next.span.start)
+        }) {
+            (index, CommentPlacement::Leading)
+        } else if let Some(index) = innermost(span.start) {
+            (index, CommentPlacement::Inner)
+        } else {
+            continue;
+        };
+        declarations[index].comments.push(Comment {
+            kind,
+            placement,
+            text: source[span.clone()].to_string(),
+            span,
+        });
+    }
+    declarations
+}
+
+/// Collect the declarations under `node` in preorder, with their paths.
+fn collect_declarations(
+    source: &str,
+    node: &SyntaxNode,
+    path: &mut Vec<String>,
+    declarations: &mut Vec<CommentedDeclaration>,
+) {
+    let name = match node.kind {
+        "protocolDeclaration"
+        | "recordDeclaration"
+        | "enumDeclaration"
+        | "fixedDeclaration"
+        | "enumSymbol"
+        | "messageDeclaration" => node.child("identifier"),
+        "fieldDeclaration" => node
+            .child("variableDeclaration")
+            .and_then(|v| v.child("identifier")),
+        _ => None,
+    };
+    if let Some(identifier) = name {
+        path.push(source[identifier.span.clone()].replace('`', ""));
+        declarations.push(CommentedDeclaration {
+            kind: node.kind,
+            path: path.clone(),
+            span: node.span.clone(),
+            comments: Vec::new(),
+        });
+    }
+    for child in &node.children {
+        if let SyntaxElement::Node(child) = child {
+            collect_declarations(source, child, path, declarations);
+        }
+    }
+    if name.is_some() {
+        path.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use CommentKind::*;
+    use CommentPlacement::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn comments_attach_to_the_nearest_declaration() {
+        let source = "\
+// The service.
+protocol Service {
+  /* Legacy: a user. */
+  /** A user. */
+  record User {
+    string name; // full name
+    // Deprecated.
+    int age;
+    // Nothing after this.
+  }
+  enum Color { RED, /* primary */ GREEN }
+}
+";
+        let declarations = declaration_comments(source);
+        let summary: Vec<_> = declarations
+            .iter()
+            .map(|d| {
+                let comments: Vec<_> = d
+                    .comments
+                    .iter()
+                    .map(|c| (c.kind, c.placement, c.text.as_str()))
+                    .collect();
+                (d.kind, d.path.join("."), comments)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (
+                    "protocolDeclaration",
+                    "Service".to_string(),
+                    vec![(Line, Leading, "// The service.")]
+                ),
+                (
+                    "recordDeclaration",
+                    "Service.User".to_string(),
+                    vec![
+                        (Block, Leading, "/* Legacy: a user. */"),
+                        (Doc, Leading, "/** A user. */"),
+                        (Line, Inner, "// Nothing after this."),
+                    ]
+                ),
+                (
+                    "fieldDeclaration",
+                    "Service.User.name".to_string(),
+                    vec![(Line, Trailing, "// full name")]
+                ),
+                (
+                    "fieldDeclaration",
+                    "Service.User.age".to_string(),
+                    vec![(Line, Leading, "// Deprecated.")]
+                ),
+                ("enumDeclaration", "Service.Color".to_string(), vec![]),
+                ("enumSymbol", "Service.Color.RED".to_string(), vec![]),
+                (
+                    "enumSymbol",
+                    "Service.Color.GREEN".to_string(),
+                    vec![(Block, Leading, "/* primary */")]
+                ),
+            ]
+        );
+    }
+}
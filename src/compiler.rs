@@ -19,33 +19,90 @@
 // builder's `*_impl` method.
 
 use std::borrow::Cow;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
+use indexmap::IndexMap;
 use miette::Context;
 use serde_json::Value;
 
-use crate::error::{ParseDiagnostic, SpanWithSource};
-use crate::import::{ImportContext, import_protocol, import_schema};
-use crate::model::json::{build_lookup, protocol_to_json, schema_to_json};
-use crate::model::protocol::Message;
-use crate::model::schema::validate_record_field_defaults;
-use crate::reader::{DeclItem, IdlFile, ImportKind, parse_idl_named};
-use crate::resolve::SchemaRegistry;
+use crate::defaults::{
+    RecordDefaults, UnionDefaults, effective_defaults, effective_defaults_unchecked,
+};
+use crate::diff::{SchemaChange, diff_json};
+use crate::doc::{DocPage, doc_comments, html_pages, json_page, markdown_page};
+use crate::encoding::decode_source;
+use crate::error::{ErrorKind, ParseDiagnostic, SpanWithSource, keep_source};
+use crate::fix::FixOutput;
+use crate::graph::{
+    TypeGraph, too_deeply_nested, type_references, unguarded_cycles, unreferenced_types,
+};
+use crate::ide::{Definition, EditedFile, Hover, SymbolIndex};
+use crate::import::{
+    ImportContext, json_import_kind, parse_import_json, register_protocol, register_schema,
+    wrong_import_kind,
+};
+use crate::lint::{DocScope, LintOptions, NamingRule, NullableStyle, lint};
+use crate::model::fingerprint::{canonical_eq, fingerprint64, rabin_fingerprint};
+use crate::model::json::{
+    OrderedJson, build_lookup, protocol_to_json, protocol_to_json_by_name, schema_to_json,
+};
+use crate::model::protocol::{Message, Protocol};
+use crate::model::schema::{
+    AvroSchema, has_unresolved_field_defaults, is_valid_default, validate_record_field_defaults,
+};
+use crate::model::transform::{
+    OutputOptions, glob_matches, qualify_protocol_references, qualify_schema_references,
+};
+use crate::reader::{
+    Annotation, AnnotationHandler, DeclItem, IdlFile, ImportKind, ParamDefaults, ReaderOptions,
+    Warning, WarningKind, normalize_line_endings, parse_idl_named, split_protocols,
+};
+use crate::resolve::{Resolution, SchemaRegistry};
+use crate::template::{OutputTemplate, TemplateValues};
+use crate::visibility::validate_visibility;
 
 // ==============================================================================
 // Shared `IdlCompiler` — common builder state and compilation preamble
 // ==============================================================================
 
 /// Shared inner struct that owns the builder state common to both [`Idl`] and
-/// [`Idl2Schemata`]: import directories and accumulated warnings.
+/// [`Idl2Schemata`]: import directories, reader options, and accumulated
+/// warnings.
 ///
 /// This is intentionally private — the public API surface is through `Idl` and
 /// `Idl2Schemata`, which wrap this struct and add their type-specific
 /// serialization logic.
 struct IdlCompiler {
     import_dirs: Vec<PathBuf>,
+    /// Imported files supplied in memory, by the path they are imported as.
+    import_sources: Vec<(PathBuf, String)>,
+    /// Logical import path prefixes and the directories they map to.
+    import_mappings: Vec<(String, PathBuf)>,
+    /// Overlay import directories, highest priority first.
+    overlay_dirs: Vec<PathBuf>,
+    /// Directory that relative imports in string sources resolve against,
+    /// instead of the current directory.
+    import_base: Option<PathBuf>,
+    /// Options passed to the reader for the input file and every imported
+    /// IDL file.
+    reader_options: ReaderOptions,
+    /// Post-processing applied to the emitted JSON.
+    output_options: OutputOptions,
+    /// Which union branch a field default may match.
+    union_defaults: UnionDefaults,
+    /// Warn about named types that nothing references.
+    warn_unused_types: bool,
+    /// Lints run over the input file.
+    lints: LintOptions,
+    /// Fail compilation if it produced any warnings.
+    deny_warnings: bool,
+    /// Warning kinds that are dropped instead of reported.
+    allowed_warnings: Vec<WarningKind>,
     /// Warnings accumulated during the most recent compilation call. Populated
     /// even when the call returns `Err`, so the CLI can emit warnings before
     /// propagating the error.
@@ -63,6 +120,15 @@ struct CompileOutput {
     idl_file: IdlFile,
     registry: SchemaRegistry,
     warnings: Vec<miette::Report>,
+    /// Source name of the file that defined each registered schema, parallel
+    /// to `registry.schemas()`.
+    type_sources: Vec<String>,
+    /// Every import statement encountered, including nested ones.
+    imports: Vec<ImportEdge>,
+    /// The effective default of every record field, in registration order.
+    defaults: Vec<RecordDefaults>,
+    /// Where each named type was declared, for types declared in IDL.
+    type_spans: HashMap<String, SpanWithSource>,
     /// Original source text, retained for error diagnostics in type-specific
     /// serialization logic.
     source: &'static str,
@@ -70,10 +136,61 @@ struct CompileOutput {
     source_name: &'static str,
 }
 
+/// A `.avdl` file read from disk, ready to compile.
+struct InputFile {
+    source: &'static str,
+    /// Name used for the file in diagnostics.
+    name: &'static str,
+    /// Directory that relative imports resolve against.
+    dir: PathBuf,
+    canonical_path: Option<PathBuf>,
+}
+
+impl InputFile {
+    fn read(path: &Path) -> miette::Result<Self> {
+        let source = fs::read(path)
+            .map_err(|e| miette::miette!(code = ErrorKind::ReadFile.code(), "{e}"))
+            .and_then(|bytes| {
+                decode_source(bytes).map_err(|e| {
+                    miette::miette!(
+                        code = ErrorKind::Encoding.code(),
+                        help = "save the file as UTF-8",
+                        "{e}"
+                    )
+                })
+            })
+            .with_context(|| format!("read {}", path.display()))?;
+
+        let name = path.display().to_string();
+        let dir = path
+            .parent()
+            .map(|p| p.to_path_buf())
+            .unwrap_or_else(|| PathBuf::from("."));
+        let dir = dir.canonicalize().unwrap_or(dir);
+        Ok(InputFile {
+            source: keep_source(source),
+            name: keep_source(name),
+            dir,
+            canonical_path: path.canonicalize().ok(),
+        })
+    }
+}
+
 impl IdlCompiler {
     fn new() -> Self {
         IdlCompiler {
             import_dirs: Vec::new(),
+            import_sources: Vec::new(),
+            import_mappings: Vec::new(),
+            overlay_dirs: Vec::new(),
+            import_base: None,
+            reader_options: ReaderOptions::default(),
+            output_options: OutputOptions::default(),
+            union_defaults: UnionDefaults::default(),
+            warn_unused_types: false,
+            lints: LintOptions::default(),
+            deny_warnings: false,
+            allowed_warnings: Vec::new(),
             accumulated_warnings: Vec::new(),
         }
     }
@@ -82,6 +199,56 @@ impl IdlCompiler {
         self.import_dirs.push(dir);
     }
 
+    fn import_source(&mut self, path: PathBuf, contents: String) {
+        self.import_sources.push((path, contents));
+    }
+
+    fn import_mapping(&mut self, prefix: String, dir: PathBuf) {
+        self.import_mappings.push((prefix, dir));
+    }
+
+    fn overlay_dir(&mut self, dir: PathBuf) {
+        self.overlay_dirs.push(dir);
+    }
+
+    fn param_defaults(&mut self, mode: ParamDefaults) {
+        self.reader_options.param_defaults = mode;
+    }
+
+    fn skip_unknown_declarations(&mut self, enabled: bool) {
+        self.reader_options.skip_unknown_declarations = enabled;
+    }
+
+    fn iso8601_defaults(&mut self, enabled: bool) {
+        self.reader_options.iso8601_defaults = enabled;
+    }
+
+    fn promote_logical_types(&mut self, enabled: bool) {
+        self.reader_options.promote_logical_types = enabled;
+    }
+
+    fn validate_defaults(&mut self, enabled: bool) {
+        self.reader_options.validate_defaults = enabled;
+    }
+
+    fn flatten_nested_unions(&mut self, enabled: bool) {
+        self.reader_options.flatten_nested_unions = enabled;
+    }
+
+    fn normalize_line_endings(&mut self, enabled: bool) {
+        self.reader_options.normalize_line_endings = enabled;
+    }
+
+    fn max_nesting_depth(&mut self, depth: usize) {
+        self.reader_options.max_nesting_depth = depth;
+    }
+
+    fn annotation_handler(&mut self, name: String, handler: AnnotationHandler) {
+        self.reader_options
+            .annotation_handlers
+            .insert(name, handler);
+    }
+
     fn drain_warnings(&mut self) -> Vec<miette::Report> {
         std::mem::take(&mut self.accumulated_warnings)
     }
@@ -92,35 +259,47 @@ impl IdlCompiler {
     /// `Idl2Schemata::extract(path)`. It reads the file, determines the parent
     /// directory and canonical path, then delegates to [`compile`](Self::compile).
     fn compile_file(&mut self, path: &Path) -> miette::Result<CompileOutput> {
-        let source = fs::read_to_string(path)
-            .map_err(|e| miette::miette!("{e}"))
-            .with_context(|| format!("read {}", path.display()))?;
-
-        let source_name = path.display().to_string();
-        let dir = path
-            .parent()
-            .map(|p| p.to_path_buf())
-            .unwrap_or_else(|| PathBuf::from("."));
-        let dir = dir.canonicalize().unwrap_or(dir);
-        let canonical_path = path.canonicalize().ok();
-
-        let source = source.leak();
-        let source_name = source_name.leak();
-        self.compile(source, source_name, &dir, canonical_path)
+        let input = InputFile::read(path)?;
+        self.compile(input.source, input.name, &input.dir, input.canonical_path)
     }
 
-    /// Compile an IDL source string using the current working directory as the
-    /// import base. This is the shared implementation behind
+    /// Compile an IDL source string using the configured import base, or the
+    /// current working directory, as the base for relative imports. This is the shared implementation behind
     /// `Idl::convert_str_named` and `Idl2Schemata::extract_str_named`.
     fn compile_str(
         &mut self,
         source: &'static str,
         name: &'static str,
     ) -> miette::Result<CompileOutput> {
-        let cwd = std::env::current_dir()
-            .map_err(|e| miette::miette!("{e}"))
-            .context("determine current directory")?;
-        self.compile(source, name, &cwd, None)
+        let base = self.str_base();
+        self.compile(source, name, &base, None)
+    }
+
+    /// The directory that relative imports in a source string resolve
+    /// against: the configured import base, or the current working directory.
+    fn str_base(&self) -> PathBuf {
+        // Targets without a filesystem (such as `wasm32-unknown-unknown`) have
+        // no current directory; imports can then only come from memory.
+        match &self.import_base {
+            Some(base) => base.clone(),
+            None => std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+        }
+    }
+
+    /// Apply the safe fixes for the errors in `source`, recompiling after
+    /// each round of fixes. See [`crate::fix`].
+    fn fix(
+        &mut self,
+        source: &str,
+        name: &'static str,
+        input_dir: &Path,
+        input_path: Option<PathBuf>,
+    ) -> FixOutput {
+        let import_ctx = ImportContext::new(self.import_dirs.clone());
+        crate::fix::fix(source, &import_ctx, |source| {
+            self.compile(source, name, input_dir, input_path.clone())
+                .map(|_| ())
+        })
     }
 
     /// Core compilation preamble shared by both `Idl` and `Idl2Schemata`.
@@ -138,8 +317,23 @@ impl IdlCompiler {
         input_path: Option<PathBuf>,
     ) -> miette::Result<CompileOutput> {
         self.accumulated_warnings.clear();
+        // Drop a UTF-8 byte order mark here rather than only in the reader, so
+        // spans from the reader line up with `source` in later diagnostics.
+        let source = source.strip_prefix('\u{feff}').unwrap_or(source);
+        let source = if self.reader_options.normalize_line_endings {
+            normalize_line_endings(source)
+        } else {
+            source
+        };
 
-        let mut ctx = CompileContext::new(&self.import_dirs);
+        let mut ctx = CompileContext::new(
+            &self.import_dirs,
+            &self.import_sources,
+            &self.import_mappings,
+            &self.overlay_dirs,
+            &self.reader_options,
+            &self.allowed_warnings,
+        );
 
         let (idl_file, registry) =
             match parse_and_resolve(source, source_name, input_dir, input_path, &mut ctx) {
@@ -156,6 +350,7 @@ impl IdlCompiler {
         if let Err(e) = validate_all_references(
             &idl_file,
             &registry,
+            &ctx.import_ctx,
             source,
             source_name,
             &ctx.json_import_spans,
@@ -164,11 +359,123 @@ impl IdlCompiler {
             return Err(e);
         }
 
+        // Serialization and default expansion inline referenced types, so a
+        // long chain of them recurses as deeply as nested brackets would.
+        let max_depth = self.reader_options.max_nesting_depth;
+        if let Some((name, depth)) = too_deeply_nested(&registry, max_depth) {
+            self.accumulated_warnings = std::mem::take(&mut ctx.warnings);
+            let message = format!(
+                "nesting too deep: type `{name}` nests {depth} levels deep through the types \
+                 it references, more than {max_depth}"
+            );
+            let help = "raise the limit with `--max-nesting-depth` or `Idl::max_nesting_depth`";
+            return Err(match ctx.type_spans.get(&name) {
+                Some(span) => ParseDiagnostic {
+                    kind: ErrorKind::NestingTooDeep,
+                    span: *span,
+                    message,
+                    label: Some("declared here".to_string()),
+                    help: Some(help.to_string()),
+                    related: Vec::new(),
+                    name: None,
+                    replacement: None,
+                }
+                .into(),
+                None => miette::miette!(
+                    code = ErrorKind::NestingTooDeep.code(),
+                    help = help,
+                    "{message}"
+                ),
+            });
+        }
+
+        // Public types and messages must not expose `@visibility("internal")`
+        // types.
+        if let Err(e) = validate_visibility(&idl_file, &registry, source, source_name) {
+            self.accumulated_warnings = std::mem::take(&mut ctx.warnings);
+            return Err(e);
+        }
+
+        // Expanding every default up front rejects defaults that can never be
+        // completed, which Java only discovers when a reader applies them.
+        let defaults = if self.reader_options.validate_defaults {
+            match effective_defaults(&registry, self.union_defaults) {
+                Ok(defaults) => defaults,
+                Err(e) => {
+                    self.accumulated_warnings = std::mem::take(&mut ctx.warnings);
+                    return Err(e);
+                }
+            }
+        } else {
+            let (defaults, errors) = effective_defaults_unchecked(&registry, self.union_defaults);
+            for (record, error) in errors {
+                ctx.warn(
+                    Warning {
+                        kind: WarningKind::InvalidDefault,
+                        message: error.to_string(),
+                        span: ctx.type_spans.get(&record).copied(),
+                    },
+                    None,
+                );
+            }
+            defaults
+        };
+
+        if self.warn_unused_types {
+            for name in unreferenced_types(&registry, &idl_file) {
+                let span = ctx.type_spans.get(&name).copied();
+                ctx.warn(
+                    Warning {
+                        kind: WarningKind::UnusedType,
+                        message: format!("type `{name}` is declared but is never referenced"),
+                        span,
+                    },
+                    None,
+                );
+            }
+        }
+
+        for warning in lint(source, source_name, &self.lints) {
+            ctx.warn(warning, None);
+        }
+
+        for cycle in unguarded_cycles(&registry) {
+            let span = ctx.type_spans.get(&cycle[0]).copied();
+            ctx.warn(
+                Warning {
+                    kind: WarningKind::RecursiveType,
+                    message: format!(
+                        "record `{}` can never hold a value, because it always contains \
+                         itself: {}",
+                        cycle[0],
+                        cycle.join(" -> ")
+                    ),
+                    span,
+                },
+                None,
+            );
+        }
+
         let warnings = std::mem::take(&mut ctx.warnings);
+        if self.deny_warnings && !warnings.is_empty() {
+            let count = warnings.len();
+            self.accumulated_warnings = warnings;
+            return Err(miette::miette!(
+                code = ErrorKind::DeniedWarnings.code(),
+                help = "fix the warnings, or stop denying them",
+                "warnings are denied, and compilation produced {count} {}",
+                if count == 1 { "warning" } else { "warnings" }
+            ));
+        }
+
         Ok(CompileOutput {
             idl_file,
             registry,
             warnings,
+            type_sources: std::mem::take(&mut ctx.type_sources),
+            imports: std::mem::take(&mut ctx.imports),
+            defaults,
+            type_spans: std::mem::take(&mut ctx.type_spans),
             source,
             source_name,
         })
@@ -205,6 +512,8 @@ impl IdlCompiler {
 /// ```
 pub struct Idl {
     inner: IdlCompiler,
+    references_by_name: bool,
+    build_metadata: bool,
 }
 
 /// Result of compiling an Avro IDL source.
@@ -219,7 +528,11 @@ pub struct Idl {
 /// `protocol`) are rejected, matching Java's `IdlTool` behavior. Use
 /// [`Idl2Schemata`] to extract schemas from such files.
 pub struct IdlOutput {
-    /// The compiled JSON (`.avpr` object or `.avsc` value).
+    /// The compiled JSON (`.avpr` object or `.avsc` value). Like every
+    /// `serde_json::Map`, its `messages` are sorted by name, whatever the
+    /// [`sort_messages`](Idl::sort_messages) setting; serialize
+    /// [`ordered_json`](IdlOutput::ordered_json) instead to write them in
+    /// declaration order.
     pub json: Value,
     /// Non-fatal warnings from parsing (e.g., orphaned doc comments).
     ///
@@ -227,6 +540,283 @@ pub struct IdlOutput {
     /// Print with `eprintln!("{report:?}")` for rich diagnostic output
     /// including source spans and labels.
     pub warnings: Vec<miette::Report>,
+    names: Vec<DeclaredName>,
+    imports: Vec<ImportEdge>,
+    graph: TypeGraph,
+    defaults: Vec<RecordDefaults>,
+    symbols: SymbolIndex,
+    idl_file: IdlFile,
+    /// The protocol's message names, in the order to write them.
+    message_order: Vec<String>,
+}
+
+impl IdlOutput {
+    /// All named types (records, errors, enums, and fixed) known to the
+    /// compiled file, including imported ones, in declaration order.
+    ///
+    /// This is a cheap index for catalogs and tooling that only need to know
+    /// which names exist and where they came from, without walking
+    /// [`json`](IdlOutput::json).
+    #[must_use]
+    pub fn names(&self) -> &[DeclaredName] {
+        &self.names
+    }
+
+    /// The compiled protocol as a typed model: its types, with imported
+    /// ones in place, and its messages. `None` if the file declares a
+    /// schema instead (see [`schema`](IdlOutput::schema)).
+    ///
+    /// Named types are defined once; other uses of them are
+    /// [`AvroSchema::Reference`]s to look up in
+    /// [`types`](Protocol::types). Output options such as
+    /// [`strip_docs`](Idl::strip_docs) only apply to
+    /// [`json`](IdlOutput::json).
+    #[must_use]
+    pub fn protocol(&self) -> Option<&Protocol> {
+        match &self.idl_file {
+            IdlFile::Protocol(protocol) => Some(protocol),
+            _ => None,
+        }
+    }
+
+    /// The type a `schema <type>;` file declares, as a typed model. `None`
+    /// for a protocol (see [`protocol`](IdlOutput::protocol)).
+    ///
+    /// Named types in it, including the type itself for `schema Order;`,
+    /// are [`AvroSchema::Reference`]s to look up in
+    /// [`types`](IdlOutput::types).
+    #[must_use]
+    pub fn schema(&self) -> Option<&AvroSchema> {
+        match &self.idl_file {
+            IdlFile::Schema(schema) => Some(schema),
+            _ => None,
+        }
+    }
+
+    /// Every named type known to the compiled file, including imported
+    /// ones, as typed models in declaration order, like
+    /// [`names`](IdlOutput::names).
+    #[must_use]
+    pub fn types(&self) -> Vec<&AvroSchema> {
+        self.symbols.registry().schemas().collect()
+    }
+
+    /// The transitive import graph of the compiled file: one entry per
+    /// `import` statement, in the order they were resolved.
+    #[must_use]
+    pub fn imports(&self) -> &[ImportEdge] {
+        &self.imports
+    }
+
+    /// Which named types reference which, including edges from protocol
+    /// messages to their request, response, and error types.
+    #[must_use]
+    pub fn type_graph(&self) -> &TypeGraph {
+        &self.graph
+    }
+
+    /// The value every record field takes when it is absent from the data,
+    /// with record-typed defaults expanded using the nested records' own
+    /// field defaults. Lists every record, including imported ones, in
+    /// declaration order.
+    #[must_use]
+    pub fn effective_defaults(&self) -> &[RecordDefaults] {
+        &self.defaults
+    }
+
+    /// The named type declared or referenced at byte `offset` of the compiled
+    /// source, with its doc comment and resolved schema, for editor hovers.
+    ///
+    /// If declarations and references are nested, such as a reference inside
+    /// a record declaration, the innermost one wins. Returns `None` if
+    /// nothing is declared or referenced at `offset`. Only offsets in the
+    /// compiled file itself are indexed, not in the files it imports.
+    #[must_use]
+    pub fn hover(&self, offset: usize) -> Option<Hover> {
+        self.symbols.hover(offset)
+    }
+
+    /// Bind a type name, as written in IDL, in the enclosing `namespace`,
+    /// exactly as the compiler binds references: a dotted name is fully
+    /// qualified, anything else is in `namespace`, and unescaped type
+    /// keywords such as `int` are built-in types. If no known type has the
+    /// resulting full name, the types with the same simple name are returned
+    /// as candidates.
+    #[must_use]
+    pub fn resolve_name(&self, identifier: &str, namespace: Option<&str>) -> Resolution {
+        self.symbols.registry().resolve(identifier, namespace)
+    }
+
+    /// Where the named type referenced or declared at byte `offset` of the
+    /// source is declared, which may be in an imported IDL file. `None` if
+    /// there is no type name at `offset`, or if the type comes from a JSON
+    /// schema or protocol.
+    #[must_use]
+    pub fn definition(&self, offset: usize) -> Option<Definition> {
+        self.symbols.definition_at(offset)
+    }
+
+    /// Where the named type `full_name` is declared. See
+    /// [`definition`](IdlOutput::definition).
+    #[must_use]
+    pub fn definition_of(&self, full_name: &str) -> Option<Definition> {
+        self.symbols.definition(full_name)
+    }
+
+    /// Rename the named type `full_name` to `new_name`, keeping its
+    /// namespace: rewrite its declaration, every reference to it (including
+    /// `throws` clauses), and every `@aliases` entry naming it, in the
+    /// compiled file and the IDL files it imports.
+    ///
+    /// Returns the new contents of each file that changed; nothing is
+    /// written. Fails if `new_name` is not a valid name or is taken, or if
+    /// the type is not declared in an IDL file.
+    pub fn rename(&self, full_name: &str, new_name: &str) -> miette::Result<Vec<EditedFile>> {
+        self.symbols.rename(full_name, new_name)
+    }
+
+    /// Render the compiled file back to Avro IDL: a protocol, or a
+    /// `schema <type>;` file. Imports are resolved, so the types they brought
+    /// in are declared in the output rather than imported.
+    ///
+    /// The result compiles to the same [`json`](IdlOutput::json), except that
+    /// output options such as [`strip_docs`](Idl::strip_docs) are not
+    /// applied.
+    #[must_use]
+    pub fn to_idl_string(&self) -> String {
+        match &self.idl_file {
+            IdlFile::Protocol(protocol) => protocol.to_idl_string(),
+            IdlFile::Schema(schema) => {
+                let types: Vec<_> = self.symbols.registry().schemas().cloned().collect();
+                schema.to_idl_string(&types)
+            }
+            IdlFile::NamedSchemas(_) => unreachable!("NamedSchemas rejected earlier"),
+        }
+    }
+
+    /// Write [`ordered_json`](IdlOutput::ordered_json) to `writer`,
+    /// pretty-printed like `avdl idl` prints it, without building the whole
+    /// text in memory.
+    pub fn write_json(&self, writer: impl io::Write) -> io::Result<()> {
+        serde_json::to_writer_pretty(writer, &self.ordered_json()).map_err(io::Error::from)
+    }
+
+    /// [`json`](IdlOutput::json), serializing a protocol's messages in
+    /// declaration order, or by name with
+    /// [`sort_messages`](Idl::sort_messages).
+    #[must_use]
+    pub fn ordered_json(&self) -> OrderedJson<'_> {
+        OrderedJson::new(&self.json, &self.message_order)
+    }
+
+    /// The semantic differences from `self` to `newer`: added, removed, and
+    /// changed types, fields, defaults, logical types, and messages. Types
+    /// and messages are matched by name, so reordering is not a change.
+    #[must_use]
+    pub fn diff(&self, newer: &IdlOutput) -> Vec<SchemaChange> {
+        diff_json(&self.json, &newer.json)
+    }
+
+    /// Render a static HTML documentation site: an `index.html` listing the
+    /// named types and message signatures, and one page per named type (doc
+    /// comment, fields, symbols, size) with links to the types it references.
+    #[must_use]
+    pub fn html_docs(&self) -> Vec<DocPage> {
+        html_pages(&self.json)
+    }
+
+    /// Render the same documentation as [`html_docs`](IdlOutput::html_docs)
+    /// as a single Markdown file named after the protocol, with an anchor per
+    /// named type and a table per record's fields.
+    #[must_use]
+    pub fn markdown_docs(&self) -> DocPage {
+        markdown_page(&self.json)
+    }
+
+    /// Every doc comment, keyed by the full name of what it documents: the
+    /// protocol and each named type by their full names, fields as
+    /// `<type>.<field>`, messages as `<protocol>.<message>`, and message
+    /// parameters as `<protocol>.<message>.<parameter>`.
+    #[must_use]
+    pub fn doc_comments(&self) -> BTreeMap<String, String> {
+        doc_comments(&self.json)
+    }
+
+    /// Render [`doc_comments`](IdlOutput::doc_comments) as a JSON object in
+    /// a file named `<protocol full name>.docs.json`.
+    #[must_use]
+    pub fn json_docs(&self) -> DocPage {
+        json_page(&self.json)
+    }
+}
+
+/// The kind of a named Avro type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NameKind {
+    Record,
+    /// A record declared with the `error` keyword.
+    Error,
+    Enum,
+    Fixed,
+}
+
+/// A named type known to a compiled IDL file. See [`IdlOutput::names`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeclaredName {
+    /// Fully-qualified name (e.g., `"org.example.User"`).
+    pub full_name: String,
+    pub kind: NameKind,
+    /// The file that defined the type, as it appears in diagnostics: the
+    /// input path, the resolved path of an import, or the custom name passed
+    /// to `convert_str_named` (`"<input>"` by default).
+    pub source: String,
+}
+
+/// A single resolved `import` statement. See [`IdlOutput::imports`].
+///
+/// Files that are imported more than once (or that form a cycle) are only
+/// read the first time, but every import statement gets its own edge, so the
+/// edges describe the complete graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportEdge {
+    /// The importing file, as it appears in diagnostics (see
+    /// [`DeclaredName::source`]).
+    pub importer: String,
+    pub kind: ImportKind,
+    /// The path as written in the import statement.
+    pub path: String,
+    /// The canonical path the import resolved to.
+    pub resolved: PathBuf,
+    /// The overlay or import directory the file was found in, as it was
+    /// passed to [`Idl::overlay_dir`] or [`Idl::import_dir`]. `None` for a
+    /// file found next to the importing file (outside any overlay), supplied
+    /// in memory, or found through [`Idl::import_mapping`].
+    pub root: Option<PathBuf>,
+    /// The paths on disk searched before `resolved` that had no file, so
+    /// that a file created at one of them would be imported instead. `None`
+    /// if the file was supplied in memory or an archive was searched.
+    pub missed: Option<Vec<PathBuf>>,
+}
+
+impl DeclaredName {
+    /// Describe a registered schema. Returns `None` for non-named schemas,
+    /// which the registry never contains.
+    pub(crate) fn new(schema: &AvroSchema, source: &str) -> Option<Self> {
+        let kind = match schema {
+            AvroSchema::Record {
+                is_error: false, ..
+            } => NameKind::Record,
+            AvroSchema::Record { is_error: true, .. } => NameKind::Error,
+            AvroSchema::Enum { .. } => NameKind::Enum,
+            AvroSchema::Fixed { .. } => NameKind::Fixed,
+            _ => return None,
+        };
+        Some(DeclaredName {
+            full_name: schema.full_name()?.into_owned(),
+            kind,
+            source: source.to_string(),
+        })
+    }
 }
 
 /// Shows the JSON shape and warning count without dumping the full graphical
@@ -239,6 +829,10 @@ impl std::fmt::Debug for IdlOutput {
                 "warnings",
                 &format_args!("[{} warnings]", self.warnings.len()),
             )
+            .field("names", &self.names)
+            .field("imports", &self.imports)
+            .field("graph", &self.graph)
+            .field("defaults", &self.defaults)
             .finish()
     }
 }
@@ -255,108 +849,674 @@ impl Idl {
     pub fn new() -> Self {
         Idl {
             inner: IdlCompiler::new(),
+            references_by_name: false,
+            build_metadata: false,
         }
     }
 
     /// Add an import search directory. Searched in order added, after the input
-    /// file's parent directory.
+    /// file's parent directory. A `.zip` or `.jar` file is searched as if it
+    /// were unpacked, so schemas packaged for Java can be imported as they
+    /// are.
     pub fn import_dir(&mut self, dir: impl Into<PathBuf>) -> &mut Self {
         self.inner.import_dir(dir.into());
         self
     }
 
-    /// Drain warnings accumulated during the most recent `convert*` call.
+    /// Resolve relative imports in sources compiled from strings (the `*_str`
+    /// methods) against `dir` instead of the current directory, e.g. when
+    /// the source is read from stdin. Files compiled by path always resolve
+    /// imports against their own directory.
+    pub fn import_base(&mut self, dir: impl Into<PathBuf>) -> &mut Self {
+        self.inner.import_base = Some(dir.into());
+        self
+    }
+
+    /// Supply the contents of an imported file in memory. An `import` whose
+    /// path names `path`, either relative to the importing file or on its
+    /// own, reads `contents` instead of the filesystem.
     ///
-    /// When `convert` or `convert_str_named` returns `Ok`, the warnings are
-    /// also available in [`IdlOutput::warnings`]. When they return `Err`,
-    /// this method is the only way to retrieve warnings that were collected
-    /// before the error occurred (e.g., orphaned doc-comment warnings from
-    /// parsing that precede a later type-resolution failure).
+    /// ```
+    /// use avdl::Idl;
     ///
-    /// Each call drains the internal buffer, so a second call returns an
-    /// empty `Vec`.
-    pub fn drain_warnings(&mut self) -> Vec<miette::Report> {
-        self.inner.drain_warnings()
+    /// let output = Idl::new()
+    ///     .import_source("common.avdl", "protocol Common { record Id { string value; } }")
+    ///     .convert_str(r#"protocol P { import idl "common.avdl"; record R { Id id; } }"#)?;
+    /// assert_eq!(output.json["types"][0]["name"], "Id");
+    /// # Ok::<(), miette::Report>(())
+    /// ```
+    pub fn import_source(
+        &mut self,
+        path: impl Into<PathBuf>,
+        contents: impl Into<String>,
+    ) -> &mut Self {
+        self.inner.import_source(path.into(), contents.into());
+        self
     }
 
-    /// Compile a `.avdl` file to JSON.
-    pub fn convert(&mut self, path: impl AsRef<Path>) -> miette::Result<IdlOutput> {
-        let compiled = self.inner.compile_file(path.as_ref())?;
-        self.convert_impl(compiled)
+    /// Map a logical import path prefix to a directory: an `import` whose
+    /// path starts with `prefix` reads the rest of the path under `dir`,
+    /// and nowhere else. This lets files share stable paths such as
+    /// `"@shared/money.avdl"` instead of `"../../shared/money.avdl"`. The
+    /// prefix is matched as written, so it should end in `/`; the longest
+    /// matching prefix wins.
+    ///
+    /// ```no_run
+    /// use avdl::Idl;
+    ///
+    /// // `import idl "@shared/money.avdl";` reads
+    /// // `/repo/schemas/shared/money.avdl`.
+    /// let output = Idl::new()
+    ///     .import_mapping("@shared/", "/repo/schemas/shared/")
+    ///     .convert("schemas/billing/invoice.avdl")?;
+    /// # Ok::<(), miette::Report>(())
+    /// ```
+    pub fn import_mapping(
+        &mut self,
+        prefix: impl Into<String>,
+        dir: impl Into<PathBuf>,
+    ) -> &mut Self {
+        self.inner.import_mapping(prefix.into(), dir.into());
+        self
     }
 
-    /// Compile an IDL source string to JSON. Uses `"<input>"` as the source
-    /// name in diagnostics.
-    pub fn convert_str(&mut self, source: &'static str) -> miette::Result<IdlOutput> {
-        self.convert_str_named(source, "<input>")
+    /// Add an overlay import directory. Overlay directories are layered
+    /// into one tree, earlier ones shadowing later ones: an import is
+    /// satisfied by the first overlay that has a file at its path, and a
+    /// relative import from a file in an overlay is looked up by its path
+    /// within the tree, so it also sees files patched in earlier overlays.
+    /// This lets a few shared files be patched locally without copying the
+    /// rest of the shared tree. Overlays are searched after the importing
+    /// file's own directory and before [`import_dir`](Self::import_dir)s.
+    ///
+    /// [`IdlOutput::imports`] reports which directory satisfied each import.
+    ///
+    /// ```no_run
+    /// use avdl::Idl;
+    ///
+    /// // Imports of `acme/money.avdl`, including ones from files under
+    /// // `vendor/shared`, read `patches/acme/money.avdl` if it exists.
+    /// let output = Idl::new()
+    ///     .overlay_dir("patches")
+    ///     .overlay_dir("vendor/shared")
+    ///     .convert("service.avdl")?;
+    /// # Ok::<(), miette::Report>(())
+    /// ```
+    pub fn overlay_dir(&mut self, dir: impl Into<PathBuf>) -> &mut Self {
+        self.inner.overlay_dir(dir.into());
+        self
     }
 
-    /// Compile an IDL source string to JSON with a custom source name for
-    /// diagnostics.
-    pub fn convert_str_named(
-        &mut self,
-        source: &'static str,
-        name: &'static str,
-    ) -> miette::Result<IdlOutput> {
-        let compiled = self.inner.compile_str(source, name)?;
-        self.convert_impl(compiled)
+    /// Choose how defaults on message request parameters are handled. The
+    /// default, [`ParamDefaults::Validate`], matches `avro-tools`.
+    pub fn param_defaults(&mut self, mode: ParamDefaults) -> &mut Self {
+        self.inner.param_defaults(mode);
+        self
     }
 
-    /// Type-specific serialization: serialize the parsed IDL to a single JSON
-    /// value (protocol or schema).
-    ///
-    /// This is the only logic that differs from `Idl2Schemata`. It rejects
-    /// `NamedSchemas` (bare declarations without a `schema` keyword or
-    /// `protocol`), matching Java's `IdlTool` behavior.
-    fn convert_impl(&mut self, compiled: CompileOutput) -> miette::Result<IdlOutput> {
-        let CompileOutput {
-            idl_file,
-            registry,
-            warnings,
-            source,
-            source_name,
-        } = compiled;
+    /// Skip declarations from a newer version of the IDL grammar, with a
+    /// warning, instead of failing. Only declarations that start with an
+    /// unknown word and are delimited by braces are skipped, such as
+    /// `service Billing { ... }`, at the top level or in a protocol body.
+    /// Imported IDL files are read the same way.
+    pub fn skip_unknown_declarations(&mut self, enabled: bool) -> &mut Self {
+        self.inner.skip_unknown_declarations(enabled);
+        self
+    }
 
-        // The `idl` subcommand requires either a protocol or a `schema` keyword.
-        // Schema-mode files with only bare named type declarations (records, enums,
-        // fixed) but no `schema` keyword are rejected — Java's `IdlTool.run()`
-        // checks `if (m == null && p == null)` and errors with "the IDL file does
-        // not contain a schema nor a protocol." The `idl2schemata` path
-        // intentionally omits this check so it can extract named schemas.
-        if let IdlFile::NamedSchemas(_) = &idl_file {
-            // Stash warnings before returning the error so they're available
-            // via `drain_warnings()`.
-            self.inner.accumulated_warnings = warnings;
+    /// Choose which branch of a union a field default may match. The default,
+    /// [`UnionDefaults::AnyBranch`], matches `avro-tools`;
+    /// [`UnionDefaults::FirstBranch`] follows the Avro specification, so the
+    /// output also passes stricter Avro implementations.
+    pub fn union_defaults(&mut self, mode: UnionDefaults) -> &mut Self {
+        self.inner.union_defaults = mode;
+        self
+    }
 
-            let span_len = source.len().min(1);
-            return Err(ParseDiagnostic {
-                span: SpanWithSource::new(0, span_len, source_name, source),
-                message: "IDL file contains neither a protocol nor a schema declaration"
-                    .to_string(),
-                label: Some("this file".to_string()),
-                help: Some(
-                    "wrap declarations in `protocol MyProto { ... }` or prefix with `schema <type>;`"
-                        .to_string(),
-                ),
-                related: Vec::new(),
+    /// Accept ISO-8601 strings as defaults of date, time, and timestamp
+    /// fields, such as `date since = "2024-01-31";` or
+    /// `timestamp_ms at = "2024-01-31T13:45:00Z";`, and emit them as the
+    /// numbers Avro stores. Timestamps need an offset; local timestamps must
+    /// not have one. Imported IDL files are read the same way.
+    pub fn iso8601_defaults(&mut self, enabled: bool) -> &mut Self {
+        self.inner.iso8601_defaults(enabled);
+        self
+    }
+
+    /// Turn a valid `@logicalType(...)` annotation on a primitive or fixed
+    /// type into the logical type it names, as Java does. On by default.
+    /// When off, the annotation stays a plain property, e.g. for consumers
+    /// that treat it as opaque metadata: the JSON is the same, but the typed
+    /// model (see [`IdlOutput::types`]) has an annotated primitive. Logical
+    /// type keywords such as `date` or `decimal(9, 2)` are unaffected.
+    pub fn promote_logical_types(&mut self, enabled: bool) -> &mut Self {
+        self.inner.promote_logical_types(enabled);
+        self
+    }
+
+    /// Reject field defaults that do not match the field's type. On by
+    /// default. When off, each invalid default is reported as a warning
+    /// (kind [`WarningKind::InvalidDefault`]) and emitted as written, e.g. to
+    /// compile legacy files that Java accepted while they are cleaned up.
+    /// Imported IDL files are read the same way.
+    pub fn validate_defaults(&mut self, enabled: bool) -> &mut Self {
+        self.inner.validate_defaults(enabled);
+        self
+    }
+
+    /// Flatten a union nested directly in another union, such as
+    /// `union { null, union { int, string } }`, into it, with a warning,
+    /// instead of failing. The Avro specification forbids nested unions, but
+    /// some producers write them. Imported IDL files are read the same way.
+    pub fn flatten_nested_unions(&mut self, enabled: bool) -> &mut Self {
+        self.inner.flatten_nested_unions(enabled);
+        self
+    }
+
+    /// Convert CRLF line endings to LF before parsing the input and imported
+    /// IDL files, so doc comments in the output and spans in diagnostics are
+    /// the same whether a file was checked out with Windows or Unix line
+    /// endings.
+    pub fn normalize_line_endings(&mut self, enabled: bool) -> &mut Self {
+        self.inner.normalize_line_endings(enabled);
+        self
+    }
+
+    /// Fail with an error, instead of overflowing the stack, on input that
+    /// nests more than `depth` levels of brackets, or types that nest more
+    /// than `depth` levels through the types they reference. The default is
+    /// 128.
+    pub fn max_nesting_depth(&mut self, depth: usize) -> &mut Self {
+        self.inner.max_nesting_depth(depth);
+        self
+    }
+
+    /// Run `handler` for every `@name(...)` annotation that becomes a custom
+    /// property, in the input file and in every imported IDL file. The
+    /// handler can validate the value, rewrite or remove it, and report
+    /// warnings (kind [`WarningKind::Annotation`]); returning `Err` fails
+    /// compilation with the message, pointing at the annotation. Several
+    /// handlers for the same name run in registration order. Imported files
+    /// are parsed in parallel, so a handler may be called from several
+    /// threads at once.
+    ///
+    /// ```
+    /// use avdl::Idl;
+    ///
+    /// let mut idl = Idl::new();
+    /// idl.annotation_handler("pii", |annotation| match annotation.value() {
+    ///     Some(serde_json::Value::Bool(_)) => Ok(()),
+    ///     _ => Err("expected true or false".to_string()),
+    /// });
+    /// let err = idl
+    ///     .convert_str(r#"protocol P { record R { string @pii("yes") email; } }"#)
+    ///     .unwrap_err();
+    /// assert!(format!("{err:?}").contains("invalid @pii annotation"));
+    /// ```
+    pub fn annotation_handler<F>(&mut self, name: impl Into<String>, handler: F) -> &mut Self
+    where
+        F: Fn(&mut Annotation<'_>) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.inner
+            .annotation_handler(name.into(), Arc::new(handler));
+        self
+    }
+
+    /// Warn about named types, declared or imported, that nothing references:
+    /// no record field, union, or message request, response, or error list
+    /// (and, in a schema file, not the `schema` declaration). Off by default,
+    /// since a protocol's top-level records are often meant to stand alone.
+    pub fn warn_unused_types(&mut self, enabled: bool) -> &mut Self {
+        self.inner.warn_unused_types = enabled;
+        self
+    }
+
+    /// Warn about every namespace declared in the input file that is not
+    /// `prefix` or inside it, e.g. `com.mycorp`, and about protocols and named
+    /// types with no namespace. Each warning points at the `@namespace`
+    /// annotation, `namespace` statement, or dotted name responsible.
+    /// Imported files are not checked.
+    pub fn require_namespace_prefix(&mut self, prefix: impl Into<String>) -> &mut Self {
+        self.inner.lints.namespace_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Warn about every name in the input file that breaks `rule`, e.g.
+    /// [`NamingRule::TypeNames`] for `PascalCase` type names, pointing at the
+    /// name and suggesting one that follows it. May be called repeatedly.
+    pub fn lint_names(&mut self, rule: NamingRule) -> &mut Self {
+        self.inner.lints.naming.push(rule);
+        self
+    }
+
+    /// Warn about every explicit union in the input file with more than
+    /// `max` branches.
+    pub fn max_union_branches(&mut self, max: usize) -> &mut Self {
+        self.inner.lints.max_union_branches = Some(max);
+        self
+    }
+
+    /// Warn about every nullable type in the input file not written in
+    /// `style`: `union { null, T }` where `T?` would do, or the reverse.
+    pub fn nullable_style(&mut self, style: NullableStyle) -> &mut Self {
+        self.inner.lints.nullable_style = Some(style);
+        self
+    }
+
+    /// Warn about every named type, field, and message in the input file
+    /// without a doc comment, within `scope`.
+    pub fn require_docs(&mut self, scope: DocScope) -> &mut Self {
+        self.inner.lints.require_docs = Some(scope);
+        self
+    }
+
+    /// Fail if compilation produces any warnings, e.g. to keep IDL
+    /// warning-free in CI. The warnings themselves are available from
+    /// `drain_warnings` after the call returns `Err`.
+    pub fn deny_warnings(&mut self, enabled: bool) -> &mut Self {
+        self.inner.deny_warnings = enabled;
+        self
+    }
+
+    /// Drop warnings of the given kind instead of reporting them, in the
+    /// input file and in every imported file. May be called repeatedly.
+    pub fn allow_warning(&mut self, kind: WarningKind) -> &mut Self {
+        self.inner.allowed_warnings.push(kind);
+        self
+    }
+
+    /// Omit all `doc` keys (from doc comments) from the output, e.g. to keep
+    /// internal notes out of schemas shared with third parties.
+    pub fn strip_docs(&mut self, enabled: bool) -> &mut Self {
+        self.inner.output_options.strip_docs = enabled;
+        self
+    }
+
+    /// Write a protocol's `messages` sorted by name rather than in
+    /// declaration order (imported protocols' messages first).
+    pub fn sort_messages(&mut self, enabled: bool) -> &mut Self {
+        self.inner.output_options.sort_messages = enabled;
+        self
+    }
+
+    /// Emit references to named types as fully-qualified name strings
+    /// instead of inlining a type's definition where it is first used. A
+    /// protocol then defines each type only in its `types` array, and a
+    /// standalone schema contains only its own definition, so consumers must
+    /// resolve the names themselves, as registries that support schema
+    /// references do.
+    pub fn references_by_name(&mut self, enabled: bool) -> &mut Self {
+        self.references_by_name = enabled;
+        self
+    }
+
+    /// Record how a protocol was built in two custom properties on it:
+    /// `avdl.compiler`, the compiler name and version (e.g. `"avdl 0.1.0"`),
+    /// and `avdl.sourceHash`, the CRC-64-AVRO fingerprint of the compiled
+    /// file's source text as 16 hex digits, so a deployed `.avpr` can be
+    /// traced back to the IDL it came from. Imported files are not part of
+    /// the hash. Has no effect on a `schema <type>;` file.
+    pub fn build_metadata(&mut self, enabled: bool) -> &mut Self {
+        self.build_metadata = enabled;
+        self
+    }
+
+    /// Only emit custom properties (annotations such as `@java-class`) whose
+    /// name matches `pattern`. May be called repeatedly; a property is kept
+    /// if it matches any allowed pattern. Properties the Avro specification
+    /// defines, including `logicalType`, `precision`, and `scale`, are never
+    /// removed.
+    ///
+    /// Patterns are globs in which `*` matches any run of characters and `?`
+    /// matches exactly one.
+    pub fn allow_property(&mut self, pattern: impl Into<String>) -> &mut Self {
+        self.inner
+            .output_options
+            .allow_properties
+            .push(pattern.into());
+        self
+    }
+
+    /// Remove custom properties whose name matches `pattern` (e.g.
+    /// `internal-*`) from the output, even if they match an
+    /// [`allow_property`](Self::allow_property) pattern.
+    pub fn deny_property(&mut self, pattern: impl Into<String>) -> &mut Self {
+        self.inner
+            .output_options
+            .deny_properties
+            .push(pattern.into());
+        self
+    }
+
+    /// Add the custom property `key` to a compiled protocol, replacing any
+    /// property of that name the source declares, e.g. to stamp a build
+    /// number into `version`. May be called repeatedly. Has no effect on a
+    /// `schema <type>;` file.
+    ///
+    /// Fails if `key` is one the Avro specification defines for protocols,
+    /// such as `namespace` or `types`.
+    pub fn set_property(
+        &mut self,
+        key: impl Into<String>,
+        value: Value,
+    ) -> miette::Result<&mut Self> {
+        let key = key.into();
+        if OutputOptions::is_protocol_key(&key) {
+            return Err(miette::miette!(
+                code = ErrorKind::ReservedProperty.code(),
+                "cannot set protocol property `{key}`: the Avro specification defines it"
+            ));
+        }
+        self.inner.output_options.set_properties.push((key, value));
+        Ok(self)
+    }
+
+    /// Drain warnings accumulated during the most recent `convert*` call.
+    ///
+    /// When `convert` or `convert_str_named` returns `Ok`, the warnings are
+    /// also available in [`IdlOutput::warnings`]. When they return `Err`,
+    /// this method is the only way to retrieve warnings that were collected
+    /// before the error occurred (e.g., orphaned doc-comment warnings from
+    /// parsing that precede a later type-resolution failure).
+    ///
+    /// Each call drains the internal buffer, so a second call returns an
+    /// empty `Vec`.
+    pub fn drain_warnings(&mut self) -> Vec<miette::Report> {
+        self.inner.drain_warnings()
+    }
+
+    /// Compile a `.avdl` file to JSON.
+    pub fn convert(&mut self, path: impl AsRef<Path>) -> miette::Result<IdlOutput> {
+        let compiled = self.inner.compile_file(path.as_ref())?;
+        self.convert_impl(compiled)
+    }
+
+    /// Compile a `.avdl` file and write the JSON to `writer`, pretty-printed
+    /// like `avdl idl` prints it.
+    ///
+    /// The JSON is streamed rather than first rendered into a `String`,
+    /// which for very large protocols would double peak memory. Wrap
+    /// `writer` in a [`BufWriter`](std::io::BufWriter) if it is unbuffered.
+    pub fn convert_to_writer(
+        &mut self,
+        path: impl AsRef<Path>,
+        writer: impl io::Write,
+    ) -> miette::Result<IdlOutput> {
+        let output = self.convert(path)?;
+        output
+            .write_json(writer)
+            .map_err(|e| miette::miette!("{e}: write JSON"))?;
+        Ok(output)
+    }
+
+    /// Compile an IDL source string to JSON. Uses `"<input>"` as the source
+    /// name in diagnostics.
+    pub fn convert_str(&mut self, source: &'static str) -> miette::Result<IdlOutput> {
+        self.convert_str_named(source, "<input>")
+    }
+
+    /// Compile an owned IDL source string to JSON. Uses `"<input>"` as the
+    /// source name in diagnostics.
+    ///
+    /// Compiled schemas and diagnostics point into the source, so it is kept
+    /// for the rest of the process, but only once: compiling the same text
+    /// again reuses the copy kept before. Prefer this over leaking a string
+    /// for [`convert_str`](Self::convert_str) when compiling repeatedly.
+    pub fn convert_string(&mut self, source: String) -> miette::Result<IdlOutput> {
+        self.convert_str(keep_source(source))
+    }
+
+    /// Compile an IDL source string to JSON with a custom source name for
+    /// diagnostics.
+    pub fn convert_str_named(
+        &mut self,
+        source: &'static str,
+        name: &'static str,
+    ) -> miette::Result<IdlOutput> {
+        let compiled = self.inner.compile_str(source, name)?;
+        self.convert_impl(compiled)
+    }
+
+    /// Apply the safe fixes for the errors in a `.avdl` file: insert the
+    /// `import` that defines an undefined name, quote an identifier where a
+    /// string is expected, and remove duplicate union branches. The file
+    /// itself is left alone; write [`FixOutput::content`] back to change it.
+    ///
+    /// Fails only if the file cannot be read. Errors that have no safe fix
+    /// are returned in [`FixOutput::remaining_error`].
+    pub fn fix(&mut self, path: impl AsRef<Path>) -> miette::Result<FixOutput> {
+        let input = InputFile::read(path.as_ref())?;
+        Ok(self
+            .inner
+            .fix(input.source, input.name, &input.dir, input.canonical_path))
+    }
+
+    /// Apply the safe fixes for the errors in an IDL source string. See
+    /// [`fix`](Self::fix).
+    ///
+    /// ```
+    /// use avdl::Idl;
+    ///
+    /// let output = Idl::new().fix_str(
+    ///     "protocol P { enum E { A, B } record R { E e = A; } }",
+    /// );
+    /// assert_eq!(
+    ///     output.content,
+    ///     r#"protocol P { enum E { A, B } record R { E e = "A"; } }"#,
+    /// );
+    /// ```
+    #[must_use]
+    pub fn fix_str(&mut self, source: &str) -> FixOutput {
+        let base = self.inner.str_base();
+        self.inner.fix(source, "<input>", &base, None)
+    }
+
+    /// Compile a `.avdl` file that declares several protocols, one after
+    /// another, into one output per protocol, in file order.
+    ///
+    /// The IDL grammar allows a single protocol per file; this is an
+    /// extension. Each protocol compiles as if it were alone in the file, so
+    /// it sees only its own imports and types. A file with a single protocol,
+    /// or a schema file, gives a single output.
+    pub fn convert_protocols(&mut self, path: impl AsRef<Path>) -> miette::Result<Vec<IdlOutput>> {
+        let input = InputFile::read(path.as_ref())?;
+        let mut outputs = Vec::new();
+        for source in split_protocols(input.source) {
+            let compiled =
+                self.inner
+                    .compile(source, input.name, &input.dir, input.canonical_path.clone())?;
+            outputs.push(self.convert_impl(compiled)?);
+        }
+        Ok(outputs)
+    }
+
+    /// Compile an IDL source string that declares several protocols into
+    /// one output per protocol. See
+    /// [`convert_protocols`](Self::convert_protocols).
+    ///
+    /// ```
+    /// use avdl::Idl;
+    ///
+    /// let outputs = Idl::new().convert_str_protocols_named(
+    ///     "protocol Orders { record Order { string id; } }\n\
+    ///      protocol Users { record User { string name; } }",
+    ///     "services.avdl",
+    /// )?;
+    /// assert_eq!(outputs.len(), 2);
+    /// assert_eq!(outputs[1].json["protocol"], "Users");
+    /// # Ok::<(), miette::Report>(())
+    /// ```
+    pub fn convert_str_protocols_named(
+        &mut self,
+        source: &'static str,
+        name: &'static str,
+    ) -> miette::Result<Vec<IdlOutput>> {
+        let mut outputs = Vec::new();
+        for source in split_protocols(source) {
+            let compiled = self.inner.compile_str(source, name)?;
+            outputs.push(self.convert_impl(compiled)?);
+        }
+        Ok(outputs)
+    }
+
+    /// Type-specific serialization: serialize the parsed IDL to a single JSON
+    /// value (protocol or schema).
+    ///
+    /// This is the only logic that differs from `Idl2Schemata`. It rejects
+    /// `NamedSchemas` (bare declarations without a `schema` keyword or
+    /// `protocol`), matching Java's `IdlTool` behavior.
+    fn convert_impl(&mut self, compiled: CompileOutput) -> miette::Result<IdlOutput> {
+        let CompileOutput {
+            idl_file,
+            registry,
+            warnings,
+            type_sources,
+            imports,
+            defaults,
+            type_spans,
+            source,
+            source_name,
+        } = compiled;
+
+        // The `idl` subcommand requires either a protocol or a `schema` keyword.
+        // Schema-mode files with only bare named type declarations (records, enums,
+        // fixed) but no `schema` keyword are rejected — Java's `IdlTool.run()`
+        // checks `if (m == null && p == null)` and errors with "the IDL file does
+        // not contain a schema nor a protocol." The `idl2schemata` path
+        // intentionally omits this check so it can extract named schemas.
+        if let IdlFile::NamedSchemas(_) = &idl_file {
+            // Stash warnings before returning the error so they're available
+            // via `drain_warnings()`.
+            self.inner.accumulated_warnings = warnings;
+
+            let span_len = source.len().min(1);
+            return Err(ParseDiagnostic {
+                kind: ErrorKind::NoDeclaration,
+                span: SpanWithSource::new(0, span_len, source_name, source),
+                message: "IDL file contains neither a protocol nor a schema declaration"
+                    .to_string(),
+                label: Some("this file".to_string()),
+                help: Some(
+                    "wrap declarations in `protocol MyProto { ... }` or prefix with `schema <type>;`"
+                        .to_string(),
+                ),
+                related: Vec::new(),
+                name: None,
+                replacement: None,
             }
             .into());
         }
 
         // Serialize the parsed IDL to JSON. Protocols become .avpr, standalone
         // schemas become .avsc.
-        let json = match &idl_file {
-            IdlFile::Protocol(protocol) => protocol_to_json(protocol),
+        let mut json = match &idl_file {
+            IdlFile::Protocol(protocol) => protocol_json(protocol, self.references_by_name),
             IdlFile::Schema(schema) => {
-                let registry_schemas: Vec<_> = registry.schemas().cloned().collect();
-                let lookup = build_lookup(&registry_schemas, None);
-                schema_to_json(schema, &mut HashSet::new(), None, &lookup)
+                let lookup = build_lookup(registry.schemas(), None);
+                // Marking every other type as already serialized makes
+                // `schema_to_json` emit a name wherever it is referenced.
+                let mut known_names: HashSet<String> = if self.references_by_name {
+                    let own_name = schema.full_name();
+                    registry
+                        .names()
+                        .filter(|name| Some(*name) != own_name.as_deref())
+                        .map(str::to_string)
+                        .collect()
+                } else {
+                    HashSet::new()
+                };
+                let mut json = schema_to_json(schema, &mut known_names, None, &lookup);
+                if self.references_by_name {
+                    qualify_schema_references(&mut json, None);
+                }
+                json
             }
             // `NamedSchemas` is rejected above — this arm is unreachable.
             IdlFile::NamedSchemas(_) => unreachable!("NamedSchemas rejected earlier"),
         };
+        let output_options = &self.inner.output_options;
+        match &idl_file {
+            IdlFile::Protocol(_) => output_options.apply_to_protocol(&mut json),
+            _ => output_options.apply_to_schema(&mut json),
+        }
+        if self.build_metadata
+            && let IdlFile::Protocol(_) = &idl_file
+            && let Value::Object(obj) = &mut json
+        {
+            let compiler = format!("avdl {}", env!("CARGO_PKG_VERSION"));
+            let source_hash = format!("{:016x}", rabin_fingerprint(source.as_bytes()));
+            obj.insert("avdl.compiler".to_string(), Value::String(compiler));
+            obj.insert("avdl.sourceHash".to_string(), Value::String(source_hash));
+        }
+
+        let names = registry
+            .schemas()
+            .zip(&type_sources)
+            .filter_map(|(schema, source)| DeclaredName::new(schema, source))
+            .collect();
+
+        let protocol = match &idl_file {
+            IdlFile::Protocol(protocol) => Some(protocol),
+            _ => None,
+        };
+        let message_order = protocol
+            .map(|protocol| self.inner.output_options.message_order(protocol))
+            .unwrap_or_default();
+        let graph = TypeGraph::build(&registry, protocol);
+        let symbols = SymbolIndex::build(&idl_file, registry, &type_spans, source_name);
+
+        Ok(IdlOutput {
+            json,
+            warnings,
+            names,
+            imports,
+            graph,
+            defaults,
+            symbols,
+            idl_file,
+            message_order,
+        })
+    }
+}
+
+/// Serialize a protocol to `.avpr` JSON, inlining each named type at its
+/// first use or, with `by_name`, referencing every type by full name.
+fn protocol_json(protocol: &Protocol, by_name: bool) -> Value {
+    if by_name {
+        let mut json = protocol_to_json_by_name(protocol);
+        qualify_protocol_references(&mut json);
+        json
+    } else {
+        protocol_to_json(protocol)
+    }
+}
+
+// ==============================================================================
+// Phase Hooks for Benchmarks
+// ==============================================================================
+
+/// A compiled-but-not-serialized file, handed from
+/// [`Idl::compile_phase`] to [`Idl::emit_phase`].
+#[cfg(feature = "bench")]
+pub(crate) struct Compiled(CompileOutput);
+
+#[cfg(feature = "bench")]
+impl Idl {
+    /// Run the compilation preamble (parse, resolve, validate) alone.
+    pub(crate) fn compile_phase(
+        &mut self,
+        source: &'static str,
+        source_name: &'static str,
+        input_dir: &Path,
+    ) -> miette::Result<Compiled> {
+        self.inner
+            .compile(source, source_name, input_dir, None)
+            .map(Compiled)
+    }
 
-        Ok(IdlOutput { json, warnings })
+    /// Serialize the output of [`compile_phase`](Self::compile_phase).
+    pub(crate) fn emit_phase(&mut self, compiled: Compiled) -> miette::Result<IdlOutput> {
+        self.convert_impl(compiled.0)
     }
 }
 
@@ -373,11 +1533,56 @@ impl Idl {
 pub struct NamedSchema {
     /// Simple name of the schema (the `.avsc` filename stem).
     pub name: String,
+    /// Namespace of the schema, if any (e.g., `"com.example.events"`). Two
+    /// schemas may share a [`name`](NamedSchema::name) if their namespaces
+    /// differ.
+    pub namespace: Option<String>,
+    /// Full names of the named types this schema references directly through
+    /// its fields, deduplicated, in field order. Excludes the schema itself.
+    /// With [`Idl2Schemata::transitive_dependencies`], also the types those
+    /// reference, and so on, each after its own dependencies.
+    pub dependencies: Vec<String>,
+    /// The file that declared the schema, as it appears in diagnostics (see
+    /// [`DeclaredName::source`]).
+    pub source: String,
+    /// Whether the schema came from an import rather than from the input
+    /// file itself.
+    pub imported: bool,
+    /// The relative path `avdl idl2schemata` writes the schema to:
+    /// `<name>.avsc`, `<namespace as path>/<name>.avsc` with
+    /// [`Idl2Schemata::namespace_dirs`], or as laid out by
+    /// [`Idl2Schemata::output_template`]. In the flat layout, schemas with
+    /// the same simple name share a path.
+    pub suggested_path: PathBuf,
+    /// CRC-64-AVRO fingerprint of the schema's Parsing Canonical Form, as
+    /// defined by the Avro specification.
+    pub fingerprint: u64,
     /// Self-contained JSON representation with all referenced types inlined on
     /// first occurrence.
     pub schema: Value,
 }
 
+impl NamedSchema {
+    /// The schema's full name: its namespace and name joined with a dot, or
+    /// just the name if it has no namespace.
+    #[must_use]
+    pub fn full_name(&self) -> String {
+        match &self.namespace {
+            Some(namespace) => format!("{namespace}.{}", self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Whether this schema reads and writes the same data as `other`, by
+    /// comparing their Parsing Canonical Forms (see [`canonical_eq`]).
+    ///
+    /// [`canonical_eq`]: crate::canonical_eq
+    #[must_use]
+    pub fn canonical_eq(&self, other: &NamedSchema) -> bool {
+        canonical_eq(&self.schema, &other.schema)
+    }
+}
+
 /// Result of extracting individual schemas from Avro IDL.
 ///
 /// Contains all named schemas (records, enums, fixed) from the IDL source,
@@ -392,6 +1597,88 @@ pub struct SchemataOutput {
     /// Print with `eprintln!("{report:?}")` for rich diagnostic output
     /// including source spans and labels.
     pub warnings: Vec<miette::Report>,
+    /// The protocols of the extracted files, if
+    /// [`Idl2Schemata::protocols`] is enabled. Schema-mode files have none.
+    pub protocols: Vec<NamedProtocol>,
+    imports: Vec<ImportEdge>,
+}
+
+/// A protocol compiled alongside the extracted schemas (see
+/// [`Idl2Schemata::protocols`]).
+#[derive(Debug)]
+pub struct NamedProtocol {
+    /// Simple name of the protocol (the `.avpr` filename stem).
+    pub name: String,
+    /// Namespace of the protocol, if any.
+    pub namespace: Option<String>,
+    /// The `.avpr` JSON, as [`Idl`] produces it with the same options. Its
+    /// `messages` are keyed by name; [`ordered_json`](NamedProtocol::ordered_json)
+    /// writes them in declaration order.
+    pub json: Value,
+    /// The protocol's message names, in the order to write them.
+    message_order: Vec<String>,
+}
+
+impl NamedProtocol {
+    /// [`json`](NamedProtocol::json), serializing the messages in declaration
+    /// order, or by name with [`sort_messages`](Idl2Schemata::sort_messages).
+    #[must_use]
+    pub fn ordered_json(&self) -> OrderedJson<'_> {
+        OrderedJson::new(&self.json, &self.message_order)
+    }
+}
+
+impl SchemataOutput {
+    /// The transitive import graph of the extracted file(s). See
+    /// [`IdlOutput::imports`].
+    #[must_use]
+    pub fn imports(&self) -> &[ImportEdge] {
+        &self.imports
+    }
+
+    /// Named types that are defined more than once with structurally
+    /// different schemas.
+    ///
+    /// Mostly useful after extracting a directory, where each `.avdl` file is
+    /// compiled independently and the same full name may come from several
+    /// files. Two definitions are the same if their Parsing Canonical Forms
+    /// match (see [`NamedSchema::fingerprint`]), so differences in docs,
+    /// aliases, or custom properties are not conflicts. Each definition that
+    /// differs from the first one seen is reported once.
+    #[must_use]
+    pub fn conflicts(&self) -> Vec<SchemaConflict> {
+        let mut first_seen: HashMap<String, &NamedSchema> = HashMap::new();
+        let mut reported = HashSet::new();
+        let mut conflicts = Vec::new();
+        for schema in &self.schemas {
+            let full_name = schema.full_name();
+            let Some(first) = first_seen.get(&full_name).copied() else {
+                first_seen.insert(full_name, schema);
+                continue;
+            };
+            if first.fingerprint != schema.fingerprint
+                && reported.insert((full_name.clone(), schema.fingerprint))
+            {
+                conflicts.push(SchemaConflict {
+                    full_name,
+                    first_source: first.source.clone(),
+                    second_source: schema.source.clone(),
+                });
+            }
+        }
+        conflicts
+    }
+}
+
+/// A named type with two structurally different definitions, as returned by
+/// [`SchemataOutput::conflicts`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaConflict {
+    pub full_name: String,
+    /// The file that defined the first definition seen.
+    pub first_source: String,
+    /// The file that defined the differing definition.
+    pub second_source: String,
 }
 
 impl std::fmt::Debug for SchemataOutput {
@@ -402,6 +1689,8 @@ impl std::fmt::Debug for SchemataOutput {
                 "warnings",
                 &format_args!("[{} warnings]", self.warnings.len()),
             )
+            .field("protocols", &self.protocols)
+            .field("imports", &self.imports)
             .finish()
     }
 }
@@ -422,6 +1711,18 @@ impl std::fmt::Debug for SchemataOutput {
 /// ```
 pub struct Idl2Schemata {
     inner: IdlCompiler,
+    /// Name patterns a schema must match one of to be extracted. Empty means
+    /// every schema is included.
+    include: Vec<String>,
+    /// Name patterns that exclude a schema, even if it is included.
+    exclude: Vec<String>,
+    dependency_order: bool,
+    references_by_name: bool,
+    only_roots: bool,
+    protocols: bool,
+    namespace_dirs: bool,
+    output_template: Option<OutputTemplate>,
+    transitive_dependencies: bool,
 }
 
 impl Default for Idl2Schemata {
@@ -436,41 +1737,407 @@ impl Idl2Schemata {
     pub fn new() -> Self {
         Idl2Schemata {
             inner: IdlCompiler::new(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            dependency_order: false,
+            references_by_name: false,
+            only_roots: false,
+            protocols: false,
+            namespace_dirs: false,
+            output_template: None,
+            transitive_dependencies: false,
         }
     }
 
-    /// Add an import search directory.
+    /// Add an import search directory, or a `.zip` or `.jar` archive to
+    /// search as if it were one. See [`Idl::import_dir`].
     pub fn import_dir(&mut self, dir: impl Into<PathBuf>) -> &mut Self {
         self.inner.import_dir(dir.into());
         self
     }
 
-    /// Drain warnings accumulated during the most recent `extract*` call.
-    ///
-    /// When `extract` or `extract_str_named` returns `Ok`, the warnings are
-    /// also available in [`SchemataOutput::warnings`]. When they return `Err`,
-    /// this method is the only way to retrieve warnings that were collected
-    /// before the error occurred (e.g., orphaned doc-comment warnings from
-    /// parsing that precede a later type-resolution failure).
-    ///
-    /// Each call drains the internal buffer, so a second call returns an
-    /// empty `Vec`.
-    pub fn drain_warnings(&mut self) -> Vec<miette::Report> {
-        self.inner.drain_warnings()
+    /// Supply the contents of an imported file in memory. See
+    /// [`Idl::import_source`].
+    pub fn import_source(
+        &mut self,
+        path: impl Into<PathBuf>,
+        contents: impl Into<String>,
+    ) -> &mut Self {
+        self.inner.import_source(path.into(), contents.into());
+        self
     }
 
-    /// Extract named schemas from a `.avdl` file or a directory of `.avdl`
-    /// files. When given a directory, recursively walks it for `.avdl` files
-    /// (using [`walkdir`]).
-    pub fn extract(&mut self, path: impl AsRef<Path>) -> miette::Result<SchemataOutput> {
-        let path = path.as_ref();
+    /// Map a logical import path prefix to a directory. See
+    /// [`Idl::import_mapping`].
+    pub fn import_mapping(
+        &mut self,
+        prefix: impl Into<String>,
+        dir: impl Into<PathBuf>,
+    ) -> &mut Self {
+        self.inner.import_mapping(prefix.into(), dir.into());
+        self
+    }
 
-        if path.is_dir() {
+    /// Add an overlay import directory, shadowed by the ones added before
+    /// it. See [`Idl::overlay_dir`].
+    pub fn overlay_dir(&mut self, dir: impl Into<PathBuf>) -> &mut Self {
+        self.inner.overlay_dir(dir.into());
+        self
+    }
+
+    /// Resolve relative imports in sources compiled from strings (the `*_str`
+    /// methods) against `dir` instead of the current directory, e.g. when
+    /// the source is read from stdin. Files compiled by path always resolve
+    /// imports against their own directory.
+    pub fn import_base(&mut self, dir: impl Into<PathBuf>) -> &mut Self {
+        self.inner.import_base = Some(dir.into());
+        self
+    }
+
+    /// Choose how defaults on message request parameters are handled. Only
+    /// validation is affected, since extracted schemas do not include
+    /// messages.
+    pub fn param_defaults(&mut self, mode: ParamDefaults) -> &mut Self {
+        self.inner.param_defaults(mode);
+        self
+    }
+
+    /// Skip declarations from a newer version of the IDL grammar, with a
+    /// warning, instead of failing. Only declarations that start with an
+    /// unknown word and are delimited by braces are skipped, such as
+    /// `service Billing { ... }`, at the top level or in a protocol body.
+    /// Imported IDL files are read the same way.
+    pub fn skip_unknown_declarations(&mut self, enabled: bool) -> &mut Self {
+        self.inner.skip_unknown_declarations(enabled);
+        self
+    }
+
+    /// Choose which branch of a union a field default may match. The default,
+    /// [`UnionDefaults::AnyBranch`], matches `avro-tools`;
+    /// [`UnionDefaults::FirstBranch`] follows the Avro specification, so the
+    /// output also passes stricter Avro implementations.
+    pub fn union_defaults(&mut self, mode: UnionDefaults) -> &mut Self {
+        self.inner.union_defaults = mode;
+        self
+    }
+
+    /// Accept ISO-8601 strings as defaults of date, time, and timestamp
+    /// fields, such as `date since = "2024-01-31";` or
+    /// `timestamp_ms at = "2024-01-31T13:45:00Z";`, and emit them as the
+    /// numbers Avro stores. Timestamps need an offset; local timestamps must
+    /// not have one. Imported IDL files are read the same way.
+    pub fn iso8601_defaults(&mut self, enabled: bool) -> &mut Self {
+        self.inner.iso8601_defaults(enabled);
+        self
+    }
+
+    /// Turn a valid `@logicalType(...)` annotation on a primitive or fixed
+    /// type into the logical type it names, as Java does. On by default.
+    /// When off, the annotation stays a plain property, e.g. for consumers
+    /// that treat it as opaque metadata: the JSON is the same, but the typed
+    /// model (see [`IdlOutput::types`]) has an annotated primitive. Logical
+    /// type keywords such as `date` or `decimal(9, 2)` are unaffected.
+    pub fn promote_logical_types(&mut self, enabled: bool) -> &mut Self {
+        self.inner.promote_logical_types(enabled);
+        self
+    }
+
+    /// Reject field defaults that do not match the field's type. On by
+    /// default. When off, each invalid default is reported as a warning
+    /// (kind [`WarningKind::InvalidDefault`]) and emitted as written, e.g. to
+    /// compile legacy files that Java accepted while they are cleaned up.
+    /// Imported IDL files are read the same way.
+    pub fn validate_defaults(&mut self, enabled: bool) -> &mut Self {
+        self.inner.validate_defaults(enabled);
+        self
+    }
+
+    /// Flatten a union nested directly in another union, such as
+    /// `union { null, union { int, string } }`, into it, with a warning,
+    /// instead of failing. The Avro specification forbids nested unions, but
+    /// some producers write them. Imported IDL files are read the same way.
+    pub fn flatten_nested_unions(&mut self, enabled: bool) -> &mut Self {
+        self.inner.flatten_nested_unions(enabled);
+        self
+    }
+
+    /// Convert CRLF line endings to LF before parsing the input and imported
+    /// IDL files, so doc comments in the output and spans in diagnostics are
+    /// the same whether a file was checked out with Windows or Unix line
+    /// endings.
+    pub fn normalize_line_endings(&mut self, enabled: bool) -> &mut Self {
+        self.inner.normalize_line_endings(enabled);
+        self
+    }
+
+    /// Fail with an error, instead of overflowing the stack, on input that
+    /// nests more than `depth` levels of brackets, or types that nest more
+    /// than `depth` levels through the types they reference. The default is
+    /// 128.
+    pub fn max_nesting_depth(&mut self, depth: usize) -> &mut Self {
+        self.inner.max_nesting_depth(depth);
+        self
+    }
+
+    /// Run `handler` for every `@name(...)` annotation that becomes a custom
+    /// property, in the input file and in every imported IDL file. The
+    /// handler can validate the value, rewrite or remove it, and report
+    /// warnings (kind [`WarningKind::Annotation`]); returning `Err` fails
+    /// compilation with the message, pointing at the annotation. Several
+    /// handlers for the same name run in registration order. Imported files
+    /// are parsed in parallel, so a handler may be called from several
+    /// threads at once.
+    ///
+    /// ```
+    /// use avdl::Idl2Schemata;
+    ///
+    /// let mut idl = Idl2Schemata::new();
+    /// idl.annotation_handler("pii", |annotation| match annotation.value() {
+    ///     Some(serde_json::Value::Bool(_)) => Ok(()),
+    ///     _ => Err("expected true or false".to_string()),
+    /// });
+    /// let err = idl
+    ///     .extract_str(r#"protocol P { record R { string @pii("yes") email; } }"#)
+    ///     .unwrap_err();
+    /// assert!(format!("{err:?}").contains("invalid @pii annotation"));
+    /// ```
+    pub fn annotation_handler<F>(&mut self, name: impl Into<String>, handler: F) -> &mut Self
+    where
+        F: Fn(&mut Annotation<'_>) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.inner
+            .annotation_handler(name.into(), Arc::new(handler));
+        self
+    }
+
+    /// Warn about named types, declared or imported, that nothing references:
+    /// no record field, union, or message request, response, or error list
+    /// (and, in a schema file, not the `schema` declaration). Off by default,
+    /// since a protocol's top-level records are often meant to stand alone.
+    pub fn warn_unused_types(&mut self, enabled: bool) -> &mut Self {
+        self.inner.warn_unused_types = enabled;
+        self
+    }
+
+    /// Warn about every namespace declared in the input file that is not
+    /// `prefix` or inside it, e.g. `com.mycorp`, and about protocols and named
+    /// types with no namespace. Each warning points at the `@namespace`
+    /// annotation, `namespace` statement, or dotted name responsible.
+    /// Imported files are not checked.
+    pub fn require_namespace_prefix(&mut self, prefix: impl Into<String>) -> &mut Self {
+        self.inner.lints.namespace_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Warn about every name in the input file that breaks `rule`, e.g.
+    /// [`NamingRule::TypeNames`] for `PascalCase` type names, pointing at the
+    /// name and suggesting one that follows it. May be called repeatedly.
+    pub fn lint_names(&mut self, rule: NamingRule) -> &mut Self {
+        self.inner.lints.naming.push(rule);
+        self
+    }
+
+    /// Warn about every explicit union in the input file with more than
+    /// `max` branches.
+    pub fn max_union_branches(&mut self, max: usize) -> &mut Self {
+        self.inner.lints.max_union_branches = Some(max);
+        self
+    }
+
+    /// Warn about every nullable type in the input file not written in
+    /// `style`: `union { null, T }` where `T?` would do, or the reverse.
+    pub fn nullable_style(&mut self, style: NullableStyle) -> &mut Self {
+        self.inner.lints.nullable_style = Some(style);
+        self
+    }
+
+    /// Warn about every named type, field, and message in the input file
+    /// without a doc comment, within `scope`.
+    pub fn require_docs(&mut self, scope: DocScope) -> &mut Self {
+        self.inner.lints.require_docs = Some(scope);
+        self
+    }
+
+    /// Fail if compilation produces any warnings, e.g. to keep IDL
+    /// warning-free in CI. The warnings themselves are available from
+    /// `drain_warnings` after the call returns `Err`.
+    pub fn deny_warnings(&mut self, enabled: bool) -> &mut Self {
+        self.inner.deny_warnings = enabled;
+        self
+    }
+
+    /// Drop warnings of the given kind instead of reporting them, in the
+    /// input file and in every imported file. May be called repeatedly.
+    pub fn allow_warning(&mut self, kind: WarningKind) -> &mut Self {
+        self.inner.allowed_warnings.push(kind);
+        self
+    }
+
+    /// Omit all `doc` keys (from doc comments) from the output, e.g. to keep
+    /// internal notes out of schemas shared with third parties.
+    pub fn strip_docs(&mut self, enabled: bool) -> &mut Self {
+        self.inner.output_options.strip_docs = enabled;
+        self
+    }
+
+    /// Write a protocol's `messages` sorted by name rather than in
+    /// declaration order (imported protocols' messages first).
+    pub fn sort_messages(&mut self, enabled: bool) -> &mut Self {
+        self.inner.output_options.sort_messages = enabled;
+        self
+    }
+
+    /// Only emit custom properties (annotations such as `@java-class`) whose
+    /// name matches `pattern`. May be called repeatedly; a property is kept
+    /// if it matches any allowed pattern. Properties the Avro specification
+    /// defines, including `logicalType`, `precision`, and `scale`, are never
+    /// removed.
+    ///
+    /// Patterns are globs in which `*` matches any run of characters and `?`
+    /// matches exactly one.
+    pub fn allow_property(&mut self, pattern: impl Into<String>) -> &mut Self {
+        self.inner
+            .output_options
+            .allow_properties
+            .push(pattern.into());
+        self
+    }
+
+    /// Remove custom properties whose name matches `pattern` (e.g.
+    /// `internal-*`) from the output, even if they match an
+    /// [`allow_property`](Self::allow_property) pattern.
+    pub fn deny_property(&mut self, pattern: impl Into<String>) -> &mut Self {
+        self.inner
+            .output_options
+            .deny_properties
+            .push(pattern.into());
+        self
+    }
+
+    /// Only extract schemas whose full name matches `pattern`. May be called
+    /// repeatedly; a schema is extracted if it matches any included pattern.
+    ///
+    /// A pattern is either a full name (`com.example.User`) or a glob in
+    /// which `*` matches any run of characters, dots included, and `?`
+    /// matches exactly one (`com.example.*`).
+    pub fn include(&mut self, pattern: impl Into<String>) -> &mut Self {
+        self.include.push(pattern.into());
+        self
+    }
+
+    /// Skip schemas whose full name matches `pattern`, even if they match an
+    /// [`include`](Self::include) pattern. Uses the same pattern syntax.
+    pub fn exclude(&mut self, pattern: impl Into<String>) -> &mut Self {
+        self.exclude.push(pattern.into());
+        self
+    }
+
+    /// Emit schemas in dependency order, so that every schema comes after the
+    /// schemas it references (see [`NamedSchema::dependencies`]). Schemas that
+    /// are not ordered by a dependency keep their declaration order, as do the
+    /// members of a reference cycle. When extracting a directory, each file is
+    /// ordered separately.
+    ///
+    /// Registries that register schemas one at a time need this order.
+    pub fn dependency_order(&mut self, enabled: bool) -> &mut Self {
+        self.dependency_order = enabled;
+        self
+    }
+
+    /// List every named type a schema depends on in
+    /// [`NamedSchema::dependencies`], including through other types, instead
+    /// of only the ones it references directly. Each name comes after the
+    /// names it depends on, so registering them in order satisfies every
+    /// reference, except within a reference cycle.
+    pub fn transitive_dependencies(&mut self, enabled: bool) -> &mut Self {
+        self.transitive_dependencies = enabled;
+        self
+    }
+
+    /// Emit references to other named types as fully-qualified name strings
+    /// instead of inlining their definitions, so each schema contains only its own
+    /// definition. The output is then no longer self-contained: consumers
+    /// must resolve the names themselves, as registries that support schema
+    /// references do. Usually combined with
+    /// [`dependency_order`](Self::dependency_order).
+    pub fn references_by_name(&mut self, enabled: bool) -> &mut Self {
+        self.references_by_name = enabled;
+        self
+    }
+
+    /// Only extract root schemas: named types that no other named type
+    /// references. References from protocol messages and self-references do
+    /// not count. Since each extracted schema inlines the types it
+    /// references, the roots alone still contain every type they use.
+    pub fn only_roots(&mut self, enabled: bool) -> &mut Self {
+        self.only_roots = enabled;
+        self
+    }
+
+    /// Also serialize each extracted protocol file to its `.avpr` JSON, in
+    /// [`SchemataOutput::protocols`], so that pipelines needing both the
+    /// schemas and the protocol compile the file once.
+    pub fn protocols(&mut self, enabled: bool) -> &mut Self {
+        self.protocols = enabled;
+        self
+    }
+
+    /// Lay out [`NamedSchema::suggested_path`] with one directory per
+    /// namespace component, as `avdl idl2schemata --namespace-dirs` does,
+    /// instead of flat.
+    pub fn namespace_dirs(&mut self, enabled: bool) -> &mut Self {
+        self.namespace_dirs = enabled;
+        self
+    }
+
+    /// Lay out [`NamedSchema::suggested_path`] by `template`, as
+    /// `avdl idl2schemata --output-template` does, instead of flat or by
+    /// [`namespace_dirs`](Self::namespace_dirs). `{stem}` is the stem of the
+    /// file that declared the schema, and `{ext}` is `avsc`.
+    ///
+    /// ```
+    /// use avdl::{Idl2Schemata, OutputTemplate};
+    ///
+    /// let output = Idl2Schemata::new()
+    ///     .output_template(OutputTemplate::parse("{namespace}/{name}.avsc")?)
+    ///     .extract_str(r#"@namespace("com.example") protocol P { record User {} }"#)?;
+    /// assert_eq!(
+    ///     output.schemas[0].suggested_path,
+    ///     std::path::Path::new("com.example/User.avsc")
+    /// );
+    /// # Ok::<(), miette::Report>(())
+    /// ```
+    pub fn output_template(&mut self, template: OutputTemplate) -> &mut Self {
+        self.output_template = Some(template);
+        self
+    }
+
+    /// Drain warnings accumulated during the most recent `extract*` call.
+    ///
+    /// When `extract` or `extract_str_named` returns `Ok`, the warnings are
+    /// also available in [`SchemataOutput::warnings`]. When they return `Err`,
+    /// this method is the only way to retrieve warnings that were collected
+    /// before the error occurred (e.g., orphaned doc-comment warnings from
+    /// parsing that precede a later type-resolution failure).
+    ///
+    /// Each call drains the internal buffer, so a second call returns an
+    /// empty `Vec`.
+    pub fn drain_warnings(&mut self) -> Vec<miette::Report> {
+        self.inner.drain_warnings()
+    }
+
+    /// Extract named schemas from a `.avdl` file or a directory of `.avdl`
+    /// files. When given a directory, recursively walks it for `.avdl` files
+    /// (using [`walkdir`]).
+    pub fn extract(&mut self, path: impl AsRef<Path>) -> miette::Result<SchemataOutput> {
+        let path = path.as_ref();
+
+        if path.is_dir() {
             return self.extract_directory(path);
         }
 
         let compiled = self.inner.compile_file(path)?;
-        Ok(Self::extract_impl(compiled))
+        Ok(self.extract_impl(compiled))
     }
 
     /// Extract named schemas from an IDL source string.
@@ -486,7 +2153,7 @@ impl Idl2Schemata {
         name: &'static str,
     ) -> miette::Result<SchemataOutput> {
         let compiled = self.inner.compile_str(source, name)?;
-        Ok(Self::extract_impl(compiled))
+        Ok(self.extract_impl(compiled))
     }
 
     /// Recursively walk a directory for `.avdl` files and extract schemas from
@@ -495,6 +2162,8 @@ impl Idl2Schemata {
     fn extract_directory(&mut self, dir: &Path) -> miette::Result<SchemataOutput> {
         let mut all_schemas = Vec::new();
         let mut all_warnings = Vec::new();
+        let mut all_protocols = Vec::new();
+        let mut all_imports = Vec::new();
 
         let mut avdl_paths: Vec<PathBuf> = Vec::new();
         for entry in walkdir::WalkDir::new(dir)
@@ -510,14 +2179,18 @@ impl Idl2Schemata {
 
         for avdl_path in &avdl_paths {
             let compiled = self.inner.compile_file(avdl_path)?;
-            let output = Self::extract_impl(compiled);
+            let output = self.extract_impl(compiled);
             all_schemas.extend(output.schemas);
             all_warnings.extend(output.warnings);
+            all_protocols.extend(output.protocols);
+            all_imports.extend(output.imports);
         }
 
         Ok(SchemataOutput {
             schemas: all_schemas,
             warnings: all_warnings,
+            protocols: all_protocols,
+            imports: all_imports,
         })
     }
 
@@ -527,35 +2200,221 @@ impl Idl2Schemata {
     /// This is the only logic that differs from `Idl`. Unlike `Idl::convert_impl`,
     /// this accepts `NamedSchemas` (bare declarations without `schema` keyword or
     /// `protocol`), matching Java's `IdlToSchemataTool` behavior.
-    fn extract_impl(compiled: CompileOutput) -> SchemataOutput {
+    fn extract_impl(&self, compiled: CompileOutput) -> SchemataOutput {
         let CompileOutput {
-            registry, warnings, ..
+            idl_file,
+            registry,
+            warnings,
+            type_sources,
+            imports,
+            source_name,
+            ..
         } = compiled;
 
         // Build a lookup table from all registered schemas so that references
         // within each schema can be resolved and inlined.
-        let registry_schemas: Vec<_> = registry.schemas().cloned().collect();
-        let all_lookup = build_lookup(&registry_schemas, None);
+        let all_lookup = build_lookup(registry.schemas(), None);
 
         // Serialize each named schema independently with fresh `known_names`,
         // matching Java's `Schema.toString(true)` which creates a fresh
         // `HashSet` per call. This ensures each `.avsc` file is self-contained.
+        // Every type referenced by a type other than itself, for `only_roots`.
+        let mut referenced = HashSet::new();
+        if self.only_roots {
+            for schema in registry.schemas() {
+                let own_name = schema.full_name();
+                for dep in type_references(schema) {
+                    if own_name.as_deref() != Some(dep.as_str()) {
+                        referenced.insert(dep);
+                    }
+                }
+            }
+        }
+
         let mut schemas = Vec::new();
-        for schema in registry.schemas() {
-            let simple_name = match schema.name() {
-                Some(n) => n.to_string(),
-                None => continue,
+        for (schema, source) in registry.schemas().zip(type_sources) {
+            let (Some(simple_name), Some(full_name)) = (schema.name(), schema.full_name()) else {
+                continue;
+            };
+            if !self.selects(&full_name) || referenced.contains(full_name.as_ref()) {
+                continue;
+            }
+            let namespace = full_name.rsplit_once('.').map(|(ns, _)| ns.to_string());
+
+            let dependencies = if self.transitive_dependencies {
+                transitive_dependencies(&registry, &full_name)
+            } else {
+                let mut dependencies: Vec<String> = Vec::new();
+                for dep in type_references(schema) {
+                    if dep != full_name && !dependencies.contains(&dep) {
+                        dependencies.push(dep);
+                    }
+                }
+                dependencies
+            };
+
+            // Marking every other type as already serialized makes
+            // `schema_to_json` emit a name wherever it is referenced.
+            let mut known_names: HashSet<String> = if self.references_by_name {
+                registry
+                    .names()
+                    .filter(|name| *name != full_name)
+                    .map(str::to_string)
+                    .collect()
+            } else {
+                HashSet::new()
+            };
+            let mut json_value = schema_to_json(schema, &mut known_names, None, &all_lookup);
+            if self.references_by_name {
+                qualify_schema_references(&mut json_value, None);
+            }
+            self.inner.output_options.apply_to_schema(&mut json_value);
+            let suggested_path = match &self.output_template {
+                Some(template) => template.expand(&TemplateValues {
+                    stem: &Path::new(&source)
+                        .file_stem()
+                        .unwrap_or_default()
+                        .to_string_lossy(),
+                    name: simple_name,
+                    namespace: namespace.as_deref(),
+                    ext: "avsc",
+                }),
+                None => {
+                    let mut suggested_path = PathBuf::new();
+                    if self.namespace_dirs
+                        && let Some(namespace) = &namespace
+                    {
+                        suggested_path.extend(namespace.split('.'));
+                    }
+                    suggested_path.push(format!("{simple_name}.avsc"));
+                    suggested_path
+                }
             };
-            let mut known_names = HashSet::new();
-            let json_value = schema_to_json(schema, &mut known_names, None, &all_lookup);
             schemas.push(NamedSchema {
-                name: simple_name,
+                name: simple_name.to_string(),
+                namespace,
+                dependencies,
+                imported: source != source_name,
+                source,
+                suggested_path,
+                fingerprint: fingerprint64(&json_value),
                 schema: json_value,
             });
         }
 
-        SchemataOutput { schemas, warnings }
+        if self.dependency_order {
+            schemas = order_by_dependencies(schemas);
+        }
+
+        let mut protocols = Vec::new();
+        if self.protocols
+            && let IdlFile::Protocol(protocol) = &idl_file
+        {
+            let mut json = protocol_json(protocol, self.references_by_name);
+            self.inner.output_options.apply_to_protocol(&mut json);
+            protocols.push(NamedProtocol {
+                name: protocol.name.clone(),
+                namespace: protocol.namespace.clone(),
+                json,
+                message_order: self.inner.output_options.message_order(protocol),
+            });
+        }
+
+        SchemataOutput {
+            schemas,
+            warnings,
+            protocols,
+            imports,
+        }
+    }
+
+    /// Whether the schema named `full_name` passes the include and exclude
+    /// filters.
+    fn selects(&self, full_name: &str) -> bool {
+        let included = self.include.is_empty()
+            || self
+                .include
+                .iter()
+                .any(|pattern| glob_matches(pattern, full_name));
+        included
+            && !self
+                .exclude
+                .iter()
+                .any(|pattern| glob_matches(pattern, full_name))
+    }
+}
+
+/// Reorder `schemas` so that each one follows the schemas it depends on.
+///
+/// A depth-first walk in declaration order, emitting a schema after its
+/// dependencies, keeps unrelated schemas in declaration order. A schema that
+/// is still being visited is skipped when reached again, which breaks
+/// reference cycles. Dependencies that are not in `schemas` (e.g. filtered
+/// out) are ignored.
+fn order_by_dependencies(schemas: Vec<NamedSchema>) -> Vec<NamedSchema> {
+    fn visit(
+        index: usize,
+        schemas: &[NamedSchema],
+        positions: &HashMap<String, usize>,
+        visited: &mut [bool],
+        order: &mut Vec<usize>,
+    ) {
+        if visited[index] {
+            return;
+        }
+        visited[index] = true;
+        for dep in &schemas[index].dependencies {
+            if let Some(&dep_index) = positions.get(dep) {
+                visit(dep_index, schemas, positions, visited, order);
+            }
+        }
+        order.push(index);
+    }
+
+    let positions: HashMap<String, usize> = schemas
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (s.full_name(), i))
+        .collect();
+    let mut visited = vec![false; schemas.len()];
+    let mut order = Vec::with_capacity(schemas.len());
+    for index in 0..schemas.len() {
+        visit(index, &schemas, &positions, &mut visited, &mut order);
+    }
+
+    let mut slots: Vec<Option<NamedSchema>> = schemas.into_iter().map(Some).collect();
+    order
+        .into_iter()
+        .map(|i| slots[i].take().expect("each schema is emitted once"))
+        .collect()
+}
+
+/// Every named type `full_name` depends on, directly or through other types,
+/// each after the types it depends on (depth-first postorder). Excludes
+/// `full_name` itself.
+fn transitive_dependencies(registry: &SchemaRegistry, full_name: &str) -> Vec<String> {
+    fn visit(
+        name: &str,
+        registry: &SchemaRegistry,
+        visited: &mut HashSet<String>,
+        order: &mut Vec<String>,
+    ) {
+        if !visited.insert(name.to_string()) {
+            return;
+        }
+        if let Some(schema) = registry.lookup(name) {
+            for dep in type_references(schema) {
+                visit(&dep, registry, visited, order);
+            }
+        }
+        order.push(name.to_string());
     }
+
+    let mut visited = HashSet::new();
+    let mut order = Vec::new();
+    visit(full_name, registry, &mut visited, &mut order);
+    order.pop();
+    order
 }
 
 // ==============================================================================
@@ -568,23 +2427,114 @@ impl Idl2Schemata {
 struct CompileContext {
     registry: SchemaRegistry,
     import_ctx: ImportContext,
-    messages: HashMap<String, Message>,
+    messages: IndexMap<String, Message>,
     warnings: Vec<miette::Report>,
+    reader_options: ReaderOptions,
+    /// Source name for each schema in `registry`, in registration order.
+    /// Kept in step with the registry by `attribute_new_types`.
+    type_sources: Vec<String>,
+    /// Import statements in resolution order, including ones that are skipped
+    /// because the file was already imported.
+    imports: Vec<ImportEdge>,
     /// Maps JSON-imported file display names to their import statement spans
     /// in the IDL source. Used to enrich error messages for unresolved
     /// references from `.avsc`/`.avpr` imports, which lack source spans of
     /// their own.
     json_import_spans: Vec<(String, Option<SpanWithSource>)>,
+    /// Records with field defaults that could not be validated when the
+    /// record was registered, because a referenced type came later.
+    deferred_defaults: Vec<DeferredDefaults>,
+    /// Declaration span of each named type declared in IDL, by full name.
+    type_spans: HashMap<String, SpanWithSource>,
+    /// Warning kinds that `warn` drops.
+    allowed_warnings: Vec<WarningKind>,
+    /// Imported files read and parsed ahead of time by `prefetch_imports`,
+    /// by resolved path and import kind, waiting for their import to be
+    /// resolved.
+    prefetched: HashMap<(PathBuf, ImportKind), miette::Result<LoadedImport>>,
+}
+
+/// A record whose field defaults are validated after all types are known,
+/// together with the spans to report problems at.
+struct DeferredDefaults {
+    schema: AvroSchema,
+    span: Option<SpanWithSource>,
+    field_spans: HashMap<String, SpanWithSource>,
+    /// Fields whose invalid default was already reported.
+    reported: Vec<String>,
 }
 
 impl CompileContext {
-    fn new(import_dirs: &[PathBuf]) -> Self {
+    fn new(
+        import_dirs: &[PathBuf],
+        import_sources: &[(PathBuf, String)],
+        import_mappings: &[(String, PathBuf)],
+        overlay_dirs: &[PathBuf],
+        reader_options: &ReaderOptions,
+        allowed_warnings: &[WarningKind],
+    ) -> Self {
+        let mut import_ctx = ImportContext::new(import_dirs.to_vec());
+        for (path, contents) in import_sources {
+            import_ctx.add_source(path, contents.clone());
+        }
+        for (prefix, dir) in import_mappings {
+            import_ctx.add_mapping(prefix.clone(), dir.clone());
+        }
+        for dir in overlay_dirs {
+            import_ctx.add_overlay(dir.clone());
+        }
         CompileContext {
             registry: SchemaRegistry::new(),
-            import_ctx: ImportContext::new(import_dirs.to_vec()),
-            messages: HashMap::new(),
+            import_ctx,
+            messages: IndexMap::new(),
             warnings: Vec::new(),
+            reader_options: reader_options.clone(),
+            type_sources: Vec::new(),
+            imports: Vec::new(),
             json_import_spans: Vec::new(),
+            deferred_defaults: Vec::new(),
+            type_spans: HashMap::new(),
+            allowed_warnings: allowed_warnings.to_vec(),
+            prefetched: HashMap::new(),
+        }
+    }
+
+    /// Report `warning` unless its kind is allowed. Warnings from imported
+    /// files are wrapped with the file name, so the user knows where they
+    /// originated.
+    fn warn(&mut self, warning: Warning, imported_from: Option<&str>) {
+        if self.allowed_warnings.contains(&warning.kind) {
+            return;
+        }
+        let report = miette::Report::new(warning);
+        self.warnings.push(match imported_from {
+            Some(file) => report.wrap_err(file.to_string()),
+            None => report,
+        });
+    }
+
+    /// Attribute every schema registered since the last call to `source`.
+    fn attribute_new_types(&mut self, source: &str) {
+        let added = self.registry.names().skip(self.type_sources.len()).count();
+        self.type_sources
+            .extend(std::iter::repeat_n(source.to_string(), added));
+    }
+
+    /// The parsed JSON of the protocol or schema file at `path`, prefetched
+    /// if it was, or read and parsed now. `what` names the kind of file.
+    fn take_loaded_json(&mut self, path: &Path, what: &str) -> miette::Result<Value> {
+        let kind = if what == "protocol" {
+            ImportKind::Protocol
+        } else {
+            ImportKind::Schema
+        };
+        match self.prefetched.remove(&(path.to_path_buf(), kind)) {
+            Some(Ok(LoadedImport::Json(json))) => Ok(json),
+            Some(Err(e)) => Err(e),
+            _ => {
+                let content = self.import_ctx.read_source(path, what)?;
+                parse_import_json(path, &content)
+            }
         }
     }
 }
@@ -608,16 +2558,14 @@ fn parse_and_resolve(
     ctx: &mut CompileContext,
 ) -> miette::Result<(IdlFile, SchemaRegistry)> {
     let (idl_file, decl_items, local_warnings) =
-        parse_idl_named(source, source_name).context("parse IDL source")?;
+        parse_idl_named(source, source_name, &ctx.reader_options).context("parse IDL source")?;
 
     // Immediately convert local warnings into `miette::Report`s and store
     // them in `ctx.warnings`. This must happen before any fallible operation
     // so that warnings survive even if a later step returns `Err`.
-    let local_reports: Vec<miette::Report> = local_warnings
-        .into_iter()
-        .map(miette::Report::new)
-        .collect();
-    ctx.warnings.extend(local_reports);
+    for warning in local_warnings {
+        ctx.warn(warning, None);
+    }
 
     // Pre-size the registry based on the number of type declarations in this
     // file. This avoids incremental reallocation of the backing IndexMap.
@@ -640,7 +2588,8 @@ fn parse_and_resolve(
     // Process declaration items in source order: resolve imports when
     // encountered, register local types when encountered. Any import-derived
     // warnings are appended to `ctx.warnings` by `process_decl_items`.
-    process_decl_items(&decl_items, ctx, input_dir)?;
+    process_decl_items(&decl_items, ctx, input_dir, source_name)?;
+    validate_deferred_defaults(ctx)?;
 
     // For protocol files, rebuild the types list from the registry (which now
     // includes imported types in declaration order) and prepend imported
@@ -664,104 +2613,233 @@ fn parse_and_resolve(
 }
 
 /// Process declaration items (imports and local types) in source order.
+///
+/// `source_name` is the display name of the file the items came from, used to
+/// attribute locally-declared types.
 fn process_decl_items(
     decl_items: &[DeclItem],
     ctx: &mut CompileContext,
     current_dir: &Path,
+    source_name: &str,
 ) -> miette::Result<()> {
+    prefetch_imports(decl_items, ctx, current_dir);
+    // Invalid defaults of every record in the file, reported together once
+    // the whole file is registered.
+    let mut invalid_defaults = Vec::new();
     for item in decl_items {
         match item {
             DeclItem::Import(import) => {
-                resolve_single_import(import, ctx, current_dir)?;
+                resolve_single_import(import, ctx, current_dir, source_name)?;
             }
             DeclItem::Type(schema, span, field_spans) => {
-                if let Err(msg) = ctx.registry.register(schema.as_ref().clone()) {
+                if let Err(e) = ctx.registry.register(schema.as_ref().clone()) {
                     if let Some(span) = span.as_ref() {
                         return Err(ParseDiagnostic {
+                            kind: ErrorKind::of(&e).unwrap_or(ErrorKind::DuplicateType),
                             span: *span,
-                            message: msg,
+                            message: e.to_string(),
                             label: None,
                             help: None,
                             related: Vec::new(),
+                            name: None,
+                            replacement: None,
                         }
                         .into());
                     }
-                    return Err(miette::miette!("{msg}"));
+                    return Err(e);
+                }
+                ctx.attribute_new_types(source_name);
+                if let (Some(full_name), Some(span)) = (schema.full_name(), span) {
+                    ctx.type_spans.insert(full_name.into_owned(), *span);
                 }
 
                 // Validate field defaults for Reference-typed fields now that
                 // the registry contains all previously-registered types.
-                // All validation errors are reported at once so users can fix
-                // multiple bad defaults in one edit cycle.
-                let errors = validate_record_field_defaults(schema, |full_name| {
-                    ctx.registry.lookup(full_name).cloned()
-                });
-                if errors.is_empty() {
-                    continue;
-                }
-                let type_name = schema.full_name().unwrap_or(Cow::Borrowed("<unknown>"));
-                let mut error_iter = errors.into_iter();
-                let (first_field, first_reason) = error_iter.next().expect("errors is non-empty");
-
-                // Build related diagnostics from subsequent errors.
-                let related: Vec<ParseDiagnostic> = error_iter
-                    .filter_map(|(field_name, reason)| {
-                        let msg = format!(
-                            "Invalid default for field `{field_name}` in `{type_name}`: {reason}"
-                        );
-                        let effective_span = field_spans.get(&field_name).copied().or(*span);
-                        effective_span.map(|span| ParseDiagnostic {
-                            span,
-                            message: msg,
-                            label: None,
-                            help: None,
-                            related: Vec::new(),
-                        })
-                    })
-                    .collect();
-
-                let first_msg = format!(
-                    "Invalid default for field `{first_field}` in `{type_name}`: {first_reason}"
+                // Defaults that reference types not registered yet are checked
+                // again by `validate_deferred_defaults` once every declaration
+                // and import has been processed.
+                let lookup = |full_name: &str| ctx.registry.lookup_shared(full_name);
+                let errors = unreported_default_errors(
+                    schema,
+                    validate_record_field_defaults(schema, lookup),
+                    &[],
                 );
-                // Prefer the per-field span (from the variable declaration)
-                // over the type-level span (from the record keyword), so the
-                // diagnostic highlights the offending field, not the record.
-                let effective_span = field_spans.get(&first_field).copied().or(*span);
-                if let Some(span) = effective_span {
-                    return Err(ParseDiagnostic {
-                        span,
-                        message: first_msg,
-                        label: None,
-                        help: None,
-                        related,
+                let unresolved = has_unresolved_field_defaults(schema, lookup);
+                let reported = errors.iter().map(|(field, _)| field.clone()).collect();
+                let found = invalid_default_warnings(schema, *span, field_spans, errors);
+                if ctx.reader_options.validate_defaults {
+                    invalid_defaults.extend(found);
+                } else {
+                    for warning in found {
+                        ctx.warn(warning, None);
                     }
-                    .into());
                 }
-                return Err(miette::miette!("{first_msg}"));
+                if unresolved {
+                    ctx.deferred_defaults.push(DeferredDefaults {
+                        schema: schema.as_ref().clone(),
+                        span: *span,
+                        field_spans: field_spans.clone(),
+                        reported,
+                    });
+                }
             }
         }
     }
 
+    if !invalid_defaults.is_empty() {
+        return Err(invalid_defaults_error(invalid_defaults));
+    }
+    Ok(())
+}
+
+/// Validate the field defaults that `process_decl_items` had to skip because
+/// they referenced types declared later in the file or in a later import.
+///
+/// References that still do not resolve are left to
+/// `validate_all_references`, which reports them as undefined.
+fn validate_deferred_defaults(ctx: &mut CompileContext) -> miette::Result<()> {
+    let mut invalid_defaults = Vec::new();
+    for deferred in std::mem::take(&mut ctx.deferred_defaults) {
+        let errors = unreported_default_errors(
+            &deferred.schema,
+            validate_record_field_defaults(&deferred.schema, |full_name| {
+                ctx.registry.lookup_shared(full_name)
+            }),
+            &deferred.reported,
+        );
+        let found = invalid_default_warnings(
+            &deferred.schema,
+            deferred.span,
+            &deferred.field_spans,
+            errors,
+        );
+        if ctx.reader_options.validate_defaults {
+            invalid_defaults.extend(found);
+        } else {
+            for warning in found {
+                ctx.warn(warning, None);
+            }
+        }
+    }
+    if !invalid_defaults.is_empty() {
+        return Err(invalid_defaults_error(invalid_defaults));
+    }
     Ok(())
 }
 
+/// The field default errors of a record that have not been reported yet:
+/// those of fields not in `reported`, and whose default the reader did not
+/// already find invalid before references were resolved.
+fn unreported_default_errors(
+    schema: &AvroSchema,
+    errors: Vec<(String, String)>,
+    reported: &[String],
+) -> Vec<(String, String)> {
+    let AvroSchema::Record { fields, .. } = schema else {
+        return errors;
+    };
+    errors
+        .into_iter()
+        .filter(|(name, _)| {
+            !reported.contains(name)
+                && fields.iter().any(|field| {
+                    field.name == *name
+                        && field
+                            .default
+                            .as_ref()
+                            .is_some_and(|default| is_valid_default(default, &field.schema))
+                })
+        })
+        .collect()
+}
+
+/// A record's invalid field defaults, one per field, at the field where
+/// known. They are reported as warnings when default validation is off, and
+/// combined by `invalid_defaults_error` otherwise.
+fn invalid_default_warnings(
+    schema: &AvroSchema,
+    span: Option<SpanWithSource>,
+    field_spans: &HashMap<String, SpanWithSource>,
+    errors: Vec<(String, String)>,
+) -> Vec<Warning> {
+    let type_name = schema.full_name().unwrap_or(Cow::Borrowed("<unknown>"));
+    errors
+        .into_iter()
+        .map(|(field_name, reason)| Warning {
+            kind: WarningKind::InvalidDefault,
+            message: format!("Invalid default for field `{field_name}` in `{type_name}`: {reason}"),
+            span: field_spans.get(&field_name).copied().or(span),
+        })
+        .collect()
+}
+
+/// Build one diagnostic for the invalid field defaults of any number of
+/// records.
+///
+/// All validation errors are reported at once so users can fix multiple bad
+/// defaults in one edit cycle: the first is the primary diagnostic, and the
+/// rest are attached as related diagnostics.
+fn invalid_defaults_error(invalid: Vec<Warning>) -> miette::Report {
+    let mut invalid = invalid.into_iter();
+    let first = invalid.next().expect("invalid is non-empty");
+    let related: Vec<ParseDiagnostic> = invalid
+        .filter_map(|warning| {
+            warning.span.map(|span| ParseDiagnostic {
+                kind: ErrorKind::InvalidDefault,
+                span,
+                message: warning.message,
+                label: None,
+                help: None,
+                related: Vec::new(),
+                name: None,
+                replacement: None,
+            })
+        })
+        .collect();
+
+    // The span is the field's (from the variable declaration) where known,
+    // so the diagnostic highlights the offending field, not the record.
+    match first.span {
+        Some(span) => ParseDiagnostic {
+            kind: ErrorKind::InvalidDefault,
+            span,
+            message: first.message,
+            label: None,
+            help: None,
+            related,
+            name: None,
+            replacement: None,
+        }
+        .into(),
+        None => miette::miette!(code = ErrorKind::InvalidDefault.code(), "{}", first.message),
+    }
+}
+
 /// Resolve a single import entry, registering schemas and merging messages
-/// into the current protocol.
+/// into the current protocol. `importer` is the display name of the file
+/// containing the import statement.
 fn resolve_single_import(
     import: &crate::reader::ImportEntry,
     ctx: &mut CompileContext,
     current_dir: &Path,
+    importer: &str,
 ) -> miette::Result<()> {
-    let resolved_path = match ctx.import_ctx.resolve_import(&import.path, current_dir) {
-        Ok(p) => p,
+    let resolved = match ctx
+        .import_ctx
+        .resolve_import_root(&import.path, current_dir)
+    {
+        Ok(resolved) => resolved,
         Err(e) => {
             if let Some(span) = import.span {
                 return Err(ParseDiagnostic {
+                    kind: ErrorKind::of(&e).unwrap_or(ErrorKind::ImportNotFound),
                     span,
                     message: format!("{e}"),
                     label: None,
                     help: None,
                     related: Vec::new(),
+                    name: None,
+                    replacement: None,
                 }
                 .into());
             }
@@ -769,6 +2847,16 @@ fn resolve_single_import(
         }
     };
 
+    let resolved_path = resolved.path;
+    ctx.imports.push(ImportEdge {
+        importer: importer.to_string(),
+        kind: import.kind,
+        path: import.path.clone(),
+        resolved: resolved_path.clone(),
+        root: resolved.root,
+        missed: resolved.missed,
+    });
+
     // Skip files we've already imported (cycle prevention).
     if ctx.import_ctx.mark_imported(&resolved_path) {
         return Ok(());
@@ -781,9 +2869,12 @@ fn resolve_single_import(
 
     match import.kind {
         ImportKind::Protocol => {
-            let imported_messages = import_protocol(&resolved_path, &mut ctx.registry)
+            let imported_messages = ctx
+                .take_loaded_json(&resolved_path, "protocol")
+                .and_then(|json| register_protocol(&resolved_path, &json, &mut ctx.registry))
                 .map_err(|e| wrap_import_error(e, import.span, &resolved_path, "protocol"))?;
             ctx.messages.extend(imported_messages);
+            ctx.attribute_new_types(&resolved_path.display().to_string());
 
             // Track the import so unresolved references from this .avpr can
             // be attributed to the import statement in error diagnostics.
@@ -791,8 +2882,10 @@ fn resolve_single_import(
                 .push((resolved_path.display().to_string(), import.span));
         }
         ImportKind::Schema => {
-            import_schema(&resolved_path, &mut ctx.registry)
+            ctx.take_loaded_json(&resolved_path, "schema")
+                .and_then(|json| register_schema(&resolved_path, &json, &mut ctx.registry))
                 .map_err(|e| wrap_import_error(e, import.span, &resolved_path, "schema"))?;
+            ctx.attribute_new_types(&resolved_path.display().to_string());
 
             // Track the import so unresolved references from this .avsc can
             // be attributed to the import statement in error diagnostics.
@@ -800,15 +2893,38 @@ fn resolve_single_import(
                 .push((resolved_path.display().to_string(), import.span));
         }
         ImportKind::Idl => {
-            let imported_source = fs::read_to_string(&resolved_path)
-                .map_err(|e| miette::miette!("{e}"))
-                .with_context(|| format!("read imported IDL {}", resolved_path.display()))
-                .map(String::leak)?;
+            let LoadedIdl {
+                source: imported_source,
+                parsed,
+            } = match ctx
+                .prefetched
+                .remove(&(resolved_path.clone(), ImportKind::Idl))
+            {
+                Some(Ok(LoadedImport::Idl(loaded))) => *loaded,
+                Some(Err(e)) => return Err(e),
+                _ => load_idl_import(&ctx.import_ctx, &ctx.reader_options, &resolved_path)?,
+            };
 
-            let imported_name = resolved_path.display().to_string().leak();
-            let (imported_idl, nested_decl_items, import_warnings) =
-                parse_idl_named(imported_source, imported_name)
-                    .with_context(|| format!("parse imported IDL {}", resolved_path.display()))?;
+            let imported_name = resolved_path.display().to_string();
+            let (imported_idl, nested_decl_items, import_warnings) = match parsed {
+                Ok(parsed) => parsed,
+                // `import idl` of an `.avpr` or `.avsc` file fails with a
+                // syntax error at the first `{`; name the right import
+                // kind instead.
+                Err(e) => {
+                    return Err(match json_import_kind(imported_source) {
+                        Some(actual) => wrap_import_error(
+                            wrong_import_kind(&resolved_path, actual, ImportKind::Idl),
+                            import.span,
+                            &resolved_path,
+                            "idl",
+                        ),
+                        None => {
+                            e.wrap_err(format!("parse imported IDL {}", resolved_path.display()))
+                        }
+                    });
+                }
+            };
 
             // Propagate warnings from the imported file, wrapping each with the
             // import filename as context so the user knows where they originated.
@@ -817,8 +2933,7 @@ fn resolve_single_import(
                 .and_then(|n| n.to_str())
                 .unwrap_or(import.path.as_str());
             for w in import_warnings {
-                ctx.warnings
-                    .push(miette::Report::new(w).wrap_err(import_file_name.to_string()));
+                ctx.warn(w, Some(import_file_name));
             }
 
             // If the imported IDL is a protocol, merge its messages.
@@ -830,18 +2945,143 @@ fn resolve_single_import(
             // IDL imports use their own source text for span tracking, so
             // `ctx.json_import_spans` is passed through to capture any nested
             // JSON imports within the imported IDL file.
-            process_decl_items(&nested_decl_items, ctx, &import_dir).with_context(|| {
-                format!("resolve nested imports from `{}`", resolved_path.display())
-            })?;
+            process_decl_items(&nested_decl_items, ctx, &import_dir, &imported_name).with_context(
+                || format!("resolve nested imports from `{}`", resolved_path.display()),
+            )?;
         }
     }
 
     Ok(())
 }
 
-/// Wrap an import error with the IDL source span of the import statement.
-///
-/// When the import statement's byte range (`span`) is available, the returned
+// ==============================================================================
+// Parallel Import Loading
+// ==============================================================================
+//
+// Parsing dominates the time spent on a file with many imports, and the files
+// a file imports can be read and parsed independently of each other. Before
+// resolving a file's imports one by one, `prefetch_imports` loads all of its
+// imports on a few threads: IDL files are parsed, and JSON files are parsed
+// into a `serde_json::Value`. Resolution then proceeds in declaration order
+// exactly as before, taking each parsed file from
+// `CompileContext::prefetched` and registering its types on the main thread,
+// so types, messages, warnings, and the first error reported are the same as
+// when loading sequentially. Imports of the imported files are prefetched in
+// turn when their items are processed.
+
+/// The output of `parse_idl_named`.
+type ParsedIdl = (IdlFile, Vec<DeclItem>, Vec<Warning>);
+
+/// An imported IDL file, read and parsed.
+struct LoadedIdl {
+    source: &'static str,
+    parsed: miette::Result<ParsedIdl>,
+}
+
+/// An imported file, read and parsed ahead of time.
+enum LoadedImport {
+    Idl(Box<LoadedIdl>),
+    /// A `.avpr` or `.avsc` file, whose types are yet to be registered.
+    Json(Value),
+}
+
+/// Read and parse the file at a resolved import path as an import of the
+/// given kind.
+fn load_import(
+    import_ctx: &ImportContext,
+    options: &ReaderOptions,
+    path: &Path,
+    kind: ImportKind,
+) -> miette::Result<LoadedImport> {
+    let what = match kind {
+        ImportKind::Idl => {
+            return load_idl_import(import_ctx, options, path)
+                .map(|loaded| LoadedImport::Idl(Box::new(loaded)));
+        }
+        ImportKind::Protocol => "protocol",
+        ImportKind::Schema => "schema",
+    };
+    let content = import_ctx.read_source(path, what)?;
+    parse_import_json(path, &content).map(LoadedImport::Json)
+}
+
+/// Read and parse the IDL file at a resolved import path. Fails only if the
+/// file cannot be read; a parse error is kept in `LoadedIdl::parsed`.
+fn load_idl_import(
+    import_ctx: &ImportContext,
+    options: &ReaderOptions,
+    path: &Path,
+) -> miette::Result<LoadedIdl> {
+    let source = import_ctx.read_source(path, "IDL").map(keep_source)?;
+    let source = if options.normalize_line_endings {
+        normalize_line_endings(source)
+    } else {
+        source
+    };
+    let name = keep_source(path.display().to_string());
+    Ok(LoadedIdl {
+        source,
+        parsed: parse_idl_named(source, name, options),
+    })
+}
+
+/// Load the not-yet-imported files that `decl_items` import, in parallel,
+/// into `ctx.prefetched`. Imports that fail to resolve are left to
+/// `resolve_single_import` to report.
+fn prefetch_imports(decl_items: &[DeclItem], ctx: &mut CompileContext, current_dir: &Path) {
+    let mut pending: Vec<(PathBuf, ImportKind)> = Vec::new();
+    for item in decl_items {
+        if let DeclItem::Import(import) = item
+            && let Ok(path) = ctx.import_ctx.resolve_import(&import.path, current_dir)
+            && !ctx.import_ctx.is_imported(&path)
+            && !ctx.prefetched.contains_key(&(path.clone(), import.kind))
+            && !pending.iter().any(|(queued, _)| *queued == path)
+        {
+            pending.push((path, import.kind));
+        }
+    }
+    // A single import gains nothing from a thread.
+    if pending.len() < 2 {
+        return;
+    }
+
+    let workers = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(pending.len());
+    let next = AtomicUsize::new(0);
+    let (import_ctx, options) = (&ctx.import_ctx, &ctx.reader_options);
+    let loaded: Vec<(usize, miette::Result<LoadedImport>)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut loaded = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some((path, kind)) = pending.get(i) else {
+                            return loaded;
+                        };
+                        loaded.push((i, load_import(import_ctx, options, path, *kind)));
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    });
+    for (i, result) in loaded {
+        ctx.prefetched.insert(pending[i].clone(), result);
+    }
+}
+
+/// Wrap an import error with the IDL source span of the import statement.
+///
+/// When the import statement's byte range (`span`) is available, the returned
 /// error places the `ParseDiagnostic` (which carries `source_code()` and
 /// `labels()`) as the **root** diagnostic, and attaches the downstream error
 /// as context. This ordering is important because miette's
@@ -855,11 +3095,16 @@ fn wrap_import_error(
 ) -> miette::Report {
     if let Some(span) = span {
         let diag = ParseDiagnostic {
+            kind: ErrorKind::of(&error).unwrap_or(ErrorKind::InvalidImport),
             span,
             message: format!("import {} {}", kind, resolved_path.display()),
             label: None,
-            help: None,
+            // Only the root diagnostic's help is rendered, so carry the
+            // downstream error's over (e.g., naming the right import kind).
+            help: error.help().map(|help| help.to_string()),
             related: Vec::new(),
+            name: None,
+            replacement: None,
         };
         // Place ParseDiagnostic as root so its source span is rendered,
         // and attach the downstream error (e.g., JSON parse failure) as
@@ -903,6 +3148,44 @@ fn keyword_misuse_hint(simple: &str) -> Option<String> {
     }
 }
 
+/// The help for an unresolved type name: the `import` statements that would
+/// define it, or failing that a "did you mean?" suggestion.
+fn undefined_name_help(
+    unresolved: &str,
+    registry: &SchemaRegistry,
+    import_ctx: &ImportContext,
+) -> Option<String> {
+    suggest_import(unresolved, import_ctx).or_else(|| suggest_similar_name(unresolved, registry))
+}
+
+/// Build a help string naming the `import` statements that read a file under
+/// the import directories defining `unresolved`. When only a type with the
+/// same simple name in another namespace exists, the help also gives the
+/// name to refer to it by.
+fn suggest_import(unresolved: &str, import_ctx: &ImportContext) -> Option<String> {
+    let suggestions = import_ctx.suggest_imports(unresolved);
+    if suggestions.is_empty() {
+        return None;
+    }
+    let fixes: Vec<String> = suggestions
+        .iter()
+        .map(|suggestion| {
+            if suggestion.full_name == unresolved {
+                format!("`{}`", suggestion.statement)
+            } else {
+                format!(
+                    "`{}` and refer to the type as `{}`",
+                    suggestion.statement, suggestion.full_name
+                )
+            }
+        })
+        .collect();
+    Some(format!(
+        "a file in the import directories defines this type; add {}",
+        fixes.join(", or ")
+    ))
+}
+
 /// Build a "did you mean?" help string for an unresolved type name.
 ///
 /// Checks the unresolved name against:
@@ -993,11 +3276,14 @@ fn suggest_similar_name(unresolved: &str, registry: &SchemaRegistry) -> Option<S
 /// statement's span and a help message naming the imported file, so the
 /// user can identify which import brought in the undefined type.
 ///
-/// When an unresolved name is similar to a primitive or registered type,
-/// the error includes a "did you mean?" suggestion.
+/// When a file under the import directories defines an unresolved name, the
+/// error suggests the `import` statement that reads it; otherwise, when the
+/// name is similar to a primitive or registered type, it includes a "did you
+/// mean?" suggestion.
 fn validate_all_references(
     idl_file: &IdlFile,
     registry: &SchemaRegistry,
+    import_ctx: &ImportContext,
     source: &'static str,
     source_name: &'static str,
     json_import_spans: &[(String, Option<SpanWithSource>)],
@@ -1088,11 +3374,14 @@ fn validate_all_references(
 
         if let Some(span) = first_import_span {
             return Err(ParseDiagnostic {
+                kind: ErrorKind::UndefinedName,
                 span,
                 message,
                 label: Some("this import contains undefined type references".to_string()),
                 help,
                 related: Vec::new(),
+                name: None,
+                replacement: None,
             }
             .into());
         }
@@ -1100,9 +3389,12 @@ fn validate_all_references(
         // No import span available either (e.g., import from string input
         // without span tracking). Fall back to plain message with help.
         if let Some(help) = help {
-            miette::bail!("{message}\n  help: {help}");
+            miette::bail!(
+                code = ErrorKind::UndefinedName.code(),
+                "{message}\n  help: {help}"
+            );
         }
-        miette::bail!("{message}");
+        miette::bail!(code = ErrorKind::UndefinedName.code(), "{message}");
     }
 
     // The first spanned reference becomes the primary diagnostic; the rest
@@ -1115,13 +3407,16 @@ fn validate_all_references(
     let mut related: Vec<ParseDiagnostic> = span_iter
         .map(|(name, span)| {
             let span = span.expect("partitioned into Some");
-            let help = suggest_similar_name(&name, registry);
+            let help = undefined_name_help(&name, registry, import_ctx);
             ParseDiagnostic {
+                kind: ErrorKind::UndefinedName,
                 span,
                 message: format!("Undefined name: {name}"),
                 label: None,
                 help,
                 related: Vec::new(),
+                name: Some(name),
+                replacement: None,
             }
         })
         .collect();
@@ -1153,21 +3448,27 @@ fn validate_all_references(
         };
 
         related.push(ParseDiagnostic {
+            kind: ErrorKind::UndefinedName,
             span,
             message: format!("Undefined name: {name}"),
             label,
             help,
             related: Vec::new(),
+            name: Some(name.clone()),
+            replacement: None,
         });
     }
 
-    let first_help = suggest_similar_name(&first_name, registry);
+    let first_help = undefined_name_help(&first_name, registry, import_ctx);
     Err(ParseDiagnostic {
+        kind: ErrorKind::UndefinedName,
         span: first_span,
         message: format!("Undefined name: {first_name}"),
         label: None,
         help: first_help,
         related,
+        name: Some(first_name),
+        replacement: None,
     }
     .into())
 }
@@ -1179,7 +3480,7 @@ fn validate_all_references(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::model::schema::AvroSchema;
+    use crate::model::schema::{AvroSchema, LogicalType, Properties};
     use pretty_assertions::assert_eq;
 
     #[test]
@@ -1233,6 +3534,23 @@ mod tests {
         insta::assert_snapshot!(crate::error::render_diagnostic(&err));
     }
 
+    #[test]
+    fn convert_string_keeps_each_source_once() {
+        let kept: Vec<_> = (0..2)
+            .map(|_| {
+                let err = Idl::new()
+                    .convert_string(String::from("protocol P { record R { Missing m; } }"))
+                    .expect_err("undefined type");
+                let diagnostic = err
+                    .chain()
+                    .find_map(|e| e.downcast_ref::<ParseDiagnostic>())
+                    .expect("a spanned diagnostic");
+                diagnostic.span.content.as_ptr()
+            })
+            .collect();
+        assert_eq!(kept[0], kept[1]);
+    }
+
     #[test]
     fn convert_str_named_custom_source_name() {
         let result = Idl::new().convert_str_named(r#"protocol { }"#, "my-test.avdl");
@@ -1241,6 +3559,188 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn convert_to_writer_streams_pretty_json() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        let path = dir.path().join("p.avdl");
+        std::fs::write(&path, "protocol P { record R { int x = 1; } }").expect("write .avdl");
+
+        let mut written = Vec::new();
+        let output = Idl::new()
+            .convert_to_writer(&path, &mut written)
+            .expect("should compile and write");
+        assert_eq!(
+            String::from_utf8(written).expect("UTF-8 JSON"),
+            serde_json::to_string_pretty(&output.json).expect("serialize")
+        );
+    }
+
+    #[test]
+    fn protocol_messages_are_written_in_declaration_order() {
+        let output = Idl::new()
+            .convert_str(
+                r#"
+                protocol P {
+                    void zebra();
+                    int apple(int x);
+                    void mango() oneway;
+                }
+                "#,
+            )
+            .expect("should compile");
+        let mut written = Vec::new();
+        output.write_json(&mut written).expect("write JSON");
+        let json = String::from_utf8(written).expect("UTF-8 JSON");
+        insta::assert_snapshot!(json);
+    }
+
+    #[test]
+    fn sort_messages_writes_messages_by_name() {
+        let output = Idl::new()
+            .sort_messages(true)
+            .convert_str("protocol P { void zebra(); void apple(); void mango(); }")
+            .expect("should compile");
+        let mut written = Vec::new();
+        output.write_json(&mut written).expect("write JSON");
+        assert_eq!(
+            String::from_utf8(written).expect("UTF-8 JSON"),
+            serde_json::to_string_pretty(&output.json).expect("serialize")
+        );
+    }
+
+    #[test]
+    fn unguarded_recursive_records_are_reported_with_the_cycle() {
+        let output = Idl::new()
+            .convert_str(
+                r#"
+                @namespace("ex") protocol P {
+                    record Loop { string label; Loop next; }
+                    record Ping { Pong pong; }
+                    record Pong { union { Ping, Loop } back; }
+                    record Holder { Ping ping; }
+                    record List { int head; List? tail; }
+                    record Tree { array<Tree> children; map<Tree> named; }
+                }
+                "#,
+            )
+            .expect("recursive records are valid Avro");
+        let warnings: Vec<_> = output.warnings.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            warnings,
+            vec![
+                "record `ex.Loop` can never hold a value, because it always contains \
+                 itself: ex.Loop -> ex.Loop",
+                "record `ex.Ping` can never hold a value, because it always contains \
+                 itself: ex.Ping -> ex.Pong -> ex.Ping",
+            ]
+        );
+    }
+
+    #[test]
+    fn warn_unused_types_reports_unreferenced_declarations() {
+        let source = r#"
+            @namespace("ex") protocol P {
+                record Used { Used? next; }
+                record Nested { Used used; }
+                record Lonely { Lonely? next; }
+                enum Stale { A }
+                error Oops { string why; }
+                Nested get() throws Oops;
+            }
+        "#;
+        let warnings = |enabled: bool| -> Vec<String> {
+            Idl::new()
+                .warn_unused_types(enabled)
+                .convert_str(source)
+                .expect("should compile")
+                .warnings
+                .iter()
+                .map(ToString::to_string)
+                .collect()
+        };
+        assert_eq!(warnings(false), Vec::<String>::new());
+        assert_eq!(
+            warnings(true),
+            vec![
+                "type `ex.Lonely` is declared but is never referenced",
+                "type `ex.Stale` is declared but is never referenced",
+            ]
+        );
+
+        let schema_file = Idl::new()
+            .warn_unused_types(true)
+            .convert_str("schema array<Root>; record Root { int x; } record Spare { int y; }")
+            .expect("should compile");
+        let warnings: Vec<String> = schema_file
+            .warnings
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            warnings,
+            vec!["type `Spare` is declared but is never referenced"]
+        );
+    }
+
+    #[test]
+    fn deny_warnings_fails_and_keeps_the_warnings() {
+        let source = "protocol P { record R { int x; /** orphan */ } }";
+        let mut idl = Idl::new();
+        let output = idl.convert_str(source).expect("warnings are allowed");
+        assert_eq!(output.warnings.len(), 1);
+
+        let err = idl
+            .deny_warnings(true)
+            .convert_str(source)
+            .expect_err("warnings are denied");
+        assert_eq!(
+            err.to_string(),
+            "warnings are denied, and compilation produced 1 warning"
+        );
+        assert_eq!(idl.drain_warnings().len(), 1);
+    }
+
+    #[test]
+    fn allow_warning_drops_only_that_kind() {
+        let source = r#"protocol P {
+            record R { int x; /** orphan */ }
+            record S { @ignored("yes") union { null, int } y; }
+        }"#;
+        let kinds = |idl: &mut Idl| -> Vec<WarningKind> {
+            let output = idl.convert_str(source).expect("should compile");
+            output
+                .warnings
+                .iter()
+                .filter_map(|w| w.downcast_ref::<Warning>().map(|w| w.kind))
+                .collect()
+        };
+        assert_eq!(
+            kinds(&mut Idl::new()),
+            vec![WarningKind::OutOfPlaceDoc, WarningKind::UnionAnnotation]
+        );
+        assert_eq!(
+            kinds(Idl::new().allow_warning(WarningKind::OutOfPlaceDoc)),
+            vec![WarningKind::UnionAnnotation]
+        );
+    }
+
+    #[test]
+    fn import_base_anchors_imports_of_string_sources() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        std::fs::write(
+            dir.path().join("common.avdl"),
+            "protocol Common { record Id { string value; } }",
+        )
+        .expect("write common.avdl");
+
+        let output = Idl2Schemata::new()
+            .import_base(dir.path())
+            .extract_str(r#"protocol P { import idl "common.avdl"; record R { Id id; } }"#)
+            .expect("import should resolve against the import base");
+        let names: Vec<&str> = output.schemas.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["Id", "R"]);
+    }
+
     #[test]
     fn extract_str_simple_protocol() {
         let output = Idl2Schemata::new()
@@ -1262,6 +3762,20 @@ mod tests {
         assert_eq!(output.schemas[1].schema["type"], "enum");
     }
 
+    #[test]
+    fn extract_str_reads_in_memory_imports() {
+        let output = Idl2Schemata::new()
+            .import_source(
+                "common.avdl",
+                "protocol Common { record Id { string value; } }",
+            )
+            .extract_str(r#"protocol P { import idl "common.avdl"; record R { Id id; } }"#)
+            .expect("in-memory import should resolve");
+
+        let names: Vec<&str> = output.schemas.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["Id", "R"]);
+    }
+
     #[test]
     fn extract_str_undefined_type_error() {
         let result = Idl2Schemata::new().extract_str(
@@ -1581,6 +4095,46 @@ mod tests {
         insta::assert_snapshot!(stable);
     }
 
+    #[test]
+    #[cfg_attr(windows, ignore)]
+    fn wrong_import_kind_names_the_right_one() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        std::fs::write(
+            dir.path().join("Foo.avsc"),
+            r#"{"type":"record","name":"Foo","fields":[]}"#,
+        )
+        .expect("write .avsc");
+        std::fs::write(
+            dir.path().join("Service.avpr"),
+            r#"{"protocol":"Service","types":[],"messages":{}}"#,
+        )
+        .expect("write .avpr");
+
+        for (import, expected) in [
+            (
+                r#"import schema "Service.avpr";"#,
+                "this file is an Avro protocol; use `import protocol`",
+            ),
+            (
+                r#"import protocol "Foo.avsc";"#,
+                "this file is an Avro schema; use `import schema`",
+            ),
+            (
+                r#"import idl "Service.avpr";"#,
+                "this file is an Avro protocol; use `import protocol`",
+            ),
+        ] {
+            let avdl_path = dir.path().join("main.avdl");
+            std::fs::write(&avdl_path, format!("protocol P {{ {import} }}")).expect("write .avdl");
+            let err = Idl::new()
+                .convert(&avdl_path)
+                .expect_err("wrong import kind");
+            let rendered = crate::error::render_diagnostic(&err);
+            assert!(rendered.contains(expected), "{import}:\n{rendered}");
+            assert!(rendered.contains(import), "{import}:\n{rendered}");
+        }
+    }
+
     // =========================================================================
     // Bare named type declarations (no `schema` keyword, no `protocol`)
     // =========================================================================
@@ -1644,6 +4198,26 @@ mod tests {
         insta::assert_snapshot!(crate::error::render_diagnostic(&err));
     }
 
+    #[test]
+    fn field_default_invalid_references_across_records() {
+        // Bad defaults in different records are reported in one diagnostic
+        // rather than stopping at the first record.
+        let err = Idl::new()
+            .convert_str(
+                r#"
+                protocol P {
+                    enum Color { RED, GREEN, BLUE }
+                    record A { Color first = 1; }
+                    record B { Color second = 2; }
+                }
+                "#,
+            )
+            .unwrap_err();
+        let rendered = crate::error::render_diagnostic(&err);
+        assert!(rendered.contains("field `first` in `A`"), "{rendered}");
+        assert!(rendered.contains("field `second` in `B`"), "{rendered}");
+    }
+
     #[test]
     fn field_default_valid_for_enum_reference() {
         // A valid string default for an enum reference should be accepted.
@@ -1680,6 +4254,74 @@ mod tests {
         insta::assert_snapshot!(crate::error::render_diagnostic(&err));
     }
 
+    #[test]
+    fn field_default_invalid_for_forward_enum_reference() {
+        // The enum is declared after the record, so the default can only be
+        // checked once every type is registered.
+        let err = Idl::new()
+            .convert_str(
+                r#"
+                protocol P {
+                    record R { Color favorite = 42; }
+                    enum Color { RED, GREEN, BLUE }
+                }
+                "#,
+            )
+            .expect_err("default is not a string, as the later enum needs");
+        assert!(
+            err.to_string()
+                .contains("Invalid default for field `favorite` in `R`"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn field_default_valid_for_forward_references() {
+        let output = Idl::new()
+            .convert_str(
+                r#"
+                protocol P {
+                    record R {
+                        Color favorite = "RED";
+                        Inner nested = {"name": "x"};
+                        union { null, Inner } maybe = null;
+                    }
+                    enum Color { RED, GREEN, BLUE }
+                    record Inner { string name; }
+                }
+                "#,
+            )
+            .expect("valid forward-reference defaults should be accepted");
+        assert_eq!(output.json["types"][0]["fields"][0]["default"], "RED");
+    }
+
+    #[test]
+    fn field_default_invalid_for_type_declared_after_import() {
+        // The imported record references a type that the importing file
+        // declares after the import statement.
+        let dir = tempfile::tempdir().expect("create temp dir");
+        std::fs::write(
+            dir.path().join("holder.avdl"),
+            "protocol Holder { record Holder { Color color = 7; } }\n",
+        )
+        .expect("write imported .avdl");
+        let main_path = dir.path().join("main.avdl");
+        std::fs::write(
+            &main_path,
+            "protocol Main {\n  import idl \"holder.avdl\";\n  enum Color { RED }\n}\n",
+        )
+        .expect("write main .avdl");
+
+        let err = Idl::new()
+            .convert(&main_path)
+            .expect_err("integer default for an enum field");
+        assert!(
+            err.to_string()
+                .contains("Invalid default for field `color` in `Holder`"),
+            "{err}"
+        );
+    }
+
     #[test]
     fn idl2schemata_accepts_bare_named_types() {
         let output = Idl2Schemata::new()
@@ -1700,6 +4342,25 @@ mod tests {
     // "Did you mean?" suggestions for undefined type names
     // =========================================================================
 
+    #[test]
+    fn undefined_name_suggests_import_from_import_dir() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        std::fs::write(
+            dir.path().join("common.avdl"),
+            r#"@namespace("com.common") protocol C { record Address { string street; } }"#,
+        )
+        .expect("write .avdl");
+        let err = Idl::new()
+            .import_dir(dir.path())
+            .convert_str(r#"@namespace("com.common") protocol P { record R { Address home; } }"#)
+            .unwrap_err();
+        let rendered = crate::error::render_diagnostic(&err);
+        assert!(
+            rendered.contains(r#"add `import idl "common.avdl";`"#),
+            "{rendered}"
+        );
+    }
+
     #[test]
     fn suggest_primitive_typo_stiring() {
         let reg = SchemaRegistry::new();
@@ -1744,7 +4405,7 @@ mod tests {
             fields: vec![],
             is_error: false,
             aliases: vec![],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         })
         .expect("registration succeeds");
 
@@ -1763,7 +4424,7 @@ mod tests {
             fields: vec![],
             is_error: false,
             aliases: vec![],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         })
         .expect("registration succeeds");
 
@@ -2419,4 +5080,1089 @@ mod tests {
             .replace(&dir.path().display().to_string(), "<tmpdir>");
         insta::assert_snapshot!(stable);
     }
+
+    // ------------------------------------------------------------------
+    // Message parameter defaults
+    // ------------------------------------------------------------------
+
+    const INVALID_PARAM_DEFAULT: &str = r#"
+        protocol P {
+            void ping(int count = "many");
+        }
+    "#;
+
+    #[test]
+    fn param_defaults_validate_rejects_invalid_default() {
+        Idl::new()
+            .convert_str(INVALID_PARAM_DEFAULT)
+            .expect_err("invalid parameter default should be rejected by default");
+    }
+
+    #[test]
+    fn param_defaults_unchecked_emits_invalid_default() {
+        let output = Idl::new()
+            .param_defaults(ParamDefaults::Unchecked)
+            .convert_str(INVALID_PARAM_DEFAULT)
+            .expect("unchecked mode should skip parameter default validation");
+        let param = &output.json["messages"]["ping"]["request"][0];
+        assert_eq!(param["default"], "many");
+    }
+
+    #[test]
+    fn param_defaults_omit_drops_default_and_union_reordering() {
+        let output = Idl::new()
+            .param_defaults(ParamDefaults::Omit)
+            .convert_str(
+                r#"
+                protocol P {
+                    void greet(string? name = "world");
+                }
+                "#,
+            )
+            .expect("omit mode should compile");
+        let param = &output.json["messages"]["greet"]["request"][0];
+        assert_eq!(param.get("default"), None);
+        assert_eq!(param["type"], serde_json::json!(["null", "string"]));
+    }
+
+    #[test]
+    fn param_defaults_do_not_affect_record_fields() {
+        Idl::new()
+            .param_defaults(ParamDefaults::Omit)
+            .convert_str(r#"protocol P { record R { int count = "many"; } }"#)
+            .expect_err("record field defaults are always validated");
+    }
+
+    // ------------------------------------------------------------------
+    // Unknown declarations
+    // ------------------------------------------------------------------
+
+    const FUTURE_DECLARATIONS: &str = r#"
+        protocol P {
+            record R { int x; }
+            @since("2.0") service Billing { rpc charge(R) returns R; }
+            R get(int id);
+            option { retries: 3 };
+        }
+    "#;
+
+    #[test]
+    fn unknown_declarations_fail_by_default() {
+        Idl::new()
+            .convert_str(FUTURE_DECLARATIONS)
+            .expect_err("unknown declarations are syntax errors by default");
+    }
+
+    #[test]
+    fn skip_unknown_declarations_warns_and_keeps_the_rest() {
+        let output = Idl::new()
+            .skip_unknown_declarations(true)
+            .convert_str(FUTURE_DECLARATIONS)
+            .expect("unknown declarations should be skipped");
+        assert_eq!(output.json["types"][0]["name"], "R");
+        assert_eq!(
+            output.json["messages"]
+                .as_object()
+                .map(|m| m.keys().map(String::as_str).collect::<Vec<_>>()),
+            Some(vec!["get"])
+        );
+        let warnings: Vec<_> = output.warnings.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            warnings,
+            vec![
+                "skipping unknown declaration `service`; it may need a newer avdl",
+                "skipping unknown declaration `option`; it may need a newer avdl",
+            ]
+        );
+    }
+
+    #[test]
+    fn skip_unknown_declarations_still_rejects_malformed_messages() {
+        // An unknown word followed by `;` rather than a braced block could be
+        // a typo in a message declaration, so it is not skipped.
+        Idl::new()
+            .skip_unknown_declarations(true)
+            .convert_str("protocol P { frobnicate Billing; }")
+            .expect_err("not a braced declaration");
+    }
+
+    #[test]
+    fn byte_order_marks_are_ignored() {
+        let output = Idl::new()
+            .import_source(
+                "common.avdl",
+                "\u{feff}protocol Common { record Id { string value; } }",
+            )
+            .import_source(
+                "money.avsc",
+                "\u{feff}{\"type\": \"record\", \"name\": \"Money\", \"fields\": []}",
+            )
+            .convert_str(
+                "\u{feff}protocol P {\n\
+                 import idl \"common.avdl\";\n\
+                 import schema \"money.avsc\";\n\
+                 record R { Id id; Money price; }\n\
+                 }",
+            )
+            .expect("byte order marks should be skipped");
+        assert_eq!(output.json["types"][2]["name"], "R");
+        assert!(output.warnings.is_empty(), "{:?}", output.warnings);
+    }
+
+    #[test]
+    fn normalize_line_endings_strips_carriage_returns_from_doc_comments() {
+        let source = "protocol P {\r\n  import idl \"common.avdl\";\r\n\
+                      /**\r\n   * Line one.\r\n   * Line two.\r\n   */\r\n  record R { Id id; }\r\n}";
+        let common = "protocol C {\r\n  /**\r\n   * An\r\n   * id.\r\n   */\r\n  record Id {}\r\n}";
+
+        let output = Idl::new()
+            .import_source("common.avdl", common)
+            .convert_str(source)
+            .expect("CRLF sources compile");
+        assert_eq!(output.json["types"][1]["doc"], "Line one.\r\nLine two.");
+
+        let output = Idl::new()
+            .normalize_line_endings(true)
+            .import_source("common.avdl", common)
+            .convert_str(source)
+            .expect("CRLF sources compile");
+        assert_eq!(output.json["types"][0]["doc"], "An\nid.");
+        assert_eq!(output.json["types"][1]["doc"], "Line one.\nLine two.");
+    }
+
+    #[test]
+    fn utf16_files_are_transcoded_and_latin1_files_rejected() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        let main = dir.path().join("main.avdl");
+        let utf16: Vec<u8> = [0xFF, 0xFE]
+            .into_iter()
+            .chain(
+                "protocol P { import idl \"latin1.avdl\"; record R { string s; } }"
+                    .encode_utf16()
+                    .flat_map(u16::to_le_bytes),
+            )
+            .collect();
+        std::fs::write(&main, utf16).expect("write main.avdl");
+        std::fs::write(
+            dir.path().join("latin1.avdl"),
+            b"protocol L {\n  /** Caf\xE9. */ record C {}\n}",
+        )
+        .expect("write latin1.avdl");
+
+        let err = Idl::new()
+            .convert(&main)
+            .expect_err("the Latin-1 import should be rejected");
+        let message = format!("{err:?}");
+        assert!(message.contains("AVDL0023"), "{message}");
+        assert!(
+            err.to_string()
+                .contains("byte 0xE9 at line 2, column 10 is not valid UTF-8"),
+            "{message}"
+        );
+
+        std::fs::write(dir.path().join("latin1.avdl"), "protocol L {}").expect("rewrite");
+        let output = Idl::new()
+            .convert(&main)
+            .expect("UTF-16 input should be transcoded");
+        assert_eq!(output.json["protocol"], "P");
+    }
+
+    #[test]
+    fn validate_defaults_off_warns_instead() {
+        let source = r#"protocol P {
+            record Inner { int x; }
+            record R {
+                int count = "many";
+                Inner inner = 3;
+                Later later = {};
+            }
+            record Later { string name; }
+        }"#;
+        Idl::new()
+            .convert_str(source)
+            .expect_err("invalid defaults are rejected by default");
+
+        let output = Idl::new()
+            .validate_defaults(false)
+            .convert_str(source)
+            .expect("invalid defaults are accepted");
+        let warnings: Vec<String> = output.warnings.iter().map(ToString::to_string).collect();
+        assert_eq!(warnings.len(), 3, "{warnings:#?}");
+        assert!(
+            warnings
+                .iter()
+                .all(|warning| warning.starts_with("Invalid default for field `")),
+            "{warnings:#?}"
+        );
+        let fields = &output.json["types"][1]["fields"];
+        assert_eq!(fields[0]["default"], "many");
+        assert_eq!(fields[1]["default"], 3);
+    }
+
+    #[test]
+    fn promote_logical_types_can_be_turned_off() {
+        let source = r#"protocol P { record R {
+            @logicalType("date") int since;
+            date until;
+        } }"#;
+        let field_schemas = |output: &IdlOutput| -> Vec<AvroSchema> {
+            let Some(AvroSchema::Record { fields, .. }) = output.types().first().copied() else {
+                panic!("expected a record");
+            };
+            fields.iter().map(|field| field.schema.clone()).collect()
+        };
+
+        let promoted = Idl::new().convert_str(source).expect("should compile");
+        let opaque = Idl::new()
+            .promote_logical_types(false)
+            .convert_str(source)
+            .expect("should compile");
+        assert_eq!(opaque.json, promoted.json);
+        assert!(matches!(
+            field_schemas(&promoted)[..],
+            [AvroSchema::Logical { .. }, AvroSchema::Logical { .. }]
+        ));
+        assert!(matches!(
+            field_schemas(&opaque)[..],
+            [
+                AvroSchema::AnnotatedPrimitive { .. },
+                AvroSchema::Logical { .. }
+            ]
+        ));
+    }
+
+    #[test]
+    fn iso8601_defaults_become_avro_numbers() {
+        let source = r#"protocol P { record R {
+            date since = "2024-01-31";
+            timestamp_ms? at = "2024-01-31T14:45:00+01:00";
+        } }"#;
+        Idl::new()
+            .convert_str(source)
+            .expect_err("string defaults are invalid for dates by default");
+        let output = Idl::new()
+            .iso8601_defaults(true)
+            .convert_str(source)
+            .expect("ISO-8601 defaults should convert");
+        let fields = &output.json["types"][0]["fields"];
+        assert_eq!(fields[0]["default"], 19_753);
+        assert_eq!(fields[1]["default"], 1_706_708_700_000_i64);
+        assert_eq!(fields[1]["type"][1], "null");
+    }
+
+    #[test]
+    fn flatten_nested_unions_warns_and_merges_the_branches() {
+        let source = "protocol P { record R { union { null, union { int, string } } v; } }";
+        Idl::new()
+            .convert_str(source)
+            .expect_err("nested unions are rejected by default");
+        let output = Idl::new()
+            .flatten_nested_unions(true)
+            .convert_str(source)
+            .expect("nested union should be flattened");
+        assert_eq!(
+            output.json["types"][0]["fields"][0]["type"],
+            serde_json::json!(["null", "int", "string"])
+        );
+        let warnings: Vec<_> = output.warnings.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            warnings,
+            vec!["flattening a union nested in another union into it"]
+        );
+
+        // Flattening can still produce a union with duplicate branches.
+        Idl::new()
+            .flatten_nested_unions(true)
+            .convert_str("protocol P { record R { union { int, union { null, int } } v; } }")
+            .expect_err("duplicate int after flattening");
+    }
+
+    // ------------------------------------------------------------------
+    // annotation_handler
+    // ------------------------------------------------------------------
+
+    const PII_FIELDS: &str = r#"
+        protocol P {
+            record User {
+                string @pii("email") email;
+                string @pii(true) @owner("growth") phone;
+                string @pii(false) name;
+            }
+        }
+    "#;
+
+    #[test]
+    fn annotation_handler_rewrites_removes_and_warns() {
+        let output = Idl::new()
+            .annotation_handler("pii", |annotation| {
+                match annotation.value() {
+                    Some(Value::Bool(false)) => annotation.remove(),
+                    Some(Value::String(kind)) => {
+                        let kind = kind.to_uppercase();
+                        annotation.warn(format!("prefer @pii(true) over a kind, got {kind}"));
+                        annotation.set_value(Value::Bool(true));
+                    }
+                    _ => {}
+                }
+                Ok(())
+            })
+            .convert_str(PII_FIELDS)
+            .expect("handler only warns");
+        let fields = &output.json["types"][0]["fields"];
+        assert_eq!(fields[0]["pii"], true);
+        assert_eq!(fields[1]["pii"], true);
+        assert_eq!(fields[1]["owner"], "growth");
+        assert!(fields[2].get("pii").is_none());
+        let warnings: Vec<_> = output.warnings.iter().map(ToString::to_string).collect();
+        assert_eq!(warnings, vec!["prefer @pii(true) over a kind, got EMAIL"]);
+    }
+
+    #[test]
+    fn annotation_handler_error_points_at_the_annotation() {
+        let err = Idl::new()
+            .annotation_handler("pii", |annotation| match annotation.value() {
+                Some(Value::Bool(_)) => Ok(()),
+                _ => Err("expected true or false".to_string()),
+            })
+            .convert_str(PII_FIELDS)
+            .expect_err("@pii(\"email\") is rejected");
+        let diagnostic = err
+            .chain()
+            .find_map(|e| e.downcast_ref::<ParseDiagnostic>())
+            .expect("a spanned diagnostic");
+        assert_eq!(
+            diagnostic.message,
+            "invalid @pii annotation: expected true or false"
+        );
+        assert_eq!(
+            &PII_FIELDS[diagnostic.span.offset..diagnostic.span.offset + diagnostic.span.length],
+            r#"@pii("email")"#
+        );
+    }
+
+    // ------------------------------------------------------------------
+    // IdlOutput::names
+    // ------------------------------------------------------------------
+
+    #[test]
+    fn names_lists_named_types_with_kinds() {
+        let output = Idl::new()
+            .convert_str_named(
+                r#"
+                @namespace("org.example")
+                protocol P {
+                    enum Color { RED, GREEN }
+                    fixed Hash(16);
+                    record User { Color color; }
+                    error Oops { string message; }
+                }
+                "#,
+                "service.avdl",
+            )
+            .expect("should compile");
+
+        let names: Vec<_> = output
+            .names()
+            .iter()
+            .map(|n| (n.full_name.as_str(), n.kind, n.source.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("org.example.Color", NameKind::Enum, "service.avdl"),
+                ("org.example.Hash", NameKind::Fixed, "service.avdl"),
+                ("org.example.User", NameKind::Record, "service.avdl"),
+                ("org.example.Oops", NameKind::Error, "service.avdl"),
+            ]
+        );
+    }
+
+    #[test]
+    fn names_attributes_imported_types_to_their_file() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        std::fs::write(
+            dir.path().join("shared.avsc"),
+            r#"{"type": "enum", "name": "Kind", "namespace": "shared", "symbols": ["A"]}"#,
+        )
+        .expect("write .avsc");
+        let avdl_path = dir.path().join("main.avdl");
+        std::fs::write(
+            &avdl_path,
+            "protocol P {\n  import schema \"shared.avsc\";\n  record R { shared.Kind kind; }\n}\n",
+        )
+        .expect("write .avdl");
+
+        let output = Idl::new().convert(&avdl_path).expect("should compile");
+        let names = output.names();
+        assert_eq!(names.len(), 2);
+
+        assert_eq!(names[0].full_name, "shared.Kind");
+        assert!(
+            names[0].source.ends_with("shared.avsc"),
+            "imported type should be attributed to the .avsc, got {}",
+            names[0].source
+        );
+        assert_eq!(names[1].full_name, "R");
+        assert_eq!(names[1].source, avdl_path.display().to_string());
+    }
+
+    #[test]
+    fn typed_model_matches_the_source() {
+        let output = Idl::new()
+            .convert_str(
+                r#"@namespace("ex") protocol P {
+                    record Event { timestamp_ms at; }
+                    void send(Event event) oneway;
+                }"#,
+            )
+            .expect("should compile");
+        assert!(output.schema().is_none());
+        let protocol = output.protocol().expect("a protocol");
+        assert_eq!(protocol.name, "P");
+        let AvroSchema::Record { fields, .. } = &protocol.types[0] else {
+            panic!("expected a record, got {:?}", protocol.types[0]);
+        };
+        assert!(matches!(
+            fields[0].schema,
+            AvroSchema::Logical {
+                logical_type: LogicalType::TimestampMillis,
+                ..
+            }
+        ));
+        let send = &protocol.messages["send"];
+        assert!(send.one_way);
+        assert!(matches!(
+            &send.request[0].schema,
+            AvroSchema::Reference { name, .. } if name == "Event"
+        ));
+
+        let output = Idl::new()
+            .convert_str("namespace ex; schema array<Id>; fixed Id(4);")
+            .expect("should compile");
+        assert!(output.protocol().is_none());
+        assert!(matches!(output.schema(), Some(AvroSchema::Array { .. })));
+        assert_eq!(output.types()[0].full_name().as_deref(), Some("ex.Id"));
+    }
+
+    // ------------------------------------------------------------------
+    // Import graph
+    // ------------------------------------------------------------------
+
+    #[test]
+    fn imports_record_nested_and_repeated_edges() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        std::fs::write(
+            dir.path().join("shared.avsc"),
+            r#"{"type": "fixed", "name": "Id", "size": 16}"#,
+        )
+        .expect("write .avsc");
+        std::fs::write(
+            dir.path().join("common.avdl"),
+            "protocol Common {\n  import schema \"shared.avsc\";\n}\n",
+        )
+        .expect("write common.avdl");
+        let avdl_path = dir.path().join("main.avdl");
+        std::fs::write(
+            &avdl_path,
+            "protocol P {\n  import idl \"common.avdl\";\n  import schema \"shared.avsc\";\n}\n",
+        )
+        .expect("write main.avdl");
+
+        let output = Idl::new().convert(&avdl_path).expect("should compile");
+        let edges: Vec<_> = output
+            .imports()
+            .iter()
+            .map(|e| {
+                let importer = Path::new(&e.importer)
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .expect("importer has a file name")
+                    .to_string();
+                (importer, e.kind, e.path.as_str())
+            })
+            .collect();
+        assert_eq!(
+            edges,
+            vec![
+                ("main.avdl".to_string(), ImportKind::Idl, "common.avdl"),
+                ("common.avdl".to_string(), ImportKind::Schema, "shared.avsc"),
+                // Already imported through `common.avdl`, but still an edge.
+                ("main.avdl".to_string(), ImportKind::Schema, "shared.avsc"),
+            ]
+        );
+        assert_eq!(output.imports()[1].resolved, output.imports()[2].resolved);
+    }
+
+    #[test]
+    fn overlays_shadow_shared_files_and_report_their_root() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        let patches = dir.path().join("patches");
+        let shared = dir.path().join("shared");
+        std::fs::create_dir_all(&patches).expect("create patches");
+        std::fs::create_dir_all(&shared).expect("create shared");
+        std::fs::write(
+            shared.join("common.avdl"),
+            "protocol Common {\n  import idl \"money.avdl\";\n}\n",
+        )
+        .expect("write common.avdl");
+        std::fs::write(
+            shared.join("money.avdl"),
+            "protocol Money { record Money { int cents; } }",
+        )
+        .expect("write shared money.avdl");
+        std::fs::write(
+            patches.join("money.avdl"),
+            "protocol Money { record Money { long cents; } }",
+        )
+        .expect("write patched money.avdl");
+
+        let output = Idl::new()
+            .overlay_dir(&patches)
+            .overlay_dir(&shared)
+            .convert_str(r#"protocol P { import idl "common.avdl"; }"#)
+            .expect("should compile");
+        assert_eq!(output.json["types"][0]["fields"][0]["type"], "long");
+        let roots: Vec<_> = output
+            .imports()
+            .iter()
+            .map(|e| (e.path.as_str(), e.root.clone()))
+            .collect();
+        assert_eq!(
+            roots,
+            vec![
+                ("common.avdl", Some(shared.clone())),
+                ("money.avdl", Some(patches.clone())),
+            ]
+        );
+    }
+
+    #[test]
+    fn nesting_beyond_the_limit_is_an_error_not_a_stack_overflow() {
+        let nested = format!(
+            "protocol P {{ record R {{ {}int{} x; }} }}",
+            "array<".repeat(10_000),
+            ">".repeat(10_000)
+        );
+        let err = Idl::new()
+            .convert_string(nested)
+            .expect_err("brackets nest too deeply");
+        let diagnostic = err
+            .chain()
+            .find_map(|e| e.downcast_ref::<ParseDiagnostic>())
+            .expect("a spanned diagnostic");
+        assert_eq!(
+            diagnostic.message,
+            "nesting too deep: more than 128 levels of brackets"
+        );
+        assert_eq!(
+            diagnostic.span.offset,
+            "protocol P { record R { ".len() + 6 * 126 + 5
+        );
+
+        let chain: String = (0..140)
+            .map(|i| format!("record R{i} {{ R{} next; }}\n", i + 1))
+            .collect();
+        let chain = format!("protocol P {{\n{chain}record R140 {{}}\n}}");
+        let err = Idl::new()
+            .convert_string(chain.clone())
+            .expect_err("references nest too deeply");
+        let diagnostic = err
+            .chain()
+            .find_map(|e| e.downcast_ref::<ParseDiagnostic>())
+            .expect("a spanned diagnostic");
+        assert_eq!(
+            diagnostic.message,
+            "nesting too deep: type `R0` nests 141 levels deep through the types it \
+             references, more than 128"
+        );
+
+        let output = Idl::new()
+            .max_nesting_depth(256)
+            .convert_string(chain)
+            .expect("a higher limit accepts the chain");
+        assert_eq!(output.json["types"][0]["name"], "R0");
+    }
+
+    #[test]
+    fn parallel_imports_keep_declaration_order_and_first_error() {
+        let mut idl = Idl::new();
+        let mut source = String::from("protocol P {\n");
+        for i in 0..20 {
+            idl.import_source(
+                format!("t{i}.avdl"),
+                format!(
+                    "protocol T{i} {{ import idl \"n{i}.avdl\"; record T{i} {{ /** orphan */ }} }}"
+                ),
+            );
+            idl.import_source(
+                format!("n{i}.avdl"),
+                format!("protocol N{i} {{ record N{i} {{}} }}"),
+            );
+            source.push_str(&format!("  import idl \"t{i}.avdl\";\n"));
+        }
+        source.push('}');
+
+        let output = idl.convert_string(source.clone()).expect("should compile");
+        let names: Vec<_> = output.json["types"]
+            .as_array()
+            .expect("types array")
+            .iter()
+            .map(|t| t["name"].as_str().expect("type name"))
+            .collect();
+        let expected: Vec<_> = (0..20)
+            .flat_map(|i| [format!("N{i}"), format!("T{i}")])
+            .collect();
+        assert_eq!(names, expected);
+        let warned_files: Vec<_> = output.warnings.iter().map(|w| w.to_string()).collect();
+        let expected: Vec<_> = (0..20).map(|i| format!("t{i}.avdl")).collect();
+        assert_eq!(warned_files, expected);
+
+        idl.import_source("t3.avdl", "protocol Broken {")
+            .import_source("t7.avdl", "protocol AlsoBroken {");
+        let err = idl.convert_string(source).expect_err("broken imports");
+        let message = format!("{err:?}");
+        assert!(message.contains("t3.avdl"), "{message}");
+        assert!(!message.contains("t7.avdl"), "{message}");
+    }
+
+    #[test]
+    fn parallel_json_imports_match_sequential_output() {
+        let source = r#"
+            protocol M {
+                import schema "a.avsc";
+                import protocol "b.avpr";
+                import schema "c.avsc";
+                import protocol "d.avpr";
+                record Use { Color color; Point point; Hash hash; Item item; }
+            }
+        "#;
+        let output = Idl::new()
+            .import_source("a.avsc", r#"{"type": "enum", "name": "Color", "symbols": ["RED"]}"#)
+            .import_source(
+                "b.avpr",
+                r#"{"protocol": "B", "types": [
+                    {"type": "record", "name": "Point", "fields": [{"name": "x", "type": "int"}]}
+                ], "messages": {"ping": {"request": [], "response": "null"}}}"#,
+            )
+            .import_source("c.avsc", r#"{"type": "fixed", "name": "Hash", "size": 16}"#)
+            .import_source(
+                "d.avpr",
+                r#"{"protocol": "D", "types": [
+                    {"type": "record", "name": "Item", "fields": [{"name": "name", "type": "string"}]}
+                ], "messages": {"get": {"request": [{"name": "id", "type": "int"}], "response": "Item"}}}"#,
+            )
+            .convert_str(source)
+            .expect("should compile");
+        let mut written = Vec::new();
+        output.write_json(&mut written).expect("write JSON");
+        let json = String::from_utf8(written).expect("UTF-8 JSON");
+        // The snapshot is the output of resolving the imports one at a time.
+        insta::assert_snapshot!(json);
+    }
+
+    // ------------------------------------------------------------------
+    // Per-schema metadata
+    // ------------------------------------------------------------------
+
+    #[test]
+    fn schemata_carry_dependencies_provenance_and_fingerprint() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        std::fs::write(
+            dir.path().join("shared.avsc"),
+            r#"{"type": "enum", "name": "Kind", "namespace": "shared", "symbols": ["A"]}"#,
+        )
+        .expect("write .avsc");
+        let avdl_path = dir.path().join("main.avdl");
+        std::fs::write(
+            &avdl_path,
+            "@namespace(\"ex\") protocol P {\n  import schema \"shared.avsc\";\n  \
+             record R { shared.Kind kind; array<shared.Kind> more; union { null, R } next; }\n}\n",
+        )
+        .expect("write .avdl");
+
+        let output = Idl2Schemata::new()
+            .extract(&avdl_path)
+            .expect("should compile");
+        assert_eq!(output.schemas.len(), 2);
+
+        let kind = &output.schemas[0];
+        assert_eq!(kind.name, "Kind");
+        assert!(kind.imported);
+        assert!(kind.source.ends_with("shared.avsc"));
+        assert!(kind.dependencies.is_empty());
+
+        let record = &output.schemas[1];
+        assert_eq!(record.name, "R");
+        assert!(!record.imported);
+        assert_eq!(record.source, avdl_path.display().to_string());
+        // Deduplicated, and the self-reference is not a dependency.
+        assert_eq!(record.dependencies, vec!["shared.Kind".to_string()]);
+        assert_eq!(
+            record.fingerprint,
+            crate::model::fingerprint::fingerprint64(&record.schema)
+        );
+        assert_ne!(record.fingerprint, kind.fingerprint);
+        assert_eq!(record.suggested_path, PathBuf::from("R.avsc"));
+
+        let output = Idl2Schemata::new()
+            .namespace_dirs(true)
+            .extract(&avdl_path)
+            .expect("should compile");
+        let paths: Vec<_> = output.schemas.iter().map(|s| &s.suggested_path).collect();
+        assert_eq!(
+            paths,
+            [Path::new("shared/Kind.avsc"), Path::new("ex/R.avsc")]
+        );
+    }
+
+    // ------------------------------------------------------------------
+    // Include/exclude filtering
+    // ------------------------------------------------------------------
+
+    #[test]
+    fn include_and_exclude_filter_extracted_schemas() {
+        const SOURCE: &str = r#"
+            @namespace("com.example")
+            protocol P {
+                record User { string name; }
+                record Account { User owner; }
+                @namespace("com.other") enum Kind { A }
+            }
+        "#;
+        let names = |builder: &mut Idl2Schemata| -> Vec<String> {
+            builder
+                .extract_str(SOURCE)
+                .expect("should compile")
+                .schemas
+                .into_iter()
+                .map(|s| s.name)
+                .collect()
+        };
+
+        assert_eq!(
+            names(Idl2Schemata::new().include("com.example.*")),
+            vec!["User", "Account"]
+        );
+        assert_eq!(
+            names(
+                Idl2Schemata::new()
+                    .include("com.example.*")
+                    .exclude("com.example.User")
+            ),
+            vec!["Account"]
+        );
+        assert_eq!(
+            names(Idl2Schemata::new().exclude("*.User").exclude("com.other.*")),
+            vec!["Account"]
+        );
+        // Filtered-out dependencies are still inlined where referenced.
+        let output = Idl2Schemata::new()
+            .include("com.example.Account")
+            .extract_str(SOURCE)
+            .expect("should compile");
+        assert_eq!(
+            output.schemas[0].schema["fields"][0]["type"]["name"],
+            "User"
+        );
+    }
+
+    // ------------------------------------------------------------------
+    // Dependency order and references by name
+    // ------------------------------------------------------------------
+
+    const DEPENDENCY_CHAIN: &str = r#"
+        @namespace("ex")
+        protocol P {
+            record A { B b; C c; }
+            record B { C c; }
+            enum C { X }
+            record D { union { null, E } e; }
+            record E { union { null, D } d; }
+        }
+    "#;
+
+    fn schema_names(output: &SchemataOutput) -> Vec<&str> {
+        output.schemas.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    #[test]
+    fn dependency_order_emits_dependencies_first() {
+        let output = Idl2Schemata::new()
+            .dependency_order(true)
+            .extract_str(DEPENDENCY_CHAIN)
+            .expect("should compile");
+        // The D/E cycle keeps every schema exactly once.
+        assert_eq!(schema_names(&output), vec!["C", "B", "A", "E", "D"]);
+
+        let output = Idl2Schemata::new()
+            .extract_str(DEPENDENCY_CHAIN)
+            .expect("should compile");
+        assert_eq!(schema_names(&output), vec!["A", "B", "C", "D", "E"]);
+    }
+
+    #[test]
+    fn transitive_dependencies_follow_references() {
+        let output = Idl2Schemata::new()
+            .transitive_dependencies(true)
+            .extract_str(
+                r#"@namespace("ex") protocol P {
+                    record A { B b; }
+                    record B { array<C> cs; }
+                    enum C { X }
+                    record D { union { null, E } e; }
+                    record E { union { null, D } d; }
+                }"#,
+            )
+            .expect("should compile");
+        let dependencies: Vec<_> = output
+            .schemas
+            .iter()
+            .map(|s| (s.name.as_str(), s.dependencies.join(" ")))
+            .collect();
+        assert_eq!(
+            dependencies,
+            [
+                ("A", "ex.C ex.B".to_string()),
+                ("B", "ex.C".to_string()),
+                ("C", String::new()),
+                ("D", "ex.E".to_string()),
+                ("E", "ex.D".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn references_by_name_does_not_inline() {
+        let output = Idl2Schemata::new()
+            .references_by_name(true)
+            .extract_str(DEPENDENCY_CHAIN)
+            .expect("should compile");
+        let a = &output.schemas[0].schema;
+        assert_eq!(a["fields"][0]["type"], "ex.B");
+        assert_eq!(a["fields"][1]["type"], "ex.C");
+        // References inside unions are names too.
+        let d = &output.schemas[3].schema;
+        assert_eq!(d["name"], "D");
+        assert_eq!(d["fields"][0]["type"], serde_json::json!(["null", "ex.E"]));
+    }
+
+    #[test]
+    fn idl_references_by_name_defines_each_type_once() {
+        let output = Idl::new()
+            .references_by_name(true)
+            .convert_str(
+                r#"
+                @namespace("ex")
+                protocol P {
+                    record A { B b; other.C c; }
+                    record B { int x; }
+                    @namespace("other") record C { ex.B b; }
+                    A get(B b);
+                }
+                "#,
+            )
+            .expect("should compile");
+        let types = output.json["types"].as_array().expect("types array");
+        let names: Vec<&str> = types.iter().map(|t| t["name"].as_str().unwrap()).collect();
+        // Without the option, the forward references inline B and C into A.
+        assert_eq!(names, vec!["A", "B", "C"]);
+        assert_eq!(types[0]["fields"][0]["type"], "ex.B");
+        assert_eq!(types[0]["fields"][1]["type"], "other.C");
+        assert_eq!(types[2]["fields"][0]["type"], "ex.B");
+        assert_eq!(output.json["messages"]["get"]["response"], "ex.A");
+        assert_eq!(output.json["messages"]["get"]["request"][0]["type"], "ex.B");
+    }
+
+    // ------------------------------------------------------------------
+    // Cross-file consistency
+    // ------------------------------------------------------------------
+
+    #[test]
+    fn conflicts_report_diverging_definitions_across_files() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        let write = |name: &str, content: &str| {
+            std::fs::write(dir.path().join(name), content).expect("write .avdl");
+        };
+        write(
+            "a.avdl",
+            "@namespace(\"money\") protocol A { record Money { long cents; } }",
+        );
+        // Same structure, different doc: not a conflict.
+        write(
+            "b.avdl",
+            "@namespace(\"money\") protocol B { /** Cash. */ record Money { long cents; } }",
+        );
+        write(
+            "c.avdl",
+            "@namespace(\"money\") protocol C { record Money { int cents; string currency; } }",
+        );
+
+        let output = Idl2Schemata::new()
+            .extract(dir.path())
+            .expect("should compile");
+        let conflicts = output.conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].full_name, "money.Money");
+        assert!(conflicts[0].first_source.ends_with("a.avdl"));
+        assert!(conflicts[0].second_source.ends_with("c.avdl"));
+    }
+
+    #[test]
+    fn only_roots_skips_referenced_types() {
+        let output = Idl2Schemata::new()
+            .only_roots(true)
+            .extract_str(DEPENDENCY_CHAIN)
+            .expect("should compile");
+        // B and C are referenced by A; D and E reference each other.
+        assert_eq!(schema_names(&output), vec!["A"]);
+
+        let output = Idl2Schemata::new()
+            .only_roots(true)
+            .extract_str(
+                r#"protocol P {
+                    record Node { union { null, Node } next; }
+                    record Helper { int x; }
+                    Helper get();
+                }"#,
+            )
+            .expect("should compile");
+        // Self-references and message references do not count.
+        assert_eq!(schema_names(&output), vec!["Node", "Helper"]);
+    }
+
+    #[test]
+    fn protocols_match_idl_output() {
+        let source = r#"@namespace("ex") protocol Shop {
+            record Order { string id; }
+            Order get(string id);
+        }"#;
+        let output = Idl2Schemata::new()
+            .protocols(true)
+            .extract_str(source)
+            .expect("should compile");
+        assert_eq!(output.protocols.len(), 1);
+        assert_eq!(output.protocols[0].name, "Shop");
+        assert_eq!(output.protocols[0].namespace.as_deref(), Some("ex"));
+        let idl = Idl::new().convert_str(source).expect("should compile");
+        assert_eq!(output.protocols[0].json, idl.json);
+
+        // Off by default, and schema-mode files have no protocol.
+        let output = Idl2Schemata::new()
+            .extract_str(source)
+            .expect("should compile");
+        assert!(output.protocols.is_empty());
+        let output = Idl2Schemata::new()
+            .protocols(true)
+            .extract_str("namespace ex; schema int;")
+            .expect("should compile");
+        assert!(output.protocols.is_empty());
+    }
+
+    // ------------------------------------------------------------------
+    // Output determinism
+    // ------------------------------------------------------------------
+
+    #[test]
+    fn output_is_byte_identical_regardless_of_annotation_order() {
+        // `serde_json::Map` is a `BTreeMap` here, so object keys are always
+        // sorted; the order annotations are written in must not matter.
+        let render = |source: &'static str| {
+            let output = Idl::new().convert_str(source).expect("should compile");
+            serde_json::to_string_pretty(&output.json).expect("serialize")
+        };
+        assert_eq!(
+            render(
+                r#"@a("1") @b("2") protocol P {
+                    @x("1") @y("2") record R { @p("1") @q("2") int f; }
+                    @m("1") @n("2") void ping();
+                    void pong();
+                }"#
+            ),
+            render(
+                r#"@b("2") @a("1") protocol P {
+                    @y("2") @x("1") record R { @q("2") @p("1") int f; }
+                    void pong();
+                    @n("2") @m("1") void ping();
+                }"#
+            ),
+        );
+    }
+
+    #[test]
+    fn deny_property_strips_matching_annotations() {
+        let output = Idl::new()
+            .deny_property("internal-*")
+            .convert_str(
+                r#"@internal-owner("a") protocol P {
+                    @internal-owner("b") @java-class("X") record R {
+                        @internal-note("c") @logicalType("custom") string s;
+                    }
+                    @internal-note("d") void ping();
+                }"#,
+            )
+            .expect("should compile");
+        let rendered = output.json.to_string();
+        assert!(!rendered.contains("internal-"), "{rendered}");
+        assert_eq!(output.json["types"][0]["java-class"], "X");
+        assert_eq!(
+            output.json["types"][0]["fields"][0]["type"]["logicalType"],
+            "custom"
+        );
+    }
+
+    #[test]
+    fn set_property_overrides_protocol_properties() {
+        let output = Idl::new()
+            .deny_property("build")
+            .set_property("build", serde_json::json!(42))
+            .expect("`build` is a custom property")
+            .convert_str(r#"@build(1) protocol P { @build(2) record R { int x; } }"#)
+            .expect("should compile");
+        assert_eq!(output.json["build"], 42);
+        // Only the protocol itself gets the property; the deny list still
+        // applies everywhere else.
+        assert!(output.json["types"][0].get("build").is_none());
+
+        let output = Idl::new()
+            .set_property("build", serde_json::json!(42))
+            .expect("`build` is a custom property")
+            .convert_str("schema int;")
+            .expect("should compile");
+        assert_eq!(output.json, "int");
+
+        let Err(err) = Idl::new().set_property("namespace", serde_json::json!("org.example"))
+        else {
+            panic!("`namespace` is defined by the specification");
+        };
+        assert_eq!(ErrorKind::of(&err), Some(ErrorKind::ReservedProperty));
+    }
+
+    #[test]
+    fn build_metadata_stamps_compiler_and_source_hash() {
+        let source = "protocol P { record R { int x; } }";
+        let output = Idl::new()
+            .build_metadata(true)
+            .convert_str(source)
+            .expect("should compile");
+        assert_eq!(
+            output.json["avdl.compiler"],
+            format!("avdl {}", env!("CARGO_PKG_VERSION"))
+        );
+        assert_eq!(
+            output.json["avdl.sourceHash"],
+            format!("{:016x}", rabin_fingerprint(source.as_bytes()))
+        );
+
+        let edited = Idl::new()
+            .build_metadata(true)
+            .convert_str("protocol P { record R { long x; } }")
+            .expect("should compile");
+        assert_ne!(
+            edited.json["avdl.sourceHash"],
+            output.json["avdl.sourceHash"]
+        );
+
+        let output = Idl::new().convert_str(source).expect("should compile");
+        assert!(output.json.get("avdl.compiler").is_none());
+    }
 }
@@ -0,0 +1,363 @@
+// ==============================================================================
+// Effective Field Defaults
+// ==============================================================================
+//
+// A record-typed field's default is a JSON object that may leave out fields
+// which have defaults of their own; readers fill those in. This module does
+// the same filling ahead of time, producing the value each field would
+// actually take, with nested record defaults expanded through arrays, maps,
+// and unions.
+//
+// Expansion also catches defaults that can never be built. Field-level
+// validation (`validate_record_field_defaults`) only checks that an omitted
+// field *has* a default, not that filling it in terminates: with
+// `record A { B b = {}; } record B { A a = {}; }` each default requires the
+// other forever, which Java only discovers when a reader applies it.
+
+use serde_json::{Map, Value};
+
+use crate::error::ErrorKind;
+use crate::model::schema::{AvroSchema, Field, is_valid_default, validate_record_field_defaults};
+use crate::resolve::SchemaRegistry;
+
+/// Which branch of a union a field default may match.
+///
+/// The Avro specification says a union default must match the union's first
+/// branch. Java's `Schema.isValidDefault` accepts a default that matches any
+/// branch, and so does avdl by default, but stricter Avro implementations
+/// reject such schemas.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnionDefaults {
+    /// Accept a default that matches any branch. This matches `avro-tools`.
+    #[default]
+    AnyBranch,
+    /// Require the default to match the first branch, as the specification
+    /// does.
+    FirstBranch,
+}
+
+/// The effective defaults of one record's fields, as returned by
+/// [`IdlOutput::effective_defaults`](crate::IdlOutput::effective_defaults).
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordDefaults {
+    /// Full name of the record.
+    pub record: String,
+    /// Every field of the record, in declaration order.
+    pub fields: Vec<FieldDefault>,
+}
+
+/// The effective default of a single field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDefault {
+    pub name: String,
+    /// The default with every nested record default filled in, or `None` if
+    /// the field has no default.
+    pub value: Option<Value>,
+}
+
+/// Compute the effective defaults of every record in the registry, in
+/// registration order. With [`UnionDefaults::FirstBranch`], a union default
+/// anywhere in a field default must match the union's first branch.
+pub(crate) fn effective_defaults(
+    registry: &SchemaRegistry,
+    union_defaults: UnionDefaults,
+) -> miette::Result<Vec<RecordDefaults>> {
+    collect_defaults(registry, union_defaults, &mut |_, _, error| Err(error))
+}
+
+/// Like [`effective_defaults`], but a default that cannot be expanded is kept
+/// as declared, and its error is returned with the full name of its record
+/// instead of failing. Defaults that fail field-level validation, which the
+/// caller has already reported, are kept without an error.
+pub(crate) fn effective_defaults_unchecked(
+    registry: &SchemaRegistry,
+    union_defaults: UnionDefaults,
+) -> (Vec<RecordDefaults>, Vec<(String, miette::Report)>) {
+    let mut errors = Vec::new();
+    let records = collect_defaults(registry, union_defaults, &mut |record, field, error| {
+        let default = field.default.as_ref().expect("only defaults are expanded");
+        let reported = !is_valid_default(default, &field.schema)
+            || validate_record_field_defaults(record, |name| registry.lookup_shared(name))
+                .iter()
+                .any(|(name, _)| *name == field.name);
+        if !reported {
+            let full_name = record.full_name().expect("records are named");
+            errors.push((full_name.into_owned(), error));
+        }
+        Ok(())
+    })
+    .expect("errors are collected, not returned");
+    (records, errors)
+}
+
+/// Expand every field default, passing each failure to `on_error` with the
+/// record and field it belongs to. The field keeps its declared default
+/// unless `on_error` fails.
+fn collect_defaults(
+    registry: &SchemaRegistry,
+    union_defaults: UnionDefaults,
+    on_error: &mut dyn FnMut(&AvroSchema, &Field, miette::Report) -> miette::Result<()>,
+) -> miette::Result<Vec<RecordDefaults>> {
+    let mut records = Vec::new();
+    for schema in registry.schemas() {
+        let AvroSchema::Record { fields, .. } = schema else {
+            continue;
+        };
+        let record = schema.full_name().expect("records are named").into_owned();
+        let mut defaults = Vec::with_capacity(fields.len());
+        for field in fields {
+            let value = match &field.default {
+                Some(default) => {
+                    let mut expander = Expander {
+                        registry,
+                        union_defaults,
+                        stack: vec![(record.clone(), field.name.clone())],
+                    };
+                    match expander.expand(default, &field.schema) {
+                        Ok(value) => Some(value),
+                        Err(error) => {
+                            on_error(schema, field, error)?;
+                            Some(default.clone())
+                        }
+                    }
+                }
+                None => None,
+            };
+            defaults.push(FieldDefault {
+                name: field.name.clone(),
+                value,
+            });
+        }
+        records.push(RecordDefaults {
+            record,
+            fields: defaults,
+        });
+    }
+    Ok(records)
+}
+
+struct Expander<'a> {
+    registry: &'a SchemaRegistry,
+    union_defaults: UnionDefaults,
+    /// The `(record, field)` pairs whose declared defaults are currently being
+    /// expanded, outermost first. Seeing a pair twice means the expansion
+    /// would never end.
+    stack: Vec<(String, String)>,
+}
+
+impl Expander<'_> {
+    fn expand(&mut self, value: &Value, schema: &AvroSchema) -> miette::Result<Value> {
+        match schema {
+            AvroSchema::Reference { .. } => {
+                let full_name = schema.full_name().expect("references are named");
+                match self.registry.lookup(&full_name) {
+                    Some(resolved) => self.expand(value, resolved),
+                    // Unresolved references were already reported.
+                    None => Ok(value.clone()),
+                }
+            }
+            AvroSchema::Record { fields, .. } => match value.as_object() {
+                Some(obj) => {
+                    let record = schema.full_name().expect("records are named").into_owned();
+                    self.expand_record(&record, fields, obj)
+                }
+                None => Ok(value.clone()),
+            },
+            AvroSchema::Array { items, .. } => match value.as_array() {
+                Some(elements) => elements
+                    .iter()
+                    .map(|element| self.expand(element, items))
+                    .collect::<miette::Result<_>>()
+                    .map(Value::Array),
+                None => Ok(value.clone()),
+            },
+            AvroSchema::Map { values, .. } => match value.as_object() {
+                Some(entries) => entries
+                    .iter()
+                    .map(|(key, entry)| Ok((key.clone(), self.expand(entry, values)?)))
+                    .collect::<miette::Result<Map<_, _>>>()
+                    .map(Value::Object),
+                None => Ok(value.clone()),
+            },
+            // The default belongs to the first branch it is valid for.
+            AvroSchema::Union { types, .. } => {
+                for (i, branch) in types.iter().enumerate() {
+                    let resolved = match branch {
+                        AvroSchema::Reference { .. } => {
+                            let full_name = branch.full_name().expect("references are named");
+                            match self.registry.lookup(&full_name) {
+                                Some(resolved) => resolved,
+                                None => continue,
+                            }
+                        }
+                        other => other,
+                    };
+                    if is_valid_default(value, resolved) {
+                        if i > 0 && self.union_defaults == UnionDefaults::FirstBranch {
+                            miette::bail!(
+                                code = ErrorKind::InvalidDefault.code(),
+                                help = "move that branch to the front of the union, or \
+                                        change the default to match the first branch",
+                                "default value of `{}.{}` matches union branch `{}`, not the \
+                                 first branch `{}`",
+                                self.stack[0].0,
+                                self.stack[0].1,
+                                branch.union_type_key(),
+                                types[0].union_type_key()
+                            );
+                        }
+                        return self.expand(value, resolved);
+                    }
+                }
+                Ok(value.clone())
+            }
+            _ => Ok(value.clone()),
+        }
+    }
+
+    fn expand_record(
+        &mut self,
+        record: &str,
+        fields: &[Field],
+        obj: &Map<String, Value>,
+    ) -> miette::Result<Value> {
+        let mut expanded = Map::new();
+        for field in fields {
+            let value = if let Some(given) = obj.get(&field.name) {
+                self.expand(given, &field.schema)?
+            } else if let Some(default) = &field.default {
+                let key = (record.to_string(), field.name.clone());
+                if self.stack.contains(&key) {
+                    let path: Vec<String> = self
+                        .stack
+                        .iter()
+                        .chain(std::iter::once(&key))
+                        .map(|(record, field)| format!("{record}.{field}"))
+                        .collect();
+                    miette::bail!(
+                        code = ErrorKind::InvalidDefault.code(),
+                        help = "give one of these defaults an explicit value for the field \
+                                that closes the cycle",
+                        "default value of `{}.{}` can never be completed: {}",
+                        self.stack[0].0,
+                        self.stack[0].1,
+                        path.join(" -> ")
+                    );
+                }
+                self.stack.push(key);
+                let value = self.expand(default, &field.schema)?;
+                self.stack.pop();
+                value
+            } else {
+                miette::bail!(
+                    code = ErrorKind::InvalidDefault.code(),
+                    "default value of `{}.{}` has no value for `{record}.{}`, which has no default",
+                    self.stack[0].0,
+                    self.stack[0].1,
+                    field.name
+                );
+            };
+            expanded.insert(field.name.clone(), value);
+        }
+        Ok(Value::Object(expanded))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UnionDefaults;
+    use crate::Idl;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    #[test]
+    fn nested_record_defaults_are_filled_in() {
+        let output = Idl::new()
+            .convert_str(
+                r#"
+                @namespace("ex")
+                protocol P {
+                    record Money { long cents = 0; string currency = "USD"; }
+                    record Line { Money price = {"cents": 5}; }
+                    record Order {
+                        string id;
+                        Money total = {};
+                        array<Line> lines = [{}, {"price": {"currency": "EUR"}}];
+                        union { null, Money } tip = null;
+                    }
+                }
+                "#,
+            )
+            .expect("should compile");
+
+        let order = output
+            .effective_defaults()
+            .iter()
+            .find(|r| r.record == "ex.Order")
+            .expect("ex.Order is listed");
+        let values: Vec<_> = order
+            .fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.value.clone()))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                ("id", None),
+                ("total", Some(json!({"cents": 0, "currency": "USD"}))),
+                (
+                    "lines",
+                    Some(json!([
+                        {"price": {"cents": 5, "currency": "USD"}},
+                        {"price": {"cents": 0, "currency": "EUR"}}
+                    ]))
+                ),
+                ("tip", Some(json!(null))),
+            ]
+        );
+    }
+
+    #[test]
+    fn mutually_recursive_defaults_are_rejected() {
+        let err = Idl::new()
+            .convert_str(
+                r#"protocol P {
+                    record A { B b = {}; }
+                    record B { A a = {}; }
+                }"#,
+            )
+            .expect_err("defaults require each other");
+        assert!(
+            err.to_string()
+                .contains("default value of `A.b` can never be completed: A.b -> B.a -> A.b"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn first_branch_union_defaults_reject_later_branches() {
+        let source = r#"protocol P { record R { union { null, string } name = "anonymous"; } }"#;
+        Idl::new()
+            .convert_str(source)
+            .expect("avro-tools accepts a default matching any branch");
+        let err = Idl::new()
+            .union_defaults(UnionDefaults::FirstBranch)
+            .convert_str(source)
+            .expect_err("\"anonymous\" matches the second branch");
+        assert_eq!(
+            err.to_string(),
+            "default value of `R.name` matches union branch `string`, not the first branch `null`"
+        );
+
+        // `string?` puts the branch the default matches first, and `null`
+        // matches the first branch of `union { null, int }`.
+        Idl::new()
+            .union_defaults(UnionDefaults::FirstBranch)
+            .convert_str(
+                r#"protocol P {
+                    record R { string? nickname = "none"; array<union { null, int }> counts = [null]; }
+                }"#,
+            )
+            .expect("every default matches the first branch");
+    }
+}
@@ -0,0 +1,220 @@
+// ==============================================================================
+// Reading Compiled JSON
+// ==============================================================================
+//
+// Helpers for tooling that works on the emitted `.avpr` / `.avsc` JSON rather
+// than on the domain model: finding every named type (however deeply it is
+// inlined) and describing schemas and messages in IDL-like shorthand with
+// full names (`array<com.example.Line>`, `union { null, string }`,
+// `decimal(9, 2)`). Shared by the semantic diff and the doc generator.
+
+use std::collections::BTreeMap;
+
+use serde_json::{Map, Value};
+
+use crate::model::schema::PRIMITIVE_TYPE_NAMES;
+
+// ==============================================================================
+// Named Types
+// ==============================================================================
+
+/// A named type's JSON object together with the namespace its own field
+/// types are resolved against.
+pub(crate) struct NamedType<'a> {
+    pub(crate) obj: &'a Map<String, Value>,
+    pub(crate) namespace: Option<String>,
+}
+
+impl NamedType<'_> {
+    pub(crate) fn kind(&self) -> &str {
+        self.obj.get("type").and_then(Value::as_str).unwrap_or("")
+    }
+}
+
+/// Collect every named type defined anywhere in a protocol or schema, keyed by
+/// full name.
+pub(crate) fn named_types(json: &Value) -> BTreeMap<String, NamedType<'_>> {
+    let mut types = BTreeMap::new();
+    match json.get("types") {
+        Some(Value::Array(schemas)) if json.get("protocol").is_some() => {
+            let namespace = json.get("namespace").and_then(Value::as_str);
+            for schema in schemas {
+                collect_named(schema, namespace, &mut types);
+            }
+        }
+        _ => collect_named(json, None, &mut types),
+    }
+    types
+}
+
+fn collect_named<'a>(
+    schema: &'a Value,
+    namespace: Option<&str>,
+    types: &mut BTreeMap<String, NamedType<'a>>,
+) {
+    match schema {
+        Value::Array(branches) => {
+            for branch in branches {
+                collect_named(branch, namespace, types);
+            }
+        }
+        Value::Object(obj) => match obj.get("type") {
+            Some(Value::String(kind)) => match kind.as_str() {
+                "record" | "error" | "enum" | "fixed" => {
+                    let full = declared_name(obj, namespace);
+                    let own_ns = full.rsplit_once('.').map(|(ns, _)| ns.to_string());
+                    if let Some(Value::Array(fields)) = obj.get("fields") {
+                        for field in fields {
+                            if let Some(field_type) = field.get("type") {
+                                collect_named(field_type, own_ns.as_deref(), types);
+                            }
+                        }
+                    }
+                    types.insert(
+                        full,
+                        NamedType {
+                            obj,
+                            namespace: own_ns,
+                        },
+                    );
+                }
+                "array" => {
+                    if let Some(items) = obj.get("items") {
+                        collect_named(items, namespace, types);
+                    }
+                }
+                "map" => {
+                    if let Some(values) = obj.get("values") {
+                        collect_named(values, namespace, types);
+                    }
+                }
+                _ => {}
+            },
+            // `{"type": {...}}` or `{"type": [...]}` wraps another schema.
+            Some(inner) => collect_named(inner, namespace, types),
+            None => {}
+        },
+        _ => {}
+    }
+}
+
+/// The fields of a record, by name, in declaration order.
+pub(crate) fn record_fields<'a>(record: &NamedType<'a>) -> Vec<(&'a str, &'a Map<String, Value>)> {
+    record
+        .obj
+        .get("fields")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object)
+        .map(|field| {
+            let name = field.get("name").and_then(Value::as_str).unwrap_or("");
+            (name, field)
+        })
+        .collect()
+}
+
+pub(crate) fn field_schema(field: &Map<String, Value>) -> &Value {
+    field.get("type").unwrap_or(&Value::Null)
+}
+
+// ==============================================================================
+// Type Descriptions
+// ==============================================================================
+
+/// The full name a named type's JSON object declares.
+pub(crate) fn declared_name(obj: &Map<String, Value>, namespace: Option<&str>) -> String {
+    let name = obj.get("name").and_then(Value::as_str).unwrap_or("");
+    let namespace = obj.get("namespace").and_then(Value::as_str).or(namespace);
+    resolve_name(name, namespace)
+}
+
+/// Resolve a (possibly short) name against the enclosing namespace.
+pub(crate) fn resolve_name(name: &str, namespace: Option<&str>) -> String {
+    match namespace {
+        Some(ns) if !ns.is_empty() && !name.contains('.') => format!("{ns}.{name}"),
+        _ => name.to_string(),
+    }
+}
+
+/// Describe a schema in IDL-like shorthand with full names.
+pub(crate) fn type_string(schema: &Value, namespace: Option<&str>) -> String {
+    match schema {
+        Value::String(name) if PRIMITIVE_TYPE_NAMES.contains(&name.as_str()) => name.clone(),
+        Value::String(name) => resolve_name(name, namespace),
+        Value::Array(branches) => {
+            let branches: Vec<String> = branches
+                .iter()
+                .map(|branch| type_string(branch, namespace))
+                .collect();
+            format!("union {{ {} }}", branches.join(", "))
+        }
+        Value::Object(obj) => match obj.get("type") {
+            Some(Value::String(kind)) => match kind.as_str() {
+                "record" | "error" | "enum" | "fixed" => declared_name(obj, namespace),
+                "array" => format!(
+                    "array<{}>",
+                    type_string(obj.get("items").unwrap_or(&Value::Null), namespace)
+                ),
+                "map" => format!(
+                    "map<{}>",
+                    type_string(obj.get("values").unwrap_or(&Value::Null), namespace)
+                ),
+                primitive => logical_type(obj).unwrap_or_else(|| primitive.to_string()),
+            },
+            Some(inner) => type_string(inner, namespace),
+            None => schema.to_string(),
+        },
+        other => other.to_string(),
+    }
+}
+
+/// The logical type of a schema object, with decimal's precision and scale.
+pub(crate) fn logical_type(obj: &Map<String, Value>) -> Option<String> {
+    let logical = obj.get("logicalType")?.as_str()?;
+    if logical == "decimal" {
+        let precision = obj.get("precision").cloned().unwrap_or(Value::Null);
+        let scale = obj.get("scale").cloned().unwrap_or(Value::from(0));
+        return Some(format!("decimal({precision}, {scale})"));
+    }
+    Some(logical.to_string())
+}
+
+/// Render a message the way it is declared in IDL, e.g.
+/// `com.example.Order get(string id, int limit = 10) throws com.example.Oops`.
+pub(crate) fn message_signature(name: &str, message: &Value, namespace: Option<&str>) -> String {
+    let response = message
+        .get("response")
+        .map_or_else(|| "null".to_string(), |r| type_string(r, namespace));
+    let params: Vec<String> = message
+        .get("request")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|param| {
+            let param_name = param.get("name").and_then(Value::as_str).unwrap_or("");
+            let param_type = type_string(param.get("type").unwrap_or(&Value::Null), namespace);
+            match param.get("default") {
+                Some(default) => format!("{param_type} {param_name} = {default}"),
+                None => format!("{param_type} {param_name}"),
+            }
+        })
+        .collect();
+    let mut signature = format!("{response} {name}({})", params.join(", "));
+
+    let errors: Vec<String> = message
+        .get("errors")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|error| type_string(error, namespace))
+        .collect();
+    if !errors.is_empty() {
+        signature.push_str(" throws ");
+        signature.push_str(&errors.join(", "));
+    }
+    if message.get("one-way").and_then(Value::as_bool) == Some(true) {
+        signature.push_str(" oneway");
+    }
+    signature
+}
@@ -0,0 +1,535 @@
+// ==============================================================================
+// Semantic Diff of Compiled Protocols and Schemas
+// ==============================================================================
+//
+// Compares two compiled outputs by meaning rather than by text. Named types
+// are matched by full name and messages by name, so reordering (which imports
+// do freely) never shows up as a change. Like the fingerprint code, this works
+// on the emitted JSON: that is what consumers see, and it is already fully
+// resolved.
+//
+// Types are described in IDL-like shorthand (see `describe.rs`), always with
+// full names, so a named type written inline in one output and by name in the
+// other compares equal.
+//
+// Docs, aliases, field order attributes, and custom properties are not
+// compared. A field counts as renamed only when the new field lists the old
+// name in its `aliases`, which is how Avro readers recognize a rename too.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde_json::{Map, Value};
+
+use crate::describe::{
+    NamedType, field_schema, logical_type, message_signature, named_types, record_fields,
+    type_string,
+};
+use crate::model::schema::PRIMITIVE_TYPE_NAMES;
+
+/// One difference between two compiled files, as returned by
+/// [`IdlOutput::diff`](crate::IdlOutput::diff).
+///
+/// Type names are full names; field and message types are given in IDL-like
+/// shorthand such as `array<com.example.Line>` or `union { null, string }`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaChange {
+    TypeAdded {
+        name: String,
+        kind: String,
+    },
+    TypeRemoved {
+        name: String,
+        kind: String,
+    },
+    /// The name now belongs to a different kind of type, e.g. a record that
+    /// became an enum.
+    KindChanged {
+        name: String,
+        old: String,
+        new: String,
+    },
+    FieldAdded {
+        record: String,
+        field: String,
+        field_type: String,
+    },
+    FieldRemoved {
+        record: String,
+        field: String,
+        field_type: String,
+    },
+    /// The new field lists the old name among its aliases.
+    FieldRenamed {
+        record: String,
+        old: String,
+        new: String,
+    },
+    FieldTypeChanged {
+        record: String,
+        field: String,
+        old: String,
+        new: String,
+    },
+    /// The default was added, removed (`None`), or changed.
+    DefaultChanged {
+        record: String,
+        field: String,
+        old: Option<Value>,
+        new: Option<Value>,
+    },
+    /// The logical type of a field, or of a named fixed type, changed while
+    /// the underlying type stayed the same. `location` is `record.field` or
+    /// the fixed type's name.
+    LogicalTypeChanged {
+        location: String,
+        old: Option<String>,
+        new: Option<String>,
+    },
+    SymbolsChanged {
+        name: String,
+        added: Vec<String>,
+        removed: Vec<String>,
+    },
+    SizeChanged {
+        name: String,
+        old: u64,
+        new: u64,
+    },
+    MessageAdded {
+        name: String,
+        signature: String,
+    },
+    MessageRemoved {
+        name: String,
+        signature: String,
+    },
+    /// The message's parameters, response, errors, or one-way flag changed.
+    MessageChanged {
+        name: String,
+        old: String,
+        new: String,
+    },
+}
+
+impl fmt::Display for SchemaChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaChange::TypeAdded { name, kind } => write!(f, "+ {kind} {name}"),
+            SchemaChange::TypeRemoved { name, kind } => write!(f, "- {kind} {name}"),
+            SchemaChange::KindChanged { name, old, new } => {
+                write!(f, "~ {name}: {old} -> {new}")
+            }
+            SchemaChange::FieldAdded {
+                record,
+                field,
+                field_type,
+            } => write!(f, "+ field {record}.{field}: {field_type}"),
+            SchemaChange::FieldRemoved {
+                record,
+                field,
+                field_type,
+            } => write!(f, "- field {record}.{field}: {field_type}"),
+            SchemaChange::FieldRenamed { record, old, new } => {
+                write!(f, "~ field {record}.{old}: renamed to {new}")
+            }
+            SchemaChange::FieldTypeChanged {
+                record,
+                field,
+                old,
+                new,
+            } => write!(f, "~ field {record}.{field}: type {old} -> {new}"),
+            SchemaChange::DefaultChanged {
+                record,
+                field,
+                old,
+                new,
+            } => {
+                let show = |value: &Option<Value>| match value {
+                    Some(value) => value.to_string(),
+                    None => "(none)".to_string(),
+                };
+                write!(
+                    f,
+                    "~ field {record}.{field}: default {} -> {}",
+                    show(old),
+                    show(new)
+                )
+            }
+            SchemaChange::LogicalTypeChanged { location, old, new } => write!(
+                f,
+                "~ {location}: logical type {} -> {}",
+                old.as_deref().unwrap_or("(none)"),
+                new.as_deref().unwrap_or("(none)")
+            ),
+            SchemaChange::SymbolsChanged {
+                name,
+                added,
+                removed,
+            } => {
+                write!(f, "~ enum {name}: symbols")?;
+                for symbol in added {
+                    write!(f, " +{symbol}")?;
+                }
+                for symbol in removed {
+                    write!(f, " -{symbol}")?;
+                }
+                Ok(())
+            }
+            SchemaChange::SizeChanged { name, old, new } => {
+                write!(f, "~ fixed {name}: size {old} -> {new}")
+            }
+            SchemaChange::MessageAdded { name, signature } => {
+                write!(f, "+ message {name}: {signature}")
+            }
+            SchemaChange::MessageRemoved { name, signature } => {
+                write!(f, "- message {name}: {signature}")
+            }
+            SchemaChange::MessageChanged { name, old, new } => {
+                write!(f, "~ message {name}: {old} -> {new}")
+            }
+        }
+    }
+}
+
+/// Compare two compiled `.avpr` or `.avsc` JSON values. Changes are listed
+/// by type name, then messages by name.
+pub(crate) fn diff_json(old: &Value, new: &Value) -> Vec<SchemaChange> {
+    let old_types = named_types(old);
+    let new_types = named_types(new);
+    let mut changes = Vec::new();
+
+    let mut names: Vec<&String> = old_types.keys().chain(new_types.keys()).collect();
+    names.sort();
+    names.dedup();
+    for name in names {
+        match (old_types.get(name), new_types.get(name)) {
+            (Some(old), Some(new)) => diff_named(name, old, new, &mut changes),
+            (Some(old), None) => changes.push(SchemaChange::TypeRemoved {
+                name: name.clone(),
+                kind: old.kind().to_string(),
+            }),
+            (None, Some(new)) => changes.push(SchemaChange::TypeAdded {
+                name: name.clone(),
+                kind: new.kind().to_string(),
+            }),
+            (None, None) => unreachable!("name came from one of the maps"),
+        }
+    }
+
+    diff_messages(old, new, &mut changes);
+    changes
+}
+
+// ==============================================================================
+// Named Types
+// ==============================================================================
+
+fn diff_named(name: &str, old: &NamedType, new: &NamedType, changes: &mut Vec<SchemaChange>) {
+    if old.kind() != new.kind() {
+        changes.push(SchemaChange::KindChanged {
+            name: name.to_string(),
+            old: old.kind().to_string(),
+            new: new.kind().to_string(),
+        });
+        return;
+    }
+    match old.kind() {
+        "record" | "error" => diff_fields(name, old, new, changes),
+        "enum" => {
+            let symbols = |t: &NamedType<'_>| -> Vec<String> {
+                t.obj
+                    .get("symbols")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect()
+            };
+            let (old_symbols, new_symbols) = (symbols(old), symbols(new));
+            let added: Vec<String> = new_symbols
+                .iter()
+                .filter(|s| !old_symbols.contains(s))
+                .cloned()
+                .collect();
+            let removed: Vec<String> = old_symbols
+                .iter()
+                .filter(|s| !new_symbols.contains(s))
+                .cloned()
+                .collect();
+            if !added.is_empty() || !removed.is_empty() {
+                changes.push(SchemaChange::SymbolsChanged {
+                    name: name.to_string(),
+                    added,
+                    removed,
+                });
+            }
+        }
+        "fixed" => {
+            let size = |t: &NamedType<'_>| t.obj.get("size").and_then(Value::as_u64).unwrap_or(0);
+            if size(old) != size(new) {
+                changes.push(SchemaChange::SizeChanged {
+                    name: name.to_string(),
+                    old: size(old),
+                    new: size(new),
+                });
+            }
+            let (old_logical, new_logical) = (logical_type(old.obj), logical_type(new.obj));
+            if old_logical != new_logical {
+                changes.push(SchemaChange::LogicalTypeChanged {
+                    location: name.to_string(),
+                    old: old_logical,
+                    new: new_logical,
+                });
+            }
+        }
+        _ => {}
+    }
+}
+
+// ==============================================================================
+// Record Fields
+// ==============================================================================
+
+fn diff_fields(record: &str, old: &NamedType, new: &NamedType, changes: &mut Vec<SchemaChange>) {
+    let (old_fields, new_fields) = (record_fields(old), record_fields(new));
+
+    for &(name, old_field) in &old_fields {
+        if find_field(&new_fields, name).is_some() {
+            continue;
+        }
+        let renamed = new_fields.iter().find(|&&(new_name, field)| {
+            find_field(&old_fields, new_name).is_none() && field_aliases(field).any(|a| a == name)
+        });
+        match renamed {
+            Some(&(new_name, new_field)) => {
+                changes.push(SchemaChange::FieldRenamed {
+                    record: record.to_string(),
+                    old: name.to_string(),
+                    new: new_name.to_string(),
+                });
+                diff_field(
+                    record,
+                    new_name,
+                    (old_field, old),
+                    (new_field, new),
+                    changes,
+                );
+            }
+            None => changes.push(SchemaChange::FieldRemoved {
+                record: record.to_string(),
+                field: name.to_string(),
+                field_type: type_string(field_schema(old_field), old.namespace.as_deref()),
+            }),
+        }
+    }
+
+    for &(name, new_field) in &new_fields {
+        match find_field(&old_fields, name) {
+            Some(old_field) => {
+                diff_field(record, name, (old_field, old), (new_field, new), changes);
+            }
+            None => {
+                let is_rename = field_aliases(new_field).any(|alias| {
+                    find_field(&new_fields, alias).is_none()
+                        && find_field(&old_fields, alias).is_some()
+                });
+                if !is_rename {
+                    changes.push(SchemaChange::FieldAdded {
+                        record: record.to_string(),
+                        field: name.to_string(),
+                        field_type: type_string(field_schema(new_field), new.namespace.as_deref()),
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn find_field<'a>(
+    fields: &[(&str, &'a Map<String, Value>)],
+    name: &str,
+) -> Option<&'a Map<String, Value>> {
+    fields
+        .iter()
+        .find(|(field_name, _)| *field_name == name)
+        .map(|&(_, field)| field)
+}
+
+fn field_aliases(field: &Map<String, Value>) -> impl Iterator<Item = &str> {
+    field
+        .get("aliases")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+}
+
+/// Compare the type and default of a field present in both records, reporting
+/// it under its new name.
+fn diff_field(
+    record: &str,
+    field: &str,
+    (old_field, old): (&Map<String, Value>, &NamedType),
+    (new_field, new): (&Map<String, Value>, &NamedType),
+    changes: &mut Vec<SchemaChange>,
+) {
+    let old_schema = field_schema(old_field);
+    let new_schema = field_schema(new_field);
+    let old_type = type_string(old_schema, old.namespace.as_deref());
+    let new_type = type_string(new_schema, new.namespace.as_deref());
+    if old_type != new_type {
+        match (
+            underlying_primitive(old_schema),
+            underlying_primitive(new_schema),
+        ) {
+            (Some(old_base), Some(new_base)) if old_base == new_base => {
+                changes.push(SchemaChange::LogicalTypeChanged {
+                    location: format!("{record}.{field}"),
+                    old: old_schema.as_object().and_then(logical_type),
+                    new: new_schema.as_object().and_then(logical_type),
+                });
+            }
+            _ => changes.push(SchemaChange::FieldTypeChanged {
+                record: record.to_string(),
+                field: field.to_string(),
+                old: old_type,
+                new: new_type,
+            }),
+        }
+    }
+
+    let old_default = old_field.get("default");
+    let new_default = new_field.get("default");
+    if old_default != new_default {
+        changes.push(SchemaChange::DefaultChanged {
+            record: record.to_string(),
+            field: field.to_string(),
+            old: old_default.cloned(),
+            new: new_default.cloned(),
+        });
+    }
+}
+
+/// The primitive type a schema is, or annotates with a logical type.
+fn underlying_primitive(schema: &Value) -> Option<&str> {
+    let name = match schema {
+        Value::String(name) => name.as_str(),
+        Value::Object(obj) => obj.get("type")?.as_str()?,
+        _ => return None,
+    };
+    PRIMITIVE_TYPE_NAMES.contains(&name).then_some(name)
+}
+
+// ==============================================================================
+// Messages
+// ==============================================================================
+
+fn diff_messages(old: &Value, new: &Value, changes: &mut Vec<SchemaChange>) {
+    let signatures = |json: &Value| -> BTreeMap<String, String> {
+        let namespace = json.get("namespace").and_then(Value::as_str);
+        json.get("messages")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+            .map(|(name, message)| (name.clone(), message_signature(name, message, namespace)))
+            .collect()
+    };
+    let (old_messages, new_messages) = (signatures(old), signatures(new));
+
+    let mut names: Vec<&String> = old_messages.keys().chain(new_messages.keys()).collect();
+    names.sort();
+    names.dedup();
+    for name in names {
+        match (old_messages.get(name), new_messages.get(name)) {
+            (Some(old), Some(new)) if old != new => changes.push(SchemaChange::MessageChanged {
+                name: name.clone(),
+                old: old.clone(),
+                new: new.clone(),
+            }),
+            (Some(_), Some(_)) => {}
+            (Some(old), None) => changes.push(SchemaChange::MessageRemoved {
+                name: name.clone(),
+                signature: old.clone(),
+            }),
+            (None, Some(new)) => changes.push(SchemaChange::MessageAdded {
+                name: name.clone(),
+                signature: new.clone(),
+            }),
+            (None, None) => unreachable!("name came from one of the maps"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Idl;
+    use pretty_assertions::assert_eq;
+
+    fn diff(old: &'static str, new: &'static str) -> Vec<String> {
+        let old = Idl::new().convert_str(old).expect("old should compile");
+        let new = Idl::new().convert_str(new).expect("new should compile");
+        old.diff(&new).iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn reordering_types_is_not_a_change() {
+        let changes = diff(
+            "protocol P { record A { B b; } record B { int x; } enum E { X } }",
+            "protocol P { enum E { X } record B { int x; } record A { B b; } }",
+        );
+        assert_eq!(changes, Vec::<String>::new());
+    }
+
+    #[test]
+    fn reports_type_field_and_message_changes() {
+        let changes = diff(
+            r#"@namespace("ex") protocol P {
+                enum Status { NEW, OLD }
+                fixed Hash(16);
+                record Order {
+                    string id;
+                    int quantity = 1;
+                    timestamp_ms created;
+                    string note;
+                }
+                record Legacy { int x; }
+                Order get(string id);
+                void ping();
+            }"#,
+            r#"@namespace("ex") protocol P {
+                enum Status { NEW, DONE }
+                fixed Hash(32);
+                record Order {
+                    long id;
+                    int quantity = 2;
+                    timestamp_micros created;
+                    string @aliases(["note"]) comment;
+                    array<string> tags = [];
+                }
+                record Fresh { int y; }
+                Order get(string id, boolean verbose = false);
+                void pong() oneway;
+            }"#,
+        );
+        assert_eq!(
+            changes,
+            vec![
+                "+ record ex.Fresh",
+                "~ fixed ex.Hash: size 16 -> 32",
+                "- record ex.Legacy",
+                "~ field ex.Order.note: renamed to comment",
+                "~ field ex.Order.id: type string -> long",
+                "~ field ex.Order.quantity: default 1 -> 2",
+                "~ ex.Order.created: logical type timestamp-millis -> timestamp-micros",
+                "+ field ex.Order.tags: array<string>",
+                "~ enum ex.Status: symbols +DONE -OLD",
+                "~ message get: ex.Order get(string id) -> \
+                 ex.Order get(string id, boolean verbose = false)",
+                "- message ping: null ping()",
+                "+ message pong: null pong() oneway",
+            ]
+        );
+    }
+}
@@ -0,0 +1,691 @@
+// ==============================================================================
+// Documentation Generator
+// ==============================================================================
+//
+// Renders a compiled protocol or schema as a static HTML site: an index page
+// with the protocol's doc comment, a table of every named type, and the
+// message signatures, plus one page per named type with its doc comment,
+// aliases, and kind-specific details (record fields, enum symbols, fixed
+// size). Every full type name in a type description links to that type's
+// page, so references can be followed across the site.
+//
+// The same content can also be rendered as a single Markdown file, for
+// committing into a docs repository or wiki. There, each type gets a section
+// with an explicit anchor named after its full name, since heading slugs
+// differ between Markdown renderers.
+//
+// Like the semantic diff, this works on the emitted JSON, so imported types
+// are documented too and doc comments are already extracted and
+// de-indented. The pages are self-contained (inline CSS, no scripts) and use
+// relative links, so the output directory can be served or opened as is.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use crate::describe::{
+    NamedType, field_schema, logical_type, message_signature, named_types, record_fields,
+    type_string,
+};
+
+/// One generated documentation file, as returned by
+/// [`IdlOutput::html_docs`](crate::IdlOutput::html_docs) and
+/// [`IdlOutput::markdown_docs`](crate::IdlOutput::markdown_docs).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocPage {
+    /// File name relative to the output directory, e.g.
+    /// `"com.example.Order.html"` or `"com.example.Shop.md"`.
+    pub path: String,
+    pub content: String,
+}
+
+/// Render the HTML site for a compiled `.avpr` or `.avsc` JSON value:
+/// `index.html` first, then one page per named type, sorted by full name.
+pub(crate) fn html_pages(json: &Value) -> Vec<DocPage> {
+    let types = named_types(json);
+    let title = site_title(json);
+
+    let mut pages = Vec::with_capacity(types.len() + 1);
+    pages.push(DocPage {
+        path: "index.html".to_string(),
+        content: index_page(json, &title, &types),
+    });
+    for (name, named) in &types {
+        pages.push(DocPage {
+            path: page_path(name),
+            content: type_page(name, named, &title, &types),
+        });
+    }
+    pages
+}
+
+/// The protocol's full name, or a generic title for a standalone schema.
+fn site_title(json: &Value) -> String {
+    match json.get("protocol").and_then(Value::as_str) {
+        Some(protocol) => match json.get("namespace").and_then(Value::as_str) {
+            Some(ns) if !ns.is_empty() => format!("{ns}.{protocol}"),
+            _ => protocol.to_string(),
+        },
+        None => "Schemas".to_string(),
+    }
+}
+
+fn page_path(full_name: &str) -> String {
+    format!("{full_name}.html")
+}
+
+fn string_array<'a>(obj: &'a Map<String, Value>, key: &str) -> impl Iterator<Item = &'a str> {
+    obj.get(key)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+}
+
+// ==============================================================================
+// HTML Pages
+// ==============================================================================
+
+const STYLE: &str = "\
+body { font-family: system-ui, sans-serif; max-width: 60rem; margin: 2rem auto; padding: 0 1rem; }
+code, pre { font-family: ui-monospace, monospace; }
+table { border-collapse: collapse; width: 100%; }
+th, td { border: 1px solid #ccc; padding: 0.3rem 0.6rem; text-align: left; vertical-align: top; }
+.doc { white-space: pre-line; }
+.kind { color: #666; font-weight: normal; }";
+
+fn index_page(json: &Value, title: &str, types: &BTreeMap<String, NamedType<'_>>) -> String {
+    let mut body = format!("<h1>{}</h1>\n", escape_html(title));
+    push_doc(&mut body, json.get("doc"));
+
+    if !types.is_empty() {
+        body.push_str(
+            "<h2>Types</h2>\n<table>\n\
+             <thead><tr><th>Name</th><th>Kind</th><th>Summary</th></tr></thead>\n<tbody>\n",
+        );
+        for (name, named) in types {
+            let summary = named
+                .obj
+                .get("doc")
+                .and_then(Value::as_str)
+                .and_then(|doc| doc.lines().next())
+                .unwrap_or("");
+            writeln!(
+                body,
+                "<tr><td><a href=\"{}\"><code>{}</code></a></td><td>{}</td><td>{}</td></tr>",
+                escape_html(&page_path(name)),
+                escape_html(name),
+                escape_html(named.kind()),
+                escape_html(summary),
+            )
+            .expect("write to String is infallible");
+        }
+        body.push_str("</tbody>\n</table>\n");
+    }
+
+    if let Some(messages) = json.get("messages").and_then(Value::as_object)
+        && !messages.is_empty()
+    {
+        let namespace = json.get("namespace").and_then(Value::as_str);
+        body.push_str("<h2>Messages</h2>\n");
+        for (name, message) in messages {
+            let signature = message_signature(name, message, namespace);
+            writeln!(
+                body,
+                "<h3 id=\"message-{}\">{}</h3>\n<pre><code>{}</code></pre>",
+                escape_html(name),
+                escape_html(name),
+                link_names(&signature, types, escape_html, html_link),
+            )
+            .expect("write to String is infallible");
+            push_doc(&mut body, message.get("doc"));
+        }
+    }
+
+    html_document(title, title, &body)
+}
+
+fn type_page(
+    name: &str,
+    named: &NamedType<'_>,
+    site_title: &str,
+    types: &BTreeMap<String, NamedType<'_>>,
+) -> String {
+    let obj = named.obj;
+    let mut body = format!(
+        "<h1><span class=\"kind\">{}</span> {}</h1>\n",
+        escape_html(named.kind()),
+        escape_html(name)
+    );
+    push_doc(&mut body, obj.get("doc"));
+    push_aliases(&mut body, obj);
+
+    match named.kind() {
+        "record" | "error" => {
+            body.push_str(
+                "<h2>Fields</h2>\n<table>\n<thead><tr><th>Name</th><th>Type</th>\
+                 <th>Default</th><th>Description</th></tr></thead>\n<tbody>\n",
+            );
+            for (field_name, field) in record_fields(named) {
+                let field_type = type_string(field_schema(field), named.namespace.as_deref());
+                let default = field.get("default").map_or_else(String::new, |d| {
+                    format!("<code>{}</code>", escape_html(&d.to_string()))
+                });
+                let doc = field.get("doc").and_then(Value::as_str).unwrap_or("");
+                writeln!(
+                    body,
+                    "<tr id=\"field-{}\"><td><code>{}</code></td><td><code>{}</code></td>\
+                     <td>{default}</td><td class=\"doc\">{}</td></tr>",
+                    escape_html(field_name),
+                    escape_html(field_name),
+                    link_names(&field_type, types, escape_html, html_link),
+                    escape_html(doc),
+                )
+                .expect("write to String is infallible");
+            }
+            body.push_str("</tbody>\n</table>\n");
+        }
+        "enum" => {
+            body.push_str("<h2>Symbols</h2>\n<ul>\n");
+            for symbol in string_array(obj, "symbols") {
+                writeln!(body, "<li><code>{}</code></li>", escape_html(symbol))
+                    .expect("write to String is infallible");
+            }
+            body.push_str("</ul>\n");
+            if let Some(default) = obj.get("default").and_then(Value::as_str) {
+                writeln!(
+                    body,
+                    "<p>Default: <code>{}</code></p>",
+                    escape_html(default)
+                )
+                .expect("write to String is infallible");
+            }
+        }
+        "fixed" => {
+            if let Some(size) = obj.get("size").and_then(Value::as_u64) {
+                writeln!(body, "<p>Size: {size} bytes</p>").expect("write to String is infallible");
+            }
+            if let Some(logical) = logical_type(obj) {
+                writeln!(
+                    body,
+                    "<p>Logical type: <code>{}</code></p>",
+                    escape_html(&logical)
+                )
+                .expect("write to String is infallible");
+            }
+        }
+        _ => {}
+    }
+
+    html_document(&format!("{name} — {site_title}"), site_title, &body)
+}
+
+/// Wrap a page body in the shared document skeleton, with a link back to the
+/// index.
+fn html_document(title: &str, site_title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{}</title>\n<style>\n{STYLE}\n</style>\n</head>\n<body>\n\
+         <nav><a href=\"index.html\">{}</a></nav>\n{body}</body>\n</html>\n",
+        escape_html(title),
+        escape_html(site_title),
+    )
+}
+
+fn push_doc(body: &mut String, doc: Option<&Value>) {
+    if let Some(doc) = doc.and_then(Value::as_str) {
+        writeln!(body, "<div class=\"doc\">{}</div>", escape_html(doc))
+            .expect("write to String is infallible");
+    }
+}
+
+fn push_aliases(body: &mut String, obj: &Map<String, Value>) {
+    let aliases: Vec<String> = string_array(obj, "aliases")
+        .map(|alias| format!("<code>{}</code>", escape_html(alias)))
+        .collect();
+    if !aliases.is_empty() {
+        writeln!(body, "<p>Aliases: {}</p>", aliases.join(", "))
+            .expect("write to String is infallible");
+    }
+}
+
+// ==============================================================================
+// Markdown
+// ==============================================================================
+
+/// Render a single Markdown file for a compiled `.avpr` or `.avsc` JSON
+/// value: the protocol's doc comment, a table of named types, the message
+/// signatures, and then a section per named type, each with an anchor named
+/// after the type's full name.
+pub(crate) fn markdown_page(json: &Value) -> DocPage {
+    let types = named_types(json);
+    let title = site_title(json);
+    let link = |text: &str| link_names(text, &types, escape_markdown, markdown_link);
+
+    let mut md = format!("# {}\n", escape_markdown(&title));
+    push_markdown_doc(&mut md, json.get("doc"));
+
+    if !types.is_empty() {
+        md.push_str("\n## Types\n\n| Name | Kind | Summary |\n| --- | --- | --- |\n");
+        for (name, named) in &types {
+            let summary = named
+                .obj
+                .get("doc")
+                .and_then(Value::as_str)
+                .and_then(|doc| doc.lines().next())
+                .unwrap_or("");
+            writeln!(
+                md,
+                "| {} | {} | {} |",
+                markdown_link(name),
+                named.kind(),
+                table_cell(summary)
+            )
+            .expect("write to String is infallible");
+        }
+    }
+
+    if let Some(messages) = json.get("messages").and_then(Value::as_object)
+        && !messages.is_empty()
+    {
+        let namespace = json.get("namespace").and_then(Value::as_str);
+        md.push_str("\n## Messages\n");
+        for (name, message) in messages {
+            let signature = message_signature(name, message, namespace);
+            write!(
+                md,
+                "\n### {}\n\n{}\n",
+                escape_markdown(name),
+                link(&signature)
+            )
+            .expect("write to String is infallible");
+            push_markdown_doc(&mut md, message.get("doc"));
+        }
+    }
+
+    for (name, named) in &types {
+        let obj = named.obj;
+        write!(
+            md,
+            "\n<a id=\"{}\"></a>\n\n## {} `{name}`\n",
+            escape_html(name),
+            named.kind()
+        )
+        .expect("write to String is infallible");
+        push_markdown_doc(&mut md, obj.get("doc"));
+        let aliases: Vec<String> = string_array(obj, "aliases")
+            .map(|alias| format!("`{alias}`"))
+            .collect();
+        if !aliases.is_empty() {
+            write!(md, "\nAliases: {}\n", aliases.join(", "))
+                .expect("write to String is infallible");
+        }
+
+        match named.kind() {
+            "record" | "error" => {
+                md.push_str(
+                    "\n| Field | Type | Default | Description |\n| --- | --- | --- | --- |\n",
+                );
+                for (field_name, field) in record_fields(named) {
+                    let field_type = type_string(field_schema(field), named.namespace.as_deref());
+                    let default = field.get("default").map_or_else(String::new, |d| {
+                        format!("`{}`", d.to_string().replace('|', "\\|"))
+                    });
+                    let doc = field.get("doc").and_then(Value::as_str).unwrap_or("");
+                    writeln!(
+                        md,
+                        "| `{field_name}` | {} | {default} | {} |",
+                        link(&field_type),
+                        table_cell(doc)
+                    )
+                    .expect("write to String is infallible");
+                }
+            }
+            "enum" => {
+                let symbols: Vec<String> = string_array(obj, "symbols")
+                    .map(|symbol| format!("`{symbol}`"))
+                    .collect();
+                write!(md, "\nSymbols: {}\n", symbols.join(", "))
+                    .expect("write to String is infallible");
+                if let Some(default) = obj.get("default").and_then(Value::as_str) {
+                    write!(md, "\nDefault: `{default}`\n").expect("write to String is infallible");
+                }
+            }
+            "fixed" => {
+                if let Some(size) = obj.get("size").and_then(Value::as_u64) {
+                    write!(md, "\nSize: {size} bytes\n").expect("write to String is infallible");
+                }
+                if let Some(logical) = logical_type(obj) {
+                    write!(md, "\nLogical type: `{logical}`\n")
+                        .expect("write to String is infallible");
+                }
+            }
+            _ => {}
+        }
+    }
+
+    DocPage {
+        path: format!("{title}.md"),
+        content: md,
+    }
+}
+
+/// Doc comments are often Markdown already, so they are written as is.
+fn push_markdown_doc(md: &mut String, doc: Option<&Value>) {
+    if let Some(doc) = doc.and_then(Value::as_str) {
+        write!(md, "\n{doc}\n").expect("write to String is infallible");
+    }
+}
+
+/// A doc comment squeezed into a single Markdown table cell.
+fn table_cell(doc: &str) -> String {
+    doc.replace('|', "\\|")
+        .lines()
+        .collect::<Vec<_>>()
+        .join("<br>")
+}
+
+// ==============================================================================
+// Doc Comments by Name
+// ==============================================================================
+//
+// For data catalogs and other tools that only want the text: every doc
+// comment, keyed by the full name of what it documents. Types use their full
+// name, fields `<type>.<field>`, and messages `<protocol>.<message>`, with
+// their parameters as `<protocol>.<message>.<parameter>`. The protocol itself
+// uses its full name.
+
+/// Every doc comment in a compiled `.avpr` or `.avsc` JSON value, by the full
+/// name of the protocol, type, field, message, or parameter it documents.
+pub(crate) fn doc_comments(json: &Value) -> BTreeMap<String, String> {
+    let mut docs = BTreeMap::new();
+    let mut insert = |name: String, obj: &Map<String, Value>| {
+        if let Some(doc) = obj.get("doc").and_then(Value::as_str) {
+            docs.insert(name, doc.to_string());
+        }
+    };
+
+    if let Some(protocol) = json.as_object().filter(|obj| obj.contains_key("protocol")) {
+        let title = site_title(json);
+        insert(title.clone(), protocol);
+        for (name, message) in protocol
+            .get("messages")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+        {
+            let Some(message) = message.as_object() else {
+                continue;
+            };
+            let message_name = format!("{title}.{name}");
+            for param in message
+                .get("request")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_object)
+            {
+                if let Some(param_name) = param.get("name").and_then(Value::as_str) {
+                    insert(format!("{message_name}.{param_name}"), param);
+                }
+            }
+            insert(message_name, message);
+        }
+    }
+
+    for (name, named) in &named_types(json) {
+        insert(name.clone(), named.obj);
+        if named.kind() == "record" || named.kind() == "error" {
+            for (field_name, field) in record_fields(named) {
+                insert(format!("{name}.{field_name}"), field);
+            }
+        }
+    }
+    docs
+}
+
+/// The doc comments of [`doc_comments`] as a pretty-printed JSON object, in a
+/// file named after the protocol.
+pub(crate) fn json_page(json: &Value) -> DocPage {
+    let docs: Map<String, Value> = doc_comments(json)
+        .into_iter()
+        .map(|(name, doc)| (name, Value::String(doc)))
+        .collect();
+    let mut content =
+        serde_json::to_string_pretty(&docs).expect("a map of strings serializes to JSON");
+    content.push('\n');
+    DocPage {
+        path: format!("{}.docs.json", site_title(json)),
+        content,
+    }
+}
+
+// ==============================================================================
+// Escaping and Cross-links
+// ==============================================================================
+
+/// A (possibly dotted) name in a type description or message signature.
+static NAME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"[A-Za-z_][A-Za-z0-9_]*(?:\.[A-Za-z_][A-Za-z0-9_]*)*")
+        .expect("constant regex pattern")
+});
+
+/// Escape `text` for use in HTML text or a quoted attribute value.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn html_link(full_name: &str) -> String {
+    format!(
+        "<a href=\"{}\">{}</a>",
+        escape_html(&page_path(full_name)),
+        escape_html(full_name)
+    )
+}
+
+/// Escape `text` for use in Markdown text or a table cell.
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '|' => escaped.push_str("\\|"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn markdown_link(full_name: &str) -> String {
+    format!("[{full_name}](#{full_name})")
+}
+
+/// Escape a type description or message signature, turning every documented
+/// full type name into a link.
+fn link_names(
+    text: &str,
+    types: &BTreeMap<String, NamedType<'_>>,
+    escape: fn(&str) -> String,
+    link: fn(&str) -> String,
+) -> String {
+    let mut linked = String::with_capacity(text.len());
+    let mut last = 0;
+    for name in NAME.find_iter(text) {
+        if !types.contains_key(name.as_str()) {
+            continue;
+        }
+        linked.push_str(&escape(&text[last..name.start()]));
+        linked.push_str(&link(name.as_str()));
+        last = name.end();
+    }
+    linked.push_str(&escape(&text[last..]));
+    linked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Idl;
+
+    fn pages(idl: &'static str) -> BTreeMap<String, String> {
+        let output = Idl::new().convert_str(idl).expect("IDL should compile");
+        output
+            .html_docs()
+            .into_iter()
+            .map(|page| (page.path, page.content))
+            .collect()
+    }
+
+    #[test]
+    fn one_page_per_type_with_cross_links() {
+        let pages = pages(
+            r#"/** Order <management>. */
+            @namespace("ex") protocol Shop {
+                /** Where an order is. */
+                enum Status { NEW, SHIPPED } = NEW;
+                fixed Hash(16);
+                record Order {
+                    /** Unique & stable. */
+                    string id;
+                    array<Status> history = [];
+                    union { null, Hash } checksum = null;
+                }
+                error NotFound { string id; }
+                Order get(string id) throws NotFound;
+            }"#,
+        );
+        assert_eq!(
+            pages.keys().collect::<Vec<_>>(),
+            vec![
+                "ex.Hash.html",
+                "ex.NotFound.html",
+                "ex.Order.html",
+                "ex.Status.html",
+                "index.html"
+            ]
+        );
+
+        let index = &pages["index.html"];
+        assert!(index.contains("<h1>ex.Shop</h1>"));
+        assert!(index.contains("<div class=\"doc\">Order &lt;management&gt;.</div>"));
+        assert!(index.contains(
+            "<a href=\"ex.Status.html\"><code>ex.Status</code></a></td><td>enum</td>\
+             <td>Where an order is.</td>"
+        ));
+        assert!(index.contains(
+            "<pre><code><a href=\"ex.Order.html\">ex.Order</a> get(string id) \
+             throws <a href=\"ex.NotFound.html\">ex.NotFound</a></code></pre>"
+        ));
+
+        let order = &pages["ex.Order.html"];
+        assert!(order.contains("<h1><span class=\"kind\">record</span> ex.Order</h1>"));
+        assert!(order.contains(
+            "<tr id=\"field-id\"><td><code>id</code></td><td><code>string</code></td>\
+             <td></td><td class=\"doc\">Unique &amp; stable.</td></tr>"
+        ));
+        assert!(order.contains(
+            "<code>array&lt;<a href=\"ex.Status.html\">ex.Status</a>&gt;</code></td>\
+             <td><code>[]</code></td>"
+        ));
+        assert!(order.contains(
+            "<code>union { null, <a href=\"ex.Hash.html\">ex.Hash</a> }</code></td>\
+             <td><code>null</code></td>"
+        ));
+
+        let status = &pages["ex.Status.html"];
+        assert!(status.contains("<li><code>SHIPPED</code></li>"));
+        assert!(status.contains("<p>Default: <code>NEW</code></p>"));
+        assert!(pages["ex.Hash.html"].contains("<p>Size: 16 bytes</p>"));
+    }
+
+    #[test]
+    fn markdown_has_anchors_and_field_tables() {
+        let output = Idl::new()
+            .convert_str(
+                r#"@namespace("ex") protocol Shop {
+                    enum Status { NEW, DONE }
+                    record Order {
+                        /** Either | or. */
+                        map<Status> states = {};
+                    }
+                    void ping();
+                }"#,
+            )
+            .expect("IDL should compile");
+        let page = output.markdown_docs();
+        assert_eq!(page.path, "ex.Shop.md");
+        assert_eq!(
+            page.content,
+            "# ex.Shop\n\
+             \n## Types\n\n\
+             | Name | Kind | Summary |\n\
+             | --- | --- | --- |\n\
+             | [ex.Order](#ex.Order) | record |  |\n\
+             | [ex.Status](#ex.Status) | enum |  |\n\
+             \n## Messages\n\
+             \n### ping\n\nnull ping()\n\
+             \n<a id=\"ex.Order\"></a>\n\n## record `ex.Order`\n\
+             \n| Field | Type | Default | Description |\n\
+             | --- | --- | --- | --- |\n\
+             | `states` | map&lt;[ex.Status](#ex.Status)&gt; | `{}` | Either \\| or. |\n\
+             \n<a id=\"ex.Status\"></a>\n\n## enum `ex.Status`\n\
+             \nSymbols: `NEW`, `DONE`\n"
+        );
+    }
+
+    #[test]
+    fn doc_comments_are_keyed_by_full_name() {
+        let output = Idl::new()
+            .convert_str(
+                r#"/** The shop. */
+                @namespace("ex") protocol Shop {
+                    /** An order. */
+                    record Order {
+                        /** Unique. */
+                        string id;
+                        int undocumented;
+                    }
+                    /** Not found. */
+                    error NotFound { /** What was missing. */ string id; }
+                    /** Fetch an order. */
+                    Order get(string id) throws NotFound;
+                    void ping();
+                }"#,
+            )
+            .expect("IDL should compile");
+        let docs = output.doc_comments();
+        assert_eq!(
+            docs.iter()
+                .map(|(name, doc)| (name.as_str(), doc.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("ex.NotFound", "Not found."),
+                ("ex.NotFound.id", "What was missing."),
+                ("ex.Order", "An order."),
+                ("ex.Order.id", "Unique."),
+                ("ex.Shop", "The shop."),
+                ("ex.Shop.get", "Fetch an order."),
+            ]
+        );
+
+        let page = output.json_docs();
+        assert_eq!(page.path, "ex.Shop.docs.json");
+        let parsed: Value = serde_json::from_str(&page.content).unwrap();
+        assert_eq!(parsed["ex.Order.id"], "Unique.");
+    }
+}
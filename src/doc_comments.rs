@@ -1,78 +1,51 @@
-use std::borrow::Borrow;
 use std::collections::HashSet;
 use std::sync::LazyLock;
 
-use antlr4rust::char_stream::InputData;
-use antlr4rust::token::Token;
-use antlr4rust::token_factory::TokenFactory;
-use antlr4rust::token_stream::TokenStream;
 use regex::Regex;
 
-use crate::generated::idlparser::{Idl_DocComment, Idl_EmptyComment, Idl_WS};
+use crate::lexer::{Token, TokenType};
 
 /// Extract the doc comment associated with a parse tree node, given the
-/// token index of the node's start token.
+/// index of the node's first token in the token list.
 ///
-/// Scans backwards from `token_index - 1` through the token stream,
-/// skipping whitespace and empty comments, looking for a `DocComment` token.
+/// Scans backwards from `token_index - 1` through the tokens, skipping
+/// whitespace and other comments, looking for a `DocComment` token.
 ///
 /// If `consumed_indices` is provided, the index of the consumed doc comment
 /// token is recorded so callers can later detect orphaned (unconsumed) doc
 /// comments and generate warnings.
-///
-/// antlr4rust's `CommonTokenStream` does not expose `getHiddenTokensToLeft()`
-/// the way Java ANTLR does [yet](https://github.com/antlr4rust/antlr4/pull/39), but `get(index)`
-/// is public and lets us access any token by index, including hidden-channel tokens.
-pub fn extract_doc_comment<'input, TS>(
-    token_stream: &TS,
-    token_index: isize,
-    consumed_indices: Option<&mut HashSet<isize>>,
-) -> Option<String>
-where
-    TS: TokenStream<'input>,
-{
-    if token_index <= 0 {
-        return None;
-    }
-
-    let mut i = token_index - 1;
-    let mut doc_token_text: Option<String> = None;
-    let mut doc_token_index: Option<isize> = None;
-
-    while i >= 0 {
-        let tok_wrapper = token_stream.get(i);
-        let token: &<TS::TF as TokenFactory<'input>>::Inner = tok_wrapper.borrow();
-        let token_type = token.get_token_type();
-
-        if token_type == Idl_DocComment {
-            doc_token_text = Some(token.get_text().to_display());
-            doc_token_index = Some(i);
-            break;
-        } else if token_type == Idl_WS || token_type == Idl_EmptyComment {
-            // Skip whitespace and empty comments, continue scanning.
-            i -= 1;
-            continue;
-        } else {
-            // Hit a non-hidden, non-doc token -- no doc comment for this node.
-            break;
-        }
-    }
-
-    let text = doc_token_text?;
+pub fn extract_doc_comment(
+    tokens: &[Token],
+    source: &str,
+    token_index: usize,
+    consumed_indices: Option<&mut HashSet<usize>>,
+) -> Option<String> {
+    let doc_token_index = tokens[..token_index.min(tokens.len())]
+        .iter()
+        .rposition(|token| {
+            !matches!(
+                token.token_type,
+                TokenType::WS
+                    | TokenType::EmptyComment
+                    | TokenType::MultiLineComment
+                    | TokenType::SingleLineComment
+            )
+        })
+        // Hit a non-hidden, non-doc token -- no doc comment for this node.
+        .filter(|&i| tokens[i].token_type == TokenType::DocComment)?;
+    let text = &source[tokens[doc_token_index].span.clone()];
 
     // Record the consumed token index so we can later detect orphaned doc
     // comments (those not consumed by any declaration).
-    if let Some(consumed) = consumed_indices
-        && let Some(idx) = doc_token_index
-    {
-        consumed.insert(idx);
+    if let Some(consumed) = consumed_indices {
+        consumed.insert(doc_token_index);
     }
 
     // Strip the /** prefix and */ suffix.
     let inner = text
         .strip_prefix("/**")
         .and_then(|s| s.strip_suffix("*/"))
-        .unwrap_or(&text);
+        .unwrap_or(text);
     let trimmed = inner.trim();
 
     if trimmed.is_empty() {
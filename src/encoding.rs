@@ -0,0 +1,153 @@
+// ==============================================================================
+// Source Encoding Detection
+// ==============================================================================
+//
+// IDL and JSON sources are UTF-8, but schemas exported from older tools are
+// sometimes UTF-16 (mostly on Windows) or a single-byte encoding such as
+// Latin-1. Reading those as UTF-8 either fails outright or, after lossy
+// decoding, produces a cascade of token recognition errors. UTF-16 is
+// unambiguous enough to transcode; anything else that is not UTF-8 is
+// reported with the position of the first offending byte.
+
+use std::fmt;
+
+/// Why a source could not be decoded.
+#[derive(Debug)]
+pub(crate) enum EncodingError {
+    /// Not UTF-8 or UTF-16, e.g. Latin-1.
+    NotUtf8 {
+        byte: u8,
+        line: usize,
+        column: usize,
+    },
+    /// UTF-16 with an unpaired surrogate.
+    InvalidUtf16,
+}
+
+impl fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodingError::NotUtf8 { byte, line, column } => write!(
+                f,
+                "unsupported encoding: byte 0x{byte:02X} at line {line}, column {column} is not \
+                 valid UTF-8 (the file may be Latin-1 or Windows-1252)"
+            ),
+            EncodingError::InvalidUtf16 => {
+                write!(
+                    f,
+                    "unsupported encoding: the file looks like UTF-16 but is not valid UTF-16"
+                )
+            }
+        }
+    }
+}
+
+/// Decode the bytes of a source file: UTF-8 as is, and UTF-16 (with a byte
+/// order mark, or detected from its zero bytes) transcoded to UTF-8. A UTF-8
+/// byte order mark is kept; the reader skips it.
+pub(crate) fn decode_source(bytes: Vec<u8>) -> Result<String, EncodingError> {
+    if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        return decode_utf16(rest, u16::from_le_bytes);
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        return decode_utf16(rest, u16::from_be_bytes);
+    }
+    match String::from_utf8(bytes) {
+        Ok(text) => Ok(text),
+        Err(err) => {
+            let bytes = err.into_bytes();
+            match utf16_without_bom(&bytes) {
+                Some(true) => decode_utf16(&bytes, u16::from_le_bytes),
+                Some(false) => decode_utf16(&bytes, u16::from_be_bytes),
+                None => Err(not_utf8(&bytes)),
+            }
+        }
+    }
+}
+
+/// Guess whether BOM-less bytes are UTF-16: mostly-ASCII text in UTF-16 has a
+/// zero in one byte of nearly every pair and never in the other. Returns
+/// `Some(true)` for little-endian, `Some(false)` for big-endian.
+fn utf16_without_bom(bytes: &[u8]) -> Option<bool> {
+    if bytes.len() < 2 || !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let pairs = bytes.len() / 2;
+    let zeros_at = |parity: usize| {
+        bytes
+            .iter()
+            .skip(parity)
+            .step_by(2)
+            .filter(|&&b| b == 0)
+            .count()
+    };
+    let (even, odd) = (zeros_at(0), zeros_at(1));
+    if odd * 2 >= pairs && even == 0 {
+        Some(true)
+    } else if even * 2 >= pairs && odd == 0 {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> Result<String, EncodingError> {
+    if !bytes.len().is_multiple_of(2) {
+        return Err(EncodingError::InvalidUtf16);
+    }
+    let units = bytes.chunks_exact(2).map(|pair| unit([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .map_err(|_| EncodingError::InvalidUtf16)
+}
+
+/// Locate the first byte that is not valid UTF-8, by line and column (in
+/// characters, both 1-based).
+fn not_utf8(bytes: &[u8]) -> EncodingError {
+    let valid_up_to = match std::str::from_utf8(bytes) {
+        Ok(_) => bytes.len(),
+        Err(err) => err.valid_up_to(),
+    };
+    let valid = std::str::from_utf8(&bytes[..valid_up_to]).unwrap_or_default();
+    let line = valid.matches('\n').count() + 1;
+    let line_start = valid.rfind('\n').map_or(0, |i| i + 1);
+    EncodingError::NotUtf8 {
+        byte: bytes.get(valid_up_to).copied().unwrap_or_default(),
+        line,
+        column: valid[line_start..].chars().count() + 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn utf16le(text: &str, bom: bool) -> Vec<u8> {
+        let mut bytes = if bom { vec![0xFF, 0xFE] } else { Vec::new() };
+        bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        bytes
+    }
+
+    #[test]
+    fn utf16_is_transcoded() {
+        let text = "protocol P { record Café { string s; } }";
+        assert_eq!(decode_source(utf16le(text, true)).unwrap(), text);
+        assert_eq!(decode_source(utf16le(text, false)).unwrap(), text);
+        let be: Vec<u8> = [0xFE, 0xFF]
+            .into_iter()
+            .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+            .collect();
+        assert_eq!(decode_source(be).unwrap(), text);
+    }
+
+    #[test]
+    fn latin1_is_reported_with_its_position() {
+        let err = decode_source(b"protocol P {\n  // caf\xE9\n}".to_vec()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unsupported encoding: byte 0xE9 at line 2, column 9 is not valid UTF-8 \
+             (the file may be Latin-1 or Windows-1252)"
+        );
+    }
+}
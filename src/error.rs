@@ -1,3 +1,6 @@
+use std::collections::HashSet;
+use std::sync::{LazyLock, Mutex, PoisonError};
+
 use miette::{Diagnostic, LabeledSpan, MietteError, MietteSpanContents, SourceCode, SourceSpan};
 
 /// A source span paired with the file it points into.
@@ -60,6 +63,154 @@ impl SourceCode for SpanWithSource {
     }
 }
 
+/// Keep `text`, a source or file name, for the rest of the process, so that
+/// spans can point into it.
+///
+/// Each distinct text is kept once: keeping text that is already kept returns
+/// the earlier copy, so compiling the same files again and again (an editor,
+/// watch mode, a web page) does not grow memory with every compile.
+pub(crate) fn keep_source(text: String) -> &'static str {
+    static KEPT: LazyLock<Mutex<HashSet<&'static str>>> = LazyLock::new(Default::default);
+    let mut kept = KEPT.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(&text) = kept.get(text.as_str()) {
+        return text;
+    }
+    let text: &'static str = text.leak();
+    kept.insert(text);
+    text
+}
+
+/// A category of error, which fixes its stable diagnostic code (see
+/// [`explain`](crate::explain)).
+///
+/// Every error is given its kind where it is built, so that rewording a
+/// message never changes its code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// Source that does not follow the IDL grammar.
+    Syntax,
+    /// A reference to a type that is not defined.
+    UndefinedName,
+    /// Two named types with the same full name.
+    DuplicateType,
+    /// Two fields of a record, or parameters of a message, with one name.
+    DuplicateField,
+    /// An enum symbol listed twice.
+    DuplicateSymbol,
+    /// A type that appears twice in one union.
+    DuplicateInUnion,
+    /// A union directly inside another union.
+    NestedUnion,
+    /// A protocol, type, or namespace name that is not a valid Avro name.
+    InvalidName,
+    /// A default value that does not match its field's type.
+    InvalidDefault,
+    /// An enum default that is not one of the enum's symbols.
+    EnumDefault,
+    /// An annotation on a reference to a named type.
+    AnnotatedReference,
+    /// An annotation that sets a property Avro itself defines.
+    ReservedProperty,
+    /// An annotation Avro interprets whose value has the wrong form.
+    InvalidAnnotation,
+    /// A one-way message whose response is not `void`.
+    OnewayResponse,
+    /// A `fixed` size that is not a non-negative 32-bit integer.
+    InvalidFixedSize,
+    /// A decimal with an impossible precision or scale.
+    InvalidDecimal,
+    /// An IDL file compiled as one protocol or schema that declares neither.
+    NoDeclaration,
+    /// An imported file that cannot be found.
+    ImportNotFound,
+    /// An imported file that was found but cannot be imported.
+    InvalidImport,
+    /// A public type or message that references an internal type.
+    InternalTypeLeak,
+    /// A file that cannot be read.
+    ReadFile,
+    /// Warnings turned into an error by
+    /// [`Idl::deny_warnings`](crate::Idl::deny_warnings).
+    DeniedWarnings,
+    /// A file that is neither UTF-8 nor UTF-16.
+    Encoding,
+    /// Input nested more deeply than the nesting limit.
+    NestingTooDeep,
+}
+
+impl ErrorKind {
+    /// Every error kind, in the order listed above.
+    pub const ALL: [ErrorKind; 24] = [
+        ErrorKind::Syntax,
+        ErrorKind::UndefinedName,
+        ErrorKind::DuplicateType,
+        ErrorKind::DuplicateField,
+        ErrorKind::DuplicateSymbol,
+        ErrorKind::DuplicateInUnion,
+        ErrorKind::NestedUnion,
+        ErrorKind::InvalidName,
+        ErrorKind::InvalidDefault,
+        ErrorKind::EnumDefault,
+        ErrorKind::AnnotatedReference,
+        ErrorKind::ReservedProperty,
+        ErrorKind::InvalidAnnotation,
+        ErrorKind::OnewayResponse,
+        ErrorKind::InvalidFixedSize,
+        ErrorKind::InvalidDecimal,
+        ErrorKind::NoDeclaration,
+        ErrorKind::ImportNotFound,
+        ErrorKind::InvalidImport,
+        ErrorKind::InternalTypeLeak,
+        ErrorKind::ReadFile,
+        ErrorKind::DeniedWarnings,
+        ErrorKind::Encoding,
+        ErrorKind::NestingTooDeep,
+    ];
+
+    /// The stable diagnostic code, e.g. `"AVDL0004"`.
+    #[must_use]
+    pub fn code(self) -> &'static str {
+        match self {
+            ErrorKind::Syntax => "AVDL0001",
+            ErrorKind::UndefinedName => "AVDL0002",
+            ErrorKind::DuplicateType => "AVDL0003",
+            ErrorKind::DuplicateField => "AVDL0004",
+            ErrorKind::DuplicateSymbol => "AVDL0005",
+            ErrorKind::DuplicateInUnion => "AVDL0006",
+            ErrorKind::NestedUnion => "AVDL0007",
+            ErrorKind::InvalidName => "AVDL0008",
+            ErrorKind::InvalidDefault => "AVDL0009",
+            ErrorKind::EnumDefault => "AVDL0010",
+            ErrorKind::AnnotatedReference => "AVDL0011",
+            ErrorKind::ReservedProperty => "AVDL0012",
+            ErrorKind::InvalidAnnotation => "AVDL0013",
+            ErrorKind::OnewayResponse => "AVDL0014",
+            ErrorKind::InvalidFixedSize => "AVDL0015",
+            ErrorKind::InvalidDecimal => "AVDL0016",
+            ErrorKind::NoDeclaration => "AVDL0017",
+            ErrorKind::ImportNotFound => "AVDL0018",
+            ErrorKind::InvalidImport => "AVDL0019",
+            ErrorKind::InternalTypeLeak => "AVDL0020",
+            ErrorKind::ReadFile => "AVDL0021",
+            ErrorKind::DeniedWarnings => "AVDL0022",
+            ErrorKind::Encoding => "AVDL0023",
+            ErrorKind::NestingTooDeep => "AVDL0024",
+        }
+    }
+
+    /// The kind with the given code, e.g. from [`miette::Diagnostic::code`].
+    #[must_use]
+    pub fn from_code(code: &str) -> Option<ErrorKind> {
+        ErrorKind::ALL.into_iter().find(|kind| kind.code() == code)
+    }
+
+    /// The kind of `error`, from the code it was built with.
+    #[must_use]
+    pub fn of(error: &miette::Report) -> Option<ErrorKind> {
+        ErrorKind::from_code(&error.code()?.to_string())
+    }
+}
+
 /// A parse error with source location information for rich diagnostics.
 ///
 /// The `message` field is used for the top-level `Display` text (the line after
@@ -72,6 +223,8 @@ impl SourceCode for SpanWithSource {
 /// source-underline label.
 #[derive(Debug)]
 pub struct ParseDiagnostic {
+    /// What kind of error this is, which fixes its diagnostic code.
+    pub kind: ErrorKind,
     pub span: SpanWithSource,
     pub message: String,
     /// Shorter label for the source-underline annotation. When `None`, falls
@@ -85,6 +238,12 @@ pub struct ParseDiagnostic {
     /// errors, multiple unresolved type references) so users can fix them all
     /// in one edit cycle.
     pub related: Vec<ParseDiagnostic>,
+    /// The name the error is about, where there is one: the full name of an
+    /// undefined type, or the key of a repeated union branch.
+    pub name: Option<String>,
+    /// Text that fixes the error when it replaces the span, where there is
+    /// exactly one such fix.
+    pub replacement: Option<String>,
 }
 
 impl std::fmt::Display for ParseDiagnostic {
@@ -112,6 +271,10 @@ pub(crate) fn render_diagnostic(report: &miette::Report) -> String {
 }
 
 impl miette::Diagnostic for ParseDiagnostic {
+    fn code<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        Some(Box::new(self.kind.code()))
+    }
+
     fn source_code(&self) -> Option<&dyn SourceCode> {
         Some(&self.span)
     }
@@ -0,0 +1,362 @@
+// ==============================================================================
+// Automatic Fixes
+// ==============================================================================
+//
+// `avdl fix` rewrites a file to get past the errors that have exactly one
+// reasonable repair. Every fix starts from a diagnostic the compiler already
+// reports, and edits the source at that diagnostic's span:
+//
+//   - `Undefined name` (AVDL0002), when a file under the import directories
+//     defines exactly that name: insert the `import` statement that reads it.
+//   - An identifier where a string is expected, such as `Color c = RED;`:
+//     quote it, with the replacement the reader attaches to the diagnostic.
+//   - `Duplicate in union` (AVDL0006): remove the repeated branch.
+//
+// Compilation stops at the first stage that fails, so fixing one error can
+// uncover the next. The source is recompiled after each round of fixes until
+// it compiles or nothing left is fixable.
+
+use std::collections::HashSet;
+use std::ops::Range;
+
+use crate::error::{ErrorKind, ParseDiagnostic};
+use crate::import::ImportContext;
+use crate::syntax::{SyntaxElement, SyntaxNode, syntax_tree};
+
+/// How many rounds of compiling and fixing to make at most. Each round fixes
+/// at least one error and compiles a source not compiled before, so this only
+/// bounds pathological sources.
+const MAX_ROUNDS: usize = 100;
+
+/// A fix applied by [`Idl::fix`](crate::Idl::fix).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedFix {
+    /// The code of the diagnostic the fix addresses, such as `AVDL0002`.
+    pub code: &'static str,
+    /// What the fix changed, such as ``add `import idl "common.avdl";` ``.
+    pub description: String,
+}
+
+/// The result of [`Idl::fix`](crate::Idl::fix).
+#[derive(Debug)]
+pub struct FixOutput {
+    /// The source with every fix applied; the input unchanged if there was
+    /// nothing to fix.
+    pub content: String,
+    /// The fixes applied, in the order they were made.
+    pub fixes: Vec<AppliedFix>,
+    /// The error the fixed source still fails to compile with, if any.
+    pub remaining_error: Option<miette::Report>,
+}
+
+/// Replace `range` of the source with `replacement`.
+struct Edit {
+    range: Range<usize>,
+    replacement: String,
+    fix: AppliedFix,
+}
+
+/// Fix `source`, which `compile` compiles. `import_ctx` supplies the import
+/// directories to look for undefined names in.
+pub(crate) fn fix(
+    source: &str,
+    import_ctx: &ImportContext,
+    mut compile: impl FnMut(&'static str) -> miette::Result<()>,
+) -> FixOutput {
+    // Spans are reported against the source without its byte order mark.
+    let (bom, source) = match source.strip_prefix('\u{feff}') {
+        Some(rest) => ("\u{feff}", rest),
+        None => ("", source),
+    };
+    let mut content = source.to_string();
+    let mut fixes = Vec::new();
+    // Diagnostics point into the source they were compiled from for
+    // `'static`, so every source compiled is leaked. Each is compiled once:
+    // fixes that lead back to a source already compiled end the loop.
+    let mut compiled: HashSet<&'static str> = HashSet::new();
+    let remaining_error = loop {
+        let current: &'static str = content.clone().leak();
+        compiled.insert(current);
+        let error = match compile(current) {
+            Ok(()) => break None,
+            Err(error) => error,
+        };
+        let mut edits = edits_for(&error, current, import_ctx);
+        if edits.is_empty() || compiled.len() > MAX_ROUNDS {
+            break Some(error);
+        }
+
+        // Apply back to front so the ranges still to apply stay valid, and
+        // skip any edit that overlaps one already applied; the next round
+        // reports its error again if it still stands.
+        edits.sort_by_key(|edit| std::cmp::Reverse(edit.range.start));
+        edits.dedup_by(|a, b| a.range == b.range && a.replacement == b.replacement);
+        let mut fixed = content.clone();
+        let mut applied = Vec::new();
+        let mut applied_from = usize::MAX;
+        for edit in edits {
+            if edit.range.end > applied_from {
+                continue;
+            }
+            fixed.replace_range(edit.range.clone(), &edit.replacement);
+            applied_from = edit.range.start;
+            applied.push(edit.fix);
+        }
+        if compiled.contains(fixed.as_str()) {
+            break Some(error);
+        }
+        content = fixed;
+        fixes.extend(applied.into_iter().rev());
+    };
+    content.insert_str(0, bom);
+    FixOutput {
+        content,
+        fixes,
+        remaining_error,
+    }
+}
+
+/// The edits that fix `error` and the errors related to it, where they are
+/// in `source` and fixable.
+fn edits_for(error: &miette::Report, source: &str, import_ctx: &ImportContext) -> Vec<Edit> {
+    let Some(diagnostic) = error.downcast_ref::<ParseDiagnostic>() else {
+        return Vec::new();
+    };
+    let tree = syntax_tree(source);
+    std::iter::once(diagnostic)
+        .chain(&diagnostic.related)
+        // Errors in imported files are theirs to fix.
+        .filter(|diagnostic| diagnostic.span.content == source)
+        .filter_map(|diagnostic| edit_for(diagnostic, source, &tree, import_ctx))
+        .collect()
+}
+
+/// The edit that fixes a single diagnostic, if it is one with a safe fix.
+fn edit_for(
+    diagnostic: &ParseDiagnostic,
+    source: &str,
+    tree: &SyntaxNode,
+    import_ctx: &ImportContext,
+) -> Option<Edit> {
+    let span = diagnostic.span.offset..diagnostic.span.offset + diagnostic.span.length;
+    let code = diagnostic.kind.code();
+    let text = source.get(span.clone())?;
+
+    match diagnostic.kind {
+        ErrorKind::UndefinedName => {
+            // Only a file defining exactly this name fixes the reference as
+            // written; one defining it in another namespace would also need
+            // the reference renamed.
+            let full_name = diagnostic.name.as_deref()?;
+            let suggestions = import_ctx.suggest_imports(full_name);
+            let [suggestion] = suggestions.as_slice() else {
+                return None;
+            };
+            if suggestion.full_name != full_name {
+                return None;
+            }
+            let (offset, replacement) = import_insertion(source, tree, &suggestion.statement);
+            Some(Edit {
+                range: offset..offset,
+                replacement,
+                fix: AppliedFix {
+                    code,
+                    description: format!("add `{}` for `{full_name}`", suggestion.statement),
+                },
+            })
+        }
+        ErrorKind::DuplicateInUnion => {
+            let key = diagnostic.name.as_deref()?;
+            Some(Edit {
+                range: duplicate_branch(tree, span.start)?,
+                replacement: String::new(),
+                fix: AppliedFix {
+                    code,
+                    description: format!("remove the duplicate `{key}` from a union"),
+                },
+            })
+        }
+        // Errors the reader knows the one fix for, such as an identifier
+        // where a string is expected.
+        _ => {
+            let replacement = diagnostic.replacement.clone()?;
+            Some(Edit {
+                range: span,
+                fix: AppliedFix {
+                    code,
+                    description: format!("replace `{text}` with `{replacement}`"),
+                },
+                replacement,
+            })
+        }
+    }
+}
+
+/// Where to insert `statement` into `source`, and the text to insert there:
+/// on a line after the last import if there is one, otherwise at the start of
+/// the protocol body or after a schema file's `namespace` and `schema`
+/// declarations.
+fn import_insertion(source: &str, tree: &SyntaxNode, statement: &str) -> (usize, String) {
+    let descendants = tree.descendants();
+    if let Some(last) = descendants
+        .iter()
+        .rev()
+        .find(|node| node.kind == "importStatement")
+    {
+        let indent = line_indent(source, last.span.start);
+        return (last.span.end, format!("\n{indent}{statement}"));
+    }
+
+    if let Some(body) = descendants
+        .iter()
+        .find(|node| node.kind == "protocolDeclarationBody")
+    {
+        // The body starts with its `{`. Put the import on a line of its own,
+        // indented like the first member, unless the protocol is on one line.
+        let open = body.span.start + 1;
+        let next = body
+            .tokens()
+            .into_iter()
+            .find(|token| token.span.start >= open && token.kind != "WS");
+        return match next {
+            Some(next) if source[open..next.span.start].contains('\n') => {
+                let indent = if next.kind == "RBrace" {
+                    format!("{}    ", line_indent(source, next.span.start))
+                } else {
+                    line_indent(source, next.span.start).to_string()
+                };
+                (open, format!("\n{indent}{statement}"))
+            }
+            _ => (open, format!(" {statement}")),
+        };
+    }
+
+    let header = ["namespaceDeclaration", "mainSchemaDeclaration"]
+        .iter()
+        .filter_map(|kind| tree.child(kind))
+        .map(|node| node.span.end)
+        .max();
+    match header {
+        Some(end) => (end, format!("\n{statement}")),
+        None => (0, format!("{statement}\n")),
+    }
+}
+
+/// The range to delete to remove the union branch at `offset`: from the end
+/// of the branch before it through the end of the branch, so the separating
+/// comma goes too. `None` unless the branch is a single type written directly
+/// in the union; a nested union flattened into its parent is left alone.
+fn duplicate_branch(tree: &SyntaxNode, offset: usize) -> Option<Range<usize>> {
+    for union in tree.descendants() {
+        if union.kind != "unionType" {
+            continue;
+        }
+        let branches: Vec<&SyntaxNode> = union
+            .children
+            .iter()
+            .filter_map(|child| match child {
+                SyntaxElement::Node(node) if node.kind == "fullType" => Some(node),
+                _ => None,
+            })
+            .collect();
+        let Some(index) = branches
+            .iter()
+            .position(|branch| branch.span.contains(&offset))
+        else {
+            continue;
+        };
+        let branch = branches[index];
+        if index == 0
+            || branch
+                .descendants()
+                .iter()
+                .any(|node| node.kind == "unionType")
+        {
+            return None;
+        }
+        return Some(branches[index - 1].span.end..branch.span.end);
+    }
+    None
+}
+
+/// The spaces and tabs that start the line containing `offset`.
+fn line_indent(source: &str, offset: usize) -> &str {
+    let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line = &source[line_start..];
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Idl;
+    use pretty_assertions::assert_eq;
+
+    fn fix_str(source: &'static str) -> FixOutput {
+        Idl::new().fix_str(source)
+    }
+
+    #[test]
+    fn quotes_bare_enum_defaults() {
+        let output = fix_str(
+            "protocol P {\n  enum Color { RED, GREEN }\n  record R { Color c = GREEN; }\n}\n",
+        );
+        assert_eq!(
+            output.content,
+            "protocol P {\n  enum Color { RED, GREEN }\n  record R { Color c = \"GREEN\"; }\n}\n"
+        );
+        assert_eq!(output.fixes.len(), 1);
+        assert_eq!(
+            output.fixes[0].description,
+            "replace `GREEN` with `\"GREEN\"`"
+        );
+        assert!(output.remaining_error.is_none());
+    }
+
+    #[test]
+    fn removes_duplicate_union_branches() {
+        let output =
+            fix_str("protocol P { record R { union { null, string, null, int, string } u; } }");
+        assert_eq!(
+            output.content,
+            "protocol P { record R { union { null, string, int } u; } }"
+        );
+        assert_eq!(output.fixes.len(), 2);
+        assert!(output.remaining_error.is_none());
+    }
+
+    #[test]
+    fn inserts_imports_from_import_dirs() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        std::fs::write(
+            dir.path().join("common.avdl"),
+            r#"@namespace("ex") protocol C { record Address { string street; } }"#,
+        )
+        .expect("write .avdl");
+        let output = Idl::new()
+            .import_dir(dir.path())
+            .fix_str("@namespace(\"ex\")\nprotocol P {\n    record R { Address home; }\n}\n");
+        assert_eq!(
+            output.content,
+            "@namespace(\"ex\")\nprotocol P {\n    import idl \"common.avdl\";\n    \
+             record R { Address home; }\n}\n"
+        );
+        assert_eq!(
+            output.fixes,
+            vec![AppliedFix {
+                code: "AVDL0002",
+                description: "add `import idl \"common.avdl\";` for `ex.Address`".to_string(),
+            }]
+        );
+        assert!(output.remaining_error.is_none());
+    }
+
+    #[test]
+    fn leaves_errors_without_a_safe_fix() {
+        let source = "protocol P { record R { Missing m; } }";
+        let output = fix_str(source);
+        assert_eq!(output.content, source);
+        assert!(output.fixes.is_empty());
+        assert!(output.remaining_error.is_some());
+    }
+}
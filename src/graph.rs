@@ -0,0 +1,503 @@
+// ==============================================================================
+// Type Dependency Graph
+// ==============================================================================
+//
+// Records which named types reference which: a record depends on the named
+// types of its fields, and a protocol message depends on the named types of
+// its request parameters, response, and declared errors. Only named types
+// (record, error, enum, fixed) appear as targets; primitives, arrays, maps,
+// and unions are looked through.
+//
+// The graph is built from the resolved schema registry, after imports, so
+// imported types are included as nodes like any other.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+
+use crate::model::protocol::Protocol;
+use crate::model::schema::{AvroSchema, make_full_name};
+use crate::reader::IdlFile;
+use crate::resolve::SchemaRegistry;
+
+/// A node in a [`TypeGraph`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GraphNode {
+    /// A named type, identified by its full name.
+    Type(String),
+    /// A protocol message, identified by its name.
+    Message(String),
+}
+
+/// A dependency edge: `from` references the named type `to`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeEdge {
+    pub from: GraphNode,
+    /// Full name of the referenced type.
+    pub to: String,
+}
+
+/// Which named types reference which, as returned by
+/// [`IdlOutput::type_graph`](crate::IdlOutput::type_graph).
+///
+/// Nodes are listed in declaration order (types first, then messages sorted
+/// by name). Each node's outgoing edges are deduplicated and listed in the
+/// order the references appear.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TypeGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<TypeEdge>,
+}
+
+impl TypeGraph {
+    /// Build the graph for all registered types and, if present, the
+    /// protocol's messages.
+    pub(crate) fn build(registry: &SchemaRegistry, protocol: Option<&Protocol>) -> Self {
+        let mut graph = TypeGraph::default();
+
+        for schema in registry.schemas() {
+            let Some(full_name) = schema.full_name() else {
+                continue;
+            };
+            let node = GraphNode::Type(full_name.into_owned());
+            graph.add_node(node, type_references(schema));
+        }
+
+        if let Some(protocol) = protocol {
+            // Sort by name, so the graph does not change when messages are
+            // reordered.
+            let mut messages: Vec<_> = protocol.messages.iter().collect();
+            messages.sort_by_key(|(name, _)| name.as_str());
+            for (name, message) in messages {
+                let mut targets = Vec::new();
+                for param in &message.request {
+                    collect_named_refs(&param.schema, &mut targets);
+                }
+                collect_named_refs(&message.response, &mut targets);
+                for error in message.errors.iter().flatten() {
+                    collect_named_refs(error, &mut targets);
+                }
+                graph.add_node(GraphNode::Message(name.clone()), targets);
+            }
+        }
+
+        graph
+    }
+
+    fn add_node(&mut self, node: GraphNode, targets: Vec<String>) {
+        let mut seen = HashSet::new();
+        for to in targets {
+            if seen.insert(to.clone()) {
+                self.edges.push(TypeEdge {
+                    from: node.clone(),
+                    to,
+                });
+            }
+        }
+        self.nodes.push(node);
+    }
+
+    /// Render the graph in Graphviz DOT format, as a digraph called
+    /// `graph_name`. Types are drawn as boxes and messages as ellipses.
+    #[must_use]
+    pub fn to_dot(&self, graph_name: &str) -> String {
+        let mut out = String::new();
+        writeln!(out, "digraph {} {{", dot_quote(graph_name))
+            .expect("write to String is infallible");
+        writeln!(out, "  node [shape=box];").expect("write to String is infallible");
+        for node in &self.nodes {
+            match node {
+                GraphNode::Type(name) => {
+                    writeln!(out, "  {};", dot_quote(name)).expect("write to String is infallible");
+                }
+                GraphNode::Message(name) => {
+                    writeln!(
+                        out,
+                        "  {} [shape=ellipse, label={}];",
+                        dot_quote(&dot_id(node)),
+                        dot_quote(name)
+                    )
+                    .expect("write to String is infallible");
+                }
+            }
+        }
+        for edge in &self.edges {
+            writeln!(
+                out,
+                "  {} -> {};",
+                dot_quote(&dot_id(&edge.from)),
+                dot_quote(&edge.to)
+            )
+            .expect("write to String is infallible");
+        }
+        out.push('}');
+        out
+    }
+}
+
+/// DOT node identifier. Message identifiers are prefixed so that a message
+/// and a type with the same name stay distinct nodes.
+fn dot_id(node: &GraphNode) -> String {
+    match node {
+        GraphNode::Type(name) => name.clone(),
+        GraphNode::Message(name) => format!("message:{name}"),
+    }
+}
+
+/// Quote a string as a DOT identifier.
+fn dot_quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Full names of the registered types that nothing references: no other
+/// type's fields, no protocol message, and not the `schema` declaration of a
+/// schema file. A type that only references itself counts as unreferenced.
+/// Listed in registration order.
+pub(crate) fn unreferenced_types(registry: &SchemaRegistry, idl_file: &IdlFile) -> Vec<String> {
+    let (protocol, root) = match idl_file {
+        IdlFile::Protocol(protocol) => (Some(protocol), None),
+        IdlFile::Schema(schema) => (None, Some(schema)),
+        IdlFile::NamedSchemas(_) => (None, None),
+    };
+    let graph = TypeGraph::build(registry, protocol);
+
+    let mut referenced: HashSet<&str> = graph
+        .edges
+        .iter()
+        .filter(|edge| !matches!(&edge.from, GraphNode::Type(from) if *from == edge.to))
+        .map(|edge| edge.to.as_str())
+        .collect();
+    let mut roots = Vec::new();
+    if let Some(root) = root {
+        collect_named_refs(root, &mut roots);
+    }
+    referenced.extend(roots.iter().map(String::as_str));
+
+    graph
+        .nodes
+        .iter()
+        .filter_map(|node| match node {
+            GraphNode::Type(name) if !referenced.contains(name.as_str()) => Some(name.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Cycles of records that can never hold a value, because each record on the
+/// cycle requires the next through a field with no null, array, or map to end
+/// the recursion. Each cycle is listed once, as the full names along it,
+/// starting and ending with the member registered first.
+pub(crate) fn unguarded_cycles(registry: &SchemaRegistry) -> Vec<Vec<String>> {
+    // A record can hold a value once every one of its fields can; iterate
+    // until no more records qualify. Whatever is left is stuck on a cycle or
+    // requires a record that is.
+    let mut constructible: HashSet<String> = HashSet::new();
+    loop {
+        let before = constructible.len();
+        for schema in registry.schemas() {
+            if let AvroSchema::Record { fields, .. } = schema
+                && let Some(name) = schema.full_name()
+                && !constructible.contains(name.as_ref())
+                && fields
+                    .iter()
+                    .all(|f| is_constructible(&f.schema, registry, &constructible))
+            {
+                constructible.insert(name.into_owned());
+            }
+        }
+        if constructible.len() == before {
+            break;
+        }
+    }
+
+    let mut reported: HashSet<String> = HashSet::new();
+    let mut cycles = Vec::new();
+    for schema in registry.schemas() {
+        let AvroSchema::Record { .. } = schema else {
+            continue;
+        };
+        let Some(start) = schema.full_name() else {
+            continue;
+        };
+        if constructible.contains(start.as_ref()) || reported.contains(start.as_ref()) {
+            continue;
+        }
+        // Follow the first field that cannot hold a value. A record that
+        // only leads into a cycle is skipped; the cycle is reported from
+        // one of its own members.
+        let mut path = vec![start.into_owned()];
+        while let Some(next) = registry
+            .lookup(&path[path.len() - 1])
+            .and_then(|schema| blocking_record(schema, registry, &constructible))
+        {
+            if next == path[0] {
+                reported.extend(path.iter().cloned());
+                path.push(next);
+                cycles.push(path);
+                break;
+            }
+            if path.contains(&next) {
+                break;
+            }
+            path.push(next);
+        }
+    }
+    cycles
+}
+
+/// Whether `schema` has some value, given the records known to have one.
+fn is_constructible(
+    schema: &AvroSchema,
+    registry: &SchemaRegistry,
+    constructible: &HashSet<String>,
+) -> bool {
+    match schema {
+        AvroSchema::Reference {
+            name, namespace, ..
+        } => {
+            let full_name = make_full_name(name, namespace.as_deref());
+            match registry.lookup(&full_name) {
+                Some(AvroSchema::Record { .. }) => constructible.contains(full_name.as_ref()),
+                _ => true,
+            }
+        }
+        AvroSchema::Record { .. } => schema
+            .full_name()
+            .is_some_and(|name| constructible.contains(name.as_ref())),
+        AvroSchema::Union { types, .. } => types
+            .iter()
+            .any(|t| is_constructible(t, registry, constructible)),
+        _ => true,
+    }
+}
+
+/// The full name of the record that keeps `schema`, a record, from holding a
+/// value: the target of its first field that cannot hold one.
+fn blocking_record(
+    schema: &AvroSchema,
+    registry: &SchemaRegistry,
+    constructible: &HashSet<String>,
+) -> Option<String> {
+    let AvroSchema::Record { fields, .. } = schema else {
+        return None;
+    };
+    let field = fields
+        .iter()
+        .find(|f| !is_constructible(&f.schema, registry, constructible))?;
+    // A union that cannot hold a value has only such branches; follow the
+    // first.
+    let target = match &field.schema {
+        AvroSchema::Union { types, .. } => types.first()?,
+        other => other,
+    };
+    let mut names = Vec::new();
+    collect_named_refs(target, &mut names);
+    names.into_iter().next()
+}
+
+/// The first registered type, with its depth, that nests more than `limit`
+/// levels deep once the types it references are inlined, as JSON output
+/// inlines each type at its first use. Every record, array, map, and union
+/// counts as a level. A reference back to a type that is already being
+/// inlined is written as a name, so recursion adds no depth.
+pub(crate) fn too_deeply_nested(
+    registry: &SchemaRegistry,
+    limit: usize,
+) -> Option<(String, usize)> {
+    // Depth of each type on its own, and the types it references with the
+    // level each reference sits at.
+    let mut own: HashMap<String, (usize, Vec<(String, usize)>)> = HashMap::new();
+    for schema in registry.schemas() {
+        let Some(name) = schema.full_name() else {
+            continue;
+        };
+        let mut refs = Vec::new();
+        let depth = match schema {
+            AvroSchema::Record { fields, .. } => fields
+                .iter()
+                .map(|f| local_depth(&f.schema, 2, &mut refs))
+                .max()
+                .unwrap_or(1),
+            _ => 1,
+        };
+        own.insert(name.into_owned(), (depth, refs));
+    }
+
+    // Longest path, with an explicit stack so that a long chain of records
+    // cannot overflow this check itself.
+    let mut depths: HashMap<&str, usize> = HashMap::new();
+    let mut on_path: HashSet<&str> = HashSet::new();
+    for root in registry.names() {
+        if depths.contains_key(root) {
+            continue;
+        }
+        let mut stack: Vec<(&str, usize)> = vec![(root, 0)];
+        on_path.insert(root);
+        while let Some(&(name, next)) = stack.last() {
+            let (depth, refs) = &own[name];
+            if let Some((target, _)) = refs.get(next) {
+                stack.last_mut().expect("stack is not empty").1 += 1;
+                if let Some((target, _)) = own.get_key_value(target.as_str())
+                    && !depths.contains_key(target.as_str())
+                    && on_path.insert(target.as_str())
+                {
+                    stack.push((target.as_str(), 0));
+                }
+                continue;
+            }
+            let depth = refs
+                .iter()
+                .map(|(target, level)| {
+                    level - 1 + depths.get(target.as_str()).copied().unwrap_or(1)
+                })
+                .fold(*depth, usize::max);
+            depths.insert(name, depth);
+            on_path.remove(name);
+            stack.pop();
+        }
+    }
+
+    registry
+        .names()
+        .map(|name| (name, depths[name]))
+        .find(|&(_, depth)| depth > limit)
+        .map(|(name, depth)| (name.to_string(), depth))
+}
+
+/// The depth of `schema`, at `level`, within its type, recording the named
+/// types it references and the level of each.
+fn local_depth(schema: &AvroSchema, level: usize, refs: &mut Vec<(String, usize)>) -> usize {
+    match schema {
+        AvroSchema::Reference {
+            name, namespace, ..
+        } => {
+            refs.push((
+                make_full_name(name, namespace.as_deref()).into_owned(),
+                level,
+            ));
+            level
+        }
+        AvroSchema::Record { .. } | AvroSchema::Enum { .. } | AvroSchema::Fixed { .. } => {
+            if let Some(full_name) = schema.full_name() {
+                refs.push((full_name.into_owned(), level));
+            }
+            level
+        }
+        AvroSchema::Array { items, .. } => local_depth(items, level + 1, refs),
+        AvroSchema::Map { values, .. } => local_depth(values, level + 1, refs),
+        AvroSchema::Union { types, .. } => types
+            .iter()
+            .map(|t| local_depth(t, level + 1, refs))
+            .max()
+            .unwrap_or(level),
+        _ => level,
+    }
+}
+
+/// Full names of the named types a registered type references through its
+/// fields, in field order and possibly with duplicates. Enums and fixed types
+/// reference nothing.
+pub(crate) fn type_references(schema: &AvroSchema) -> Vec<String> {
+    let mut targets = Vec::new();
+    if let AvroSchema::Record { fields, .. } = schema {
+        for field in fields {
+            collect_named_refs(&field.schema, &mut targets);
+        }
+    }
+    targets
+}
+
+/// Collect the full names of all named types referenced by `schema`, looking
+/// through arrays, maps, and unions. An inline named type counts as a
+/// reference to itself; its own fields belong to its own node.
+fn collect_named_refs(schema: &AvroSchema, out: &mut Vec<String>) {
+    match schema {
+        AvroSchema::Reference {
+            name, namespace, ..
+        } => out.push(make_full_name(name, namespace.as_deref()).into_owned()),
+        AvroSchema::Record { .. } | AvroSchema::Enum { .. } | AvroSchema::Fixed { .. } => {
+            if let Some(full_name) = schema.full_name() {
+                out.push(full_name.into_owned());
+            }
+        }
+        AvroSchema::Array { items, .. } => collect_named_refs(items, out),
+        AvroSchema::Map { values, .. } => collect_named_refs(values, out),
+        AvroSchema::Union { types, .. } => {
+            for t in types {
+                collect_named_refs(t, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Idl;
+    use pretty_assertions::assert_eq;
+
+    fn graph_for(source: &'static str) -> TypeGraph {
+        Idl::new()
+            .convert_str(source)
+            .expect("should compile")
+            .type_graph()
+            .clone()
+    }
+
+    #[test]
+    fn records_and_messages_reference_named_types() {
+        let graph = graph_for(
+            r#"
+            @namespace("ex")
+            protocol P {
+                enum Color { RED }
+                record Item { Color color; array<Color> more; union { null, Item } next; }
+                error Oops { string why; }
+                Item get(map<Color> filter) throws Oops;
+            }
+            "#,
+        );
+
+        let edges: Vec<_> = graph
+            .edges
+            .iter()
+            .map(|e| (e.from.clone(), e.to.as_str()))
+            .collect();
+        assert_eq!(
+            edges,
+            vec![
+                (GraphNode::Type("ex.Item".into()), "ex.Color"),
+                (GraphNode::Type("ex.Item".into()), "ex.Item"),
+                (GraphNode::Message("get".into()), "ex.Color"),
+                (GraphNode::Message("get".into()), "ex.Item"),
+                (GraphNode::Message("get".into()), "ex.Oops"),
+            ]
+        );
+        assert_eq!(graph.nodes.len(), 4);
+    }
+
+    #[test]
+    fn to_dot_renders_nodes_and_edges() {
+        let graph = graph_for(
+            r#"
+            protocol P {
+                record A { B b; }
+                record B { int x; }
+                void ping(A a);
+            }
+            "#,
+        );
+        assert_eq!(
+            graph.to_dot("P"),
+            "\
+digraph \"P\" {
+  node [shape=box];
+  \"A\";
+  \"B\";
+  \"message:ping\" [shape=ellipse, label=\"ping\"];
+  \"A\" -> \"B\";
+  \"message:ping\" -> \"A\";
+}"
+        );
+    }
+}
@@ -0,0 +1,522 @@
+// ==============================================================================
+// Editor Introspection
+// ==============================================================================
+//
+// An index of where named types are declared and referenced in the compiled
+// file, so editor plugins can answer "what is under the cursor?" without
+// running a language server. Built from the spans the reader already records
+// on declarations (`DeclItem::Type`) and references (`AvroSchema::Reference`).
+//
+// Declarations and references in imported IDL files are indexed too, for
+// renames, which must reach every file. Hovers are only answered for the
+// compiled file itself: imported files have their own offsets, and an editor
+// hovering in one of them compiles that file.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Range;
+
+use miette::{Result, bail, miette};
+use serde_json::Value;
+
+use crate::error::SpanWithSource;
+use crate::model::json::{build_lookup, schema_to_json};
+use crate::model::schema::{AvroSchema, make_full_name};
+use crate::reader::IdlFile;
+use crate::resolve::{SchemaRegistry, is_valid_avro_name};
+use crate::syntax::syntax_tree;
+
+/// Whether a [`Hover`] is over a named type's declaration or a reference to
+/// it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolRole {
+    Declaration,
+    Reference,
+}
+
+/// What is at a byte offset in the source, as returned by
+/// [`IdlOutput::hover`](crate::IdlOutput::hover).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hover {
+    /// The byte range of the declaration or reference in the source.
+    pub span: Range<usize>,
+    pub role: SymbolRole,
+    /// The full name of the named type declared or referenced.
+    pub full_name: String,
+    /// The named type's doc comment, if it has one.
+    pub doc: Option<String>,
+    /// The named type's resolved schema, with the types it references
+    /// inlined, as it would appear in an `.avsc` file.
+    pub schema: Value,
+}
+
+/// A declaration or reference in the compiled file or one of its imports.
+struct Symbol {
+    span: SpanWithSource,
+    role: SymbolRole,
+    full_name: String,
+}
+
+impl Symbol {
+    fn range(&self) -> Range<usize> {
+        self.span.offset..self.span.offset + self.span.length
+    }
+}
+
+/// The declarations and references in one compiled file and its imports,
+/// plus the registry to resolve them against.
+pub(crate) struct SymbolIndex {
+    symbols: Vec<Symbol>,
+    registry: SchemaRegistry,
+    /// The compiled file, as it appears in diagnostics.
+    source_name: String,
+}
+
+impl SymbolIndex {
+    /// Index the declarations (by their recorded spans) and references in
+    /// `source_name` and the files it imports.
+    pub(crate) fn build(
+        idl_file: &IdlFile,
+        registry: SchemaRegistry,
+        declarations: &HashMap<String, SpanWithSource>,
+        source_name: &str,
+    ) -> Self {
+        let mut symbols: Vec<Symbol> = declarations
+            .iter()
+            .map(|(full_name, span)| Symbol {
+                span: *span,
+                role: SymbolRole::Declaration,
+                full_name: full_name.clone(),
+            })
+            .collect();
+
+        let mut push_references = |schema: &AvroSchema| {
+            collect_references(schema, &mut symbols);
+        };
+        for schema in registry.schemas() {
+            push_references(schema);
+        }
+        match idl_file {
+            IdlFile::Protocol(protocol) => {
+                for message in protocol.messages.values() {
+                    push_references(&message.response);
+                    for param in &message.request {
+                        push_references(&param.schema);
+                    }
+                    for error in message.errors.iter().flatten() {
+                        push_references(error);
+                    }
+                }
+            }
+            IdlFile::Schema(schema) => push_references(schema),
+            IdlFile::NamedSchemas(_) => {}
+        }
+
+        SymbolIndex {
+            symbols,
+            registry,
+            source_name: source_name.to_string(),
+        }
+    }
+
+    /// The registry the index resolves names against.
+    pub(crate) fn registry(&self) -> &SchemaRegistry {
+        &self.registry
+    }
+
+    /// The innermost declaration or reference in the compiled file
+    /// containing `offset`.
+    fn symbol_at(&self, offset: usize) -> Option<&Symbol> {
+        self.symbols
+            .iter()
+            .filter(|symbol| symbol.span.name == self.source_name)
+            .filter(|symbol| symbol.range().contains(&offset))
+            .min_by_key(|symbol| symbol.span.length)
+    }
+
+    /// The hover for the declaration or reference at `offset` in the compiled
+    /// file.
+    pub(crate) fn hover(&self, offset: usize) -> Option<Hover> {
+        let symbol = self.symbol_at(offset)?;
+        let definition = self.registry.lookup(&symbol.full_name)?;
+
+        let doc = match definition {
+            AvroSchema::Record { doc, .. }
+            | AvroSchema::Enum { doc, .. }
+            | AvroSchema::Fixed { doc, .. } => doc.clone(),
+            _ => None,
+        };
+        let lookup = build_lookup(self.registry.schemas(), None);
+        let schema = schema_to_json(definition, &mut HashSet::new(), None, &lookup);
+
+        Some(Hover {
+            span: symbol.range(),
+            role: symbol.role,
+            full_name: symbol.full_name.clone(),
+            doc,
+            schema,
+        })
+    }
+}
+
+/// Collect every reference in `schema` that has a span.
+fn collect_references(schema: &AvroSchema, symbols: &mut Vec<Symbol>) {
+    match schema {
+        AvroSchema::Reference {
+            name,
+            namespace,
+            span: Some(span),
+            ..
+        } => {
+            symbols.push(Symbol {
+                span: *span,
+                role: SymbolRole::Reference,
+                full_name: make_full_name(name, namespace.as_deref()).into_owned(),
+            });
+        }
+        AvroSchema::Record { fields, .. } => {
+            for field in fields {
+                collect_references(&field.schema, symbols);
+            }
+        }
+        AvroSchema::Array { items, .. } => collect_references(items, symbols),
+        AvroSchema::Map { values, .. } => collect_references(values, symbols),
+        AvroSchema::Union { types, .. } => {
+            for branch in types {
+                collect_references(branch, symbols);
+            }
+        }
+        _ => {}
+    }
+}
+
+// ==============================================================================
+// Go to Definition
+// ==============================================================================
+
+/// Where a named type is declared, as returned by
+/// [`IdlOutput::definition`](crate::IdlOutput::definition).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Definition {
+    /// The full name of the type.
+    pub full_name: String,
+    /// The declaring file, as it appears in diagnostics (see
+    /// [`DeclaredName::source`](crate::DeclaredName::source)).
+    pub path: String,
+    /// The byte range of the type's name in its declaration.
+    pub span: Range<usize>,
+}
+
+impl SymbolIndex {
+    /// Where the type named by the reference (or declaration) at `offset` in
+    /// the compiled file is declared.
+    pub(crate) fn definition_at(&self, offset: usize) -> Option<Definition> {
+        let symbol = self.symbol_at(offset)?;
+        self.definition(&symbol.full_name)
+    }
+
+    /// Where the type `full_name` is declared, if in an IDL file.
+    pub(crate) fn definition(&self, full_name: &str) -> Option<Definition> {
+        let declaration = self
+            .symbols
+            .iter()
+            .find(|s| s.role == SymbolRole::Declaration && s.full_name == full_name)?;
+        let span = declared_name_range(declaration.span.content, declaration.span.offset)
+            .unwrap_or_else(|| declaration.range());
+        Some(Definition {
+            full_name: full_name.to_string(),
+            path: declaration.span.name.to_string(),
+            span,
+        })
+    }
+}
+
+// ==============================================================================
+// Renaming
+// ==============================================================================
+//
+// A rename rewrites the declaration's name, every reference's name as
+// written (keeping a namespace qualifier if there is one), and every
+// `@aliases` entry naming the type. The type keeps its namespace, so short
+// references stay short. Reference spans come from the registry; the
+// declaration's span only covers its first token, so its name is found in
+// the syntax tree, as are aliases, which the schema model keeps only as
+// strings.
+
+/// A file rewritten by [`IdlOutput::rename`](crate::IdlOutput::rename).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditedFile {
+    /// The file, as it appears in diagnostics (see
+    /// [`DeclaredName::source`](crate::DeclaredName::source)).
+    pub path: String,
+    /// The new contents of the file.
+    pub content: String,
+}
+
+/// Replacement text for a byte range of a file.
+type Edit = (Range<usize>, String);
+
+impl SymbolIndex {
+    /// Rename the type `full_name` to `new_name` in its namespace, in every
+    /// file that declares, references, or aliases it.
+    pub(crate) fn rename(&self, full_name: &str, new_name: &str) -> Result<Vec<EditedFile>> {
+        if !is_valid_avro_name(new_name) {
+            bail!(
+                "`{new_name}` is not a valid Avro name; give the new name without a \
+                 namespace, since the type keeps its own"
+            );
+        }
+        if self.registry.lookup(full_name).is_none() {
+            bail!("no type named `{full_name}`");
+        }
+        let (namespace, old_name) = match full_name.rsplit_once('.') {
+            Some((namespace, name)) => (Some(namespace), name),
+            None => (None, full_name),
+        };
+        let new_full_name = make_full_name(new_name, namespace).into_owned();
+        if self.registry.lookup(&new_full_name).is_some() {
+            bail!("cannot rename `{full_name}`: a type named `{new_full_name}` already exists");
+        }
+
+        // Content and edits of every IDL file with a declaration or reference.
+        let mut files: BTreeMap<&str, (&str, Vec<Edit>)> = BTreeMap::new();
+        for symbol in &self.symbols {
+            files
+                .entry(symbol.span.name)
+                .or_insert((symbol.span.content, Vec::new()));
+        }
+        let mut declared = false;
+        for symbol in self.symbols.iter().filter(|s| s.full_name == full_name) {
+            let (content, edits) = files
+                .get_mut(symbol.span.name)
+                .expect("every symbol's file is in the map");
+            let range = match symbol.role {
+                SymbolRole::Reference => symbol.range(),
+                SymbolRole::Declaration => {
+                    declared = true;
+                    declared_name_range(content, symbol.span.offset).ok_or_else(|| {
+                        miette!(
+                            "cannot find the name of `{full_name}` in `{}`",
+                            symbol.span.name
+                        )
+                    })?
+                }
+            };
+            let replacement = renamed(&content[range.clone()], new_name);
+            edits.push((range, replacement));
+        }
+        if !declared {
+            bail!("cannot rename `{full_name}`: it is not declared in an IDL file");
+        }
+        for (content, edits) in files.values_mut() {
+            edits.extend(alias_edits(
+                content,
+                (full_name, old_name),
+                (&new_full_name, new_name),
+            ));
+        }
+
+        Ok(files
+            .into_iter()
+            .filter(|(_, (_, edits))| !edits.is_empty())
+            .map(|(path, (content, mut edits))| {
+                edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+                edits.dedup_by(|a, b| a.0 == b.0);
+                let mut content = content.to_string();
+                for (range, replacement) in edits {
+                    content.replace_range(range, &replacement);
+                }
+                EditedFile {
+                    path: path.to_string(),
+                    content,
+                }
+            })
+            .collect())
+    }
+}
+
+/// The byte range of the name in the named type declaration starting at
+/// `offset`.
+fn declared_name_range(content: &str, offset: usize) -> Option<Range<usize>> {
+    let tree = syntax_tree(content);
+    let declaration = tree.descendants().into_iter().find(|node| {
+        matches!(
+            node.kind,
+            "recordDeclaration" | "enumDeclaration" | "fixedDeclaration"
+        ) && node.span.start == offset
+    })?;
+    Some(declaration.child("identifier")?.span.clone())
+}
+
+/// A written name with its last component replaced by `new_name`, keeping
+/// any namespace qualifier and backquotes.
+fn renamed(written: &str, new_name: &str) -> String {
+    let last = written.rfind('.').map_or(0, |dot| dot + 1);
+    if written[last..].starts_with('`') {
+        format!("{}`{new_name}`", &written[..last])
+    } else {
+        format!("{}{new_name}", &written[..last])
+    }
+}
+
+/// Edits renaming the `@aliases` entries in `content` that name the old type,
+/// by full or simple name, to the new full or simple name.
+fn alias_edits(
+    content: &str,
+    (old_full_name, old_name): (&str, &str),
+    (new_full_name, new_name): (&str, &str),
+) -> Vec<Edit> {
+    let tree = syntax_tree(content);
+    let mut edits = Vec::new();
+    for property in tree.descendants() {
+        let is_aliases = property.kind == "schemaProperty"
+            && property
+                .child("identifier")
+                .is_some_and(|name| content[name.span.clone()].trim_matches('`') == "aliases");
+        if !is_aliases {
+            continue;
+        }
+        for token in property.tokens() {
+            if token.kind != "StringLiteral" {
+                continue;
+            }
+            let Ok(Value::String(alias)) = serde_json::from_str(&content[token.span.clone()])
+            else {
+                continue;
+            };
+            let replacement = if alias == old_full_name {
+                new_full_name
+            } else if alias == old_name {
+                new_name
+            } else {
+                continue;
+            };
+            edits.push((token.span.clone(), Value::from(replacement).to_string()));
+        }
+    }
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Idl;
+    use pretty_assertions::assert_eq;
+
+    const SHOP: &str = r#"@namespace("ex") protocol Shop {
+        /** Where an order is. */
+        enum Status { NEW, SHIPPED }
+        record Order { string id; array<Status> history; }
+        Order get(string id);
+    }"#;
+
+    #[test]
+    fn hover_resolves_references_and_declarations() {
+        let output = Idl::new().convert_str(SHOP).expect("IDL should compile");
+
+        let reference = SHOP.find("array<Status>").expect("reference") + "array<".len();
+        let hover = output.hover(reference + 2).expect("hover on reference");
+        assert_eq!(hover.role, SymbolRole::Reference);
+        assert_eq!(hover.span, reference..reference + "Status".len());
+        assert_eq!(hover.full_name, "ex.Status");
+        assert_eq!(hover.doc.as_deref(), Some("Where an order is."));
+        assert_eq!(
+            hover.schema["symbols"],
+            serde_json::json!(["NEW", "SHIPPED"])
+        );
+
+        let declaration = SHOP.find("record Order").expect("declaration");
+        let hover = output.hover(declaration).expect("hover on declaration");
+        assert_eq!(hover.role, SymbolRole::Declaration);
+        assert_eq!(hover.full_name, "ex.Order");
+
+        let response = SHOP.find("Order get").expect("message");
+        let hover = output.hover(response).expect("hover on message response");
+        assert_eq!(hover.role, SymbolRole::Reference);
+        assert_eq!(hover.schema["fields"][1]["type"]["items"]["name"], "Status");
+
+        assert_eq!(output.hover(SHOP.find("protocol").expect("keyword")), None);
+    }
+
+    #[test]
+    fn rename_rewrites_declaration_references_and_aliases_across_imports() {
+        let common = "@namespace(\"ex\") protocol Common {\n  \
+                      error Missing { string what; }\n  \
+                      record Id { string value; }\n}\n";
+        let source = "@namespace(\"ex\") protocol Shop {\n  \
+                      import idl \"common.avdl\";\n  \
+                      @aliases([\"ex.Missing\"]) error Gone { string what; }\n  \
+                      record Order { ex.Missing last; union { null, Missing } maybe = null; }\n  \
+                      Order get(Id id) throws Missing;\n}\n";
+        let output = Idl::new()
+            .import_source("common.avdl", common)
+            .convert_str(source)
+            .expect("IDL should compile");
+
+        let edited = output.rename("ex.Missing", "NotFound").expect("rename");
+        let files: HashMap<_, _> = edited
+            .iter()
+            .map(|file| {
+                let name = if file.path == "<input>" {
+                    "<input>"
+                } else {
+                    assert!(file.path.ends_with("common.avdl"), "{}", file.path);
+                    "common.avdl"
+                };
+                (name, file.content.as_str())
+            })
+            .collect();
+        assert_eq!(
+            files["common.avdl"],
+            common.replace("error Missing", "error NotFound")
+        );
+        assert_eq!(
+            files["<input>"],
+            "@namespace(\"ex\") protocol Shop {\n  \
+             import idl \"common.avdl\";\n  \
+             @aliases([\"ex.NotFound\"]) error Gone { string what; }\n  \
+             record Order { ex.NotFound last; union { null, NotFound } maybe = null; }\n  \
+             Order get(Id id) throws NotFound;\n}\n"
+        );
+
+        assert!(output.rename("ex.Missing", "Id").is_err());
+        assert!(output.rename("ex.Missing", "a.b").is_err());
+        assert!(output.rename("ex.Nope", "Other").is_err());
+    }
+
+    #[test]
+    fn definition_follows_references_into_imports() {
+        let common = "@namespace(\"ex\") protocol Common {\n  record Id { string value; }\n}\n";
+        let source = "@namespace(\"ex\") protocol Shop {\n  \
+                      import idl \"common.avdl\";\n  \
+                      record Order { Id id; }\n}\n";
+        let output = Idl::new()
+            .import_source("common.avdl", common)
+            .convert_str(source)
+            .expect("IDL should compile");
+
+        let reference = source.find("Id id").expect("reference");
+        let definition = output.definition(reference + 1).expect("definition");
+        assert_eq!(definition.full_name, "ex.Id");
+        assert!(
+            definition.path.ends_with("common.avdl"),
+            "{}",
+            definition.path
+        );
+        assert_eq!(&common[definition.span], "Id");
+
+        let declaration = source.find("record Order").expect("declaration");
+        let definition = output.definition(declaration).expect("own declaration");
+        assert_eq!(definition.path, "<input>");
+        let name = declaration + "record ".len();
+        assert_eq!(definition.span, name..name + "Order".len());
+
+        assert_eq!(
+            output.definition(source.find("protocol").expect("keyword")),
+            None
+        );
+        assert_eq!(output.definition_of("ex.Id").map(|d| d.span), {
+            let start = common.find("Id {").expect("name");
+            Some(start..start + 2)
+        });
+    }
+}
@@ -12,7 +12,10 @@
 //
 // This module provides:
 //   - `ImportContext`: state tracking for cycle prevention and search paths
-//   - `import_protocol` / `import_schema`: JSON-based import helpers
+//   - `ImportContext::suggest_imports`: the imports that would define an
+//     undefined name, for diagnostics
+//   - `import_protocol` / `import_schema`: JSON-based import helpers, each
+//     `parse_import_json` followed by `register_protocol` / `register_schema`
 //   - `json_to_schema` and friends: conversion from serde_json `Value` to our
 //     `AvroSchema` model
 //
@@ -21,15 +24,26 @@
 // Instead, the reader calls into `ImportContext` to resolve paths and check for
 // cycles, then handles the recursive parse itself.
 
+use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Read as _;
 use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
 
+use indexmap::IndexMap;
 use serde_json::Value;
+use zip::ZipArchive;
 
+use crate::encoding::decode_source;
+use crate::error::ErrorKind;
+use crate::lint::{explicit_namespaces, identifier_text};
 use crate::model::protocol::Message;
 use crate::model::schema::{
-    AvroSchema, FieldOrder, LogicalType, PrimitiveType, parse_logical_type, split_full_name,
+    AvroSchema, FieldOrder, LogicalType, PrimitiveType, Properties, parse_logical_type,
+    split_full_name,
 };
+use crate::reader::ImportKind;
 use crate::resolve::SchemaRegistry;
 use miette::Result;
 
@@ -43,20 +57,119 @@ fn parse_json_with_comments(input: &str) -> std::result::Result<Value, serde_jso
     )
 }
 
+// ==============================================================================
+// Import Kind Detection
+// ==============================================================================
+//
+// Importing a file with the wrong `import` kind would otherwise fail with an
+// error about the file's contents (a protocol has no `type`, IDL is not JSON),
+// or, for a schema imported as a protocol, silently import nothing. Detecting
+// what the file actually is lets the diagnostic name the right kind.
+
+/// Which JSON import reads `json`. A protocol has `protocol`, `types`, or
+/// `messages` at the top level and no `type`; anything else is a schema.
+fn json_kind(json: &Value) -> ImportKind {
+    let is_protocol = json.get("type").is_none()
+        && ["protocol", "types", "messages"]
+            .iter()
+            .any(|key| json.get(key).is_some());
+    if is_protocol {
+        ImportKind::Protocol
+    } else {
+        ImportKind::Schema
+    }
+}
+
+/// If `content` is Avro JSON rather than IDL, the `import` kind that reads it.
+pub(crate) fn json_import_kind(content: &str) -> Option<ImportKind> {
+    parse_json_with_comments(content)
+        .ok()
+        .map(|json| json_kind(&json))
+}
+
+/// The error for `path`, which contains `actual`, imported with `import used`.
+pub(crate) fn wrong_import_kind(
+    path: &Path,
+    actual: ImportKind,
+    used: ImportKind,
+) -> miette::Report {
+    let noun = |kind| match kind {
+        ImportKind::Idl => "an Avro IDL",
+        ImportKind::Protocol => "an Avro protocol",
+        ImportKind::Schema => "an Avro schema",
+    };
+    miette::miette!(
+        code = ErrorKind::InvalidImport.code(),
+        help = format!(
+            "this file is {}; use `import {}`",
+            noun(actual),
+            actual.as_str()
+        ),
+        "`{}` is {} file, not {} file",
+        path.display(),
+        noun(actual),
+        noun(used)
+    )
+}
+
 // ==============================================================================
 // Import Context: Cycle Prevention and Path Resolution
 // ==============================================================================
 
+/// Where an import was found by [`ImportContext::resolve_import_root`].
+#[derive(Debug)]
+pub struct ResolvedImport {
+    /// The path [`ImportContext::resolve_import`] returns.
+    pub path: PathBuf,
+    /// The overlay or import search directory the file was found in, as it
+    /// was added. `None` for a file found relative to the importing file
+    /// outside any overlay, in memory, or through a mapped prefix.
+    pub root: Option<PathBuf>,
+    /// The paths on disk searched before `path` that had no file, so that a
+    /// file created at one of them would be imported instead. `None` if the
+    /// file is in memory or an archive was searched, since a later change
+    /// to those cannot be seen on disk.
+    pub missed: Option<Vec<PathBuf>>,
+}
+
+/// Add `path` to the paths searched without finding a file, unless those
+/// are no longer tracked.
+fn note_missed(missed: &mut Option<Vec<PathBuf>>, path: PathBuf) {
+    if let Some(missed) = missed {
+        missed.push(path);
+    }
+}
+
 /// Tracks import state across recursive IDL parsing.
 ///
 /// The Java Avro compiler resolves imports first relative to the current file's
 /// directory, then via the classpath. We replace the classpath mechanism with
 /// configurable import search directories, which serves the same purpose.
+///
+/// Files can also be supplied in memory, for callers without a filesystem
+/// (such as WebAssembly) or that generate imported files on the fly. These
+/// take precedence over files on disk.
+///
+/// Overlay directories are layered into a single tree, like an overlay
+/// filesystem: a file in an earlier overlay shadows the file at the same
+/// relative path in a later one, including for imports between the overlaid
+/// files themselves. This lets a few shared files be patched locally without
+/// copying the rest of the tree.
 pub struct ImportContext {
     /// Files that have already been imported (canonical paths, for cycle prevention).
     read_locations: HashSet<PathBuf>,
     /// Additional directories to search for imports (replaces Java classpath).
     import_dirs: Vec<PathBuf>,
+    /// Overlay directories, highest priority first.
+    overlay_dirs: Vec<PathBuf>,
+    /// In-memory files, keyed by their lexically normalized path.
+    sources: HashMap<PathBuf, String>,
+    /// Logical import path prefixes and the directories they stand for, in
+    /// the order added.
+    mappings: Vec<(String, PathBuf)>,
+    /// Archive import directories opened so far, by canonical path. Behind a
+    /// lock since imports are read from several threads at once.
+    archives: Mutex<HashMap<PathBuf, ZipArchive<File>>>,
 }
 
 impl ImportContext {
@@ -64,50 +177,243 @@ impl ImportContext {
         ImportContext {
             read_locations: HashSet::new(),
             import_dirs,
+            overlay_dirs: Vec::new(),
+            sources: HashMap::new(),
+            mappings: Vec::new(),
+            archives: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Resolve imports whose path starts with `prefix` to the rest of the
+    /// path under `dir`, so that `import idl "@shared/money.avdl"` can read
+    /// `/repo/schemas/shared/money.avdl` whichever file imports it. Prefixes
+    /// are matched as written, so a prefix should end in `/`.
+    pub fn add_mapping(&mut self, prefix: String, dir: PathBuf) {
+        self.mappings.push((prefix, dir));
+    }
+
+    /// Where a mapped import path points, using the longest matching prefix
+    /// (the first added among equally long ones).
+    fn map_import(&self, import_file: &str) -> Option<PathBuf> {
+        let mut best: Option<(&str, &Path)> = None;
+        for (prefix, dir) in &self.mappings {
+            if import_file.starts_with(prefix.as_str())
+                && best.is_none_or(|(best, _)| prefix.len() > best.len())
+            {
+                best = Some((prefix, dir));
+            }
         }
+        best.map(|(prefix, dir)| dir.join(&import_file[prefix.len()..]))
+    }
+
+    /// Layer `dir` under the overlay directories added so far: its files are
+    /// only found where no earlier overlay has a file at the same path.
+    pub fn add_overlay(&mut self, dir: PathBuf) {
+        self.overlay_dirs.push(dir);
+    }
+
+    /// Make `content` importable as `path`, shadowing any file on disk.
+    pub fn add_source(&mut self, path: &Path, content: String) {
+        self.sources.insert(normalize_path(path), content);
     }
 
-    /// Resolve an import file path. Searches:
-    /// 1. Relative to `current_dir` (the directory containing the importing file)
-    /// 2. In each import search directory, in order
+    /// Resolve an import file path. A path starting with a mapped prefix (see
+    /// [`add_mapping`](Self::add_mapping)) is only looked for where the
+    /// mapping points. Other paths are searched for:
+    /// 1. The in-memory files, relative to `current_dir` and then by the
+    ///    import path alone
+    /// 2. Relative to `current_dir` (the directory containing the importing
+    ///    file, which may be in an archive). If `current_dir` is inside an
+    ///    overlay directory, the path relative to it is looked for in each
+    ///    overlay directory in turn instead.
+    /// 3. In each overlay directory, in order
+    /// 4. In each import search directory or archive, in order
     ///
-    /// Returns the canonical (absolute, symlink-resolved) path on success.
+    /// Returns the normalized path of an in-memory file or a file in an
+    /// archive, or the canonical (absolute, symlink-resolved) path of a file
+    /// on disk.
     pub fn resolve_import(&self, import_file: &str, current_dir: &Path) -> Result<PathBuf> {
-        // Try relative to current file's directory first.
-        let relative = current_dir.join(import_file);
-        if relative.exists() {
-            return relative.canonicalize().map_err(|e| {
+        self.resolve_import_root(import_file, current_dir)
+            .map(|resolved| resolved.path)
+    }
+
+    /// Like [`resolve_import`](Self::resolve_import), but also returns where
+    /// the file was found and where it was looked for first.
+    pub fn resolve_import_root(
+        &self,
+        import_file: &str,
+        current_dir: &Path,
+    ) -> Result<ResolvedImport> {
+        let mut missed = Some(Vec::new());
+        let (path, root) = self.search(import_file, current_dir, &mut missed)?;
+        if self.sources.contains_key(&path) || self.archive_entry(&path).is_some() {
+            missed = None;
+        }
+        Ok(ResolvedImport { path, root, missed })
+    }
+
+    /// Find `import_file` as [`resolve_import`](Self::resolve_import)
+    /// describes, adding each path on disk that has no file to `missed`, or
+    /// clearing it if an archive was searched.
+    fn search(
+        &self,
+        import_file: &str,
+        current_dir: &Path,
+        missed: &mut Option<Vec<PathBuf>>,
+    ) -> Result<(PathBuf, Option<PathBuf>)> {
+        if let Some(mapped) = self.map_import(import_file) {
+            let normalized = normalize_path(&mapped);
+            if self.sources.contains_key(&normalized) {
+                return Ok((normalized, None));
+            }
+            if !mapped.exists() {
+                return Err(miette::miette!(
+                    code = ErrorKind::ImportNotFound.code(),
+                    "import not found: {import_file} (mapped to {})",
+                    mapped.display()
+                ));
+            }
+            return mapped.canonicalize().map(|path| (path, None)).map_err(|e| {
                 miette::miette!(
-                    "canonicalize import path `{import_file}` relative to `{}`: {e}",
-                    current_dir.display()
+                    code = ErrorKind::ReadFile.code(),
+                    "canonicalize mapped import path `{import_file}`: {e}"
                 )
             });
         }
 
+        if !self.sources.is_empty() {
+            for candidate in [current_dir.join(import_file), PathBuf::from(import_file)] {
+                let candidate = normalize_path(&candidate);
+                if self.sources.contains_key(&candidate) {
+                    return Ok((candidate, None));
+                }
+            }
+        }
+
+        // Try relative to current file's directory first, or to its place in
+        // the overlay tree if it is in one.
+        if let Some(relative) = self.overlay_relative(import_file, current_dir) {
+            if let Some(found) = self.search_overlays(&relative, missed)? {
+                return Ok(found);
+            }
+        } else {
+            let relative = current_dir.join(import_file);
+            if self.archive_entry(&relative).is_some() {
+                *missed = None;
+                if self.archive_contains(&relative) {
+                    return Ok((normalize_path(&relative), None));
+                }
+            }
+            if relative.exists() {
+                return relative
+                    .canonicalize()
+                    .map(|path| (path, None))
+                    .map_err(|e| {
+                        miette::miette!(
+                            code = ErrorKind::ReadFile.code(),
+                            "canonicalize import path `{import_file}` relative to `{}`: {e}",
+                            current_dir.display()
+                        )
+                    });
+            }
+            note_missed(missed, relative);
+        }
+
+        if Path::new(import_file).is_relative()
+            && let Some(found) = self.search_overlays(Path::new(import_file), missed)?
+        {
+            return Ok(found);
+        }
+
         // Try each import search directory.
         for dir in &self.import_dirs {
+            if is_archive(dir) {
+                *missed = None;
+                if let Ok(archive) = dir.canonicalize() {
+                    let candidate = normalize_path(&archive.join(import_file));
+                    if self.archive_contains(&candidate) {
+                        return Ok((candidate, Some(dir.clone())));
+                    }
+                }
+                continue;
+            }
             let candidate = dir.join(import_file);
             if candidate.exists() {
-                return candidate.canonicalize().map_err(|e| {
-                    miette::miette!(
-                        "canonicalize import path `{import_file}` in import dir `{}`: {e}",
-                        dir.display()
-                    )
-                });
+                return candidate
+                    .canonicalize()
+                    .map(|path| (path, Some(dir.clone())))
+                    .map_err(|e| {
+                        miette::miette!(
+                            code = ErrorKind::ReadFile.code(),
+                            "canonicalize import path `{import_file}` in import dir `{}`: {e}",
+                            dir.display()
+                        )
+                    });
             }
+            note_missed(missed, candidate);
         }
 
         // Build a comma-separated list of all directories that were searched,
-        // starting with the importing file's directory, then each --import-dir.
+        // starting with the importing file's directory, then each overlay and
+        // --import-dir.
         let searched: Vec<String> = std::iter::once(current_dir.display().to_string())
+            .chain(self.overlay_dirs.iter().map(|d| d.display().to_string()))
             .chain(self.import_dirs.iter().map(|d| d.display().to_string()))
             .collect();
         Err(miette::miette!(
+            code = ErrorKind::ImportNotFound.code(),
             "import not found: {import_file} (searched: {})",
             searched.join(", ")
         ))
     }
 
+    /// The path of `import_file` relative to the overlay tree, if
+    /// `current_dir` is inside an overlay directory and the import does not
+    /// climb out of it.
+    fn overlay_relative(&self, import_file: &str, current_dir: &Path) -> Option<PathBuf> {
+        if self.overlay_dirs.is_empty() {
+            return None;
+        }
+        let current_dir = current_dir.canonicalize().ok()?;
+        // The deepest overlay containing the file, in case one is nested in
+        // another.
+        let within = self
+            .overlay_dirs
+            .iter()
+            .filter_map(|dir| {
+                let dir = dir.canonicalize().ok()?;
+                current_dir.strip_prefix(dir).ok().map(Path::to_path_buf)
+            })
+            .min_by_key(|within| within.components().count())?;
+        let relative = normalize_path(&within.join(import_file));
+        (relative.is_relative() && !relative.starts_with("..")).then_some(relative)
+    }
+
+    /// Find `relative` in the first overlay directory that has it, adding
+    /// the overlay paths that do not to `missed`.
+    fn search_overlays(
+        &self,
+        relative: &Path,
+        missed: &mut Option<Vec<PathBuf>>,
+    ) -> Result<Option<(PathBuf, Option<PathBuf>)>> {
+        for dir in &self.overlay_dirs {
+            let candidate = dir.join(relative);
+            if candidate.exists() {
+                let path = candidate.canonicalize().map_err(|e| {
+                    miette::miette!(
+                        code = ErrorKind::ReadFile.code(),
+                        "canonicalize import path `{}` in overlay dir `{}`: {e}",
+                        relative.display(),
+                        dir.display()
+                    )
+                })?;
+                return Ok(Some((path, Some(dir.clone()))));
+            }
+            note_missed(missed, candidate);
+        }
+        Ok(None)
+    }
+
     /// Check if a file has already been imported (cycle prevention).
     ///
     /// If the file has not yet been imported, marks it as imported and returns
@@ -116,6 +422,304 @@ impl ImportContext {
     pub fn mark_imported(&mut self, path: &Path) -> bool {
         !self.read_locations.insert(path.to_path_buf())
     }
+
+    /// Whether [`mark_imported`](Self::mark_imported) has seen `path`.
+    pub fn is_imported(&self, path: &Path) -> bool {
+        self.read_locations.contains(path)
+    }
+
+    /// Read a path returned by [`resolve_import`](Self::resolve_import).
+    /// `what` names the kind of file in the error message.
+    ///
+    /// A leading UTF-8 byte order mark is dropped, since neither the IDL
+    /// lexer nor the JSON parser accepts one.
+    pub fn read_source(&self, path: &Path, what: &str) -> Result<String> {
+        let mut content = match self.sources.get(path) {
+            Some(content) => content.clone(),
+            None => {
+                let bytes = match self.archive_entry(path) {
+                    Some((archive, name)) => self.read_archive_file(&archive, &name),
+                    None => std::fs::read(path),
+                };
+                let bytes = bytes.map_err(|e| {
+                    miette::miette!(
+                        code = ErrorKind::ReadFile.code(),
+                        "read {what} file `{}`: {e}",
+                        path.display()
+                    )
+                })?;
+                decode_source(bytes).map_err(|e| {
+                    miette::miette!(
+                        code = ErrorKind::Encoding.code(),
+                        help = "save the file as UTF-8",
+                        "read {what} file `{}`: {e}",
+                        path.display()
+                    )
+                })?
+            }
+        };
+        if content.starts_with('\u{feff}') {
+            content.drain(..'\u{feff}'.len_utf8());
+        }
+        Ok(content)
+    }
+}
+
+// ==============================================================================
+// Archive Import Directories
+// ==============================================================================
+//
+// Java projects package their schemas in jars and put those on the
+// classpath, so an import search directory may also be a `.zip` or `.jar`
+// file, searched as if it were unpacked. A file in an archive is named by the
+// archive's path followed by the file's path in it, such as
+// `/repo/lib/schemas.jar/com/acme/money.avdl`, so imports relative to that
+// file are found in the archive too. Each archive is opened once and kept
+// open, with its index read, for the rest of the compile.
+
+/// Whether an import search directory is really an archive to search.
+fn is_archive(dir: &Path) -> bool {
+    dir.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip") || ext.eq_ignore_ascii_case("jar"))
+        && dir.is_file()
+}
+
+impl ImportContext {
+    /// The canonical path of the archive import directory that `path` names
+    /// a file in, and the file's name in the archive.
+    fn archive_entry(&self, path: &Path) -> Option<(PathBuf, String)> {
+        let path = normalize_path(path);
+        self.import_dirs
+            .iter()
+            .filter(|dir| is_archive(dir))
+            .find_map(|dir| {
+                let archive = dir.canonicalize().ok()?;
+                let entry = path.strip_prefix(&archive).ok()?;
+                let name: Vec<_> = entry
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect();
+                Some((archive, name.join("/")))
+            })
+    }
+
+    /// Whether `path` names a file in an archive import directory.
+    fn archive_contains(&self, path: &Path) -> bool {
+        self.archive_entry(path).is_some_and(|(archive, name)| {
+            self.with_archive(&archive, |zip| Ok(zip.index_for_name(&name).is_some()))
+                .unwrap_or(false)
+        })
+    }
+
+    /// The contents of the file `name` in `archive`.
+    fn read_archive_file(&self, archive: &Path, name: &str) -> std::io::Result<Vec<u8>> {
+        self.with_archive(archive, |zip| {
+            let mut file = zip.by_name(name).map_err(std::io::Error::other)?;
+            let mut bytes = Vec::new();
+            file.read_to_end(&mut bytes)?;
+            Ok(bytes)
+        })
+    }
+
+    /// Run `f` on the archive at `archive`, opening it on first use.
+    fn with_archive<T>(
+        &self,
+        archive: &Path,
+        f: impl FnOnce(&mut ZipArchive<File>) -> std::io::Result<T>,
+    ) -> std::io::Result<T> {
+        let mut archives = self.archives.lock().unwrap_or_else(PoisonError::into_inner);
+        let zip = match archives.entry(archive.to_path_buf()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let zip = ZipArchive::new(File::open(archive)?).map_err(std::io::Error::other)?;
+                entry.insert(zip)
+            }
+        };
+        f(zip)
+    }
+}
+
+/// Remove `.` components and fold `..` into the preceding component, without
+/// touching the filesystem.
+fn normalize_path(path: &Path) -> PathBuf {
+    use std::path::Component;
+
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+// ==============================================================================
+// Import Suggestions
+// ==============================================================================
+//
+// An undefined name is most often a type the source forgot to import. When a
+// file under one of the import directories defines it, the diagnostic can
+// name the exact `import` statement to add. Files are only scanned once a
+// reference fails to resolve, so successful compiles pay nothing for this.
+
+/// A file under an import directory that defines a type.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct ImportSuggestion {
+    /// The `import` statement that reads the file, e.g.
+    /// `import idl "common/types.avdl";`.
+    pub(crate) statement: String,
+    /// The full name the file gives the type. This differs from the name
+    /// looked up when only the simple names match.
+    pub(crate) full_name: String,
+}
+
+impl ImportContext {
+    /// The files under the import directories that define a type named
+    /// `full_name` and are not already imported, in directory order. If none
+    /// does, the files that define a type with the same simple name in
+    /// another namespace.
+    pub(crate) fn suggest_imports(&self, full_name: &str) -> Vec<ImportSuggestion> {
+        let simple = full_name.rsplit('.').next().unwrap_or(full_name);
+        let mut exact = Vec::new();
+        let mut same_simple_name = Vec::new();
+        for dir in &self.import_dirs {
+            for path in files_under(dir) {
+                let kind = match path.extension().and_then(|ext| ext.to_str()) {
+                    Some("avdl") => ImportKind::Idl,
+                    Some("avpr") => ImportKind::Protocol,
+                    Some("avsc") => ImportKind::Schema,
+                    _ => continue,
+                };
+                if path
+                    .canonicalize()
+                    .is_ok_and(|canonical| self.is_imported(&canonical))
+                {
+                    continue;
+                }
+                let Ok(content) = self.read_source(&path, kind.as_str()) else {
+                    continue;
+                };
+                let Ok(relative) = path.strip_prefix(dir) else {
+                    continue;
+                };
+                // Import paths are written with forward slashes everywhere.
+                let relative: Vec<_> = relative
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect();
+                let statement = format!("import {} \"{}\";", kind.as_str(), relative.join("/"));
+                for name in defined_type_names(kind, &path, &content) {
+                    let suggestion = ImportSuggestion {
+                        statement: statement.clone(),
+                        full_name: name,
+                    };
+                    if suggestion.full_name == full_name {
+                        exact.push(suggestion);
+                    } else if suggestion.full_name.rsplit('.').next() == Some(simple) {
+                        same_simple_name.push(suggestion);
+                    }
+                }
+            }
+        }
+        let mut suggestions = if exact.is_empty() {
+            same_simple_name
+        } else {
+            exact
+        };
+        // The same file can be reachable from more than one import directory.
+        let mut seen = HashSet::new();
+        suggestions.retain(|suggestion| seen.insert(suggestion.clone()));
+        suggestions
+    }
+}
+
+/// Every file under `dir`, recursively and sorted so suggestions come out in
+/// a stable order. Hidden files and directories are skipped, as are
+/// directories that cannot be read.
+fn files_under(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .map(|entry| entry.path())
+        .collect();
+    entries.sort();
+    let mut files = Vec::new();
+    for path in entries {
+        if path.is_dir() {
+            files.extend(files_under(&path));
+        } else {
+            files.push(path);
+        }
+    }
+    files
+}
+
+/// The full names of the named types that `content` declares. Files that do
+/// not parse declare nothing.
+fn defined_type_names(kind: ImportKind, path: &Path, content: &str) -> Vec<String> {
+    let mut registry = SchemaRegistry::new();
+    let imported = match kind {
+        ImportKind::Idl => return idl_type_names(content),
+        ImportKind::Protocol => import_protocol(path, content, &mut registry).map(|_| ()),
+        ImportKind::Schema => import_schema(path, content, &mut registry),
+    };
+    match imported {
+        Ok(()) => registry.names().map(str::to_string).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// The full names of the named types declared in an IDL source, found from
+/// its syntax tree so that a file with unrelated errors (or imports of its
+/// own that cannot be read) still counts.
+fn idl_type_names(source: &str) -> Vec<String> {
+    let tree = crate::syntax::syntax_tree(source);
+    let mut enclosing = tree
+        .child("namespaceDeclaration")
+        .and_then(|declaration| declaration.child("identifier"))
+        .map(|identifier| identifier_text(source, identifier));
+    let mut names = Vec::new();
+    for node in tree.descendants() {
+        let is_protocol = match node.kind {
+            "protocolDeclaration" => true,
+            "recordDeclaration" | "enumDeclaration" | "fixedDeclaration" => false,
+            _ => continue,
+        };
+        let Some(identifier) = node.child("identifier") else {
+            continue;
+        };
+        let name = identifier_text(source, identifier);
+        // As in the reader, an explicit `@namespace` wins over a dotted name,
+        // which wins over the enclosing namespace.
+        let (namespace, simple) = match explicit_namespaces(source, node).pop() {
+            Some((namespace, _)) => (Some(namespace), name.rsplit('.').next().unwrap_or(&name)),
+            None => match name.rsplit_once('.') {
+                Some((namespace, simple)) => (Some(namespace.to_string()), simple),
+                None => (enclosing.clone(), name.as_str()),
+            },
+        };
+        if is_protocol {
+            enclosing = namespace;
+            continue;
+        }
+        names.push(match namespace.filter(|namespace| !namespace.is_empty()) {
+            Some(namespace) => format!("{namespace}.{simple}"),
+            None => simple.to_string(),
+        });
+    }
+    names
 }
 
 // ==============================================================================
@@ -202,7 +806,7 @@ fn flatten_schema_inner(schema: AvroSchema, collected: &mut Vec<AvroSchema>) ->
             AvroSchema::Reference {
                 name,
                 namespace,
-                properties: HashMap::new(),
+                properties: Properties::new(),
                 span: None,
             }
         }
@@ -217,7 +821,7 @@ fn flatten_schema_inner(schema: AvroSchema, collected: &mut Vec<AvroSchema>) ->
             let reference = AvroSchema::Reference {
                 name: name.clone(),
                 namespace: namespace.clone(),
-                properties: HashMap::new(),
+                properties: Properties::new(),
                 span: None,
             };
             collected.push(schema);
@@ -232,7 +836,7 @@ fn flatten_schema_inner(schema: AvroSchema, collected: &mut Vec<AvroSchema>) ->
             let reference = AvroSchema::Reference {
                 name: name.clone(),
                 namespace: namespace.clone(),
-                properties: HashMap::new(),
+                properties: Properties::new(),
                 span: None,
             };
             collected.push(schema);
@@ -300,17 +904,48 @@ fn flatten_and_register(schema: AvroSchema, registry: &mut SchemaRegistry) {
 /// The `.avpr` format is the JSON serialization of an Avro protocol. It contains
 /// a `types` array of named schema definitions and a `messages` object mapping
 /// message names to their definitions.
+///
+/// `path` is only used to name the file in errors.
 pub fn import_protocol(
     path: &Path,
+    content: &str,
+    registry: &mut SchemaRegistry,
+) -> Result<IndexMap<String, Message>> {
+    register_protocol(path, &parse_import_json(path, content)?, registry)
+}
+
+/// Parse the contents of a JSON protocol or schema file being imported.
+///
+/// This is the part of a JSON import that does not touch the registry, so it
+/// can run ahead of time on another thread. `path` is only used to name the
+/// file in errors.
+pub fn parse_import_json(path: &Path, content: &str) -> Result<Value> {
+    parse_json_with_comments(content).map_err(|e| {
+        miette::miette!(
+            code = ErrorKind::InvalidImport.code(),
+            "invalid JSON in {}: {e}",
+            path.display()
+        )
+    })
+}
+
+/// Register the types of an already-parsed JSON protocol, returning its
+/// messages. See [`import_protocol`].
+pub fn register_protocol(
+    path: &Path,
+    json: &Value,
     registry: &mut SchemaRegistry,
-) -> Result<HashMap<String, Message>> {
-    let content = std::fs::read_to_string(path)
-        .map_err(|e| miette::miette!("read protocol file `{}`: {e}", path.display()))?;
-    let json: Value = parse_json_with_comments(&content)
-        .map_err(|e| miette::miette!("invalid JSON in {}: {e}", path.display()))?;
+) -> Result<IndexMap<String, Message>> {
+    if json_kind(json) == ImportKind::Schema {
+        return Err(wrong_import_kind(
+            path,
+            ImportKind::Schema,
+            ImportKind::Protocol,
+        ));
+    }
 
     let default_namespace = json.get("namespace").and_then(|n| n.as_str());
-    let mut messages = HashMap::new();
+    let mut messages = IndexMap::new();
 
     // Extract types from the protocol JSON and register them. Schemas are
     // flattened so that nested named types (records, enums, fixed) within
@@ -320,6 +955,7 @@ pub fn import_protocol(
         for (i, type_json) in types.iter().enumerate() {
             let schema = json_to_schema(type_json, default_namespace).map_err(|e| {
                 miette::miette!(
+                    code = ErrorKind::InvalidImport.code(),
                     "parse type at index {i} in protocol `{}`: {e}",
                     path.display()
                 )
@@ -333,6 +969,7 @@ pub fn import_protocol(
         for (name, msg_json) in msgs {
             let message = json_to_message(msg_json, default_namespace).map_err(|e| {
                 miette::miette!(
+                    code = ErrorKind::InvalidImport.code(),
                     "parse message `{name}` in protocol `{}`: {e}",
                     path.display()
                 )
@@ -355,14 +992,30 @@ pub fn import_protocol(
 /// named types (record, enum, fixed) found in the schema tree -- including those
 /// nested inside record fields, union branches, array items, or map values --
 /// are registered so that subsequent IDL code can reference them by name.
-pub fn import_schema(path: &Path, registry: &mut SchemaRegistry) -> Result<()> {
-    let content = std::fs::read_to_string(path)
-        .map_err(|e| miette::miette!("read schema file `{}`: {e}", path.display()))?;
-    let json: Value = parse_json_with_comments(&content)
-        .map_err(|e| miette::miette!("invalid JSON in {}: {e}", path.display()))?;
-
-    let schema = json_to_schema(&json, None)
-        .map_err(|e| miette::miette!("parse schema from `{}`: {e}", path.display()))?;
+///
+/// `path` is only used to name the file in errors.
+pub fn import_schema(path: &Path, content: &str, registry: &mut SchemaRegistry) -> Result<()> {
+    register_schema(path, &parse_import_json(path, content)?, registry)
+}
+
+/// Register an already-parsed JSON schema and the named types nested in it.
+/// See [`import_schema`].
+pub fn register_schema(path: &Path, json: &Value, registry: &mut SchemaRegistry) -> Result<()> {
+    if json_kind(json) == ImportKind::Protocol {
+        return Err(wrong_import_kind(
+            path,
+            ImportKind::Protocol,
+            ImportKind::Schema,
+        ));
+    }
+
+    let schema = json_to_schema(json, None).map_err(|e| {
+        miette::miette!(
+            code = ErrorKind::InvalidImport.code(),
+            "parse schema from `{}`: {e}",
+            path.display()
+        )
+    })?;
     flatten_and_register(schema, registry);
 
     Ok(())
@@ -403,7 +1056,10 @@ fn json_to_schema(json: &Value, default_namespace: Option<&str>) -> Result<AvroS
         // Object = complex type (record, enum, fixed, array, map, or annotated primitive).
         Value::Object(obj) => object_to_schema(obj, default_namespace),
 
-        _ => Err(miette::miette!("invalid schema JSON: {json}")),
+        _ => Err(miette::miette!(
+            code = ErrorKind::InvalidImport.code(),
+            "invalid schema JSON: {json}"
+        )),
     }
 }
 
@@ -420,14 +1076,14 @@ fn string_to_schema(s: &str, default_namespace: Option<&str>) -> AvroSchema {
         AvroSchema::Reference {
             name: name.to_string(),
             namespace: Some(ns.to_string()),
-            properties: HashMap::new(),
+            properties: Properties::new(),
             span: None,
         }
     } else {
         AvroSchema::Reference {
             name: s.to_string(),
             namespace: default_namespace.map(|s| s.to_string()),
-            properties: HashMap::new(),
+            properties: Properties::new(),
             span: None,
         }
     }
@@ -438,10 +1094,12 @@ fn object_to_schema(
     obj: &serde_json::Map<String, Value>,
     default_namespace: Option<&str>,
 ) -> Result<AvroSchema> {
-    let type_str = obj
-        .get("type")
-        .and_then(|t| t.as_str())
-        .ok_or_else(|| miette::miette!("schema object missing 'type' field"))?;
+    let type_str = obj.get("type").and_then(|t| t.as_str()).ok_or_else(|| {
+        miette::miette!(
+            code = ErrorKind::InvalidImport.code(),
+            "schema object missing 'type' field"
+        )
+    })?;
 
     match type_str {
         "record" | "error" => parse_record(obj, type_str, default_namespace),
@@ -455,7 +1113,10 @@ fn object_to_schema(
             parse_annotated_primitive(obj, prim, default_namespace)
         }
 
-        other => Err(miette::miette!("unknown schema type: {other}")),
+        other => Err(miette::miette!(
+            code = ErrorKind::InvalidImport.code(),
+            "unknown schema type: {other}"
+        )),
     }
 }
 
@@ -481,10 +1142,12 @@ fn parse_record(
     type_str: &str,
     default_namespace: Option<&str>,
 ) -> Result<AvroSchema> {
-    let raw_name = obj
-        .get("name")
-        .and_then(|n| n.as_str())
-        .ok_or_else(|| miette::miette!("record missing 'name'"))?;
+    let raw_name = obj.get("name").and_then(|n| n.as_str()).ok_or_else(|| {
+        miette::miette!(
+            code = ErrorKind::InvalidImport.code(),
+            "record missing 'name'"
+        )
+    })?;
     let (name, inferred_ns) = split_qualified_name(raw_name);
     let namespace = obj
         .get("namespace")
@@ -507,7 +1170,10 @@ fn parse_record(
             .enumerate()
             .map(|(i, f)| {
                 json_to_field(f, ns_for_fields).map_err(|e| {
-                    miette::miette!("parse field at index {i} of record `{name}`: {e}")
+                    miette::miette!(
+                        code = ErrorKind::InvalidImport.code(),
+                        "parse field at index {i} of record `{name}`: {e}"
+                    )
                 })
             })
             .collect::<Result<Vec<_>>>()?
@@ -537,10 +1203,12 @@ fn parse_enum(
     obj: &serde_json::Map<String, Value>,
     default_namespace: Option<&str>,
 ) -> Result<AvroSchema> {
-    let raw_name = obj
-        .get("name")
-        .and_then(|n| n.as_str())
-        .ok_or_else(|| miette::miette!("enum missing 'name'"))?;
+    let raw_name = obj.get("name").and_then(|n| n.as_str()).ok_or_else(|| {
+        miette::miette!(
+            code = ErrorKind::InvalidImport.code(),
+            "enum missing 'name'"
+        )
+    })?;
     let (name, inferred_ns) = split_qualified_name(raw_name);
     let namespace = obj
         .get("namespace")
@@ -587,10 +1255,12 @@ fn parse_fixed(
     obj: &serde_json::Map<String, Value>,
     default_namespace: Option<&str>,
 ) -> Result<AvroSchema> {
-    let raw_name = obj
-        .get("name")
-        .and_then(|n| n.as_str())
-        .ok_or_else(|| miette::miette!("fixed missing 'name'"))?;
+    let raw_name = obj.get("name").and_then(|n| n.as_str()).ok_or_else(|| {
+        miette::miette!(
+            code = ErrorKind::InvalidImport.code(),
+            "fixed missing 'name'"
+        )
+    })?;
     let (name, inferred_ns) = split_qualified_name(raw_name);
     let namespace = obj
         .get("namespace")
@@ -602,12 +1272,19 @@ fn parse_fixed(
         .get("doc")
         .and_then(|d| d.as_str())
         .map(|s| s.to_string());
-    let size_u64 = obj
-        .get("size")
-        .and_then(|s| s.as_u64())
-        .ok_or_else(|| miette::miette!("fixed missing 'size'"))?;
-    let size = u32::try_from(size_u64)
-        .map_err(|_| miette::miette!("fixed size {size_u64} exceeds maximum ({})", u32::MAX))?;
+    let size_u64 = obj.get("size").and_then(|s| s.as_u64()).ok_or_else(|| {
+        miette::miette!(
+            code = ErrorKind::InvalidImport.code(),
+            "fixed missing 'size'"
+        )
+    })?;
+    let size = u32::try_from(size_u64).map_err(|_| {
+        miette::miette!(
+            code = ErrorKind::InvalidImport.code(),
+            "fixed size {size_u64} exceeds maximum ({})",
+            u32::MAX
+        )
+    })?;
     let aliases = extract_string_array(obj.get("aliases"));
 
     let properties = collect_extra_properties(
@@ -633,11 +1310,18 @@ fn parse_array(
     obj: &serde_json::Map<String, Value>,
     default_namespace: Option<&str>,
 ) -> Result<AvroSchema> {
-    let items = obj
-        .get("items")
-        .ok_or_else(|| miette::miette!("array missing 'items'"))?;
-    let items_schema = json_to_schema(items, default_namespace)
-        .map_err(|e| miette::miette!("parse array items schema: {e}"))?;
+    let items = obj.get("items").ok_or_else(|| {
+        miette::miette!(
+            code = ErrorKind::InvalidImport.code(),
+            "array missing 'items'"
+        )
+    })?;
+    let items_schema = json_to_schema(items, default_namespace).map_err(|e| {
+        miette::miette!(
+            code = ErrorKind::InvalidImport.code(),
+            "parse array items schema: {e}"
+        )
+    })?;
     let properties = collect_extra_properties(obj, &["type", "items"]);
     Ok(AvroSchema::Array {
         items: Box::new(items_schema),
@@ -649,11 +1333,18 @@ fn parse_map(
     obj: &serde_json::Map<String, Value>,
     default_namespace: Option<&str>,
 ) -> Result<AvroSchema> {
-    let values = obj
-        .get("values")
-        .ok_or_else(|| miette::miette!("map missing 'values'"))?;
-    let values_schema = json_to_schema(values, default_namespace)
-        .map_err(|e| miette::miette!("parse map values schema: {e}"))?;
+    let values = obj.get("values").ok_or_else(|| {
+        miette::miette!(
+            code = ErrorKind::InvalidImport.code(),
+            "map missing 'values'"
+        )
+    })?;
+    let values_schema = json_to_schema(values, default_namespace).map_err(|e| {
+        miette::miette!(
+            code = ErrorKind::InvalidImport.code(),
+            "parse map values schema: {e}"
+        )
+    })?;
     let properties = collect_extra_properties(obj, &["type", "values"]);
     Ok(AvroSchema::Map {
         values: Box::new(values_schema),
@@ -693,7 +1384,10 @@ fn parse_annotated_primitive(
             if let LogicalType::Decimal { precision, .. } = &lt
                 && *precision < 1
             {
-                miette::bail!("decimal precision must be >= 1");
+                miette::bail!(
+                    code = ErrorKind::InvalidImport.code(),
+                    "decimal precision must be >= 1"
+                );
             }
 
             let properties =
@@ -729,7 +1423,7 @@ fn parse_annotated_primitive(
 fn primitive_from_str(name: &str) -> Result<AvroSchema> {
     name.parse::<PrimitiveType>()
         .map(|p| p.to_schema())
-        .map_err(|e| miette::miette!("{e}"))
+        .map_err(|e| miette::miette!(code = ErrorKind::InvalidImport.code(), "{e}"))
 }
 
 /// Map a primitive type name string to its `PrimitiveType` variant.
@@ -749,20 +1443,35 @@ fn json_to_field(
     json: &Value,
     default_namespace: Option<&str>,
 ) -> Result<crate::model::schema::Field> {
-    let obj = json
-        .as_object()
-        .ok_or_else(|| miette::miette!("field must be an object"))?;
+    let obj = json.as_object().ok_or_else(|| {
+        miette::miette!(
+            code = ErrorKind::InvalidImport.code(),
+            "field must be an object"
+        )
+    })?;
 
     let name = obj
         .get("name")
         .and_then(|n| n.as_str())
-        .ok_or_else(|| miette::miette!("field missing 'name'"))?
+        .ok_or_else(|| {
+            miette::miette!(
+                code = ErrorKind::InvalidImport.code(),
+                "field missing 'name'"
+            )
+        })?
         .to_string();
-    let type_json = obj
-        .get("type")
-        .ok_or_else(|| miette::miette!("field missing 'type'"))?;
-    let schema = json_to_schema(type_json, default_namespace)
-        .map_err(|e| miette::miette!("parse type for field `{name}`: {e}"))?;
+    let type_json = obj.get("type").ok_or_else(|| {
+        miette::miette!(
+            code = ErrorKind::InvalidImport.code(),
+            "field missing 'type'"
+        )
+    })?;
+    let schema = json_to_schema(type_json, default_namespace).map_err(|e| {
+        miette::miette!(
+            code = ErrorKind::InvalidImport.code(),
+            "parse type for field `{name}`: {e}"
+        )
+    })?;
     let doc = obj
         .get("doc")
         .and_then(|d| d.as_str())
@@ -796,9 +1505,12 @@ fn json_to_field(
 }
 
 fn json_to_message(json: &Value, default_namespace: Option<&str>) -> Result<Message> {
-    let obj = json
-        .as_object()
-        .ok_or_else(|| miette::miette!("message must be an object"))?;
+    let obj = json.as_object().ok_or_else(|| {
+        miette::miette!(
+            code = ErrorKind::InvalidImport.code(),
+            "message must be an object"
+        )
+    })?;
 
     let doc = obj
         .get("doc")
@@ -814,8 +1526,12 @@ fn json_to_message(json: &Value, default_namespace: Option<&str>) -> Result<Mess
             .iter()
             .enumerate()
             .map(|(i, p)| {
-                json_to_field(p, default_namespace)
-                    .map_err(|e| miette::miette!("parse request parameter at index {i}: {e}"))
+                json_to_field(p, default_namespace).map_err(|e| {
+                    miette::miette!(
+                        code = ErrorKind::InvalidImport.code(),
+                        "parse request parameter at index {i}: {e}"
+                    )
+                })
             })
             .collect::<Result<Vec<_>>>()?
     } else {
@@ -823,8 +1539,12 @@ fn json_to_message(json: &Value, default_namespace: Option<&str>) -> Result<Mess
     };
 
     let response = if let Some(resp) = obj.get("response") {
-        json_to_schema(resp, default_namespace)
-            .map_err(|e| miette::miette!("parse response type for message: {e}"))?
+        json_to_schema(resp, default_namespace).map_err(|e| {
+            miette::miette!(
+                code = ErrorKind::InvalidImport.code(),
+                "parse response type for message: {e}"
+            )
+        })?
     } else {
         AvroSchema::Null
     };
@@ -835,7 +1555,10 @@ fn json_to_message(json: &Value, default_namespace: Option<&str>) -> Result<Mess
                 .enumerate()
                 .map(|(i, e)| {
                     json_to_schema(e, default_namespace).map_err(|e| {
-                        miette::miette!("parse error type at index {i} for message: {e}")
+                        miette::miette!(
+                            code = ErrorKind::InvalidImport.code(),
+                            "parse error type at index {i} for message: {e}"
+                        )
                     })
                 })
                 .collect::<Result<Vec<_>>>()?,
@@ -878,8 +1601,8 @@ fn extract_string_array(value: Option<&Value>) -> Vec<String> {
 fn collect_extra_properties(
     obj: &serde_json::Map<String, Value>,
     known_keys: &[&str],
-) -> HashMap<String, Value> {
-    let mut properties = HashMap::new();
+) -> Properties {
+    let mut properties = Properties::new();
     for (k, v) in obj {
         if !known_keys.contains(&k.as_str()) {
             properties.insert(k.clone(), v.clone());
@@ -919,6 +1642,227 @@ mod tests {
         );
     }
 
+    #[test]
+    fn mapped_prefixes_resolve_under_their_directory() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        std::fs::create_dir_all(dir.path().join("shared/money")).expect("create dirs");
+        std::fs::write(dir.path().join("shared/common.avdl"), "").expect("write file");
+        std::fs::write(dir.path().join("shared/money/common.avdl"), "").expect("write file");
+        let mut ctx = ImportContext::new(vec![]);
+        ctx.add_mapping("@shared/".to_string(), dir.path().join("shared"));
+        ctx.add_mapping(
+            "@shared/money/".to_string(),
+            dir.path().join("shared/money"),
+        );
+
+        let resolved = ctx
+            .resolve_import("@shared/common.avdl", Path::new("elsewhere"))
+            .expect("mapped import resolves");
+        assert_eq!(
+            resolved,
+            dir.path()
+                .join("shared/common.avdl")
+                .canonicalize()
+                .unwrap()
+        );
+        // The longest matching prefix wins.
+        let resolved = ctx
+            .resolve_import("@shared/money/common.avdl", Path::new("elsewhere"))
+            .expect("mapped import resolves");
+        assert_eq!(
+            resolved,
+            dir.path()
+                .join("shared/money/common.avdl")
+                .canonicalize()
+                .unwrap()
+        );
+        // A mapped path is not looked for anywhere else.
+        let err = ctx
+            .resolve_import("@shared/missing.avdl", dir.path())
+            .unwrap_err();
+        assert!(err.to_string().contains("mapped to"), "{err}");
+    }
+
+    #[test]
+    fn archives_are_searched_like_directories() {
+        use std::io::Write as _;
+
+        let dir = tempfile::tempdir().expect("create temp dir");
+        let jar = dir.path().join("schemas.jar");
+        let mut writer = zip::ZipWriter::new(File::create(&jar).expect("create jar"));
+        for (name, content) in [
+            ("com/acme/money.avdl", "money"),
+            ("com/acme/currency.avsc", "currency"),
+        ] {
+            writer
+                .start_file(name, zip::write::SimpleFileOptions::default())
+                .expect("start entry");
+            writer.write_all(content.as_bytes()).expect("write entry");
+        }
+        writer.finish().expect("finish jar");
+        let ctx = ImportContext::new(vec![dir.path().join("missing"), jar.clone()]);
+
+        let money = ctx
+            .resolve_import("com/acme/money.avdl", dir.path())
+            .expect("file in archive resolves");
+        let archive = jar.canonicalize().expect("canonicalize jar");
+        assert_eq!(money, archive.join("com/acme/money.avdl"));
+        assert_eq!(ctx.read_source(&money, "IDL").expect("read"), "money");
+
+        // Relative to a file in the archive.
+        let currency = ctx
+            .resolve_import("currency.avsc", money.parent().expect("has parent"))
+            .expect("sibling in archive resolves");
+        assert_eq!(
+            ctx.read_source(&currency, "schema").expect("read"),
+            "currency"
+        );
+
+        assert!(
+            ctx.resolve_import("com/acme/other.avdl", dir.path())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn earlier_overlays_shadow_later_ones() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        let patched = dir.path().join("patched");
+        let shared = dir.path().join("shared");
+        std::fs::create_dir_all(patched.join("acme")).expect("create dirs");
+        std::fs::create_dir_all(shared.join("acme")).expect("create dirs");
+        for path in ["acme/money.avdl", "acme/currency.avdl"] {
+            std::fs::write(shared.join(path), "").expect("write file");
+        }
+        std::fs::write(patched.join("acme/currency.avdl"), "").expect("write file");
+        let mut ctx = ImportContext::new(vec![]);
+        ctx.add_overlay(patched.clone());
+        ctx.add_overlay(shared.clone());
+
+        let money = ctx
+            .resolve_import_root("acme/money.avdl", dir.path())
+            .expect("overlaid import resolves");
+        assert_eq!(
+            money.path,
+            shared.join("acme/money.avdl").canonicalize().unwrap()
+        );
+        assert_eq!(money.root, Some(shared.clone()));
+        // Creating the file in the patch overlay would shadow it.
+        assert_eq!(
+            money.missed,
+            Some(vec![
+                dir.path().join("acme/money.avdl"),
+                patched.join("acme/money.avdl")
+            ])
+        );
+
+        // A sibling import from a file in the shared tree picks up the patch.
+        let currency = ctx
+            .resolve_import_root("currency.avdl", money.path.parent().expect("has parent"))
+            .expect("sibling import resolves");
+        assert_eq!(
+            currency.path,
+            patched.join("acme/currency.avdl").canonicalize().unwrap()
+        );
+        assert_eq!(currency.root, Some(patched));
+        assert_eq!(currency.missed, Some(vec![]));
+    }
+
+    #[test]
+    fn in_memory_sources_resolve_before_the_filesystem() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        std::fs::write(dir.path().join("common.avdl"), "on disk").expect("write file");
+        let mut ctx = ImportContext::new(vec![]);
+        ctx.add_source(
+            Path::new("./shared/../common.avdl"),
+            "in memory".to_string(),
+        );
+        ctx.add_source(Path::new("lib/util.avdl"), "util".to_string());
+
+        // By name alone, from any importing directory.
+        let resolved = ctx
+            .resolve_import("common.avdl", dir.path())
+            .expect("in-memory file resolves");
+        assert_eq!(resolved, PathBuf::from("common.avdl"));
+        assert_eq!(
+            ctx.read_source(&resolved, "IDL").expect("readable"),
+            "in memory"
+        );
+
+        // Relative to an in-memory importer's directory.
+        let resolved = ctx
+            .resolve_import("util.avdl", Path::new("lib"))
+            .expect("sibling in-memory file resolves");
+        assert_eq!(resolved, PathBuf::from("lib/util.avdl"));
+    }
+
+    // =========================================================================
+    // Import suggestion tests
+    // =========================================================================
+
+    #[test]
+    fn idl_type_names_follow_the_reader_namespace_rules() {
+        let names = idl_type_names(
+            r#"@namespace("com.app") protocol P {
+                record Plain { int x; }
+                @namespace("com.other") record Annotated { int x; }
+                record com.dotted.Dotted { int x; }
+            }"#,
+        );
+        assert_eq!(
+            names,
+            vec!["com.app.Plain", "com.other.Annotated", "com.dotted.Dotted",]
+        );
+        assert_eq!(
+            idl_type_names("namespace com.app; fixed Hash(16);"),
+            vec!["com.app.Hash"]
+        );
+    }
+
+    #[test]
+    fn suggest_imports_prefers_exact_names() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        std::fs::create_dir(dir.path().join("common")).expect("create subdir");
+        std::fs::write(
+            dir.path().join("common/types.avdl"),
+            r#"@namespace("com.common") protocol T { record Address { string street; } }"#,
+        )
+        .expect("write .avdl");
+        std::fs::write(
+            dir.path().join("address.avsc"),
+            r#"{"type": "record", "name": "Address", "namespace": "com.legacy", "fields": []}"#,
+        )
+        .expect("write .avsc");
+        let ctx = ImportContext::new(vec![dir.path().to_path_buf()]);
+
+        assert_eq!(
+            ctx.suggest_imports("com.common.Address"),
+            vec![ImportSuggestion {
+                statement: r#"import idl "common/types.avdl";"#.to_string(),
+                full_name: "com.common.Address".to_string(),
+            }]
+        );
+        let statements: Vec<_> = ctx
+            .suggest_imports("com.app.Address")
+            .into_iter()
+            .map(|suggestion| (suggestion.statement, suggestion.full_name))
+            .collect();
+        assert_eq!(
+            statements,
+            vec![
+                (
+                    r#"import schema "address.avsc";"#.to_string(),
+                    "com.legacy.Address".to_string()
+                ),
+                (
+                    r#"import idl "common/types.avdl";"#.to_string(),
+                    "com.common.Address".to_string()
+                ),
+            ]
+        );
+        assert!(ctx.suggest_imports("com.app.Missing").is_empty());
+    }
+
     // =========================================================================
     // json_to_schema tests
     // =========================================================================
@@ -943,7 +1887,7 @@ mod tests {
             AvroSchema::Reference {
                 name: "Foo".to_string(),
                 namespace: Some("org.example".to_string()),
-                properties: HashMap::new(),
+                properties: Properties::new(),
                 span: None,
             }
         );
@@ -958,7 +1902,7 @@ mod tests {
             AvroSchema::Reference {
                 name: "Bar".to_string(),
                 namespace: Some("com.other".to_string()),
-                properties: HashMap::new(),
+                properties: Properties::new(),
                 span: None,
             }
         );
@@ -1046,7 +1990,7 @@ mod tests {
             schema,
             AvroSchema::Logical {
                 logical_type: LogicalType::Date,
-                properties: HashMap::new(),
+                properties: Properties::new(),
             }
         );
     }
@@ -1066,7 +2010,7 @@ mod tests {
                     precision: 10,
                     scale: 2,
                 },
-                properties: HashMap::new(),
+                properties: Properties::new(),
             }
         );
     }
@@ -2086,6 +3030,10 @@ mod tests {
     // =========================================================================
     // Error path: protocol JSON parse errors (issue f512e05f, item 6)
     // =========================================================================
+
+    fn read(path: &Path) -> String {
+        std::fs::read_to_string(path).expect("read test file")
+    }
     //
     // import_protocol should propagate errors when types or messages in a
     // .avpr file contain invalid schema structure.
@@ -2109,7 +3057,7 @@ mod tests {
         .expect("write .avpr");
 
         let mut registry = SchemaRegistry::new();
-        let result = import_protocol(&avpr_path, &mut registry);
+        let result = import_protocol(&avpr_path, &read(&avpr_path), &mut registry);
         let err = result.expect_err("invalid type in protocol should be rejected");
         let rendered = crate::error::render_diagnostic(&err);
         let stable = rendered.replace(&dir.path().display().to_string(), "<tmpdir>");
@@ -2138,7 +3086,7 @@ mod tests {
         .expect("write .avpr");
 
         let mut registry = SchemaRegistry::new();
-        let result = import_protocol(&avpr_path, &mut registry);
+        let result = import_protocol(&avpr_path, &read(&avpr_path), &mut registry);
         let err = result.expect_err("invalid message in protocol should be rejected");
         let rendered = crate::error::render_diagnostic(&err);
         let stable = rendered.replace(&dir.path().display().to_string(), "<tmpdir>");
@@ -2148,10 +3096,8 @@ mod tests {
     #[test]
     #[cfg_attr(windows, ignore)]
     fn import_protocol_rejects_unreadable_file() {
-        let result = import_protocol(
-            Path::new("/nonexistent/path/to/missing.avpr"),
-            &mut SchemaRegistry::new(),
-        );
+        let result = ImportContext::new(Vec::new())
+            .read_source(Path::new("/nonexistent/path/to/missing.avpr"), "protocol");
         let err = result.expect_err("missing file should produce an error");
         insta::assert_snapshot!(crate::error::render_diagnostic(&err));
     }
@@ -2163,7 +3109,7 @@ mod tests {
         let avpr_path = dir.path().join("bad-json.avpr");
         std::fs::write(&avpr_path, "{ this is not valid json }").expect("write .avpr");
 
-        let result = import_protocol(&avpr_path, &mut SchemaRegistry::new());
+        let result = import_protocol(&avpr_path, &read(&avpr_path), &mut SchemaRegistry::new());
         let err = result.expect_err("invalid JSON should produce an error");
         let rendered = crate::error::render_diagnostic(&err);
         let stable = rendered.replace(&dir.path().display().to_string(), "<tmpdir>");
@@ -2173,10 +3119,8 @@ mod tests {
     #[test]
     #[cfg_attr(windows, ignore)]
     fn import_schema_rejects_unreadable_file() {
-        let result = import_schema(
-            Path::new("/nonexistent/path/to/missing.avsc"),
-            &mut SchemaRegistry::new(),
-        );
+        let result = ImportContext::new(Vec::new())
+            .read_source(Path::new("/nonexistent/path/to/missing.avsc"), "schema");
         let err = result.expect_err("missing file should produce an error");
         insta::assert_snapshot!(crate::error::render_diagnostic(&err));
     }
@@ -2188,7 +3132,7 @@ mod tests {
         let avsc_path = dir.path().join("bad-json.avsc");
         std::fs::write(&avsc_path, "not valid json").expect("write .avsc");
 
-        let result = import_schema(&avsc_path, &mut SchemaRegistry::new());
+        let result = import_schema(&avsc_path, &read(&avsc_path), &mut SchemaRegistry::new());
         let err = result.expect_err("invalid JSON should produce an error");
         let rendered = crate::error::render_diagnostic(&err);
         let stable = rendered.replace(&dir.path().display().to_string(), "<tmpdir>");
@@ -2203,7 +3147,7 @@ mod tests {
         // Valid JSON, but not a valid schema (a boolean).
         std::fs::write(&avsc_path, "true").expect("write .avsc");
 
-        let result = import_schema(&avsc_path, &mut SchemaRegistry::new());
+        let result = import_schema(&avsc_path, &read(&avsc_path), &mut SchemaRegistry::new());
         let err = result.expect_err("invalid schema structure should produce an error");
         let rendered = crate::error::render_diagnostic(&err);
         let stable = rendered.replace(&dir.path().display().to_string(), "<tmpdir>");
@@ -0,0 +1,253 @@
+// ==============================================================================
+// ISO-8601 Defaults for Date and Time Logical Types
+// ==============================================================================
+//
+// Avro stores `date` as days since the Unix epoch, times of day as
+// milliseconds or microseconds since midnight, and timestamps as milliseconds
+// or microseconds since the epoch. Writing those numbers by hand in a default
+// is error-prone, so `Idl::iso8601_defaults` lets a default be written as an
+// ISO-8601 string instead, which is converted here.
+//
+// Only the extended formats are accepted: `2024-01-31`, `13:45:00.250`, and
+// `2024-01-31T13:45:00Z` (with `Z` or an offset like `+01:00` for UTC
+// timestamps, and without one for local timestamps).
+
+use crate::model::schema::LogicalType;
+
+/// Convert an ISO-8601 string default to the number that `logical_type`
+/// stores, or explain why it is not a valid one. Returns `None` for logical
+/// types that are not dates or times.
+pub(crate) fn iso8601_default(
+    text: &str,
+    logical_type: &LogicalType,
+) -> Option<Result<i64, String>> {
+    let converted = match logical_type {
+        LogicalType::Date => parse_date(text)
+            .filter(|days| i32::try_from(*days).is_ok())
+            .ok_or_else(|| format!("expected an ISO-8601 date like `2024-01-31`, got `{text}`")),
+        LogicalType::TimeMillis => parse_time(text)
+            .ok_or_else(|| format!("expected an ISO-8601 time like `13:45:00.250`, got `{text}`"))
+            .and_then(|time| time.to_unit(1_000, "time-millis")),
+        LogicalType::TimeMicros => parse_time(text)
+            .ok_or_else(|| {
+                format!("expected an ISO-8601 time like `13:45:00.000250`, got `{text}`")
+            })
+            .and_then(|time| time.to_unit(1_000_000, "time-micros")),
+        LogicalType::TimestampMillis => parse_timestamp(text, true)
+            .ok_or_else(|| utc_expected(text))
+            .and_then(|time| time.to_unit(1_000, "timestamp-millis")),
+        LogicalType::TimestampMicros => parse_timestamp(text, true)
+            .ok_or_else(|| utc_expected(text))
+            .and_then(|time| time.to_unit(1_000_000, "timestamp-micros")),
+        LogicalType::LocalTimestampMillis => parse_timestamp(text, false)
+            .ok_or_else(|| local_expected(text))
+            .and_then(|time| time.to_unit(1_000, "local-timestamp-millis")),
+        LogicalType::LocalTimestampMicros => parse_timestamp(text, false)
+            .ok_or_else(|| local_expected(text))
+            .and_then(|time| time.to_unit(1_000_000, "local-timestamp-micros")),
+        LogicalType::Uuid | LogicalType::Decimal { .. } => return None,
+    };
+    Some(converted)
+}
+
+fn utc_expected(text: &str) -> String {
+    format!(
+        "expected an ISO-8601 timestamp with an offset like `2024-01-31T13:45:00Z`, got `{text}`"
+    )
+}
+
+fn local_expected(text: &str) -> String {
+    format!(
+        "expected an ISO-8601 timestamp without an offset like `2024-01-31T13:45:00`, \
+         got `{text}`"
+    )
+}
+
+/// Whole seconds plus a fraction, counted from midnight or from the epoch.
+struct Elapsed {
+    seconds: i64,
+    nanos: u32,
+}
+
+impl Elapsed {
+    /// The elapsed time in units of `1 / per_second` seconds, refusing to round
+    /// away digits of the fraction that the unit cannot hold.
+    fn to_unit(&self, per_second: i64, logical_name: &str) -> Result<i64, String> {
+        let nanos_per_unit = 1_000_000_000 / per_second as u32;
+        if !self.nanos.is_multiple_of(nanos_per_unit) {
+            return Err(format!(
+                "more fractional digits than {logical_name} can hold"
+            ));
+        }
+        self.seconds
+            .checked_mul(per_second)
+            .and_then(|units| units.checked_add(i64::from(self.nanos / nanos_per_unit)))
+            .ok_or_else(|| format!("out of range for {logical_name}"))
+    }
+}
+
+/// Parse `YYYY-MM-DD` into days since 1970-01-01.
+fn parse_date(text: &str) -> Option<i64> {
+    let mut parts = text.split('-');
+    let (year, month, day) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() || year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return None;
+    }
+    let year = parse_digits(year)?;
+    let month = parse_digits(month)?;
+    let day = parse_digits(day)?;
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+    Some(days_from_civil(year, month, day))
+}
+
+/// Parse `HH:MM:SS` with an optional fraction of up to nine digits into the
+/// time since midnight.
+fn parse_time(text: &str) -> Option<Elapsed> {
+    let (clock, fraction) = match text.split_once('.') {
+        Some((clock, fraction)) => (clock, Some(fraction)),
+        None => (text, None),
+    };
+    let mut parts = clock.split(':');
+    let (hour, minute, second) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() || [hour, minute, second].iter().any(|p| p.len() != 2) {
+        return None;
+    }
+    let hour = parse_digits(hour)?;
+    let minute = parse_digits(minute)?;
+    let second = parse_digits(second)?;
+    if hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    let nanos = match fraction {
+        Some(digits) if (1..=9).contains(&digits.len()) => {
+            let value = u32::try_from(parse_digits(digits)?).ok()?;
+            value * 10u32.pow(9 - digits.len() as u32)
+        }
+        Some(_) => return None,
+        None => 0,
+    };
+    Some(Elapsed {
+        seconds: hour * 3600 + minute * 60 + second,
+        nanos,
+    })
+}
+
+/// Parse `YYYY-MM-DDTHH:MM:SS[.fraction]` into the time since the epoch. With
+/// `with_offset`, the time must end in `Z` or `±HH:MM`; without it, it must
+/// not have an offset at all.
+fn parse_timestamp(text: &str, with_offset: bool) -> Option<Elapsed> {
+    let (date, time) = text.split_once('T')?;
+    let days = parse_date(date)?;
+    let (time, offset) = if with_offset {
+        split_offset(time)?
+    } else {
+        (time, 0)
+    };
+    let time = parse_time(time)?;
+    Some(Elapsed {
+        seconds: days * 86_400 + time.seconds - offset,
+        nanos: time.nanos,
+    })
+}
+
+/// Split a trailing `Z` or `±HH:MM` off a time, returning the offset in
+/// seconds east of UTC.
+fn split_offset(time: &str) -> Option<(&str, i64)> {
+    if let Some(time) = time.strip_suffix('Z') {
+        return Some((time, 0));
+    }
+    let at = time.len().checked_sub(6)?;
+    let (time, offset) = time.split_at_checked(at)?;
+    let sign = match offset.as_bytes()[0] {
+        b'+' => 1,
+        b'-' => -1,
+        _ => return None,
+    };
+    let (hours, minutes) = offset[1..].split_once(':')?;
+    if hours.len() != 2 || minutes.len() != 2 {
+        return None;
+    }
+    let hours = parse_digits(hours)?;
+    let minutes = parse_digits(minutes)?;
+    if hours > 23 || minutes > 59 {
+        return None;
+    }
+    Some((time, sign * (hours * 3600 + minutes * 60)))
+}
+
+/// Parse a non-empty run of ASCII digits.
+fn parse_digits(text: &str) -> Option<i64> {
+    if text.is_empty() || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date, using Howard
+/// Hinnant's `days_from_civil` algorithm.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_from_march = (month + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn dates_and_times_convert_to_avro_numbers() {
+        let convert = |text, logical_type| iso8601_default(text, &logical_type).unwrap();
+        assert_eq!(convert("1970-01-01", LogicalType::Date), Ok(0));
+        assert_eq!(convert("2024-02-29", LogicalType::Date), Ok(19_782));
+        assert_eq!(convert("1969-12-31", LogicalType::Date), Ok(-1));
+        assert_eq!(convert("00:00:01.5", LogicalType::TimeMillis), Ok(1_500));
+        assert_eq!(
+            convert("23:59:59.000001", LogicalType::TimeMicros),
+            Ok(86_399_000_001)
+        );
+        assert_eq!(
+            convert("2024-01-31T13:45:00Z", LogicalType::TimestampMillis),
+            Ok(1_706_708_700_000)
+        );
+        assert_eq!(
+            convert("2024-01-31T14:45:00+01:00", LogicalType::TimestampMicros),
+            Ok(1_706_708_700_000_000)
+        );
+        assert_eq!(
+            convert("2024-01-31T13:45:00", LogicalType::LocalTimestampMillis),
+            Ok(1_706_708_700_000)
+        );
+        assert_eq!(iso8601_default("x", &LogicalType::Uuid), None);
+    }
+
+    #[test]
+    fn malformed_or_imprecise_values_are_rejected() {
+        let convert = |text, logical_type| iso8601_default(text, &logical_type).unwrap();
+        assert!(convert("2023-02-29", LogicalType::Date).is_err());
+        assert!(convert("2024-1-31", LogicalType::Date).is_err());
+        assert!(convert("24:00:00", LogicalType::TimeMillis).is_err());
+        assert_eq!(
+            convert("00:00:00.0001", LogicalType::TimeMillis),
+            Err("more fractional digits than time-millis can hold".to_string())
+        );
+        assert!(convert("2024-01-31T13:45:00", LogicalType::TimestampMillis).is_err());
+        assert!(convert("2024-01-31T13:45:00Z", LogicalType::LocalTimestampMillis).is_err());
+    }
+}
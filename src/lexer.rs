@@ -0,0 +1,813 @@
+// ==============================================================================
+// Lexer
+// ==============================================================================
+//
+// A hand-written lexer for Avro IDL that splits source exactly the way the
+// grammar's lexer does (see `Idl.g4`): the longest token any rule matches
+// wins, ties going to the rule listed first, so `record` is a keyword but
+// `records` and `record.x` are identifiers, and `NaN` is a number.
+//
+// Unlike the ANTLR lexer, this one also returns the whitespace and comments
+// the grammar skips, so it serves the lossless syntax tree and syntax
+// highlighting too; the parser just looks through them.
+//
+// Where no rule matches, ANTLR reports everything from the start of the
+// attempted token up to and including the first character no rule could
+// continue with, and resumes after that character. That is reproduced here,
+// message and all, because callers turn these errors into diagnostics.
+
+use std::ops::Range;
+
+/// The type of a [`Token`], named like the grammar's token types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum TokenType {
+    /// The `\u001a` (SUB) character, which ends the input.
+    Sub,
+    DocComment,
+    EmptyComment,
+    MultiLineComment,
+    SingleLineComment,
+    /// A run of whitespace.
+    WS,
+    Protocol,
+    Namespace,
+    Import,
+    Idl,
+    Schema,
+    Enum,
+    Fixed,
+    Error,
+    Record,
+    Array,
+    Map,
+    Union,
+    Boolean,
+    Int,
+    Long,
+    Float,
+    Double,
+    String,
+    Bytes,
+    Null,
+    BTrue,
+    BFalse,
+    Decimal,
+    Date,
+    Time,
+    Timestamp,
+    LocalTimestamp,
+    Uuid,
+    Void,
+    Oneway,
+    Throws,
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    Colon,
+    Semicolon,
+    Comma,
+    At,
+    Equals,
+    Dot,
+    Dash,
+    QuestionMark,
+    LT,
+    GT,
+    StringLiteral,
+    IntegerLiteral,
+    FloatingPointLiteral,
+    IdentifierToken,
+}
+
+/// The keywords, in the order the grammar lists them.
+const KEYWORDS: [(&str, TokenType); 31] = [
+    ("protocol", TokenType::Protocol),
+    ("namespace", TokenType::Namespace),
+    ("import", TokenType::Import),
+    ("idl", TokenType::Idl),
+    ("schema", TokenType::Schema),
+    ("enum", TokenType::Enum),
+    ("fixed", TokenType::Fixed),
+    ("error", TokenType::Error),
+    ("record", TokenType::Record),
+    ("array", TokenType::Array),
+    ("map", TokenType::Map),
+    ("union", TokenType::Union),
+    ("boolean", TokenType::Boolean),
+    ("int", TokenType::Int),
+    ("long", TokenType::Long),
+    ("float", TokenType::Float),
+    ("double", TokenType::Double),
+    ("string", TokenType::String),
+    ("bytes", TokenType::Bytes),
+    ("null", TokenType::Null),
+    ("true", TokenType::BTrue),
+    ("false", TokenType::BFalse),
+    ("decimal", TokenType::Decimal),
+    ("date", TokenType::Date),
+    ("time_ms", TokenType::Time),
+    ("timestamp_ms", TokenType::Timestamp),
+    ("local_timestamp_ms", TokenType::LocalTimestamp),
+    ("uuid", TokenType::Uuid),
+    ("void", TokenType::Void),
+    ("oneway", TokenType::Oneway),
+    ("throws", TokenType::Throws),
+];
+
+impl TokenType {
+    /// The grammar's name for the token type, e.g. `Record` or `WS`. The
+    /// SUB character has no name in the grammar and is `Unknown`.
+    #[must_use]
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            TokenType::Sub => "Unknown",
+            TokenType::DocComment => "DocComment",
+            TokenType::EmptyComment => "EmptyComment",
+            TokenType::MultiLineComment => "MultiLineComment",
+            TokenType::SingleLineComment => "SingleLineComment",
+            TokenType::WS => "WS",
+            TokenType::Protocol => "Protocol",
+            TokenType::Namespace => "Namespace",
+            TokenType::Import => "Import",
+            TokenType::Idl => "IDL",
+            TokenType::Schema => "Schema",
+            TokenType::Enum => "Enum",
+            TokenType::Fixed => "Fixed",
+            TokenType::Error => "Error",
+            TokenType::Record => "Record",
+            TokenType::Array => "Array",
+            TokenType::Map => "Map",
+            TokenType::Union => "Union",
+            TokenType::Boolean => "Boolean",
+            TokenType::Int => "Int",
+            TokenType::Long => "Long",
+            TokenType::Float => "Float",
+            TokenType::Double => "Double",
+            TokenType::String => "String",
+            TokenType::Bytes => "Bytes",
+            TokenType::Null => "Null",
+            TokenType::BTrue => "BTrue",
+            TokenType::BFalse => "BFalse",
+            TokenType::Decimal => "Decimal",
+            TokenType::Date => "Date",
+            TokenType::Time => "Time",
+            TokenType::Timestamp => "Timestamp",
+            TokenType::LocalTimestamp => "LocalTimestamp",
+            TokenType::Uuid => "UUID",
+            TokenType::Void => "Void",
+            TokenType::Oneway => "Oneway",
+            TokenType::Throws => "Throws",
+            TokenType::LParen => "LParen",
+            TokenType::RParen => "RParen",
+            TokenType::LBrace => "LBrace",
+            TokenType::RBrace => "RBrace",
+            TokenType::LBracket => "LBracket",
+            TokenType::RBracket => "RBracket",
+            TokenType::Colon => "Colon",
+            TokenType::Semicolon => "Semicolon",
+            TokenType::Comma => "Comma",
+            TokenType::At => "At",
+            TokenType::Equals => "Equals",
+            TokenType::Dot => "Dot",
+            TokenType::Dash => "Dash",
+            TokenType::QuestionMark => "QuestionMark",
+            TokenType::LT => "LT",
+            TokenType::GT => "GT",
+            TokenType::StringLiteral => "StringLiteral",
+            TokenType::IntegerLiteral => "IntegerLiteral",
+            TokenType::FloatingPointLiteral => "FloatingPointLiteral",
+            TokenType::IdentifierToken => "IdentifierToken",
+        }
+    }
+
+    /// How the grammar writes the token in its messages: the keyword or
+    /// punctuation in quotes (`'record'`, `'{'`), or the name of the token
+    /// type for the rest (`IdentifierToken`).
+    #[must_use]
+    pub(crate) fn display(self) -> std::borrow::Cow<'static, str> {
+        if let Some((keyword, _)) = KEYWORDS.iter().find(|(_, t)| *t == self) {
+            return format!("'{keyword}'").into();
+        }
+        let punctuation = match self {
+            TokenType::Sub => "'\\u001A'",
+            TokenType::LParen => "'('",
+            TokenType::RParen => "')'",
+            TokenType::LBrace => "'{'",
+            TokenType::RBrace => "'}'",
+            TokenType::LBracket => "'['",
+            TokenType::RBracket => "']'",
+            TokenType::Colon => "':'",
+            TokenType::Semicolon => "';'",
+            TokenType::Comma => "','",
+            TokenType::At => "'@'",
+            TokenType::Equals => "'='",
+            TokenType::Dot => "'.'",
+            TokenType::Dash => "'-'",
+            TokenType::QuestionMark => "'?'",
+            TokenType::LT => "'<'",
+            TokenType::GT => "'>'",
+            TokenType::EmptyComment => "'/**/'",
+            other => other.name(),
+        };
+        punctuation.into()
+    }
+
+    /// Whether the token is whitespace or a comment, which the parser skips.
+    #[must_use]
+    pub(crate) fn is_trivia(self) -> bool {
+        matches!(
+            self,
+            TokenType::WS
+                | TokenType::DocComment
+                | TokenType::EmptyComment
+                | TokenType::MultiLineComment
+                | TokenType::SingleLineComment
+        )
+    }
+
+    /// Whether the token is a keyword, which may also be used as a name.
+    #[must_use]
+    pub(crate) fn is_keyword(self) -> bool {
+        KEYWORDS.iter().any(|(_, t)| *t == self)
+    }
+}
+
+/// A token and its byte range in the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Token {
+    pub(crate) token_type: TokenType,
+    pub(crate) span: Range<usize>,
+}
+
+/// Characters no token could be made of.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LexError {
+    /// The characters skipped, up to and including the first one that no
+    /// rule could continue with.
+    pub(crate) span: Range<usize>,
+    /// The 1-based line and 0-based column (in characters) where the
+    /// skipped characters start.
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+    /// The message ANTLR gives, e.g. `token recognition error at: '#'`.
+    pub(crate) message: String,
+}
+
+/// Split `source` into tokens, whitespace and comments included, and the
+/// characters between them that do not form a token.
+#[must_use]
+pub(crate) fn lex(source: &str) -> (Vec<Token>, Vec<LexError>) {
+    let mut tokens = Vec::new();
+    let mut errors = Vec::new();
+    let mut position = Position::default();
+    let mut start = 0;
+    while start < source.len() {
+        let scan = scan(source, start);
+        match scan.accept {
+            Some((token_type, end)) => {
+                tokens.push(Token {
+                    token_type,
+                    span: start..end,
+                });
+                start = end;
+            }
+            None => {
+                let end = next_char_end(source, scan.reach);
+                let (line, column) = position.advance(source, start);
+                let text = &source[start..end];
+                errors.push(LexError {
+                    span: start..end,
+                    line,
+                    column,
+                    message: format!("token recognition error at: '{}'", error_display(text)),
+                });
+                start = end;
+            }
+        }
+    }
+    (tokens, errors)
+}
+
+/// The 1-based line and 0-based column, in characters, of byte `offset` in
+/// `source`, as ANTLR counts them: only `\n` starts a new line.
+#[must_use]
+pub(crate) fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    Position::default().advance(source, offset)
+}
+
+/// A running line and column, so that positions found in order are counted
+/// in one pass over the source.
+#[derive(Default)]
+struct Position {
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Position {
+    fn advance(&mut self, source: &str, offset: usize) -> (usize, usize) {
+        if offset < self.offset {
+            *self = Position::default();
+        }
+        for c in source[self.offset..offset].chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.column = 0;
+            } else {
+                self.column += 1;
+            }
+        }
+        self.offset = offset;
+        (self.line + 1, self.column)
+    }
+}
+
+/// ANTLR's rendering of skipped text in a message: line breaks and tabs are
+/// escaped, everything else is kept.
+fn error_display(text: &str) -> String {
+    let mut display = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\n' => display.push_str("\\n"),
+            '\r' => display.push_str("\\r"),
+            '\t' => display.push_str("\\t"),
+            c => display.push(c),
+        }
+    }
+    display
+}
+
+/// The outcome of matching every rule at one position: the longest token
+/// matched, if any, and how far the source could be read before every rule
+/// failed, which is where an error ends.
+struct Scan {
+    accept: Option<(TokenType, usize)>,
+    /// The byte offset of the first character no rule could continue with,
+    /// or the end of the source.
+    reach: usize,
+}
+
+impl Scan {
+    fn new(start: usize) -> Self {
+        Scan {
+            accept: None,
+            reach: start,
+        }
+    }
+
+    /// Record a match of `token_type` up to `end`, unless an earlier rule
+    /// matched at least as much.
+    fn accept(&mut self, token_type: TokenType, end: usize) {
+        if self.accept.is_none_or(|(_, longest)| end > longest) {
+            self.accept = Some((token_type, end));
+        }
+        self.reach(end);
+    }
+
+    fn reach(&mut self, offset: usize) {
+        self.reach = self.reach.max(offset);
+    }
+
+    fn merge(&mut self, other: Scan) {
+        if let Some((token_type, end)) = other.accept {
+            self.accept(token_type, end);
+        }
+        self.reach(other.reach);
+    }
+}
+
+fn scan(source: &str, start: usize) -> Scan {
+    let mut scan = Scan::new(start);
+    let Some(c) = char_at(source, start) else {
+        return scan;
+    };
+    let punctuation = match c {
+        '\u{1a}' => Some(TokenType::Sub),
+        '(' => Some(TokenType::LParen),
+        ')' => Some(TokenType::RParen),
+        '{' => Some(TokenType::LBrace),
+        '}' => Some(TokenType::RBrace),
+        '[' => Some(TokenType::LBracket),
+        ']' => Some(TokenType::RBracket),
+        ':' => Some(TokenType::Colon),
+        ';' => Some(TokenType::Semicolon),
+        ',' => Some(TokenType::Comma),
+        '@' => Some(TokenType::At),
+        '=' => Some(TokenType::Equals),
+        '?' => Some(TokenType::QuestionMark),
+        '<' => Some(TokenType::LT),
+        '>' => Some(TokenType::GT),
+        _ => None,
+    };
+    if let Some(token_type) = punctuation {
+        scan.accept(token_type, start + 1);
+        return scan;
+    }
+    match c {
+        '\t' | '\n' | '\u{c}' | '\r' | ' ' => {
+            let end = source[start..]
+                .find(|c| !matches!(c, '\t' | '\n' | '\u{c}' | '\r' | ' '))
+                .map_or(source.len(), |n| start + n);
+            scan.accept(TokenType::WS, end);
+        }
+        '/' => scan.merge(comment(source, start)),
+        '"' => scan.merge(string(source, start)),
+        '.' => {
+            scan.accept(TokenType::Dot, start + 1);
+            scan.merge(float(source, start));
+        }
+        '-' => {
+            scan.accept(TokenType::Dash, start + 1);
+            scan.merge(integer(source, start + 1));
+            scan.merge(float(source, start + 1));
+        }
+        '+' => scan.merge(float(source, start + 1)),
+        '0'..='9' => {
+            scan.merge(integer(source, start));
+            scan.merge(float(source, start));
+        }
+        c if c == '`' || is_identifier_start(c) => {
+            if matches!(c, 'N' | 'I') {
+                scan.merge(float(source, start));
+            }
+            scan.merge(identifier(source, start));
+        }
+        _ => {}
+    }
+    scan
+}
+
+/// `DocComment`, `EmptyComment`, `MultiLineComment`, or
+/// `SingleLineComment`, at a `/`.
+fn comment(source: &str, start: usize) -> Scan {
+    let bytes = source.as_bytes();
+    let mut scan = Scan::new(start + 1);
+    match bytes.get(start + 1) {
+        // `'//' .*? ('\n' | '\r' '\n'?)`: the line break is required.
+        Some(b'/') => match source[start + 2..].find(['\n', '\r']) {
+            Some(n) => {
+                let mut end = start + 2 + n + 1;
+                if bytes[end - 1] == b'\r' && bytes.get(end) == Some(&b'\n') {
+                    end += 1;
+                }
+                scan.accept(TokenType::SingleLineComment, end);
+            }
+            None => scan.reach(source.len()),
+        },
+        Some(b'*') => {
+            let close = |from: usize| {
+                source
+                    .get(from..)
+                    .and_then(|rest| rest.find("*/"))
+                    .map(|n| from + n + 2)
+            };
+            if bytes.get(start + 2) == Some(&b'*') {
+                // `'/**' .*? '*/'`
+                if let Some(end) = close(start + 3) {
+                    scan.accept(TokenType::DocComment, end);
+                }
+                // `'/**/'`
+                if bytes.get(start + 3) == Some(&b'/') {
+                    scan.accept(TokenType::EmptyComment, start + 4);
+                }
+            } else if let Some(c) = char_at(source, start + 2) {
+                // `'/*' ~'*' .*? '*/'`
+                if let Some(end) = close(start + 2 + c.len_utf8()) {
+                    scan.accept(TokenType::MultiLineComment, end);
+                }
+            }
+            if scan.accept.is_none() {
+                scan.reach(source.len());
+            }
+        }
+        _ => {}
+    }
+    scan
+}
+
+/// `StringLiteral`, at a `"`.
+fn string(source: &str, start: usize) -> Scan {
+    let mut scan = Scan::new(start);
+    let mut i = start + 1;
+    loop {
+        let Some(c) = char_at(source, i) else {
+            scan.reach(source.len());
+            return scan;
+        };
+        match c {
+            '"' => {
+                scan.accept(TokenType::StringLiteral, i + 1);
+                return scan;
+            }
+            '\u{8}' | '\t' | '\n' | '\u{c}' | '\r' => {
+                scan.reach(i);
+                return scan;
+            }
+            '\\' => match escape(source, i) {
+                Ok(end) => i = end,
+                Err(reach) => {
+                    scan.reach(reach);
+                    return scan;
+                }
+            },
+            c => i += c.len_utf8(),
+        }
+    }
+}
+
+/// The end of the escape sequence at the `\` at `start`, or where it goes
+/// wrong.
+fn escape(source: &str, start: usize) -> Result<usize, usize> {
+    let bytes = source.as_bytes();
+    let octal = |i: usize| bytes.get(i).is_some_and(|b| matches!(b, b'0'..=b'7'));
+    match bytes.get(start + 1) {
+        None => Err(source.len()),
+        Some(b'b' | b't' | b'n' | b'f' | b'r' | b'"' | b'\'' | b'\\') => Ok(start + 2),
+        Some(b'u') => {
+            for i in start + 2..start + 6 {
+                if !bytes.get(i).is_some_and(u8::is_ascii_hexdigit) {
+                    return Err(i.min(source.len()));
+                }
+            }
+            Ok(start + 6)
+        }
+        // `[0-3] [0-7] [0-7]` or `[0-7] [0-7]?`
+        Some(b'0'..=b'3') if octal(start + 2) && octal(start + 3) => Ok(start + 4),
+        Some(b'0'..=b'7') if octal(start + 2) => Ok(start + 3),
+        Some(b'0'..=b'7') => Ok(start + 2),
+        Some(_) => Err(start + 1),
+    }
+}
+
+/// `IntegerLiteral`, with the digits at `digits`, after any `-`.
+fn integer(source: &str, digits: usize) -> Scan {
+    let bytes = source.as_bytes();
+    let mut scan = Scan::new(digits);
+    let end = match bytes.get(digits) {
+        Some(b'1'..=b'9') => skip(bytes, digits, u8::is_ascii_digit),
+        Some(b'0') => {
+            let hex = skip(bytes, digits + 2, u8::is_ascii_hexdigit);
+            if matches!(bytes.get(digits + 1), Some(b'x' | b'X')) && hex > digits + 2 {
+                hex
+            } else {
+                skip(bytes, digits + 1, |b| matches!(b, b'0'..=b'7'))
+            }
+        }
+        _ => return scan,
+    };
+    let end = match bytes.get(end) {
+        Some(b'l' | b'L') => end + 1,
+        _ => end,
+    };
+    scan.accept(TokenType::IntegerLiteral, end);
+    scan
+}
+
+/// `FloatingPointLiteral`, with the number at `number`, after any sign.
+fn float(source: &str, number: usize) -> Scan {
+    let bytes = source.as_bytes();
+    let mut scan = Scan::new(number);
+    let suffix = |end: usize| match bytes.get(end) {
+        Some(b'd' | b'D' | b'f' | b'F') => end + 1,
+        _ => end,
+    };
+    // `[eE] [+-]? Digit+`, or `[pP]` for hexadecimal numbers.
+    let exponent = |at: usize, markers: [u8; 2]| -> Result<usize, usize> {
+        if !bytes.get(at).is_some_and(|b| markers.contains(b)) {
+            return Err(at);
+        }
+        let mut digits = at + 1;
+        if matches!(bytes.get(digits), Some(b'+' | b'-')) {
+            digits += 1;
+        }
+        let end = skip(bytes, digits, u8::is_ascii_digit);
+        if end == digits { Err(end) } else { Ok(end) }
+    };
+
+    for word in ["NaN", "Infinity"] {
+        let matched = source[number..]
+            .bytes()
+            .zip(word.bytes())
+            .take_while(|(a, b)| a == b)
+            .count();
+        if matched == word.len() {
+            scan.accept(TokenType::FloatingPointLiteral, number + matched);
+        } else {
+            scan.reach(number + matched);
+        }
+    }
+
+    // `Digit+ '.' Digit* Exp? Suffix?`, `'.' Digit+ Exp? Suffix?`, and
+    // `Digit+ (Exp Suffix? | Suffix)`.
+    let integral = skip(bytes, number, u8::is_ascii_digit);
+    let (mantissa, has_point) = if bytes.get(integral) == Some(&b'.') {
+        (skip(bytes, integral + 1, u8::is_ascii_digit), true)
+    } else {
+        (integral, false)
+    };
+    let has_digits = mantissa > number + usize::from(has_point);
+    if has_digits {
+        match exponent(mantissa, [b'e', b'E']) {
+            Ok(end) => scan.accept(TokenType::FloatingPointLiteral, suffix(end)),
+            Err(reach) => {
+                scan.reach(reach);
+                if has_point || suffix(mantissa) > mantissa {
+                    scan.accept(TokenType::FloatingPointLiteral, suffix(mantissa));
+                }
+            }
+        }
+    } else {
+        scan.reach(mantissa);
+    }
+
+    // `'0' [xX] (HexDigit+ '.'? | HexDigit* '.' HexDigit+) [pP] [+-]? Digit+
+    // Suffix?`
+    if bytes.get(number) == Some(&b'0') && matches!(bytes.get(number + 1), Some(b'x' | b'X')) {
+        let integral = skip(bytes, number + 2, u8::is_ascii_hexdigit);
+        let (mantissa, has_digits) = if bytes.get(integral) == Some(&b'.') {
+            let fraction = skip(bytes, integral + 1, u8::is_ascii_hexdigit);
+            (fraction, integral > number + 2 || fraction > integral + 1)
+        } else {
+            (integral, integral > number + 2)
+        };
+        if has_digits {
+            match exponent(mantissa, [b'p', b'P']) {
+                Ok(end) => scan.accept(TokenType::FloatingPointLiteral, suffix(end)),
+                Err(reach) => scan.reach(reach),
+            }
+        } else {
+            scan.reach(mantissa);
+        }
+    }
+    scan
+}
+
+/// `IdentifierToken` or a keyword: `IdentifierPart ([.-] IdentifierPart)*`,
+/// where each part may be quoted in backticks.
+fn identifier(source: &str, start: usize) -> Scan {
+    let mut scan = Scan::new(start);
+    let mut end = match identifier_part(source, start) {
+        Ok(end) => end,
+        Err(reach) => {
+            scan.reach(reach);
+            return scan;
+        }
+    };
+    let first = end;
+    while matches!(source.as_bytes().get(end), Some(b'.' | b'-')) {
+        match identifier_part(source, end + 1) {
+            Ok(next) => end = next,
+            Err(reach) => {
+                scan.reach(reach);
+                break;
+            }
+        }
+    }
+    let keyword = KEYWORDS
+        .iter()
+        .find(|(keyword, _)| *keyword == &source[start..end])
+        .filter(|_| end == first);
+    match keyword {
+        Some(&(_, token_type)) => scan.accept(token_type, end),
+        None => scan.accept(TokenType::IdentifierToken, end),
+    }
+    scan
+}
+
+/// The end of the identifier part at `start`, plain or in backticks, or
+/// where it goes wrong.
+fn identifier_part(source: &str, start: usize) -> Result<usize, usize> {
+    let quoted = source.as_bytes().get(start) == Some(&b'`');
+    let name = start + usize::from(quoted);
+    match char_at(source, name) {
+        Some(c) if is_identifier_start(c) => {}
+        Some(_) => return Err(name),
+        None => return Err(source.len()),
+    }
+    let end = source[name..]
+        .char_indices()
+        .skip(1)
+        .find(|&(_, c)| !unicode_ident::is_xid_continue(c))
+        .map_or(source.len(), |(n, _)| name + n);
+    if !quoted {
+        return Ok(end);
+    }
+    match source.as_bytes().get(end) {
+        Some(b'`') => Ok(end + 1),
+        Some(_) => Err(end),
+        None => Err(source.len()),
+    }
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c == '_' || unicode_ident::is_xid_start(c)
+}
+
+fn char_at(source: &str, offset: usize) -> Option<char> {
+    source.get(offset..).and_then(|rest| rest.chars().next())
+}
+
+/// The end of the character at `offset`, or the end of the source.
+fn next_char_end(source: &str, offset: usize) -> usize {
+    char_at(source, offset).map_or(source.len(), |c| offset + c.len_utf8())
+}
+
+/// The offset of the first byte from `start` that does not satisfy `keep`.
+fn skip(bytes: &[u8], start: usize, keep: impl Fn(&u8) -> bool) -> usize {
+    let mut end = start;
+    while bytes.get(end).is_some_and(&keep) {
+        end += 1;
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    /// Render each token as `Kind:text`, and each error as `!text`.
+    fn render(source: &str) -> Vec<String> {
+        let (tokens, errors) = lex(source);
+        let mut items: Vec<(usize, String)> = tokens
+            .iter()
+            .filter(|token| token.token_type != TokenType::WS)
+            .map(|token| {
+                let text = &source[token.span.clone()];
+                (
+                    token.span.start,
+                    format!("{}:{text}", token.token_type.name()),
+                )
+            })
+            .collect();
+        items.extend(errors.iter().map(|error| {
+            (
+                error.span.start,
+                format!("!{}", &source[error.span.clone()]),
+            )
+        }));
+        items.sort();
+        items.into_iter().map(|(_, item)| item).collect()
+    }
+
+    #[test]
+    fn longest_match_wins_and_keywords_win_ties() {
+        assert_eq!(
+            render("record records record.x `record` time_ms NaN NaNs - -5 -5.0e3 .5 0x1p3 0_1"),
+            [
+                "Record:record",
+                "IdentifierToken:records",
+                "IdentifierToken:record.x",
+                "IdentifierToken:`record`",
+                "Time:time_ms",
+                "FloatingPointLiteral:NaN",
+                "IdentifierToken:NaNs",
+                "Dash:-",
+                "IntegerLiteral:-5",
+                "FloatingPointLiteral:-5.0e3",
+                "FloatingPointLiteral:.5",
+                "FloatingPointLiteral:0x1p3",
+                "IntegerLiteral:0",
+                "IdentifierToken:_1",
+            ]
+        );
+    }
+
+    #[test]
+    fn comments_are_tokens() {
+        // As in the ANTLR lexer, `/**/` only stands alone if no `*/` follows,
+        // since a doc comment running to the next `*/` is a longer match.
+        assert_eq!(
+            render("/**/ x /** doc */ /* block */ // line\n/**/ /***/"),
+            [
+                "DocComment:/**/ x /** doc */",
+                "MultiLineComment:/* block */",
+                "SingleLineComment:// line\n",
+                "DocComment:/**/ /***/",
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_skip_through_the_first_bad_character() {
+        assert_eq!(
+            render("a # /x y \"\\q\" z +5 w // end"),
+            [
+                "IdentifierToken:a",
+                "!#",
+                "!/x",
+                "IdentifierToken:y",
+                "!\"\\q",
+                "!\" z +5 w // end",
+            ]
+        );
+        let (_, errors) = lex("x\n  \"abc\ndef");
+        assert_eq!(
+            (errors[0].line, errors[0].column, errors[0].message.as_str()),
+            (2, 2, "token recognition error at: '\"abc\\n'")
+        );
+    }
+}
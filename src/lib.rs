@@ -20,7 +20,9 @@
 //! let output = Idl::new()
 //!     .import_dir("schemas/shared/")
 //!     .convert("schemas/service.avdl")?;
-//! println!("{}", serde_json::to_string_pretty(&output.json)?);
+//! // `ordered_json` keeps the messages in declaration order, as `avdl idl`
+//! // writes them; `output.json` is a `Value`, whose keys are all sorted.
+//! println!("{}", serde_json::to_string_pretty(&output.ordered_json())?);
 //! # Ok::<(), Box<dyn std::error::Error>>(())
 //! ```
 //!
@@ -45,16 +47,76 @@
 //! All fallible methods return [`miette::Result`], which provides rich
 //! diagnostic output with source spans when printed with `{:?}`.
 
+#[cfg(not(any(feature = "antlr", feature = "handwritten-parser")))]
+compile_error!("enable one of the `antlr` and `handwritten-parser` features to pick a parser");
+
+#[cfg(feature = "antlr")]
 pub(crate) mod generated;
 
+#[cfg(feature = "bench")]
+pub mod bench;
+pub(crate) mod codes;
+pub(crate) mod comments;
 pub(crate) mod compiler;
+pub(crate) mod defaults;
+pub(crate) mod describe;
+pub(crate) mod diff;
+pub(crate) mod doc;
 pub(crate) mod doc_comments;
+pub(crate) mod encoding;
 pub(crate) mod error;
+pub(crate) mod fix;
+pub(crate) mod graph;
+pub(crate) mod ide;
 pub(crate) mod import;
+pub(crate) mod iso8601;
+pub(crate) mod lexer;
+pub(crate) mod lint;
 pub(crate) mod model;
+pub(crate) mod parse_tree;
+#[cfg(feature = "handwritten-parser")]
+#[cfg_attr(
+    all(feature = "antlr", not(test)),
+    allow(dead_code, reason = "with both parsers, only the tests use this one")
+)]
+pub(crate) mod parser;
 pub(crate) mod reader;
 pub(crate) mod resolve;
+pub(crate) mod subject;
 pub(crate) mod suggest;
+pub(crate) mod syntax;
+pub(crate) mod template;
+pub(crate) mod tokens;
+pub(crate) mod tolerant;
+pub(crate) mod visibility;
 
 // Re-export the small number of public API at the crate root.
-pub use compiler::{Idl, Idl2Schemata, IdlOutput, NamedSchema, SchemataOutput};
+pub use codes::{DIAGNOSTIC_CODES, DiagnosticCode, explain};
+pub use comments::{
+    Comment, CommentKind, CommentPlacement, CommentedDeclaration, declaration_comments,
+};
+pub use compiler::{
+    DeclaredName, Idl, Idl2Schemata, IdlOutput, ImportEdge, NameKind, NamedProtocol, NamedSchema,
+    SchemaConflict, SchemataOutput,
+};
+pub use defaults::{FieldDefault, RecordDefaults, UnionDefaults};
+pub use diff::SchemaChange;
+pub use doc::DocPage;
+pub use error::ErrorKind;
+pub use fix::{AppliedFix, FixOutput};
+pub use graph::{GraphNode, TypeEdge, TypeGraph};
+pub use ide::{Definition, EditedFile, Hover, SymbolRole};
+pub use lint::{DocScope, NamingRule, NullableStyle};
+pub use model::fingerprint::canonical_eq;
+pub use model::json::OrderedJson;
+pub use model::protocol::{Message, Protocol};
+pub use model::schema::{AvroSchema, Field, FieldOrder, LogicalType, PrimitiveType, Properties};
+pub use reader::{Annotation, ImportKind, ParamDefaults, WarningKind};
+pub use resolve::Resolution;
+pub use subject::{
+    RecordNameStrategy, SubjectRole, SubjectStrategy, TopicNameStrategy, TopicRecordNameStrategy,
+};
+pub use syntax::{SyntaxElement, SyntaxNode, SyntaxToken, syntax_tree};
+pub use template::{OutputTemplate, TemplateValues};
+pub use tokens::{SemanticToken, TokenKind, semantic_tokens};
+pub use tolerant::{Declaration, TolerantParse, parse_idl_tolerant};
@@ -0,0 +1,842 @@
+// ==============================================================================
+// Lints
+// ==============================================================================
+//
+// Opt-in checks of house style that the Avro specification does not require,
+// such as which namespaces a shared repository accepts. They report warnings,
+// so `--deny-warnings` turns them into a gate and `--allow` silences one.
+//
+// Lints read the lossless syntax tree of the file being compiled rather than
+// the resolved schemas, so each warning points at the source that caused it
+// (a `@namespace` annotation rather than every type that inherits it).
+// Imported files are not linted: they are linted when they are compiled
+// themselves.
+
+use std::ops::Range;
+
+use crate::error::SpanWithSource;
+use crate::reader::{Warning, WarningKind};
+use crate::syntax::{SyntaxElement, SyntaxNode, syntax_tree};
+
+/// A naming convention checked by
+/// [`Idl::lint_names`](crate::Idl::lint_names).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NamingRule {
+    /// Record, error, enum, and fixed names are `PascalCase`, e.g. `UserInfo`.
+    TypeNames,
+    /// Field names are camelCase, e.g. `userId`.
+    FieldNames,
+    /// Message names are camelCase, e.g. `getUser`.
+    MessageNames,
+    /// Enum symbols are `SCREAMING_SNAKE_CASE`, e.g. `DARK_RED`.
+    EnumSymbols,
+}
+
+impl NamingRule {
+    /// Every naming rule, in the order listed above.
+    pub const ALL: [NamingRule; 4] = [
+        NamingRule::TypeNames,
+        NamingRule::FieldNames,
+        NamingRule::MessageNames,
+        NamingRule::EnumSymbols,
+    ];
+
+    /// The name used on the command line, e.g. `"type-names"`.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NamingRule::TypeNames => "type-names",
+            NamingRule::FieldNames => "field-names",
+            NamingRule::MessageNames => "message-names",
+            NamingRule::EnumSymbols => "enum-symbols",
+        }
+    }
+}
+
+/// How nullable types should be written, as checked by
+/// [`Idl::nullable_style`](crate::Idl::nullable_style).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NullableStyle {
+    /// `string?`, not `union { null, string }`.
+    Shorthand,
+    /// `union { null, string }`, not `string?`.
+    Union,
+}
+
+/// Which declarations must have doc comments, as checked by
+/// [`Idl::require_docs`](crate::Idl::require_docs).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocScope {
+    /// Named types and messages not marked `@visibility("internal")`, and the
+    /// fields of those types.
+    Public,
+    /// Every named type, field, and message.
+    All,
+}
+
+/// Which lints to run, and their settings. The default runs none.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LintOptions {
+    /// Every declared namespace must be this namespace or inside it.
+    pub(crate) namespace_prefix: Option<String>,
+    /// Naming conventions to check.
+    pub(crate) naming: Vec<NamingRule>,
+    /// The most branches an explicit union may have.
+    pub(crate) max_union_branches: Option<usize>,
+    /// How nullable types should be written.
+    pub(crate) nullable_style: Option<NullableStyle>,
+    /// Which declarations must have doc comments.
+    pub(crate) require_docs: Option<DocScope>,
+}
+
+impl LintOptions {
+    fn is_empty(&self) -> bool {
+        self.namespace_prefix.is_none()
+            && self.naming.is_empty()
+            && self.max_union_branches.is_none()
+            && self.nullable_style.is_none()
+            && self.require_docs.is_none()
+    }
+}
+
+/// Run the enabled lints over `source`.
+pub(crate) fn lint(
+    source: &'static str,
+    source_name: &'static str,
+    options: &LintOptions,
+) -> Vec<Warning> {
+    if options.is_empty() {
+        return Vec::new();
+    }
+    let tree = syntax_tree(source);
+    let mut findings = Vec::new();
+    if let Some(prefix) = &options.namespace_prefix {
+        check_namespace_prefix(source, &tree, prefix, &mut findings);
+    }
+    if !options.naming.is_empty() {
+        check_naming(source, &tree, &options.naming, &mut findings);
+    }
+    if let Some(max) = options.max_union_branches {
+        check_union_size(&tree, max, &mut findings);
+    }
+    if let Some(style) = options.nullable_style {
+        check_nullable_style(source, &tree, style, &mut findings);
+    }
+    if let Some(scope) = options.require_docs {
+        check_docs(source, &tree, scope, &mut findings);
+    }
+    findings
+        .into_iter()
+        .map(|(kind, span, message)| Warning {
+            kind,
+            message,
+            span: Some(SpanWithSource::new(
+                span.start,
+                span.len(),
+                source_name,
+                source,
+            )),
+        })
+        .collect()
+}
+
+type Finding = (WarningKind, Range<usize>, String);
+
+// ==============================================================================
+// Namespace Prefix
+// ==============================================================================
+
+/// Report every namespace declared in `tree` that is not `prefix` or inside
+/// it, and every protocol or named type that ends up in the null namespace.
+/// Namespaces are compared by component, so `com.mycorp` and `com.mycorp.`
+/// both accept `com.mycorp.billing` but not `com.mycorpse`.
+fn check_namespace_prefix(
+    source: &str,
+    tree: &SyntaxNode,
+    prefix: &str,
+    findings: &mut Vec<Finding>,
+) {
+    let prefix = prefix.trim_end_matches('.');
+    let check = |namespace: &str, span: Range<usize>| -> Option<Finding> {
+        let inside = namespace
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'));
+        (!inside).then(|| {
+            (
+                WarningKind::NamespacePrefix,
+                span,
+                format!("namespace `{namespace}` is outside the required prefix `{prefix}`"),
+            )
+        })
+    };
+
+    // The namespace types inherit when they declare none of their own.
+    let mut enclosing = None;
+    if let Some(declaration) = tree.child("namespaceDeclaration")
+        && let Some(identifier) = declaration.child("identifier")
+    {
+        let namespace = identifier_text(source, identifier);
+        findings.extend(check(&namespace, identifier.span.clone()));
+        enclosing = Some(namespace);
+    }
+
+    for node in tree.descendants() {
+        let what = match node.kind {
+            "protocolDeclaration" => "protocol",
+            "recordDeclaration" | "enumDeclaration" | "fixedDeclaration" => "type",
+            _ => continue,
+        };
+        let Some(identifier) = node.child("identifier") else {
+            continue;
+        };
+        let name = identifier_text(source, identifier);
+        let annotated = explicit_namespaces(source, node);
+        for (namespace, span) in &annotated {
+            findings.extend(check(namespace, span.clone()));
+        }
+        let declared = match annotated.last() {
+            // As in the reader, an explicit `@namespace` wins over a dotted
+            // name.
+            Some((namespace, _)) => Some(namespace.clone()),
+            None => match name.rsplit_once('.') {
+                Some((namespace, _)) => {
+                    findings.extend(check(namespace, identifier.span.clone()));
+                    Some(namespace.to_string())
+                }
+                None => None,
+            },
+        };
+        if node.kind == "protocolDeclaration" {
+            enclosing = declared.clone();
+        }
+        if declared.is_none() && enclosing.is_none() {
+            findings.push((
+                WarningKind::NamespacePrefix,
+                identifier.span.clone(),
+                format!("{what} `{name}` has no namespace; the required prefix is `{prefix}`"),
+            ));
+        }
+    }
+}
+
+/// The values of the `@namespace` annotations directly on a declaration,
+/// with the span of each annotation. Values that are not strings are left to
+/// the reader to reject.
+pub(crate) fn explicit_namespaces(
+    source: &str,
+    declaration: &SyntaxNode,
+) -> Vec<(String, Range<usize>)> {
+    annotations(source, declaration, "namespace")
+        .filter_map(|(value, span)| Some((value.as_str()?.to_string(), span)))
+        .collect()
+}
+
+/// The values of the annotations named `name` directly on a declaration,
+/// with the span of each annotation.
+fn annotations<'a>(
+    source: &'a str,
+    declaration: &'a SyntaxNode,
+    name: &'a str,
+) -> impl Iterator<Item = (serde_json::Value, Range<usize>)> + 'a {
+    child_nodes(declaration, "schemaProperty")
+        .filter(move |property| {
+            property
+                .child("identifier")
+                .is_some_and(|identifier| identifier_text(source, identifier) == name)
+        })
+        .filter_map(|property| {
+            let value = property.child("jsonValue")?;
+            let value = serde_json::from_str(&source[value.span.clone()]).ok()?;
+            Some((value, property.span.clone()))
+        })
+}
+
+// ==============================================================================
+// Naming Conventions
+// ==============================================================================
+
+/// Report every identifier in `tree` that breaks one of `rules`, in source
+/// order, suggesting a name that follows it.
+fn check_naming(
+    source: &str,
+    tree: &SyntaxNode,
+    rules: &[NamingRule],
+    findings: &mut Vec<Finding>,
+) {
+    for node in tree.descendants() {
+        let (rule, identifiers) = match node.kind {
+            "recordDeclaration" | "enumDeclaration" | "fixedDeclaration" => {
+                (NamingRule::TypeNames, vec![node.child("identifier")])
+            }
+            "fieldDeclaration" => (
+                NamingRule::FieldNames,
+                child_nodes(node, "variableDeclaration")
+                    .map(|variable| variable.child("identifier"))
+                    .collect(),
+            ),
+            "messageDeclaration" => (NamingRule::MessageNames, vec![node.child("identifier")]),
+            "enumSymbol" => (NamingRule::EnumSymbols, vec![node.child("identifier")]),
+            _ => continue,
+        };
+        if !rules.contains(&rule) {
+            continue;
+        }
+        for identifier in identifiers.into_iter().flatten() {
+            let text = identifier_text(source, identifier);
+            // Only the last component of a dotted type name is the name.
+            let name = text.rsplit('.').next().unwrap_or(&text);
+            let (what, convention, follows, suggestion) = match rule {
+                NamingRule::TypeNames => (
+                    "type",
+                    "PascalCase",
+                    is_pascal_case(name),
+                    pascal_case(name),
+                ),
+                NamingRule::FieldNames => {
+                    ("field", "camelCase", is_camel_case(name), camel_case(name))
+                }
+                NamingRule::MessageNames => (
+                    "message",
+                    "camelCase",
+                    is_camel_case(name),
+                    camel_case(name),
+                ),
+                NamingRule::EnumSymbols => (
+                    "enum symbol",
+                    "SCREAMING_SNAKE_CASE",
+                    is_screaming_snake_case(name),
+                    screaming_snake_case(name),
+                ),
+            };
+            if follows {
+                continue;
+            }
+            let mut message = format!("{what} name `{name}` is not {convention}");
+            if !suggestion.is_empty() {
+                message.push_str(&format!("; consider `{suggestion}`"));
+            }
+            findings.push((
+                WarningKind::NamingConvention,
+                identifier.span.clone(),
+                message,
+            ));
+        }
+    }
+}
+
+fn is_pascal_case(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+fn is_camel_case(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+fn is_screaming_snake_case(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+        && !name.contains("__")
+        && !name.ends_with('_')
+}
+
+fn pascal_case(name: &str) -> String {
+    words(name).iter().map(|word| capitalize(word)).collect()
+}
+
+fn camel_case(name: &str) -> String {
+    let mut words = words(name).into_iter();
+    let first = words
+        .next()
+        .map(|word| word.to_lowercase())
+        .unwrap_or_default();
+    first + &words.map(|word| capitalize(&word)).collect::<String>()
+}
+
+fn screaming_snake_case(name: &str) -> String {
+    let words: Vec<_> = words(name).iter().map(|word| word.to_uppercase()).collect();
+    words.join("_")
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first
+            .to_uppercase()
+            .chain(chars.flat_map(char::to_lowercase))
+            .collect(),
+        None => String::new(),
+    }
+}
+
+/// Split a name into words at underscores and case changes, keeping
+/// acronyms together: `HTTPServer_port` is `HTTP`, `Server`, `port`.
+fn words(name: &str) -> Vec<String> {
+    let chars: Vec<char> = name.chars().collect();
+    let mut words = Vec::new();
+    let mut current = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '_' {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+        let previous = i.checked_sub(1).map(|i| chars[i]);
+        let next = chars.get(i + 1);
+        let boundary = c.is_uppercase()
+            && previous.is_some_and(|p| {
+                p.is_lowercase()
+                    || p.is_ascii_digit()
+                    || (p.is_uppercase() && next.is_some_and(|n| n.is_lowercase()))
+            });
+        if boundary && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        current.push(c);
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+// ==============================================================================
+// Unions
+// ==============================================================================
+
+/// Report every explicit union with more than `max` branches.
+fn check_union_size(tree: &SyntaxNode, max: usize, findings: &mut Vec<Finding>) {
+    for union in tree.descendants() {
+        if union.kind != "unionType" {
+            continue;
+        }
+        let branches = child_nodes(union, "fullType").count();
+        if branches > max {
+            findings.push((
+                WarningKind::UnionSize,
+                union.span.clone(),
+                format!("union has {branches} branches, more than the limit of {max}"),
+            ));
+        }
+    }
+}
+
+/// Report every nullable type not written in `style`: each `union { null, T }`
+/// that could be `T?` for [`NullableStyle::Shorthand`], and each `T?` for
+/// [`NullableStyle::Union`].
+fn check_nullable_style(
+    source: &str,
+    tree: &SyntaxNode,
+    style: NullableStyle,
+    findings: &mut Vec<Finding>,
+) {
+    // `T? field = value` with a non-null default is `union { T, null }`, so
+    // `union { null, T }` on such a field has no shorthand.
+    let mut non_null_defaults = Vec::new();
+    for field in tree.descendants() {
+        if field.kind == "fieldDeclaration"
+            && child_nodes(field, "variableDeclaration").any(|variable| {
+                variable
+                    .child("jsonValue")
+                    .is_some_and(|value| source[value.span.clone()].trim() != "null")
+            })
+            && let Some(field_type) = field.child("fullType")
+        {
+            non_null_defaults.push(field_type.span.clone());
+        }
+    }
+
+    for node in tree.descendants() {
+        match (style, node.kind) {
+            (NullableStyle::Shorthand, "unionType") => {
+                let branches: Vec<_> = child_nodes(node, "fullType").collect();
+                let [first, second] = branches[..] else {
+                    continue;
+                };
+                if source[first.span.clone()].trim() != "null"
+                    || !is_plain_nullable(second)
+                    || non_null_defaults
+                        .iter()
+                        .any(|span| span.contains(&node.span.start))
+                {
+                    continue;
+                }
+                let inner = source[second.span.clone()].trim();
+                findings.push((
+                    WarningKind::NullableStyle,
+                    node.span.clone(),
+                    format!("write `union {{ null, {inner} }}` as `{inner}?`"),
+                ));
+            }
+            (NullableStyle::Union, "nullableType") if has_token(node, "QuestionMark") => {
+                let written = source[node.span.clone()].trim();
+                let inner = written.trim_end_matches('?').trim_end();
+                findings.push((
+                    WarningKind::NullableStyle,
+                    node.span.clone(),
+                    format!(
+                        "write `{written}` as an explicit union, e.g. `union {{ null, {inner} }}`"
+                    ),
+                ));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whether a union branch could be written with the `?` shorthand: a
+/// primitive or named type, without annotations or a `?` of its own.
+fn is_plain_nullable(branch: &SyntaxNode) -> bool {
+    branch.child("schemaProperty").is_none()
+        && branch
+            .child("plainType")
+            .and_then(|plain| plain.child("nullableType"))
+            .is_some_and(|nullable| {
+                !has_token(nullable, "QuestionMark")
+                    && nullable
+                        .child("primitiveType")
+                        .is_none_or(|primitive| !has_token(primitive, "Null"))
+            })
+}
+
+// ==============================================================================
+// Documentation
+// ==============================================================================
+
+/// Report every named type, field, and message in `scope` without a doc
+/// comment. As in the reader, a doc comment documents a declaration if only
+/// whitespace separates them; a field's may also sit right before its name.
+fn check_docs(source: &str, tree: &SyntaxNode, scope: DocScope, findings: &mut Vec<Finding>) {
+    let tokens = tree.tokens();
+    let documented = |node: &SyntaxNode| {
+        let Some(first) = tokens
+            .iter()
+            .position(|token| token.span.start == node.span.start && !token.is_trivia())
+        else {
+            return false;
+        };
+        tokens[..first]
+            .iter()
+            .rev()
+            .find(|token| !matches!(token.kind, "WS" | "EmptyComment"))
+            .is_some_and(|token| token.kind == "DocComment")
+    };
+    let internal = |node: &SyntaxNode| {
+        scope == DocScope::Public
+            && annotations(source, node, "visibility").any(|(value, _)| value == "internal")
+    };
+
+    // The record being walked, and whether its fields are skipped.
+    let mut record: Option<(String, Range<usize>, bool)> = None;
+    for node in tree.descendants() {
+        let name = |identifier: &SyntaxNode| identifier_text(source, identifier);
+        match node.kind {
+            "recordDeclaration" | "enumDeclaration" | "fixedDeclaration" | "messageDeclaration" => {
+                let Some(identifier) = node.child("identifier") else {
+                    continue;
+                };
+                let skip = internal(node);
+                if node.kind == "recordDeclaration" {
+                    record = Some((name(identifier), node.span.clone(), skip));
+                }
+                if !skip && !documented(node) {
+                    let what = if node.kind == "messageDeclaration" {
+                        "message"
+                    } else {
+                        "type"
+                    };
+                    findings.push((
+                        WarningKind::MissingDoc,
+                        identifier.span.clone(),
+                        format!("{what} `{}` has no doc comment", name(identifier)),
+                    ));
+                }
+            }
+            "fieldDeclaration" => {
+                let Some((record_name, _, false)) = record
+                    .as_ref()
+                    .filter(|(_, span, _)| span.contains(&node.span.start))
+                else {
+                    continue;
+                };
+                for variable in child_nodes(node, "variableDeclaration") {
+                    let Some(identifier) = variable.child("identifier") else {
+                        continue;
+                    };
+                    if !documented(node) && !documented(variable) {
+                        findings.push((
+                            WarningKind::MissingDoc,
+                            identifier.span.clone(),
+                            format!(
+                                "field `{record_name}.{}` has no doc comment",
+                                name(identifier)
+                            ),
+                        ));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The child nodes of `node` of the given kind.
+fn child_nodes<'a>(node: &'a SyntaxNode, kind: &'a str) -> impl Iterator<Item = &'a SyntaxNode> {
+    node.children.iter().filter_map(move |child| match child {
+        SyntaxElement::Node(child) if child.kind == kind => Some(child),
+        _ => None,
+    })
+}
+
+/// Whether `node` has a token of the given kind directly under it.
+fn has_token(node: &SyntaxNode, kind: &str) -> bool {
+    node.children
+        .iter()
+        .any(|child| matches!(child, SyntaxElement::Token(token) if token.kind == kind))
+}
+
+/// The text of an identifier, without backquotes.
+pub(crate) fn identifier_text(source: &str, identifier: &SyntaxNode) -> String {
+    source[identifier.span.clone()].replace('`', "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn namespace_findings(source: &'static str) -> Vec<(String, &'static str)> {
+        let options = LintOptions {
+            namespace_prefix: Some("com.mycorp.".to_string()),
+            ..LintOptions::default()
+        };
+        lint(source, "<input>", &options)
+            .into_iter()
+            .map(|warning| {
+                let span = warning.span.expect("lints have spans");
+                (
+                    warning.message,
+                    &source[span.offset..span.offset + span.length],
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn namespace_prefix_reports_each_declaration() {
+        let source = r#"@namespace("com.mycorp.billing")
+protocol Billing {
+  record Invoice { string id; }
+  @namespace("org.rogue") record Stray { int x; }
+  enum com.mycorpse.Color { RED }
+  fixed com.mycorp.Hash(16);
+}"#;
+        assert_eq!(
+            namespace_findings(source),
+            vec![
+                (
+                    "namespace `org.rogue` is outside the required prefix `com.mycorp`".to_string(),
+                    r#"@namespace("org.rogue")"#,
+                ),
+                (
+                    "namespace `com.mycorpse` is outside the required prefix `com.mycorp`"
+                        .to_string(),
+                    "com.mycorpse.Color",
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn namespace_prefix_reports_the_null_namespace() {
+        assert_eq!(
+            namespace_findings("protocol P { record R { int x; } }"),
+            vec![
+                (
+                    "protocol `P` has no namespace; the required prefix is `com.mycorp`"
+                        .to_string(),
+                    "P",
+                ),
+                (
+                    "type `R` has no namespace; the required prefix is `com.mycorp`".to_string(),
+                    "R",
+                ),
+            ]
+        );
+        assert_eq!(
+            namespace_findings("namespace org.other; record R { int x; }"),
+            vec![(
+                "namespace `org.other` is outside the required prefix `com.mycorp`".to_string(),
+                "org.other",
+            )]
+        );
+    }
+
+    #[test]
+    fn naming_rules_report_each_identifier_with_a_suggestion() {
+        let source = r#"protocol P {
+  record user_info { string UserId; int age, Zip_code; }
+  enum Color { DARK_RED, lightBlue }
+  fixed HTTPHash(16);
+  void Get_user();
+}"#;
+        let findings = |naming: Vec<NamingRule>| -> Vec<(String, &str)> {
+            let options = LintOptions {
+                naming,
+                ..LintOptions::default()
+            };
+            lint(source, "<input>", &options)
+                .into_iter()
+                .map(|warning| {
+                    let span = warning.span.expect("lints have spans");
+                    (
+                        warning.message,
+                        &source[span.offset..span.offset + span.length],
+                    )
+                })
+                .collect()
+        };
+        assert_eq!(
+            findings(NamingRule::ALL.to_vec()),
+            vec![
+                (
+                    "type name `user_info` is not PascalCase; consider `UserInfo`".to_string(),
+                    "user_info",
+                ),
+                (
+                    "field name `UserId` is not camelCase; consider `userId`".to_string(),
+                    "UserId",
+                ),
+                (
+                    "field name `Zip_code` is not camelCase; consider `zipCode`".to_string(),
+                    "Zip_code",
+                ),
+                (
+                    "enum symbol name `lightBlue` is not SCREAMING_SNAKE_CASE; \
+                     consider `LIGHT_BLUE`"
+                        .to_string(),
+                    "lightBlue",
+                ),
+                (
+                    "message name `Get_user` is not camelCase; consider `getUser`".to_string(),
+                    "Get_user",
+                ),
+            ]
+        );
+        assert_eq!(
+            findings(vec![NamingRule::EnumSymbols]),
+            vec![(
+                "enum symbol name `lightBlue` is not SCREAMING_SNAKE_CASE; consider `LIGHT_BLUE`"
+                    .to_string(),
+                "lightBlue",
+            )]
+        );
+        assert_eq!(
+            words("HTTPServer_port2Go"),
+            ["HTTP", "Server", "port2", "Go"]
+        );
+    }
+
+    #[test]
+    fn union_lints_report_each_union() {
+        let source = r#"protocol P {
+  record R {
+    union { null, string } name;
+    union { null, string } nickname = "none";
+    union { null, array<int> } scores;
+    union { null, int, long, string } id;
+    long? created;
+  }
+}"#;
+        let findings = |options: LintOptions| -> Vec<(String, &str)> {
+            lint(source, "<input>", &options)
+                .into_iter()
+                .map(|warning| {
+                    let span = warning.span.expect("lints have spans");
+                    (
+                        warning.message,
+                        &source[span.offset..span.offset + span.length],
+                    )
+                })
+                .collect()
+        };
+        assert_eq!(
+            findings(LintOptions {
+                max_union_branches: Some(3),
+                nullable_style: Some(NullableStyle::Shorthand),
+                ..LintOptions::default()
+            }),
+            vec![
+                (
+                    "union has 4 branches, more than the limit of 3".to_string(),
+                    "union { null, int, long, string }",
+                ),
+                (
+                    "write `union { null, string }` as `string?`".to_string(),
+                    "union { null, string }",
+                ),
+            ]
+        );
+        assert_eq!(
+            findings(LintOptions {
+                nullable_style: Some(NullableStyle::Union),
+                ..LintOptions::default()
+            }),
+            vec![(
+                "write `long?` as an explicit union, e.g. `union { null, long }`".to_string(),
+                "long?",
+            )]
+        );
+    }
+
+    #[test]
+    fn require_docs_reports_undocumented_declarations() {
+        let source = r#"/** The service. */
+protocol P {
+  /** A user. */
+  record User {
+    /** Full name. */ string name;
+    int age;
+    string /** The email address. */ email;
+  }
+  @visibility("internal") record Secret { string key; }
+  enum Color { RED }
+  /** Fetch a user. */
+  User get(string name);
+  // Not a doc comment.
+  void reset();
+}"#;
+        let findings = |scope: DocScope| -> Vec<String> {
+            let options = LintOptions {
+                require_docs: Some(scope),
+                ..LintOptions::default()
+            };
+            lint(source, "<input>", &options)
+                .into_iter()
+                .map(|warning| warning.message)
+                .collect()
+        };
+        assert_eq!(
+            findings(DocScope::Public),
+            vec![
+                "field `User.age` has no doc comment",
+                "type `Color` has no doc comment",
+                "message `reset` has no doc comment",
+            ]
+        );
+        assert_eq!(
+            findings(DocScope::All),
+            vec![
+                "field `User.age` has no doc comment",
+                "type `Secret` has no doc comment",
+                "field `Secret.key` has no doc comment",
+                "type `Color` has no doc comment",
+                "message `reset` has no doc comment",
+            ]
+        );
+    }
+}
@@ -5,13 +5,39 @@
 // Two subcommands that mirror the Java `avro-tools` interface:
 //   - `avdl idl [INPUT] [OUTPUT]`        -- compile .avdl to .avpr or .avsc JSON
 //   - `avdl idl2schemata [INPUT] [OUTDIR]` -- extract individual .avsc files
+//
+// Plus tooling subcommands with no Java equivalent, each in its own module
+// under `commands/`:
+//   - `avdl deps INPUT`                  -- print the transitive import graph
+//   - `avdl graph INPUT`                 -- print the type dependency graph
+//   - `avdl check INPUT`                 -- check that shared types agree across files
+//   - `avdl defaults INPUT`              -- print the effective default of every field
+//   - `avdl diff OLD NEW`                -- print the semantic differences between two files
+//   - `avdl equal A B`                   -- check that two schemas are canonically equal
+//   - `avdl doc INPUT [OUTDIR]`          -- generate HTML, Markdown, or JSON documentation
+//   - `avdl verify --against JAR INPUT`  -- compare the output with Java avro-tools
+//   - `avdl explain [CODE]`              -- explain a diagnostic code such as AVDL0004
+//   - `avdl bench [INPUT...]`            -- time each compilation phase (`bench` feature)
+//
+// `avdl tool ...` (or invoking the binary as `avro-tools`) is a drop-in mode
+// that accepts exactly the arguments `avro-tools idl` and `avro-tools
+// idl2schemata` accept, so existing build scripts can switch binaries.
 
+use std::fmt::Write as _;
 use std::fs;
 use std::io::{self, Read as _};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
-use avdl::{Idl, Idl2Schemata};
+use avdl::{
+    DocScope, ErrorKind, Idl, Idl2Schemata, IdlOutput, ImportEdge, NamedProtocol, NamedSchema,
+    NamingRule, NullableStyle, OutputTemplate, ParamDefaults, TemplateValues, UnionDefaults,
+    WarningKind,
+};
 use lexopt::prelude::*;
+use serde::{Deserialize, Serialize};
+
+mod commands;
 
 // ==============================================================================
 // CLI Help Text
@@ -20,63 +46,550 @@ use lexopt::prelude::*;
 const MAIN_HELP: &str = "\
 avdl - Avro IDL compiler
 
-Usage: avdl <COMMAND>
+Usage: avdl [OPTIONS] <COMMAND>
 
 Commands:
   idl           Compile an Avro IDL file to protocol (.avpr) or schema (.avsc) JSON
   idl2schemata  Extract individual .avsc schema files from an Avro IDL protocol
+  deps          Print the transitive import graph of an Avro IDL file
+  graph         Print the type dependency graph of an Avro IDL file
+  check         Check that types shared by several Avro IDL files are defined identically
+  fix           Apply the safe fixes for the errors in an Avro IDL file, in place
+  defaults      Print the effective default value of every record field
+  diff          Print the semantic differences between two Avro IDL files
+  equal         Check that two schemas have the same Parsing Canonical Form
+  doc           Generate HTML, Markdown, or JSON documentation for an Avro IDL file
+  verify        Compare the output for Avro IDL files with Java avro-tools
+  explain       Explain a diagnostic code such as AVDL0004, or list every code
+  bench         Time the parse, resolve, and emit phases (requires the `bench` feature)
+  tool          Run `idl` or `idl2schemata` with avro-tools' exact arguments
 
 Options:
-  -h, --help       Print help
-  -V, --version    Print version";
+      --color <WHEN>            Color diagnostics: auto (default), always, or never;
+                                auto honors NO_COLOR
+      --diagnostic-width <N>    Wrap diagnostics at N columns instead of the terminal
+                                width (80 if unknown); 0 disables wrapping
+  -q, --quiet                   Do not print warnings
+  -v, --verbose                 Also print how each import resolved, and which import
+                                brought in a warning from an imported file
+      --warnings-format <FMT>   Print warnings as human (default) or ndjson: one JSON
+                                object per line with code, category, message, file,
+                                and span
+      --detailed-exit-codes     Exit with a code that tells failures apart (see below)
+  -h, --help                    Print help
+  -V, --version                 Print version
+
+Exit codes with --detailed-exit-codes (otherwise every failure exits 1, except
+a missing or unknown subcommand or global option, which exits 2):
+  1  Any failure not listed below
+  2  Invalid command line
+  3  The IDL is invalid
+  4  A type name is undefined
+  5  An input or import could not be found or read
+  6  Warnings were denied";
 
 const IDL_HELP: &str = "\
 Usage: avdl idl [OPTIONS] [INPUT] [OUTPUT]
+       avdl idl [OPTIONS] --out-dir <DIR> INPUT...
+       avdl idl [OPTIONS] --recursive --out-dir <DIR> ROOT...
+       avdl idl [OPTIONS] --out-dir <DIR> --input-list <FILE>
+
+With --out-dir, each INPUT is an .avdl file, a directory searched recursively
+for .avdl files, or a glob such as `schemas/**/*.avdl`, and each compiles to
+DIR/<input name>.avpr (or .avsc for schema-mode files). With --recursive, every
+.avdl file under each ROOT compiles into the same relative directory under DIR,
+failures do not stop the rest, and a summary is printed at the end.
 
 Options:
-      --import-dir <DIR>  Additional directories to search for imports (repeatable)
-  -h, --help              Print help";
+      --out-dir <DIR>          Compile every INPUT, writing one output file per input
+      --recursive              Compile every .avdl file under each ROOT (requires --out-dir)
+      --input-list <FILE>      Compile the INPUTs (or ROOTs) listed in FILE, one per line,
+                               or on stdin if FILE is `-` (requires --out-dir)
+      --output-template <TEMPLATE>
+                               Name each output DIR/TEMPLATE instead of
+                               `{stem}.{ext}` (requires --out-dir); see the placeholders below
+      --cache <FILE>           Skip inputs whose sources and imports are unchanged since
+                               the build recorded in FILE (requires --out-dir)
+      --multiple-protocols     Accept an INPUT that declares several protocols, writing a
+                               JSON array with one protocol each (not with --out-dir)
+      --import-dir <DIR>       Additional directories to search for imports (repeatable);
+                               a .zip or .jar file is searched as if unpacked
+      --import-map <PREFIX=DIR>
+                               Resolve imports whose path starts with PREFIX (such as
+                               `@shared/`) to the rest of the path under DIR (repeatable)
+      --import-map-file <FILE> Read PREFIX=DIR import mappings from FILE, one per line;
+                               `#` starts a comment, and DIR is relative to FILE
+      --overlay-dir <DIR>      Layer DIR into one import tree with the other overlay
+                               directories, earlier ones shadowing later ones (repeatable)
+      --param-defaults <MODE>  Message parameter defaults: validate (default), unchecked, omit
+      --union-defaults <MODE>  Union branch a field default may match: any-branch (default),
+                               first-branch (as the Avro specification requires)
+      --skip-unknown-declarations
+                               Warn about and skip declarations from a newer IDL grammar
+      --flatten-nested-unions  Flatten a union nested in another union into it, with a
+                               warning, instead of failing
+      --iso8601-defaults       Accept ISO-8601 strings as date, time, and timestamp defaults
+      --no-validate-defaults   Warn about field defaults that do not match their type, and
+                               emit them as written, instead of failing
+      --max-nesting-depth <N>  Fail on input or types nesting more than N levels deep
+                               (default: 128)
+      --strip-docs             Omit doc comments (`doc` keys) from the output
+      --sort-messages          Write protocol messages sorted by name instead of in
+                               declaration order
+      --refs-by-name           Reference named types by full name instead of inlining them
+      --allow-property <GLOB>  Only emit custom properties matching GLOB (repeatable)
+      --deny-property <GLOB>   Omit custom properties matching GLOB (repeatable)
+      --set-property <KEY=VALUE>
+                               Add or replace a protocol property (repeatable); VALUE is
+                               JSON, or else a string
+      --build-metadata         Record the compiler version and a hash of the source as
+                               `avdl.compiler` and `avdl.sourceHash` protocol properties
+      --warn-unused-types      Warn about named types that nothing references
+      --require-namespace-prefix <PREFIX>
+                               Warn about namespaces outside PREFIX, e.g. com.mycorp
+      --lint-names <RULE>      Warn about names breaking RULE (repeatable): type-names
+                               (PascalCase), field-names, message-names (camelCase),
+                               enum-symbols (SCREAMING_SNAKE_CASE)
+      --max-union-branches <N> Warn about unions with more than N branches
+      --nullable-style <STYLE> Warn about nullable types not written as STYLE: shorthand
+                               (`T?`) or union (`union { null, T }`)
+      --require-docs <SCOPE>   Warn about types, fields, and messages without a doc comment:
+                               public (not @visibility(\"internal\")) or all
+      --deny-warnings          Fail if there are any warnings
+      --allow <KIND>           Drop warnings of KIND (repeatable): out-of-place-doc,
+                               unrecognized-token, union-annotation, alias-name,
+                               unknown-declaration, unused-type, annotation,
+                               recursive-type, nested-union, namespace-prefix,
+                               naming-convention, union-size, nullable-style,
+                               missing-doc, invalid-default
+  -h, --help                   Print help
+
+--output-template placeholders: {stem} (the input file name without its
+extension), {name} and {namespace} (of the protocol or schema),
+{namespace_path} (the namespace as directories), {full_name}, and {ext}
+(avpr or avsc). `{{` and `}}` are literal braces.";
 
 const IDL2SCHEMATA_HELP: &str = "\
 Usage: avdl idl2schemata [OPTIONS] INPUT [OUTDIR]
 
+INPUT is an .avdl file, a directory searched recursively for .avdl files, or
+`-` to read from stdin.
+
 Options:
-      --import-dir <DIR>  Additional directories to search for imports (repeatable)
-  -h, --help              Print help";
+      --import-dir <DIR>       Additional directories to search for imports (repeatable);
+                               a .zip or .jar file is searched as if unpacked
+      --import-map <PREFIX=DIR>
+                               Resolve imports whose path starts with PREFIX (such as
+                               `@shared/`) to the rest of the path under DIR (repeatable)
+      --import-map-file <FILE> Read PREFIX=DIR import mappings from FILE, one per line;
+                               `#` starts a comment, and DIR is relative to FILE
+      --overlay-dir <DIR>      Layer DIR into one import tree with the other overlay
+                               directories, earlier ones shadowing later ones (repeatable)
+      --import-base <DIR>      Resolve relative imports of stdin input against DIR
+                               (default: the current directory)
+      --param-defaults <MODE>  Message parameter defaults: validate (default), unchecked, omit
+      --union-defaults <MODE>  Union branch a field default may match: any-branch (default),
+                               first-branch (as the Avro specification requires)
+      --skip-unknown-declarations
+                               Warn about and skip declarations from a newer IDL grammar
+      --flatten-nested-unions  Flatten a union nested in another union into it, with a
+                               warning, instead of failing
+      --iso8601-defaults       Accept ISO-8601 strings as date, time, and timestamp defaults
+      --no-validate-defaults   Warn about field defaults that do not match their type, and
+                               emit them as written, instead of failing
+      --max-nesting-depth <N>  Fail on input or types nesting more than N levels deep
+                               (default: 128)
+      --strip-docs             Omit doc comments (`doc` keys) from the output
+      --sort-messages          Write protocol messages sorted by name instead of in
+                               declaration order
+      --allow-property <GLOB>  Only emit custom properties matching GLOB (repeatable)
+      --deny-property <GLOB>   Omit custom properties matching GLOB (repeatable)
+      --warn-unused-types      Warn about named types that nothing references
+      --require-namespace-prefix <PREFIX>
+                               Warn about namespaces outside PREFIX, e.g. com.mycorp
+      --lint-names <RULE>      Warn about names breaking RULE (repeatable): type-names
+                               (PascalCase), field-names, message-names (camelCase),
+                               enum-symbols (SCREAMING_SNAKE_CASE)
+      --max-union-branches <N> Warn about unions with more than N branches
+      --nullable-style <STYLE> Warn about nullable types not written as STYLE: shorthand
+                               (`T?`) or union (`union { null, T }`)
+      --require-docs <SCOPE>   Warn about types, fields, and messages without a doc comment:
+                               public (not @visibility(\"internal\")) or all
+      --deny-warnings          Fail if there are any warnings
+      --allow <KIND>           Drop warnings of KIND (repeatable): out-of-place-doc,
+                               unrecognized-token, union-annotation, alias-name,
+                               unknown-declaration, unused-type, annotation,
+                               recursive-type, nested-union, namespace-prefix,
+                               naming-convention, union-size, nullable-style,
+                               missing-doc, invalid-default
+      --namespace-dirs         Write each schema to OUTDIR/<namespace as path>/<name>.avsc
+      --output-template <TEMPLATE>
+                               Write each schema to OUTDIR/TEMPLATE, such as
+                               `{namespace_path}/{name}.avsc`; see the placeholders below
+      --output-archive <FILE>  Write the schemas into a zip (or jar) archive instead of
+                               OUTDIR, laid out like --namespace-dirs
+      --include <PATTERN>      Only write schemas whose full name matches (glob, repeatable)
+      --exclude <PATTERN>      Skip schemas whose full name matches (glob, repeatable)
+      --refs-by-name           Reference other named types by name instead of inlining them
+      --only-roots             Only write schemas that no other schema references
+      --protocol               Also write the protocol to OUTDIR/<protocol name>.avpr
+                               (or into the archive), as `avdl idl` would
+      --stdout                 Print one JSON object mapping each schema's full name to
+                               the schema instead of writing files
+      --force                  Overwrite existing files in OUTDIR, or an existing archive,
+                               that differ from the new output, instead of failing
+  -h, --help                   Print help
+
+--output-template placeholders: {stem} (the name of the file that declares the
+schema, without its extension), {name}, {namespace}, {namespace_path} (the
+namespace as directories), {full_name}, and {ext} (avsc). `{{` and `}}` are
+literal braces.";
+
+// Usage text for the `avro-tools` drop-in mode. The per-tool usage lines
+// follow the wording of the corresponding `avro-tools` tools.
+const TOOL_HELP: &str = "\
+Usage: avdl tool <TOOL> [ARGS...]
+
+Runs a subcommand with the exact argument handling of `avro-tools`: no
+options, positional arguments only. Invoking the binary as `avro-tools`
+(e.g., through a symlink) does the same without the `tool` prefix.
+
+Tools:
+  idl [in] [out]              `-` or no argument means stdin/stdout
+  idl2schemata [idl] [outdir] outdir defaults to the current directory";
+
+const TOOL_IDL_USAGE: &str = "Usage: idl [in] [out]";
+
+const TOOL_IDL2SCHEMATA_USAGE: &str = "Usage: idl2schemata [idl] [outdir]";
 
 // ==============================================================================
 // Argument Parsing
 // ==============================================================================
 
+/// Options shared by the `idl` and `idl2schemata` subcommands: where imports
+/// are found, how the input is read, what the output contains, and which
+/// warnings are reported.
+#[derive(Debug)]
+struct CompileOptions {
+    import_dirs: Vec<PathBuf>,
+    import_mappings: Vec<(String, PathBuf)>,
+    overlay_dirs: Vec<PathBuf>,
+    param_defaults: ParamDefaults,
+    union_defaults: UnionDefaults,
+    skip_unknown_declarations: bool,
+    flatten_nested_unions: bool,
+    iso8601_defaults: bool,
+    validate_defaults: bool,
+    max_nesting_depth: Option<usize>,
+    strip_docs: bool,
+    sort_messages: bool,
+    refs_by_name: bool,
+    allow_properties: Vec<String>,
+    deny_properties: Vec<String>,
+    warn_unused_types: bool,
+    namespace_prefix: Option<String>,
+    naming_rules: Vec<NamingRule>,
+    max_union_branches: Option<usize>,
+    nullable_style: Option<NullableStyle>,
+    require_docs: Option<DocScope>,
+    deny_warnings: bool,
+    allowed_warnings: Vec<WarningKind>,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        CompileOptions {
+            import_dirs: Vec::new(),
+            import_mappings: Vec::new(),
+            overlay_dirs: Vec::new(),
+            param_defaults: ParamDefaults::default(),
+            union_defaults: UnionDefaults::default(),
+            skip_unknown_declarations: false,
+            flatten_nested_unions: false,
+            iso8601_defaults: false,
+            validate_defaults: true,
+            max_nesting_depth: None,
+            strip_docs: false,
+            sort_messages: false,
+            refs_by_name: false,
+            allow_properties: Vec::new(),
+            deny_properties: Vec::new(),
+            warn_unused_types: false,
+            namespace_prefix: None,
+            naming_rules: Vec::new(),
+            max_union_branches: None,
+            nullable_style: None,
+            require_docs: None,
+            deny_warnings: false,
+            allowed_warnings: Vec::new(),
+        }
+    }
+}
+
+impl CompileOptions {
+    /// Parse `--option` and its value if it is one of the shared options.
+    /// Returns `false`, without consuming anything, if it is not.
+    fn parse_option(
+        &mut self,
+        option: &str,
+        parser: &mut lexopt::Parser,
+    ) -> Result<bool, lexopt::Error> {
+        match option {
+            "import-dir" => {
+                let val: String = parser.value()?.string()?;
+                self.import_dirs.push(PathBuf::from(val));
+            }
+            "import-map" => {
+                self.import_mappings.push(parse_import_mapping(parser)?);
+            }
+            "import-map-file" => {
+                let path = PathBuf::from(parser.value()?);
+                self.import_mappings.extend(read_import_map_file(&path)?);
+            }
+            "overlay-dir" => {
+                let val: String = parser.value()?.string()?;
+                self.overlay_dirs.push(PathBuf::from(val));
+            }
+            "param-defaults" => {
+                self.param_defaults = parse_param_defaults(parser)?;
+            }
+            "union-defaults" => {
+                self.union_defaults = parse_union_defaults(parser)?;
+            }
+            "skip-unknown-declarations" => {
+                self.skip_unknown_declarations = true;
+            }
+            "flatten-nested-unions" => {
+                self.flatten_nested_unions = true;
+            }
+            "iso8601-defaults" => {
+                self.iso8601_defaults = true;
+            }
+            "no-validate-defaults" => {
+                self.validate_defaults = false;
+            }
+            "max-nesting-depth" => {
+                self.max_nesting_depth = Some(parser.value()?.parse()?);
+            }
+            "strip-docs" => {
+                self.strip_docs = true;
+            }
+            "sort-messages" => {
+                self.sort_messages = true;
+            }
+            "refs-by-name" => {
+                self.refs_by_name = true;
+            }
+            "allow-property" => {
+                self.allow_properties.push(parser.value()?.string()?);
+            }
+            "deny-property" => {
+                self.deny_properties.push(parser.value()?.string()?);
+            }
+            "warn-unused-types" => {
+                self.warn_unused_types = true;
+            }
+            "require-namespace-prefix" => {
+                self.namespace_prefix = Some(parser.value()?.string()?);
+            }
+            "lint-names" => {
+                self.naming_rules.push(parse_naming_rule(parser)?);
+            }
+            "max-union-branches" => {
+                self.max_union_branches = Some(parser.value()?.parse()?);
+            }
+            "nullable-style" => {
+                self.nullable_style = Some(parse_nullable_style(parser)?);
+            }
+            "require-docs" => {
+                self.require_docs = Some(parse_doc_scope(parser)?);
+            }
+            "deny-warnings" => {
+                self.deny_warnings = true;
+            }
+            "allow" => {
+                self.allowed_warnings.push(parse_warning_kind(parser)?);
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+/// Apply a [`CompileOptions`] to an `Idl` or `Idl2Schemata` builder, which
+/// have the same setters for these options.
+macro_rules! apply_compile_options {
+    ($options:expr, $builder:expr) => {{
+        let CompileOptions {
+            import_dirs,
+            import_mappings,
+            overlay_dirs,
+            param_defaults,
+            union_defaults,
+            skip_unknown_declarations,
+            flatten_nested_unions,
+            iso8601_defaults,
+            validate_defaults,
+            max_nesting_depth,
+            strip_docs,
+            sort_messages,
+            refs_by_name,
+            allow_properties,
+            deny_properties,
+            warn_unused_types,
+            namespace_prefix,
+            naming_rules,
+            max_union_branches,
+            nullable_style,
+            require_docs,
+            deny_warnings,
+            allowed_warnings,
+        } = $options;
+        let builder = $builder;
+        for dir in import_dirs {
+            builder.import_dir(dir);
+        }
+        for (prefix, dir) in import_mappings {
+            builder.import_mapping(prefix, dir);
+        }
+        for dir in overlay_dirs {
+            builder.overlay_dir(dir);
+        }
+        builder
+            .param_defaults(*param_defaults)
+            .union_defaults(*union_defaults)
+            .skip_unknown_declarations(*skip_unknown_declarations)
+            .flatten_nested_unions(*flatten_nested_unions)
+            .iso8601_defaults(*iso8601_defaults)
+            .validate_defaults(*validate_defaults)
+            .warn_unused_types(*warn_unused_types)
+            .deny_warnings(*deny_warnings)
+            .strip_docs(*strip_docs)
+            .sort_messages(*sort_messages)
+            .references_by_name(*refs_by_name);
+        if let Some(depth) = *max_nesting_depth {
+            builder.max_nesting_depth(depth);
+        }
+        if let Some(prefix) = namespace_prefix {
+            builder.require_namespace_prefix(prefix.clone());
+        }
+        for rule in naming_rules {
+            builder.lint_names(*rule);
+        }
+        if let Some(max) = *max_union_branches {
+            builder.max_union_branches(max);
+        }
+        if let Some(style) = *nullable_style {
+            builder.nullable_style(style);
+        }
+        if let Some(scope) = *require_docs {
+            builder.require_docs(scope);
+        }
+        for kind in allowed_warnings {
+            builder.allow_warning(*kind);
+        }
+        for pattern in allow_properties {
+            builder.allow_property(pattern.clone());
+        }
+        for pattern in deny_properties {
+            builder.deny_property(pattern.clone());
+        }
+    }};
+}
+
 /// Parsed CLI arguments for the `idl` subcommand.
 struct IdlArgs {
     input: Option<String>,
     output: Option<String>,
-    import_dirs: Vec<PathBuf>,
+    /// With `--out-dir`, every positional is an input and `input`/`output`
+    /// are unset.
+    out_dir: Option<PathBuf>,
+    inputs: Vec<String>,
+    recursive: bool,
+    output_template: Option<OutputTemplate>,
+    multiple_protocols: bool,
+    cache: Option<PathBuf>,
+    compile: CompileOptions,
+    set_properties: Vec<(String, serde_json::Value)>,
+    build_metadata: bool,
 }
 
 /// Parsed CLI arguments for the `idl2schemata` subcommand.
 struct Idl2schemataArgs {
     input: String,
     outdir: Option<PathBuf>,
-    import_dirs: Vec<PathBuf>,
+    compile: CompileOptions,
+    import_base: Option<PathBuf>,
+    output_archive: Option<PathBuf>,
+    namespace_dirs: bool,
+    output_template: Option<OutputTemplate>,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    only_roots: bool,
+    protocol: bool,
+    stdout: bool,
+    force: bool,
 }
 
-/// Parse `--import-dir` and positional args for the `idl` subcommand.
+/// Parse options and positional args for the `idl` subcommand.
 fn parse_idl_args(parser: &mut lexopt::Parser) -> Result<IdlArgs, lexopt::Error> {
-    let mut import_dirs = Vec::new();
+    let mut out_dir = None;
+    let mut recursive = false;
+    let mut output_template = None;
+    let mut multiple_protocols = false;
+    let mut cache = None;
+    let mut compile = CompileOptions::default();
+    let mut set_properties = Vec::new();
+    let mut build_metadata = false;
     let mut positionals: Vec<String> = Vec::new();
+    let mut input_list = false;
 
     while let Some(arg) = parser.next()? {
         match arg {
-            Long("import-dir") => {
+            Long("out-dir") => {
+                out_dir = Some(PathBuf::from(parser.value()?));
+            }
+            Long("input-list") => {
+                let path: String = parser.value()?.string()?;
+                positionals.extend(read_input_list(&path)?);
+                input_list = true;
+            }
+            Long("multiple-protocols") => {
+                multiple_protocols = true;
+            }
+            Long("recursive") => {
+                recursive = true;
+            }
+            Long("output-template") => {
+                output_template = Some(parse_output_template(parser)?);
+            }
+            Long("cache") => {
+                cache = Some(PathBuf::from(parser.value()?));
+            }
+            Long("set-property") => {
                 let val: String = parser.value()?.string()?;
-                import_dirs.push(PathBuf::from(val));
+                let Some((key, value)) = val.split_once('=') else {
+                    return Err(lexopt::Error::Custom(
+                        format!("--set-property expects KEY=VALUE, got `{val}`").into(),
+                    ));
+                };
+                let value = serde_json::from_str(value)
+                    .unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
+                set_properties.push((key.to_string(), value));
+            }
+            Long("build-metadata") => {
+                build_metadata = true;
             }
             Short('h') | Long("help") => {
                 println!("{IDL_HELP}");
                 std::process::exit(0);
             }
+            Long(option) => {
+                let option = option.to_string();
+                if !compile.parse_option(&option, parser)? {
+                    return Err(lexopt::Error::UnexpectedOption(format!("--{option}")));
+                }
+            }
             Value(val) => {
                 positionals.push(val.string()?);
             }
@@ -84,31 +597,116 @@ fn parse_idl_args(parser: &mut lexopt::Parser) -> Result<IdlArgs, lexopt::Error>
         }
     }
 
-    let input = positionals.first().cloned();
-    let output = positionals.get(1).cloned();
+    if recursive && out_dir.is_none() {
+        return Err(lexopt::Error::Custom(
+            "--recursive requires --out-dir".into(),
+        ));
+    }
+    if cache.is_some() && out_dir.is_none() {
+        return Err(lexopt::Error::Custom("--cache requires --out-dir".into()));
+    }
+    if input_list && out_dir.is_none() {
+        return Err(lexopt::Error::Custom(
+            "--input-list requires --out-dir".into(),
+        ));
+    }
+    if output_template.is_some() && out_dir.is_none() {
+        return Err(lexopt::Error::Custom(
+            "--output-template requires --out-dir".into(),
+        ));
+    }
+    if multiple_protocols && out_dir.is_some() {
+        return Err(lexopt::Error::Custom(
+            "--multiple-protocols cannot be used with --out-dir".into(),
+        ));
+    }
+    let (input, output, inputs) = if out_dir.is_some() {
+        if positionals.is_empty() {
+            return Err(lexopt::Error::Custom(
+                "--out-dir needs at least one INPUT".into(),
+            ));
+        }
+        (None, None, positionals)
+    } else {
+        (
+            positionals.first().cloned(),
+            positionals.get(1).cloned(),
+            Vec::new(),
+        )
+    };
 
     Ok(IdlArgs {
         input,
         output,
-        import_dirs,
+        out_dir,
+        inputs,
+        recursive,
+        output_template,
+        multiple_protocols,
+        cache,
+        compile,
+        set_properties,
+        build_metadata,
     })
 }
 
-/// Parse `--import-dir` and positional args for the `idl2schemata` subcommand.
+/// Parse options and positional args for the `idl2schemata` subcommand.
 fn parse_idl2schemata_args(parser: &mut lexopt::Parser) -> Result<Idl2schemataArgs, lexopt::Error> {
-    let mut import_dirs = Vec::new();
+    let mut compile = CompileOptions::default();
+    let mut import_base = None;
+    let mut output_archive = None;
+    let mut namespace_dirs = false;
+    let mut output_template = None;
+    let mut include = Vec::new();
+    let mut exclude = Vec::new();
+    let mut only_roots = false;
+    let mut protocol = false;
+    let mut stdout = false;
+    let mut force = false;
     let mut positionals: Vec<String> = Vec::new();
 
     while let Some(arg) = parser.next()? {
         match arg {
-            Long("import-dir") => {
-                let val: String = parser.value()?.string()?;
-                import_dirs.push(PathBuf::from(val));
+            Long("import-base") => {
+                import_base = Some(PathBuf::from(parser.value()?));
+            }
+            Long("output-archive") => {
+                output_archive = Some(PathBuf::from(parser.value()?));
+            }
+            Long("namespace-dirs") => {
+                namespace_dirs = true;
+            }
+            Long("output-template") => {
+                output_template = Some(parse_output_template(parser)?);
+            }
+            Long("include") => {
+                include.push(parser.value()?.string()?);
+            }
+            Long("exclude") => {
+                exclude.push(parser.value()?.string()?);
+            }
+            Long("only-roots") => {
+                only_roots = true;
+            }
+            Long("protocol") => {
+                protocol = true;
+            }
+            Long("force") => {
+                force = true;
+            }
+            Long("stdout") => {
+                stdout = true;
             }
             Short('h') | Long("help") => {
                 println!("{IDL2SCHEMATA_HELP}");
                 std::process::exit(0);
             }
+            Long(option) => {
+                let option = option.to_string();
+                if !compile.parse_option(&option, parser)? {
+                    return Err(lexopt::Error::UnexpectedOption(format!("--{option}")));
+                }
+            }
             Value(val) => {
                 positionals.push(val.string()?);
             }
@@ -123,151 +721,986 @@ fn parse_idl2schemata_args(parser: &mut lexopt::Parser) -> Result<Idl2schemataAr
             option: Some("INPUT".to_string()),
         })?;
     let outdir = positionals.get(1).map(PathBuf::from);
+    if outdir.is_some() && output_archive.is_some() {
+        return Err(lexopt::Error::Custom(
+            "OUTDIR and --output-archive cannot be used together".into(),
+        ));
+    }
+    if stdout && (outdir.is_some() || output_archive.is_some() || protocol) {
+        return Err(lexopt::Error::Custom(
+            "--stdout cannot be used with OUTDIR, --output-archive, or --protocol".into(),
+        ));
+    }
+    if namespace_dirs && output_template.is_some() {
+        return Err(lexopt::Error::Custom(
+            "--namespace-dirs and --output-template cannot be used together".into(),
+        ));
+    }
 
     Ok(Idl2schemataArgs {
         input,
         outdir,
-        import_dirs,
+        compile,
+        import_base,
+        output_archive,
+        namespace_dirs,
+        output_template,
+        include,
+        exclude,
+        only_roots,
+        protocol,
+        stdout,
+        force,
     })
 }
 
+/// Parse the value of `--param-defaults`.
+fn parse_param_defaults(parser: &mut lexopt::Parser) -> Result<ParamDefaults, lexopt::Error> {
+    let val: String = parser.value()?.string()?;
+    match val.as_str() {
+        "validate" => Ok(ParamDefaults::Validate),
+        "unchecked" => Ok(ParamDefaults::Unchecked),
+        "omit" => Ok(ParamDefaults::Omit),
+        _ => Err(lexopt::Error::UnexpectedValue {
+            option: "--param-defaults".to_string(),
+            value: val.into(),
+        }),
+    }
+}
+
+/// Parse the value of `--union-defaults`.
+fn parse_union_defaults(parser: &mut lexopt::Parser) -> Result<UnionDefaults, lexopt::Error> {
+    let val: String = parser.value()?.string()?;
+    match val.as_str() {
+        "any-branch" => Ok(UnionDefaults::AnyBranch),
+        "first-branch" => Ok(UnionDefaults::FirstBranch),
+        _ => Err(lexopt::Error::UnexpectedValue {
+            option: "--union-defaults".to_string(),
+            value: val.into(),
+        }),
+    }
+}
+
+/// When to color diagnostics (`--color`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+/// Parse the value of `--color`.
+fn parse_color_choice(parser: &mut lexopt::Parser) -> Result<ColorChoice, lexopt::Error> {
+    let val: String = parser.value()?.string()?;
+    match val.as_str() {
+        "auto" => Ok(ColorChoice::Auto),
+        "always" => Ok(ColorChoice::Always),
+        "never" => Ok(ColorChoice::Never),
+        _ => Err(lexopt::Error::UnexpectedValue {
+            option: "--color".to_string(),
+            value: val.into(),
+        }),
+    }
+}
+
+/// Parse the value of `--warnings-format`.
+fn parse_warnings_format(parser: &mut lexopt::Parser) -> Result<WarningsFormat, lexopt::Error> {
+    let val: String = parser.value()?.string()?;
+    match val.as_str() {
+        "human" => Ok(WarningsFormat::Human),
+        "ndjson" => Ok(WarningsFormat::Ndjson),
+        _ => Err(lexopt::Error::UnexpectedValue {
+            option: "--warnings-format".to_string(),
+            value: val.into(),
+        }),
+    }
+}
+
+/// Parse the value of `--allow` as a warning kind.
+fn parse_warning_kind(parser: &mut lexopt::Parser) -> Result<WarningKind, lexopt::Error> {
+    let val: String = parser.value()?.string()?;
+    WarningKind::ALL
+        .into_iter()
+        .find(|kind| kind.as_str() == val)
+        .ok_or_else(|| lexopt::Error::UnexpectedValue {
+            option: "--allow".to_string(),
+            value: val.into(),
+        })
+}
+
+/// Parse the value of `--output-template`.
+fn parse_output_template(parser: &mut lexopt::Parser) -> Result<OutputTemplate, lexopt::Error> {
+    let val: String = parser.value()?.string()?;
+    OutputTemplate::parse(&val).map_err(|e| lexopt::Error::Custom(e.to_string().into()))
+}
+
+/// Parse the value of `--import-map` as `PREFIX=DIR`.
+fn parse_import_mapping(parser: &mut lexopt::Parser) -> Result<(String, PathBuf), lexopt::Error> {
+    let val: String = parser.value()?.string()?;
+    match val.split_once('=') {
+        Some((prefix, dir)) if !prefix.is_empty() && !dir.is_empty() => {
+            Ok((prefix.to_string(), PathBuf::from(dir)))
+        }
+        _ => Err(lexopt::Error::UnexpectedValue {
+            option: "--import-map".to_string(),
+            value: val.into(),
+        }),
+    }
+}
+
+/// Read the inputs in an `--input-list` file, or stdin for `-`, one per line.
+/// Blank lines are skipped. Paths are used as written, relative to the
+/// current directory like inputs given as arguments.
+fn read_input_list(path: &str) -> Result<Vec<String>, lexopt::Error> {
+    let content = if path == "-" {
+        let mut content = String::new();
+        io::stdin().read_to_string(&mut content).map_err(|e| {
+            lexopt::Error::Custom(format!("read input list from stdin: {e}").into())
+        })?;
+        content
+    } else {
+        fs::read_to_string(path)
+            .map_err(|e| lexopt::Error::Custom(format!("read input list {path}: {e}").into()))?
+    };
+    Ok(content
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.trim().is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Read the `PREFIX=DIR` import mappings in an `--import-map-file`, one per
+/// line. Blank lines and lines starting with `#` are skipped, and a relative
+/// DIR is taken relative to the file's directory, so the file can be checked
+/// in next to the schemas it describes.
+fn read_import_map_file(path: &Path) -> Result<Vec<(String, PathBuf)>, lexopt::Error> {
+    let content = fs::read_to_string(path).map_err(|e| {
+        lexopt::Error::Custom(format!("read import map file {}: {e}", path.display()).into())
+    })?;
+    let base = path.parent().unwrap_or(Path::new(""));
+    let mut mappings = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_once('=') {
+            Some((prefix, dir)) if !prefix.trim().is_empty() && !dir.trim().is_empty() => {
+                mappings.push((prefix.trim().to_string(), base.join(dir.trim())));
+            }
+            _ => {
+                return Err(lexopt::Error::Custom(
+                    format!(
+                        "{}:{}: expected PREFIX=DIR, found `{line}`",
+                        path.display(),
+                        number + 1
+                    )
+                    .into(),
+                ));
+            }
+        }
+    }
+    Ok(mappings)
+}
+
+/// Parse the value of `--lint-names` as a naming rule.
+fn parse_naming_rule(parser: &mut lexopt::Parser) -> Result<NamingRule, lexopt::Error> {
+    let val: String = parser.value()?.string()?;
+    NamingRule::ALL
+        .into_iter()
+        .find(|rule| rule.as_str() == val)
+        .ok_or_else(|| lexopt::Error::UnexpectedValue {
+            option: "--lint-names".to_string(),
+            value: val.into(),
+        })
+}
+
+/// Parse the value of `--nullable-style`.
+fn parse_nullable_style(parser: &mut lexopt::Parser) -> Result<NullableStyle, lexopt::Error> {
+    let val: String = parser.value()?.string()?;
+    match val.as_str() {
+        "shorthand" => Ok(NullableStyle::Shorthand),
+        "union" => Ok(NullableStyle::Union),
+        _ => Err(lexopt::Error::UnexpectedValue {
+            option: "--nullable-style".to_string(),
+            value: val.into(),
+        }),
+    }
+}
+
+/// Parse the value of `--require-docs`.
+fn parse_doc_scope(parser: &mut lexopt::Parser) -> Result<DocScope, lexopt::Error> {
+    let val: String = parser.value()?.string()?;
+    match val.as_str() {
+        "public" => Ok(DocScope::Public),
+        "all" => Ok(DocScope::All),
+        _ => Err(lexopt::Error::UnexpectedValue {
+            option: "--require-docs".to_string(),
+            value: val.into(),
+        }),
+    }
+}
+
 // ==============================================================================
 // Entry Point
 // ==============================================================================
 
 fn main() -> miette::Result<()> {
-    miette::set_hook(Box::new(|_| {
-        Box::new(miette::MietteHandlerOpts::new().build())
-    }))?;
-
     let mut parser = lexopt::Parser::from_env();
 
-    // The first positional value is the subcommand name.
-    let subcommand = match parser.next() {
-        Ok(Some(Value(val))) => val.string().map_err(|e| miette::miette!("{e}"))?,
-        Ok(Some(Short('h') | Long("help"))) => {
-            println!("{MAIN_HELP}");
-            return Ok(());
-        }
-        Ok(Some(Short('V') | Long("version"))) => {
-            println!("avdl {}", env!("CARGO_PKG_VERSION"));
-            return Ok(());
-        }
-        Ok(Some(other)) => {
-            let err = other.unexpected();
-            eprintln!("error: {err}\n\n{MAIN_HELP}");
-            std::process::exit(2);
+    // Invoked as `avro-tools` (typically through a symlink): every argument
+    // belongs to the drop-in mode.
+    if parser
+        .bin_name()
+        .and_then(|name| Path::new(name).file_stem())
+        .is_some_and(|stem| stem == "avro-tools")
+    {
+        set_diagnostic_hook(ColorChoice::Auto, None)?;
+        let args = collect_raw_args(&mut parser)?;
+        return run_tool(args);
+    }
+
+    // Global options come before the subcommand; the first positional value
+    // is the subcommand name.
+    let mut color = ColorChoice::Auto;
+    let mut diagnostic_width = None;
+    let mut detailed_exit_codes = false;
+    let mut warning_output = WarningOutput {
+        verbosity: Verbosity::Normal,
+        format: WarningsFormat::Human,
+    };
+    let subcommand = loop {
+        match parser.next() {
+            Ok(Some(Short('q') | Long("quiet"))) => warning_output.verbosity = Verbosity::Quiet,
+            Ok(Some(Short('v') | Long("verbose"))) => {
+                warning_output.verbosity = Verbosity::Verbose;
+            }
+            Ok(Some(Long("detailed-exit-codes"))) => detailed_exit_codes = true,
+            Ok(Some(Long("warnings-format"))) => match parse_warnings_format(&mut parser) {
+                Ok(format) => warning_output.format = format,
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    std::process::exit(2);
+                }
+            },
+            Ok(Some(Long("color"))) => match parse_color_choice(&mut parser) {
+                Ok(choice) => color = choice,
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    std::process::exit(2);
+                }
+            },
+            Ok(Some(Long("diagnostic-width"))) => {
+                match parser.value().and_then(|val| val.parse()) {
+                    Ok(width) => diagnostic_width = Some(width),
+                    Err(e) => {
+                        eprintln!("error: {e}");
+                        std::process::exit(2);
+                    }
+                }
+            }
+            Ok(Some(Value(val))) => break val,
+            Ok(Some(Short('h') | Long("help"))) => {
+                println!("{MAIN_HELP}");
+                return Ok(());
+            }
+            Ok(Some(Short('V') | Long("version"))) => {
+                println!("avdl {}", env!("CARGO_PKG_VERSION"));
+                return Ok(());
+            }
+            Ok(Some(other)) => {
+                let err = other.unexpected();
+                eprintln!("error: {err}\n\n{MAIN_HELP}");
+                std::process::exit(2);
+            }
+            Ok(None) => {
+                eprintln!("error: a subcommand is required\n\n{MAIN_HELP}");
+                std::process::exit(2);
+            }
+            Err(e) => {
+                eprintln!("error: {e}");
+                std::process::exit(2);
+            }
         }
-        Ok(None) => {
-            eprintln!("error: a subcommand is required\n\n{MAIN_HELP}");
+    };
+    set_diagnostic_hook(color, diagnostic_width)?;
+    WARNING_OUTPUT
+        .set(warning_output)
+        .expect("warning output is only set once");
+    let subcommand = subcommand.string().map_err(|e| miette::miette!("{e}"))?;
+
+    let result = match subcommand.as_str() {
+        "idl" => parse_idl_args(&mut parser)
+            .map_err(usage_error)
+            .and_then(run_idl),
+        "idl2schemata" => parse_idl2schemata_args(&mut parser)
+            .map_err(usage_error)
+            .and_then(run_idl2schemata),
+        "deps" => commands::deps::parse_args(&mut parser)
+            .map_err(usage_error)
+            .and_then(commands::deps::run),
+        "graph" => commands::graph::parse_args(&mut parser)
+            .map_err(usage_error)
+            .and_then(commands::graph::run),
+        "check" => commands::check::parse_args(&mut parser)
+            .map_err(usage_error)
+            .and_then(commands::check::run),
+        "fix" => commands::fix::parse_args(&mut parser)
+            .map_err(usage_error)
+            .and_then(commands::fix::run),
+        "defaults" => commands::defaults::parse_args(&mut parser)
+            .map_err(usage_error)
+            .and_then(commands::defaults::run),
+        "diff" => commands::diff::parse_args(&mut parser)
+            .map_err(usage_error)
+            .and_then(commands::diff::run),
+        "equal" => commands::equal::parse_args(&mut parser)
+            .map_err(usage_error)
+            .and_then(commands::equal::run),
+        "doc" => commands::doc::parse_args(&mut parser)
+            .map_err(usage_error)
+            .and_then(commands::doc::run),
+        "verify" => commands::verify::parse_args(&mut parser)
+            .map_err(usage_error)
+            .and_then(commands::verify::run),
+        "explain" => commands::explain::parse_args(&mut parser)
+            .map_err(usage_error)
+            .and_then(commands::explain::run),
+        #[cfg(feature = "bench")]
+        "bench" => commands::bench::parse_args(&mut parser)
+            .map_err(usage_error)
+            .and_then(commands::bench::run),
+        #[cfg(not(feature = "bench"))]
+        "bench" => Err(miette::miette!(
+            help = "reinstall with `cargo install avdl --features bench`",
+            "this avdl was built without the `bench` feature"
+        )),
+        "tool" => collect_raw_args(&mut parser).and_then(run_tool),
+        other => {
+            eprintln!("error: unknown subcommand `{other}`\n\n{MAIN_HELP}");
             std::process::exit(2);
         }
-        Err(e) => {
-            eprintln!("error: {e}");
-            std::process::exit(2);
+    };
+
+    match result {
+        Err(e) if detailed_exit_codes => {
+            eprintln!("Error: {e:?}");
+            std::process::exit(exit_code(&e));
         }
+        result => result,
+    }
+}
+
+/// An invalid subcommand option or argument, told apart from other errors
+/// for `--detailed-exit-codes`.
+#[derive(Debug)]
+struct UsageError(lexopt::Error);
+
+impl std::fmt::Display for UsageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for UsageError {}
+
+impl miette::Diagnostic for UsageError {}
+
+fn usage_error(e: lexopt::Error) -> miette::Report {
+    UsageError(e).into()
+}
+
+/// The exit code for a failed run with `--detailed-exit-codes`, chosen by
+/// the error's kind (see `avdl explain`).
+fn exit_code(error: &miette::Report) -> i32 {
+    if error.downcast_ref::<UsageError>().is_some() {
+        return 2;
+    }
+    match ErrorKind::of(error) {
+        Some(ErrorKind::UndefinedName) => 4,
+        Some(ErrorKind::ImportNotFound | ErrorKind::ReadFile | ErrorKind::Encoding) => 5,
+        Some(ErrorKind::DeniedWarnings) => 6,
+        Some(_) => 3,
+        None => 1,
+    }
+}
+
+/// Install the handler that renders errors and warnings, with the color and
+/// width chosen by `--color` and `--diagnostic-width`.
+fn set_diagnostic_hook(color: ColorChoice, width: Option<usize>) -> miette::Result<()> {
+    let color = match color {
+        ColorChoice::Always => Some(true),
+        ColorChoice::Never => Some(false),
+        // https://no-color.org: any non-empty value disables color.
+        ColorChoice::Auto => std::env::var_os("NO_COLOR")
+            .is_some_and(|val| !val.is_empty())
+            .then_some(false),
     };
+    miette::set_hook(Box::new(move |_| {
+        let mut opts = miette::MietteHandlerOpts::new();
+        if let Some(color) = color {
+            opts = opts.color(color);
+        }
+        match width {
+            Some(0) => opts = opts.wrap_lines(false),
+            Some(width) => opts = opts.width(width),
+            None => {}
+        }
+        Box::new(opts.build())
+    }))?;
+    Ok(())
+}
 
-    match subcommand.as_str() {
-        "idl" => {
-            let args = parse_idl_args(&mut parser).map_err(|e| miette::miette!("{e}"))?;
-            run_idl(args.input, args.output, args.import_dirs)
+/// How much to print besides errors, chosen by `-q` and `-v`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+/// How to print warnings, chosen by `--warnings-format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WarningsFormat {
+    /// Rendered by miette, like errors.
+    Human,
+    /// One JSON object per line, for log aggregators.
+    Ndjson,
+}
+
+/// The global options that control how warnings are printed.
+#[derive(Debug, Clone, Copy)]
+struct WarningOutput {
+    verbosity: Verbosity,
+    format: WarningsFormat,
+}
+
+/// Set once in `main`; the `avro-tools` drop-in mode leaves it unset, which
+/// means human-readable warnings at normal verbosity.
+static WARNING_OUTPUT: OnceLock<WarningOutput> = OnceLock::new();
+
+/// Print warnings to stderr, unless `-q` was given. With `-v`, first print
+/// how each import resolved, and follow each warning from an imported file
+/// with the import statement that brought the file in. With
+/// `--warnings-format ndjson`, print one JSON object per warning instead.
+fn print_warnings(warnings: &[miette::Report], imports: &[ImportEdge]) {
+    let WarningOutput { verbosity, format } =
+        WARNING_OUTPUT.get().copied().unwrap_or(WarningOutput {
+            verbosity: Verbosity::Normal,
+            format: WarningsFormat::Human,
+        });
+    if verbosity == Verbosity::Quiet {
+        return;
+    }
+    if format == WarningsFormat::Ndjson {
+        for w in warnings {
+            eprintln!("{}", warning_json(w));
         }
-        "idl2schemata" => {
-            let args = parse_idl2schemata_args(&mut parser).map_err(|e| miette::miette!("{e}"))?;
-            run_idl2schemata(args.input, args.outdir, args.import_dirs)
+        return;
+    }
+    if verbosity == Verbosity::Verbose {
+        for edge in imports {
+            eprintln!(
+                "note: {}: import {} \"{}\" resolved to {}",
+                edge.importer,
+                edge.kind.as_str(),
+                edge.path,
+                edge.resolved.display()
+            );
         }
-        other => {
-            eprintln!("error: unknown subcommand `{other}`\n\n{MAIN_HELP}");
-            std::process::exit(2);
+    }
+    for w in warnings {
+        eprintln!("{w:?}");
+        if verbosity != Verbosity::Verbose {
+            continue;
+        }
+        let Some(location) = warning_location(w) else {
+            continue;
+        };
+        if let Some(edge) = imports
+            .iter()
+            .find(|edge| edge.resolved.display().to_string() == location.file)
+        {
+            eprintln!(
+                "note: this warning is from {}, imported by {} with `import {} \"{}\"`\n",
+                location.file,
+                edge.importer,
+                edge.kind.as_str(),
+                edge.path
+            );
         }
     }
 }
 
+/// Where a warning points: its file, and its span as a byte range and a
+/// 1-based line and column.
+struct WarningLocation {
+    file: String,
+    offset: usize,
+    length: usize,
+    line: usize,
+    column: usize,
+}
+
+/// The location of a warning, if it has a source span.
+fn warning_location(warning: &miette::Report) -> Option<WarningLocation> {
+    let label = warning.labels()?.next()?;
+    let contents = warning.source_code()?.read_span(label.inner(), 0, 0).ok()?;
+    Some(WarningLocation {
+        file: contents.name()?.to_string(),
+        offset: label.offset(),
+        length: label.len(),
+        line: contents.line() + 1,
+        column: contents.column() + 1,
+    })
+}
+
+/// A warning as a single-line JSON object, for `--warnings-format ndjson`.
+fn warning_json(warning: &miette::Report) -> serde_json::Value {
+    let code = warning.code().map(|code| code.to_string());
+    let category = WarningKind::ALL
+        .into_iter()
+        .find(|kind| code.as_deref() == Some(kind.code()))
+        .map(WarningKind::as_str);
+    let location = warning_location(warning);
+    serde_json::json!({
+        "code": code,
+        "category": category,
+        "message": warning.to_string(),
+        "file": location.as_ref().map(|location| &location.file),
+        "span": location.map(|location| serde_json::json!({
+            "offset": location.offset,
+            "length": location.length,
+            "line": location.line,
+            "column": location.column,
+        })),
+    })
+}
+
 // ==============================================================================
 // `idl` Subcommand
 // ==============================================================================
 
-fn run_idl(
-    input: Option<String>,
-    output: Option<String>,
-    import_dirs: Vec<PathBuf>,
-) -> miette::Result<()> {
+fn run_idl(args: IdlArgs) -> miette::Result<()> {
+    let IdlArgs {
+        input,
+        output,
+        out_dir,
+        inputs,
+        recursive,
+        output_template,
+        multiple_protocols,
+        cache,
+        compile,
+        set_properties,
+        build_metadata,
+    } = args;
+
+    // Everything that changes the output for unchanged sources.
+    let options_key = format!(
+        "{:?}",
+        (
+            env!("CARGO_PKG_VERSION"),
+            &compile,
+            &output_template,
+            &set_properties,
+            build_metadata,
+        )
+    );
+
     let mut builder = Idl::new();
-    for dir in &import_dirs {
-        builder.import_dir(dir);
+    apply_compile_options!(&compile, &mut builder);
+    builder.build_metadata(build_metadata);
+    for (key, value) in set_properties {
+        builder.set_property(key, value)?;
+    }
+
+    if let Some(out_dir) = out_dir {
+        let mut cache = cache
+            .map(|path| BuildCache::load(path, &options_key))
+            .transpose()?;
+        let template = output_template.as_ref();
+        let result = if recursive {
+            compile_idl_tree(&mut builder, &inputs, &out_dir, template, cache.as_mut())
+        } else {
+            compile_idl_batch(&mut builder, &inputs, &out_dir, template, cache.as_mut())
+        };
+        // Keep what did compile, even if something else failed.
+        if let Some(cache) = &cache {
+            cache.save()?;
+        }
+        return result;
     }
 
-    let idl_output = match &input {
-        Some(path) if path != "-" => builder.convert(path),
-        _ => {
+    let source = match input {
+        Some(path) if path != "-" => IdlSource::Path(path),
+        input => {
             // Read from stdin.
             let mut source = String::new();
             io::stdin()
                 .read_to_string(&mut source)
                 .map_err(|e| miette::miette!("{e}: read IDL from stdin"))?;
-            let source_name = input.map(|s| &*String::leak(s)).unwrap_or("<stdin>");
-            let source = source.leak();
-            builder.convert_str_named(source, source_name)
+            let name = input.map(|s| &*String::leak(s)).unwrap_or("<stdin>");
+            IdlSource::Stdin {
+                source: source.leak(),
+                name,
+            }
         }
     };
 
+    if multiple_protocols {
+        let outputs = match source {
+            IdlSource::Path(path) => builder.convert_protocols(path),
+            IdlSource::Stdin { source, name } => builder.convert_str_protocols_named(source, name),
+        };
+        return match outputs {
+            Ok(outputs) => {
+                for idl_output in &outputs {
+                    print_warnings(&idl_output.warnings, idl_output.imports());
+                }
+                let json: Vec<_> = outputs.iter().map(IdlOutput::ordered_json).collect();
+                stream_output(output.as_deref(), |w| {
+                    serde_json::to_writer_pretty(w, &json).map_err(io::Error::from)
+                })
+            }
+            Err(e) => {
+                print_warnings(&builder.drain_warnings(), &[]);
+                Err(e)
+            }
+        };
+    }
+
+    let idl_output = match source {
+        IdlSource::Path(path) => builder.convert(path),
+        IdlSource::Stdin { source, name } => builder.convert_str_named(source, name),
+    };
+
     // Emit warnings to stderr regardless of whether compilation succeeded.
     // On success, warnings come from the output; on error, they come from
     // the builder's accumulated state (since `convert` stores them before
     // returning `Err`).
     match idl_output {
         Ok(idl_output) => {
-            for w in &idl_output.warnings {
-                eprintln!("{w:?}");
+            print_warnings(&idl_output.warnings, idl_output.imports());
+
+            // Stream the JSON: rendering it to a `String` first would double
+            // peak memory for very large protocols.
+            stream_output(output.as_deref(), |w| idl_output.write_json(w))
+        }
+        Err(e) => {
+            print_warnings(&builder.drain_warnings(), &[]);
+            Err(e)
+        }
+    }
+}
+
+/// Where `avdl idl` reads its input from.
+enum IdlSource {
+    Path(String),
+    Stdin {
+        source: &'static str,
+        name: &'static str,
+    },
+}
+
+/// Compile every input (file, directory, or glob) with the same options,
+/// writing `out_dir/<input stem>.avpr` (or `.avsc`), or the path `template`
+/// gives, for each.
+///
+/// Stops at the first file that fails to compile.
+fn compile_idl_batch(
+    builder: &mut Idl,
+    inputs: &[String],
+    out_dir: &Path,
+    template: Option<&OutputTemplate>,
+    mut cache: Option<&mut BuildCache>,
+) -> miette::Result<()> {
+    let mut files = Vec::new();
+    for input in inputs {
+        let matched = expand_glob(input)?;
+        if matched.is_empty() {
+            miette::bail!("no files match {input}");
+        }
+        files.extend(expand_avdl_inputs(&matched));
+    }
+
+    // By default, outputs are named after the input alone, so two inputs with
+    // the same stem would silently overwrite each other.
+    let mut stems: std::collections::HashMap<&std::ffi::OsStr, &Path> =
+        std::collections::HashMap::new();
+    for file in files.iter().filter(|_| template.is_none()) {
+        if let Some(previous) = stems.insert(file.file_stem().unwrap_or_default(), file) {
+            miette::bail!(
+                help = "compile them with --recursive, or into separate output directories",
+                "{} and {} would both be written to {}",
+                previous.display(),
+                file.display(),
+                out_dir.display()
+            );
+        }
+    }
+
+    for file in &files {
+        compile_idl_file(builder, file, out_dir, template, cache.as_deref_mut())?;
+    }
+    Ok(())
+}
+
+/// Compile every `.avdl` file under each of `roots`, mirroring the
+/// directory structure below the root in `out_dir`.
+///
+/// Unlike [`compile_idl_batch`], a file that fails to compile does not stop
+/// the others; its error is printed, and a summary follows at the end.
+fn compile_idl_tree(
+    builder: &mut Idl,
+    roots: &[String],
+    out_dir: &Path,
+    template: Option<&OutputTemplate>,
+    mut cache: Option<&mut BuildCache>,
+) -> miette::Result<()> {
+    let mut compiled = 0;
+    let mut up_to_date = 0;
+    let mut failed = Vec::new();
+    for root in roots.iter().map(Path::new) {
+        if !root.is_dir() {
+            miette::bail!(
+                help = "--recursive compiles directories; drop it to compile single files",
+                "{} is not a directory",
+                root.display()
+            );
+        }
+        for file in expand_avdl_inputs(&[root.to_path_buf()]) {
+            let relative_dir = file
+                .strip_prefix(root)
+                .ok()
+                .and_then(Path::parent)
+                .unwrap_or(Path::new(""));
+            let dir = out_dir.join(relative_dir);
+            match compile_idl_file(builder, &file, &dir, template, cache.as_deref_mut()) {
+                Ok(Compiled::Written) => compiled += 1,
+                Ok(Compiled::UpToDate) => {
+                    compiled += 1;
+                    up_to_date += 1;
+                }
+                Err(e) => {
+                    eprintln!("{e:?}");
+                    failed.push(file);
+                }
             }
+        }
+    }
 
-            let json_str = serde_json::to_string_pretty(&idl_output.json)
-                .map_err(|e| miette::miette!("serialize JSON: {e}"))?;
+    let total = compiled + failed.len();
+    if up_to_date > 0 {
+        eprintln!("compiled {compiled} of {total} files ({up_to_date} already up to date)");
+    } else {
+        eprintln!("compiled {compiled} of {total} files");
+    }
+    for file in &failed {
+        eprintln!("  failed: {}", file.display());
+    }
+    if !failed.is_empty() {
+        miette::bail!("{} of {total} files failed to compile", failed.len());
+    }
+    Ok(())
+}
+
+/// The name and namespace of a compiled protocol, or of a compiled schema if
+/// it is a named type, and which of the two it is: what the output path of
+/// [`compile_idl_file`] depends on, besides the input.
+#[derive(Default, Serialize, Deserialize)]
+struct OutputName {
+    name: Option<String>,
+    namespace: Option<String>,
+    protocol: bool,
+}
 
-            write_output(output.as_deref(), &json_str)?;
+impl OutputName {
+    fn of(json: &serde_json::Value) -> Self {
+        let (name, namespace) = output_name(json);
+        OutputName {
+            name: name.map(str::to_string),
+            namespace: namespace.map(str::to_string),
+            protocol: json.get("protocol").is_some(),
+        }
+    }
+}
 
-            Ok(())
+/// Where [`compile_idl_file`] writes the output for `file`, which declares
+/// `declared`: `dir/<file stem>.avpr` (or `.avsc`), or the path under `dir`
+/// that `template` gives.
+fn output_path(
+    file: &Path,
+    dir: &Path,
+    template: Option<&OutputTemplate>,
+    declared: &OutputName,
+) -> PathBuf {
+    let ext = if declared.protocol { "avpr" } else { "avsc" };
+    match template {
+        Some(template) => {
+            let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+            dir.join(template.expand(&TemplateValues {
+                stem: &stem,
+                name: declared.name.as_deref().unwrap_or(&stem),
+                namespace: declared.namespace.as_deref(),
+                ext,
+            }))
         }
+        None => {
+            let mut name = file.file_stem().unwrap_or_default().to_os_string();
+            name.push(".");
+            name.push(ext);
+            dir.join(name)
+        }
+    }
+}
+
+/// The name and namespace of a compiled protocol, or of a compiled schema if
+/// it is a named type, for `--output-template`.
+fn output_name(json: &serde_json::Value) -> (Option<&str>, Option<&str>) {
+    let get = |key| json.get(key).and_then(serde_json::Value::as_str);
+    let (name, namespace) = match get("protocol") {
+        Some(protocol) => (Some(protocol), get("namespace")),
+        None => (get("name"), get("namespace")),
+    };
+    // A schema's name may carry its namespace.
+    match name.and_then(|name| name.rsplit_once('.')) {
+        Some((namespace, name)) => (Some(name), Some(namespace)),
+        None => (name, namespace.filter(|namespace| !namespace.is_empty())),
+    }
+}
+
+/// Whether [`compile_idl_file`] wrote the output or found it up to date.
+enum Compiled {
+    Written,
+    UpToDate,
+}
+
+/// Compile `file` into `dir/<file stem>.avpr` (or `.avsc` for a schema-mode
+/// file), or the path under `dir` that `template` gives, printing its
+/// warnings. With a `cache`, a file whose previous output is still up to date
+/// is skipped.
+fn compile_idl_file(
+    builder: &mut Idl,
+    file: &Path,
+    dir: &Path,
+    template: Option<&OutputTemplate>,
+    cache: Option<&mut BuildCache>,
+) -> miette::Result<Compiled> {
+    if cache
+        .as_ref()
+        .is_some_and(|cache| cache.is_fresh(file, dir, template))
+    {
+        return Ok(Compiled::UpToDate);
+    }
+    let idl_output = match builder.convert(file) {
+        Ok(idl_output) => idl_output,
         Err(e) => {
-            for w in builder.drain_warnings() {
-                eprintln!("{w:?}");
-            }
-            Err(e)
+            print_warnings(&builder.drain_warnings(), &[]);
+            return Err(e);
         }
+    };
+    print_warnings(&idl_output.warnings, idl_output.imports());
+
+    let declared = OutputName::of(&idl_output.json);
+    let path = output_path(file, dir, template, &declared);
+    let parent = path.parent().unwrap_or(dir);
+    fs::create_dir_all(parent)
+        .map_err(|e| miette::miette!("{e}: create output directory {}", parent.display()))?;
+    write_output_file(&path, |w| idl_output.write_json(w))?;
+    if let Some(cache) = cache {
+        cache.record(file, idl_output.imports(), declared, &path);
     }
+    Ok(Compiled::Written)
 }
 
 // ==============================================================================
 // `idl2schemata` Subcommand
 // ==============================================================================
 
-fn run_idl2schemata(
-    input: String,
-    outdir: Option<PathBuf>,
-    import_dirs: Vec<PathBuf>,
-) -> miette::Result<()> {
+fn run_idl2schemata(args: Idl2schemataArgs) -> miette::Result<()> {
+    let Idl2schemataArgs {
+        input,
+        outdir,
+        compile,
+        import_base,
+        output_archive,
+        namespace_dirs,
+        output_template,
+        include,
+        exclude,
+        only_roots,
+        protocol,
+        stdout,
+        force,
+    } = args;
+
     let mut builder = Idl2Schemata::new();
-    for dir in &import_dirs {
-        builder.import_dir(dir);
+    apply_compile_options!(&compile, &mut builder);
+    for pattern in include {
+        builder.include(pattern);
+    }
+    for pattern in exclude {
+        builder.exclude(pattern);
+    }
+    builder
+        .only_roots(only_roots)
+        .protocols(protocol)
+        .namespace_dirs(namespace_dirs);
+    if let Some(template) = output_template {
+        builder.output_template(template);
+    }
+    if let Some(base) = import_base {
+        builder.import_base(base);
     }
 
-    let schemata_output = match builder.extract(&input) {
+    let schemata_output = if input == "-" {
+        let mut source = String::new();
+        io::stdin()
+            .read_to_string(&mut source)
+            .map_err(|e| miette::miette!("{e}: read IDL from stdin"))?;
+        builder.extract_str_named(source.leak(), "<stdin>")
+    } else {
+        builder.extract(&input)
+    };
+    let schemata_output = match schemata_output {
         Ok(output) => output,
         Err(e) => {
             // Emit warnings that were accumulated before the error. These
             // would otherwise be lost since `extract` returns `Err`.
-            for w in builder.drain_warnings() {
-                eprintln!("{w:?}");
-            }
+            print_warnings(&builder.drain_warnings(), &[]);
             return Err(e);
         }
     };
 
     // Emit warnings to stderr. Rendered through miette for rich diagnostic
     // output with source spans and labels when available.
-    for w in &schemata_output.warnings {
-        eprintln!("{w:?}");
+    print_warnings(&schemata_output.warnings, schemata_output.imports());
+
+    if stdout {
+        let schemas: serde_json::Map<String, serde_json::Value> = schemata_output
+            .schemas
+            .iter()
+            .map(|named_schema| (named_schema.full_name(), named_schema.schema.clone()))
+            .collect();
+        let json_str = serde_json::to_string_pretty(&schemas)
+            .map_err(|e| miette::miette!("serialize JSON: {e}"))?;
+        println!("{json_str}");
+        return Ok(());
+    }
+
+    if let Some(archive) = output_archive {
+        let mut outputs = OutputFiles::default();
+        outputs.add(
+            archive.clone(),
+            schema_archive(
+                &archive,
+                &schemata_output.schemas,
+                &schemata_output.protocols,
+            )?,
+        );
+        return outputs.write_all_unless_conflicting(force);
     }
 
     let output_dir = outdir.unwrap_or_else(|| PathBuf::from("."));
@@ -279,25 +1712,491 @@ fn run_idl2schemata(
     }
     fs::create_dir_all(&output_dir).map_err(|e| miette::miette!("{e}: create output directory"))?;
 
+    // In the flat layout (or a template without the namespace), schemas with
+    // the same simple name in different namespaces map to the same file. Java
+    // silently lets the last one win; we do the same but say so.
+    let mut seen = std::collections::HashSet::new();
+    for named_schema in &schemata_output.schemas {
+        if !seen.insert(&named_schema.suggested_path) {
+            eprintln!(
+                "{:?}",
+                miette::miette!(
+                    severity = miette::Severity::Warning,
+                    help = "use --namespace-dirs to write one directory per namespace",
+                    "multiple schemas are written to `{}`; it is overwritten",
+                    named_schema.suggested_path.display()
+                )
+            );
+        }
+    }
+
+    let mut outputs = OutputFiles::default();
     for named_schema in &schemata_output.schemas {
         let json_str = serde_json::to_string_pretty(&named_schema.schema)
             .map_err(|e| miette::miette!("serialize JSON for {}: {e}", named_schema.name))?;
-
-        let file_path = output_dir.join(format!("{}.avsc", named_schema.name));
         // Append trailing newline to match Java's `PrintStream.println()`.
-        fs::write(&file_path, format!("{json_str}\n"))
-            .map_err(|e| miette::miette!("{e}: write {}", file_path.display()))?;
+        outputs.add(
+            output_dir.join(&named_schema.suggested_path),
+            format!("{json_str}\n"),
+        );
     }
 
-    Ok(())
+    for named_protocol in &schemata_output.protocols {
+        let json_str = serde_json::to_string_pretty(&named_protocol.ordered_json())
+            .map_err(|e| miette::miette!("serialize JSON for {}: {e}", named_protocol.name))?;
+        outputs.add(
+            output_dir.join(format!("{}.avpr", named_protocol.name)),
+            format!("{json_str}\n"),
+        );
+    }
+
+    outputs.write_all_unless_conflicting(force)
+}
+
+/// Output files to write, by path. Nothing is written until every file is
+/// known, so that conflicts with existing files can be reported before the
+/// output directory is touched. Schemas sharing a path still behave as they
+/// do in Java: the last one wins.
+#[derive(Default)]
+struct OutputFiles {
+    files: std::collections::BTreeMap<PathBuf, Vec<u8>>,
+}
+
+impl OutputFiles {
+    /// Plan to write `content` to `path`, replacing what was planned for it.
+    fn add(&mut self, path: PathBuf, content: impl Into<Vec<u8>>) {
+        self.files.insert(path, content.into());
+    }
+
+    /// The files that would replace an existing file with different content.
+    fn conflicts(&self) -> Vec<&Path> {
+        self.files
+            .iter()
+            .filter(|(path, content)| fs::read(path).is_ok_and(|existing| existing != **content))
+            .map(|(path, _)| path.as_path())
+            .collect()
+    }
+
+    /// Write every file, unless one would replace an existing file with
+    /// different content and `force` is not set.
+    fn write_all_unless_conflicting(&self, force: bool) -> miette::Result<()> {
+        if !force {
+            let conflicts = self.conflicts();
+            for file in &conflicts {
+                eprintln!("  would overwrite: {}", file.display());
+            }
+            if !conflicts.is_empty() {
+                miette::bail!(
+                    help = "pass --force to overwrite them",
+                    "{} existing files differ from the new output; no files were written",
+                    conflicts.len()
+                );
+            }
+        }
+        self.write_all()
+    }
+
+    /// Write every file, creating its directory if needed.
+    fn write_all(&self) -> miette::Result<()> {
+        for (path, content) in &self.files {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).map_err(|e| {
+                    miette::miette!("{e}: create output directory {}", dir.display())
+                })?;
+            }
+            fs::write(path, content)
+                .map_err(|e| miette::miette!("{e}: write {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+// ==============================================================================
+// `tool` Drop-in Mode
+// ==============================================================================
+
+/// Collect all remaining arguments verbatim, without option parsing. In the
+/// drop-in mode, `-` and `--help` are ordinary positional arguments, exactly
+/// as `avro-tools` sees them.
+fn collect_raw_args(parser: &mut lexopt::Parser) -> miette::Result<Vec<String>> {
+    let raw = parser.raw_args().map_err(|e| miette::miette!("{e}"))?;
+    raw.map(|arg| {
+        arg.into_string()
+            .map_err(|arg| miette::miette!("argument is not valid UTF-8: {arg:?}"))
+    })
+    .collect()
+}
+
+/// Dispatch a drop-in invocation. `args[0]` is the tool name.
+///
+/// Usage errors print the tool's usage line to stderr and exit with status
+/// 255, which is what `avro-tools` produces when a tool returns `-1`.
+fn run_tool(args: Vec<String>) -> miette::Result<()> {
+    let Some((tool, rest)) = args.split_first() else {
+        eprintln!("{TOOL_HELP}");
+        std::process::exit(255);
+    };
+    let is_help = |a: &[String]| a.len() == 1 && matches!(a[0].as_str(), "-h" | "-help" | "--help");
+
+    match tool.as_str() {
+        "idl" => {
+            if rest.len() > 2 || is_help(rest) {
+                eprintln!("{TOOL_IDL_USAGE}");
+                std::process::exit(255);
+            }
+            run_idl(IdlArgs {
+                input: rest.first().cloned(),
+                output: rest.get(1).cloned(),
+                out_dir: None,
+                inputs: Vec::new(),
+                recursive: false,
+                output_template: None,
+                multiple_protocols: false,
+                cache: None,
+                compile: CompileOptions::default(),
+                set_properties: Vec::new(),
+                build_metadata: false,
+            })
+        }
+        "idl2schemata" => {
+            if rest.is_empty() || rest.len() > 2 || is_help(rest) {
+                eprintln!("{TOOL_IDL2SCHEMATA_USAGE}");
+                std::process::exit(255);
+            }
+            run_idl2schemata(Idl2schemataArgs {
+                input: rest[0].clone(),
+                outdir: rest.get(1).map(PathBuf::from),
+                compile: CompileOptions::default(),
+                import_base: None,
+                output_archive: None,
+                namespace_dirs: false,
+                output_template: None,
+                include: Vec::new(),
+                exclude: Vec::new(),
+                only_roots: false,
+                protocol: false,
+                stdout: false,
+                // `avro-tools` always overwrites.
+                force: true,
+            })
+        }
+        other => {
+            eprintln!("Unknown tool: {other}\n\n{TOOL_HELP}");
+            std::process::exit(255);
+        }
+    }
+}
+
+// ==============================================================================
+// Build Cache
+// ==============================================================================
+
+/// What each input of a batch `idl` build was last compiled from, kept in a
+/// JSON file between runs so unchanged inputs can be skipped.
+///
+/// An input is up to date if it was compiled with the same compiler version
+/// and options, to the path it would be compiled to now, its output is
+/// unchanged, neither it nor any file it (transitively) imported has changed
+/// since, and no file has been created where an import was searched for
+/// before it was found. An input that imported from an archive is always
+/// compiled again.
+struct BuildCache {
+    path: PathBuf,
+    options: String,
+    file: CacheFile,
+}
+
+/// The contents of a [`BuildCache`] file.
+#[derive(Default, Serialize, Deserialize)]
+struct CacheFile {
+    /// By input path.
+    entries: std::collections::BTreeMap<String, CacheEntry>,
+}
+
+/// How one input was last compiled.
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    /// Hash of the compiler version and options.
+    options: String,
+    /// Hash of the input and of every file it imported, by path.
+    sources: std::collections::BTreeMap<String, String>,
+    /// Paths with no file that were searched for an import first.
+    #[serde(default)]
+    missed: std::collections::BTreeSet<String>,
+    /// What the input declared, which its output path depends on.
+    #[serde(default)]
+    declared: OutputName,
+    output: PathBuf,
+    output_hash: String,
+}
+
+impl BuildCache {
+    /// Load the cache at `path`, or start an empty one if there is no file
+    /// there yet. Fails if the file cannot be read or is not a cache.
+    fn load(path: PathBuf, options_key: &str) -> miette::Result<Self> {
+        let file = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| {
+                miette::miette!(
+                    help = "delete it to rebuild every input",
+                    "{e}: corrupt build cache {}",
+                    path.display()
+                )
+            })?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => CacheFile::default(),
+            Err(e) => miette::bail!("{e}: read build cache {}", path.display()),
+        };
+        Ok(BuildCache {
+            path,
+            options: content_hash(options_key.as_bytes()),
+            file,
+        })
+    }
+
+    /// Whether `input` was last compiled, to the path in `dir` it would be
+    /// compiled to now, from exactly the sources and options it would be
+    /// compiled from now.
+    fn is_fresh(&self, input: &Path, dir: &Path, template: Option<&OutputTemplate>) -> bool {
+        let Some(entry) = self.file.entries.get(&*input.to_string_lossy()) else {
+            return false;
+        };
+        let hash_matches = |path: &Path, hash: &str| {
+            fs::read(path).is_ok_and(|bytes| content_hash(&bytes) == hash)
+        };
+
+        entry.options == self.options
+            && entry.output == output_path(input, dir, template, &entry.declared)
+            && hash_matches(entry.output.as_path(), entry.output_hash.as_str())
+            && entry
+                .sources
+                .iter()
+                .all(|(source, hash)| hash_matches(Path::new(source), hash.as_str()))
+            && !entry.missed.iter().any(|path| Path::new(path).exists())
+    }
+
+    /// Remember that `input`, which imported `imports` and declared
+    /// `declared`, was just compiled to `output`.
+    fn record(
+        &mut self,
+        input: &Path,
+        imports: &[ImportEdge],
+        declared: OutputName,
+        output: &Path,
+    ) {
+        let key = input.to_string_lossy().into_owned();
+        let hash = |file: &Path| fs::read(file).ok().map(|bytes| content_hash(&bytes));
+        let sources: Option<std::collections::BTreeMap<_, _>> = std::iter::once(input)
+            .chain(imports.iter().map(|edge| edge.resolved.as_path()))
+            .map(|file| Some((file.to_string_lossy().into_owned(), hash(file)?)))
+            .collect();
+        let missed: Option<std::collections::BTreeSet<_>> = imports
+            .iter()
+            .map(|edge| edge.missed.as_deref())
+            .try_fold(std::collections::BTreeSet::new(), |mut all, missed| {
+                all.extend(
+                    missed?
+                        .iter()
+                        .map(|path| path.to_string_lossy().into_owned()),
+                );
+                Some(all)
+            });
+        let (Some(sources), Some(missed), Some(output_hash)) = (sources, missed, hash(output))
+        else {
+            // Without a hash, or with imports that are not plain files, the
+            // entry could never be trusted to be fresh.
+            self.file.entries.remove(&key);
+            return;
+        };
+        self.file.entries.insert(
+            key,
+            CacheEntry {
+                options: self.options.clone(),
+                sources,
+                missed,
+                declared,
+                output: output.to_path_buf(),
+                output_hash,
+            },
+        );
+    }
+
+    fn save(&self) -> miette::Result<()> {
+        write_output_file(&self.path, |w| {
+            serde_json::to_writer(w, &self.file).map_err(io::Error::from)
+        })
+    }
+}
+
+/// A 64-bit FNV-1a hash of `bytes`, as hex. Unlike the standard library's
+/// hasher, its output is guaranteed not to change between Rust releases.
+fn content_hash(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{hash:016x}")
+}
+
+// ==============================================================================
+// Input Discovery
+// ==============================================================================
+
+/// Replace each directory among `inputs` with the `.avdl` files found
+/// recursively inside it, sorted by path.
+fn expand_avdl_inputs(inputs: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for input in inputs {
+        if input.is_dir() {
+            let mut found: Vec<PathBuf> = walkdir::WalkDir::new(input)
+                .into_iter()
+                .filter_map(Result::ok)
+                .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "avdl"))
+                .map(walkdir::DirEntry::into_path)
+                .collect();
+            found.sort();
+            files.extend(found);
+        } else {
+            files.push(input.clone());
+        }
+    }
+    files
+}
+
+/// Expand a glob `pattern` into the files it matches, sorted by path. `*` and
+/// `?` match within one path component and `**` matches any number of
+/// directories. A pattern without wildcards is returned as is, so plain paths
+/// (and the errors for missing ones) pass through unchanged.
+///
+/// Shells on Windows do not expand globs, so `avdl` does it itself.
+fn expand_glob(pattern: &str) -> miette::Result<Vec<PathBuf>> {
+    let Some(wildcard) = pattern.find(['*', '?']) else {
+        return Ok(vec![PathBuf::from(pattern)]);
+    };
+    // Walk from the deepest directory named before the first wildcard.
+    let (root, rest) = match pattern[..wildcard].rfind(['/', '\\']) {
+        Some(0) => ("/", &pattern[1..]),
+        Some(sep) => (&pattern[..sep], &pattern[sep + 1..]),
+        None => (".", pattern),
+    };
+    let segments: Vec<&str> = rest.split(['/', '\\']).filter(|s| !s.is_empty()).collect();
+
+    let mut files = Vec::new();
+    for entry in walkdir::WalkDir::new(root).min_depth(1) {
+        let entry = entry.map_err(|e| miette::miette!("{e}: expand {pattern}"))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Ok(relative) = entry.path().strip_prefix(root) else {
+            continue;
+        };
+        let components: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        if path_matches(&segments, &components) {
+            files.push(entry.into_path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Match path components against glob segments, where a `**` segment
+/// matches zero or more components.
+fn path_matches(segments: &[&str], components: &[String]) -> bool {
+    match segments.split_first() {
+        None => components.is_empty(),
+        Some((&"**", rest)) => {
+            (0..=components.len()).any(|skip| path_matches(rest, &components[skip..]))
+        }
+        Some((segment, rest)) => components.split_first().is_some_and(|(first, others)| {
+            component_matches(segment, first) && path_matches(rest, others)
+        }),
+    }
+}
+
+/// Match one path component against a glob segment, where `*` matches any
+/// run of characters and `?` matches exactly one.
+fn component_matches(segment: &str, name: &str) -> bool {
+    let mut segment = segment.chars();
+    match segment.next() {
+        None => name.is_empty(),
+        Some('*') => {
+            let rest = segment.as_str();
+            name.char_indices()
+                .map(|(i, _)| i)
+                .chain([name.len()])
+                .any(|i| component_matches(rest, &name[i..]))
+        }
+        Some(c) => {
+            let mut chars = name.chars();
+            chars.next().is_some_and(|n| c == '?' || c == n)
+                && component_matches(segment.as_str(), chars.as_str())
+        }
+    }
 }
 
 // ==============================================================================
 // Output Writing
 // ==============================================================================
 
+/// A zip archive holding each schema as `<namespace as path>/<name>.avsc`,
+/// and each protocol as `<name>.avpr`, to be written to `path`.
+fn schema_archive(
+    path: &Path,
+    schemas: &[NamedSchema],
+    protocols: &[NamedProtocol],
+) -> miette::Result<Vec<u8>> {
+    use std::io::Write;
+
+    let mut archive = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default();
+    for named_schema in schemas {
+        let json_str = serde_json::to_string_pretty(&named_schema.schema)
+            .map_err(|e| miette::miette!("serialize JSON for {}: {e}", named_schema.name))?;
+
+        let mut entry = String::new();
+        if let Some(namespace) = &named_schema.namespace {
+            entry.push_str(&namespace.replace('.', "/"));
+            entry.push('/');
+        }
+        write!(entry, "{}.avsc", named_schema.name).expect("write to String is infallible");
+
+        archive
+            .start_file(entry.as_str(), options)
+            .map_err(|e| miette::miette!("{e}: add {entry} to {}", path.display()))?;
+        // Same trailing newline as the loose files.
+        archive
+            .write_all(format!("{json_str}\n").as_bytes())
+            .map_err(|e| miette::miette!("{e}: add {entry} to {}", path.display()))?;
+    }
+    for named_protocol in protocols {
+        let json_str = serde_json::to_string_pretty(&named_protocol.ordered_json())
+            .map_err(|e| miette::miette!("serialize JSON for {}: {e}", named_protocol.name))?;
+        let entry = format!("{}.avpr", named_protocol.name);
+        archive
+            .start_file(entry.as_str(), options)
+            .map_err(|e| miette::miette!("{e}: add {entry} to {}", path.display()))?;
+        archive
+            .write_all(format!("{json_str}\n").as_bytes())
+            .map_err(|e| miette::miette!("{e}: add {entry} to {}", path.display()))?;
+    }
+    let archive = archive
+        .finish()
+        .map_err(|e| miette::miette!("{e}: write {}", path.display()))?;
+    Ok(archive.into_inner())
+}
+
 /// Write output to a file or stdout.
 fn write_output(output: Option<&str>, content: &str) -> miette::Result<()> {
+    stream_output(output, |w| w.write_all(content.as_bytes()))
+}
+
+/// Write output to a file or stdout as `write` produces it.
+fn stream_output(
+    output: Option<&str>,
+    write: impl FnOnce(&mut dyn io::Write) -> io::Result<()>,
+) -> miette::Result<()> {
+    use std::io::Write;
+
     // Treat `None` and `Some("-")` as stdout; everything else is a file path.
     let file_path = output.filter(|s| *s != "-");
 
@@ -305,20 +2204,31 @@ fn write_output(output: Option<&str>, content: &str) -> miette::Result<()> {
         None => {
             // Write to stdout without trailing newline, matching Java behavior.
             // Handle BrokenPipe gracefully.
-            use std::io::Write;
-            if let Err(e) = write!(io::stdout(), "{content}") {
-                if e.kind() == io::ErrorKind::BrokenPipe {
-                    return Ok(());
-                }
-                return Err(miette::miette!("{e}: write to stdout"));
+            let mut stdout = io::BufWriter::new(io::stdout().lock());
+            match write(&mut stdout).and_then(|()| stdout.flush()) {
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+                Err(e) => Err(miette::miette!("{e}: write to stdout")),
+                Ok(()) => Ok(()),
             }
-            Ok(())
-        }
-        Some(file_path) => {
-            let path = PathBuf::from(file_path);
-            // Append a trailing newline to match the golden files.
-            fs::write(&path, format!("{content}\n"))
-                .map_err(|e| miette::miette!("{e}: write {}", path.display()))
         }
+        Some(file_path) => write_output_file(Path::new(file_path), write),
     }
 }
+
+/// Write output to the file at `path` as `write` produces it.
+fn write_output_file(
+    path: &Path,
+    write: impl FnOnce(&mut dyn io::Write) -> io::Result<()>,
+) -> miette::Result<()> {
+    use std::io::Write;
+
+    // Append a trailing newline to match the golden files.
+    fs::File::create(path)
+        .map(io::BufWriter::new)
+        .and_then(|mut file| {
+            write(&mut file)?;
+            file.write_all(b"\n")?;
+            file.flush()
+        })
+        .map_err(|e| miette::miette!("{e}: write {}", path.display()))
+}
@@ -0,0 +1,241 @@
+// ==============================================================================
+// Parsing Canonical Form and Schema Fingerprints
+// ==============================================================================
+//
+// Implements the "Parsing Canonical Form for Schemas" and the CRC-64-AVRO
+// (Rabin) fingerprint from the Avro specification. Both operate on the JSON
+// produced by `schema_to_json`, so the result is exactly what a consumer
+// reading the emitted `.avsc` file would compute.
+//
+// The canonical form keeps only the attributes relevant to parsing data
+// (`name`, `type`, `fields`, `symbols`, `items`, `values`, `size`), in that
+// order, with full names everywhere and no whitespace. Like Java's
+// `SchemaNormalization`, error schemas canonicalize as `"record"` and logical
+// types collapse to their underlying type.
+
+use serde_json::Value;
+
+use super::schema::PRIMITIVE_TYPE_NAMES;
+
+/// Compute the Parsing Canonical Form of a schema JSON value.
+pub(crate) fn parsing_canonical_form(schema: &Value) -> String {
+    let mut out = String::new();
+    write_canonical(schema, None, &mut out);
+    out
+}
+
+/// Whether two schema JSON values have the same Parsing Canonical Form, i.e.
+/// read and write exactly the same data.
+///
+/// Docs, aliases, defaults, field order attributes, logical types, and custom
+/// properties are ignored, and short names compare equal to the full names
+/// they resolve to. Useful for skipping no-op changes, such as a republish to
+/// a schema registry after only a doc comment was edited.
+#[must_use]
+pub fn canonical_eq(a: &Value, b: &Value) -> bool {
+    parsing_canonical_form(a) == parsing_canonical_form(b)
+}
+
+/// Compute the CRC-64-AVRO fingerprint of a schema JSON value's Parsing
+/// Canonical Form.
+pub(crate) fn fingerprint64(schema: &Value) -> u64 {
+    rabin_fingerprint(parsing_canonical_form(schema).as_bytes())
+}
+
+/// Resolve a (possibly short) name against the enclosing namespace.
+fn full_name(name: &str, namespace: Option<&str>) -> String {
+    match namespace {
+        Some(ns) if !ns.is_empty() && !name.contains('.') => format!("{ns}.{name}"),
+        _ => name.to_string(),
+    }
+}
+
+fn push_json_string(s: &str, out: &mut String) {
+    out.push_str(&Value::String(s.to_string()).to_string());
+}
+
+fn write_canonical(schema: &Value, namespace: Option<&str>, out: &mut String) {
+    match schema {
+        Value::String(name) => {
+            if PRIMITIVE_TYPE_NAMES.contains(&name.as_str()) {
+                push_json_string(name, out);
+            } else {
+                push_json_string(&full_name(name, namespace), out);
+            }
+        }
+        Value::Array(branches) => {
+            out.push('[');
+            for (i, branch) in branches.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(branch, namespace, out);
+            }
+            out.push(']');
+        }
+        Value::Object(obj) => {
+            let Some(type_value) = obj.get("type") else {
+                // Not a valid schema; emit it verbatim so the fingerprint is
+                // still deterministic.
+                out.push_str(&schema.to_string());
+                return;
+            };
+            let Some(type_name) = type_value.as_str() else {
+                // `{"type": {...}}` or `{"type": [...]}` wraps another schema.
+                write_canonical(type_value, namespace, out);
+                return;
+            };
+            match type_name {
+                "record" | "error" | "enum" | "fixed" => {
+                    let name = obj.get("name").and_then(Value::as_str).unwrap_or("");
+                    let explicit_ns = obj.get("namespace").and_then(Value::as_str);
+                    let full = full_name(name, explicit_ns.or(namespace));
+                    let own_ns = full.rsplit_once('.').map(|(ns, _)| ns.to_string());
+
+                    out.push_str("{\"name\":");
+                    push_json_string(&full, out);
+                    out.push_str(",\"type\":");
+                    match type_name {
+                        "record" | "error" => {
+                            out.push_str("\"record\",\"fields\":[");
+                            let fields = obj.get("fields").and_then(Value::as_array);
+                            for (i, field) in fields.into_iter().flatten().enumerate() {
+                                if i > 0 {
+                                    out.push(',');
+                                }
+                                out.push_str("{\"name\":");
+                                let field_name =
+                                    field.get("name").and_then(Value::as_str).unwrap_or("");
+                                push_json_string(field_name, out);
+                                out.push_str(",\"type\":");
+                                let field_type = field.get("type").unwrap_or(&Value::Null);
+                                write_canonical(field_type, own_ns.as_deref(), out);
+                                out.push('}');
+                            }
+                            out.push(']');
+                        }
+                        "enum" => {
+                            out.push_str("\"enum\",\"symbols\":[");
+                            let symbols = obj.get("symbols").and_then(Value::as_array);
+                            for (i, symbol) in symbols.into_iter().flatten().enumerate() {
+                                if i > 0 {
+                                    out.push(',');
+                                }
+                                push_json_string(symbol.as_str().unwrap_or(""), out);
+                            }
+                            out.push(']');
+                        }
+                        _ => {
+                            out.push_str("\"fixed\",\"size\":");
+                            let size = obj.get("size").cloned().unwrap_or(Value::from(0));
+                            out.push_str(&size.to_string());
+                        }
+                    }
+                    out.push('}');
+                }
+                "array" => {
+                    out.push_str("{\"type\":\"array\",\"items\":");
+                    write_canonical(obj.get("items").unwrap_or(&Value::Null), namespace, out);
+                    out.push('}');
+                }
+                "map" => {
+                    out.push_str("{\"type\":\"map\",\"values\":");
+                    write_canonical(obj.get("values").unwrap_or(&Value::Null), namespace, out);
+                    out.push('}');
+                }
+                // A primitive (possibly with a logical type or other
+                // properties) or a reference to a named type.
+                other => write_canonical(&Value::String(other.to_string()), namespace, out),
+            }
+        }
+        // Not a valid schema; emit it verbatim.
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+/// The CRC-64-AVRO fingerprint algorithm from the Avro specification.
+pub(crate) fn rabin_fingerprint(bytes: &[u8]) -> u64 {
+    const EMPTY: u64 = 0xc15d_213a_a4d7_a795;
+    let table: [u64; 256] = std::array::from_fn(|i| {
+        let mut fp = i as u64;
+        for _ in 0..8 {
+            fp = (fp >> 1) ^ (EMPTY & (fp & 1).wrapping_neg());
+        }
+        fp
+    });
+
+    let mut fp = EMPTY;
+    for &b in bytes {
+        fp = (fp >> 8) ^ table[((fp ^ u64::from(b)) & 0xff) as usize];
+    }
+    fp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    #[test]
+    fn primitive_fingerprints_match_spec_test_vectors() {
+        // Values from the Avro test suite (`share/test/data/schema-tests.txt`).
+        assert_eq!(fingerprint64(&json!("int")), 8247732601305521295);
+        assert_eq!(fingerprint64(&json!("null")), 7195948357588979594);
+        assert_eq!(fingerprint64(&json!({"type": "int"})), 8247732601305521295);
+    }
+
+    #[test]
+    fn canonical_form_strips_and_orders_attributes() {
+        let schema = json!({
+            "type": "record",
+            "name": "User",
+            "namespace": "org.example",
+            "doc": "dropped",
+            "fields": [
+                {"name": "id", "type": {"type": "long", "logicalType": "timestamp-millis"}, "default": 0},
+                {"name": "kind", "type": {"type": "enum", "name": "Kind", "symbols": ["A", "B"]}},
+                {"name": "tags", "type": {"type": "map", "values": ["null", "Kind"]}},
+                {"name": "hash", "type": {"type": "fixed", "name": "other.Hash", "size": 16}}
+            ]
+        });
+        assert_eq!(
+            parsing_canonical_form(&schema),
+            r#"{"name":"org.example.User","type":"record","fields":[{"name":"id","type":"long"},{"name":"kind","type":{"name":"org.example.Kind","type":"enum","symbols":["A","B"]}},{"name":"tags","type":{"type":"map","values":["null","org.example.Kind"]}},{"name":"hash","type":{"name":"other.Hash","type":"fixed","size":16}}]}"#
+        );
+    }
+
+    #[test]
+    fn canonical_eq_ignores_docs_aliases_and_properties() {
+        let schema = json!({
+            "type": "record",
+            "name": "User",
+            "namespace": "org.example",
+            "fields": [{"name": "id", "type": "long"}]
+        });
+        let annotated = json!({
+            "type": "record",
+            "name": "org.example.User",
+            "doc": "A user.",
+            "aliases": ["Person"],
+            "java-class": "org.example.User",
+            "fields": [{"name": "id", "type": "long", "doc": "The id.", "default": 0}]
+        });
+        let widened = json!({
+            "type": "record",
+            "name": "org.example.User",
+            "fields": [{"name": "id", "type": "string"}]
+        });
+        assert!(canonical_eq(&schema, &annotated));
+        assert!(!canonical_eq(&schema, &widened));
+    }
+
+    #[test]
+    fn error_canonicalizes_as_record() {
+        let schema = json!({"type": "error", "name": "Oops", "fields": []});
+        assert_eq!(
+            parsing_canonical_form(&schema),
+            r#"{"name":"Oops","type":"record","fields":[]}"#
+        );
+    }
+}
@@ -0,0 +1,546 @@
+// ==============================================================================
+// Avro IDL Serialization
+// ==============================================================================
+//
+// The inverse of the reader: render the domain model as `.avdl` text that
+// compiles back to the same JSON. Every named type becomes a top-level
+// declaration, in model order; a named type nested inline in another (as JSON
+// imports produce) is declared right after the type that contains it, which
+// keeps the first-use inlining of the JSON output unchanged.
+//
+// References are written relative to the namespace they are resolved in: the
+// record's own namespace for fields, and the protocol's (or file's) namespace
+// for messages. Properties are written in the order they were declared.
+//
+// Two things have no IDL spelling and are lost: properties on a reference to a
+// named type (IDL rejects annotated references), and references from a
+// namespaced context to a type in the null namespace.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use serde_json::Value;
+
+use super::protocol::{Message, Protocol};
+use super::schema::{AvroSchema, Field, FieldOrder, LogicalType, Properties, make_full_name};
+
+/// Words the IDL reader treats as keywords; names spelled like one are
+/// escaped with backticks. The `_micros` type keywords lex as identifiers but
+/// are read as logical types wherever a type is referenced.
+const KEYWORDS: &[&str] = &[
+    "protocol",
+    "namespace",
+    "import",
+    "idl",
+    "schema",
+    "enum",
+    "fixed",
+    "error",
+    "record",
+    "array",
+    "map",
+    "union",
+    "boolean",
+    "int",
+    "long",
+    "float",
+    "double",
+    "string",
+    "bytes",
+    "null",
+    "true",
+    "false",
+    "decimal",
+    "date",
+    "time_ms",
+    "timestamp_ms",
+    "local_timestamp_ms",
+    "uuid",
+    "time_micros",
+    "timestamp_micros",
+    "local_timestamp_micros",
+    "void",
+    "oneway",
+    "throws",
+];
+
+const INDENT: &str = "    ";
+
+impl Protocol {
+    /// Render this protocol as Avro IDL source.
+    ///
+    /// All of [`types`](Protocol::types) are declared in the protocol body,
+    /// followed by the messages in declaration order.
+    #[must_use]
+    pub fn to_idl_string(&self) -> String {
+        let namespace = self.namespace.as_deref();
+        let mut writer = IdlWriter::new(namespace, INDENT);
+
+        write_doc(&mut writer.out, self.doc.as_deref(), "");
+        if let Some(namespace) = namespace {
+            writer
+                .out
+                .push_str(&annotation("namespace", &namespace.into()));
+            writer.out.push(' ');
+        }
+        for annotation in annotations(&self.properties) {
+            writer.out.push_str(&annotation);
+            writer.out.push(' ');
+        }
+        let _ = writeln!(writer.out, "protocol {} {{", escape(&self.name));
+
+        for schema in &self.types {
+            writer.declare_all(schema);
+        }
+
+        for (name, message) in &self.messages {
+            writer.out.push('\n');
+            writer.message(name, message);
+        }
+        writer.out.push_str("}\n");
+        writer.out
+    }
+}
+
+impl AvroSchema {
+    /// Render this schema as an Avro IDL file in schema mode: a
+    /// `schema <type>;` declaration followed by the named types in `types`
+    /// (typically every type it references) and any named types nested
+    /// inline in it.
+    ///
+    /// The file's `namespace` is that of this schema if it is named.
+    #[must_use]
+    pub fn to_idl_string(&self, types: &[AvroSchema]) -> String {
+        let namespace = self.full_name().and_then(|full_name| {
+            full_name
+                .rsplit_once('.')
+                .map(|(namespace, _)| namespace.to_string())
+        });
+        let mut writer = IdlWriter::new(namespace.as_deref(), "");
+
+        if let Some(namespace) = &namespace {
+            let _ = writeln!(writer.out, "namespace {};", escape(namespace));
+        }
+        let main = writer.type_text(self, namespace.as_deref());
+        let _ = writeln!(writer.out, "schema {main};");
+
+        writer.declare_pending();
+        for schema in types {
+            writer.declare_all(schema);
+        }
+        writer.out
+    }
+}
+
+// ==============================================================================
+// Writer
+// ==============================================================================
+
+struct IdlWriter<'a> {
+    out: String,
+    /// The namespace of the protocol or file, which declarations inherit.
+    namespace: Option<&'a str>,
+    /// Indentation of top-level declarations.
+    indent: &'static str,
+    /// Full names of the named types declared so far.
+    declared: HashSet<String>,
+    /// Named types found inline while writing a declaration, to be declared
+    /// after it.
+    pending: Vec<&'a AvroSchema>,
+}
+
+impl<'a> IdlWriter<'a> {
+    fn new(namespace: Option<&'a str>, indent: &'static str) -> Self {
+        IdlWriter {
+            out: String::new(),
+            namespace,
+            indent,
+            declared: HashSet::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Declare a named type, then the named types nested inline in it.
+    fn declare_all(&mut self, schema: &'a AvroSchema) {
+        self.declare(schema);
+        self.declare_pending();
+    }
+
+    fn declare_pending(&mut self) {
+        for nested in std::mem::take(&mut self.pending) {
+            self.declare_all(nested);
+        }
+    }
+
+    /// Write the declaration of a named type, unless it was already written.
+    /// Anything that is not a named type declares nothing.
+    fn declare(&mut self, schema: &'a AvroSchema) {
+        let (name, namespace, doc, aliases, properties) = match schema {
+            AvroSchema::Record {
+                name,
+                namespace,
+                doc,
+                aliases,
+                properties,
+                ..
+            }
+            | AvroSchema::Enum {
+                name,
+                namespace,
+                doc,
+                aliases,
+                properties,
+                ..
+            }
+            | AvroSchema::Fixed {
+                name,
+                namespace,
+                doc,
+                aliases,
+                properties,
+                ..
+            } => (name, namespace.as_deref(), doc, aliases, properties),
+            _ => return,
+        };
+        if !self
+            .declared
+            .insert(make_full_name(name, namespace).into_owned())
+        {
+            return;
+        }
+
+        let indent = self.indent;
+        self.out.push('\n');
+        write_doc(&mut self.out, doc.as_deref(), indent);
+        let mut prefix = Vec::new();
+        if namespace.unwrap_or("") != self.namespace.unwrap_or("") {
+            prefix.push(annotation("namespace", &namespace.unwrap_or("").into()));
+        }
+        if !aliases.is_empty() {
+            prefix.push(annotation("aliases", &aliases.clone().into()));
+        }
+        prefix.extend(annotations(properties));
+        if !prefix.is_empty() {
+            let _ = writeln!(self.out, "{indent}{}", prefix.join(" "));
+        }
+
+        match schema {
+            AvroSchema::Record {
+                fields, is_error, ..
+            } => {
+                let keyword = if *is_error { "error" } else { "record" };
+                let _ = writeln!(self.out, "{indent}{keyword} {} {{", escape(name));
+                for field in fields {
+                    let field = self.variable(field, namespace);
+                    let _ = writeln!(self.out, "{indent}{INDENT}{field};");
+                }
+                let _ = writeln!(self.out, "{indent}}}");
+            }
+            AvroSchema::Enum {
+                symbols, default, ..
+            } => {
+                let symbols: Vec<_> = symbols.iter().map(|symbol| escape(symbol)).collect();
+                let _ = write!(
+                    self.out,
+                    "{indent}enum {} {{ {} }}",
+                    escape(name),
+                    symbols.join(", ")
+                );
+                if let Some(default) = default {
+                    let _ = write!(self.out, " = {}", escape(default));
+                }
+                self.out.push_str(";\n");
+            }
+            AvroSchema::Fixed { size, .. } => {
+                let _ = writeln!(self.out, "{indent}fixed {}({size});", escape(name));
+            }
+            _ => unreachable!("only named types reach here"),
+        }
+    }
+
+    /// A record field or message parameter, without the trailing `;`:
+    /// `/** doc */ type @order("descending") name = default`.
+    fn variable(&mut self, field: &'a Field, namespace: Option<&str>) -> String {
+        let mut text = String::new();
+        if let Some(doc) = &field.doc {
+            let _ = write!(text, "/** {} */ ", escape_doc(doc));
+        }
+        text.push_str(&self.type_text(&field.schema, namespace));
+        text.push(' ');
+        let order = field.order.as_ref().map(|order| match order {
+            FieldOrder::Ascending => "ascending",
+            FieldOrder::Descending => "descending",
+            FieldOrder::Ignore => "ignore",
+        });
+        if let Some(order) = order {
+            text.push_str(&annotation("order", &order.into()));
+            text.push(' ');
+        }
+        if !field.aliases.is_empty() {
+            text.push_str(&annotation("aliases", &field.aliases.clone().into()));
+            text.push(' ');
+        }
+        for annotation in annotations(&field.properties) {
+            text.push_str(&annotation);
+            text.push(' ');
+        }
+        text.push_str(&escape(&field.name));
+        if let Some(default) = &field.default {
+            let _ = write!(text, " = {default}");
+        }
+        text
+    }
+
+    fn message(&mut self, name: &str, message: &'a Message) {
+        let indent = self.indent;
+        let namespace = self.namespace;
+        write_doc(&mut self.out, message.doc.as_deref(), indent);
+        self.out.push_str(indent);
+        for annotation in annotations(&message.properties) {
+            self.out.push_str(&annotation);
+            self.out.push(' ');
+        }
+        let response = match &message.response {
+            AvroSchema::Null => "void".to_string(),
+            response => self.type_text(response, namespace),
+        };
+        let params: Vec<String> = message
+            .request
+            .iter()
+            .map(|param| self.variable(param, namespace))
+            .collect();
+        let _ = write!(
+            self.out,
+            "{response} {}({})",
+            escape(name),
+            params.join(", ")
+        );
+        if message.one_way {
+            self.out.push_str(" oneway");
+        } else if let Some(errors) = message.errors.as_ref().filter(|e| !e.is_empty()) {
+            let errors: Vec<String> = errors
+                .iter()
+                .map(|error| self.type_text(error, namespace))
+                .collect();
+            let _ = write!(self.out, " throws {}", errors.join(", "));
+        }
+        self.out.push_str(";\n");
+        self.declare_pending();
+    }
+
+    /// The IDL spelling of a type used in `namespace`. Named types nested
+    /// inline are referenced by name and queued for declaration.
+    fn type_text(&mut self, schema: &'a AvroSchema, namespace: Option<&str>) -> String {
+        match schema {
+            AvroSchema::Null => "null".to_string(),
+            AvroSchema::Boolean => "boolean".to_string(),
+            AvroSchema::Int => "int".to_string(),
+            AvroSchema::Long => "long".to_string(),
+            AvroSchema::Float => "float".to_string(),
+            AvroSchema::Double => "double".to_string(),
+            AvroSchema::Bytes => "bytes".to_string(),
+            AvroSchema::String => "string".to_string(),
+            AvroSchema::Record {
+                name,
+                namespace: type_namespace,
+                ..
+            }
+            | AvroSchema::Enum {
+                name,
+                namespace: type_namespace,
+                ..
+            }
+            | AvroSchema::Fixed {
+                name,
+                namespace: type_namespace,
+                ..
+            } => {
+                self.pending.push(schema);
+                reference(name, type_namespace.as_deref(), namespace)
+            }
+            AvroSchema::Reference {
+                name,
+                namespace: type_namespace,
+                ..
+            } => reference(name, type_namespace.as_deref(), namespace),
+            AvroSchema::Array { items, properties } => {
+                let items = self.type_text(items, namespace);
+                annotated(properties, format!("array<{items}>"))
+            }
+            AvroSchema::Map { values, properties } => {
+                let values = self.type_text(values, namespace);
+                annotated(properties, format!("map<{values}>"))
+            }
+            AvroSchema::Union { types, .. } => {
+                let branches: Vec<String> = types
+                    .iter()
+                    .map(|branch| self.type_text(branch, namespace))
+                    .collect();
+                format!("union {{ {} }}", branches.join(", "))
+            }
+            AvroSchema::AnnotatedPrimitive { kind, properties } => {
+                annotated(properties, kind.as_str().to_string())
+            }
+            AvroSchema::Logical {
+                logical_type,
+                properties,
+            } => {
+                let keyword = match logical_type {
+                    LogicalType::Date => "date".to_string(),
+                    LogicalType::TimeMillis => "time_ms".to_string(),
+                    LogicalType::TimeMicros => "time_micros".to_string(),
+                    LogicalType::TimestampMillis => "timestamp_ms".to_string(),
+                    LogicalType::TimestampMicros => "timestamp_micros".to_string(),
+                    LogicalType::LocalTimestampMillis => "local_timestamp_ms".to_string(),
+                    LogicalType::LocalTimestampMicros => "local_timestamp_micros".to_string(),
+                    LogicalType::Uuid => "uuid".to_string(),
+                    LogicalType::Decimal { precision, scale } => {
+                        format!("decimal({precision}, {scale})")
+                    }
+                };
+                annotated(properties, keyword)
+            }
+        }
+    }
+}
+
+// ==============================================================================
+// Helpers
+// ==============================================================================
+
+/// A reference to a named type, short when it is in the namespace it is
+/// resolved in.
+fn reference(name: &str, type_namespace: Option<&str>, namespace: Option<&str>) -> String {
+    if type_namespace.unwrap_or("") == namespace.unwrap_or("") {
+        escape(name)
+    } else {
+        escape(&make_full_name(name, type_namespace))
+    }
+}
+
+/// Escape each dot-separated part of a name that is spelled like a keyword.
+fn escape(name: &str) -> String {
+    name.split('.')
+        .map(|part| {
+            if KEYWORDS.contains(&part) {
+                format!("`{part}`")
+            } else {
+                part.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn annotation(key: &str, value: &Value) -> String {
+    format!("@{key}({value})")
+}
+
+/// Annotations for `properties`, in declaration order.
+fn annotations(properties: &Properties) -> Vec<String> {
+    properties
+        .iter()
+        .map(|(key, value)| annotation(key, value))
+        .collect()
+}
+
+/// A type prefixed with the annotations for its properties.
+fn annotated(properties: &Properties, text: String) -> String {
+    let mut annotations = annotations(properties);
+    annotations.push(text);
+    annotations.join(" ")
+}
+
+/// Write a doc comment on its own line(s), if there is one.
+fn write_doc(out: &mut String, doc: Option<&str>, indent: &str) {
+    let Some(doc) = doc else {
+        return;
+    };
+    let doc = escape_doc(doc);
+    if doc.contains('\n') {
+        let _ = writeln!(out, "{indent}/**");
+        for line in doc.lines() {
+            let _ = writeln!(out, "{indent} * {line}");
+        }
+        let _ = writeln!(out, "{indent} */");
+    } else {
+        let _ = writeln!(out, "{indent}/** {doc} */");
+    }
+}
+
+/// A doc comment cannot contain `*/`, which would end it early.
+fn escape_doc(doc: &str) -> String {
+    doc.replace("*/", "*\\/")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Idl;
+    use pretty_assertions::assert_eq;
+
+    /// Compile `source`, render it back to IDL, and check that the rendered
+    /// IDL compiles to the same JSON.
+    fn assert_round_trips(source: &'static str) -> String {
+        let output = Idl::new().convert_str(source).expect("IDL should compile");
+        let rendered = output.to_idl_string();
+        let recompiled = Idl::new()
+            .convert_string(rendered.clone())
+            .unwrap_or_else(|e| panic!("rendered IDL should compile: {e:?}\n{rendered}"));
+        assert_eq!(recompiled.json, output.json, "rendered IDL:\n{rendered}");
+        rendered
+    }
+
+    #[test]
+    fn protocol_round_trips() {
+        let rendered = assert_round_trips(
+            r#"/** A shop. */
+            @namespace("ex") @version(2) protocol Shop {
+                /**
+                 * Where an order is.
+                 * Always set.
+                 */
+                @aliases(["State"]) enum Status { NEW, `error` } = NEW;
+                @namespace("ex.money") fixed Cents(8);
+                @since(3) @deprecated(true) record Order {
+                    /** The id. */ string @order("descending") id;
+                    array<Status> history = [];
+                    union { null, ex.money.Cents } total = null;
+                    @logicalType("decimal") @precision(9) @scale(2) bytes amount;
+                    timestamp_ms placed;
+                    map<@java-class("java.lang.Long") long> counts = {};
+                }
+                error NotFound { string message; }
+                Order get(string id, int limit = 10) throws NotFound;
+                void ping() oneway;
+            }"#,
+        );
+        assert!(rendered.contains("enum Status { NEW, `error` } = NEW;"));
+        assert!(rendered.contains("@since(3) @deprecated(true)\n"));
+        assert!(rendered.contains("union { null, ex.money.Cents } total = null;"));
+    }
+
+    #[test]
+    fn messages_keep_declaration_order() {
+        let rendered = assert_round_trips("protocol P { void zeta(); void alpha(); }");
+        assert!(rendered.find("zeta") < rendered.find("alpha"), "{rendered}");
+    }
+
+    #[test]
+    fn micros_type_names_round_trip() {
+        let rendered = assert_round_trips(
+            "protocol P { fixed time_micros(8); record R { `time_micros` a; time_micros b; } }",
+        );
+        assert!(rendered.contains("`time_micros` a;"), "{rendered}");
+        assert!(rendered.contains("time_micros b;"), "{rendered}");
+    }
+
+    #[test]
+    fn schema_mode_round_trips() {
+        assert_round_trips(
+            r#"namespace ex;
+            schema Order;
+            record Order { Line line; decimal(9, 2) total; }
+            record Line { string sku; int quantity = 1; }"#,
+        );
+    }
+}
@@ -9,7 +9,9 @@
 //   then as bare string names afterward.
 // - Primitives serialize as plain strings: "null", "int", etc.
 // - Unions serialize as JSON arrays: ["null", "string"].
-// - JSON object keys are alphabetically sorted (BTreeMap-backed serde_json::Map).
+// - JSON object keys are alphabetically sorted (BTreeMap-backed serde_json::Map),
+//   except a protocol's messages, which `OrderedJson` writes in declaration
+//   order.
 //
 // References (`AvroSchema::Reference`) are resolved against a lookup table so
 // they can be inlined at their first use, just as the Java tools do. This is
@@ -17,13 +19,15 @@
 // after the record that uses it -- the expected JSON inlines the enum inside
 // the record's field.
 
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 
+use serde::ser::{Serialize, SerializeMap, Serializer};
 use serde_json::{Map, Value};
 
 use super::protocol::{Message, Protocol};
 use super::schema::{
-    AvroSchema, Field, FieldOrder, LogicalType, PRIMITIVE_TYPE_NAMES, make_full_name,
+    AvroSchema, Field, FieldOrder, LogicalType, PRIMITIVE_TYPE_NAMES, Properties, make_full_name,
     split_full_name,
 };
 
@@ -48,10 +52,22 @@ fn is_schema_type_name(name: &str) -> bool {
 
 /// A lookup table from full type name to the actual schema definition. This
 /// allows `Reference` nodes to be resolved and inlined at their first use.
-pub type SchemaLookup = HashMap<String, AvroSchema>;
+/// It borrows the definitions, so building one does not copy every schema.
+pub type SchemaLookup<'a> = HashMap<String, &'a AvroSchema>;
 
 /// Serialize a `Protocol` to a `serde_json::Value` matching the Java Avro tools output.
 pub fn protocol_to_json(protocol: &Protocol) -> Value {
+    protocol_json(protocol, false)
+}
+
+/// Serialize a `Protocol` like [`protocol_to_json`], but define each named
+/// type only in the `types` array and refer to it by name everywhere else,
+/// even ahead of its definition.
+pub fn protocol_to_json_by_name(protocol: &Protocol) -> Value {
+    protocol_json(protocol, true)
+}
+
+fn protocol_json(protocol: &Protocol, references_by_name: bool) -> Value {
     // Build a lookup table from all named types in the protocol's type list.
     // This includes nested types inside records/fields that were registered
     // in the schema registry.
@@ -79,10 +95,30 @@ pub fn protocol_to_json(protocol: &Protocol) -> Value {
     // Reference resolution (e.g., a forward reference inside a record field),
     // `schema_to_json` returns a bare string. The Java tools omit such
     // already-inlined types from the top-level array, so we filter them out.
+    //
+    // By name, every other type counts as already serialized, so nothing is
+    // inlined. Once all types are done, every name is known.
+    let all_names: HashSet<String> = if references_by_name {
+        protocol
+            .types
+            .iter()
+            .filter_map(|s| s.full_name().map(Cow::into_owned))
+            .collect()
+    } else {
+        HashSet::new()
+    };
     let types: Vec<Value> = protocol
         .types
         .iter()
-        .map(|s| schema_to_json(s, &mut known_names, protocol.namespace.as_deref(), &lookup))
+        .map(|s| {
+            if references_by_name {
+                known_names = all_names.clone();
+                if let Some(name) = s.full_name() {
+                    known_names.remove(name.as_ref());
+                }
+            }
+            schema_to_json(s, &mut known_names, protocol.namespace.as_deref(), &lookup)
+        })
         .filter(|v| !v.is_string())
         .collect();
     obj.insert("types".to_string(), Value::Array(types));
@@ -104,6 +140,70 @@ pub fn protocol_to_json(protocol: &Protocol) -> Value {
     Value::Object(obj)
 }
 
+/// A protocol's JSON that serializes its `messages` object in a given order.
+///
+/// A `serde_json::Map` keeps its keys sorted, so the order messages were
+/// declared in is lost once they are in a `Value`. Serializing through this
+/// writes them back out in `message_order`, followed by any message it does
+/// not name, by name. Everything else serializes as the `Value` itself does,
+/// and a value without a `messages` object is serialized unchanged.
+#[derive(Debug, Clone, Copy)]
+pub struct OrderedJson<'a> {
+    json: &'a Value,
+    message_order: &'a [String],
+}
+
+impl<'a> OrderedJson<'a> {
+    pub(crate) fn new(json: &'a Value, message_order: &'a [String]) -> Self {
+        OrderedJson {
+            json,
+            message_order,
+        }
+    }
+}
+
+impl Serialize for OrderedJson<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Value::Object(obj) = self.json else {
+            return self.json.serialize(serializer);
+        };
+        let mut map = serializer.serialize_map(Some(obj.len()))?;
+        for (key, value) in obj {
+            match value {
+                Value::Object(messages) if key == "messages" => {
+                    map.serialize_entry(key, &OrderedMessages(messages, self.message_order))?;
+                }
+                _ => map.serialize_entry(key, value)?,
+            }
+        }
+        map.end()
+    }
+}
+
+/// The `messages` object of an [`OrderedJson`], and the order to write it in.
+struct OrderedMessages<'a>(&'a Map<String, Value>, &'a [String]);
+
+impl Serialize for OrderedMessages<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let OrderedMessages(messages, order) = *self;
+        let mut map = serializer.serialize_map(Some(messages.len()))?;
+        let mut written = HashSet::with_capacity(order.len());
+        for name in order {
+            if let Some(message) = messages.get(name)
+                && written.insert(name.as_str())
+            {
+                map.serialize_entry(name, message)?;
+            }
+        }
+        for (name, message) in messages {
+            if !written.contains(name.as_str()) {
+                map.serialize_entry(name, message)?;
+            }
+        }
+        map.end()
+    }
+}
+
 /// Build a lookup table of `full_name` -> `AvroSchema` for all named types,
 /// recursively collecting types nested inside records, unions, arrays, etc.
 ///
@@ -113,7 +213,10 @@ pub fn protocol_to_json(protocol: &Protocol) -> Value {
 ///
 /// This is public so that schema-mode callers (which don't go through
 /// `protocol_to_json`) can build a lookup from registry schemas.
-pub fn build_lookup(types: &[AvroSchema], default_namespace: Option<&str>) -> SchemaLookup {
+pub fn build_lookup<'a>(
+    types: impl IntoIterator<Item = &'a AvroSchema>,
+    default_namespace: Option<&str>,
+) -> SchemaLookup<'a> {
     let mut lookup = HashMap::new();
     for schema in types {
         collect_named_types(schema, default_namespace, &mut lookup);
@@ -122,10 +225,10 @@ pub fn build_lookup(types: &[AvroSchema], default_namespace: Option<&str>) -> Sc
 }
 
 /// Recursively collect named types from a schema tree into the lookup.
-fn collect_named_types(
-    schema: &AvroSchema,
+fn collect_named_types<'a>(
+    schema: &'a AvroSchema,
     default_namespace: Option<&str>,
-    lookup: &mut SchemaLookup,
+    lookup: &mut SchemaLookup<'a>,
 ) {
     match schema {
         AvroSchema::Record {
@@ -136,7 +239,7 @@ fn collect_named_types(
         } => {
             let effective_ns = namespace.as_deref().or(default_namespace);
             let full_name = make_full_name(name, effective_ns).into_owned();
-            lookup.insert(full_name, schema.clone());
+            lookup.insert(full_name, schema);
             // Nested types inside a record's fields inherit the record's
             // effective namespace (not the protocol-level default), per the
             // Avro specification.
@@ -152,7 +255,7 @@ fn collect_named_types(
         } => {
             let effective_ns = namespace.as_deref().or(default_namespace);
             let full_name = make_full_name(name, effective_ns).into_owned();
-            lookup.insert(full_name, schema.clone());
+            lookup.insert(full_name, schema);
         }
         AvroSchema::Array { items, .. } => {
             collect_named_types(items, default_namespace, lookup);
@@ -230,7 +333,7 @@ fn named_type_preamble(
 /// aliases. Called after the caller has inserted all type-specific keys.
 fn finish_named_type(
     obj: &mut Map<String, Value>,
-    properties: &HashMap<String, Value>,
+    properties: &Properties,
     aliases: &[String],
     namespace: Option<&str>,
 ) {
@@ -256,7 +359,7 @@ pub fn schema_to_json(
     schema: &AvroSchema,
     known_names: &mut HashSet<String>,
     enclosing_namespace: Option<&str>,
-    lookup: &SchemaLookup,
+    lookup: &SchemaLookup<'_>,
 ) -> Value {
     // Primitives: serialize as plain strings.
     if let Some(name) = schema.primitive_type_name() {
@@ -488,7 +591,7 @@ fn field_to_json(
     field: &Field,
     known_names: &mut HashSet<String>,
     enclosing_namespace: Option<&str>,
-    lookup: &SchemaLookup,
+    lookup: &SchemaLookup<'_>,
 ) -> Value {
     let mut obj = Map::new();
     obj.insert("name".to_string(), Value::String(field.name.clone()));
@@ -534,7 +637,7 @@ fn message_to_json(
     msg: &Message,
     known_names: &mut HashSet<String>,
     enclosing_namespace: Option<&str>,
-    lookup: &SchemaLookup,
+    lookup: &SchemaLookup<'_>,
 ) -> Value {
     let mut obj = Map::new();
     if let Some(doc) = &msg.doc {
@@ -609,6 +712,7 @@ fn alias_ref_name(alias: &str, schema_namespace: Option<&str>) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use indexmap::IndexMap;
     use pretty_assertions::assert_eq;
     use serde_json::json;
 
@@ -623,12 +727,12 @@ mod tests {
     }
 
     /// Serialize a schema with the given known names and lookup, returning the
-    /// updated known_names set for subsequent assertions.
+    /// updated `known_names` set for subsequent assertions.
     fn serialize_schema_tracking(
         schema: &AvroSchema,
         known_names: &mut HashSet<String>,
         enclosing_ns: Option<&str>,
-        lookup: &SchemaLookup,
+        lookup: &SchemaLookup<'_>,
     ) -> Value {
         schema_to_json(schema, known_names, enclosing_ns, lookup)
     }
@@ -655,7 +759,7 @@ mod tests {
 
     #[test]
     fn annotated_primitive_serializes_as_object() {
-        let mut props = HashMap::new();
+        let mut props = Properties::new();
         props.insert("foo.bar".to_string(), json!("baz"));
 
         let schema = AvroSchema::AnnotatedPrimitive {
@@ -686,7 +790,7 @@ mod tests {
             ],
             is_error: false,
             aliases: vec![],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
 
         let result = serialize_schema(&schema);
@@ -714,7 +818,7 @@ mod tests {
             fields: vec![Field::simple("message", AvroSchema::String)],
             is_error: true,
             aliases: vec![],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
 
         let result = serialize_schema(&schema);
@@ -724,7 +828,7 @@ mod tests {
 
     #[test]
     fn record_with_aliases_and_properties() {
-        let mut props = HashMap::new();
+        let mut props = Properties::new();
         props.insert("my-prop".to_string(), json!({"key": 42}));
 
         let schema = AvroSchema::Record {
@@ -783,7 +887,7 @@ mod tests {
             symbols: vec!["A".to_string(), "B".to_string(), "C".to_string()],
             default: Some("C".to_string()),
             aliases: vec![],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
 
         let result = serialize_schema(&schema);
@@ -821,7 +925,7 @@ mod tests {
             doc: Some("An MD5 hash.".to_string()),
             size: 16,
             aliases: vec![],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
 
         let result = serialize_schema(&schema);
@@ -844,7 +948,7 @@ mod tests {
     fn array_serializes_correctly() {
         let schema = AvroSchema::Array {
             items: Box::new(AvroSchema::String),
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
 
         let result = serialize_schema(&schema);
@@ -853,7 +957,7 @@ mod tests {
 
     #[test]
     fn array_with_properties() {
-        let mut props = HashMap::new();
+        let mut props = Properties::new();
         props.insert("foo.bar".to_string(), json!("baz"));
 
         let schema = AvroSchema::Array {
@@ -876,7 +980,7 @@ mod tests {
     fn map_serializes_correctly() {
         let schema = AvroSchema::Map {
             values: Box::new(AvroSchema::Int),
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
 
         let result = serialize_schema(&schema);
@@ -906,7 +1010,7 @@ mod tests {
     fn logical_type_date() {
         let schema = AvroSchema::Logical {
             logical_type: LogicalType::Date,
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         assert_eq!(
             serialize_schema(&schema),
@@ -918,7 +1022,7 @@ mod tests {
     fn logical_type_time_millis() {
         let schema = AvroSchema::Logical {
             logical_type: LogicalType::TimeMillis,
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         assert_eq!(
             serialize_schema(&schema),
@@ -930,7 +1034,7 @@ mod tests {
     fn logical_type_time_micros() {
         let schema = AvroSchema::Logical {
             logical_type: LogicalType::TimeMicros,
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         assert_eq!(
             serialize_schema(&schema),
@@ -942,7 +1046,7 @@ mod tests {
     fn logical_type_timestamp_millis() {
         let schema = AvroSchema::Logical {
             logical_type: LogicalType::TimestampMillis,
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         assert_eq!(
             serialize_schema(&schema),
@@ -954,7 +1058,7 @@ mod tests {
     fn logical_type_timestamp_micros() {
         let schema = AvroSchema::Logical {
             logical_type: LogicalType::TimestampMicros,
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         assert_eq!(
             serialize_schema(&schema),
@@ -966,7 +1070,7 @@ mod tests {
     fn logical_type_local_timestamp_millis() {
         let schema = AvroSchema::Logical {
             logical_type: LogicalType::LocalTimestampMillis,
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         assert_eq!(
             serialize_schema(&schema),
@@ -978,7 +1082,7 @@ mod tests {
     fn logical_type_local_timestamp_micros() {
         let schema = AvroSchema::Logical {
             logical_type: LogicalType::LocalTimestampMicros,
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         assert_eq!(
             serialize_schema(&schema),
@@ -990,7 +1094,7 @@ mod tests {
     fn logical_type_uuid() {
         let schema = AvroSchema::Logical {
             logical_type: LogicalType::Uuid,
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         assert_eq!(
             serialize_schema(&schema),
@@ -1005,7 +1109,7 @@ mod tests {
                 precision: 6,
                 scale: 2,
             },
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         assert_eq!(
             serialize_schema(&schema),
@@ -1028,12 +1132,12 @@ mod tests {
         );
 
         let mut lookup = HashMap::new();
-        lookup.insert("org.example.Ping".to_string(), record);
+        lookup.insert("org.example.Ping".to_string(), &record);
 
         let reference = AvroSchema::Reference {
             name: "Ping".to_string(),
             namespace: Some("org.example".to_string()),
-            properties: HashMap::new(),
+            properties: Properties::new(),
             span: None,
         };
 
@@ -1054,12 +1158,12 @@ mod tests {
         let record = AvroSchema::simple_record("Ping", Some("org.example"), vec![]);
 
         let mut lookup = HashMap::new();
-        lookup.insert("org.example.Ping".to_string(), record);
+        lookup.insert("org.example.Ping".to_string(), &record);
 
         let reference = AvroSchema::Reference {
             name: "Ping".to_string(),
             namespace: Some("org.example".to_string()),
-            properties: HashMap::new(),
+            properties: Properties::new(),
             span: None,
         };
 
@@ -1076,12 +1180,12 @@ mod tests {
         let record = AvroSchema::simple_record("Ping", Some("org.example"), vec![]);
 
         let mut lookup = HashMap::new();
-        lookup.insert("org.example.Ping".to_string(), record);
+        lookup.insert("org.example.Ping".to_string(), &record);
 
         let reference = AvroSchema::Reference {
             name: "Ping".to_string(),
             namespace: Some("org.example".to_string()),
-            properties: HashMap::new(),
+            properties: Properties::new(),
             span: None,
         };
 
@@ -1283,7 +1387,7 @@ mod tests {
                 "other.DiffNs".to_string(),
                 "NoNs".to_string(),
             ],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
 
         let result = schema_to_json(
@@ -1307,7 +1411,7 @@ mod tests {
                 "test.aliases.OldEnum".to_string(),
                 "other.ns.ForeignEnum".to_string(),
             ],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
 
         let result = schema_to_json(
@@ -1330,7 +1434,7 @@ mod tests {
             doc: None,
             size: 16,
             aliases: vec!["test.aliases.OldFixed".to_string()],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
 
         let result = schema_to_json(
@@ -1355,7 +1459,7 @@ mod tests {
                 "test.kw.record".to_string(),
                 "test.kw.NormalAlias".to_string(),
             ],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
 
         let result = schema_to_json(
@@ -1382,12 +1486,12 @@ mod tests {
         );
 
         let mut lookup = HashMap::new();
-        lookup.insert("test.kw.record".to_string(), record.clone());
+        lookup.insert("test.kw.record".to_string(), &record);
 
         let reference = AvroSchema::Reference {
             name: "record".to_string(),
             namespace: Some("test.kw".to_string()),
-            properties: HashMap::new(),
+            properties: Properties::new(),
             span: None,
         };
 
@@ -1446,7 +1550,7 @@ mod tests {
 
     #[test]
     fn field_with_aliases_and_properties() {
-        let mut props = HashMap::new();
+        let mut props = Properties::new();
         props.insert("custom-prop".to_string(), json!(true));
 
         let field = Field {
@@ -1474,7 +1578,7 @@ mod tests {
             name: "Echo".to_string(),
             namespace: Some("org.example".to_string()),
             doc: None,
-            properties: HashMap::new(),
+            properties: Properties::new(),
             types: vec![AvroSchema::simple_record(
                 "Ping",
                 Some("org.example"),
@@ -1483,7 +1587,7 @@ mod tests {
                     ..Field::simple("ts", AvroSchema::Long)
                 }],
             )],
-            messages: HashMap::new(),
+            messages: IndexMap::new(),
         };
 
         let result = protocol_to_json(&protocol);
@@ -1505,9 +1609,9 @@ mod tests {
             name: "Simple".to_string(),
             namespace: Some(String::new()),
             doc: None,
-            properties: HashMap::new(),
+            properties: Properties::new(),
             types: vec![],
-            messages: HashMap::new(),
+            messages: IndexMap::new(),
         };
 
         let result = protocol_to_json(&protocol);
@@ -1520,7 +1624,7 @@ mod tests {
 
     #[test]
     fn protocol_with_doc_and_properties() {
-        let mut props = HashMap::new();
+        let mut props = Properties::new();
         props.insert("version".to_string(), json!("1.0"));
 
         let protocol = Protocol {
@@ -1529,7 +1633,7 @@ mod tests {
             doc: Some("A greeter protocol.".to_string()),
             properties: props,
             types: vec![],
-            messages: HashMap::new(),
+            messages: IndexMap::new(),
         };
 
         let result = protocol_to_json(&protocol);
@@ -1544,15 +1648,15 @@ mod tests {
             name: "Svc".to_string(),
             namespace: Some("org.example".to_string()),
             doc: None,
-            properties: HashMap::new(),
+            properties: Properties::new(),
             types: vec![],
             messages: {
-                let mut msgs = HashMap::new();
+                let mut msgs = IndexMap::new();
                 msgs.insert(
                     "hello".to_string(),
                     Message {
                         doc: Some("Say hello.".to_string()),
-                        properties: HashMap::new(),
+                        properties: Properties::new(),
                         request: vec![Field::simple("greeting", AvroSchema::String)],
                         response: AvroSchema::String,
                         errors: None,
@@ -1563,7 +1667,7 @@ mod tests {
                     "ping".to_string(),
                     Message {
                         doc: None,
-                        properties: HashMap::new(),
+                        properties: Properties::new(),
                         request: vec![],
                         response: AvroSchema::Null,
                         errors: None,
@@ -1610,7 +1714,8 @@ mod tests {
             vec![Field::simple("status", status_enum)],
         );
 
-        let lookup = build_lookup(&[record], Some("org.example"));
+        let types = [record];
+        let lookup = build_lookup(&types, Some("org.example"));
         assert!(lookup.contains_key("org.example.Rec"));
         assert!(lookup.contains_key("org.example.Status"));
     }
@@ -1620,7 +1725,8 @@ mod tests {
         // A record with no explicit namespace should inherit the default.
         let record = AvroSchema::simple_record("Rec", None, vec![]);
 
-        let lookup = build_lookup(&[record], Some("org.default"));
+        let types = [record];
+        let lookup = build_lookup(&types, Some("org.default"));
         assert!(lookup.contains_key("org.default.Rec"));
     }
 
@@ -1659,4 +1765,15 @@ mod tests {
         let second = serialize_schema_tracking(&schema, &mut known, Some("org.palette"), &lookup);
         assert_eq!(second, json!("Color"));
     }
+
+    #[test]
+    fn ordered_json_writes_each_message_once() {
+        let json = json!({"protocol": "P", "messages": {"a": {}, "b": {}, "c": {}}});
+        let order = ["b", "a", "b", "gone"].map(String::from);
+        let written = serde_json::to_string(&OrderedJson::new(&json, &order)).expect("serialize");
+        assert_eq!(
+            written,
+            r#"{"messages":{"b":{},"a":{},"c":{}},"protocol":"P"}"#
+        );
+    }
 }
@@ -1,3 +1,6 @@
+pub mod fingerprint;
+pub mod idl;
 pub mod json;
 pub mod protocol;
 pub mod schema;
+pub mod transform;
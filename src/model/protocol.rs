@@ -1,7 +1,6 @@
-use serde_json::Value;
-use std::collections::HashMap;
+use indexmap::IndexMap;
 
-use super::schema::{AvroSchema, Field};
+use super::schema::{AvroSchema, Field, Properties};
 
 /// An Avro protocol.
 #[derive(Debug, Clone, PartialEq)]
@@ -9,16 +8,18 @@ pub struct Protocol {
     pub name: String,
     pub namespace: Option<String>,
     pub doc: Option<String>,
-    pub properties: HashMap<String, Value>,
+    pub properties: Properties,
     pub types: Vec<AvroSchema>,
-    pub messages: HashMap<String, Message>,
+    /// Messages by name, in declaration order, after those of imported
+    /// protocols.
+    pub messages: IndexMap<String, Message>,
 }
 
 /// An Avro protocol message (RPC method).
 #[derive(Debug, Clone, PartialEq)]
 pub struct Message {
     pub doc: Option<String>,
-    pub properties: HashMap<String, Value>,
+    pub properties: Properties,
     pub request: Vec<Field>,
     pub response: AvroSchema,
     pub errors: Option<Vec<AvroSchema>>,
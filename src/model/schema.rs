@@ -1,11 +1,16 @@
+use indexmap::IndexMap;
 use serde_json::Value;
 use std::borrow::Cow;
-use std::collections::HashMap;
 use std::fmt;
 use std::str::FromStr;
+use std::sync::Arc;
 
 use crate::error::SpanWithSource;
 
+/// Custom properties (annotations) on a schema, field, message, or
+/// protocol, in the order they were written.
+pub type Properties = IndexMap<String, Value>;
+
 /// The eight Avro primitive type names.
 ///
 /// Both `is_invalid_type_name` in `reader.rs` and `is_schema_type_name` in
@@ -66,6 +71,7 @@ pub enum PrimitiveType {
 
 impl PrimitiveType {
     /// Return the Avro type name string for this primitive.
+    #[must_use]
     pub fn as_str(&self) -> &'static str {
         match self {
             PrimitiveType::Null => "null",
@@ -80,6 +86,7 @@ impl PrimitiveType {
     }
 
     /// Convert this primitive type to its corresponding `AvroSchema` variant.
+    #[must_use]
     pub fn to_schema(&self) -> AvroSchema {
         match self {
             PrimitiveType::Null => AvroSchema::Null,
@@ -245,6 +252,8 @@ pub(crate) fn parse_logical_type(
 /// fixed-based logical types:
 ///
 /// - `"duration"` requires exactly `fixed(12)`
+/// - `"uuid"` requires exactly `fixed(16)` (Avro 1.11.3+; `uuid` on `string`
+///   is handled by `parse_logical_type`)
 /// - `"decimal"` is valid on `fixed(N)` if the precision fits within `N` bytes
 ///   (max precision = floor(log10(2^(8*N - 1))))
 ///
@@ -258,6 +267,7 @@ pub(crate) fn validate_logical_type_on_fixed(
 ) -> bool {
     match logical_name {
         "duration" => size == 12,
+        "uuid" => size == 16,
         "decimal" => {
             let Some(precision) = precision else {
                 return false;
@@ -267,16 +277,19 @@ pub(crate) fn validate_logical_type_on_fixed(
             if precision < 1 || scale > precision {
                 return false;
             }
-            // Maximum precision for a fixed(N) is floor(log10(2^(8*N-1))).
-            // This matches Java's Decimal.maxPrecision(Schema).
-            let max_precision =
-                ((8.0 * size as f64 - 1.0) * std::f64::consts::LOG10_2).floor() as u32;
-            precision <= max_precision
+            precision <= max_decimal_precision(size)
         }
         _ => false,
     }
 }
 
+/// The largest `decimal` precision that a `fixed(size)` can hold:
+/// floor(log10(2^(8*size - 1))). This matches Java's
+/// `Decimal.maxPrecision(Schema)`.
+pub(crate) fn max_decimal_precision(size: u32) -> u32 {
+    ((8.0 * size as f64 - 1.0) * std::f64::consts::LOG10_2).floor() as u32
+}
+
 /// An Avro schema.
 ///
 /// We use our own domain model rather than depending on the `apache-avro` crate,
@@ -306,7 +319,7 @@ pub enum AvroSchema {
         fields: Vec<Field>,
         is_error: bool,
         aliases: Vec<std::string::String>,
-        properties: HashMap<std::string::String, Value>,
+        properties: Properties,
     },
     Enum {
         name: std::string::String,
@@ -315,7 +328,7 @@ pub enum AvroSchema {
         symbols: Vec<std::string::String>,
         default: Option<std::string::String>,
         aliases: Vec<std::string::String>,
-        properties: HashMap<std::string::String, Value>,
+        properties: Properties,
     },
     Fixed {
         name: std::string::String,
@@ -323,7 +336,7 @@ pub enum AvroSchema {
         doc: Option<std::string::String>,
         size: u32,
         aliases: Vec<std::string::String>,
-        properties: HashMap<std::string::String, Value>,
+        properties: Properties,
     },
 
     // =========================================================================
@@ -331,11 +344,11 @@ pub enum AvroSchema {
     // =========================================================================
     Array {
         items: Box<AvroSchema>,
-        properties: HashMap<std::string::String, Value>,
+        properties: Properties,
     },
     Map {
         values: Box<AvroSchema>,
-        properties: HashMap<std::string::String, Value>,
+        properties: Properties,
     },
     Union {
         types: Vec<AvroSchema>,
@@ -352,7 +365,7 @@ pub enum AvroSchema {
     /// instead of the bare string `"long"`.
     AnnotatedPrimitive {
         kind: PrimitiveType,
-        properties: HashMap<std::string::String, Value>,
+        properties: Properties,
     },
 
     // =========================================================================
@@ -361,7 +374,7 @@ pub enum AvroSchema {
     Logical {
         logical_type: LogicalType,
         /// Extra properties on the underlying primitive (e.g., `@foo.bar("baz")` on a `long`).
-        properties: HashMap<std::string::String, Value>,
+        properties: Properties,
     },
 
     // =========================================================================
@@ -370,7 +383,7 @@ pub enum AvroSchema {
     Reference {
         name: std::string::String,
         namespace: Option<std::string::String>,
-        properties: HashMap<std::string::String, Value>,
+        properties: Properties,
         /// Source location and file of this reference in the `.avdl` input,
         /// used for error diagnostics when the reference cannot be resolved.
         /// `None` for references created from JSON imports.
@@ -387,13 +400,14 @@ pub struct Field {
     pub default: Option<Value>,
     pub order: Option<FieldOrder>,
     pub aliases: Vec<std::string::String>,
-    pub properties: HashMap<std::string::String, Value>,
+    pub properties: Properties,
 }
 
 impl AvroSchema {
     /// If this is a bare primitive variant (`Null` through `String`), return
     /// the corresponding `PrimitiveType`. Returns `None` for all other variants
     /// (including `AnnotatedPrimitive`).
+    #[must_use]
     pub fn to_primitive_type(&self) -> Option<PrimitiveType> {
         match self {
             AvroSchema::Null => Some(PrimitiveType::Null),
@@ -410,6 +424,7 @@ impl AvroSchema {
 
     /// If this is a primitive variant (`Null` through `String`), return its
     /// Avro type name. Returns `None` for all non-primitive variants.
+    #[must_use]
     pub fn primitive_type_name(&self) -> Option<&'static str> {
         match self {
             AvroSchema::Null => Some("null"),
@@ -428,6 +443,7 @@ impl AvroSchema {
     ///
     /// Returns `Cow::Borrowed` when there is no namespace (avoiding allocation),
     /// and `Cow::Owned` when a namespace prefix must be prepended.
+    #[must_use]
     pub fn full_name(&self) -> Option<Cow<'_, str>> {
         match self {
             AvroSchema::Record {
@@ -447,6 +463,7 @@ impl AvroSchema {
     }
 
     /// Returns the simple name of a named type, or `None` if not a named type.
+    #[must_use]
     pub fn name(&self) -> Option<&str> {
         match self {
             AvroSchema::Record { name, .. }
@@ -466,6 +483,7 @@ impl AvroSchema {
     ///
     /// This mirrors Java's `Schema.getFullName()` behavior used in
     /// `UnionSchema`'s constructor for duplicate checking.
+    #[must_use]
     pub fn union_type_key(&self) -> String {
         // Primitives: keyed by their type name.
         if let Some(name) = self.primitive_type_name() {
@@ -502,7 +520,55 @@ impl AvroSchema {
         }
     }
 
+    // -- Union introspection --
+
+    /// Whether this is a union written with the `T?` shorthand, as opposed to
+    /// an explicit `union { ... }`. The two serialize identically.
+    #[must_use]
+    pub fn is_nullable_type(&self) -> bool {
+        matches!(
+            self,
+            AvroSchema::Union {
+                is_nullable_type: true,
+                ..
+            }
+        )
+    }
+
+    /// Whether this is a union with a `null` branch, however it was written.
+    #[must_use]
+    pub fn is_nullable(&self) -> bool {
+        self.union_contains("null")
+    }
+
+    /// The branches of a union other than `null`, in declaration order. Empty
+    /// for schemas that are not unions.
+    ///
+    /// An "optional `T`" field is a nullable union with exactly one such
+    /// branch.
+    pub fn non_null_branches(&self) -> impl Iterator<Item = &AvroSchema> {
+        let types = match self {
+            AvroSchema::Union { types, .. } => types.as_slice(),
+            _ => &[],
+        };
+        types.iter().filter(|t| !matches!(t, AvroSchema::Null))
+    }
+
+    /// Whether this is a union with a branch whose
+    /// [`union_type_key`](Self::union_type_key) is `name`: the full name of a
+    /// named type, or the type name of an anonymous one (`"null"`, `"array"`,
+    /// and so on; logical types go by their underlying type). Avro allows at
+    /// most one such branch per union.
+    #[must_use]
+    pub fn union_contains(&self, name: &str) -> bool {
+        match self {
+            AvroSchema::Union { types, .. } => types.iter().any(|t| t.union_type_key() == name),
+            _ => false,
+        }
+    }
+
     /// Returns a human-readable type description for use in error messages.
+    #[must_use]
     pub fn type_description(&self) -> String {
         // Primitives: use their type name directly.
         if let Some(name) = self.primitive_type_name() {
@@ -520,11 +586,11 @@ impl AvroSchema {
             AvroSchema::Logical { logical_type, .. } => match logical_type {
                 LogicalType::Date => "date".to_string(),
                 LogicalType::TimeMillis => "time_ms".to_string(),
-                LogicalType::TimeMicros => "time_us".to_string(),
+                LogicalType::TimeMicros => "time_micros".to_string(),
                 LogicalType::TimestampMillis => "timestamp_ms".to_string(),
-                LogicalType::TimestampMicros => "timestamp_us".to_string(),
+                LogicalType::TimestampMicros => "timestamp_micros".to_string(),
                 LogicalType::LocalTimestampMillis => "local_timestamp_ms".to_string(),
-                LogicalType::LocalTimestampMicros => "local_timestamp_us".to_string(),
+                LogicalType::LocalTimestampMicros => "local_timestamp_micros".to_string(),
                 LogicalType::Uuid => "uuid".to_string(),
                 LogicalType::Decimal { .. } => "decimal".to_string(),
             },
@@ -545,7 +611,8 @@ impl AvroSchema {
     ///
     /// This does NOT perform logical type promotion — callers that need it
     /// should apply `try_promote_logical_type` to the result.
-    pub fn with_merged_properties(self, properties: HashMap<std::string::String, Value>) -> Self {
+    #[must_use]
+    pub fn with_merged_properties(self, properties: Properties) -> Self {
         // Bare primitives: wrap in AnnotatedPrimitive to carry the properties.
         if let Some(kind) = self.to_primitive_type() {
             return AvroSchema::AnnotatedPrimitive { kind, properties };
@@ -893,14 +960,15 @@ pub fn validate_default(value: &Value, schema: &AvroSchema) -> Option<String> {
 /// because the referenced schema is not yet available. This function runs
 /// after type registration, when a resolver can look up previously-registered
 /// types. If the reference resolves, the default is validated against the
-/// resolved schema. If resolution fails (true forward reference), validation
-/// is skipped, matching the existing behavior.
+/// resolved schema. If resolution fails (a forward reference, or a type that
+/// does not exist), that field is skipped; see
+/// [`has_unresolved_field_defaults`] for deciding whether to try again later.
 ///
 /// Returns a list of `(field_name, reason)` pairs for any invalid defaults
 /// found.
 pub fn validate_record_field_defaults<F>(schema: &AvroSchema, resolver: F) -> Vec<(String, String)>
 where
-    F: Fn(&str) -> Option<AvroSchema>,
+    F: Fn(&str) -> Option<Arc<AvroSchema>>,
 {
     let fields = match schema {
         AvroSchema::Record { fields, .. } => fields,
@@ -924,12 +992,27 @@ where
             errors.push((field.name.clone(), reason));
         }
         // If resolve_for_validation returns None, the reference could not be
-        // resolved (true forward reference), so we skip validation.
+        // resolved (yet), so we skip validation.
     }
 
     errors
 }
 
+/// Whether any field default in a record schema was skipped by
+/// [`validate_record_field_defaults`] with the same resolver, because its
+/// type references something the resolver does not know about.
+pub fn has_unresolved_field_defaults<F>(schema: &AvroSchema, resolver: F) -> bool
+where
+    F: Fn(&str) -> Option<Arc<AvroSchema>>,
+{
+    let AvroSchema::Record { fields, .. } = schema else {
+        return false;
+    };
+    fields.iter().any(|field| {
+        field.default.is_some() && resolve_for_validation(&field.schema, &resolver).is_none()
+    })
+}
+
 /// Attempt to resolve `Reference` types in a schema for default validation.
 ///
 /// Returns `Some(resolved_schema)` if all references in the schema can be
@@ -942,7 +1025,7 @@ where
 /// be References.
 fn resolve_for_validation<F>(schema: &AvroSchema, resolver: &F) -> Option<AvroSchema>
 where
-    F: Fn(&str) -> Option<AvroSchema>,
+    F: Fn(&str) -> Option<Arc<AvroSchema>>,
 {
     use std::collections::HashSet;
     let mut visited = HashSet::new();
@@ -956,7 +1039,7 @@ fn resolve_for_validation_inner<F>(
     visited: &mut std::collections::HashSet<String>,
 ) -> Option<AvroSchema>
 where
-    F: Fn(&str) -> Option<AvroSchema>,
+    F: Fn(&str) -> Option<Arc<AvroSchema>>,
 {
     match schema {
         AvroSchema::Reference {
@@ -1067,7 +1150,7 @@ impl Field {
             name: name.to_string(),
             schema,
             aliases: vec![],
-            properties: HashMap::new(),
+            properties: Properties::new(),
             doc: None,
             default: None,
             order: None,
@@ -1086,7 +1169,7 @@ impl AvroSchema {
             fields,
             is_error: false,
             aliases: vec![],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         }
     }
 
@@ -1099,7 +1182,7 @@ impl AvroSchema {
             symbols,
             default: None,
             aliases: vec![],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         }
     }
 }
@@ -1480,7 +1563,7 @@ mod tests {
             doc: None,
             size: 16,
             aliases: vec![],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         assert!(is_valid_default(&json!("0000000000000000"), &schema));
     }
@@ -1493,7 +1576,7 @@ mod tests {
             doc: None,
             size: 16,
             aliases: vec![],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         assert!(!is_valid_default(&json!(42), &schema));
     }
@@ -1506,7 +1589,7 @@ mod tests {
     fn array_accepts_array() {
         let schema = AvroSchema::Array {
             items: Box::new(AvroSchema::Int),
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         assert!(is_valid_default(&json!([]), &schema));
     }
@@ -1515,7 +1598,7 @@ mod tests {
     fn array_accepts_non_empty_array() {
         let schema = AvroSchema::Array {
             items: Box::new(AvroSchema::Int),
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         assert!(is_valid_default(&json!([1, 2, 3]), &schema));
     }
@@ -1524,7 +1607,7 @@ mod tests {
     fn array_rejects_string() {
         let schema = AvroSchema::Array {
             items: Box::new(AvroSchema::Int),
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         assert!(!is_valid_default(&json!("not_an_array"), &schema));
     }
@@ -1533,7 +1616,7 @@ mod tests {
     fn map_accepts_object() {
         let schema = AvroSchema::Map {
             values: Box::new(AvroSchema::String),
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         assert!(is_valid_default(&json!({}), &schema));
     }
@@ -1542,7 +1625,7 @@ mod tests {
     fn map_rejects_array() {
         let schema = AvroSchema::Map {
             values: Box::new(AvroSchema::String),
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         assert!(!is_valid_default(&json!([1, 2]), &schema));
     }
@@ -1551,7 +1634,7 @@ mod tests {
     fn array_validates_element_types() {
         let schema = AvroSchema::Array {
             items: Box::new(AvroSchema::Int),
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         // Array with all valid elements.
         assert!(is_valid_default(&json!([1, 2, 3]), &schema));
@@ -1563,7 +1646,7 @@ mod tests {
     fn map_validates_value_types() {
         let schema = AvroSchema::Map {
             values: Box::new(AvroSchema::Int),
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         // Map with all valid values.
         assert!(is_valid_default(&json!({"a": 1, "b": 2}), &schema));
@@ -1642,7 +1725,7 @@ mod tests {
     fn annotated_long_accepts_integer() {
         let schema = AvroSchema::AnnotatedPrimitive {
             kind: PrimitiveType::Long,
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         assert!(is_valid_default(&json!(0), &schema));
     }
@@ -1651,7 +1734,7 @@ mod tests {
     fn annotated_long_rejects_string() {
         let schema = AvroSchema::AnnotatedPrimitive {
             kind: PrimitiveType::Long,
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         assert!(!is_valid_default(&json!("hello"), &schema));
     }
@@ -1660,7 +1743,7 @@ mod tests {
     fn logical_date_accepts_integer() {
         let schema = AvroSchema::Logical {
             logical_type: LogicalType::Date,
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         assert!(is_valid_default(&json!(0), &schema));
     }
@@ -1669,7 +1752,7 @@ mod tests {
     fn logical_date_rejects_string() {
         let schema = AvroSchema::Logical {
             logical_type: LogicalType::Date,
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         assert!(!is_valid_default(&json!("2023-01-01"), &schema));
     }
@@ -1678,7 +1761,7 @@ mod tests {
     fn logical_uuid_accepts_string() {
         let schema = AvroSchema::Logical {
             logical_type: LogicalType::Uuid,
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         assert!(is_valid_default(
             &json!("550e8400-e29b-41d4-a716-446655440000"),
@@ -1690,7 +1773,7 @@ mod tests {
     fn logical_uuid_rejects_integer() {
         let schema = AvroSchema::Logical {
             logical_type: LogicalType::Uuid,
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         assert!(!is_valid_default(&json!(42), &schema));
     }
@@ -1699,7 +1782,7 @@ mod tests {
     fn logical_timestamp_millis_accepts_integer() {
         let schema = AvroSchema::Logical {
             logical_type: LogicalType::TimestampMillis,
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         assert!(is_valid_default(&json!(1609459200000i64), &schema));
     }
@@ -1711,7 +1794,7 @@ mod tests {
                 precision: 10,
                 scale: 2,
             },
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         // Decimal's underlying type is bytes, which is serialized as a string.
         assert!(is_valid_default(&json!("\\u0000"), &schema));
@@ -1726,7 +1809,7 @@ mod tests {
         let schema = AvroSchema::Reference {
             name: "SomeType".to_string(),
             namespace: None,
-            properties: HashMap::new(),
+            properties: Properties::new(),
             span: None,
         };
         // References skip validation because the type is not yet resolved.
@@ -1799,13 +1882,13 @@ mod tests {
     }
 
     /// Resolver that maps "org.test.Inner" to a record schema.
-    fn record_resolver(full_name: &str) -> Option<AvroSchema> {
+    fn record_resolver(full_name: &str) -> Option<Arc<AvroSchema>> {
         if full_name == "org.test.Inner" {
-            Some(AvroSchema::simple_record(
+            Some(Arc::new(AvroSchema::simple_record(
                 "Inner",
                 Some("org.test"),
                 vec![Field::simple("name", AvroSchema::String)],
-            ))
+            )))
         } else {
             None
         }
@@ -1818,7 +1901,7 @@ mod tests {
             AvroSchema::Reference {
                 name: "Inner".to_string(),
                 namespace: Some("org.test".to_string()),
-                properties: HashMap::new(),
+                properties: Properties::new(),
                 span: None,
             },
             json!("not a record"),
@@ -1836,7 +1919,7 @@ mod tests {
             AvroSchema::Reference {
                 name: "Inner".to_string(),
                 namespace: Some("org.test".to_string()),
-                properties: HashMap::new(),
+                properties: Properties::new(),
                 span: None,
             },
             json!(42),
@@ -1854,7 +1937,7 @@ mod tests {
             AvroSchema::Reference {
                 name: "Inner".to_string(),
                 namespace: Some("org.test".to_string()),
-                properties: HashMap::new(),
+                properties: Properties::new(),
                 span: None,
             },
             json!({"name": "valid"}),
@@ -1872,7 +1955,7 @@ mod tests {
             AvroSchema::Reference {
                 name: "NotYetDefined".to_string(),
                 namespace: Some("org.test".to_string()),
-                properties: HashMap::new(),
+                properties: Properties::new(),
                 span: None,
             },
             json!("this would be invalid for a record, but we don't know that yet"),
@@ -1883,6 +1966,14 @@ mod tests {
             errors.is_empty(),
             "forward references should skip validation, got: {errors:?}"
         );
+        assert!(has_unresolved_field_defaults(&schema, |_| None));
+        assert!(!has_unresolved_field_defaults(&schema, |_| {
+            Some(Arc::new(AvroSchema::simple_record(
+                "NotYetDefined",
+                Some("org.test"),
+                vec![],
+            )))
+        }));
     }
 
     #[test]
@@ -1892,7 +1983,7 @@ mod tests {
             AvroSchema::Reference {
                 name: "Inner".to_string(),
                 namespace: Some("org.test".to_string()),
-                properties: HashMap::new(),
+                properties: Properties::new(),
                 span: None,
             },
             json!([1, 2, 3]),
@@ -1910,7 +2001,7 @@ mod tests {
             AvroSchema::Reference {
                 name: "Inner".to_string(),
                 namespace: Some("org.test".to_string()),
-                properties: HashMap::new(),
+                properties: Properties::new(),
                 span: None,
             },
             json!(null),
@@ -1938,7 +2029,7 @@ mod tests {
                 AvroSchema::Reference {
                     name: "Inner".to_string(),
                     namespace: Some("org.test".to_string()),
-                    properties: HashMap::new(),
+                    properties: Properties::new(),
                     span: None,
                 },
             )],
@@ -1958,7 +2049,7 @@ mod tests {
                     AvroSchema::Reference {
                         name: "Inner".to_string(),
                         namespace: Some("org.test".to_string()),
-                        properties: HashMap::new(),
+                        properties: Properties::new(),
                         span: None,
                     },
                 ],
@@ -1981,7 +2072,7 @@ mod tests {
                     AvroSchema::Reference {
                         name: "Inner".to_string(),
                         namespace: Some("org.test".to_string()),
-                        properties: HashMap::new(),
+                        properties: Properties::new(),
                         span: None,
                     },
                 ],
@@ -1998,8 +2089,8 @@ mod tests {
     // =========================================================================
 
     /// Helper: build a single-entry properties map for testing.
-    fn test_props(key: &str, value: &str) -> HashMap<String, Value> {
-        let mut props = HashMap::new();
+    fn test_props(key: &str, value: &str) -> Properties {
+        let mut props = Properties::new();
         props.insert(key.to_string(), json!(value));
         props
     }
@@ -2203,10 +2294,14 @@ mod tests {
     }
 
     #[test]
-    fn validate_uuid_on_fixed_is_invalid() {
-        // uuid requires string, not fixed (Java supports uuid on fixed(16)
-        // but that's a separate validation not covered by parse_logical_type)
-        assert!(!validate_logical_type_on_fixed("uuid", 16, None, None));
+    fn validate_uuid_on_fixed_16_is_valid() {
+        assert!(validate_logical_type_on_fixed("uuid", 16, None, None));
+    }
+
+    #[test]
+    fn validate_uuid_on_other_fixed_sizes_is_invalid() {
+        assert!(!validate_logical_type_on_fixed("uuid", 15, None, None));
+        assert!(!validate_logical_type_on_fixed("uuid", 36, None, None));
     }
 
     #[test]
@@ -2218,4 +2313,52 @@ mod tests {
             None
         ));
     }
+
+    // ------------------------------------------------------------------
+    // Union introspection
+    // ------------------------------------------------------------------
+
+    #[test]
+    fn union_introspection_helpers() {
+        let reference = AvroSchema::Reference {
+            name: "User".to_string(),
+            namespace: Some("ex".to_string()),
+            properties: Properties::new(),
+            span: None,
+        };
+        let optional = AvroSchema::Union {
+            types: vec![AvroSchema::Null, reference.clone()],
+            is_nullable_type: true,
+        };
+        let explicit = AvroSchema::Union {
+            types: vec![AvroSchema::String, AvroSchema::Null, AvroSchema::Int],
+            is_nullable_type: false,
+        };
+        let no_null = AvroSchema::Union {
+            types: vec![AvroSchema::String, reference.clone()],
+            is_nullable_type: false,
+        };
+
+        assert!(optional.is_nullable_type());
+        assert!(!explicit.is_nullable_type());
+        assert!(optional.is_nullable());
+        assert!(explicit.is_nullable());
+        assert!(!no_null.is_nullable());
+        assert!(!AvroSchema::Null.is_nullable());
+
+        assert_eq!(
+            optional.non_null_branches().collect::<Vec<_>>(),
+            vec![&reference]
+        );
+        assert_eq!(
+            explicit.non_null_branches().collect::<Vec<_>>(),
+            vec![&AvroSchema::String, &AvroSchema::Int]
+        );
+        assert_eq!(AvroSchema::Int.non_null_branches().count(), 0);
+
+        assert!(optional.union_contains("ex.User"));
+        assert!(!optional.union_contains("User"));
+        assert!(explicit.union_contains("int"));
+        assert!(!reference.union_contains("ex.User"));
+    }
 }
@@ -0,0 +1,481 @@
+// ==============================================================================
+// Output Post-processing
+// ==============================================================================
+//
+// Options that trim what ends up in the emitted JSON, applied as a pass over
+// the values produced by `schema_to_json` / `protocol_to_json`. Keeping this
+// out of the serializer leaves the Java-compatible output path untouched when
+// no option is set.
+//
+// Custom properties are recognized by elimination: any key that the Avro
+// specification does not define for that kind of object is a property.
+//
+// The walk only visits schema-level objects (the protocol, named and complex
+// types, fields, and messages). Default values and property values are never
+// descended into, so a record default such as `{"doc": "x"}` is left alone.
+
+use serde_json::{Map, Value};
+
+use super::protocol::Protocol;
+use super::schema::PRIMITIVE_TYPE_NAMES;
+
+/// The kind of JSON object being visited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NodeKind {
+    Protocol,
+    Schema,
+    Field,
+    Message,
+}
+
+/// Keys the specification defines on schema objects, including logical type
+/// attributes. Everything else on a schema is a custom property.
+const SCHEMA_KEYS: &[&str] = &[
+    "type",
+    "name",
+    "namespace",
+    "doc",
+    "aliases",
+    "fields",
+    "symbols",
+    "default",
+    "size",
+    "items",
+    "values",
+    "logicalType",
+    "precision",
+    "scale",
+];
+
+/// Keys the specification defines on record fields and message parameters.
+const FIELD_KEYS: &[&str] = &["name", "type", "doc", "default", "order", "aliases"];
+
+/// Keys the specification defines on protocol messages.
+const MESSAGE_KEYS: &[&str] = &["doc", "request", "response", "errors", "one-way"];
+
+/// Keys the specification defines on protocols.
+const PROTOCOL_KEYS: &[&str] = &["protocol", "namespace", "doc", "types", "messages"];
+
+/// Output options shared by `Idl` and `Idl2Schemata`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct OutputOptions {
+    /// Remove every `doc` key.
+    pub(crate) strip_docs: bool,
+    /// Glob patterns naming the custom properties to keep. Empty keeps all.
+    pub(crate) allow_properties: Vec<String>,
+    /// Glob patterns naming custom properties to remove, even if allowed.
+    pub(crate) deny_properties: Vec<String>,
+    /// Properties to add to, or replace on, the protocol itself, after the
+    /// allow and deny lists are applied.
+    pub(crate) set_properties: Vec<(String, Value)>,
+    /// List protocol messages by name rather than in declaration order.
+    pub(crate) sort_messages: bool,
+}
+
+impl OutputOptions {
+    /// Apply the options to a protocol (`.avpr`) JSON object.
+    pub(crate) fn apply_to_protocol(&self, json: &mut Value) {
+        if !self.is_noop() {
+            walk_protocol(json, &mut |kind, obj| self.edit(kind, obj));
+        }
+        if let Value::Object(obj) = json {
+            for (key, value) in &self.set_properties {
+                obj.insert(key.clone(), value.clone());
+            }
+        }
+    }
+
+    /// The names of `protocol`'s messages, in the order to write them.
+    pub(crate) fn message_order(&self, protocol: &Protocol) -> Vec<String> {
+        let mut names: Vec<String> = protocol.messages.keys().cloned().collect();
+        if self.sort_messages {
+            names.sort();
+        }
+        names
+    }
+
+    /// Whether the specification defines `key` on protocols, so that it
+    /// cannot be one of [`set_properties`](Self::set_properties).
+    pub(crate) fn is_protocol_key(key: &str) -> bool {
+        PROTOCOL_KEYS.contains(&key)
+    }
+
+    /// Apply the options to a schema (`.avsc`) JSON value.
+    pub(crate) fn apply_to_schema(&self, json: &mut Value) {
+        if self.is_noop() {
+            return;
+        }
+        walk_schema(json, &mut |kind, obj| self.edit(kind, obj));
+    }
+
+    fn is_noop(&self) -> bool {
+        !self.strip_docs && self.allow_properties.is_empty() && self.deny_properties.is_empty()
+    }
+
+    fn edit(&self, kind: NodeKind, obj: &mut Map<String, Value>) {
+        if self.strip_docs {
+            obj.remove("doc");
+        }
+        if !self.allow_properties.is_empty() || !self.deny_properties.is_empty() {
+            let reserved = match kind {
+                NodeKind::Protocol => PROTOCOL_KEYS,
+                NodeKind::Schema => SCHEMA_KEYS,
+                NodeKind::Field => FIELD_KEYS,
+                NodeKind::Message => MESSAGE_KEYS,
+            };
+            obj.retain(|key, _| reserved.contains(&key.as_str()) || self.keeps_property(key));
+        }
+    }
+
+    /// Whether the custom property `name` passes the allow and deny lists.
+    fn keeps_property(&self, name: &str) -> bool {
+        let allowed = self.allow_properties.is_empty()
+            || self
+                .allow_properties
+                .iter()
+                .any(|pattern| glob_matches(pattern, name));
+        allowed
+            && !self
+                .deny_properties
+                .iter()
+                .any(|pattern| glob_matches(pattern, name))
+    }
+}
+
+fn walk_protocol(json: &mut Value, f: &mut impl FnMut(NodeKind, &mut Map<String, Value>)) {
+    let Value::Object(obj) = json else {
+        return;
+    };
+    f(NodeKind::Protocol, obj);
+    if let Some(Value::Array(types)) = obj.get_mut("types") {
+        for schema in types {
+            walk_schema(schema, f);
+        }
+    }
+    if let Some(Value::Object(messages)) = obj.get_mut("messages") {
+        for message in messages.values_mut() {
+            let Value::Object(message) = message else {
+                continue;
+            };
+            f(NodeKind::Message, message);
+            if let Some(Value::Array(request)) = message.get_mut("request") {
+                for field in request {
+                    walk_field(field, f);
+                }
+            }
+            if let Some(response) = message.get_mut("response") {
+                walk_schema(response, f);
+            }
+            if let Some(errors) = message.get_mut("errors") {
+                walk_schema(errors, f);
+            }
+        }
+    }
+}
+
+fn walk_schema(json: &mut Value, f: &mut impl FnMut(NodeKind, &mut Map<String, Value>)) {
+    match json {
+        // A union.
+        Value::Array(branches) => {
+            for branch in branches {
+                walk_schema(branch, f);
+            }
+        }
+        Value::Object(obj) => {
+            f(NodeKind::Schema, obj);
+            let type_name = obj.get("type").and_then(Value::as_str).map(str::to_string);
+            match type_name.as_deref() {
+                Some("record" | "error") => {
+                    if let Some(Value::Array(fields)) = obj.get_mut("fields") {
+                        for field in fields {
+                            walk_field(field, f);
+                        }
+                    }
+                }
+                Some("array") => {
+                    if let Some(items) = obj.get_mut("items") {
+                        walk_schema(items, f);
+                    }
+                }
+                Some("map") => {
+                    if let Some(values) = obj.get_mut("values") {
+                        walk_schema(values, f);
+                    }
+                }
+                Some(_) => {}
+                // `{"type": {...}}` or `{"type": [...]}` wraps another schema.
+                None => {
+                    if let Some(inner) = obj.get_mut("type") {
+                        walk_schema(inner, f);
+                    }
+                }
+            }
+        }
+        // Names and primitives.
+        _ => {}
+    }
+}
+
+fn walk_field(json: &mut Value, f: &mut impl FnMut(NodeKind, &mut Map<String, Value>)) {
+    let Value::Object(field) = json else {
+        return;
+    };
+    f(NodeKind::Field, field);
+    if let Some(schema) = field.get_mut("type") {
+        walk_schema(schema, f);
+    }
+}
+
+// ==============================================================================
+// Fully-qualified References
+// ==============================================================================
+
+/// Rewrite every by-name reference in a protocol to the referenced type's
+/// full name.
+pub(crate) fn qualify_protocol_references(json: &mut Value) {
+    let Value::Object(obj) = json else {
+        return;
+    };
+    let namespace = obj
+        .get("namespace")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let namespace = namespace.as_deref();
+    if let Some(Value::Array(types)) = obj.get_mut("types") {
+        for schema in types {
+            qualify_schema_references(schema, namespace);
+        }
+    }
+    if let Some(Value::Object(messages)) = obj.get_mut("messages") {
+        for message in messages.values_mut() {
+            if let Some(Value::Array(request)) = message.get_mut("request") {
+                for param in request {
+                    if let Some(schema) = param.get_mut("type") {
+                        qualify_schema_references(schema, namespace);
+                    }
+                }
+            }
+            for key in ["response", "errors"] {
+                if let Some(schema) = message.get_mut(key) {
+                    qualify_schema_references(schema, namespace);
+                }
+            }
+        }
+    }
+}
+
+/// Rewrite every by-name reference in a schema to the referenced type's full
+/// name, resolving short names against `namespace` and the namespaces of the
+/// enclosing named types. Consumers that resolve references by exact name,
+/// like schema registries, do not track namespaces.
+pub(crate) fn qualify_schema_references(json: &mut Value, namespace: Option<&str>) {
+    match json {
+        Value::String(name)
+            if !name.contains('.') && !PRIMITIVE_TYPE_NAMES.contains(&name.as_str()) =>
+        {
+            if let Some(namespace) = namespace.filter(|ns| !ns.is_empty()) {
+                *name = format!("{namespace}.{name}");
+            }
+        }
+        // A union.
+        Value::Array(branches) => {
+            for branch in branches {
+                qualify_schema_references(branch, namespace);
+            }
+        }
+        Value::Object(obj) => {
+            let type_name = obj.get("type").and_then(Value::as_str).map(str::to_string);
+            match type_name.as_deref() {
+                Some("record" | "error") => {
+                    let own_namespace = declared_namespace(obj, namespace);
+                    if let Some(Value::Array(fields)) = obj.get_mut("fields") {
+                        for field in fields {
+                            if let Some(schema) = field.get_mut("type") {
+                                qualify_schema_references(schema, own_namespace.as_deref());
+                            }
+                        }
+                    }
+                }
+                Some("array") => {
+                    if let Some(items) = obj.get_mut("items") {
+                        qualify_schema_references(items, namespace);
+                    }
+                }
+                Some("map") => {
+                    if let Some(values) = obj.get_mut("values") {
+                        qualify_schema_references(values, namespace);
+                    }
+                }
+                Some(_) => {}
+                // `{"type": {...}}` or `{"type": [...]}` wraps another schema.
+                None => {
+                    if let Some(inner) = obj.get_mut("type") {
+                        qualify_schema_references(inner, namespace);
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The namespace a named type's own references resolve against: the one in
+/// its full name, or its `namespace` key, or the enclosing one.
+fn declared_namespace(obj: &Map<String, Value>, enclosing: Option<&str>) -> Option<String> {
+    let name = obj.get("name").and_then(Value::as_str).unwrap_or("");
+    match name.rsplit_once('.') {
+        Some((namespace, _)) => Some(namespace.to_string()),
+        None => obj
+            .get("namespace")
+            .and_then(Value::as_str)
+            .or(enclosing)
+            .map(str::to_string),
+    }
+}
+
+/// Match `name` against a glob `pattern` where `*` matches any (possibly
+/// empty) run of characters and `?` matches exactly one character.
+pub(crate) fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` seen, and the name position it was tried at.
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some(&'*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` absorb one more character and retry.
+                Some((star_p, star_n)) => {
+                    backtrack = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    #[test]
+    fn strip_docs_removes_schema_level_docs_only() {
+        let options = OutputOptions {
+            strip_docs: true,
+            ..OutputOptions::default()
+        };
+        let mut protocol = json!({
+            "protocol": "P",
+            "doc": "Protocol doc.",
+            "types": [{
+                "type": "record",
+                "name": "R",
+                "doc": "Record doc.",
+                "fields": [{
+                    "name": "inner",
+                    "doc": "Field doc.",
+                    "type": {"type": "record", "name": "Inner", "doc": "Nested doc.", "fields": []},
+                    "default": {"doc": "not a doc comment"}
+                }]
+            }],
+            "messages": {
+                "ping": {
+                    "doc": "Message doc.",
+                    "request": [{"name": "e", "doc": "Param doc.", "type": {
+                        "type": "array",
+                        "items": {"type": "enum", "name": "E", "doc": "Enum doc.", "symbols": ["A"]}
+                    }}],
+                    "response": "null"
+                }
+            }
+        });
+        options.apply_to_protocol(&mut protocol);
+        assert_eq!(
+            protocol,
+            json!({
+                "protocol": "P",
+                "types": [{
+                    "type": "record",
+                    "name": "R",
+                    "fields": [{
+                        "name": "inner",
+                        "type": {"type": "record", "name": "Inner", "fields": []},
+                        "default": {"doc": "not a doc comment"}
+                    }]
+                }],
+                "messages": {
+                    "ping": {
+                        "request": [{"name": "e", "type": {
+                            "type": "array",
+                            "items": {"type": "enum", "name": "E", "symbols": ["A"]}
+                        }}],
+                        "response": "null"
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn glob_matches_wildcards_and_exact_names() {
+        assert!(glob_matches("com.example.User", "com.example.User"));
+        assert!(!glob_matches("com.example.User", "com.example.Users"));
+        assert!(glob_matches("com.example.*", "com.example.User"));
+        assert!(glob_matches("com.example.*", "com.example.sub.User"));
+        assert!(!glob_matches("com.example.*", "com.other.User"));
+        assert!(glob_matches("*.User", "com.example.User"));
+        assert!(glob_matches("com.*.U?er", "com.example.User"));
+        assert!(glob_matches("*", "User"));
+        assert!(!glob_matches("", "User"));
+    }
+
+    #[test]
+    fn property_lists_filter_custom_properties_only() {
+        let options = OutputOptions {
+            allow_properties: vec!["java-*".to_string(), "internal-*".to_string()],
+            deny_properties: vec!["internal-*".to_string()],
+            ..OutputOptions::default()
+        };
+        let mut schema = json!({
+            "type": "record",
+            "name": "R",
+            "java-class": "com.example.R",
+            "internal-owner": "team-a",
+            "other": 1,
+            "fields": [{
+                "name": "amount",
+                "internal-note": "x",
+                "order": "ignore",
+                "type": {"type": "bytes", "logicalType": "decimal", "precision": 9, "scale": 2, "other": 2}
+            }]
+        });
+        options.apply_to_schema(&mut schema);
+        assert_eq!(
+            schema,
+            json!({
+                "type": "record",
+                "name": "R",
+                "java-class": "com.example.R",
+                "fields": [{
+                    "name": "amount",
+                    "order": "ignore",
+                    "type": {"type": "bytes", "logicalType": "decimal", "precision": 9, "scale": 2}
+                }]
+            })
+        );
+    }
+}
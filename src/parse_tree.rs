@@ -0,0 +1,453 @@
+// ==============================================================================
+// Parse Tree
+// ==============================================================================
+//
+// The tree of grammar rules the parser matched, which the reader walks to
+// build the model and the lossless syntax tree is assembled from. It is the
+// same whichever parser built it: the ANTLR-generated one in `src/generated`
+// (feature `antlr`, the default) or the hand-written one in `src/parser.rs`
+// (feature `handwritten-parser`), so nothing downstream depends on the
+// choice. With both features enabled, the ANTLR parser is used, and the tests
+// check that the two build the same tree and errors for every test input.
+//
+// Nodes refer to tokens by their index in the list `crate::lexer::lex`
+// returns, which has whitespace and comments too, so doc comments and spans
+// are found the same way for both parsers.
+
+use std::ops::Range;
+
+use crate::lexer::{self, LexError, Token, TokenType};
+
+/// A grammar rule, named as in the grammar (see [`Rule::name`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Rule {
+    IdlFile,
+    ProtocolDeclaration,
+    ProtocolDeclarationBody,
+    NamespaceDeclaration,
+    MainSchemaDeclaration,
+    Identifier,
+    SchemaProperty,
+    ImportStatement,
+    NamedSchemaDeclaration,
+    FixedDeclaration,
+    EnumDeclaration,
+    EnumSymbol,
+    EnumDefault,
+    RecordDeclaration,
+    RecordBody,
+    FieldDeclaration,
+    VariableDeclaration,
+    MessageDeclaration,
+    FormalParameter,
+    ResultType,
+    FullType,
+    PlainType,
+    NullableType,
+    PrimitiveType,
+    ArrayType,
+    MapType,
+    UnionType,
+    JsonValue,
+    JsonLiteral,
+    JsonObject,
+    JsonPair,
+    JsonArray,
+}
+
+impl Rule {
+    /// The grammar's name for the rule, e.g. `recordDeclaration`.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Rule::IdlFile => "idlFile",
+            Rule::ProtocolDeclaration => "protocolDeclaration",
+            Rule::ProtocolDeclarationBody => "protocolDeclarationBody",
+            Rule::NamespaceDeclaration => "namespaceDeclaration",
+            Rule::MainSchemaDeclaration => "mainSchemaDeclaration",
+            Rule::Identifier => "identifier",
+            Rule::SchemaProperty => "schemaProperty",
+            Rule::ImportStatement => "importStatement",
+            Rule::NamedSchemaDeclaration => "namedSchemaDeclaration",
+            Rule::FixedDeclaration => "fixedDeclaration",
+            Rule::EnumDeclaration => "enumDeclaration",
+            Rule::EnumSymbol => "enumSymbol",
+            Rule::EnumDefault => "enumDefault",
+            Rule::RecordDeclaration => "recordDeclaration",
+            Rule::RecordBody => "recordBody",
+            Rule::FieldDeclaration => "fieldDeclaration",
+            Rule::VariableDeclaration => "variableDeclaration",
+            Rule::MessageDeclaration => "messageDeclaration",
+            Rule::FormalParameter => "formalParameter",
+            Rule::ResultType => "resultType",
+            Rule::FullType => "fullType",
+            Rule::PlainType => "plainType",
+            Rule::NullableType => "nullableType",
+            Rule::PrimitiveType => "primitiveType",
+            Rule::ArrayType => "arrayType",
+            Rule::MapType => "mapType",
+            Rule::UnionType => "unionType",
+            Rule::JsonValue => "jsonValue",
+            Rule::JsonLiteral => "jsonLiteral",
+            Rule::JsonObject => "jsonObject",
+            Rule::JsonPair => "jsonPair",
+            Rule::JsonArray => "jsonArray",
+        }
+    }
+}
+
+/// A rule the parser matched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Node {
+    pub(crate) rule: Rule,
+    /// Indexes of the node's first through last token in the token list,
+    /// with the whitespace and comments between them. Empty for a rule that
+    /// matched nothing, which only error recovery produces.
+    pub(crate) tokens: Range<usize>,
+    /// The rules matched inside this one and the tokens matched directly,
+    /// in source order. Whitespace and comments are not children.
+    pub(crate) children: Vec<Child>,
+}
+
+/// A child of a [`Node`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Child {
+    Node(Node),
+    /// A token, by index in the token list, and its type.
+    Token(usize, TokenType),
+}
+
+impl Node {
+    /// The first child node matching `rule`.
+    pub(crate) fn child(&self, rule: Rule) -> Option<&Node> {
+        self.children(rule).next()
+    }
+
+    /// The child nodes matching `rule`, in order.
+    pub(crate) fn children(&self, rule: Rule) -> impl Iterator<Item = &Node> {
+        self.nodes().filter(move |node| node.rule == rule)
+    }
+
+    /// Every child node, in order.
+    pub(crate) fn nodes(&self) -> impl Iterator<Item = &Node> {
+        self.children.iter().filter_map(|child| match child {
+            Child::Node(node) => Some(node),
+            Child::Token(..) => None,
+        })
+    }
+
+    /// The tokens matched directly by this node, as `(index, type)`.
+    pub(crate) fn token_children(&self) -> impl Iterator<Item = (usize, TokenType)> {
+        self.children.iter().filter_map(|child| match *child {
+            Child::Token(index, token_type) => Some((index, token_type)),
+            Child::Node(_) => None,
+        })
+    }
+
+    /// The index of the first token of type `token_type` matched directly
+    /// by this node.
+    pub(crate) fn token(&self, token_type: TokenType) -> Option<usize> {
+        self.token_children()
+            .find(|&(_, t)| t == token_type)
+            .map(|(index, _)| index)
+    }
+}
+
+/// A syntax error the parser reported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ParseError {
+    /// Byte offset and length of the offending token. At the end of the
+    /// input, that is one byte past the end.
+    pub(crate) offset: usize,
+    pub(crate) length: usize,
+    /// 1-based line and 0-based column, in characters, of the offending
+    /// token.
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+    /// The parser's message, e.g. `mismatched input ';' expecting '{'`.
+    pub(crate) message: String,
+}
+
+/// Everything lexing and parsing a source produced.
+pub(crate) struct Parse {
+    /// Every token, whitespace and comments included.
+    pub(crate) tokens: Vec<Token>,
+    /// Characters that are not part of any token.
+    pub(crate) lex_errors: Vec<LexError>,
+    /// The `idlFile` rule. With syntax errors, it has whatever shape error
+    /// recovery gave it.
+    pub(crate) tree: Node,
+    /// Syntax errors, in the order they were found.
+    pub(crate) errors: Vec<ParseError>,
+}
+
+/// Lex and parse `source`. This never fails: errors are part of the result.
+pub(crate) fn parse(source: &str) -> Parse {
+    let (tokens, lex_errors) = lexer::lex(source);
+    #[cfg(feature = "antlr")]
+    let (tree, errors) = antlr::parse(source, &tokens);
+    #[cfg(not(feature = "antlr"))]
+    let (tree, errors) = crate::parser::parse(source, &tokens);
+    Parse {
+        tokens,
+        lex_errors,
+        tree,
+        errors,
+    }
+}
+
+/// Parsing with the ANTLR-generated parser. Its lexer tokenizes exactly like
+/// `crate::lexer`, so its tokens are mapped to the lexer's by byte offset.
+#[cfg(feature = "antlr")]
+mod antlr {
+    use std::cell::RefCell;
+    use std::ops::Range;
+    use std::rc::Rc;
+
+    use antlr4rust::InputStream;
+    use antlr4rust::common_token_stream::CommonTokenStream;
+    use antlr4rust::error_listener::ErrorListener;
+    use antlr4rust::parser::Parser;
+    use antlr4rust::parser_rule_context::ParserRuleContext;
+    use antlr4rust::recognizer::Recognizer;
+    use antlr4rust::rule_context::RuleContext;
+    use antlr4rust::token::Token as _;
+    use antlr4rust::token_factory::TokenFactory;
+    use antlr4rust::tree::Tree;
+
+    use super::{Child, Node, ParseError, Rule, Token};
+    use crate::generated::idllexer::IdlLexer;
+    use crate::generated::idlparser::{IdlParser, IdlParserContextType};
+
+    /// The rules, indexed like the generated parser's `ruleNames`.
+    const RULES: [Rule; 32] = [
+        Rule::IdlFile,
+        Rule::ProtocolDeclaration,
+        Rule::ProtocolDeclarationBody,
+        Rule::NamespaceDeclaration,
+        Rule::MainSchemaDeclaration,
+        Rule::Identifier,
+        Rule::SchemaProperty,
+        Rule::ImportStatement,
+        Rule::NamedSchemaDeclaration,
+        Rule::FixedDeclaration,
+        Rule::EnumDeclaration,
+        Rule::EnumSymbol,
+        Rule::EnumDefault,
+        Rule::RecordDeclaration,
+        Rule::RecordBody,
+        Rule::FieldDeclaration,
+        Rule::VariableDeclaration,
+        Rule::MessageDeclaration,
+        Rule::FormalParameter,
+        Rule::ResultType,
+        Rule::FullType,
+        Rule::PlainType,
+        Rule::NullableType,
+        Rule::PrimitiveType,
+        Rule::ArrayType,
+        Rule::MapType,
+        Rule::UnionType,
+        Rule::JsonValue,
+        Rule::JsonLiteral,
+        Rule::JsonObject,
+        Rule::JsonPair,
+        Rule::JsonArray,
+    ];
+
+    pub(super) fn parse(source: &str, tokens: &[Token]) -> (Node, Vec<ParseError>) {
+        let mut lexer = IdlLexer::new(InputStream::new(source));
+        // `crate::lexer` reports the same lexer errors.
+        lexer.remove_error_listeners();
+        let mut parser = IdlParser::new(CommonTokenStream::new(lexer));
+        parser.build_parse_trees = true;
+
+        // Replace the default ConsoleErrorListener, which prints to stderr,
+        // with one that collects the errors.
+        let errors = Rc::new(RefCell::new(Vec::new()));
+        parser.remove_error_listeners();
+        parser.add_error_listener(Box::new(CollectingErrorListener {
+            errors: Rc::clone(&errors),
+        }));
+
+        let tree = match parser.idlFile() {
+            Ok(tree) => node(&*tree, tokens),
+            Err(e) => {
+                errors.borrow_mut().push(ParseError {
+                    offset: 0,
+                    length: 0,
+                    line: 1,
+                    column: 0,
+                    message: format!("ANTLR parse error: {e:?}"),
+                });
+                Node {
+                    rule: Rule::IdlFile,
+                    tokens: 0..0,
+                    children: Vec::new(),
+                }
+            }
+        };
+        let errors = errors.borrow().clone();
+        (tree, errors)
+    }
+
+    /// Convert a parse tree context, taking its token children from the
+    /// token list: the tokens in its range that are neither in a child rule
+    /// nor whitespace or comments. Tokens error recovery made up are not in
+    /// the list, so they are left out.
+    fn node<'input, T>(ctx: &T, tokens: &[Token]) -> Node
+    where
+        T: ParserRuleContext<'input, Ctx = IdlParserContextType> + ?Sized,
+    {
+        let index = |offset: isize| {
+            let offset = offset.max(0) as usize;
+            tokens.partition_point(|token| token.span.start < offset)
+        };
+        let first = index(ctx.start().get_start());
+        let range = if ctx.stop().get_token_index() >= ctx.start().get_token_index()
+            && ctx.start().get_token_index() >= 0
+        {
+            first..index(ctx.stop().get_start()) + 1
+        } else {
+            first..first
+        };
+
+        let mut children = Vec::new();
+        let mut next = range.start;
+        for child in ctx.get_children() {
+            if child.get_rule_index() >= RULES.len() {
+                continue;
+            }
+            let child = node(&*child, tokens);
+            push_tokens(tokens, next..child.tokens.start, &mut children);
+            next = next.max(child.tokens.end);
+            children.push(Child::Node(child));
+        }
+        push_tokens(tokens, next..range.end, &mut children);
+
+        Node {
+            rule: RULES[ctx.get_rule_index()],
+            tokens: range,
+            children,
+        }
+    }
+
+    /// Add the tokens in `range` to `children`, except whitespace and
+    /// comments.
+    fn push_tokens(tokens: &[Token], range: Range<usize>, children: &mut Vec<Child>) {
+        for index in range {
+            let token_type = tokens[index].token_type;
+            if !token_type.is_trivia() {
+                children.push(Child::Token(index, token_type));
+            }
+        }
+    }
+
+    /// An ANTLR error listener that collects syntax errors instead of
+    /// printing them to stderr.
+    struct CollectingErrorListener {
+        errors: Rc<RefCell<Vec<ParseError>>>,
+    }
+
+    impl<'a, T: Recognizer<'a>> ErrorListener<'a, T> for CollectingErrorListener {
+        fn syntax_error(
+            &self,
+            _recognizer: &T,
+            offending_symbol: Option<&<T::TF as TokenFactory<'a>>::Inner>,
+            line: isize,
+            column: isize,
+            msg: &str,
+            _error: Option<&antlr4rust::errors::ANTLRError>,
+        ) {
+            let (offset, length) = offending_symbol.map_or((0, 0), |tok| {
+                let start = tok.get_start();
+                let stop = tok.get_stop();
+                if start >= 0 && stop >= start {
+                    (start as usize, (stop - start + 1) as usize)
+                } else if start >= 0 {
+                    (start as usize, 1)
+                } else {
+                    (0, 0)
+                }
+            });
+            self.errors.borrow_mut().push(ParseError {
+                offset,
+                length,
+                line: line.max(0) as usize,
+                column: column.max(0) as usize,
+                message: msg.to_string(),
+            });
+        }
+    }
+}
+
+/// With both parsers compiled in, check that they agree: the reader and the
+/// syntax tree only see the parse tree and errors, so the same tree and
+/// errors mean byte-identical output.
+#[cfg(all(test, feature = "antlr", feature = "handwritten-parser"))]
+mod tests {
+    use std::fs;
+
+    use walkdir::WalkDir;
+
+    use super::antlr;
+    use crate::lexer;
+
+    /// Sources with syntax errors, to compare how the parsers recover.
+    const BROKEN: &[&str] = &[
+        "",
+        "protocl P { record R { int x; } }",
+        "protocol P { record R { int x } }",
+        "protocol P { record R { int x; }",
+        "protocol P { record R { union { int, , string } x; } }",
+        "protocol P { record R { 123 x; } }",
+        "protocol P { fixed F(not_a_number); }",
+        "protocol P { record R { int x, } }",
+        "protocol P { record R { int x int y; } }",
+        "protocol P { record R { int x; } } }",
+        "protocol P { record { int x; } }",
+        "protocol P { record R { x; } }",
+        "protocol P { enum E { A B C } }",
+        "protocol P { enum E { A; B; C } }",
+        "protocol P { fixed F; }",
+        "protocol P { fixed F(16; }",
+        "protocol P { @foo(\"bar\") record R { int x = ; } void m(int a,) oneway; }",
+        "namespace n; schema int; record R { map<int x; }",
+    ];
+
+    fn assert_same_parse(name: &str, source: &str) {
+        let (tokens, _) = lexer::lex(source);
+        assert_eq!(
+            crate::parser::parse(source, &tokens),
+            antlr::parse(source, &tokens),
+            "the parsers disagree on {name}"
+        );
+    }
+
+    #[test]
+    fn parsers_agree_on_test_files() {
+        let mut checked = 0;
+        for root in ["tests/testdata", "avro/lang"] {
+            for entry in WalkDir::new(root).into_iter().filter_map(Result::ok) {
+                let path = entry.path();
+                if path.extension().is_none_or(|extension| extension != "avdl") {
+                    continue;
+                }
+                // Sources in other encodings are decoded before parsing;
+                // they are covered by their UTF-8 counterparts.
+                let Ok(source) = fs::read_to_string(path) else {
+                    continue;
+                };
+                assert_same_parse(&path.display().to_string(), &source);
+                checked += 1;
+            }
+        }
+        assert!(checked > 0, "no test files found");
+    }
+
+    #[test]
+    fn parsers_agree_on_syntax_errors() {
+        for source in BROKEN {
+            assert_same_parse(&format!("{source:?}"), source);
+        }
+    }
+}
@@ -0,0 +1,1355 @@
+// ==============================================================================
+// Hand-Written Parser
+// ==============================================================================
+//
+// A recursive-descent parser for Avro IDL that builds the same parse tree as
+// the ANTLR-generated parser in `src/generated/idlparser.rs`, and reports the
+// same syntax errors for the same input, so that diagnostics (and the tests
+// that pin them) do not depend on which parser is compiled in.
+//
+// Each rule below follows the generated function for it step by step. The
+// numbers passed to `sync`, `expect`, and `call` are the generated parser's
+// ATN states for those steps, so the two can be compared side by side, and
+// the token sets are what ANTLR computes for each state. Error reporting and
+// recovery reproduce ANTLR's `DefaultErrorStrategy`:
+//
+// - `expect` deletes a single extraneous token or assumes a single missing
+//   one, if that is enough to go on; otherwise it fails.
+// - `sync` runs before every decision: an unexpected token there is deleted
+//   if that helps, skipped over to the next expected token at the end of a
+//   loop iteration, and otherwise a failure.
+// - A failure unwinds to the rule it happened in, which reports it unless an
+//   earlier error is still being recovered from, skips tokens up to one that
+//   can follow a rule being parsed, and returns as if it had matched.
+//
+// Most decisions look at the next token or two. Where the grammar needs more
+// (a protocol body item can start with any number of annotations, and a
+// keyword can be a type name or a message name), the alternatives are tried
+// in turn without building anything, and the one that gets furthest wins, as
+// ANTLR's adaptive prediction decides.
+
+use crate::lexer::{Token, TokenType as T, line_column};
+use crate::parse_tree::{Child, Node, ParseError, Rule};
+
+/// Parse the tokens of `source` into an `idlFile` tree.
+pub(crate) fn parse(source: &str, tokens: &[Token]) -> (Node, Vec<ParseError>) {
+    let visible: Vec<usize> = (0..tokens.len())
+        .filter(|&i| !tokens[i].token_type.is_trivia())
+        .collect();
+    let mut parser = Parser::new(source, tokens, &visible, false, 0);
+    // Only speculation fails a rule; otherwise it recovers.
+    let _ = parser.rule(Rule::IdlFile, Parser::idl_file);
+    let tree = match parser
+        .frames
+        .pop()
+        .and_then(|frame| frame.children.into_iter().next())
+    {
+        Some(Child::Node(tree)) => tree,
+        _ => unreachable!("the root rule leaves one node"),
+    };
+    (tree, parser.errors)
+}
+
+// ==============================================================================
+// Token Sets
+// ==============================================================================
+
+/// A set of token types, which may include the end of input and ε (the end
+/// of the rule, where what follows depends on the caller).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TokenSet(u64);
+
+const EOF_BIT: u64 = 1 << 62;
+const EPSILON_BIT: u64 = 1 << 63;
+
+impl TokenSet {
+    const EPSILON: TokenSet = TokenSet(EPSILON_BIT);
+
+    const fn of(types: &[T]) -> TokenSet {
+        let mut bits = 0;
+        let mut i = 0;
+        while i < types.len() {
+            bits |= 1 << types[i] as u64;
+            i += 1;
+        }
+        TokenSet(bits)
+    }
+
+    /// The token types from `first` through `last`, in declaration order.
+    const fn range(first: T, last: T) -> TokenSet {
+        TokenSet((u64::MAX >> (63 - last as u64)) & !((1 << first as u64) - 1))
+    }
+
+    const fn with(self, other: TokenSet) -> TokenSet {
+        TokenSet(self.0 | other.0)
+    }
+
+    const fn without(self, other: TokenSet) -> TokenSet {
+        TokenSet(self.0 & !other.0)
+    }
+
+    /// Whether the set has `token`, where `None` is the end of input.
+    fn contains(self, token: Option<T>) -> bool {
+        let bit = match token {
+            Some(token_type) => 1 << token_type as u64,
+            None => EOF_BIT,
+        };
+        self.0 & bit != 0
+    }
+
+    fn has_epsilon(self) -> bool {
+        self.0 & EPSILON_BIT != 0
+    }
+
+    /// The set as ANTLR writes it in messages: one element bare, several in
+    /// braces, the end of input first and the rest in token type order.
+    fn display(self) -> String {
+        let mut elements = Vec::new();
+        if self.0 & EOF_BIT != 0 {
+            elements.push("<EOF>".to_string());
+        }
+        for token_type in ALL_TOKEN_TYPES {
+            if self.contains(Some(token_type)) {
+                elements.push(token_type.display().into_owned());
+            }
+        }
+        if elements.len() == 1 {
+            elements.remove(0)
+        } else {
+            format!("{{{}}}", elements.join(", "))
+        }
+    }
+}
+
+/// Every token type, in declaration order.
+const ALL_TOKEN_TYPES: [T; 57] = [
+    T::Sub,
+    T::DocComment,
+    T::EmptyComment,
+    T::MultiLineComment,
+    T::SingleLineComment,
+    T::WS,
+    T::Protocol,
+    T::Namespace,
+    T::Import,
+    T::Idl,
+    T::Schema,
+    T::Enum,
+    T::Fixed,
+    T::Error,
+    T::Record,
+    T::Array,
+    T::Map,
+    T::Union,
+    T::Boolean,
+    T::Int,
+    T::Long,
+    T::Float,
+    T::Double,
+    T::String,
+    T::Bytes,
+    T::Null,
+    T::BTrue,
+    T::BFalse,
+    T::Decimal,
+    T::Date,
+    T::Time,
+    T::Timestamp,
+    T::LocalTimestamp,
+    T::Uuid,
+    T::Void,
+    T::Oneway,
+    T::Throws,
+    T::LParen,
+    T::RParen,
+    T::LBrace,
+    T::RBrace,
+    T::LBracket,
+    T::RBracket,
+    T::Colon,
+    T::Semicolon,
+    T::Comma,
+    T::At,
+    T::Equals,
+    T::Dot,
+    T::Dash,
+    T::QuestionMark,
+    T::LT,
+    T::GT,
+    T::StringLiteral,
+    T::IntegerLiteral,
+    T::FloatingPointLiteral,
+    T::IdentifierToken,
+];
+
+const fn set(types: &[T]) -> TokenSet {
+    TokenSet::of(types)
+}
+
+const END: TokenSet = TokenSet(EOF_BIT);
+/// An identifier: any keyword can be used as a name.
+const NAME: TokenSet = TokenSet::range(T::Protocol, T::Throws).with(set(&[T::IdentifierToken]));
+/// What a type, with its annotations, starts with.
+const TYPE_START: TokenSet = NAME.with(set(&[T::At]));
+/// What a field, variable, parameter, enum symbol, or message starts with.
+/// Doc comments never reach the parser, but ANTLR counts them in.
+const MEMBER_START: TokenSet = TYPE_START.with(set(&[T::DocComment]));
+/// The body of a protocol, record, or enum: members up to the closing brace.
+const BODY: TokenSet = MEMBER_START.with(set(&[T::RBrace]));
+const PARAMETERS: TokenSet = MEMBER_START.with(set(&[T::RParen]));
+
+const FILE_START: TokenSet = END.with(set(&[
+    T::Sub,
+    T::DocComment,
+    T::Protocol,
+    T::Namespace,
+    T::Import,
+    T::Schema,
+    T::Enum,
+    T::Fixed,
+    T::Error,
+    T::Record,
+    T::At,
+]));
+const SCHEMA_FILE_START: TokenSet = FILE_START.without(set(&[T::Protocol]));
+const AFTER_NAMESPACE: TokenSet = SCHEMA_FILE_START.without(set(&[T::Namespace]));
+const AFTER_MAIN_SCHEMA: TokenSet = AFTER_NAMESPACE.without(set(&[T::Schema]));
+const SCHEMA_FILE_ITEM: TokenSet = AFTER_MAIN_SCHEMA.without(END.with(set(&[T::Sub])));
+const FILE_END: TokenSet = END.with(set(&[T::Sub]));
+
+const PROTOCOL_START: TokenSet = set(&[T::DocComment, T::Protocol, T::At]);
+const IMPORT_KIND: TokenSet = set(&[T::Protocol, T::Idl, T::Schema]);
+const NAMED_SCHEMA_START: TokenSet =
+    set(&[T::DocComment, T::Enum, T::Fixed, T::Error, T::Record, T::At]);
+const FIXED_START: TokenSet = set(&[T::DocComment, T::Fixed, T::At]);
+const ENUM_START: TokenSet = set(&[T::DocComment, T::Enum, T::At]);
+const RECORD_START: TokenSet = set(&[T::DocComment, T::Error, T::Record, T::At]);
+const RECORD_TYPE: TokenSet = set(&[T::Error, T::Record]);
+const MESSAGE_END: TokenSet = set(&[T::Oneway, T::Throws, T::Semicolon]);
+
+const PRIMITIVE: TokenSet = set(&[
+    T::Boolean,
+    T::Int,
+    T::Long,
+    T::Float,
+    T::Double,
+    T::String,
+    T::Bytes,
+    T::Null,
+    T::Date,
+    T::Time,
+    T::Timestamp,
+    T::LocalTimestamp,
+    T::Uuid,
+]);
+const PRIMITIVE_START: TokenSet = PRIMITIVE.with(set(&[T::Decimal]));
+const JSON_LITERAL: TokenSet = set(&[
+    T::Null,
+    T::BTrue,
+    T::BFalse,
+    T::StringLiteral,
+    T::IntegerLiteral,
+    T::FloatingPointLiteral,
+]);
+const JSON_START: TokenSet = JSON_LITERAL.with(set(&[T::LBrace, T::LBracket]));
+
+// ==============================================================================
+// Parser
+// ==============================================================================
+
+/// Why a rule could not go on, like ANTLR's `RecognitionException`.
+#[derive(Debug, Clone, Copy)]
+enum Failure {
+    /// The next token is not one the current state accepts; `expected` is
+    /// what an earlier state accepted, if the mismatch is reported there.
+    Mismatch { expected: Option<TokenSet> },
+    /// No alternative of a decision matches the input from position `start`
+    /// through position `at`.
+    NoViableAlt { start: usize, at: usize },
+}
+
+/// What kind of decision a `sync` precedes, which decides how an unexpected
+/// token is recovered from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Decision {
+    /// Entering an optional block or a loop.
+    Enter,
+    /// Going around a loop again.
+    LoopBack,
+}
+
+/// A rule being parsed, and what it has matched so far.
+struct Frame {
+    rule: Rule,
+    /// Index of the rule's first token in the token list.
+    start: usize,
+    children: Vec<Child>,
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: &'a [Token],
+    /// Indexes of the tokens the grammar sees: all but whitespace and
+    /// comments.
+    visible: &'a [usize],
+    /// The current position in `visible`.
+    pos: usize,
+    /// The current ATN state, and the tokens it accepts.
+    state: u16,
+    next: TokenSet,
+    /// What the last state before an optional part accepted, in context, like
+    /// ANTLR's `nextTokensContext`: a mismatch after the optional parts were
+    /// skipped reports what they would have accepted.
+    skipped: Option<TokenSet>,
+    /// For each rule being parsed below the root, the tokens that can follow
+    /// it in the rule that called it.
+    follow: Vec<TokenSet>,
+    frames: Vec<Frame>,
+    /// Whether an error has been reported and no token matched since, during
+    /// which further errors are not reported.
+    recovering: bool,
+    /// The position of the last recovery, and the states it happened in, to
+    /// make sure recovering twice in one place makes progress.
+    last_error: Option<(usize, Vec<u16>)>,
+    errors: Vec<ParseError>,
+    /// Whether this parser only tries an alternative for a decision: it
+    /// stops at the first failure, reports nothing, and builds nothing.
+    speculating: bool,
+}
+
+type Parsed = Result<(), Failure>;
+
+impl<'a> Parser<'a> {
+    fn new(
+        source: &'a str,
+        tokens: &'a [Token],
+        visible: &'a [usize],
+        speculating: bool,
+        pos: usize,
+    ) -> Self {
+        Parser {
+            source,
+            tokens,
+            visible,
+            pos,
+            state: 0,
+            next: TokenSet::EPSILON,
+            skipped: None,
+            follow: Vec::new(),
+            frames: vec![Frame {
+                rule: Rule::IdlFile,
+                start: 0,
+                children: Vec::new(),
+            }],
+            recovering: false,
+            last_error: None,
+            errors: Vec::new(),
+            speculating,
+        }
+    }
+
+    // --------------------------------------------------------------------------
+    // Tokens
+    // --------------------------------------------------------------------------
+
+    /// The type of the `k`th token from the current position (1-based), or
+    /// `None` at the end of input.
+    fn la(&self, k: usize) -> Option<T> {
+        self.visible
+            .get(self.pos + k - 1)
+            .map(|&i| self.tokens[i].token_type)
+    }
+
+    /// Index in the token list of the token at `pos`; the end of input is
+    /// one past the last token.
+    fn token_index(&self, pos: usize) -> usize {
+        self.visible.get(pos).copied().unwrap_or(self.tokens.len())
+    }
+
+    fn consume(&mut self) {
+        let Some(&index) = self.visible.get(self.pos) else {
+            return;
+        };
+        self.pos += 1;
+        if !self.speculating
+            && let Some(frame) = self.frames.last_mut()
+        {
+            frame
+                .children
+                .push(Child::Token(index, self.tokens[index].token_type));
+        }
+    }
+
+    /// Consume tokens up to one in `set`, or the end of input.
+    fn consume_until(&mut self, set: TokenSet) {
+        while let Some(token_type) = self.la(1)
+            && !set.contains(Some(token_type))
+        {
+            self.consume();
+        }
+    }
+
+    // --------------------------------------------------------------------------
+    // Rules
+    // --------------------------------------------------------------------------
+
+    /// Parse `rule` with `body`, recovering from a failure in it.
+    fn rule(&mut self, rule: Rule, body: fn(&mut Self) -> Parsed) -> Parsed {
+        if !self.speculating {
+            self.frames.push(Frame {
+                rule,
+                start: self.token_index(self.pos),
+                children: Vec::new(),
+            });
+        }
+        let result = body(self);
+        if let Err(failure) = result {
+            if self.speculating {
+                return Err(failure);
+            }
+            self.report_failure(failure);
+            self.recover();
+        }
+        if !self.speculating {
+            let frame = self.frames.pop().expect("a frame per rule");
+            let stop = self.pos.checked_sub(1).map(|pos| self.visible[pos]);
+            let tokens = match stop {
+                Some(stop) if stop >= frame.start => frame.start..stop + 1,
+                _ => frame.start..frame.start,
+            };
+            let node = Node {
+                rule: frame.rule,
+                tokens,
+                children: frame.children,
+            };
+            if let Some(parent) = self.frames.last_mut() {
+                parent.children.push(Child::Node(node));
+            }
+        }
+        Ok(())
+    }
+
+    /// Call `rule` from `state`, where `follow` can come after it.
+    fn call(
+        &mut self,
+        state: u16,
+        follow: TokenSet,
+        rule: Rule,
+        body: fn(&mut Self) -> Parsed,
+    ) -> Parsed {
+        self.state = state;
+        self.follow.push(follow);
+        let result = self.rule(rule, body);
+        self.follow.pop();
+        self.state = state;
+        result
+    }
+
+    /// Match a token in `set` at `state`, where `after` can follow it.
+    fn expect(&mut self, state: u16, set: TokenSet, after: TokenSet) -> Parsed {
+        self.state = state;
+        self.next = set;
+        if set.contains(self.la(1)) {
+            self.end_error_condition();
+            self.consume();
+            return Ok(());
+        }
+        if self.speculating {
+            return Err(Failure::Mismatch { expected: None });
+        }
+        // Delete one extraneous token, if the one after it is expected.
+        if self.single_token_deletion() {
+            self.consume();
+            return Ok(());
+        }
+        // Assume one missing token, if the next one can follow it.
+        if self.in_context(after).contains(self.la(1)) {
+            self.report_missing_token();
+            return Ok(());
+        }
+        Err(Failure::Mismatch {
+            expected: self.skipped,
+        })
+    }
+
+    /// Make sure the next token is one `state` accepts before a decision,
+    /// recovering if it is not.
+    fn sync(&mut self, state: u16, decision: Decision, next: TokenSet) -> Parsed {
+        self.state = state;
+        self.next = next;
+        if self.recovering {
+            return Ok(());
+        }
+        if next.contains(self.la(1)) {
+            self.skipped = None;
+            return Ok(());
+        }
+        if next.has_epsilon() {
+            if self.skipped.is_none() {
+                self.skipped = Some(self.expected());
+            }
+            return Ok(());
+        }
+        if self.speculating {
+            return Err(Failure::Mismatch { expected: None });
+        }
+        match decision {
+            Decision::Enter => {
+                if self.single_token_deletion() {
+                    Ok(())
+                } else {
+                    Err(Failure::Mismatch { expected: None })
+                }
+            }
+            Decision::LoopBack => {
+                self.report_unwanted_token();
+                let expecting = self.expected();
+                self.consume_until(expecting.with(self.recovery_set()));
+                Ok(())
+            }
+        }
+    }
+
+    /// `schemaProperty*`: the loop's entry, call, and loop-back states, and
+    /// what it accepts, which is also what can follow each annotation.
+    fn annotations(&mut self, entry: u16, call: u16, back: u16, next: TokenSet) -> Parsed {
+        self.sync(entry, Decision::Enter, next)?;
+        while self.la(1) == Some(T::At) {
+            self.call(call, next, Rule::SchemaProperty, Self::schema_property)?;
+            self.sync(back, Decision::LoopBack, next)?;
+        }
+        Ok(())
+    }
+
+    // --------------------------------------------------------------------------
+    // Error Reporting and Recovery
+    // --------------------------------------------------------------------------
+
+    /// `set`, with ε replaced by what can follow the rules being parsed, as
+    /// far up as needed, or by the end of input at the root.
+    fn in_context(&self, set: TokenSet) -> TokenSet {
+        let mut result = set.without(TokenSet::EPSILON);
+        let mut open = set.has_epsilon();
+        for follow in self.follow.iter().rev() {
+            if !open {
+                break;
+            }
+            result = result.with(follow.without(TokenSet::EPSILON));
+            open = follow.has_epsilon();
+        }
+        if open { result.with(END) } else { result }
+    }
+
+    /// The tokens the current state expects, in context.
+    fn expected(&self) -> TokenSet {
+        self.in_context(self.next)
+    }
+
+    /// What recovery skips to: anything that can follow a rule being parsed.
+    fn recovery_set(&self) -> TokenSet {
+        self.follow
+            .iter()
+            .fold(TokenSet(0), |set, follow| set.with(*follow))
+            .without(TokenSet::EPSILON)
+    }
+
+    /// If the token after the next one is expected, report the next one as
+    /// extraneous and skip it.
+    fn single_token_deletion(&mut self) -> bool {
+        if !self.expected().contains(self.la(2)) {
+            return false;
+        }
+        self.report_unwanted_token();
+        self.consume();
+        self.end_error_condition();
+        true
+    }
+
+    fn end_error_condition(&mut self) {
+        self.recovering = false;
+        self.last_error = None;
+    }
+
+    /// Start recovering from an error, unless already recovering.
+    fn begin_error_condition(&mut self) -> bool {
+        if self.recovering {
+            return false;
+        }
+        self.recovering = true;
+        true
+    }
+
+    fn report_unwanted_token(&mut self) {
+        if self.begin_error_condition() {
+            let message = format!(
+                "extraneous input {} expecting {}",
+                self.token_display(self.pos),
+                self.expected().display()
+            );
+            self.report(self.pos, message);
+        }
+    }
+
+    fn report_missing_token(&mut self) {
+        if self.begin_error_condition() {
+            let message = format!(
+                "missing {} at {}",
+                self.expected().display(),
+                self.token_display(self.pos)
+            );
+            self.report(self.pos, message);
+        }
+    }
+
+    fn report_failure(&mut self, failure: Failure) {
+        if !self.begin_error_condition() {
+            return;
+        }
+        match failure {
+            Failure::Mismatch { expected } => {
+                let message = format!(
+                    "mismatched input {} expecting {}",
+                    self.token_display(self.pos),
+                    expected.unwrap_or_else(|| self.expected()).display()
+                );
+                self.report(self.pos, message);
+            }
+            Failure::NoViableAlt { start, at } => {
+                let input = if start < self.visible.len() {
+                    // Hidden doc comments are part of the input ANTLR shows.
+                    let first = self.token_index(start);
+                    let last = self.token_index(at).min(self.tokens.len() - 1);
+                    self.tokens[first..=last]
+                        .iter()
+                        .filter(|t| !t.token_type.is_trivia() || t.token_type == T::DocComment)
+                        .map(|t| &self.source[t.span.clone()])
+                        .collect()
+                } else {
+                    "<EOF>".to_string()
+                };
+                let message = format!("no viable alternative at input {}", quote(&input));
+                self.report(at, message);
+            }
+        }
+    }
+
+    fn report(&mut self, pos: usize, message: String) {
+        let (offset, length) = match self.visible.get(pos) {
+            Some(&i) => (self.tokens[i].span.start, self.tokens[i].span.len()),
+            None => (self.source.len(), 1),
+        };
+        let (line, column) = line_column(self.source, offset.min(self.source.len()));
+        self.errors.push(ParseError {
+            offset,
+            length,
+            line,
+            column,
+            message,
+        });
+    }
+
+    /// The token at `pos` as ANTLR shows it in messages.
+    fn token_display(&self, pos: usize) -> String {
+        match self.visible.get(pos) {
+            Some(&i) => quote(&self.source[self.tokens[i].span.clone()]),
+            None => "'<EOF>'".to_string(),
+        }
+    }
+
+    /// After a failure was reported, skip to a token that can follow a rule
+    /// being parsed. Failing again at the same place in the same state
+    /// skips one token first, so that recovery cannot loop.
+    fn recover(&mut self) {
+        let mut states = match self.last_error.take() {
+            Some((pos, states)) => {
+                if pos == self.pos && states.contains(&self.state) {
+                    self.consume();
+                }
+                states
+            }
+            None => Vec::new(),
+        };
+        states.push(self.state);
+        self.last_error = Some((self.pos, states));
+        self.consume_until(self.recovery_set());
+    }
+
+    // --------------------------------------------------------------------------
+    // Prediction
+    // --------------------------------------------------------------------------
+
+    /// Try each alternative from the current position, and pick the one
+    /// that matches furthest; the first, among ones that match to the end.
+    /// If several fail at the same furthest token, none is viable.
+    fn predict(&self, alternatives: &[fn(&mut Self) -> Parsed]) -> Result<usize, Failure> {
+        let mut best: Option<(usize, usize)> = None;
+        let mut tied = false;
+        for (alt, alternative) in alternatives.iter().enumerate() {
+            let reach = self.try_alternative(*alternative);
+            match best {
+                Some((_, furthest)) if reach < furthest => {}
+                Some((_, furthest)) if reach == furthest => tied = true,
+                _ => {
+                    best = Some((alt, reach));
+                    tied = false;
+                }
+            }
+        }
+        match best {
+            Some((_, reach)) if tied && reach != usize::MAX => Err(Failure::NoViableAlt {
+                start: self.pos,
+                at: reach,
+            }),
+            Some((alt, _)) => Ok(alt + 1),
+            None => Err(Failure::Mismatch { expected: None }),
+        }
+    }
+
+    /// How far `alternative` matches from the current position: the
+    /// position where it fails, or `usize::MAX` if it matches.
+    fn try_alternative(&self, alternative: fn(&mut Self) -> Parsed) -> usize {
+        let mut parser = Parser::new(self.source, self.tokens, self.visible, true, self.pos);
+        parser.follow = self.follow.clone();
+        match alternative(&mut parser) {
+            Ok(()) => usize::MAX,
+            Err(Failure::Mismatch { .. }) => parser.pos,
+            Err(Failure::NoViableAlt { at, .. }) => at,
+        }
+    }
+
+    /// The token after the annotations at the current position, for
+    /// decisions where only that keyword tells the alternatives apart.
+    fn after_annotations(&self) -> Result<Option<T>, Failure> {
+        let mut parser = Parser::new(self.source, self.tokens, self.visible, true, self.pos);
+        while parser.la(1) == Some(T::At) {
+            if let Err(failure) = parser.rule(Rule::SchemaProperty, Self::schema_property) {
+                let at = match failure {
+                    Failure::Mismatch { .. } => parser.pos,
+                    Failure::NoViableAlt { at, .. } => at,
+                };
+                return Err(Failure::NoViableAlt {
+                    start: self.pos,
+                    at,
+                });
+            }
+        }
+        Ok(parser.la(1))
+    }
+
+    /// A decision that no alternative matches, at the token after the
+    /// annotations.
+    fn no_viable_keyword(&self) -> Failure {
+        let mut at = self.pos;
+        let mut parser = Parser::new(self.source, self.tokens, self.visible, true, self.pos);
+        while parser.la(1) == Some(T::At)
+            && parser
+                .rule(Rule::SchemaProperty, Self::schema_property)
+                .is_ok()
+        {
+            at = parser.pos;
+        }
+        Failure::NoViableAlt {
+            start: self.pos,
+            at,
+        }
+    }
+
+    // --------------------------------------------------------------------------
+    // Grammar Rules
+    // --------------------------------------------------------------------------
+
+    /// `idlFile: protocolDeclaration | namespaceDeclaration?
+    /// mainSchemaDeclaration? (importStatement | namedSchemaDeclaration)*;
+    /// then an optional SUB and anything after it, and the end of input.`
+    fn idl_file(&mut self) -> Parsed {
+        self.sync(78, Decision::Enter, FILE_START)?;
+        let alt = match self.la(1) {
+            Some(T::Protocol) => 1,
+            // Both a protocol and a named schema can start with
+            // annotations; the keyword after them decides.
+            Some(T::At) => match self.after_annotations()? {
+                Some(T::Protocol) => 1,
+                Some(T::Enum | T::Fixed | T::Error | T::Record) => 2,
+                _ => return Err(self.no_viable_keyword()),
+            },
+            _ => 2,
+        };
+        if alt == 1 {
+            self.call(
+                64,
+                FILE_END,
+                Rule::ProtocolDeclaration,
+                Self::protocol_declaration,
+            )?;
+        } else {
+            self.sync(66, Decision::Enter, SCHEMA_FILE_START)?;
+            if self.la(1) == Some(T::Namespace) {
+                self.call(
+                    65,
+                    AFTER_NAMESPACE,
+                    Rule::NamespaceDeclaration,
+                    Self::namespace_declaration,
+                )?;
+            }
+            self.sync(69, Decision::Enter, AFTER_NAMESPACE)?;
+            if self.la(1) == Some(T::Schema) {
+                self.call(
+                    68,
+                    AFTER_MAIN_SCHEMA,
+                    Rule::MainSchemaDeclaration,
+                    Self::main_schema_declaration,
+                )?;
+            }
+            self.sync(75, Decision::Enter, AFTER_MAIN_SCHEMA)?;
+            while SCHEMA_FILE_ITEM.contains(self.la(1)) {
+                self.sync(73, Decision::Enter, SCHEMA_FILE_ITEM)?;
+                match self.la(1) {
+                    Some(T::Import) => {
+                        self.call(
+                            71,
+                            AFTER_MAIN_SCHEMA,
+                            Rule::ImportStatement,
+                            Self::import_statement,
+                        )?;
+                    }
+                    Some(T::DocComment | T::Enum | T::Fixed | T::Error | T::Record | T::At) => {
+                        self.call(
+                            72,
+                            AFTER_MAIN_SCHEMA,
+                            Rule::NamedSchemaDeclaration,
+                            Self::named_schema_declaration,
+                        )?;
+                    }
+                    _ => {
+                        return Err(Failure::NoViableAlt {
+                            start: self.pos,
+                            at: self.pos,
+                        });
+                    }
+                }
+                self.sync(77, Decision::LoopBack, AFTER_MAIN_SCHEMA)?;
+            }
+        }
+        self.sync(87, Decision::Enter, FILE_END)?;
+        if self.la(1) == Some(T::Sub) {
+            self.expect(
+                80,
+                set(&[T::Sub]),
+                TokenSet::range(T::Sub, T::IdentifierToken).with(END),
+            )?;
+            // Everything after SUB is ignored.
+            while self.la(1).is_some() {
+                self.consume();
+            }
+        }
+        self.expect(89, END, TokenSet::EPSILON)
+    }
+
+    /// `protocolDeclaration: DocComment? schemaProperty* Protocol identifier
+    /// protocolDeclarationBody;`
+    fn protocol_declaration(&mut self) -> Parsed {
+        self.sync(92, Decision::Enter, PROTOCOL_START)?;
+        self.annotations(97, 94, 99, set(&[T::Protocol, T::At]))?;
+        self.expect(100, set(&[T::Protocol]), NAME)?;
+        self.call(101, set(&[T::LBrace]), Rule::Identifier, Self::identifier)?;
+        self.call(
+            102,
+            TokenSet::EPSILON,
+            Rule::ProtocolDeclarationBody,
+            Self::protocol_declaration_body,
+        )
+    }
+
+    /// `protocolDeclarationBody: '{' (importStatement |
+    /// namedSchemaDeclaration | messageDeclaration)* '}';`
+    fn protocol_declaration_body(&mut self) -> Parsed {
+        self.expect(104, set(&[T::LBrace]), BODY)?;
+        self.sync(110, Decision::Enter, BODY)?;
+        while MEMBER_START.contains(self.la(1)) {
+            self.sync(108, Decision::Enter, MEMBER_START)?;
+            let alternatives: [fn(&mut Self) -> Parsed; 3] = [
+                |p| p.call(105, BODY, Rule::ImportStatement, Self::import_statement),
+                |p| {
+                    p.call(
+                        106,
+                        BODY,
+                        Rule::NamedSchemaDeclaration,
+                        Self::named_schema_declaration,
+                    )
+                },
+                |p| {
+                    p.call(
+                        107,
+                        BODY,
+                        Rule::MessageDeclaration,
+                        Self::message_declaration,
+                    )
+                },
+            ];
+            let alt = self.predict(&alternatives)?;
+            alternatives[alt - 1](self)?;
+            self.sync(112, Decision::LoopBack, BODY)?;
+        }
+        self.expect(113, set(&[T::RBrace]), TokenSet::EPSILON)
+    }
+
+    /// `namespaceDeclaration: Namespace identifier ';';`
+    fn namespace_declaration(&mut self) -> Parsed {
+        self.expect(115, set(&[T::Namespace]), NAME)?;
+        self.call(
+            116,
+            set(&[T::Semicolon]),
+            Rule::Identifier,
+            Self::identifier,
+        )?;
+        self.expect(117, set(&[T::Semicolon]), TokenSet::EPSILON)
+    }
+
+    /// `mainSchemaDeclaration: Schema fullType ';';`
+    fn main_schema_declaration(&mut self) -> Parsed {
+        self.expect(119, set(&[T::Schema]), TYPE_START)?;
+        self.call(120, set(&[T::Semicolon]), Rule::FullType, Self::full_type)?;
+        self.expect(121, set(&[T::Semicolon]), TokenSet::EPSILON)
+    }
+
+    /// `identifier: IdentifierToken | <any keyword>;`
+    fn identifier(&mut self) -> Parsed {
+        self.expect(123, NAME, TokenSet::EPSILON)
+    }
+
+    /// `schemaProperty: '@' identifier '(' jsonValue ')';`
+    fn schema_property(&mut self) -> Parsed {
+        self.expect(125, set(&[T::At]), NAME)?;
+        self.call(126, set(&[T::LParen]), Rule::Identifier, Self::identifier)?;
+        self.expect(127, set(&[T::LParen]), JSON_START)?;
+        self.call(128, set(&[T::RParen]), Rule::JsonValue, Self::json_value)?;
+        self.expect(129, set(&[T::RParen]), TokenSet::EPSILON)
+    }
+
+    /// `importStatement: Import (Schema | Protocol | IDL) StringLiteral ';';`
+    fn import_statement(&mut self) -> Parsed {
+        self.expect(131, set(&[T::Import]), IMPORT_KIND)?;
+        self.expect(132, IMPORT_KIND, set(&[T::StringLiteral]))?;
+        self.expect(133, set(&[T::StringLiteral]), set(&[T::Semicolon]))?;
+        self.expect(134, set(&[T::Semicolon]), TokenSet::EPSILON)
+    }
+
+    /// `namedSchemaDeclaration: fixedDeclaration | enumDeclaration |
+    /// recordDeclaration;`
+    fn named_schema_declaration(&mut self) -> Parsed {
+        self.sync(139, Decision::Enter, NAMED_SCHEMA_START)?;
+        let keyword = match self.la(1) {
+            Some(T::At) => self.after_annotations()?,
+            other => other,
+        };
+        match keyword {
+            Some(T::Fixed) => self.call(
+                136,
+                TokenSet::EPSILON,
+                Rule::FixedDeclaration,
+                Self::fixed_declaration,
+            ),
+            Some(T::Enum) => self.call(
+                137,
+                TokenSet::EPSILON,
+                Rule::EnumDeclaration,
+                Self::enum_declaration,
+            ),
+            Some(T::Error | T::Record) => self.call(
+                138,
+                TokenSet::EPSILON,
+                Rule::RecordDeclaration,
+                Self::record_declaration,
+            ),
+            _ => Err(self.no_viable_keyword()),
+        }
+    }
+
+    /// `fixedDeclaration: DocComment? schemaProperty* Fixed identifier '('
+    /// IntegerLiteral ')' ';';`
+    fn fixed_declaration(&mut self) -> Parsed {
+        self.sync(142, Decision::Enter, FIXED_START)?;
+        self.annotations(147, 144, 149, set(&[T::Fixed, T::At]))?;
+        self.expect(150, set(&[T::Fixed]), NAME)?;
+        self.call(151, set(&[T::LParen]), Rule::Identifier, Self::identifier)?;
+        self.expect(152, set(&[T::LParen]), set(&[T::IntegerLiteral]))?;
+        self.expect(153, set(&[T::IntegerLiteral]), set(&[T::RParen]))?;
+        self.expect(154, set(&[T::RParen]), set(&[T::Semicolon]))?;
+        self.expect(155, set(&[T::Semicolon]), TokenSet::EPSILON)
+    }
+
+    /// `enumDeclaration: DocComment? schemaProperty* Enum identifier '{'
+    /// (enumSymbol (',' enumSymbol)*)? '}' enumDefault?;`
+    fn enum_declaration(&mut self) -> Parsed {
+        let symbols_end = set(&[T::RBrace, T::Comma]);
+        self.sync(158, Decision::Enter, ENUM_START)?;
+        self.annotations(163, 160, 165, set(&[T::Enum, T::At]))?;
+        self.expect(166, set(&[T::Enum]), NAME)?;
+        self.call(167, set(&[T::LBrace]), Rule::Identifier, Self::identifier)?;
+        self.expect(168, set(&[T::LBrace]), BODY)?;
+        self.sync(177, Decision::Enter, BODY)?;
+        if MEMBER_START.contains(self.la(1)) {
+            self.call(169, symbols_end, Rule::EnumSymbol, Self::enum_symbol)?;
+            self.sync(174, Decision::Enter, symbols_end)?;
+            while self.la(1) == Some(T::Comma) {
+                self.expect(170, set(&[T::Comma]), MEMBER_START)?;
+                self.call(171, symbols_end, Rule::EnumSymbol, Self::enum_symbol)?;
+                self.sync(176, Decision::LoopBack, symbols_end)?;
+            }
+        }
+        let default = TokenSet::EPSILON.with(set(&[T::Equals]));
+        self.expect(179, set(&[T::RBrace]), default)?;
+        self.sync(181, Decision::Enter, default)?;
+        if self.la(1) == Some(T::Equals) {
+            self.call(
+                180,
+                TokenSet::EPSILON,
+                Rule::EnumDefault,
+                Self::enum_default,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// `enumSymbol: DocComment? schemaProperty* identifier;`
+    fn enum_symbol(&mut self) -> Parsed {
+        self.sync(184, Decision::Enter, MEMBER_START)?;
+        self.annotations(189, 186, 191, TYPE_START)?;
+        self.call(192, TokenSet::EPSILON, Rule::Identifier, Self::identifier)
+    }
+
+    /// `enumDefault: '=' identifier ';';`
+    fn enum_default(&mut self) -> Parsed {
+        self.expect(194, set(&[T::Equals]), NAME)?;
+        self.call(
+            195,
+            set(&[T::Semicolon]),
+            Rule::Identifier,
+            Self::identifier,
+        )?;
+        self.expect(196, set(&[T::Semicolon]), TokenSet::EPSILON)
+    }
+
+    /// `recordDeclaration: DocComment? schemaProperty* (Record | Error)
+    /// identifier recordBody;`
+    fn record_declaration(&mut self) -> Parsed {
+        self.sync(199, Decision::Enter, RECORD_START)?;
+        self.annotations(204, 201, 206, set(&[T::Error, T::Record, T::At]))?;
+        self.expect(207, RECORD_TYPE, NAME)?;
+        self.call(208, set(&[T::LBrace]), Rule::Identifier, Self::identifier)?;
+        self.call(209, TokenSet::EPSILON, Rule::RecordBody, Self::record_body)
+    }
+
+    /// `recordBody: '{' fieldDeclaration* '}';`
+    fn record_body(&mut self) -> Parsed {
+        self.expect(211, set(&[T::LBrace]), BODY)?;
+        self.sync(215, Decision::Enter, BODY)?;
+        while MEMBER_START.contains(self.la(1)) {
+            self.call(212, BODY, Rule::FieldDeclaration, Self::field_declaration)?;
+            self.sync(217, Decision::LoopBack, BODY)?;
+        }
+        self.expect(218, set(&[T::RBrace]), TokenSet::EPSILON)
+    }
+
+    /// `fieldDeclaration: DocComment? fullType variableDeclaration (','
+    /// variableDeclaration)* ';';`
+    fn field_declaration(&mut self) -> Parsed {
+        let variables_end = set(&[T::Semicolon, T::Comma]);
+        self.sync(221, Decision::Enter, MEMBER_START)?;
+        self.call(223, MEMBER_START, Rule::FullType, Self::full_type)?;
+        self.call(
+            224,
+            variables_end,
+            Rule::VariableDeclaration,
+            Self::variable_declaration,
+        )?;
+        self.sync(229, Decision::Enter, variables_end)?;
+        while self.la(1) == Some(T::Comma) {
+            self.expect(225, set(&[T::Comma]), MEMBER_START)?;
+            self.call(
+                226,
+                variables_end,
+                Rule::VariableDeclaration,
+                Self::variable_declaration,
+            )?;
+            self.sync(231, Decision::LoopBack, variables_end)?;
+        }
+        self.expect(232, set(&[T::Semicolon]), TokenSet::EPSILON)
+    }
+
+    /// `variableDeclaration: DocComment? schemaProperty* identifier ('='
+    /// jsonValue)?;`
+    fn variable_declaration(&mut self) -> Parsed {
+        let default = TokenSet::EPSILON.with(set(&[T::Equals]));
+        self.sync(235, Decision::Enter, MEMBER_START)?;
+        self.annotations(240, 237, 242, TYPE_START)?;
+        self.call(243, default, Rule::Identifier, Self::identifier)?;
+        self.sync(246, Decision::Enter, default)?;
+        if self.la(1) == Some(T::Equals) {
+            self.expect(244, set(&[T::Equals]), JSON_START)?;
+            self.call(245, TokenSet::EPSILON, Rule::JsonValue, Self::json_value)?;
+        }
+        Ok(())
+    }
+
+    /// `messageDeclaration: DocComment? schemaProperty* resultType
+    /// identifier '(' (formalParameter (',' formalParameter)*)? ')'
+    /// (Oneway | Throws identifier (',' identifier)*)? ';';`
+    fn message_declaration(&mut self) -> Parsed {
+        let parameters_end = set(&[T::RParen, T::Comma]);
+        let errors_end = set(&[T::Semicolon, T::Comma]);
+        self.sync(249, Decision::Enter, MEMBER_START)?;
+        self.annotations(254, 251, 256, TYPE_START)?;
+        self.call(257, NAME, Rule::ResultType, Self::result_type)?;
+        self.call(258, set(&[T::LParen]), Rule::Identifier, Self::identifier)?;
+        self.expect(259, set(&[T::LParen]), PARAMETERS)?;
+        self.sync(268, Decision::Enter, PARAMETERS)?;
+        if MEMBER_START.contains(self.la(1)) {
+            self.call(
+                260,
+                parameters_end,
+                Rule::FormalParameter,
+                Self::formal_parameter,
+            )?;
+            self.sync(265, Decision::Enter, parameters_end)?;
+            while self.la(1) == Some(T::Comma) {
+                self.expect(261, set(&[T::Comma]), MEMBER_START)?;
+                self.call(
+                    262,
+                    parameters_end,
+                    Rule::FormalParameter,
+                    Self::formal_parameter,
+                )?;
+                self.sync(267, Decision::LoopBack, parameters_end)?;
+            }
+        }
+        self.expect(270, set(&[T::RParen]), MESSAGE_END)?;
+        self.sync(281, Decision::Enter, MESSAGE_END)?;
+        match self.la(1) {
+            Some(T::Oneway) => self.expect(271, set(&[T::Oneway]), set(&[T::Semicolon]))?,
+            Some(T::Throws) => {
+                self.expect(272, set(&[T::Throws]), NAME)?;
+                self.call(273, errors_end, Rule::Identifier, Self::identifier)?;
+                self.sync(278, Decision::Enter, errors_end)?;
+                while self.la(1) == Some(T::Comma) {
+                    self.expect(274, set(&[T::Comma]), NAME)?;
+                    self.call(275, errors_end, Rule::Identifier, Self::identifier)?;
+                    self.sync(280, Decision::LoopBack, errors_end)?;
+                }
+            }
+            _ => {}
+        }
+        self.expect(283, set(&[T::Semicolon]), TokenSet::EPSILON)
+    }
+
+    /// `formalParameter: DocComment? fullType variableDeclaration;`
+    fn formal_parameter(&mut self) -> Parsed {
+        self.sync(286, Decision::Enter, MEMBER_START)?;
+        self.call(288, MEMBER_START, Rule::FullType, Self::full_type)?;
+        self.call(
+            289,
+            TokenSet::EPSILON,
+            Rule::VariableDeclaration,
+            Self::variable_declaration,
+        )
+    }
+
+    /// `resultType: Void | plainType;`
+    fn result_type(&mut self) -> Parsed {
+        self.sync(293, Decision::Enter, NAME)?;
+        // `void?` is an optional type named `void`; otherwise `void` is
+        // the keyword.
+        if self.la(1) == Some(T::Void) && self.la(2) != Some(T::QuestionMark) {
+            self.expect(291, set(&[T::Void]), TokenSet::EPSILON)
+        } else {
+            self.call(292, TokenSet::EPSILON, Rule::PlainType, Self::plain_type)
+        }
+    }
+
+    /// `fullType: schemaProperty* plainType;`
+    fn full_type(&mut self) -> Parsed {
+        self.annotations(298, 295, 300, TYPE_START)?;
+        self.call(301, TokenSet::EPSILON, Rule::PlainType, Self::plain_type)
+    }
+
+    /// `plainType: arrayType | mapType | unionType | nullableType;`
+    fn plain_type(&mut self) -> Parsed {
+        self.sync(307, Decision::Enter, NAME)?;
+        // The keywords can also be type names, unless followed by the
+        // bracket that opens their element type.
+        match (self.la(1), self.la(2)) {
+            (Some(T::Array), Some(T::LT)) => {
+                self.call(303, TokenSet::EPSILON, Rule::ArrayType, Self::array_type)
+            }
+            (Some(T::Map), Some(T::LT)) => {
+                self.call(304, TokenSet::EPSILON, Rule::MapType, Self::map_type)
+            }
+            (Some(T::Union), Some(T::LBrace)) => {
+                self.call(305, TokenSet::EPSILON, Rule::UnionType, Self::union_type)
+            }
+            _ => self.call(
+                306,
+                TokenSet::EPSILON,
+                Rule::NullableType,
+                Self::nullable_type,
+            ),
+        }
+    }
+
+    /// `nullableType: (primitiveType | identifier) '?'?;`
+    fn nullable_type(&mut self) -> Parsed {
+        let optional = TokenSet::EPSILON.with(set(&[T::QuestionMark]));
+        self.sync(311, Decision::Enter, NAME)?;
+        // `decimal` is only the primitive type with its `(precision)`.
+        let primitive = match self.la(1) {
+            Some(T::Decimal) => self.la(2) == Some(T::LParen),
+            token => PRIMITIVE.contains(token),
+        };
+        if primitive {
+            self.call(309, optional, Rule::PrimitiveType, Self::primitive_type)?;
+        } else {
+            self.call(310, optional, Rule::Identifier, Self::identifier)?;
+        }
+        self.sync(314, Decision::Enter, optional)?;
+        if self.la(1) == Some(T::QuestionMark) {
+            self.expect(313, set(&[T::QuestionMark]), TokenSet::EPSILON)?;
+        }
+        Ok(())
+    }
+
+    /// `primitiveType: Boolean | Int | ... | Decimal '(' IntegerLiteral (','
+    /// IntegerLiteral)? ')';`
+    fn primitive_type(&mut self) -> Parsed {
+        self.sync(325, Decision::Enter, PRIMITIVE_START)?;
+        match self.la(1) {
+            Some(T::Decimal) => {
+                let scale = set(&[T::RParen, T::Comma]);
+                self.expect(317, set(&[T::Decimal]), set(&[T::LParen]))?;
+                self.expect(318, set(&[T::LParen]), set(&[T::IntegerLiteral]))?;
+                self.expect(319, set(&[T::IntegerLiteral]), scale)?;
+                self.sync(322, Decision::Enter, scale)?;
+                if self.la(1) == Some(T::Comma) {
+                    self.expect(320, set(&[T::Comma]), set(&[T::IntegerLiteral]))?;
+                    self.expect(321, set(&[T::IntegerLiteral]), set(&[T::RParen]))?;
+                }
+                self.expect(324, set(&[T::RParen]), TokenSet::EPSILON)
+            }
+            token if PRIMITIVE.contains(token) => self.expect(316, PRIMITIVE, TokenSet::EPSILON),
+            _ => Err(Failure::NoViableAlt {
+                start: self.pos,
+                at: self.pos,
+            }),
+        }
+    }
+
+    /// `arrayType: Array '<' fullType '>';`
+    fn array_type(&mut self) -> Parsed {
+        self.expect(327, set(&[T::Array]), set(&[T::LT]))?;
+        self.expect(328, set(&[T::LT]), TYPE_START)?;
+        self.call(329, set(&[T::GT]), Rule::FullType, Self::full_type)?;
+        self.expect(330, set(&[T::GT]), TokenSet::EPSILON)
+    }
+
+    /// `mapType: Map '<' fullType '>';`
+    fn map_type(&mut self) -> Parsed {
+        self.expect(332, set(&[T::Map]), set(&[T::LT]))?;
+        self.expect(333, set(&[T::LT]), TYPE_START)?;
+        self.call(334, set(&[T::GT]), Rule::FullType, Self::full_type)?;
+        self.expect(335, set(&[T::GT]), TokenSet::EPSILON)
+    }
+
+    /// `unionType: Union '{' fullType (',' fullType)* '}';`
+    fn union_type(&mut self) -> Parsed {
+        let types_end = set(&[T::RBrace, T::Comma]);
+        self.expect(337, set(&[T::Union]), set(&[T::LBrace]))?;
+        self.expect(338, set(&[T::LBrace]), TYPE_START)?;
+        self.call(339, types_end, Rule::FullType, Self::full_type)?;
+        self.sync(344, Decision::Enter, types_end)?;
+        while self.la(1) == Some(T::Comma) {
+            self.expect(340, set(&[T::Comma]), TYPE_START)?;
+            self.call(341, types_end, Rule::FullType, Self::full_type)?;
+            self.sync(346, Decision::LoopBack, types_end)?;
+        }
+        self.expect(347, set(&[T::RBrace]), TokenSet::EPSILON)
+    }
+
+    /// `jsonValue: jsonObject | jsonArray | jsonLiteral;`
+    fn json_value(&mut self) -> Parsed {
+        self.sync(352, Decision::Enter, JSON_START)?;
+        match self.la(1) {
+            Some(T::LBrace) => {
+                self.call(349, TokenSet::EPSILON, Rule::JsonObject, Self::json_object)
+            }
+            Some(T::LBracket) => {
+                self.call(350, TokenSet::EPSILON, Rule::JsonArray, Self::json_array)
+            }
+            token if JSON_LITERAL.contains(token) => self.call(
+                351,
+                TokenSet::EPSILON,
+                Rule::JsonLiteral,
+                Self::json_literal,
+            ),
+            _ => Err(Failure::NoViableAlt {
+                start: self.pos,
+                at: self.pos,
+            }),
+        }
+    }
+
+    /// `jsonLiteral: Null | BTrue | BFalse | StringLiteral | IntegerLiteral |
+    /// FloatingPointLiteral;`
+    fn json_literal(&mut self) -> Parsed {
+        self.expect(354, JSON_LITERAL, TokenSet::EPSILON)
+    }
+
+    /// `jsonObject: '{' (jsonPair (',' jsonPair)*)? '}';`
+    fn json_object(&mut self) -> Parsed {
+        let pairs_end = set(&[T::RBrace, T::Comma]);
+        self.expect(356, set(&[T::LBrace]), set(&[T::RBrace, T::StringLiteral]))?;
+        self.sync(365, Decision::Enter, set(&[T::RBrace, T::StringLiteral]))?;
+        if self.la(1) == Some(T::StringLiteral) {
+            self.call(357, pairs_end, Rule::JsonPair, Self::json_pair)?;
+            self.sync(362, Decision::Enter, pairs_end)?;
+            while self.la(1) == Some(T::Comma) {
+                self.expect(358, set(&[T::Comma]), set(&[T::StringLiteral]))?;
+                self.call(359, pairs_end, Rule::JsonPair, Self::json_pair)?;
+                self.sync(364, Decision::LoopBack, pairs_end)?;
+            }
+        }
+        self.expect(367, set(&[T::RBrace]), TokenSet::EPSILON)
+    }
+
+    /// `jsonPair: StringLiteral ':' jsonValue;`
+    fn json_pair(&mut self) -> Parsed {
+        self.expect(369, set(&[T::StringLiteral]), set(&[T::Colon]))?;
+        self.expect(370, set(&[T::Colon]), JSON_START)?;
+        self.call(371, TokenSet::EPSILON, Rule::JsonValue, Self::json_value)
+    }
+
+    /// `jsonArray: '[' (jsonValue (',' jsonValue)*)? ']';`
+    fn json_array(&mut self) -> Parsed {
+        let values_end = set(&[T::RBracket, T::Comma]);
+        let contents = JSON_START.with(set(&[T::RBracket]));
+        self.expect(373, set(&[T::LBracket]), contents)?;
+        self.sync(382, Decision::Enter, contents)?;
+        if JSON_START.contains(self.la(1)) {
+            self.call(374, values_end, Rule::JsonValue, Self::json_value)?;
+            self.sync(379, Decision::Enter, values_end)?;
+            while self.la(1) == Some(T::Comma) {
+                self.expect(375, set(&[T::Comma]), JSON_START)?;
+                self.call(376, values_end, Rule::JsonValue, Self::json_value)?;
+                self.sync(381, Decision::LoopBack, values_end)?;
+            }
+        }
+        self.expect(384, set(&[T::RBracket]), TokenSet::EPSILON)
+    }
+}
+
+/// `text` as ANTLR quotes it in messages, with line breaks and tabs escaped.
+fn quote(text: &str) -> String {
+    let escaped = text
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t");
+    format!("'{escaped}'")
+}
@@ -3,51 +3,37 @@
 // ==============================================================================
 //
 // This module is the core of the Avro IDL parser. It takes a string containing
-// Avro IDL source, lexes and parses it via ANTLR, then walks the resulting
+// Avro IDL source, lexes and parses it (see `crate::parse_tree`, which picks
+// the ANTLR-generated or the hand-written parser), then walks the resulting
 // parse tree recursively to build our domain model (Protocol, AvroSchema, etc.).
 //
-// The generated parser defines token constants in lower_Camel_case (e.g.
-// `Idl_Boolean`). We suppress the naming warning for the whole module since
-// these constants appear extensively in match arms.
-#![expect(
-    non_upper_case_globals,
-    reason = "ANTLR-generated token constants use PascalCase"
-)]
-//
 // The Java reference implementation uses ANTLR's listener pattern with mutable
 // stacks. That approach is awkward in Rust due to lifetime constraints on trait
-// objects, so instead we set `build_parse_tree = true` and walk the tree with
-// plain recursive functions that return values. This is simpler and more
-// idiomatic Rust.
+// objects, so instead we walk the tree with plain recursive functions that
+// return values. This is simpler and more idiomatic Rust.
 
+use std::borrow::Cow;
 use std::cell::RefCell;
 use std::collections::HashSet;
-use std::rc::Rc;
-
-use std::borrow::Borrow;
-
-use antlr4rust::common_token_stream::CommonTokenStream;
-use antlr4rust::error_listener::ErrorListener;
-use antlr4rust::parser::Parser;
-use antlr4rust::recognizer::Recognizer;
-use antlr4rust::token::Token;
-use antlr4rust::token_factory::TokenFactory;
-use antlr4rust::token_stream::TokenStream;
-use antlr4rust::tree::{ParseTree, Tree};
-use antlr4rust::{InputStream, TidExt};
+use std::ops::Range;
+use std::sync::Arc;
+
+use indexmap::IndexMap;
 use serde_json::Value;
 use std::collections::HashMap;
 
 use crate::doc_comments::extract_doc_comment;
-use crate::error::{ParseDiagnostic, SpanWithSource};
-use crate::generated::idllexer::IdlLexer;
-use crate::generated::idlparser::*;
+use crate::error::{ErrorKind, ParseDiagnostic, SpanWithSource, keep_source};
+use crate::iso8601::iso8601_default;
+use crate::lexer::{self, LexError, Token, TokenType};
 use crate::model::protocol::{Message, Protocol};
 use crate::model::schema::{
-    AvroSchema, Field, FieldOrder, LogicalType, PRIMITIVE_TYPE_NAMES, parse_logical_type,
-    split_full_name, validate_default, validate_logical_type_on_fixed,
+    AvroSchema, Field, FieldOrder, LogicalType, PRIMITIVE_TYPE_NAMES, Properties,
+    max_decimal_precision, parse_logical_type, split_full_name, validate_default,
+    validate_logical_type_on_fixed,
 };
-use crate::resolve::is_valid_avro_name;
+use crate::parse_tree::{self, Node, ParseError, Rule};
+use crate::resolve::{is_valid_avro_name, reference_name};
 use miette::{Context, Result};
 
 // ==============================================================================
@@ -61,6 +47,7 @@ use miette::{Context, Result};
 /// source context highlighting via miette, similar to how parse errors show
 /// the offending token underlined.
 pub(crate) struct Warning {
+    pub(crate) kind: WarningKind,
     pub(crate) message: String,
     /// Source (file, source-code, offsets) of the problematic token
     pub(crate) span: Option<SpanWithSource>,
@@ -74,6 +61,7 @@ pub(crate) struct Warning {
 impl std::fmt::Debug for Warning {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Warning")
+            .field("kind", &self.kind)
             .field("message", &self.message)
             .field("file", &self.span.as_ref().map(|s| s.name))
             .field(
@@ -87,6 +75,119 @@ impl std::fmt::Debug for Warning {
     }
 }
 
+/// A category of warning, which can be suppressed with
+/// [`Idl::allow_warning`](crate::Idl::allow_warning).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WarningKind {
+    /// A doc comment (`/** ... */`) where no declaration can take it.
+    OutOfPlaceDoc,
+    /// A character the lexer does not recognize, which is skipped.
+    UnrecognizedToken,
+    /// Annotations on a union type, which are ignored.
+    UnionAnnotation,
+    /// An alias that is not a valid Avro name.
+    AliasName,
+    /// A declaration from a newer IDL grammar that was skipped (see
+    /// [`Idl::skip_unknown_declarations`](crate::Idl::skip_unknown_declarations)).
+    UnknownDeclaration,
+    /// A named type that nothing references (see
+    /// [`Idl::warn_unused_types`](crate::Idl::warn_unused_types)).
+    UnusedType,
+    /// A warning from an annotation handler (see
+    /// [`Idl::annotation_handler`](crate::Idl::annotation_handler)).
+    Annotation,
+    /// A record that always contains itself, so no value of it can exist.
+    RecursiveType,
+    /// A union nested in another union that was flattened into it (see
+    /// [`Idl::flatten_nested_unions`](crate::Idl::flatten_nested_unions)).
+    NestedUnion,
+    /// A namespace outside the required prefix (see
+    /// [`Idl::require_namespace_prefix`](crate::Idl::require_namespace_prefix)).
+    NamespacePrefix,
+    /// A name that breaks a naming convention (see
+    /// [`Idl::lint_names`](crate::Idl::lint_names)).
+    NamingConvention,
+    /// A union with more branches than allowed (see
+    /// [`Idl::max_union_branches`](crate::Idl::max_union_branches)).
+    UnionSize,
+    /// A nullable type not written in the required style (see
+    /// [`Idl::nullable_style`](crate::Idl::nullable_style)).
+    NullableStyle,
+    /// A declaration without a doc comment (see
+    /// [`Idl::require_docs`](crate::Idl::require_docs)).
+    MissingDoc,
+    /// A field default that does not match the field's type, accepted
+    /// because validation is off (see
+    /// [`Idl::validate_defaults`](crate::Idl::validate_defaults)).
+    InvalidDefault,
+}
+
+impl WarningKind {
+    /// Every warning kind, in the order listed above.
+    pub const ALL: [WarningKind; 15] = [
+        WarningKind::OutOfPlaceDoc,
+        WarningKind::UnrecognizedToken,
+        WarningKind::UnionAnnotation,
+        WarningKind::AliasName,
+        WarningKind::UnknownDeclaration,
+        WarningKind::UnusedType,
+        WarningKind::Annotation,
+        WarningKind::RecursiveType,
+        WarningKind::NestedUnion,
+        WarningKind::NamespacePrefix,
+        WarningKind::NamingConvention,
+        WarningKind::UnionSize,
+        WarningKind::NullableStyle,
+        WarningKind::MissingDoc,
+        WarningKind::InvalidDefault,
+    ];
+
+    /// The name used on the command line, e.g. `"out-of-place-doc"`.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WarningKind::OutOfPlaceDoc => "out-of-place-doc",
+            WarningKind::UnrecognizedToken => "unrecognized-token",
+            WarningKind::UnionAnnotation => "union-annotation",
+            WarningKind::AliasName => "alias-name",
+            WarningKind::UnknownDeclaration => "unknown-declaration",
+            WarningKind::UnusedType => "unused-type",
+            WarningKind::Annotation => "annotation",
+            WarningKind::RecursiveType => "recursive-type",
+            WarningKind::NestedUnion => "nested-union",
+            WarningKind::NamespacePrefix => "namespace-prefix",
+            WarningKind::NamingConvention => "naming-convention",
+            WarningKind::UnionSize => "union-size",
+            WarningKind::NullableStyle => "nullable-style",
+            WarningKind::MissingDoc => "missing-doc",
+            WarningKind::InvalidDefault => "invalid-default",
+        }
+    }
+
+    /// The stable diagnostic code, e.g. `"AVDL0101"` (see
+    /// [`explain`](crate::explain)).
+    #[must_use]
+    pub fn code(self) -> &'static str {
+        match self {
+            WarningKind::OutOfPlaceDoc => "AVDL0101",
+            WarningKind::UnrecognizedToken => "AVDL0102",
+            WarningKind::UnionAnnotation => "AVDL0103",
+            WarningKind::AliasName => "AVDL0104",
+            WarningKind::UnknownDeclaration => "AVDL0105",
+            WarningKind::UnusedType => "AVDL0106",
+            WarningKind::Annotation => "AVDL0107",
+            WarningKind::RecursiveType => "AVDL0108",
+            WarningKind::NestedUnion => "AVDL0109",
+            WarningKind::NamespacePrefix => "AVDL0110",
+            WarningKind::NamingConvention => "AVDL0111",
+            WarningKind::UnionSize => "AVDL0112",
+            WarningKind::NullableStyle => "AVDL0113",
+            WarningKind::MissingDoc => "AVDL0114",
+            WarningKind::InvalidDefault => "AVDL0115",
+        }
+    }
+}
+
 impl Warning {
     /// Create an out-of-place doc comment warning with line and column info.
     ///
@@ -94,36 +195,20 @@ impl Warning {
     ///   "Line %d, char %d: Ignoring out-of-place documentation comment.\n
     ///    Did you mean to use a multiline comment ( /* ... */ ) instead?"
     ///
-    /// `token_start` and `token_stop` are the inclusive byte offsets from
-    /// `Token::get_start()` / `Token::get_stop()`.
-    fn out_of_place_doc_comment(
-        line: isize,
-        column: isize,
-        src: &SourceInfo,
-        token_start: isize,
-        token_stop: isize,
-    ) -> Self {
-        let (offset, length) = if token_start >= 0 && token_stop >= token_start {
-            (
-                token_start as usize,
-                (token_stop - token_start + 1) as usize,
-            )
-        } else if token_start >= 0 {
-            (token_start as usize, 1)
-        } else {
-            (0, 0)
-        };
-
+    /// `line` is 1-based and `column` 0-based, as `lexer::line_column`
+    /// counts them.
+    fn out_of_place_doc_comment(line: usize, column: usize, span: SpanWithSource) -> Self {
         Warning {
+            kind: WarningKind::OutOfPlaceDoc,
             message: format!(
                 "Line {}, char {}: Ignoring out-of-place documentation comment.\n\
                  Did you mean to use a multiline comment ( /* ... */ ) instead?",
                 line,
-                // Java uses getCharPositionInLine() + 1 (1-based); ANTLR's
-                // get_column() is 0-based, so we add 1 to match.
+                // Java uses getCharPositionInLine() + 1 (1-based); our
+                // column is 0-based, so we add 1 to match.
                 column + 1,
             ),
-            span: Some(src.span(offset, length)),
+            span: Some(span),
         }
     }
 
@@ -133,36 +218,18 @@ impl Warning {
     /// annotations in Avro — any `@name(value)` annotations placed on them
     /// are silently discarded. Java also drops them without feedback. This
     /// warning lets the user know their annotations had no effect.
-    fn annotations_dropped_on_union<'a>(
+    fn annotations_dropped_on_union(
         annotation_keys: &[&str],
         src: &SourceInfo,
-        ctx: &impl antlr4rust::parser_rule_context::ParserRuleContext<'a>,
+        ctx: &Node,
     ) -> Self {
-        let start_token = ctx.start();
-        let stop_token = ctx.stop();
-        let offset = start_token.get_start();
-        let stop = {
-            let candidate = stop_token.get_stop();
-            if candidate >= offset {
-                candidate
-            } else {
-                start_token.get_stop()
-            }
-        };
-        let (byte_offset, length): (usize, usize) = if offset >= 0 && stop >= offset {
-            (offset as usize, (stop - offset + 1) as usize)
-        } else if offset >= 0 {
-            (offset as usize, 1)
-        } else {
-            (0, 0)
-        };
-
         let keys_display = annotation_keys.join(", ");
         Warning {
+            kind: WarningKind::UnionAnnotation,
             message: format!(
                 "Annotations on union types are not supported and will be ignored: {keys_display}"
             ),
-            span: Some(src.span(byte_offset, length)),
+            span: Some(context_span(src, ctx)),
         }
     }
 
@@ -171,38 +238,26 @@ impl Warning {
     /// The Avro specification says: "Aliases should be valid names, but this is
     /// not required: any string is accepted as an alias." We accept the alias
     /// but warn the user that it doesn't follow the standard naming rules.
-    fn non_standard_alias_name<'a>(
-        alias: &str,
-        src: &SourceInfo,
-        ctx: &impl antlr4rust::parser_rule_context::ParserRuleContext<'a>,
-    ) -> Self {
-        let start_token = ctx.start();
-        let stop_token = ctx.stop();
-        let offset = start_token.get_start();
-        let stop = {
-            let candidate = stop_token.get_stop();
-            if candidate >= offset {
-                candidate
-            } else {
-                start_token.get_stop()
-            }
-        };
-        let (byte_offset, length): (usize, usize) = if offset >= 0 && stop >= offset {
-            (offset as usize, (stop - offset + 1) as usize)
-        } else if offset >= 0 {
-            (offset as usize, 1)
-        } else {
-            (0, 0)
-        };
-
+    fn non_standard_alias_name(alias: &str, src: &SourceInfo, ctx: &Node) -> Self {
         Warning {
+            kind: WarningKind::AliasName,
             message: format!(
                 "Alias \"{alias}\" is not a valid Avro name. \
                  While any string is accepted to allow schema evolution to \
                  correct illegal names in old schemata, aliases should \
                  ideally be valid names."
             ),
-            span: Some(src.span(byte_offset, length)),
+            span: Some(context_span(src, ctx)),
+        }
+    }
+
+    /// Create a warning that spans the whole parse tree node, such as an
+    /// entire `@name(value)` annotation.
+    fn spanning(kind: WarningKind, message: String, src: &SourceInfo, ctx: &Node) -> Self {
+        Warning {
+            kind,
+            message,
+            span: Some(context_span(src, ctx)),
         }
     }
 }
@@ -218,6 +273,10 @@ impl std::error::Error for Warning {}
 /// Implements `miette::Diagnostic` so warnings with source spans render with
 /// underlined source context, matching how parse errors already display.
 impl miette::Diagnostic for Warning {
+    fn code<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        Some(Box::new(self.kind.code()))
+    }
+
     fn severity(&self) -> Option<miette::Severity> {
         Some(miette::Severity::Warning)
     }
@@ -238,6 +297,14 @@ impl miette::Diagnostic for Warning {
             "annotations ignored here"
         } else if self.message.contains("is not a valid Avro name") {
             "non-standard alias name"
+        } else if self.message.contains("is never referenced") {
+            "unused type"
+        } else if self.message.starts_with("Invalid default") {
+            "invalid default"
+        } else if self.message.contains("outside the required prefix") {
+            "namespace declared here"
+        } else if self.message.contains(" name `") {
+            "name declared here"
         } else {
             "here"
         };
@@ -256,8 +323,8 @@ impl miette::Diagnostic for Warning {
 // immediately. ANTLR's default `ConsoleErrorListener` only prints to stderr
 // and lets error recovery continue, which silently produces incorrect output.
 //
-// We replace the default listener with `CollectingErrorListener`, which records
-// each syntax error's line/column/message. After parsing, we check the
+// Both parsers instead collect every syntax error with its line, column, and
+// ANTLR's message (see `crate::parse_tree`). After parsing, we check the
 // collected errors and return an error if any were found.
 
 /// A collected ANTLR syntax error with byte offset information for source
@@ -275,6 +342,8 @@ struct SyntaxError {
     /// Additional help text (e.g., the full expected-token list when the main
     /// message has been simplified).
     help: Option<String>,
+    /// Text that fixes the error when it replaces the offending token.
+    replacement: Option<String>,
 }
 
 // ==========================================================================
@@ -303,6 +372,9 @@ struct EnrichedError {
     /// Additional help text displayed below the error (e.g., the full
     /// expected-token list when the main message was simplified).
     help: Option<String>,
+    /// Text that fixes the error when it replaces the offending token, such
+    /// as a bare identifier quoted where a string is expected.
+    replacement: Option<String>,
 }
 
 /// Rewrites known ANTLR error patterns into more user-friendly messages.
@@ -334,6 +406,7 @@ fn enrich_antlr_error(msg: &str) -> Option<EnrichedError> {
                 ),
                 label: Some(format!("unexpected `@` before `{kw}`")),
                 help: None,
+                replacement: None,
             });
         }
         return Some(EnrichedError {
@@ -343,6 +416,7 @@ fn enrich_antlr_error(msg: &str) -> Option<EnrichedError> {
             ),
             label: None,
             help: None,
+            replacement: None,
         });
     }
 
@@ -361,6 +435,7 @@ fn enrich_antlr_error(msg: &str) -> Option<EnrichedError> {
                 .to_string(),
             label: Some("missing import kind".to_string()),
             help: None,
+            replacement: None,
         });
     }
 
@@ -383,6 +458,7 @@ fn enrich_antlr_error(msg: &str) -> Option<EnrichedError> {
                 ),
                 label: Some(format!("did you mean `{suggestion}`?")),
                 help: None,
+                replacement: None,
             });
         }
     }
@@ -400,6 +476,7 @@ fn enrich_antlr_error(msg: &str) -> Option<EnrichedError> {
             message: format!("{msg} (annotations require `@name(value)` syntax)"),
             label: None,
             help: None,
+            replacement: None,
         });
     }
 
@@ -538,12 +615,14 @@ fn build_unexpected_token_error(
                 message: "unexpected end of file -- missing closing `}`".to_string(),
                 label: Some("missing closing `}`".to_string()),
                 help: None,
+                replacement: None,
             };
         }
         return EnrichedError {
             message: "unexpected end of file".to_string(),
             label: Some("unexpected end of file".to_string()),
             help,
+            replacement: None,
         };
     }
 
@@ -558,6 +637,7 @@ fn build_unexpected_token_error(
                 "unexpected `{offending}` -- did you mean `\"{offending}\"`?"
             )),
             help,
+            replacement: Some(format!("\"{offending}\"")),
         };
     }
 
@@ -572,6 +652,7 @@ fn build_unexpected_token_error(
             message: format!("unexpected token `{offending}` -- did you mean `{suggestion}`?"),
             label: Some(format!("did you mean `{suggestion}`?")),
             help,
+            replacement: None,
         };
     }
 
@@ -579,6 +660,7 @@ fn build_unexpected_token_error(
         message: format!("unexpected token `{offending}`"),
         label: Some(format!("unexpected `{offending}`")),
         help,
+        replacement: None,
     }
 }
 
@@ -1190,6 +1272,7 @@ fn refine_errors_with_source(errors: &[SyntaxError], source: &str) -> Option<Vec
                 message: e.message.clone(),
                 label: e.label.clone(),
                 help: e.help.clone(),
+                replacement: e.replacement.clone(),
             })
             .collect();
         result.push(refined);
@@ -1243,6 +1326,7 @@ fn detect_empty_union(error: &SyntaxError, source: &str) -> Option<SyntaxError>
         help: Some(
             "add at least one type inside the braces, e.g., `union { null, string }`".to_string(),
         ),
+        replacement: None,
     })
 }
 
@@ -1293,6 +1377,7 @@ fn detect_empty_type_parameter(error: &SyntaxError, source: &str) -> Option<Synt
         message: format!("{line_prefix}`{keyword}` type requires a type parameter"),
         label: Some("missing type inside `<>`".to_string()),
         help: Some(format!("specify the value type, e.g., `{example}`")),
+        replacement: None,
     })
 }
 
@@ -1422,6 +1507,7 @@ fn detect_missing_close_brace_before_declaration(
         message: msg,
         label: Some(label_text),
         help: Some("add a closing `}` before the next type declaration".to_string()),
+        replacement: None,
     })
 }
 
@@ -1477,6 +1563,7 @@ fn detect_fixed_non_integer(error: &SyntaxError, source: &str) -> Option<SyntaxE
         ),
         label: Some(format!("`{bad_size}` is not an integer")),
         help: None,
+        replacement: None,
     })
 }
 
@@ -1534,6 +1621,7 @@ fn detect_missing_name(error: &SyntaxError, source: &str) -> Option<SyntaxError>
                 "add a name, e.g., `{keyword} My{capitalized} {{ ... }}`"
             ))
         },
+        replacement: None,
     })
 }
 
@@ -1603,6 +1691,7 @@ fn detect_trailing_comma_in_enum(error: &SyntaxError, source: &str) -> Option<Sy
         message: format!("{line_prefix}trailing comma is not allowed in enum declaration"),
         label: Some("trailing comma".to_string()),
         help: Some(hint),
+        replacement: None,
     })
 }
 
@@ -1661,6 +1750,7 @@ fn detect_misspelled_keyword(error: &SyntaxError, source: &str) -> Option<Syntax
             ),
             label: Some(format!("did you mean `{suggestion}`?")),
             help: None,
+            replacement: None,
         });
     }
 
@@ -1730,6 +1820,7 @@ fn detect_unclosed_brace(error: &SyntaxError, source: &str) -> Option<SyntaxErro
         message: msg,
         label: Some(label_text),
         help: Some("add a closing `}` to match this opening brace".to_string()),
+        replacement: None,
     })
 }
 
@@ -1810,81 +1901,40 @@ fn extract_line_prefix(msg: &str) -> &str {
     ""
 }
 
-/// Convert 1-based `line` and 0-based `column` (as reported by ANTLR) to a
-/// byte offset into `source`. Returns 0 if the coordinates are out of range.
-fn line_col_to_byte_offset(source: &str, line: isize, column: isize) -> usize {
-    if line <= 0 || column < 0 {
-        return 0;
-    }
-    let target_line = (line - 1) as usize; // convert to 0-based
-    let mut offset = 0;
-    for (i, src_line) in source.split('\n').enumerate() {
-        if i == target_line {
-            return offset + (column as usize).min(src_line.len());
-        }
-        offset += src_line.len() + 1; // +1 for the newline character
+impl SyntaxError {
+    /// A syntax error the parser reported.
+    fn from_parse_error(error: &ParseError) -> Self {
+        SyntaxError::new(
+            error.offset,
+            error.length,
+            error.line,
+            error.column,
+            &error.message,
+        )
     }
-    0
-}
 
-/// An ANTLR error listener that collects syntax errors into a shared `Vec`
-/// instead of printing them to stderr. This lets us detect parse errors after
-/// `parser.idlFile()` returns and fail with a proper error.
-///
-/// The optional `source` field holds the original input text, enabling the
-/// listener to compute byte offsets from ANTLR's (line, column) when no
-/// offending token is available (lexer errors).
-struct CollectingErrorListener {
-    errors: Rc<RefCell<Vec<SyntaxError>>>,
-    /// Original source text for line/column-to-byte-offset conversion.
-    /// `None` for parser errors where the offending token always provides
-    /// byte offsets directly.
-    source: Option<&'static str>,
-}
-
-impl<'a, T: Recognizer<'a>> ErrorListener<'a, T> for CollectingErrorListener {
-    fn syntax_error(
-        &self,
-        _recognizer: &T,
-        offending_symbol: Option<&<T::TF as TokenFactory<'a>>::Inner>,
-        line: isize,
-        column: isize,
-        msg: &str,
-        _error: Option<&antlr4rust::errors::ANTLRError>,
-    ) {
-        // Extract byte offsets from the offending token when available. These
-        // give us a precise source span for miette to underline. When the token
-        // is absent (e.g., lexer errors), we compute the offset from the
-        // line/column parameters using the stored source text.
-        let (offset, length) = offending_symbol
-            .map(|tok| {
-                let start = tok.get_start();
-                let stop = tok.get_stop();
-                if start >= 0 && stop >= start {
-                    (start as usize, (stop - start + 1) as usize)
-                } else if start >= 0 {
-                    (start as usize, 1)
-                } else {
-                    (0, 0)
-                }
-            })
-            .unwrap_or_else(|| {
-                // Lexer errors have no offending token. Fall back to computing
-                // the byte offset from (line, column) using the source text.
-                if let Some(src) = self.source {
-                    let offset = line_col_to_byte_offset(src, line, column);
-                    (offset, 1)
-                } else {
-                    (0, 0)
-                }
-            });
+    /// Characters the lexer skipped. The span is their first character.
+    fn from_lex_error(error: &LexError, source: &str) -> Self {
+        let length = source[error.span.start..]
+            .chars()
+            .next()
+            .map_or(1, char::len_utf8);
+        SyntaxError::new(
+            error.span.start,
+            length,
+            error.line,
+            error.column,
+            &error.message,
+        )
+    }
 
+    fn new(offset: usize, length: usize, line: usize, column: usize, msg: &str) -> Self {
         // Try to enrich the raw ANTLR message with a more user-friendly
         // explanation. Fall back to the original if no pattern matches.
         let enriched = enrich_antlr_error(msg);
 
-        let (display_msg, label, help) = match enriched {
-            Some(e) => (e.message, e.label, e.help),
+        let (display_msg, label, help, replacement) = match enriched {
+            Some(e) => (e.message, e.label, e.help, e.replacement),
             // No enrichment pattern matched; sanitize the raw ANTLR message
             // to remove internal tokens like `'\u001A'` and replace `<EOF>`
             // with "end of file", then humanize internal token names like
@@ -1893,16 +1943,18 @@ impl<'a, T: Recognizer<'a>> ErrorListener<'a, T> for CollectingErrorListener {
                 humanize_antlr_message(&sanitize_antlr_message(msg)),
                 None,
                 None,
+                None,
             ),
         };
 
-        self.errors.borrow_mut().push(SyntaxError {
+        SyntaxError {
             offset,
             length,
             message: format!("line {line}:{column} {display_msg}"),
             label,
             help,
-        });
+            replacement,
+        }
     }
 }
 
@@ -1952,13 +2004,26 @@ pub struct ImportEntry {
 }
 
 /// The kind of import statement.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ImportKind {
     Idl,
     Protocol,
     Schema,
 }
 
+impl ImportKind {
+    /// The keyword used after `import` in IDL source (`idl`, `protocol`, or
+    /// `schema`).
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ImportKind::Idl => "idl",
+            ImportKind::Protocol => "protocol",
+            ImportKind::Schema => "schema",
+        }
+    }
+}
+
 /// A declaration item in source order. Captures both import statements and
 /// local type definitions interleaved exactly as they appear in the IDL file.
 /// This preserves the declaration order so that the caller can register types
@@ -1986,6 +2051,221 @@ pub enum DeclItem {
     ),
 }
 
+/// How defaults on message request parameters are handled.
+///
+/// Java's `IdlReader` builds request parameters through the same
+/// `variableDeclaration` rule as record fields, so their defaults are
+/// validated and emitted in exactly the same way. Some consumers expect
+/// request parameters to be treated more loosely, since a parameter default
+/// has no effect on the wire format.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParamDefaults {
+    /// Validate parameter defaults against the parameter type and emit them,
+    /// exactly like record field defaults. This matches `avro-tools`.
+    #[default]
+    Validate,
+    /// Emit parameter defaults as written, without validating them.
+    Unchecked,
+    /// Drop parameter defaults from the output, as if they had not been
+    /// written. Nothing is validated.
+    Omit,
+}
+
+/// Options that change how the tree walk interprets a source file.
+///
+/// Carried on `SourceInfo` so walk functions can consult them without an
+/// extra parameter on every call.
+#[derive(Debug, Clone)]
+pub(crate) struct ReaderOptions {
+    pub(crate) param_defaults: ParamDefaults,
+    /// Skip declarations that start with an unknown word and are followed by
+    /// a braced block, warning instead of failing. See
+    /// `find_unknown_declarations`.
+    pub(crate) skip_unknown_declarations: bool,
+    /// Convert ISO-8601 string defaults of date and time fields to the
+    /// numbers Avro stores. See `walk_variable`.
+    pub(crate) iso8601_defaults: bool,
+    /// Flatten a union nested directly in another union into it, warning,
+    /// instead of rejecting it. See `walk_union_type`.
+    pub(crate) flatten_nested_unions: bool,
+    /// Callbacks for custom annotations, run in `walk_schema_properties`.
+    pub(crate) annotation_handlers: AnnotationHandlers,
+    /// Convert CRLF line endings to LF before parsing. Applied by the
+    /// compiler (see `normalize_line_endings`), not by `parse_idl_named`, so
+    /// that spans line up with the source it renders diagnostics against.
+    pub(crate) normalize_line_endings: bool,
+    /// How deeply brackets may nest in a file, and types through the types
+    /// they reference. See `check_nesting_depth`.
+    pub(crate) max_nesting_depth: usize,
+    /// Turn a valid `@logicalType` annotation into the logical type it names.
+    /// When off, the annotation stays a plain property. See
+    /// `try_promote_logical_type`.
+    pub(crate) promote_logical_types: bool,
+    /// Reject field defaults that do not match the field's type. When off,
+    /// they are reported as `InvalidDefault` warnings instead. The compiler
+    /// reads this too, for the defaults it validates after resolution.
+    pub(crate) validate_defaults: bool,
+}
+
+impl Default for ReaderOptions {
+    fn default() -> Self {
+        ReaderOptions {
+            param_defaults: ParamDefaults::default(),
+            skip_unknown_declarations: false,
+            iso8601_defaults: false,
+            flatten_nested_unions: false,
+            annotation_handlers: AnnotationHandlers::default(),
+            normalize_line_endings: false,
+            max_nesting_depth: DEFAULT_MAX_NESTING_DEPTH,
+            promote_logical_types: true,
+            validate_defaults: true,
+        }
+    }
+}
+
+/// A callback registered with
+/// [`Idl::annotation_handler`](crate::Idl::annotation_handler).
+pub(crate) type AnnotationHandler =
+    Arc<dyn Fn(&mut Annotation<'_>) -> Result<(), String> + Send + Sync>;
+
+/// Annotation handlers keyed by annotation name, in registration order.
+#[derive(Clone, Default)]
+pub(crate) struct AnnotationHandlers(Vec<(String, AnnotationHandler)>);
+
+impl AnnotationHandlers {
+    pub(crate) fn insert(&mut self, name: String, handler: AnnotationHandler) {
+        self.0.push((name, handler));
+    }
+
+    fn get(&self, name: &str) -> impl Iterator<Item = &AnnotationHandler> {
+        self.0
+            .iter()
+            .filter(move |(n, _)| n == name)
+            .map(|(_, handler)| handler)
+    }
+}
+
+/// Handlers are closures, so only their annotation names are shown.
+impl std::fmt::Debug for AnnotationHandlers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.0.iter().map(|(n, _)| n))
+            .finish()
+    }
+}
+
+/// A custom annotation, such as `@pii(true)`, as seen by an annotation
+/// handler.
+///
+/// The handler can inspect the value, replace it, remove the annotation
+/// from the output, or report warnings. Returning `Err` from the handler
+/// fails compilation with an error that points at the annotation.
+#[derive(Debug)]
+pub struct Annotation<'a> {
+    name: &'a str,
+    value: Option<Value>,
+    warnings: Vec<String>,
+}
+
+impl Annotation<'_> {
+    /// The annotation's name, without the `@`.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    /// The annotation's value, or `None` if a handler removed it.
+    #[must_use]
+    pub fn value(&self) -> Option<&Value> {
+        self.value.as_ref()
+    }
+
+    /// Replace the value that is written to the output.
+    pub fn set_value(&mut self, value: Value) {
+        self.value = Some(value);
+    }
+
+    /// Leave the annotation out of the output.
+    pub fn remove(&mut self) {
+        self.value = None;
+    }
+
+    /// Report a warning that points at the annotation.
+    pub fn warn(&mut self, message: impl Into<String>) {
+        self.warnings.push(message.into());
+    }
+}
+
+// ==============================================================================
+// Nesting Depth Limit
+// ==============================================================================
+//
+// The ANTLR parser, the tree walker, and the JSON serializer all recurse once
+// per level of nesting, so deeply nested input (such as fuzzer output) would
+// overflow the stack instead of failing. Counting brackets before parsing
+// bounds the nesting within a file; `too_deeply_nested` in graph.rs bounds
+// nesting through references to other types, which serialization inlines.
+
+/// The default for `ReaderOptions::max_nesting_depth`, the same as the
+/// recursion limit `serde_json` applies to JSON input.
+pub(crate) const DEFAULT_MAX_NESTING_DEPTH: usize = 128;
+
+/// Fail at the first bracket (`{`, `[`, `(`, or `<`) that opens more than
+/// `max_depth` levels, ignoring brackets in strings and comments.
+fn check_nesting_depth(
+    input: &'static str,
+    source_name: &'static str,
+    max_depth: usize,
+) -> Result<()> {
+    let bytes = input.as_bytes();
+    let skip_past = |from: usize, end: &str| {
+        input[from..]
+            .find(end)
+            .map_or(bytes.len(), |at| from + at + end.len())
+    };
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+            }
+            b'`' => i = skip_past(i + 1, "`") - 1,
+            b'/' if bytes.get(i + 1) == Some(&b'/') => i = skip_past(i, "\n") - 1,
+            b'/' if bytes.get(i + 1) == Some(&b'*') => i = skip_past(i + 2, "*/") - 1,
+            b'{' | b'[' | b'(' | b'<' => {
+                depth += 1;
+                if depth > max_depth {
+                    return Err(ParseDiagnostic {
+                        kind: ErrorKind::NestingTooDeep,
+                        span: SpanWithSource::new(i, 1, source_name, input),
+                        message: format!(
+                            "nesting too deep: more than {max_depth} levels of brackets"
+                        ),
+                        label: Some("too deeply nested".to_string()),
+                        help: Some(
+                            "raise the limit with `--max-nesting-depth` or \
+                             `Idl::max_nesting_depth`"
+                                .to_string(),
+                        ),
+                        related: Vec::new(),
+                        name: None,
+                        replacement: None,
+                    }
+                    .into());
+                }
+            }
+            b'}' | b']' | b')' | b'>' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        i += 1;
+    }
+    Ok(())
+}
+
 /// Test-only wrapper around [`parse_idl_named`] that normalizes CRLF line
 /// endings to LF before parsing. This ensures byte offsets in ANTLR tokens
 /// (and therefore in `SourceSpan` error diagnostics) are consistent in tests
@@ -1995,15 +2275,22 @@ pub enum DeclItem {
 /// [`parse_idl_named`] directly.
 #[cfg(test)]
 pub fn parse_idl_for_test(input: &'static str) -> Result<(IdlFile, Vec<DeclItem>, Vec<Warning>)> {
-    let normalized;
-    let input = if input.contains("\r\n") {
-        normalized = input.replace("\r\n", "\n");
-        normalized.leak()
+    parse_idl_named(
+        normalize_line_endings(input),
+        "<input>",
+        &ReaderOptions::default(),
+    )
+}
+
+/// Replace CRLF line endings with LF, so that doc comments and byte offsets do
+/// not depend on how the file was checked out. Only allocates when the input
+/// contains a CRLF.
+pub(crate) fn normalize_line_endings(input: &'static str) -> &'static str {
+    if input.contains("\r\n") {
+        keep_source(input.replace("\r\n", "\n"))
     } else {
         input
-    };
-
-    parse_idl_named(input, "<input>")
+    }
 }
 
 /// Parse an Avro IDL string, attaching `source_name` to any error diagnostics
@@ -2011,7 +2298,23 @@ pub fn parse_idl_for_test(input: &'static str) -> Result<(IdlFile, Vec<DeclItem>
 pub fn parse_idl_named(
     input: &'static str,
     source_name: &'static str,
+    options: &ReaderOptions,
 ) -> Result<(IdlFile, Vec<DeclItem>, Vec<Warning>)> {
+    parse_idl_blanked(input, &[], source_name, options)
+}
+
+/// Parse `input` as if the given byte ranges were whitespace. Spans in the
+/// result and in errors still point into `input`.
+fn parse_idl_blanked(
+    input: &'static str,
+    blank: &[Range<usize>],
+    source_name: &'static str,
+    options: &ReaderOptions,
+) -> Result<(IdlFile, Vec<DeclItem>, Vec<Warning>)> {
+    // Editors on Windows often start UTF-8 files with a byte order mark,
+    // which the lexer would reject as an unrecognized character.
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+
     // The ANTLR grammar's `idlFile` rule includes `('\u001a' .*?)? EOF`
     // to treat the ASCII SUB character (U+001A) as an end-of-file marker,
     // ignoring any trailing content. The antlr4rust runtime does not handle
@@ -2022,55 +2325,50 @@ pub fn parse_idl_named(
     } else {
         input
     };
-    let input_stream = InputStream::new(input);
-    let mut lexer = IdlLexer::new(input_stream);
-
-    // Replace the lexer's default ConsoleErrorListener with a
-    // CollectingErrorListener so that token-recognition errors (e.g.,
-    // unrecognized characters) don't leak to stderr. We surface them as
-    // warnings instead, matching Java's behavior of not treating lexer
-    // errors as fatal. (Java also doesn't install a custom listener on
-    // the lexer — it just lets ConsoleErrorListener print to stderr.)
-    let lexer_errors: Rc<RefCell<Vec<SyntaxError>>> = Rc::new(RefCell::new(Vec::new()));
-    lexer.remove_error_listeners();
-    lexer.add_error_listener(Box::new(CollectingErrorListener {
-        errors: Rc::clone(&lexer_errors),
-        source: Some(input),
-    }));
-
-    let token_stream = CommonTokenStream::new(lexer);
-    let mut parser = IdlParser::new(token_stream);
-
-    // Build a parse tree so we can walk it recursively. The `build_parse_trees`
-    // field is on `BaseParser`, accessible through `Deref`.
-    parser.build_parse_trees = true;
-
-    // Replace the default ConsoleErrorListener with a CollectingErrorListener
-    // that records syntax errors. Java's IdlReader does the same thing -- it
-    // removes the default listener and installs one that throws on any error.
-    // We collect instead of throwing because ANTLR's error recovery may still
-    // produce a usable parse tree, but we'll fail after parsing completes.
-    let syntax_errors: Rc<RefCell<Vec<SyntaxError>>> = Rc::new(RefCell::new(Vec::new()));
-    parser.remove_error_listeners();
-    // Parser errors always have an offending token with byte offsets, so no
-    // source text is needed for line/column fallback.
-    parser.add_error_listener(Box::new(CollectingErrorListener {
-        errors: Rc::clone(&syntax_errors),
-        source: None,
-    }));
-
-    let tree = parser
-        .idlFile()
-        .map_err(|e| miette::miette!("ANTLR parse error: {e:?}"))
-        .wrap_err_with(|| format!("parse `{source_name}`"))?;
+    check_nesting_depth(input, source_name, options.max_nesting_depth)?;
+
+    // In forward-compatible mode, blank out declarations from a newer
+    // grammar before parsing. Blanking (rather than removing) keeps every
+    // byte offset, so spans still point into `input`. The blanked text is
+    // only lexed: a blanked range lexes as whitespace, whose text is never
+    // read, so the walk below can take token text from `input`.
+    let mut skip_warnings = Vec::new();
+    let mut blank = blank.to_vec();
+    if options.skip_unknown_declarations {
+        let unknown = find_unknown_declarations(&blank_out(input, &blank));
+        for decl in &unknown {
+            skip_warnings.push(Warning {
+                kind: WarningKind::UnknownDeclaration,
+                message: format!(
+                    "skipping unknown declaration `{}`; it may need a newer avdl",
+                    decl.keyword
+                ),
+                span: Some(SpanWithSource::new(
+                    decl.start,
+                    decl.end - decl.start,
+                    source_name,
+                    input,
+                )),
+            });
+        }
+        blank.extend(unknown.iter().map(|d| d.start..d.end));
+    }
+    let lexed_input = blank_out(input, &blank);
+    let parse = parse_tree::parse(&lexed_input);
 
     // Convert any lexer errors into warnings. Lexer errors (e.g., unrecognized
     // characters) don't necessarily prevent a valid parse — the lexer skips the
     // offending character and continues. Java also treats these as non-fatal
     // (prints to stderr via the default ConsoleErrorListener).
-    let lexer_warnings: Vec<Warning> = RefCell::borrow(&lexer_errors)
+    let lexer_errors: Vec<SyntaxError> = parse
+        .lex_errors
+        .iter()
+        .map(|e| SyntaxError::from_lex_error(e, &lexed_input))
+        .collect();
+    let lexer_warnings: Vec<Warning> = lexer_errors
         .iter()
         .map(|e| Warning {
+            kind: WarningKind::UnrecognizedToken,
             message: e.message.clone(),
             span: Some(SpanWithSource::new(e.offset, e.length, source_name, input)),
         })
@@ -2091,10 +2389,13 @@ pub fn parse_idl_named(
     // primary diagnostic and subsequent errors are attached as related
     // diagnostics. This lets users fix all syntax problems in one edit cycle
     // instead of the frustrating fix-one-rerun pattern.
-    let collected_errors = RefCell::borrow(&syntax_errors);
+    let collected_errors: Vec<SyntaxError> = parse
+        .errors
+        .iter()
+        .map(SyntaxError::from_parse_error)
+        .collect();
     if !collected_errors.is_empty() {
-        let borrowed_lexer_errors = RefCell::borrow(&lexer_errors);
-        if let Some(unterm) = find_unterminated_string_error(&borrowed_lexer_errors) {
+        if let Some(unterm) = find_unterminated_string_error(&lexer_errors) {
             // Extract the `line N:M` prefix from the original error message
             // so we can produce a clean message like `line 4:18 unterminated
             // string literal` instead of forwarding the raw ANTLR text.
@@ -2110,6 +2411,7 @@ pub fn parse_idl_named(
             };
 
             return Err(ParseDiagnostic {
+                kind: ErrorKind::Syntax,
                 span: SpanWithSource::new(unterm.offset, unterm.length, source_name, input),
                 message,
                 label: Some("unterminated string literal".to_string()),
@@ -2117,58 +2419,70 @@ pub fn parse_idl_named(
                     "string literals must be closed with a `\"` on the same line".to_string(),
                 ),
                 related: Vec::new(),
+                name: None,
+                replacement: None,
             }
             .into());
         }
-        drop(borrowed_lexer_errors);
 
         // Source-aware post-processing: refine error messages using context
         // from the original source text. This handles patterns that cannot
         // be detected from ANTLR error messages alone (e.g., empty unions,
         // misspelled keywords, fixed with non-integer size).
-        let refined = refine_errors_with_source(&collected_errors, input);
+        let refined = refine_errors_with_source(&collected_errors, &lexed_input);
         let errors_to_report = refined.as_deref().unwrap_or(&collected_errors);
 
         let first = &errors_to_report[0];
         let related: Vec<ParseDiagnostic> = errors_to_report[1..]
             .iter()
             .map(|e| ParseDiagnostic {
+                kind: ErrorKind::Syntax,
                 span: SpanWithSource::new(e.offset, e.length, source_name, input),
                 message: e.message.clone(),
                 label: e.label.clone(),
                 help: e.help.clone(),
                 related: Vec::new(),
+                name: None,
+                replacement: e.replacement.clone(),
             })
             .collect();
         return Err(ParseDiagnostic {
+            kind: ErrorKind::Syntax,
             span: SpanWithSource::new(first.offset, first.length, source_name, input),
             message: first.message.clone(),
             label: first.label.clone(),
             help: first.help.clone(),
             related,
+            name: None,
+            replacement: first.replacement.clone(),
         }
         .into());
     }
-    drop(collected_errors);
-
-    // The parser's `input` field (on `BaseParser`, accessible through `Deref`)
-    // holds the token stream. We need it for doc comment extraction (scanning
-    // backwards from a token index through hidden-channel tokens).
-    let token_stream = &parser.input;
 
     let src = SourceInfo {
         source: input,
         name: source_name,
+        tokens: parse.tokens,
         consumed_doc_indices: RefCell::new(HashSet::new()),
         warnings: RefCell::new(Vec::new()),
+        invalid_defaults: RefCell::new(Vec::new()),
+        options: options.clone(),
     };
 
     let mut namespace: Option<String> = None;
     let mut decl_items = Vec::new();
 
-    let idl_file = walk_idl_file(&tree, token_stream, &src, &mut namespace, &mut decl_items)
+    let idl_file = walk_idl_file(&parse.tree, &src, &mut namespace, &mut decl_items)
         .wrap_err_with(|| format!("parse `{source_name}`"))?;
 
+    // Report every invalid default in the file at once: the first is the
+    // primary diagnostic, the rest are related.
+    let mut invalid_defaults = src.invalid_defaults.take().into_iter();
+    if let Some(mut first) = invalid_defaults.next() {
+        first.related = invalid_defaults.collect();
+        return Err(miette::Report::new(first)).wrap_err_with(|| format!("parse `{source_name}`"));
+    }
+
     // ==============================================================================
     // Orphaned Doc Comment Detection
     // ==============================================================================
@@ -2181,13 +2495,10 @@ pub fn parse_idl_named(
     // a warning for each DocComment token in the gap between the previous call's
     // position and the current call's position that isn't the actual doc comment
     // for the current node.
-    let warnings = collect_orphaned_doc_comment_warnings(
-        token_stream,
-        &src.consumed_doc_indices.borrow(),
-        &src,
-    );
+    let warnings = collect_orphaned_doc_comment_warnings(&src.consumed_doc_indices.borrow(), &src);
 
-    let mut all_warnings = lexer_warnings;
+    let mut all_warnings = skip_warnings;
+    all_warnings.extend(lexer_warnings);
     all_warnings.extend(warnings);
     all_warnings.extend(src.warnings.into_inner());
 
@@ -2195,12 +2506,423 @@ pub fn parse_idl_named(
 }
 
 // ==========================================================================
-// Token Stream Type Alias
+// Forward-Compatible Declaration Skipping
+// ==========================================================================
+//
+// A newer grammar may add declaration kinds this parser does not know. With
+// `ReaderOptions::skip_unknown_declarations`, such declarations are found by
+// a token-level scan before parsing and blanked out, so the rest of the file
+// still compiles.
+//
+// Only declarations that cannot be valid today are skipped: at the top level
+// or directly inside a protocol body, an unknown word (after any annotations)
+// whose first bracket is `{`, through the matching `}` and an optional `;`.
+// Every known declaration there starts with a keyword, except messages, whose
+// first bracket is the `(` of the parameter list. Anything else, like an
+// unknown word followed by `;`, is still a syntax error.
+
+/// A declaration to skip, as a byte range of the source.
+struct UnknownDeclaration {
+    /// The word the declaration starts with, e.g. `service`.
+    keyword: String,
+    start: usize,
+    /// Exclusive.
+    end: usize,
+}
+
+/// A token the parser sees, with its inclusive byte range.
+struct ScannedToken {
+    token_type: TokenType,
+    start: usize,
+    stop: usize,
+}
+
+/// Find every unknown declaration in `input`. See the section comment above.
+fn find_unknown_declarations(input: &str) -> Vec<UnknownDeclaration> {
+    let tokens = lex_tokens(input);
+
+    // Index of the matching closing bracket for the opening one at `open`,
+    // or `None` if it is never closed.
+    let matching = |open: usize, close_type: TokenType| {
+        let open_type = tokens[open].token_type;
+        let mut depth = 0usize;
+        for (i, token) in tokens.iter().enumerate().skip(open) {
+            if token.token_type == open_type {
+                depth += 1;
+            } else if token.token_type == close_type {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+        }
+        None
+    };
+
+    let mut unknown = Vec::new();
+    // For each open `{`, whether it opened a protocol body.
+    let mut blocks: Vec<bool> = Vec::new();
+    let mut at_declaration_start = true;
+    let mut i = 0;
+    while i < tokens.len() {
+        let at_declaration_level = matches!(blocks.as_slice(), [] | [true]);
+        if at_declaration_level && at_declaration_start {
+            // Skip leading annotations: `@name(value)`.
+            let mut j = i;
+            while tokens.get(j).is_some_and(|t| t.token_type == TokenType::At) {
+                let Some(paren) =
+                    (j..tokens.len()).find(|&k| tokens[k].token_type == TokenType::LParen)
+                else {
+                    break;
+                };
+                match matching(paren, TokenType::RParen) {
+                    Some(close) => j = close + 1,
+                    None => break,
+                }
+            }
+            let first_bracket = (j..tokens.len()).find(|&k| {
+                matches!(
+                    tokens[k].token_type,
+                    TokenType::LParen
+                        | TokenType::LBrace
+                        | TokenType::Semicolon
+                        | TokenType::RBrace
+                )
+            });
+            if tokens
+                .get(j)
+                .is_some_and(|t| t.token_type == TokenType::IdentifierToken)
+                && let Some(open) = first_bracket
+                && tokens[open].token_type == TokenType::LBrace
+                && let Some(close) = matching(open, TokenType::RBrace)
+            {
+                let end = match tokens.get(close + 1) {
+                    Some(t) if t.token_type == TokenType::Semicolon => t.stop + 1,
+                    _ => tokens[close].stop + 1,
+                };
+                unknown.push(UnknownDeclaration {
+                    keyword: input[tokens[j].start..=tokens[j].stop].to_string(),
+                    start: tokens[i].start,
+                    end,
+                });
+                i = if end > tokens[close].stop + 1 {
+                    close + 2
+                } else {
+                    close + 1
+                };
+                continue;
+            }
+        }
+
+        let token_type = tokens[i].token_type;
+        at_declaration_start = match token_type {
+            TokenType::LBrace => {
+                let is_protocol = i >= 2 && tokens[i - 2].token_type == TokenType::Protocol;
+                blocks.push(is_protocol);
+                is_protocol
+            }
+            TokenType::RBrace => {
+                blocks.pop();
+                true
+            }
+            TokenType::Semicolon => true,
+            _ => false,
+        };
+        i += 1;
+    }
+    unknown
+}
+
+/// Lex `input` and return the tokens the parser sees: all but whitespace
+/// and comments.
+fn lex_tokens(input: &str) -> Vec<ScannedToken> {
+    // Lexer errors are reported by the real parse.
+    let (tokens, _) = lexer::lex(input);
+    tokens
+        .into_iter()
+        .filter(|token| !token.token_type.is_trivia())
+        .map(|token| ScannedToken {
+            token_type: token.token_type,
+            start: token.span.start,
+            stop: token.span.end - 1,
+        })
+        .collect()
+}
+
+/// Replace the given byte ranges with spaces, keeping line breaks so that
+/// line numbers in diagnostics are unchanged.
+fn blank_out<'a>(input: &'a str, ranges: &[Range<usize>]) -> Cow<'a, str> {
+    if ranges.is_empty() {
+        return Cow::Borrowed(input);
+    }
+    let mut blanked = String::with_capacity(input.len());
+    for (offset, c) in input.char_indices() {
+        let skipped = ranges.iter().any(|range| range.contains(&offset));
+        if skipped && c != '\n' && c != '\r' {
+            // One space per byte keeps every later offset the same.
+            blanked.extend(std::iter::repeat_n(' ', c.len_utf8()));
+        } else {
+            blanked.push(c);
+        }
+    }
+    Cow::Owned(blanked)
+}
+
+// ==========================================================================
+// Tolerant Parsing
 // ==========================================================================
+//
+// A file being edited rarely parses, yet an editor still wants a model of
+// it. `parse_idl_tolerant` parses as usual and, on an error, blanks out the
+// declaration the error is in and parses again, until what is left parses.
+// It gives up when an error is not inside any declaration (in the protocol
+// header, say, or at a missing closing `}` of the protocol). Blanking keeps
+// every byte offset, so spans in the recovered model still point into the
+// file.
+//
+// An error at a missing closing `}` of the protocol usually means that a
+// declaration in it was left open, so the declarations whose brackets are
+// never closed are the ones left out then.
+//
+// Declarations are found with a token scan like the one above: at the top
+// level or directly inside a protocol body, from the first token (an
+// annotation, if any) through the `;` that ends it, or through the `}` that
+// closes its body when no `;` or `= default;` follows. A declaration whose
+// brackets are never closed runs up to the protocol's closing `}`, taken to
+// be the last one in the file, so that one broken record does not take the
+// protocol down with it.
+
+/// The result of [`parse_idl_tolerant`].
+pub(crate) struct TolerantParse {
+    /// The file without its broken declarations, if that parses.
+    pub(crate) parsed: Option<(IdlFile, Vec<DeclItem>, Vec<Warning>)>,
+    /// Byte ranges of every declaration, as found by a token scan, in
+    /// source order.
+    pub(crate) declarations: Vec<Range<usize>>,
+    /// Byte ranges of the declarations that were left out, in source order.
+    pub(crate) broken: Vec<Range<usize>>,
+    /// Every error, from the first parse and from each retry.
+    pub(crate) errors: Vec<miette::Report>,
+}
+
+/// How many times to parse again after blanking out broken declarations.
+/// Each retry usually removes every declaration the previous parse reported
+/// errors in, so this is only reached by pathological inputs.
+const MAX_TOLERANT_RETRIES: usize = 16;
+
+/// Parse `input` like [`parse_idl_named`], but never fail: leave out the
+/// declarations with errors and return what remains, along with the errors.
+pub(crate) fn parse_idl_tolerant(
+    input: &'static str,
+    source_name: &'static str,
+    options: &ReaderOptions,
+) -> TolerantParse {
+    // Strip the byte order mark here, as `parse_idl_named` does, so that
+    // the declaration ranges and the error offsets agree.
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    let declarations = declaration_ranges(input);
+    let mut broken: Vec<Range<usize>> = Vec::new();
+    let mut errors = Vec::new();
+    for _ in 0..=MAX_TOLERANT_RETRIES {
+        let err = match parse_idl_blanked(input, &broken, source_name, options) {
+            Ok(parsed) => {
+                return TolerantParse {
+                    parsed: Some(parsed),
+                    declarations: declarations.into_iter().map(|(range, _)| range).collect(),
+                    broken,
+                    errors,
+                };
+            }
+            Err(err) => err,
+        };
+        let offsets = error_offsets(&err);
+        errors.push(err);
+
+        let mut newly_broken = Vec::new();
+        for offset in offsets {
+            let culprits: Vec<&Range<usize>> = match declarations
+                .iter()
+                .find(|(range, _)| range.contains(&offset))
+            {
+                Some((range, _)) => vec![range],
+                None => declarations
+                    .iter()
+                    .filter(|(_, unclosed)| *unclosed)
+                    .map(|(range, _)| range)
+                    .collect(),
+            };
+            if culprits.is_empty() {
+                // Outside every declaration: blanking cannot fix it.
+                newly_broken.clear();
+                break;
+            }
+            for range in culprits {
+                if !broken.contains(range) && !newly_broken.contains(range) {
+                    newly_broken.push(range.clone());
+                }
+            }
+        }
+        if newly_broken.is_empty() {
+            break;
+        }
+        broken.extend(newly_broken);
+        broken.sort_by_key(|range| range.start);
+    }
+    TolerantParse {
+        parsed: None,
+        declarations: declarations.into_iter().map(|(range, _)| range).collect(),
+        broken,
+        errors,
+    }
+}
+
+/// The byte offsets of a parse error and of the errors related to it, or
+/// nothing if the error has no source location.
+fn error_offsets(err: &miette::Report) -> Vec<usize> {
+    match err
+        .chain()
+        .find_map(|e| e.downcast_ref::<ParseDiagnostic>())
+    {
+        Some(diagnostic) => std::iter::once(diagnostic)
+            .chain(&diagnostic.related)
+            .map(|d| d.span.offset)
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Byte ranges of the declarations in `input`, each with whether its
+/// brackets are never closed. See the section comment above.
+fn declaration_ranges(input: &str) -> Vec<(Range<usize>, bool)> {
+    let tokens = lex_tokens(input);
+    let last_brace = tokens
+        .iter()
+        .rposition(|t| t.token_type == TokenType::RBrace);
+    let mut ranges = Vec::new();
+    let mut in_protocol = false;
+    let mut i = 0;
+    while i < tokens.len() {
+        if tokens[i].token_type == TokenType::RBrace {
+            // The protocol's closing brace, or a stray one.
+            in_protocol = false;
+            i += 1;
+            continue;
+        }
+
+        let mut depth = 0usize;
+        let mut end = None;
+        let mut is_protocol = false;
+        let mut j = i;
+        while j < tokens.len() {
+            match tokens[j].token_type {
+                // Not `import protocol "...";`.
+                TokenType::Protocol
+                    if depth == 0
+                        && !in_protocol
+                        && (j == 0 || tokens[j - 1].token_type != TokenType::Import) =>
+                {
+                    is_protocol = true;
+                    break;
+                }
+                TokenType::LBrace | TokenType::LParen => depth += 1,
+                TokenType::RParen => depth = depth.saturating_sub(1),
+                TokenType::RBrace if depth == 0 || (in_protocol && Some(j) == last_brace) => {
+                    // Closes the protocol: the declaration ends before it.
+                    end = Some(j - 1);
+                    break;
+                }
+                TokenType::RBrace => {
+                    depth -= 1;
+                    let continues = tokens.get(j + 1).is_some_and(|t| {
+                        matches!(t.token_type, TokenType::Semicolon | TokenType::Equals)
+                    });
+                    if depth == 0 && !continues {
+                        end = Some(j);
+                        break;
+                    }
+                }
+                TokenType::Semicolon if depth == 0 => {
+                    end = Some(j);
+                    break;
+                }
+                _ => {}
+            }
+            j += 1;
+        }
 
-/// Concrete token stream type produced by our lexer. Every walk function
-/// threads this through so it can extract doc comments from hidden tokens.
-type TS<'input> = CommonTokenStream<'input, IdlLexer<'input, InputStream<&'input str>>>;
+        if is_protocol {
+            // Scan the protocol body instead.
+            match (j..tokens.len()).find(|&k| tokens[k].token_type == TokenType::LBrace) {
+                Some(open) => {
+                    in_protocol = true;
+                    i = open + 1;
+                }
+                None => break,
+            }
+            continue;
+        }
+        let end = match end {
+            Some(end) => end,
+            None => match last_brace {
+                Some(last) if in_protocol && last > i => last - 1,
+                _ => tokens.len() - 1,
+            },
+        };
+        ranges.push((tokens[i].start..tokens[end].stop + 1, depth > 0));
+        i = end + 1;
+    }
+    ranges
+}
+
+// ==========================================================================
+// Several Protocols per File
+// ==========================================================================
+//
+// The grammar allows one protocol per file. To compile a file that declares
+// several, one after another, each protocol gets its own copy of the source
+// with every other protocol blanked out, so spans and line numbers still
+// match the original file. Whatever precedes a protocol (doc comment,
+// annotations) goes with it.
+
+/// Split `input` into one source per top-level protocol, in file order. A
+/// source with fewer than two protocols is returned as is.
+pub(crate) fn split_protocols(input: &'static str) -> Vec<&'static str> {
+    let tokens = lex_tokens(input);
+
+    // Exclusive end offset of each top-level protocol's closing `}`.
+    let mut ends = Vec::new();
+    let mut depth = 0usize;
+    let mut in_protocol = false;
+    for token in &tokens {
+        match token.token_type {
+            TokenType::Protocol if depth == 0 => in_protocol = true,
+            TokenType::LBrace => depth += 1,
+            TokenType::RBrace => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 && in_protocol {
+                    ends.push(token.stop + 1);
+                    in_protocol = false;
+                }
+            }
+            _ => {}
+        }
+    }
+    if ends.len() < 2 {
+        return vec![input];
+    }
+
+    let last = ends.len() - 1;
+    (0..=last)
+        .map(|i| {
+            let start = if i == 0 { 0 } else { ends[i - 1] };
+            let end = if i == last { input.len() } else { ends[i] };
+            match blank_out(input, &[0..start, end..input.len()]) {
+                Cow::Borrowed(source) => source,
+                Cow::Owned(source) => keep_source(source),
+            }
+        })
+        .collect()
+}
 
 // ==========================================================================
 // Source Location Diagnostic Helpers
@@ -2209,125 +2931,115 @@ type TS<'input> = CommonTokenStream<'input, IdlLexer<'input, InputStream<&'input
 /// Carries the original source text and a display name through the tree walk
 /// so that error messages can include source location context via miette.
 ///
-/// Also tracks which doc comment token indices have been consumed by
-/// declarations, so that orphaned doc comments can be detected after the walk.
+/// Also holds the source's tokens, which parse tree nodes refer to by index,
+/// and tracks which doc comment tokens have been consumed by declarations, so
+/// that orphaned doc comments can be detected after the walk.
 struct SourceInfo {
     source: &'static str,
     name: &'static str,
+    /// Every token of the source, whitespace and comments included.
+    tokens: Vec<Token>,
     /// Token indices of doc comments consumed by `extract_doc_from_context`.
     /// After the full tree walk, any `DocComment` token NOT in this set is
     /// orphaned and should generate a warning.
-    consumed_doc_indices: RefCell<HashSet<isize>>,
+    consumed_doc_indices: RefCell<HashSet<usize>>,
     /// Warnings collected during the tree walk. Functions that detect
     /// non-fatal issues (e.g., annotations silently dropped on union types)
     /// push here rather than threading `&mut Vec<Warning>` through every
     /// call site.
     warnings: RefCell<Vec<Warning>>,
+    /// Field defaults that do not match their type, collected so the walk can
+    /// go on and report all of them at once. See `walk_variable`.
+    invalid_defaults: RefCell<Vec<ParseDiagnostic>>,
+    /// Caller-supplied options that change how the walk interprets the source.
+    options: ReaderOptions,
 }
 
 impl SourceInfo {
     fn span(&self, offset: usize, length: usize) -> SpanWithSource {
         SpanWithSource::new(offset, length, self.name, self.source)
     }
-}
 
-/// Compute `(offset, length)` from ANTLR's inclusive start/stop byte offsets.
-///
-/// ANTLR tokens report byte positions where both `start` and `stop` are
-/// inclusive, so the length of the spanned region is `stop - start + 1`.
-/// Returns a span covering at least one character when possible, or `(0, 0)`
-/// when no valid position is available.
-fn span_from_offsets(start: isize, stop: isize) -> (usize, usize) {
-    if start >= 0 && stop >= start {
-        (start as usize, (stop - start + 1) as usize)
-    } else if start >= 0 {
-        (start as usize, 1)
-    } else {
-        (0, 0)
+    /// The source text of the token at `index`.
+    fn token_text(&self, index: usize) -> &'static str {
+        &self.source[self.tokens[index].span.clone()]
     }
 }
 
 /// Construct a `miette::Report` wrapping a `ParseDiagnostic` with source
-/// location extracted from an ANTLR parse tree context's start token.
-///
-/// The start token gives us a byte offset into the original source text. We
-/// use the token's `get_start()` and `get_stop()` to compute a byte-level
-/// `SourceSpan` that miette can render as an underlined region in the error
-/// output.
-fn make_diagnostic<'input>(
+/// location spanning everything a parse tree node matched.
+fn make_diagnostic(
     src: &SourceInfo,
-    ctx: &impl antlr4rust::parser_rule_context::ParserRuleContext<'input>,
+    ctx: &Node,
+    kind: ErrorKind,
     message: impl Into<String>,
 ) -> miette::Report {
-    let start_token = ctx.start();
-    let stop_token = ctx.stop();
-    let offset = start_token.get_start();
-
-    // Use the stop token's end byte to span the entire context (e.g. the full
-    // `@name(value)` annotation rather than just the leading `@`). Fall back
-    // to the start token's own stop byte if the stop token has no valid
-    // position.
-    let stop = {
-        let candidate = stop_token.get_stop();
-        if candidate >= offset {
-            candidate
-        } else {
-            start_token.get_stop()
-        }
-    };
-
-    let (offset, length) = span_from_offsets(offset, stop);
-
     let message = message.into();
     ParseDiagnostic {
-        span: src.span(offset, length),
+        kind,
+        span: context_span(src, ctx),
         message,
         label: None,
         help: None,
         related: Vec::new(),
+        name: None,
+        replacement: None,
     }
     .into()
 }
 
-/// Like `make_diagnostic` but takes a raw `Token` reference instead of a
-/// context node. Useful when the error relates to a specific token field
-/// (e.g. `ctx.size`, `ctx.typeName`) rather than the whole context.
+/// The span of everything a parse tree node matched, as used by
+/// `make_diagnostic`: from the start of its first token (e.g. the `@` of an
+/// annotation) to the end of its last (the closing `)`).
+fn context_span(src: &SourceInfo, ctx: &Node) -> SpanWithSource {
+    let start = src
+        .tokens
+        .get(ctx.tokens.start)
+        .map_or(src.source.len(), |token| token.span.start);
+    let end = match ctx.tokens.end.checked_sub(1) {
+        Some(last) if !ctx.tokens.is_empty() => src.tokens[last].span.end,
+        _ => start,
+    };
+    src.span(start, end - start)
+}
+
+/// Like `make_diagnostic` but takes a token index instead of a node. Useful
+/// when the error relates to a specific token (e.g. a fixed size or a decimal
+/// precision) rather than the whole node.
 fn make_diagnostic_from_token(
     src: &SourceInfo,
-    token: &impl Token,
+    token: usize,
+    kind: ErrorKind,
     message: impl Into<String>,
 ) -> miette::Report {
-    let (offset, length) = span_from_offsets(token.get_start(), token.get_stop());
+    let span = &src.tokens[token].span;
 
     let message = message.into();
     ParseDiagnostic {
-        span: src.span(offset, length),
+        kind,
+        span: src.span(span.start, span.len()),
         message,
         label: None,
         help: None,
         related: Vec::new(),
+        name: None,
+        replacement: None,
     }
     .into()
 }
 
-/// Extract the span (offset and length) from a parse tree context's start token.
+/// The span (offset and length) of a parse tree node's first token.
 ///
-/// Returns `None` if the token has no valid position (e.g., synthetic tokens).
-/// Used to attach spans to `DeclItem::Type` and `DeclItem::Import` entries so
-/// that downstream errors (duplicate type name, import failure) can produce
-/// source-highlighted diagnostics.
-fn span_from_context<'input>(
-    ctx: &impl antlr4rust::parser_rule_context::ParserRuleContext<'input>,
-) -> Option<(usize, usize)> {
-    let start_token = ctx.start();
-    let (offset, length) = span_from_offsets(start_token.get_start(), start_token.get_stop());
-
-    // A zero-length span at offset 0 means no valid position was available.
-    if length > 0 {
-        Some((offset, length))
-    } else {
-        None
+/// Returns `None` for a node that matched no tokens. Used to attach spans to
+/// `DeclItem::Type` and `DeclItem::Import` entries so that downstream errors
+/// (duplicate type name, import failure) can produce source-highlighted
+/// diagnostics.
+fn span_from_context(src: &SourceInfo, ctx: &Node) -> Option<(usize, usize)> {
+    if ctx.tokens.is_empty() {
+        return None;
     }
+    let span = &src.tokens[ctx.tokens.start].span;
+    Some((span.start, span.len()))
 }
 
 // ==========================================================================
@@ -2343,7 +3055,7 @@ struct SchemaProperties {
     namespace: Option<String>,
     aliases: Vec<String>,
     order: Option<FieldOrder>,
-    properties: HashMap<String, Value>,
+    properties: Properties,
 }
 
 impl SchemaProperties {
@@ -2352,7 +3064,7 @@ impl SchemaProperties {
             namespace: None,
             aliases: Vec::new(),
             order: None,
-            properties: HashMap::new(),
+            properties: Properties::new(),
         }
     }
 }
@@ -2507,24 +3219,23 @@ const MESSAGE_PROPS: PropertyContext = PropertyContext {
 /// `SchemaProperties` struct. Which annotations are intercepted as special
 /// fields (`namespace`, `aliases`, `order`) depends on the `pctx` flags,
 /// matching Java's context-sensitive `SchemaProperties` behavior.
-fn walk_schema_properties<'input>(
-    props: &[Rc<SchemaPropertyContextAll<'input>>],
-    token_stream: &TS<'input>,
+fn walk_schema_properties<'a>(
+    props: impl Iterator<Item = &'a Node>,
     src: &SourceInfo,
     pctx: PropertyContext,
 ) -> Result<SchemaProperties> {
     let mut result = SchemaProperties::new();
 
     for prop in props {
-        let name_ctx = prop
-            .identifier()
-            .ok_or_else(|| make_diagnostic(src, &**prop, "missing property name"))?;
-        let name = identifier_text(&name_ctx);
-
-        let value_ctx = prop
-            .jsonValue()
-            .ok_or_else(|| make_diagnostic(src, &**prop, "missing property value"))?;
-        let value = walk_json_value(&value_ctx, token_stream, src)
+        let name_ctx = prop.child(Rule::Identifier).ok_or_else(|| {
+            make_diagnostic(src, prop, ErrorKind::Syntax, "missing property name")
+        })?;
+        let name = identifier_text(src, name_ctx);
+
+        let value_ctx = prop.child(Rule::JsonValue).ok_or_else(|| {
+            make_diagnostic(src, prop, ErrorKind::Syntax, "missing property value")
+        })?;
+        let value = walk_json_value(value_ctx, src)
             .wrap_err_with(|| format!("parse value for schema property `{name}`"))?;
 
         // Intercept well-known annotations only when the context flags allow it.
@@ -2539,7 +3250,8 @@ fn walk_schema_properties<'input>(
             } else {
                 return Err(make_diagnostic(
                     src,
-                    &**prop,
+                    prop,
+                    ErrorKind::InvalidAnnotation,
                     "@namespace must contain a string value",
                 ));
             }
@@ -2561,13 +3273,14 @@ fn walk_schema_properties<'input>(
                         if !is_standard {
                             src.warnings
                                 .borrow_mut()
-                                .push(Warning::non_standard_alias_name(s, src, &**prop));
+                                .push(Warning::non_standard_alias_name(s, src, prop));
                         }
                         aliases.push(s.clone());
                     } else {
                         return Err(make_diagnostic(
                             src,
-                            &**prop,
+                            prop,
+                            ErrorKind::InvalidAnnotation,
                             "@aliases must contain an array of strings",
                         ));
                     }
@@ -2576,7 +3289,8 @@ fn walk_schema_properties<'input>(
             } else {
                 return Err(make_diagnostic(
                     src,
-                    &**prop,
+                    prop,
+                    ErrorKind::InvalidAnnotation,
                     "@aliases must contain an array of strings",
                 ));
             }
@@ -2589,7 +3303,8 @@ fn walk_schema_properties<'input>(
                     _ => {
                         return Err(make_diagnostic(
                             src,
-                            &**prop,
+                            prop,
+                            ErrorKind::InvalidAnnotation,
                             format!("@order must be ASCENDING, DESCENDING, or IGNORE, got: {s}"),
                         ));
                     }
@@ -2597,7 +3312,8 @@ fn walk_schema_properties<'input>(
             } else {
                 return Err(make_diagnostic(
                     src,
-                    &**prop,
+                    prop,
+                    ErrorKind::InvalidAnnotation,
                     "@order must contain a string value",
                 ));
             }
@@ -2610,11 +3326,37 @@ fn walk_schema_properties<'input>(
             if pctx.reserved.contains(&name.as_str()) {
                 return Err(make_diagnostic(
                     src,
-                    &**prop,
+                    prop,
+                    ErrorKind::ReservedProperty,
                     format!("Can't set reserved property: {name}"),
                 ));
             }
-            result.properties.insert(name, value);
+            let mut annotation = Annotation {
+                name: &name,
+                value: Some(value),
+                warnings: Vec::new(),
+            };
+            for handler in src.options.annotation_handlers.get(&name) {
+                handler(&mut annotation).map_err(|message| {
+                    make_diagnostic(
+                        src,
+                        prop,
+                        ErrorKind::InvalidAnnotation,
+                        format!("invalid @{name} annotation: {message}"),
+                    )
+                })?;
+            }
+            for message in annotation.warnings {
+                src.warnings.borrow_mut().push(Warning::spanning(
+                    WarningKind::Annotation,
+                    message,
+                    src,
+                    prop,
+                ));
+            }
+            if let Some(value) = annotation.value {
+                result.properties.insert(name, value);
+            }
         }
     }
 
@@ -2631,25 +3373,24 @@ fn walk_schema_properties<'input>(
 /// function collects all imports and local type definitions into `decl_items`
 /// in source order. The caller processes these items sequentially to build a
 /// correctly ordered registry.
-fn walk_idl_file<'input>(
-    ctx: &IdlFileContextAll<'input>,
-    token_stream: &TS<'input>,
+fn walk_idl_file(
+    ctx: &Node,
     src: &SourceInfo,
     namespace: &mut Option<String>,
     decl_items: &mut Vec<DeclItem>,
 ) -> Result<IdlFile> {
     // Protocol mode: the IDL contains `protocol Name { ... }`.
-    if let Some(protocol_ctx) = ctx.protocolDeclaration() {
-        let protocol = walk_protocol(&protocol_ctx, token_stream, src, namespace, decl_items)?;
+    if let Some(protocol_ctx) = ctx.child(Rule::ProtocolDeclaration) {
+        let protocol = walk_protocol(protocol_ctx, src, namespace, decl_items)?;
         return Ok(IdlFile::Protocol(protocol));
     }
 
     // Schema mode: optional `namespace`, optional `schema` declaration, plus
     // named type declarations.
-    if let Some(ns_ctx) = ctx.namespaceDeclaration()
-        && let Some(id_ctx) = ns_ctx.identifier()
+    if let Some(ns_ctx) = ctx.child(Rule::NamespaceDeclaration)
+        && let Some(id_ctx) = ns_ctx.child(Rule::Identifier)
     {
-        let id = identifier_text(&id_ctx);
+        let id = identifier_text(src, id_ctx);
         // In schema mode, `namespace foo.bar;` sets the enclosing namespace
         // directly. Unlike protocol/record identifiers (where dots in the
         // name imply a namespace prefix), here the entire identifier IS the
@@ -2662,26 +3403,23 @@ fn walk_idl_file<'input>(
     //   (imports+=importStatement | namedSchemas+=namedSchemaDeclaration)*
     // We iterate all children to preserve the original declaration order.
     let mut local_schemas = Vec::new();
-    for child in ctx.get_children() {
-        if let Ok(import_ctx) = child
-            .clone()
-            .downcast_rc::<ImportStatementContextAll<'input>>()
-        {
-            collect_single_import(&import_ctx, decl_items, src);
-        } else if let Ok(ns_ctx) = child.downcast_rc::<NamedSchemaDeclarationContextAll<'input>>() {
-            let span = span_from_context(&*ns_ctx).map(|(o, l)| src.span(o, l));
-            let (schema, field_spans) =
-                walk_named_schema_no_register(&ns_ctx, token_stream, src, namespace)?;
+    for child in ctx.nodes() {
+        if child.rule == Rule::ImportStatement {
+            collect_single_import(child, decl_items, src);
+        } else if child.rule == Rule::NamedSchemaDeclaration {
+            let ns_ctx = child;
+            let span = span_from_context(src, ns_ctx).map(|(o, l)| src.span(o, l));
+            let (schema, field_spans) = walk_named_schema_no_register(ns_ctx, src, namespace)?;
             local_schemas.push(schema.clone());
             decl_items.push(DeclItem::Type(Box::new(schema), span, field_spans));
         }
     }
 
     // The main schema declaration uses `schema <fullType>;`.
-    if let Some(main_ctx) = ctx.mainSchemaDeclaration()
-        && let Some(ft_ctx) = main_ctx.fullType()
+    if let Some(main_ctx) = ctx.child(Rule::MainSchemaDeclaration)
+        && let Some(ft_ctx) = main_ctx.child(Rule::FullType)
     {
-        let schema = walk_full_type(&ft_ctx, token_stream, src, namespace.as_deref())?;
+        let schema = walk_full_type(ft_ctx, src, namespace.as_deref())?;
         return Ok(IdlFile::Schema(schema));
     }
 
@@ -2700,25 +3438,23 @@ fn walk_idl_file<'input>(
 /// protocol body's children in source order, appending `DeclItem::Import`
 /// and `DeclItem::Type` entries to `decl_items`. Messages are collected
 /// directly into the protocol since they don't affect type ordering.
-fn walk_protocol<'input>(
-    ctx: &ProtocolDeclarationContextAll<'input>,
-    token_stream: &TS<'input>,
+fn walk_protocol(
+    ctx: &Node,
     src: &SourceInfo,
     namespace: &mut Option<String>,
     decl_items: &mut Vec<DeclItem>,
 ) -> Result<Protocol> {
     // Extract doc comment by scanning hidden tokens before the context's start token.
-    let doc = extract_doc_from_context(ctx, token_stream, src);
+    let doc = extract_doc_from_context(ctx, src);
 
     // Process `@namespace(...)` and other schema properties on the protocol.
-    let props =
-        walk_schema_properties(&ctx.schemaProperty_all(), token_stream, src, PROTOCOL_PROPS)?;
+    let props = walk_schema_properties(ctx.children(Rule::SchemaProperty), src, PROTOCOL_PROPS)?;
 
     // Get the protocol name from the identifier.
     let name_ctx = ctx
-        .identifier()
-        .ok_or_else(|| make_diagnostic(src, ctx, "missing protocol name"))?;
-    let raw_identifier = identifier_text(&name_ctx);
+        .child(Rule::Identifier)
+        .ok_or_else(|| make_diagnostic(src, ctx, ErrorKind::Syntax, "missing protocol name"))?;
+    let raw_identifier = identifier_text(src, name_ctx);
 
     // Determine namespace: explicit `@namespace` overrides, otherwise if the
     // identifier contains dots, the part before the last dot is the namespace.
@@ -2728,7 +3464,8 @@ fn walk_protocol<'input>(
     if is_invalid_type_name(&protocol_name) {
         return Err(make_diagnostic(
             src,
-            &*name_ctx,
+            name_ctx,
+            ErrorKind::InvalidName,
             format!("Illegal name: {protocol_name}"),
         ));
     }
@@ -2738,32 +3475,26 @@ fn walk_protocol<'input>(
 
     // Walk the protocol body.
     let body = ctx
-        .protocolDeclarationBody()
-        .ok_or_else(|| make_diagnostic(src, ctx, "missing protocol body"))?;
+        .child(Rule::ProtocolDeclarationBody)
+        .ok_or_else(|| make_diagnostic(src, ctx, ErrorKind::Syntax, "missing protocol body"))?;
 
     // Walk the protocol body children in source order. The ANTLR grammar
     // interleaves imports, named schema declarations, and message declarations:
     //   protocolDeclarationBody: '{' (import | namedSchema | message)* '}'
     // We iterate all children and dispatch based on type, preserving the
     // original declaration order for imports and types.
-    let mut messages = HashMap::new();
-    for child in body.get_children() {
-        if let Ok(import_ctx) = child
-            .clone()
-            .downcast_rc::<ImportStatementContextAll<'input>>()
-        {
-            collect_single_import(&import_ctx, decl_items, src);
-        } else if let Ok(ns_ctx) = child
-            .clone()
-            .downcast_rc::<NamedSchemaDeclarationContextAll<'input>>()
-        {
-            let span = span_from_context(&*ns_ctx).map(|(o, l)| src.span(o, l));
-            let (schema, field_spans) =
-                walk_named_schema_no_register(&ns_ctx, token_stream, src, namespace)?;
+    let mut messages = IndexMap::new();
+    for child in body.nodes() {
+        if child.rule == Rule::ImportStatement {
+            collect_single_import(child, decl_items, src);
+        } else if child.rule == Rule::NamedSchemaDeclaration {
+            let ns_ctx = child;
+            let span = span_from_context(src, ns_ctx).map(|(o, l)| src.span(o, l));
+            let (schema, field_spans) = walk_named_schema_no_register(ns_ctx, src, namespace)?;
             decl_items.push(DeclItem::Type(Box::new(schema), span, field_spans));
-        } else if let Ok(msg_ctx) = child.downcast_rc::<MessageDeclarationContextAll<'input>>() {
-            let (msg_name, message) =
-                walk_message(&msg_ctx, token_stream, src, namespace.as_deref())?;
+        } else if child.rule == Rule::MessageDeclaration {
+            let msg_ctx = child;
+            let (msg_name, message) = walk_message(msg_ctx, src, namespace.as_deref())?;
             messages.insert(msg_name, message);
         }
     }
@@ -2785,28 +3516,28 @@ fn walk_protocol<'input>(
 /// This function parses the named schema but does NOT register it in a
 /// `SchemaRegistry`. The caller is responsible for registration, which allows
 /// imports and local types to be registered in source order.
-fn walk_named_schema_no_register<'input>(
-    ctx: &NamedSchemaDeclarationContextAll<'input>,
-    token_stream: &TS<'input>,
+fn walk_named_schema_no_register(
+    ctx: &Node,
     src: &SourceInfo,
     namespace: &mut Option<String>,
 ) -> Result<(AvroSchema, HashMap<String, SpanWithSource>)> {
-    if let Some(fixed_ctx) = ctx.fixedDeclaration() {
+    if let Some(fixed_ctx) = ctx.child(Rule::FixedDeclaration) {
         Ok((
-            walk_fixed(&fixed_ctx, token_stream, src, namespace.as_deref())?,
+            walk_fixed(fixed_ctx, src, namespace.as_deref())?,
             HashMap::new(),
         ))
-    } else if let Some(enum_ctx) = ctx.enumDeclaration() {
+    } else if let Some(enum_ctx) = ctx.child(Rule::EnumDeclaration) {
         Ok((
-            walk_enum(&enum_ctx, token_stream, src, namespace.as_deref())?,
+            walk_enum(enum_ctx, src, namespace.as_deref())?,
             HashMap::new(),
         ))
-    } else if let Some(record_ctx) = ctx.recordDeclaration() {
-        walk_record(&record_ctx, token_stream, src, namespace)
+    } else if let Some(record_ctx) = ctx.child(Rule::RecordDeclaration) {
+        walk_record(record_ctx, src, namespace)
     } else {
         Err(make_diagnostic(
             src,
             ctx,
+            ErrorKind::Syntax,
             "unknown named schema declaration",
         ))
     }
@@ -2821,30 +3552,21 @@ fn walk_named_schema_no_register<'input>(
 // `walk_record` does not need access to the schema registry. If the grammar
 // is ever extended to allow nested named schema declarations inside records,
 // a `registry: &mut SchemaRegistry` parameter would need to be added back.
-fn walk_record<'input>(
-    ctx: &RecordDeclarationContextAll<'input>,
-    token_stream: &TS<'input>,
+fn walk_record(
+    ctx: &Node,
     src: &SourceInfo,
     namespace: &mut Option<String>,
 ) -> Result<(AvroSchema, HashMap<String, SpanWithSource>)> {
-    let doc = extract_doc_from_context(ctx, token_stream, src);
-    let props = walk_schema_properties(
-        &ctx.schemaProperty_all(),
-        token_stream,
-        src,
-        NAMED_TYPE_PROPS,
-    )?;
+    let doc = extract_doc_from_context(ctx, src);
+    let props = walk_schema_properties(ctx.children(Rule::SchemaProperty), src, NAMED_TYPE_PROPS)?;
 
     let name_ctx = ctx
-        .identifier()
-        .ok_or_else(|| make_diagnostic(src, ctx, "missing record name"))?;
-    let raw_identifier = identifier_text(&name_ctx);
+        .child(Rule::Identifier)
+        .ok_or_else(|| make_diagnostic(src, ctx, ErrorKind::Syntax, "missing record name"))?;
+    let raw_identifier = identifier_text(src, name_ctx);
 
     // Determine if this is a record or an error type.
-    let is_error = ctx
-        .recordType
-        .as_ref()
-        .is_some_and(|tok| tok.get_token_type() == Idl_Error);
+    let is_error = ctx.token(TokenType::Error).is_some();
 
     // Compute namespace: `@namespace` on the record overrides; otherwise
     // the identifier may contain dots, or we fall back to the enclosing namespace.
@@ -2855,7 +3577,8 @@ fn walk_record<'input>(
     if is_invalid_type_name(&record_name) {
         return Err(make_diagnostic(
             src,
-            &*name_ctx,
+            name_ctx,
+            ErrorKind::InvalidName,
             format!("Illegal name: {record_name}"),
         ));
     }
@@ -2869,32 +3592,28 @@ fn walk_record<'input>(
 
     // Walk the record body to get fields.
     let body = ctx
-        .recordBody()
-        .ok_or_else(|| make_diagnostic(src, ctx, "missing record body"))?;
+        .child(Rule::RecordBody)
+        .ok_or_else(|| make_diagnostic(src, ctx, ErrorKind::Syntax, "missing record body"))?;
 
     let mut fields = Vec::new();
     let mut field_spans: HashMap<String, SpanWithSource> = HashMap::new();
     let mut seen_field_names: HashSet<String> = HashSet::new();
-    for field_ctx in body.fieldDeclaration_all() {
-        let mut field_fields = walk_field_declaration(
-            &field_ctx,
-            token_stream,
-            src,
-            namespace.as_deref(),
-            Some(&record_name),
-        )?;
+    for field_ctx in body.children(Rule::FieldDeclaration) {
+        let mut field_fields =
+            walk_field_declaration(field_ctx, src, namespace.as_deref(), Some(&record_name))?;
         // Check for duplicates. We zip with the variable declaration contexts
         // so that the diagnostic highlights the duplicate field *name*, not the
         // type keyword that starts the field declaration.
-        let var_ctxs = field_ctx.variableDeclaration_all();
-        for (field, var_ctx) in field_fields.iter().zip(var_ctxs.iter()) {
+        let var_ctxs = field_ctx.children(Rule::VariableDeclaration);
+        for (field, var_ctx) in field_fields.iter().zip(var_ctxs) {
             if !seen_field_names.insert(field.name.clone()) {
                 *namespace = saved_namespace;
-                let name_ctx = var_ctx.identifier();
+                let name_ctx = var_ctx.child(Rule::Identifier);
                 let diag = if let Some(name_ctx) = name_ctx {
                     make_diagnostic(
                         src,
-                        &*name_ctx,
+                        name_ctx,
+                        ErrorKind::DuplicateField,
                         format!(
                             "duplicate field '{}' in record '{}'",
                             field.name, record_name
@@ -2903,7 +3622,8 @@ fn walk_record<'input>(
                 } else {
                     make_diagnostic(
                         src,
-                        &*field_ctx,
+                        field_ctx,
+                        ErrorKind::DuplicateField,
                         format!(
                             "duplicate field '{}' in record '{}'",
                             field.name, record_name
@@ -2917,9 +3637,9 @@ fn walk_record<'input>(
             // registration. Prefer the jsonValue span (the `= <value>` part)
             // so diagnostics highlight the offending value, not the field name.
             let default_span = var_ctx
-                .jsonValue()
-                .and_then(|jv| span_from_context(&*jv))
-                .or_else(|| span_from_context(&**var_ctx));
+                .child(Rule::JsonValue)
+                .and_then(|jv| span_from_context(src, jv))
+                .or_else(|| span_from_context(src, var_ctx));
             if let Some((offset, length)) = default_span {
                 field_spans.insert(field.name.clone(), src.span(offset, length));
             }
@@ -2953,34 +3673,32 @@ fn walk_record<'input>(
 ///
 /// `enclosing_name` is the name of the enclosing record (if any), included in
 /// default-validation error messages for context.
-fn walk_field_declaration<'input>(
-    ctx: &FieldDeclarationContextAll<'input>,
-    token_stream: &TS<'input>,
+fn walk_field_declaration(
+    ctx: &Node,
     src: &SourceInfo,
     namespace: Option<&str>,
     enclosing_name: Option<&str>,
 ) -> Result<Vec<Field>> {
     // The doc comment on the field declaration acts as a default for variables
     // that don't have their own doc comment.
-    let default_doc = extract_doc_from_context(ctx, token_stream, src);
+    let default_doc = extract_doc_from_context(ctx, src);
 
     // Walk the field type.
     let full_type_ctx = ctx
-        .fullType()
-        .ok_or_else(|| make_diagnostic(src, ctx, "missing field type"))?;
-    let field_type = walk_full_type(&full_type_ctx, token_stream, src, namespace)?;
+        .child(Rule::FullType)
+        .ok_or_else(|| make_diagnostic(src, ctx, ErrorKind::Syntax, "missing field type"))?;
+    let field_type = walk_full_type(full_type_ctx, src, namespace)?;
 
     // Walk each variable declaration.
     let mut fields = Vec::new();
-    for var_ctx in ctx.variableDeclaration_all() {
+    for var_ctx in ctx.children(Rule::VariableDeclaration) {
         let field = walk_variable(
-            &var_ctx,
+            var_ctx,
             &field_type,
             default_doc.as_deref(),
-            token_stream,
             src,
-            namespace,
             enclosing_name,
+            true,
         )?;
         fields.push(field);
     }
@@ -2992,39 +3710,78 @@ fn walk_field_declaration<'input>(
 ///
 /// `enclosing_name` is the name of the enclosing record (if any), included in
 /// default-validation error messages for context (e.g. "in `MyRecord`").
-fn walk_variable<'input>(
-    ctx: &VariableDeclarationContextAll<'input>,
+///
+/// `check_default` is `false` only for message parameters under a
+/// non-default [`ParamDefaults`] mode.
+fn walk_variable(
+    ctx: &Node,
     field_type: &AvroSchema,
     default_doc: Option<&str>,
-    token_stream: &TS<'input>,
     src: &SourceInfo,
-    _namespace: Option<&str>,
     enclosing_name: Option<&str>,
+    check_default: bool,
 ) -> Result<Field> {
     // Variable-specific doc comment overrides the field-level default.
-    let var_doc = extract_doc_from_context(ctx, token_stream, src);
+    let var_doc = extract_doc_from_context(ctx, src);
     let doc = var_doc.or_else(|| default_doc.map(|s| s.to_string()));
 
     let name_ctx = ctx
-        .identifier()
-        .ok_or_else(|| make_diagnostic(src, ctx, "missing variable name"))?;
-    let field_name = identifier_text(&name_ctx);
+        .child(Rule::Identifier)
+        .ok_or_else(|| make_diagnostic(src, ctx, ErrorKind::Syntax, "missing variable name"))?;
+    let field_name = identifier_text(src, name_ctx);
 
     // Walk the variable-level schema properties (e.g. @order, @aliases on a
     // specific variable rather than on the field type).
-    let props =
-        walk_schema_properties(&ctx.schemaProperty_all(), token_stream, src, VARIABLE_PROPS)?;
+    let props = walk_schema_properties(ctx.children(Rule::SchemaProperty), src, VARIABLE_PROPS)?;
 
     // Parse the default value if present.
-    let default_value = if let Some(json_ctx) = ctx.jsonValue() {
+    let default_value = if let Some(json_ctx) = ctx.child(Rule::JsonValue) {
         Some(
-            walk_json_value(&json_ctx, token_stream, src)
+            walk_json_value(json_ctx, src)
                 .wrap_err_with(|| format!("parse default value for field `{field_name}`"))?,
         )
     } else {
         None
     };
 
+    let in_clause = match enclosing_name {
+        Some(name) => format!(" in `{name}`"),
+        None => String::new(),
+    };
+
+    // With ISO-8601 defaults, a string default of a date or time field (or
+    // of an optional one) is converted to the number Avro stores.
+    let default_value = match default_value {
+        Some(Value::String(text)) if src.options.iso8601_defaults => {
+            let logical_type = match field_type {
+                AvroSchema::Logical { logical_type, .. } => Some(logical_type),
+                AvroSchema::Union {
+                    is_nullable_type: true,
+                    ..
+                } => field_type
+                    .non_null_branches()
+                    .find_map(|branch| match branch {
+                        AvroSchema::Logical { logical_type, .. } => Some(logical_type),
+                        _ => None,
+                    }),
+                _ => None,
+            };
+            match logical_type.and_then(|logical_type| iso8601_default(&text, logical_type)) {
+                Some(Ok(number)) => Some(Value::from(number)),
+                Some(Err(reason)) => {
+                    let message =
+                        format!("Invalid default for field `{field_name}`{in_clause}: {reason}");
+                    return Err(match ctx.child(Rule::JsonValue) {
+                        Some(jv) => make_diagnostic(src, jv, ErrorKind::InvalidDefault, message),
+                        None => make_diagnostic(src, ctx, ErrorKind::InvalidDefault, message),
+                    });
+                }
+                None => Some(Value::String(text)),
+            }
+        }
+        other => other,
+    };
+
     // Apply fixOptionalSchema: if the type is a nullable union (from `type?`)
     // and the default is non-null, reorder to put the non-null type first.
     let final_type = fix_optional_schema(field_type.clone(), default_value.as_ref());
@@ -3038,27 +3795,35 @@ fn walk_variable<'input>(
     // Validate that the default value's JSON type matches the field's Avro type.
     // This catches mismatches like `int count = "hello"` at compile time, matching
     // Java's `Schema.Field` constructor behavior with `validate=true`.
-    if let Some(ref default_val) = default_value
+    if check_default
+        && let Some(ref default_val) = default_value
         && let Some(reason) = validate_default(default_val, &final_type)
     {
-        let in_clause = match enclosing_name {
-            Some(name) => format!(" in `{name}`"),
-            None => String::new(),
-        };
         // Point the diagnostic at the default value expression, not the
         // entire variable declaration (which includes the field name).
-        return Err(match ctx.jsonValue() {
-            Some(ref jv) => make_diagnostic(
-                src,
-                &**jv,
-                format!("Invalid default for field `{field_name}`{in_clause}: {reason}"),
-            ),
-            None => make_diagnostic(
-                src,
-                ctx,
-                format!("Invalid default for field `{field_name}`{in_clause}: {reason}"),
-            ),
-        });
+        let span = match ctx.child(Rule::JsonValue) {
+            Some(jv) => context_span(src, jv),
+            None => context_span(src, ctx),
+        };
+        let message = format!("Invalid default for field `{field_name}`{in_clause}: {reason}");
+        if !src.options.validate_defaults {
+            src.warnings.borrow_mut().push(Warning {
+                kind: WarningKind::InvalidDefault,
+                message,
+                span: Some(span),
+            });
+        } else {
+            src.invalid_defaults.borrow_mut().push(ParseDiagnostic {
+                kind: ErrorKind::InvalidDefault,
+                span,
+                message,
+                label: None,
+                help: None,
+                related: Vec::new(),
+                name: None,
+                replacement: None,
+            });
+        }
     }
 
     Ok(Field {
@@ -3076,19 +3841,18 @@ fn walk_variable<'input>(
 // Enum
 // ==========================================================================
 
-fn walk_enum<'input>(
-    ctx: &EnumDeclarationContextAll<'input>,
-    token_stream: &TS<'input>,
+fn walk_enum(
+    ctx: &Node,
     src: &SourceInfo,
     enclosing_namespace: Option<&str>,
 ) -> Result<AvroSchema> {
-    let doc = extract_doc_from_context(ctx, token_stream, src);
-    let props = walk_schema_properties(&ctx.schemaProperty_all(), token_stream, src, ENUM_PROPS)?;
+    let doc = extract_doc_from_context(ctx, src);
+    let props = walk_schema_properties(ctx.children(Rule::SchemaProperty), src, ENUM_PROPS)?;
 
     let name_ctx = ctx
-        .identifier()
-        .ok_or_else(|| make_diagnostic(src, ctx, "missing enum name"))?;
-    let raw_identifier = identifier_text(&name_ctx);
+        .child(Rule::Identifier)
+        .ok_or_else(|| make_diagnostic(src, ctx, ErrorKind::Syntax, "missing enum name"))?;
+    let raw_identifier = identifier_text(src, name_ctx);
 
     // If compute_namespace returns None (no explicit @namespace and no dots
     // in the identifier), fall back to the enclosing namespace.
@@ -3099,7 +3863,8 @@ fn walk_enum<'input>(
     if is_invalid_type_name(&enum_name) {
         return Err(make_diagnostic(
             src,
-            &*name_ctx,
+            name_ctx,
+            ErrorKind::InvalidName,
             format!("Illegal name: {enum_name}"),
         ));
     }
@@ -3107,13 +3872,14 @@ fn walk_enum<'input>(
     // Collect enum symbols, rejecting duplicates.
     let mut symbols = Vec::new();
     let mut seen_symbols: HashSet<String> = HashSet::new();
-    for sym_ctx in ctx.enumSymbol_all() {
-        if let Some(sym_name_ctx) = sym_ctx.identifier() {
-            let sym_name = identifier_text(&sym_name_ctx);
+    for sym_ctx in ctx.children(Rule::EnumSymbol) {
+        if let Some(sym_name_ctx) = sym_ctx.child(Rule::Identifier) {
+            let sym_name = identifier_text(src, sym_name_ctx);
             if !seen_symbols.insert(sym_name.clone()) {
                 return Err(make_diagnostic(
                     src,
-                    &*sym_ctx,
+                    sym_ctx,
+                    ErrorKind::DuplicateSymbol,
                     format!("duplicate enum symbol: {sym_name}"),
                 ));
             }
@@ -3124,13 +3890,14 @@ fn walk_enum<'input>(
     // Get the default symbol if present (via `= symbolName;` after the closing brace).
     // Validate that it exists in the symbol list (Java's `EnumSchema` constructor
     // rejects unknown defaults with `SchemaParseException`).
-    let default_symbol = if let Some(default_ctx) = ctx.enumDefault() {
-        if let Some(id_ctx) = default_ctx.identifier() {
-            let sym = identifier_text(&id_ctx);
+    let default_symbol = if let Some(default_ctx) = ctx.child(Rule::EnumDefault) {
+        if let Some(id_ctx) = default_ctx.child(Rule::Identifier) {
+            let sym = identifier_text(src, id_ctx);
             if !symbols.contains(&sym) {
                 return Err(make_diagnostic(
                     src,
-                    &*id_ctx,
+                    id_ctx,
+                    ErrorKind::EnumDefault,
                     format!(
                         "The Enum Default: {} is not in the enum symbol set: {:?}",
                         sym, symbols
@@ -3160,24 +3927,18 @@ fn walk_enum<'input>(
 // Fixed
 // ==========================================================================
 
-fn walk_fixed<'input>(
-    ctx: &FixedDeclarationContextAll<'input>,
-    token_stream: &TS<'input>,
+fn walk_fixed(
+    ctx: &Node,
     src: &SourceInfo,
     enclosing_namespace: Option<&str>,
 ) -> Result<AvroSchema> {
-    let doc = extract_doc_from_context(ctx, token_stream, src);
-    let props = walk_schema_properties(
-        &ctx.schemaProperty_all(),
-        token_stream,
-        src,
-        NAMED_TYPE_PROPS,
-    )?;
+    let doc = extract_doc_from_context(ctx, src);
+    let props = walk_schema_properties(ctx.children(Rule::SchemaProperty), src, NAMED_TYPE_PROPS)?;
 
     let name_ctx = ctx
-        .identifier()
-        .ok_or_else(|| make_diagnostic(src, ctx, "missing fixed name"))?;
-    let raw_identifier = identifier_text(&name_ctx);
+        .child(Rule::Identifier)
+        .ok_or_else(|| make_diagnostic(src, ctx, ErrorKind::Syntax, "missing fixed name"))?;
+    let raw_identifier = identifier_text(src, name_ctx);
 
     // Fall back to enclosing namespace if no explicit namespace is given.
     let fixed_namespace = compute_namespace(&raw_identifier, props.namespace.as_deref())
@@ -3187,24 +3948,49 @@ fn walk_fixed<'input>(
     if is_invalid_type_name(&fixed_name) {
         return Err(make_diagnostic(
             src,
-            &*name_ctx,
+            name_ctx,
+            ErrorKind::InvalidName,
             format!("Illegal name: {fixed_name}"),
         ));
     }
 
     // Parse the size from the IntegerLiteral token.
     let size_tok = ctx
-        .size
-        .as_ref()
-        .ok_or_else(|| make_diagnostic(src, ctx, "missing fixed size"))?;
-    let size = parse_integer_as_u32(size_tok.get_text()).map_err(|e| {
+        .token(TokenType::IntegerLiteral)
+        .ok_or_else(|| make_diagnostic(src, ctx, ErrorKind::Syntax, "missing fixed size"))?;
+    let size = parse_integer_as_u32(src.token_text(size_tok)).map_err(|e| {
         make_diagnostic_from_token(
             src,
-            &**size_tok,
+            size_tok,
+            ErrorKind::InvalidFixedSize,
             format!("invalid fixed size for `{fixed_name}`: {e}"),
         )
     })?;
 
+    // A `decimal` whose precision does not fit in the fixed size cannot be
+    // read back: Java's `fromSchemaIgnoreInvalid` would drop the logical type
+    // and readers would see plain bytes. Reject it rather than emit it.
+    if src.options.promote_logical_types
+        && props.properties.get("logicalType") == Some(&Value::String("decimal".to_string()))
+        && let Some(precision) = props
+            .properties
+            .get("precision")
+            .and_then(json_value_as_u32)
+    {
+        let max_precision = max_decimal_precision(size);
+        if precision > max_precision {
+            return Err(make_diagnostic(
+                src,
+                ctx,
+                ErrorKind::InvalidDecimal,
+                format!(
+                    "invalid decimal precision for `{fixed_name}`: {precision} digits do not \
+                     fit in fixed({size}), which holds at most {max_precision}"
+                ),
+            ));
+        }
+    }
+
     let schema = AvroSchema::Fixed {
         name: fixed_name,
         namespace: fixed_namespace,
@@ -3219,7 +4005,11 @@ fn walk_fixed<'input>(
     // on every schema type, including Fixed. Named type declarations go through
     // `walk_fixed` rather than `apply_properties_to_schema`, so we must call
     // the validation here explicitly.
-    Ok(try_promote_logical_type(schema))
+    if src.options.promote_logical_types {
+        Ok(try_promote_logical_type(schema))
+    } else {
+        Ok(schema)
+    }
 }
 
 // ==========================================================================
@@ -3228,19 +4018,19 @@ fn walk_fixed<'input>(
 
 /// Walk a `fullType` node: collect schema properties, walk the inner
 /// `plainType`, then apply any custom properties to the resulting schema.
-fn walk_full_type<'input>(
-    ctx: &FullTypeContextAll<'input>,
-    token_stream: &TS<'input>,
-    src: &SourceInfo,
-    namespace: Option<&str>,
-) -> Result<AvroSchema> {
-    let props = walk_schema_properties(&ctx.schemaProperty_all(), token_stream, src, BARE_PROPS)?;
+fn walk_full_type(ctx: &Node, src: &SourceInfo, namespace: Option<&str>) -> Result<AvroSchema> {
+    let props = walk_schema_properties(ctx.children(Rule::SchemaProperty), src, BARE_PROPS)?;
 
-    let plain_ctx = ctx
-        .plainType()
-        .ok_or_else(|| make_diagnostic(src, ctx, "missing plain type in fullType"))?;
+    let plain_ctx = ctx.child(Rule::PlainType).ok_or_else(|| {
+        make_diagnostic(
+            src,
+            ctx,
+            ErrorKind::Syntax,
+            "missing plain type in fullType",
+        )
+    })?;
 
-    let schema = walk_plain_type(&plain_ctx, token_stream, src, namespace)?;
+    let schema = walk_plain_type(plain_ctx, src, namespace)?;
 
     // Type references may not be annotated. When the resolved type is a bare
     // reference to a previously-defined named type, any accumulated schema
@@ -3252,6 +4042,7 @@ fn walk_full_type<'input>(
         return Err(make_diagnostic(
             src,
             ctx,
+            ErrorKind::AnnotatedReference,
             "Type references may not be annotated",
         ));
     }
@@ -3271,7 +4062,7 @@ fn walk_full_type<'input>(
     // Apply custom properties to the schema. For nullable unions we apply
     // properties to the non-null branch (matching the Java behavior).
     let schema = if !props.properties.is_empty() {
-        apply_properties(schema, props.properties)
+        apply_properties(schema, props.properties, src.options.promote_logical_types)
     } else {
         schema
     };
@@ -3280,70 +4071,71 @@ fn walk_full_type<'input>(
 }
 
 /// Dispatch to array, map, union, or nullable type.
-fn walk_plain_type<'input>(
-    ctx: &PlainTypeContextAll<'input>,
-    token_stream: &TS<'input>,
-    src: &SourceInfo,
-    namespace: Option<&str>,
-) -> Result<AvroSchema> {
-    if let Some(array_ctx) = ctx.arrayType() {
-        return walk_array_type(&array_ctx, token_stream, src, namespace);
+fn walk_plain_type(ctx: &Node, src: &SourceInfo, namespace: Option<&str>) -> Result<AvroSchema> {
+    if let Some(array_ctx) = ctx.child(Rule::ArrayType) {
+        return walk_array_type(array_ctx, src, namespace);
     }
-    if let Some(map_ctx) = ctx.mapType() {
-        return walk_map_type(&map_ctx, token_stream, src, namespace);
+    if let Some(map_ctx) = ctx.child(Rule::MapType) {
+        return walk_map_type(map_ctx, src, namespace);
     }
-    if let Some(union_ctx) = ctx.unionType() {
-        return walk_union_type(&union_ctx, token_stream, src, namespace);
+    if let Some(union_ctx) = ctx.child(Rule::UnionType) {
+        return walk_union_type(union_ctx, src, namespace);
     }
-    if let Some(nullable_ctx) = ctx.nullableType() {
-        return walk_nullable_type(&nullable_ctx, token_stream, src, namespace);
+    if let Some(nullable_ctx) = ctx.child(Rule::NullableType) {
+        return walk_nullable_type(nullable_ctx, src, namespace);
     }
-    Err(make_diagnostic(src, ctx, "unrecognized plain type"))
+    Err(make_diagnostic(
+        src,
+        ctx,
+        ErrorKind::Syntax,
+        "unrecognized plain type",
+    ))
 }
 
 /// Walk a nullable type: either a primitive type or a named reference,
 /// optionally followed by `?` to make it nullable.
-fn walk_nullable_type<'input>(
-    ctx: &NullableTypeContextAll<'input>,
-    _token_stream: &TS<'input>,
-    src: &SourceInfo,
-    namespace: Option<&str>,
-) -> Result<AvroSchema> {
-    let base_type = if let Some(prim_ctx) = ctx.primitiveType() {
-        walk_primitive_type(&prim_ctx, src)?
-    } else if let Some(ref_ctx) = ctx.identifier() {
+fn walk_nullable_type(ctx: &Node, src: &SourceInfo, namespace: Option<&str>) -> Result<AvroSchema> {
+    let base_type = if let Some(prim_ctx) = ctx.child(Rule::PrimitiveType) {
+        walk_primitive_type(prim_ctx, src)?
+    } else if let Some(ref_ctx) = ctx.child(Rule::Identifier) {
         // Named type reference. Split the identifier into name and namespace
         // so the Reference carries them separately, enabling correct namespace
         // shortening during JSON serialization.
-        let type_name = identifier_text(&ref_ctx);
-        let ref_span = span_from_context(&*ref_ctx).map(|(o, l)| src.span(o, l));
-        if let Some((ns, name)) = type_name.rsplit_once('.') {
-            AvroSchema::Reference {
-                name: name.to_string(),
-                namespace: Some(ns.to_string()),
-                properties: HashMap::new(),
-                span: ref_span,
+        let type_name = identifier_text(src, ref_ctx);
+        let ref_span = span_from_context(src, ref_ctx).map(|(o, l)| src.span(o, l));
+        let escaped = context_text(src, ref_ctx).contains('`');
+        if let Some(logical_type) = micros_type_keyword(&type_name).filter(|_| !escaped) {
+            AvroSchema::Logical {
+                logical_type,
+                properties: Properties::new(),
             }
         } else {
+            let (name, namespace) = reference_name(&type_name, namespace);
             AvroSchema::Reference {
-                name: type_name.to_string(),
+                name: name.to_string(),
                 namespace: namespace.map(|s| s.to_string()),
-                properties: HashMap::new(),
+                properties: Properties::new(),
                 span: ref_span,
             }
         }
     } else {
-        return Err(make_diagnostic(src, ctx, "nullable type has no inner type"));
+        return Err(make_diagnostic(
+            src,
+            ctx,
+            ErrorKind::Syntax,
+            "nullable type has no inner type",
+        ));
     };
 
     // If the `?` token is present, wrap in a nullable union `[null, T]`.
     // Reject `null?` because it would produce the invalid union `[null, null]`
     // (Avro requires each type in a union to be unique). Java also rejects this.
-    if ctx.optional.is_some() {
+    if ctx.token(TokenType::QuestionMark).is_some() {
         if matches!(base_type, AvroSchema::Null) {
             return Err(make_diagnostic(
                 src,
                 ctx,
+                ErrorKind::DuplicateInUnion,
                 "`null` type cannot be made nullable",
             ));
         }
@@ -3356,56 +4148,77 @@ fn walk_nullable_type<'input>(
     }
 }
 
+/// The logical type named by a microsecond-precision time keyword:
+/// `time_micros`, `timestamp_micros`, or `local_timestamp_micros`.
+///
+/// Newer Avro IDL has these alongside the `_ms` keywords, but the grammar in
+/// `src/generated/` predates them, so they lex as identifiers and arrive here
+/// as would-be type references. Escaping with backticks keeps the reference.
+fn micros_type_keyword(name: &str) -> Option<LogicalType> {
+    match name {
+        "time_micros" => Some(LogicalType::TimeMicros),
+        "timestamp_micros" => Some(LogicalType::TimestampMicros),
+        "local_timestamp_micros" => Some(LogicalType::LocalTimestampMicros),
+        _ => None,
+    }
+}
+
 /// Walk a primitive type keyword and return the corresponding `AvroSchema`.
-fn walk_primitive_type<'input>(
-    ctx: &PrimitiveTypeContextAll<'input>,
-    src: &SourceInfo,
-) -> Result<AvroSchema> {
-    let type_tok = ctx
-        .typeName
-        .as_ref()
-        .ok_or_else(|| make_diagnostic(src, ctx, "missing primitive type name"))?;
-    let token_type = type_tok.get_token_type();
+fn walk_primitive_type(ctx: &Node, src: &SourceInfo) -> Result<AvroSchema> {
+    let (type_tok, token_type) = ctx.token_children().next().ok_or_else(|| {
+        make_diagnostic(src, ctx, ErrorKind::Syntax, "missing primitive type name")
+    })?;
+    // `decimal(precision [, scale])`
+    let mut sizes = ctx
+        .token_children()
+        .filter(|&(_, t)| t == TokenType::IntegerLiteral)
+        .map(|(index, _)| index);
+    let precision_tok = sizes.next();
+    let scale_tok = sizes.next();
 
     let schema = match token_type {
-        Idl_Boolean => AvroSchema::Boolean,
-        Idl_Int => AvroSchema::Int,
-        Idl_Long => AvroSchema::Long,
-        Idl_Float => AvroSchema::Float,
-        Idl_Double => AvroSchema::Double,
-        Idl_Bytes => AvroSchema::Bytes,
-        Idl_String => AvroSchema::String,
-        Idl_Null => AvroSchema::Null,
-        Idl_Date => AvroSchema::Logical {
+        TokenType::Boolean => AvroSchema::Boolean,
+        TokenType::Int => AvroSchema::Int,
+        TokenType::Long => AvroSchema::Long,
+        TokenType::Float => AvroSchema::Float,
+        TokenType::Double => AvroSchema::Double,
+        TokenType::Bytes => AvroSchema::Bytes,
+        TokenType::String => AvroSchema::String,
+        TokenType::Null => AvroSchema::Null,
+        TokenType::Date => AvroSchema::Logical {
             logical_type: LogicalType::Date,
-            properties: HashMap::new(),
+            properties: Properties::new(),
         },
-        Idl_Time => AvroSchema::Logical {
+        TokenType::Time => AvroSchema::Logical {
             logical_type: LogicalType::TimeMillis,
-            properties: HashMap::new(),
+            properties: Properties::new(),
         },
-        Idl_Timestamp => AvroSchema::Logical {
+        TokenType::Timestamp => AvroSchema::Logical {
             logical_type: LogicalType::TimestampMillis,
-            properties: HashMap::new(),
+            properties: Properties::new(),
         },
-        Idl_LocalTimestamp => AvroSchema::Logical {
+        TokenType::LocalTimestamp => AvroSchema::Logical {
             logical_type: LogicalType::LocalTimestampMillis,
-            properties: HashMap::new(),
+            properties: Properties::new(),
         },
-        Idl_UUID => AvroSchema::Logical {
+        TokenType::Uuid => AvroSchema::Logical {
             logical_type: LogicalType::Uuid,
-            properties: HashMap::new(),
+            properties: Properties::new(),
         },
-        Idl_Decimal => {
-            // decimal(precision [, scale])
-            let precision_tok = ctx
-                .precision
-                .as_ref()
-                .ok_or_else(|| make_diagnostic(src, ctx, "decimal type missing precision"))?;
-            let precision = parse_integer_as_u32(precision_tok.get_text()).map_err(|e| {
+        TokenType::Decimal => {
+            let precision_tok = precision_tok.ok_or_else(|| {
+                make_diagnostic(
+                    src,
+                    ctx,
+                    ErrorKind::Syntax,
+                    "decimal type missing precision",
+                )
+            })?;
+            let precision = parse_integer_as_u32(src.token_text(precision_tok)).map_err(|e| {
                 make_diagnostic_from_token(
                     src,
-                    &**precision_tok,
+                    precision_tok,
+                    ErrorKind::InvalidDecimal,
                     format!("invalid decimal precision: {e}"),
                 )
             })?;
@@ -3414,16 +4227,18 @@ fn walk_primitive_type<'input>(
             if precision == 0 {
                 return Err(make_diagnostic_from_token(
                     src,
-                    &**precision_tok,
+                    precision_tok,
+                    ErrorKind::InvalidDecimal,
                     "invalid decimal precision: 0 (must be positive)".to_string(),
                 ));
             }
 
-            let scale = if let Some(scale_tok) = ctx.scale.as_ref() {
-                parse_integer_as_u32(scale_tok.get_text()).map_err(|e| {
+            let scale = if let Some(scale_tok) = scale_tok {
+                parse_integer_as_u32(src.token_text(scale_tok)).map_err(|e| {
                     make_diagnostic_from_token(
                         src,
-                        &**scale_tok,
+                        scale_tok,
+                        ErrorKind::InvalidDecimal,
                         format!("invalid decimal scale: {e}"),
                     )
                 })?
@@ -3435,10 +4250,8 @@ fn walk_primitive_type<'input>(
             if scale > precision {
                 return Err(make_diagnostic_from_token(
                     src,
-                    &**ctx
-                        .scale
-                        .as_ref()
-                        .expect("scale token present when scale > 0"),
+                    scale_tok.expect("scale token present when scale > 0"),
+                    ErrorKind::InvalidDecimal,
                     format!(
                         "invalid decimal scale: {scale} \
                          (greater than precision: {precision})"
@@ -3448,14 +4261,15 @@ fn walk_primitive_type<'input>(
 
             AvroSchema::Logical {
                 logical_type: LogicalType::Decimal { precision, scale },
-                properties: HashMap::new(),
+                properties: Properties::new(),
             }
         }
         _ => {
             return Err(make_diagnostic_from_token(
                 src,
-                type_tok.as_ref(),
-                format!("unexpected primitive type token: {token_type}"),
+                type_tok,
+                ErrorKind::Syntax,
+                format!("unexpected primitive type token: {}", token_type.display()),
             ));
         }
     };
@@ -3464,64 +4278,70 @@ fn walk_primitive_type<'input>(
 }
 
 /// Walk `array<fullType>`.
-fn walk_array_type<'input>(
-    ctx: &ArrayTypeContextAll<'input>,
-    token_stream: &TS<'input>,
-    src: &SourceInfo,
-    namespace: Option<&str>,
-) -> Result<AvroSchema> {
-    let element_ctx = ctx
-        .fullType()
-        .ok_or_else(|| make_diagnostic(src, ctx, "array type missing element type"))?;
-    let items = walk_full_type(&element_ctx, token_stream, src, namespace)?;
+fn walk_array_type(ctx: &Node, src: &SourceInfo, namespace: Option<&str>) -> Result<AvroSchema> {
+    let element_ctx = ctx.child(Rule::FullType).ok_or_else(|| {
+        make_diagnostic(
+            src,
+            ctx,
+            ErrorKind::Syntax,
+            "array type missing element type",
+        )
+    })?;
+    let items = walk_full_type(element_ctx, src, namespace)?;
     Ok(AvroSchema::Array {
         items: Box::new(items),
-        properties: HashMap::new(),
+        properties: Properties::new(),
     })
 }
 
 /// Walk `map<fullType>`.
-fn walk_map_type<'input>(
-    ctx: &MapTypeContextAll<'input>,
-    token_stream: &TS<'input>,
-    src: &SourceInfo,
-    namespace: Option<&str>,
-) -> Result<AvroSchema> {
-    let value_ctx = ctx
-        .fullType()
-        .ok_or_else(|| make_diagnostic(src, ctx, "map type missing value type"))?;
-    let values = walk_full_type(&value_ctx, token_stream, src, namespace)?;
+fn walk_map_type(ctx: &Node, src: &SourceInfo, namespace: Option<&str>) -> Result<AvroSchema> {
+    let value_ctx = ctx.child(Rule::FullType).ok_or_else(|| {
+        make_diagnostic(src, ctx, ErrorKind::Syntax, "map type missing value type")
+    })?;
+    let values = walk_full_type(value_ctx, src, namespace)?;
     Ok(AvroSchema::Map {
         values: Box::new(values),
-        properties: HashMap::new(),
+        properties: Properties::new(),
     })
 }
 
 /// Walk `union { fullType, fullType, ... }`.
-fn walk_union_type<'input>(
-    ctx: &UnionTypeContextAll<'input>,
-    token_stream: &TS<'input>,
-    src: &SourceInfo,
-    namespace: Option<&str>,
-) -> Result<AvroSchema> {
+fn walk_union_type(ctx: &Node, src: &SourceInfo, namespace: Option<&str>) -> Result<AvroSchema> {
+    // Each type is kept with the branch it came from, for error spans. A
+    // flattened inner union contributes several types from one branch.
     let mut types = Vec::new();
-    for ft_ctx in ctx.fullType_all() {
-        types.push(walk_full_type(&ft_ctx, token_stream, src, namespace)?);
-    }
+    let mut type_ctxs = Vec::new();
+    for ft_ctx in ctx.children(Rule::FullType) {
+        let schema = walk_full_type(ft_ctx, src, namespace)?;
+        let AvroSchema::Union { types: inner, .. } = schema else {
+            types.push(schema);
+            type_ctxs.push(ft_ctx);
+            continue;
+        };
 
-    // Reject nested unions (Avro spec: "Unions may not immediately contain
-    // other unions").
-    let ft_ctxs = ctx.fullType_all();
-    for (i, t) in types.iter().enumerate() {
-        if matches!(t, AvroSchema::Union { .. }) {
+        // Reject nested unions (Avro spec: "Unions may not immediately contain
+        // other unions"), unless asked to flatten them into this one.
+        if !src.options.flatten_nested_unions {
             return Err(make_diagnostic(
                 src,
-                &*ft_ctxs[i],
+                ft_ctx,
+                ErrorKind::NestedUnion,
                 "Unions may not immediately contain other unions \
                  (per the Avro specification, §schemas). Note: Java avro-tools \
                  incorrectly accepts this syntax, producing an empty union.",
             ));
         }
+        src.warnings.borrow_mut().push(Warning::spanning(
+            WarningKind::NestedUnion,
+            "flattening a union nested in another union into it".to_string(),
+            src,
+            ft_ctx,
+        ));
+        for schema in inner {
+            types.push(schema);
+            type_ctxs.push(ft_ctx);
+        }
     }
 
     // Reject duplicate types (Avro spec: "Unions may not contain more than
@@ -3529,14 +4349,20 @@ fn walk_union_type<'input>(
     // and fixed"). For anonymous types the key is the type name; for named
     // types the key is the fully qualified name.
     let mut seen_keys: HashSet<String> = HashSet::new();
-    for (i, t) in types.iter().enumerate() {
+    for (t, ft_ctx) in types.iter().zip(&type_ctxs) {
         let key = t.union_type_key();
         if !seen_keys.insert(key.clone()) {
-            return Err(make_diagnostic(
-                src,
-                &*ft_ctxs[i],
-                format!("Duplicate in union: {key}"),
-            ));
+            return Err(ParseDiagnostic {
+                kind: ErrorKind::DuplicateInUnion,
+                span: context_span(src, ft_ctx),
+                message: format!("Duplicate in union: {key}"),
+                label: None,
+                help: None,
+                related: Vec::new(),
+                name: Some(key),
+                replacement: None,
+            }
+            .into());
         }
     }
 
@@ -3550,21 +4376,19 @@ fn walk_union_type<'input>(
 // Message Declaration
 // ==========================================================================
 
-fn walk_message<'input>(
-    ctx: &MessageDeclarationContextAll<'input>,
-    token_stream: &TS<'input>,
+fn walk_message(
+    ctx: &Node,
     src: &SourceInfo,
     namespace: Option<&str>,
 ) -> Result<(String, Message)> {
-    let doc = extract_doc_from_context(ctx, token_stream, src);
-    let props =
-        walk_schema_properties(&ctx.schemaProperty_all(), token_stream, src, MESSAGE_PROPS)?;
+    let doc = extract_doc_from_context(ctx, src);
+    let props = walk_schema_properties(ctx.children(Rule::SchemaProperty), src, MESSAGE_PROPS)?;
 
     // Walk the result type. `void` maps to Null.
-    let result_ctx = ctx
-        .resultType()
-        .ok_or_else(|| make_diagnostic(src, ctx, "missing message return type"))?;
-    let response = walk_result_type(&result_ctx, token_stream, src, namespace)?;
+    let result_ctx = ctx.child(Rule::ResultType).ok_or_else(|| {
+        make_diagnostic(src, ctx, ErrorKind::Syntax, "missing message return type")
+    })?;
+    let response = walk_result_type(result_ctx, src, namespace)?;
 
     // When the return type is a named type reference, any message-level
     // annotations are ambiguous (do they apply to the message or to the
@@ -3574,44 +4398,59 @@ fn walk_message<'input>(
         return Err(make_diagnostic(
             src,
             ctx,
+            ErrorKind::AnnotatedReference,
             "Type references may not be annotated",
         ));
     }
 
-    // The message name is stored in the `name` field of the context ext.
-    let name_ctx = ctx
-        .name
-        .as_ref()
-        .ok_or_else(|| make_diagnostic(src, ctx, "missing message name"))?;
-    let message_name = identifier_text(name_ctx);
+    // The message name is the first identifier; any others are the errors
+    // it throws.
+    let mut identifiers = ctx.children(Rule::Identifier);
+    let name_ctx = identifiers
+        .next()
+        .ok_or_else(|| make_diagnostic(src, ctx, ErrorKind::Syntax, "missing message name"))?;
+    let message_name = identifier_text(src, name_ctx);
 
     // Walk formal parameters.
     let mut request_fields = Vec::new();
     let mut seen_param_names: HashSet<String> = HashSet::new();
-    for param_ctx in ctx.formalParameter_all() {
-        let param_doc = extract_doc_from_context(&*param_ctx, token_stream, src);
+    for param_ctx in ctx.children(Rule::FormalParameter) {
+        let param_doc = extract_doc_from_context(param_ctx, src);
 
-        let ft_ctx = param_ctx
-            .fullType()
-            .ok_or_else(|| make_diagnostic(src, &*param_ctx, "missing parameter type"))?;
-        let param_type = walk_full_type(&ft_ctx, token_stream, src, namespace)?;
+        let ft_ctx = param_ctx.child(Rule::FullType).ok_or_else(|| {
+            make_diagnostic(src, param_ctx, ErrorKind::Syntax, "missing parameter type")
+        })?;
+        let param_type = walk_full_type(ft_ctx, src, namespace)?;
 
-        let var_ctx = param_ctx
-            .variableDeclaration()
-            .ok_or_else(|| make_diagnostic(src, &*param_ctx, "missing parameter variable"))?;
-        let field = walk_variable(
-            &var_ctx,
+        let var_ctx = param_ctx.child(Rule::VariableDeclaration).ok_or_else(|| {
+            make_diagnostic(
+                src,
+                param_ctx,
+                ErrorKind::Syntax,
+                "missing parameter variable",
+            )
+        })?;
+        let param_defaults = src.options.param_defaults;
+        let mut field = walk_variable(
+            var_ctx,
             &param_type,
             param_doc.as_deref(),
-            token_stream,
             src,
-            namespace,
             None, // message parameters have no enclosing record name
+            param_defaults == ParamDefaults::Validate,
         )?;
+        if param_defaults == ParamDefaults::Omit && field.default.is_some() {
+            // Treat the parameter as if no default had been written. That
+            // also undoes the union reordering `fix_optional_schema` applies
+            // for a non-null default on `type?`.
+            field.default = None;
+            field.schema = param_type;
+        }
         if !seen_param_names.insert(field.name.clone()) {
             return Err(make_diagnostic(
                 src,
-                &*param_ctx,
+                param_ctx,
+                ErrorKind::DuplicateField,
                 format!(
                     "duplicate parameter '{}' in message '{}'",
                     field.name, message_name
@@ -3622,7 +4461,7 @@ fn walk_message<'input>(
     }
 
     // Check for oneway.
-    let one_way = ctx.oneway.is_some();
+    let one_way = ctx.token(TokenType::Oneway).is_some();
 
     // One-way messages must return void (AvroSchema::Null). The Avro specification
     // requires one-way messages to have a null response and no errors. The Java
@@ -3631,32 +4470,26 @@ fn walk_message<'input>(
         return Err(make_diagnostic(
             src,
             ctx,
+            ErrorKind::OnewayResponse,
             format!("One-way message '{}' must return void", message_name),
         ));
     }
 
-    // Check for throws clause. The `errors` field on the context ext struct
-    // contains only the error type identifiers (not the message name).
-    let errors = if !ctx.errors.is_empty() {
+    // Check for throws clause. The identifiers after the message name are
+    // the error types.
+    let error_ctxs: Vec<&Node> = identifiers.collect();
+    let errors = if !error_ctxs.is_empty() {
         let mut error_schemas = Vec::new();
-        for error_id_ctx in &ctx.errors {
-            let error_name = identifier_text(error_id_ctx);
-            let error_span = span_from_context(&**error_id_ctx).map(|(o, l)| src.span(o, l));
-            if let Some((ns, name)) = error_name.rsplit_once('.') {
-                error_schemas.push(AvroSchema::Reference {
-                    name: name.to_string(),
-                    namespace: Some(ns.to_string()),
-                    properties: HashMap::new(),
-                    span: error_span,
-                });
-            } else {
-                error_schemas.push(AvroSchema::Reference {
-                    name: error_name.to_string(),
-                    namespace: namespace.map(|s| s.to_string()),
-                    properties: HashMap::new(),
-                    span: error_span,
-                });
-            }
+        for error_id_ctx in error_ctxs {
+            let error_name = identifier_text(src, error_id_ctx);
+            let error_span = span_from_context(src, error_id_ctx).map(|(o, l)| src.span(o, l));
+            let (name, namespace) = reference_name(&error_name, namespace);
+            error_schemas.push(AvroSchema::Reference {
+                name: name.to_string(),
+                namespace: namespace.map(|s| s.to_string()),
+                properties: Properties::new(),
+                span: error_span,
+            });
         }
         Some(error_schemas)
     } else if one_way {
@@ -3683,19 +4516,14 @@ fn walk_message<'input>(
 }
 
 /// Walk a `resultType`: either `void` (produces Null) or a `plainType`.
-fn walk_result_type<'input>(
-    ctx: &ResultTypeContextAll<'input>,
-    token_stream: &TS<'input>,
-    src: &SourceInfo,
-    namespace: Option<&str>,
-) -> Result<AvroSchema> {
+fn walk_result_type(ctx: &Node, src: &SourceInfo, namespace: Option<&str>) -> Result<AvroSchema> {
     // If there's a Void token, return Null.
-    if ctx.Void().is_some() {
+    if ctx.token(TokenType::Void).is_some() {
         return Ok(AvroSchema::Null);
     }
     // Otherwise walk the plainType child.
-    if let Some(plain_ctx) = ctx.plainType() {
-        return walk_plain_type(&plain_ctx, token_stream, src, namespace);
+    if let Some(plain_ctx) = ctx.child(Rule::PlainType) {
+        return walk_plain_type(plain_ctx, src, namespace);
     }
     // Fallback: void.
     Ok(AvroSchema::Null)
@@ -3705,91 +4533,93 @@ fn walk_result_type<'input>(
 // JSON Value Walking
 // ==========================================================================
 
-fn walk_json_value<'input>(
-    ctx: &JsonValueContextAll<'input>,
-    token_stream: &TS<'input>,
-    src: &SourceInfo,
-) -> Result<Value> {
-    if let Some(obj_ctx) = ctx.jsonObject() {
-        return walk_json_object(&obj_ctx, token_stream, src);
+fn walk_json_value(ctx: &Node, src: &SourceInfo) -> Result<Value> {
+    if let Some(obj_ctx) = ctx.child(Rule::JsonObject) {
+        return walk_json_object(obj_ctx, src);
     }
-    if let Some(arr_ctx) = ctx.jsonArray() {
-        return walk_json_array(&arr_ctx, token_stream, src);
+    if let Some(arr_ctx) = ctx.child(Rule::JsonArray) {
+        return walk_json_array(arr_ctx, src);
     }
-    if let Some(lit_ctx) = ctx.jsonLiteral() {
-        return walk_json_literal(&lit_ctx, src);
+    if let Some(lit_ctx) = ctx.child(Rule::JsonLiteral) {
+        return walk_json_literal(lit_ctx, src);
     }
-    Err(make_diagnostic(src, ctx, "empty JSON value"))
+    Err(make_diagnostic(
+        src,
+        ctx,
+        ErrorKind::Syntax,
+        "empty JSON value",
+    ))
 }
 
-fn walk_json_literal<'input>(
-    ctx: &JsonLiteralContextAll<'input>,
-    src: &SourceInfo,
-) -> Result<Value> {
-    let tok = ctx
-        .literal
-        .as_ref()
-        .ok_or_else(|| make_diagnostic(src, ctx, "missing JSON literal token"))?;
-    let token_type = tok.get_token_type();
-    let text = tok.get_text();
+fn walk_json_literal(ctx: &Node, src: &SourceInfo) -> Result<Value> {
+    let (tok, token_type) = ctx.token_children().next().ok_or_else(|| {
+        make_diagnostic(src, ctx, ErrorKind::Syntax, "missing JSON literal token")
+    })?;
+    let text = src.token_text(tok);
 
     match token_type {
-        Idl_Null => Ok(Value::Null),
-        Idl_BTrue => Ok(Value::Bool(true)),
-        Idl_BFalse => Ok(Value::Bool(false)),
-        Idl_StringLiteral => {
+        TokenType::Null => Ok(Value::Null),
+        TokenType::BTrue => Ok(Value::Bool(true)),
+        TokenType::BFalse => Ok(Value::Bool(false)),
+        TokenType::StringLiteral => {
             let unescaped = get_string_from_literal(text);
             Ok(Value::String(unescaped))
         }
-        Idl_IntegerLiteral => parse_integer_literal(text).map_err(|e| {
-            make_diagnostic_from_token(src, tok.as_ref(), format!("invalid integer literal: {e}"))
+        TokenType::IntegerLiteral => parse_integer_literal(text).map_err(|e| {
+            make_diagnostic_from_token(
+                src,
+                tok,
+                ErrorKind::Syntax,
+                format!("invalid integer literal: {e}"),
+            )
         }),
-        Idl_FloatingPointLiteral => parse_floating_point_literal(text).map_err(|e| {
+        TokenType::FloatingPointLiteral => parse_floating_point_literal(text).map_err(|e| {
             make_diagnostic_from_token(
                 src,
-                tok.as_ref(),
+                tok,
+                ErrorKind::Syntax,
                 format!("invalid floating-point literal: {e}"),
             )
         }),
         _ => Err(make_diagnostic_from_token(
             src,
-            tok.as_ref(),
-            format!("unexpected JSON literal token type: {token_type}"),
+            tok,
+            ErrorKind::Syntax,
+            format!(
+                "unexpected JSON literal token type: {}",
+                token_type.display()
+            ),
         )),
     }
 }
 
-fn walk_json_object<'input>(
-    ctx: &JsonObjectContextAll<'input>,
-    token_stream: &TS<'input>,
-    src: &SourceInfo,
-) -> Result<Value> {
+fn walk_json_object(ctx: &Node, src: &SourceInfo) -> Result<Value> {
     let mut map = serde_json::Map::new();
-    for pair_ctx in ctx.jsonPair_all() {
-        let key_tok = pair_ctx
-            .name
-            .as_ref()
-            .ok_or_else(|| make_diagnostic(src, &*pair_ctx, "missing JSON object key"))?;
-        let key = get_string_from_literal(key_tok.get_text());
-
-        let value_ctx = pair_ctx
-            .jsonValue()
-            .ok_or_else(|| make_diagnostic(src, &*pair_ctx, "missing JSON object value"))?;
-        let value = walk_json_value(&value_ctx, token_stream, src)?;
+    for pair_ctx in ctx.children(Rule::JsonPair) {
+        let key_tok = pair_ctx.token(TokenType::StringLiteral).ok_or_else(|| {
+            make_diagnostic(src, pair_ctx, ErrorKind::Syntax, "missing JSON object key")
+        })?;
+        let key = get_string_from_literal(src.token_text(key_tok));
+
+        let value_ctx = pair_ctx.child(Rule::JsonValue).ok_or_else(|| {
+            make_diagnostic(
+                src,
+                pair_ctx,
+                ErrorKind::Syntax,
+                "missing JSON object value",
+            )
+        })?;
+        let value = walk_json_value(value_ctx, src)?;
 
         map.insert(key, value);
     }
     Ok(Value::Object(map))
 }
 
-fn walk_json_array<'input>(
-    ctx: &JsonArrayContextAll<'input>,
-    token_stream: &TS<'input>,
-    src: &SourceInfo,
-) -> Result<Value> {
+fn walk_json_array(ctx: &Node, src: &SourceInfo) -> Result<Value> {
     let mut elements = Vec::new();
-    for val_ctx in ctx.jsonValue_all() {
-        elements.push(walk_json_value(&val_ctx, token_stream, src)?);
+    for val_ctx in ctx.children(Rule::JsonValue) {
+        elements.push(walk_json_value(val_ctx, src)?);
     }
     Ok(Value::Array(elements))
 }
@@ -3798,12 +4628,15 @@ fn walk_json_array<'input>(
 // Helper Functions
 // ==========================================================================
 
-/// Extract the text from an `IdentifierContext`, removing backtick escapes.
-fn identifier_text<'input>(ctx: &IdentifierContextAll<'input>) -> String {
-    // The generated parser stores the matched token in `ctx.word`.
-    // We use `get_text()` on the context itself as a reliable fallback.
-    let text = ctx.get_text();
-    text.replace('`', "")
+/// Extract the text from an `identifier` node, removing backtick escapes.
+fn identifier_text(src: &SourceInfo, ctx: &Node) -> String {
+    context_text(src, ctx).replace('`', "")
+}
+
+/// The source text a parse tree node matched.
+fn context_text(src: &SourceInfo, ctx: &Node) -> &'static str {
+    let span = context_span(src, ctx);
+    &src.source[span.offset..span.offset + span.length]
 }
 
 /// Strip surrounding quotes from a string literal and unescape Java-style
@@ -3989,36 +4822,57 @@ fn parse_integer_literal(text: &str) -> Result<Value> {
     // octal (leading "0"). We replicate that logic.
     let long_value: i64 = if number.starts_with("0x") || number.starts_with("0X") {
         let hex = &number[2..];
-        i64::from_str_radix(hex, 16)
-            .map_err(|e| miette::miette!("invalid hex integer literal '{text}': {e}"))?
+        i64::from_str_radix(hex, 16).map_err(|e| {
+            miette::miette!(
+                code = ErrorKind::Syntax.code(),
+                "invalid hex integer literal '{text}': {e}"
+            )
+        })?
     } else if number.starts_with('-') && (number.starts_with("-0x") || number.starts_with("-0X")) {
         let hex = &number[3..];
-        let abs = i64::from_str_radix(hex, 16)
-            .map_err(|e| miette::miette!("invalid hex integer literal '{text}': {e}"))?;
+        let abs = i64::from_str_radix(hex, 16).map_err(|e| {
+            miette::miette!(
+                code = ErrorKind::Syntax.code(),
+                "invalid hex integer literal '{text}': {e}"
+            )
+        })?;
         -abs
     } else if number.starts_with('0') && number.len() > 1 && !number.contains('.') {
         // Octal.
-        i64::from_str_radix(&number, 8)
-            .map_err(|e| miette::miette!("invalid octal integer literal '{text}': {e}"))?
+        i64::from_str_radix(&number, 8).map_err(|e| {
+            miette::miette!(
+                code = ErrorKind::Syntax.code(),
+                "invalid octal integer literal '{text}': {e}"
+            )
+        })?
     } else if number.starts_with("-0") && number.len() > 2 && !number.contains('.') {
         let oct = &number[1..];
-        let abs = i64::from_str_radix(oct, 8)
-            .map_err(|e| miette::miette!("invalid octal integer literal '{text}': {e}"))?;
+        let abs = i64::from_str_radix(oct, 8).map_err(|e| {
+            miette::miette!(
+                code = ErrorKind::Syntax.code(),
+                "invalid octal integer literal '{text}': {e}"
+            )
+        })?;
         -abs
     } else {
-        number
-            .parse::<i64>()
-            .map_err(|e| miette::miette!("invalid integer literal '{text}': {e}"))?
+        number.parse::<i64>().map_err(|e| {
+            miette::miette!(
+                code = ErrorKind::Syntax.code(),
+                "invalid integer literal '{text}': {e}"
+            )
+        })?
     };
 
     let int_value = long_value as i32;
     if coerce_to_long || int_value as i64 != long_value {
         // Doesn't fit in i32 or explicitly long -- use i64.
-        Ok(serde_json::to_value(long_value)
-            .map_err(|e| miette::miette!("JSON number error: {e}"))?)
+        Ok(serde_json::to_value(long_value).map_err(|e| {
+            miette::miette!(code = ErrorKind::Syntax.code(), "JSON number error: {e}")
+        })?)
     } else {
-        Ok(serde_json::to_value(int_value)
-            .map_err(|e| miette::miette!("JSON number error: {e}"))?)
+        Ok(serde_json::to_value(int_value).map_err(|e| {
+            miette::miette!(code = ErrorKind::Syntax.code(), "JSON number error: {e}")
+        })?)
     }
 }
 
@@ -4093,9 +4947,12 @@ fn parse_float_text(text: &str) -> Result<f64> {
     }
 
     // Standard decimal float — Rust's f64::from_str handles this directly.
-    number
-        .parse::<f64>()
-        .map_err(|e| miette::miette!("invalid floating point literal '{text}': {e}"))
+    number.parse::<f64>().map_err(|e| {
+        miette::miette!(
+            code = ErrorKind::Syntax.code(),
+            "invalid floating point literal '{text}': {e}"
+        )
+    })
 }
 
 /// Parse the body of a hex floating-point literal (everything after the `0x`
@@ -4113,13 +4970,19 @@ fn parse_hex_float_mantissa_and_exponent(hex_body: &str, original: &str) -> Resu
     // Split on the binary exponent marker (p/P). The grammar guarantees
     // exactly one is present.
     let (mantissa_str, exp_str) = hex_body.split_once(['p', 'P']).ok_or_else(|| {
-        miette::miette!("invalid hex float literal '{original}': missing 'p'/'P' exponent")
+        miette::miette!(
+            code = ErrorKind::Syntax.code(),
+            "invalid hex float literal '{original}': missing 'p'/'P' exponent"
+        )
     })?;
 
     // Parse the binary exponent (decimal integer, possibly signed).
-    let exponent: i32 = exp_str
-        .parse()
-        .map_err(|e| miette::miette!("invalid hex float exponent in '{original}': {e}"))?;
+    let exponent: i32 = exp_str.parse().map_err(|e| {
+        miette::miette!(
+            code = ErrorKind::Syntax.code(),
+            "invalid hex float exponent in '{original}': {e}"
+        )
+    })?;
 
     // Parse the hex mantissa, which may contain a '.' decimal point.
     let mantissa = if let Some((int_part, frac_part)) = mantissa_str.split_once('.') {
@@ -4128,9 +4991,12 @@ fn parse_hex_float_mantissa_and_exponent(hex_body: &str, original: &str) -> Resu
         let int_val = if int_part.is_empty() {
             0.0
         } else {
-            u64::from_str_radix(int_part, 16)
-                .map_err(|e| miette::miette!("invalid hex float mantissa in '{original}': {e}"))?
-                as f64
+            u64::from_str_radix(int_part, 16).map_err(|e| {
+                miette::miette!(
+                    code = ErrorKind::Syntax.code(),
+                    "invalid hex float mantissa in '{original}': {e}"
+                )
+            })? as f64
         };
 
         // Fractional part: each hex digit after the point represents
@@ -4139,7 +5005,10 @@ fn parse_hex_float_mantissa_and_exponent(hex_body: &str, original: &str) -> Resu
         let mut place = 1.0_f64 / 16.0;
         for ch in frac_part.chars() {
             let digit = ch.to_digit(16).ok_or_else(|| {
-                miette::miette!("invalid hex digit '{ch}' in float literal '{original}'")
+                miette::miette!(
+                    code = ErrorKind::Syntax.code(),
+                    "invalid hex digit '{ch}' in float literal '{original}'"
+                )
             })? as f64;
             frac_val += digit * place;
             place /= 16.0;
@@ -4148,16 +5017,19 @@ fn parse_hex_float_mantissa_and_exponent(hex_body: &str, original: &str) -> Resu
         int_val + frac_val
     } else {
         // No decimal point — the mantissa is a plain hex integer.
-        u64::from_str_radix(mantissa_str, 16)
-            .map_err(|e| miette::miette!("invalid hex float mantissa in '{original}': {e}"))?
-            as f64
+        u64::from_str_radix(mantissa_str, 16).map_err(|e| {
+            miette::miette!(
+                code = ErrorKind::Syntax.code(),
+                "invalid hex float mantissa in '{original}': {e}"
+            )
+        })? as f64
     };
 
     Ok(mantissa * 2.0_f64.powi(exponent))
 }
 
 /// Parse an integer literal text into a u32 (for fixed size, decimal precision/scale).
-fn parse_integer_as_u32(text: &str) -> Result<u32> {
+fn parse_integer_as_u32(text: &str) -> std::result::Result<u32, String> {
     let number = text.replace('_', "");
 
     // Detect negative numbers early so we can produce a clear error instead
@@ -4165,7 +5037,7 @@ fn parse_integer_as_u32(text: &str) -> Result<u32> {
     // found in string"), which is confusing when the input is e.g. `-5`.
     let stripped = number.trim_start_matches(|c: char| c.is_ascii_whitespace());
     if stripped.starts_with('-') {
-        return Err(miette::miette!(
+        return Err(format!(
             "value must be a non-negative integer, got `{text}`"
         ));
     }
@@ -4180,25 +5052,23 @@ fn parse_integer_as_u32(text: &str) -> Result<u32> {
     Ok(value)
 }
 
-/// Convert a `ParseIntError` into a user-friendly miette error, mapping
+/// Convert a `ParseIntError` into a user-friendly message, mapping
 /// Rust's internal error kinds to messages that make sense in context.
-fn friendly_int_error(text: &str, e: &std::num::ParseIntError) -> miette::Report {
+fn friendly_int_error(text: &str, e: &std::num::ParseIntError) -> String {
     use std::num::IntErrorKind;
     match e.kind() {
         IntErrorKind::PosOverflow => {
-            miette::miette!("integer `{text}` is too large (maximum is {})", u32::MAX)
+            format!("integer `{text}` is too large (maximum is {})", u32::MAX)
         }
         IntErrorKind::NegOverflow => {
-            miette::miette!("value must be a non-negative integer, got `{text}`")
+            format!("value must be a non-negative integer, got `{text}`")
         }
         IntErrorKind::InvalidDigit => {
-            miette::miette!("invalid integer `{text}`: contains non-digit characters")
-        }
-        IntErrorKind::Empty => {
-            miette::miette!("expected an integer, got an empty value")
+            format!("invalid integer `{text}`: contains non-digit characters")
         }
+        IntErrorKind::Empty => "expected an integer, got an empty value".to_string(),
         _ => {
-            miette::miette!("invalid integer `{text}`: {e}")
+            format!("invalid integer `{text}`: {e}")
         }
     }
 }
@@ -4264,36 +5134,28 @@ fn is_non_nullable_union(schema: &AvroSchema) -> bool {
 /// reorder the union to `[T, null]` so that the default value matches the first
 /// branch. This matches the Java `fixOptionalSchema` behavior.
 fn fix_optional_schema(schema: AvroSchema, default_value: Option<&Value>) -> AvroSchema {
-    match &schema {
-        AvroSchema::Union {
-            types,
-            is_nullable_type: true,
-        } if types.len() == 2 => {
-            let non_null_default = match default_value {
-                Some(Value::Null) | None => false,
-                Some(_) => true,
-            };
+    let non_null_default = match default_value {
+        Some(Value::Null) | None => false,
+        Some(_) => true,
+    };
+    if !(non_null_default && schema.is_nullable_type() && schema.is_nullable()) {
+        return schema;
+    }
 
-            if non_null_default {
-                // Reorder: put the non-null type first, null second.
-                let null_schema = types[0].clone();
-                let non_null_schema = types[1].clone();
-                AvroSchema::Union {
-                    types: vec![non_null_schema, null_schema],
-                    is_nullable_type: true,
-                }
-            } else {
-                schema
-            }
-        }
-        _ => schema,
+    // Reorder: put the non-null type first, null second.
+    let mut types: Vec<AvroSchema> = schema.non_null_branches().cloned().collect();
+    types.push(AvroSchema::Null);
+    AvroSchema::Union {
+        types,
+        is_nullable_type: true,
     }
 }
 
 /// Apply custom schema properties to a schema. For nullable unions, apply them
 /// to the non-null branch (matching the Java behavior where properties go on
-/// `type.getTypes().get(1)` for optional types).
-fn apply_properties(schema: AvroSchema, properties: HashMap<String, Value>) -> AvroSchema {
+/// `type.getTypes().get(1)` for optional types). `promote` is passed on to
+/// `apply_properties_to_schema`.
+fn apply_properties(schema: AvroSchema, properties: Properties, promote: bool) -> AvroSchema {
     match schema {
         AvroSchema::Union {
             types,
@@ -4309,26 +5171,33 @@ fn apply_properties(schema: AvroSchema, properties: HashMap<String, Value>) -> A
                 0
             };
             new_types[non_null_idx] =
-                apply_properties_to_schema(new_types[non_null_idx].clone(), properties);
+                apply_properties_to_schema(new_types[non_null_idx].clone(), properties, promote);
             AvroSchema::Union {
                 types: new_types,
                 is_nullable_type: true,
             }
         }
-        other => apply_properties_to_schema(other, properties),
+        other => apply_properties_to_schema(other, properties, promote),
     }
 }
 
 /// Apply properties directly to a single schema node.
 ///
 /// Merges the given properties into the schema using
-/// `AvroSchema::with_merged_properties`, then attempts logical type promotion
-/// for primitives whose new properties include a recognized `logicalType`.
+/// `AvroSchema::with_merged_properties`, then, if `promote` is set, attempts
+/// logical type promotion for primitives whose new properties include a
+/// recognized `logicalType`.
 fn apply_properties_to_schema(
     schema: AvroSchema,
-    properties: HashMap<String, Value>,
+    properties: Properties,
+    promote: bool,
 ) -> AvroSchema {
-    try_promote_logical_type(schema.with_merged_properties(properties))
+    let schema = schema.with_merged_properties(properties);
+    if promote {
+        try_promote_logical_type(schema)
+    } else {
+        schema
+    }
 }
 
 /// If the schema carries a `logicalType` property matching a recognized Avro
@@ -4340,8 +5209,8 @@ fn apply_properties_to_schema(
 /// primitive base is compatible (e.g., `date` requires `int`).
 ///
 /// For `Fixed` schemas: validates the logical type annotation against the fixed
-/// size (e.g., `duration` requires `fixed(12)`, `decimal` checks precision fits
-/// within the fixed size). Valid combinations are left as `Fixed` with the
+/// size (e.g., `duration` requires `fixed(12)`, `uuid` requires `fixed(16)`,
+/// `decimal` checks precision fits within the fixed size). Valid combinations are left as `Fixed` with the
 /// `logicalType` property intact, since the JSON output is already correct.
 /// Invalid combinations are also left as-is, matching Java's
 /// `fromSchemaIgnoreInvalid` behavior which silently ignores invalid logical
@@ -4384,10 +5253,10 @@ fn try_promote_logical_type(schema: AvroSchema) -> AvroSchema {
 
             // Remove the consumed keys from properties so they are not
             // duplicated in the serialized output.
-            properties.remove("logicalType");
+            properties.shift_remove("logicalType");
             if matches!(logical_type, LogicalType::Decimal { .. }) {
-                properties.remove("precision");
-                properties.remove("scale");
+                properties.shift_remove("precision");
+                properties.shift_remove("scale");
             }
 
             AvroSchema::Logical {
@@ -4400,8 +5269,9 @@ fn try_promote_logical_type(schema: AvroSchema) -> AvroSchema {
         // Fixed schemas: validate logical type annotations against the fixed
         // size. Java's `copyProperties` calls `fromSchemaIgnoreInvalid` on all
         // schema types, including Fixed. Valid combinations (duration on
-        // fixed(12), decimal on fixed(N)) are recognized; invalid ones are
-        // silently ignored (the logicalType property stays but is not promoted).
+        // fixed(12), uuid on fixed(16), decimal on fixed(N)) are recognized;
+        // invalid ones are silently ignored (the logicalType property stays
+        // but is not promoted).
         //
         // Unlike primitive-based logical types, Fixed schemas are not promoted
         // to `AvroSchema::Logical` because the `Logical` variant is designed
@@ -4424,11 +5294,12 @@ fn try_promote_logical_type(schema: AvroSchema) -> AvroSchema {
                     .filter(|&v| v <= i32::MAX as u32);
 
                 // Validate the logical type against the fixed base. This call
-                // mirrors Java's validation: duration requires fixed(12),
-                // decimal checks precision fits, and anything else is silently
-                // ignored. The schema is returned unchanged either way -- the
-                // validation ensures we recognize the combination even though
-                // the representation stays as Fixed.
+                // mirrors Java's validation: duration requires fixed(12), uuid
+                // requires fixed(16), decimal checks precision fits, and
+                // anything else is silently ignored. The schema is returned
+                // unchanged either way -- the validation ensures we recognize
+                // the combination even though the representation stays as
+                // Fixed.
                 let _valid = validate_logical_type_on_fixed(logical_name, size, precision, scale);
                 // TODO: emit a warning diagnostic for invalid logical types on
                 // fixed schemas, matching Java's debug-level logging.
@@ -4463,60 +5334,42 @@ fn json_value_as_u32(v: &Value) -> Option<u32> {
     }
 }
 
-/// Extract the doc comment for a parse tree context by looking at its start
-/// token index. Uses the `extract_doc_comment` function from `doc_comments`
-/// which scans backwards through hidden tokens.
+/// Extract the doc comment for a parse tree node by looking at its first
+/// token. Uses the `extract_doc_comment` function from `doc_comments` which
+/// scans backwards through whitespace and comments.
 ///
 /// Records the consumed doc comment's token index in `src.consumed_doc_indices`
 /// so that orphaned doc comments can be detected after the full tree walk.
-fn extract_doc_from_context<'input, T>(
-    ctx: &T,
-    token_stream: &TS<'input>,
-    src: &SourceInfo,
-) -> Option<String>
-where
-    T: antlr4rust::parser_rule_context::ParserRuleContext<'input>,
-{
-    let start = ctx.start();
-    let token_index = start.get_token_index();
+fn extract_doc_from_context(ctx: &Node, src: &SourceInfo) -> Option<String> {
     extract_doc_comment(
-        token_stream,
-        token_index,
+        &src.tokens,
+        src.source,
+        ctx.tokens.start,
         Some(&mut src.consumed_doc_indices.borrow_mut()),
     )
 }
 
-/// Scan the entire token stream for `DocComment` tokens that were not consumed
-/// by any declaration during the tree walk. Each orphaned doc comment generates
-/// a warning matching Java's format.
+/// Scan every token for `DocComment` tokens that were not consumed by any
+/// declaration during the tree walk. Each orphaned doc comment generates a
+/// warning matching Java's format.
 ///
 /// This implements the same logic as Java's `IdlReader.getDocComment()`, which
 /// checks for doc comment tokens between the previous call's position and the
 /// current call's position. Our approach is equivalent: after the full walk, any
 /// `DocComment` token not in the consumed set is orphaned.
-fn collect_orphaned_doc_comment_warnings<'input, S>(
-    token_stream: &S,
-    consumed_indices: &HashSet<isize>,
+fn collect_orphaned_doc_comment_warnings(
+    consumed_indices: &HashSet<usize>,
     src: &SourceInfo,
-) -> Vec<Warning>
-where
-    S: TokenStream<'input>,
-{
+) -> Vec<Warning> {
     let mut warnings = Vec::new();
-    let token_count = token_stream.size();
-
-    for i in 0..token_count {
-        let tok_wrapper = token_stream.get(i);
-        let token: &<S::TF as TokenFactory<'input>>::Inner = tok_wrapper.borrow();
-        let token_type = token.get_token_type();
 
-        if token_type == Idl_DocComment && !consumed_indices.contains(&i) {
+    for (i, token) in src.tokens.iter().enumerate() {
+        if token.token_type == TokenType::DocComment && !consumed_indices.contains(&i) {
+            let (line, column) = lexer::line_column(src.source, token.span.start);
             warnings.push(Warning::out_of_place_doc_comment(
-                token.get_line(),
-                token.get_column(),
-                src,
-                token.get_start(),
-                token.get_stop(),
+                line,
+                column,
+                src.span(token.span.start, token.span.len()),
             ));
         }
     }
@@ -4526,26 +5379,23 @@ where
 
 /// Parse a single import statement and append it as a `DeclItem::Import` to
 /// the declaration items list.
-fn collect_single_import<'input>(
-    import_ctx: &ImportStatementContextAll<'input>,
-    decl_items: &mut Vec<DeclItem>,
-    src: &SourceInfo,
-) {
-    let kind_tok = import_ctx.importType.as_ref();
-    let location_tok = import_ctx.location.as_ref();
-
-    if let (Some(kind), Some(loc)) = (kind_tok, location_tok) {
-        let import_kind = match kind.get_token_type() {
-            Idl_IDL => ImportKind::Idl,
-            Idl_Protocol => ImportKind::Protocol,
-            Idl_Schema => ImportKind::Schema,
+fn collect_single_import(import_ctx: &Node, decl_items: &mut Vec<DeclItem>, src: &SourceInfo) {
+    // `import` is followed by the kind of import.
+    let kind_tok = import_ctx.token_children().nth(1);
+    let location_tok = import_ctx.token(TokenType::StringLiteral);
+
+    if let (Some((_, kind)), Some(loc)) = (kind_tok, location_tok) {
+        let import_kind = match kind {
+            TokenType::Idl => ImportKind::Idl,
+            TokenType::Protocol => ImportKind::Protocol,
+            TokenType::Schema => ImportKind::Schema,
             _ => return,
         };
 
         decl_items.push(DeclItem::Import(ImportEntry {
             kind: import_kind,
-            path: get_string_from_literal(loc.get_text()),
-            span: span_from_context(import_ctx).map(|(o, l)| src.span(o, l)),
+            path: get_string_from_literal(src.token_text(loc)),
+            span: span_from_context(src, import_ctx).map(|(o, l)| src.span(o, l)),
         }));
     }
 }
@@ -5482,6 +6332,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn micros_time_keywords_produce_logical_types() {
+        for (keyword, expected) in [
+            ("time_micros", LogicalType::TimeMicros),
+            ("timestamp_micros", LogicalType::TimestampMicros),
+            ("local_timestamp_micros", LogicalType::LocalTimestampMicros),
+        ] {
+            let idl = format!("protocol P {{ record R {{ {keyword} t; }} }}");
+            let schema = parse_first_field_schema(idl.leak());
+            assert!(
+                matches!(&schema, AvroSchema::Logical { logical_type, .. } if *logical_type == expected),
+                "{keyword}: expected Logical({expected:?}), got: {schema:?}"
+            );
+        }
+
+        // Backticks make it a type reference again.
+        let schema = parse_first_field_schema("protocol P { record R { `time_micros` t; } }");
+        assert!(
+            matches!(&schema, AvroSchema::Reference { name, .. } if name == "time_micros"),
+            "expected Reference(time_micros), got: {schema:?}"
+        );
+
+        // Java does not reserve the keywords as type names.
+        let schema = parse_first_field_schema(
+            "protocol P { fixed time_micros(8); record R { `time_micros` t; } }",
+        );
+        assert!(
+            matches!(&schema, AvroSchema::Reference { name, .. } if name == "time_micros"),
+            "expected Reference(time_micros), got: {schema:?}"
+        );
+    }
+
     #[test]
     fn logicaltype_annotation_local_timestamp_millis_promoted() {
         let idl = r#"
@@ -5722,6 +6604,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn logicaltype_uuid_on_fixed_16_is_valid() {
+        // Avro 1.11.3+ allows `uuid` on a 16-byte fixed as well as on string.
+        // The schema stays Fixed, and defaults are 16-byte strings like any
+        // other fixed default.
+        let idl = r#"
+            @namespace("test")
+            protocol P {
+                @logicalType("uuid") fixed Uuid(16);
+                record R { Uuid id = "0123456789abcdef"; }
+            }
+        "#;
+        let result = crate::compiler::Idl::new()
+            .convert_str(idl)
+            .expect("compile should succeed");
+        assert_eq!(
+            result.json["types"][0],
+            serde_json::json!({
+                "type": "fixed",
+                "name": "Uuid",
+                "size": 16,
+                "logicalType": "uuid"
+            })
+        );
+    }
+
     #[test]
     fn logicaltype_duration_on_wrong_size_fixed_silently_accepted() {
         // `@logicalType("duration") fixed BadDuration(8)` is invalid (duration
@@ -5790,6 +6698,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn logicaltype_decimal_precision_exceeding_fixed_size_is_rejected() {
+        // fixed(4) holds at most 9 decimal digits: floor(log10(2^31)).
+        let idl = r#"
+            @namespace("test")
+            protocol P {
+                @logicalType("decimal") @precision(10) fixed Money(4);
+            }
+        "#;
+        let err = parse_idl_for_test(idl).unwrap_err();
+        let diagnostic = err
+            .chain()
+            .find_map(|e| e.downcast_ref::<ParseDiagnostic>())
+            .expect("a spanned diagnostic");
+        assert_eq!(
+            diagnostic.message,
+            "invalid decimal precision for `Money`: 10 digits do not fit in fixed(4), \
+             which holds at most 9"
+        );
+    }
+
     #[test]
     fn logicaltype_date_on_fixed_silently_accepted() {
         // `@logicalType("date") fixed BadDate(4)` is invalid (date requires
@@ -6072,6 +7001,19 @@ mod tests {
         insta::assert_snapshot!(render_diagnostic(&err));
     }
 
+    #[test]
+    fn default_errors_are_reported_together() {
+        let idl = r#"protocol P {
+            record A { int count = "hello"; }
+            record B { boolean flag = 42; string name = [1]; }
+        }"#;
+        let err = parse_idl_for_test(idl).unwrap_err();
+        let rendered = render_diagnostic(&err);
+        for field in ["`count` in `A`", "`flag` in `B`", "`name` in `B`"] {
+            assert!(rendered.contains(field), "missing {field}:\n{rendered}");
+        }
+    }
+
     #[test]
     fn default_boolean_int_is_rejected() {
         let idl = r#"protocol P { record R { boolean flag = 42; } }"#;
@@ -6362,8 +7304,9 @@ mod tests {
 
     #[test]
     fn default_forward_reference_skips_validation() {
-        // Forward references cannot be validated because the type is not yet resolved.
-        // This should not error even though the default might not match.
+        // Forward references cannot be validated while parsing because the type
+        // is not yet resolved; the compiler checks them once every type is
+        // registered (see `field_default_invalid_for_forward_enum_reference`).
         let idl = r#"
             protocol P {
                 record R { SomeEnum e = "VALUE"; }
@@ -7061,6 +8004,7 @@ protocol Test {
             message: "line 3:11 unexpected token `}`".to_string(),
             label: Some("unexpected `}`".to_string()),
             help: None,
+            replacement: None,
         };
         let refined =
             detect_empty_union(&error, source).expect("should detect empty union pattern");
@@ -7111,6 +8055,7 @@ protocol Test {
             message: "line 3:8 unexpected token `>`".to_string(),
             label: Some("unexpected `>`".to_string()),
             help: None,
+            replacement: None,
         };
         let refined = detect_empty_type_parameter(&error, source)
             .expect("should detect empty type parameter for map");
@@ -7128,6 +8073,7 @@ protocol Test {
             message: "line 3:10 unexpected token `>`".to_string(),
             label: Some("unexpected `>`".to_string()),
             help: None,
+            replacement: None,
         };
         let refined = detect_empty_type_parameter(&error, source)
             .expect("should detect empty type parameter for array");
@@ -7158,6 +8104,7 @@ protocol Test {
             message: "line 2:15 unexpected token `)`".to_string(),
             label: Some("unexpected `)`".to_string()),
             help: None,
+            replacement: None,
         };
         let refined = detect_fixed_non_integer(&error, source)
             .expect("should detect fixed non-integer pattern");
@@ -7188,6 +8135,7 @@ protocol Test {
             message: "line 2:13 unexpected token `{`".to_string(),
             label: Some("unexpected `{`".to_string()),
             help: None,
+            replacement: None,
         };
         let refined =
             detect_misspelled_keyword(&error, source).expect("should detect misspelled keyword");
@@ -7225,6 +8173,7 @@ protocol Test {
             message: "line 5:0 unexpected end of file".to_string(),
             label: Some("unexpected end of file".to_string()),
             help: Some("expected one of: protocol, ...".to_string()),
+            replacement: None,
         };
         let refined = detect_unclosed_brace(&error, source).expect("should detect unclosed brace");
         insta::assert_snapshot!(format_syntax_error(&refined));
@@ -7263,6 +8212,7 @@ protocol Test {
             message: "line 5:13 unexpected '{' expected ';' or ','".to_string(),
             label: Some("line 5:13 unexpected '{' expected ';' or ','".to_string()),
             help: None,
+            replacement: None,
         };
         let refined = detect_missing_close_brace_before_declaration(&error, source)
             .expect("should detect missing close brace before declaration");
@@ -7515,6 +8465,7 @@ protocol Test {
             message: "line 1:9 unexpected token `{`".to_string(),
             label: Some("unexpected `{`".to_string()),
             help: None,
+            replacement: None,
         };
         let refined =
             detect_missing_name(&error, source).expect("should detect missing name pattern");
@@ -7545,6 +8496,7 @@ protocol Test {
             message: "line 6:2 unexpected token `}`".to_string(),
             label: Some("unexpected `}`".to_string()),
             help: None,
+            replacement: None,
         };
         let refined = detect_trailing_comma_in_enum(&error, source)
             .expect("should detect trailing comma in enum");
@@ -14,10 +14,12 @@
 // correct Avro protocol JSON representation for subsequent occurrences of a
 // named type.
 
+use std::sync::Arc;
+
 use indexmap::IndexMap;
 
-use crate::error::SpanWithSource;
-use crate::model::schema::{AvroSchema, make_full_name};
+use crate::error::{ErrorKind, SpanWithSource};
+use crate::model::schema::{AvroSchema, make_full_name, split_full_name};
 
 // ==============================================================================
 // Avro Name Validation
@@ -52,11 +54,12 @@ pub(crate) fn is_valid_avro_name(name: &str) -> bool {
 /// Validate that a schema's name and namespace segments are valid Avro names.
 ///
 /// For namespaces, each dot-separated segment must independently satisfy the
-/// name pattern. Returns `Ok(())` if valid, or an error message describing
-/// which part is invalid.
-fn validate_schema_name(name: &str, namespace: Option<&str>) -> Result<(), String> {
+/// name pattern. Returns `Ok(())` if valid, or an error describing which part
+/// is invalid.
+fn validate_schema_name(name: &str, namespace: Option<&str>) -> miette::Result<()> {
     if !is_valid_avro_name(name) {
-        return Err(format!(
+        return Err(miette::miette!(
+            code = ErrorKind::InvalidName.code(),
             "invalid Avro name: `{name}` \
              (names must start with a letter or underscore, \
              followed by letters, digits, or underscores)"
@@ -67,7 +70,8 @@ fn validate_schema_name(name: &str, namespace: Option<&str>) -> Result<(), Strin
     {
         for segment in ns.split('.') {
             if !is_valid_avro_name(segment) {
-                return Err(format!(
+                return Err(miette::miette!(
+                    code = ErrorKind::InvalidName.code(),
                     "invalid Avro namespace segment: `{segment}` in `{ns}` \
                          (each segment must start with a letter or underscore, \
                          followed by letters, digits, or underscores)"
@@ -83,8 +87,10 @@ fn validate_schema_name(name: &str, namespace: Option<&str>) -> Result<(), Strin
 /// Named types (record, enum, fixed) are registered as they're parsed.
 /// Forward references can then be validated against this registry.
 pub struct SchemaRegistry {
-    /// Named schemas indexed by full name (namespace.name), in registration order.
-    schemas: IndexMap<String, AvroSchema>,
+    /// Named schemas indexed by full name (namespace.name), in registration
+    /// order. Shared, so that lookups during default validation do not
+    /// deep-copy records.
+    schemas: IndexMap<String, Arc<AvroSchema>>,
 }
 
 impl SchemaRegistry {
@@ -106,10 +112,15 @@ impl SchemaRegistry {
     /// record, enum, or fixed type). Returns an error if the name is already
     /// registered, if the schema is not a named type, or if the name/namespace
     /// contains characters invalid per the Avro specification.
-    pub fn register(&mut self, schema: AvroSchema) -> Result<(), String> {
+    pub fn register(&mut self, schema: AvroSchema) -> miette::Result<()> {
         let full_name = schema
             .full_name()
-            .ok_or_else(|| "cannot register non-named schema".to_string())?
+            .ok_or_else(|| {
+                miette::miette!(
+                    code = ErrorKind::Syntax.code(),
+                    "cannot register non-named schema"
+                )
+            })?
             .into_owned();
 
         // Validate that the name and namespace segments conform to the Avro
@@ -130,20 +141,28 @@ impl SchemaRegistry {
         validate_schema_name(name, namespace.as_deref())?;
 
         if self.schemas.contains_key(&full_name) {
-            return Err(format!("duplicate schema name: {full_name}"));
+            return Err(miette::miette!(
+                code = ErrorKind::DuplicateType.code(),
+                "duplicate schema name: {full_name}"
+            ));
         }
-        self.schemas.insert(full_name, schema);
+        self.schemas.insert(full_name, Arc::new(schema));
         Ok(())
     }
 
     /// Look up a named schema by full name.
     pub fn lookup(&self, full_name: &str) -> Option<&AvroSchema> {
-        self.schemas.get(full_name)
+        self.schemas.get(full_name).map(Arc::as_ref)
+    }
+
+    /// Look up a named schema by full name, sharing it instead of cloning it.
+    pub fn lookup_shared(&self, full_name: &str) -> Option<Arc<AvroSchema>> {
+        self.schemas.get(full_name).cloned()
     }
 
     /// Return all registered schemas as a reference, in registration order.
     pub fn schemas(&self) -> impl Iterator<Item = &AvroSchema> {
-        self.schemas.values()
+        self.schemas.values().map(Arc::as_ref)
     }
 
     /// Return all registered full names (e.g., `"org.example.Foo"`), in
@@ -164,7 +183,10 @@ impl SchemaRegistry {
     /// registry.
     #[cfg(test)]
     pub fn into_schemas(self) -> Vec<AvroSchema> {
-        self.schemas.into_values().collect()
+        self.schemas
+            .into_values()
+            .map(Arc::unwrap_or_clone)
+            .collect()
     }
 
     /// Merge schemas from another registry (used for imports).
@@ -212,6 +234,99 @@ impl SchemaRegistry {
     }
 }
 
+// ==============================================================================
+// Name Resolution
+// ==============================================================================
+//
+// A type reference as written binds to a full name without looking at the
+// registry: a dotted name is fully qualified, and anything else is in the
+// enclosing namespace. There is no fallback to the null namespace or to a
+// unique type with the same simple name. The reader builds every
+// `Reference` through `reference_name`, and `SchemaRegistry::resolve`
+// answers the same question for external tools, such as code generators,
+// that must bind names exactly as the compiler does.
+
+/// Type keywords that, unless escaped with backquotes, are primitive or
+/// logical types rather than references.
+const TYPE_KEYWORDS: &[&str] = &[
+    "null",
+    "boolean",
+    "int",
+    "long",
+    "float",
+    "double",
+    "bytes",
+    "string",
+    "void",
+    "decimal",
+    "date",
+    "time_ms",
+    "timestamp_ms",
+    "local_timestamp_ms",
+    "uuid",
+    "time_micros",
+    "timestamp_micros",
+    "local_timestamp_micros",
+];
+
+/// What a type name binds to, as returned by
+/// [`IdlOutput::resolve_name`](crate::IdlOutput::resolve_name).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+    /// A primitive or logical type keyword, such as `int` or `uuid`.
+    Builtin(String),
+    /// The named type with this full name.
+    Named(String),
+    /// No type has the full name the identifier binds to.
+    Unresolved {
+        /// The full name the identifier binds to.
+        full_name: String,
+        /// The known types with the same simple name, sorted by full name.
+        /// Referring to one of them takes its full name.
+        candidates: Vec<String>,
+    },
+}
+
+/// Split a type reference, as written without backquotes, into its simple
+/// name and namespace: a dotted name is fully qualified, and anything else
+/// is in the enclosing `namespace`.
+pub(crate) fn reference_name<'a>(
+    identifier: &'a str,
+    namespace: Option<&'a str>,
+) -> (&'a str, Option<&'a str>) {
+    match split_full_name(identifier) {
+        (name, Some(qualifier)) => (name, Some(qualifier)),
+        (name, None) => (name, namespace),
+    }
+}
+
+impl SchemaRegistry {
+    /// Bind a type name, as written in IDL (backquotes included), in the
+    /// enclosing `namespace`.
+    pub(crate) fn resolve(&self, identifier: &str, namespace: Option<&str>) -> Resolution {
+        let escaped = identifier.contains('`');
+        let identifier = identifier.replace('`', "");
+        if !escaped && TYPE_KEYWORDS.contains(&identifier.as_str()) {
+            return Resolution::Builtin(identifier);
+        }
+        let (name, namespace) = reference_name(&identifier, namespace);
+        let full_name = make_full_name(name, namespace).into_owned();
+        if self.schemas.contains_key(&full_name) {
+            return Resolution::Named(full_name);
+        }
+        let mut candidates: Vec<String> = self
+            .names()
+            .filter(|candidate| split_full_name(candidate).0 == name)
+            .map(str::to_string)
+            .collect();
+        candidates.sort();
+        Resolution::Unresolved {
+            full_name,
+            candidates,
+        }
+    }
+}
+
 /// Recursively walk a schema tree and collect any `Reference` names that
 /// don't correspond to a known type in the provided name set.
 ///
@@ -224,7 +339,7 @@ impl SchemaRegistry {
 /// an arbitrary schema against the registry).
 fn collect_unresolved_refs(
     schema: &AvroSchema,
-    known: &IndexMap<String, AvroSchema>,
+    known: &IndexMap<String, Arc<AvroSchema>>,
     unresolved: &mut Vec<(String, Option<SpanWithSource>)>,
 ) {
     match schema {
@@ -270,7 +385,7 @@ impl Default for SchemaRegistry {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::collections::HashMap;
+    use crate::model::schema::Properties;
 
     /// Extract just the names from unresolved reference tuples, for concise
     /// test assertions.
@@ -288,7 +403,7 @@ mod tests {
             fields: vec![],
             is_error: false,
             aliases: vec![],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         reg.register(schema)
             .expect("registration of valid named schema succeeds");
@@ -307,7 +422,7 @@ mod tests {
             symbols: vec!["A".to_string()],
             default: None,
             aliases: vec![],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         reg.register(schema.clone())
             .expect("first registration of valid schema succeeds");
@@ -324,7 +439,7 @@ mod tests {
                 doc: None,
                 size: 16,
                 aliases: vec![],
-                properties: HashMap::new(),
+                properties: Properties::new(),
             })
             .expect("registration of distinct fixed schemas succeeds");
         }
@@ -347,18 +462,18 @@ mod tests {
                 schema: AvroSchema::Reference {
                     name: "Missing".to_string(),
                     namespace: None,
-                    properties: HashMap::new(),
+                    properties: Properties::new(),
                     span: None,
                 },
                 doc: None,
                 default: None,
                 order: None,
                 aliases: vec![],
-                properties: HashMap::new(),
+                properties: Properties::new(),
             }],
             is_error: false,
             aliases: vec![],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         })
         .expect("registration of record with reference field succeeds");
         let unresolved = reg.validate_references();
@@ -376,7 +491,7 @@ mod tests {
             fields: vec![],
             is_error: false,
             aliases: vec![],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         })
         .expect("registration of Inner record succeeds");
         reg.register(AvroSchema::Record {
@@ -388,18 +503,18 @@ mod tests {
                 schema: AvroSchema::Reference {
                     name: "Inner".to_string(),
                     namespace: None,
-                    properties: HashMap::new(),
+                    properties: Properties::new(),
                     span: None,
                 },
                 doc: None,
                 default: None,
                 order: None,
                 aliases: vec![],
-                properties: HashMap::new(),
+                properties: Properties::new(),
             }],
             is_error: false,
             aliases: vec![],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         })
         .expect("registration of Outer record referencing Inner succeeds");
         let unresolved = reg.validate_references();
@@ -421,16 +536,16 @@ mod tests {
                         items: Box::new(AvroSchema::Reference {
                             name: "MissingA".to_string(),
                             namespace: None,
-                            properties: HashMap::new(),
+                            properties: Properties::new(),
                             span: None,
                         }),
-                        properties: HashMap::new(),
+                        properties: Properties::new(),
                     },
                     doc: None,
                     default: None,
                     order: None,
                     aliases: vec![],
-                    properties: HashMap::new(),
+                    properties: Properties::new(),
                 },
                 crate::model::schema::Field {
                     name: "lookup".to_string(),
@@ -438,16 +553,16 @@ mod tests {
                         values: Box::new(AvroSchema::Reference {
                             name: "MissingB".to_string(),
                             namespace: None,
-                            properties: HashMap::new(),
+                            properties: Properties::new(),
                             span: None,
                         }),
-                        properties: HashMap::new(),
+                        properties: Properties::new(),
                     },
                     doc: None,
                     default: None,
                     order: None,
                     aliases: vec![],
-                    properties: HashMap::new(),
+                    properties: Properties::new(),
                 },
                 crate::model::schema::Field {
                     name: "choice".to_string(),
@@ -457,7 +572,7 @@ mod tests {
                             AvroSchema::Reference {
                                 name: "MissingC".to_string(),
                                 namespace: None,
-                                properties: HashMap::new(),
+                                properties: Properties::new(),
                                 span: None,
                             },
                         ],
@@ -467,12 +582,12 @@ mod tests {
                     default: None,
                     order: None,
                     aliases: vec![],
-                    properties: HashMap::new(),
+                    properties: Properties::new(),
                 },
             ],
             is_error: false,
             aliases: vec![],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         })
         .expect("registration of Container with nested references succeeds");
         let unresolved = reg.validate_references();
@@ -488,7 +603,7 @@ mod tests {
             doc: None,
             size: 32,
             aliases: vec![],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         })
         .expect("registration of Hash into reg1 succeeds");
 
@@ -499,7 +614,7 @@ mod tests {
             doc: None,
             size: 64, // Different size -- should be ignored since reg1 already has "Hash".
             aliases: vec![],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         })
         .expect("registration of Hash into reg2 succeeds");
         reg2.register(AvroSchema::Fixed {
@@ -508,7 +623,7 @@ mod tests {
             doc: None,
             size: 16,
             aliases: vec![],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         })
         .expect("registration of Token into reg2 succeeds");
 
@@ -534,7 +649,7 @@ mod tests {
                 doc: None,
                 size: 8,
                 aliases: vec![],
-                properties: HashMap::new(),
+                properties: Properties::new(),
             })
             .expect("registration of distinct fixed schemas succeeds");
         }
@@ -603,7 +718,7 @@ mod tests {
             fields: vec![],
             is_error: false,
             aliases: vec![],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         });
         let err = result.unwrap_err();
         insta::assert_snapshot!(err);
@@ -619,7 +734,7 @@ mod tests {
             symbols: vec!["A".to_string()],
             default: None,
             aliases: vec![],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         });
         let err = result.unwrap_err();
         insta::assert_snapshot!(err);
@@ -635,7 +750,7 @@ mod tests {
             fields: vec![],
             is_error: false,
             aliases: vec![],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         });
         let err = result.unwrap_err();
         insta::assert_snapshot!(err);
@@ -651,7 +766,7 @@ mod tests {
             fields: vec![],
             is_error: false,
             aliases: vec![],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         });
         assert!(result.is_ok());
     }
@@ -668,7 +783,7 @@ mod tests {
         let schema = AvroSchema::Reference {
             name: "DoesNotExist".to_string(),
             namespace: Some("com.example".to_string()),
-            properties: HashMap::new(),
+            properties: Properties::new(),
             span: None,
         };
         let unresolved = reg.validate_schema(&schema);
@@ -686,14 +801,14 @@ mod tests {
             fields: vec![],
             is_error: false,
             aliases: vec![],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         })
         .expect("registration of MyRecord succeeds");
 
         let schema = AvroSchema::Reference {
             name: "MyRecord".to_string(),
             namespace: Some("com.example".to_string()),
-            properties: HashMap::new(),
+            properties: Properties::new(),
             span: None,
         };
         let unresolved = reg.validate_schema(&schema);
@@ -708,10 +823,10 @@ mod tests {
             items: Box::new(AvroSchema::Reference {
                 name: "DoesNotExist".to_string(),
                 namespace: None,
-                properties: HashMap::new(),
+                properties: Properties::new(),
                 span: None,
             }),
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         let unresolved = reg.validate_schema(&schema);
         assert_eq!(names(unresolved), vec!["DoesNotExist"]);
@@ -725,10 +840,10 @@ mod tests {
             values: Box::new(AvroSchema::Reference {
                 name: "Missing".to_string(),
                 namespace: Some("org.test".to_string()),
-                properties: HashMap::new(),
+                properties: Properties::new(),
                 span: None,
             }),
-            properties: HashMap::new(),
+            properties: Properties::new(),
         };
         let unresolved = reg.validate_schema(&schema);
         assert_eq!(names(unresolved), vec!["org.test.Missing"]);
@@ -744,7 +859,7 @@ mod tests {
                 AvroSchema::Reference {
                     name: "Missing".to_string(),
                     namespace: None,
-                    properties: HashMap::new(),
+                    properties: Properties::new(),
                     span: None,
                 },
             ],
@@ -775,7 +890,7 @@ mod tests {
             fields: vec![],
             is_error: false,
             aliases: vec![],
-            properties: HashMap::new(),
+            properties: Properties::new(),
         })
         .expect("registration of MyRecord under com.other succeeds");
 
@@ -784,10 +899,55 @@ mod tests {
         let schema = AvroSchema::Reference {
             name: "MyRecord".to_string(),
             namespace: Some("com.example".to_string()),
-            properties: HashMap::new(),
+            properties: Properties::new(),
             span: None,
         };
         let unresolved = reg.validate_schema(&schema);
         assert_eq!(names(unresolved), vec!["com.example.MyRecord"]);
     }
+
+    #[test]
+    fn test_resolve_binds_like_the_reader() {
+        let mut registry = SchemaRegistry::new();
+        for namespace in ["a", "b"] {
+            registry
+                .register(AvroSchema::Record {
+                    name: "User".to_string(),
+                    namespace: Some(namespace.to_string()),
+                    doc: None,
+                    fields: vec![],
+                    is_error: false,
+                    aliases: vec![],
+                    properties: Properties::new(),
+                })
+                .expect("valid named schema");
+        }
+
+        assert_eq!(
+            registry.resolve("User", Some("a")),
+            Resolution::Named("a.User".to_string())
+        );
+        assert_eq!(
+            registry.resolve("b.User", Some("a")),
+            Resolution::Named("b.User".to_string())
+        );
+        assert_eq!(
+            registry.resolve("User", Some("c")),
+            Resolution::Unresolved {
+                full_name: "c.User".to_string(),
+                candidates: vec!["a.User".to_string(), "b.User".to_string()],
+            }
+        );
+        assert_eq!(
+            registry.resolve("int", Some("a")),
+            Resolution::Builtin("int".to_string())
+        );
+        assert_eq!(
+            registry.resolve("`int`", None),
+            Resolution::Unresolved {
+                full_name: "int".to_string(),
+                candidates: Vec::new(),
+            }
+        );
+    }
 }
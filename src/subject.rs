@@ -0,0 +1,148 @@
+// ==============================================================================
+// Subject Naming Strategies
+// ==============================================================================
+//
+// Schema registries file schemas under a "subject". Which subject a schema
+// belongs to is a naming convention, and the common ones are the three
+// strategies Confluent's serializers ship with:
+//
+//   - `TopicNameStrategy`       — `<topic>-key` / `<topic>-value`
+//   - `RecordNameStrategy`      — `<full name>`
+//   - `TopicRecordNameStrategy` — `<topic>-<full name>`
+//
+// Anything else is a custom strategy: implement `SubjectStrategy`, or pass a
+// closure with the same signature as `SubjectStrategy::subject`.
+
+use crate::compiler::NamedSchema;
+
+/// Whether a schema describes the key or the value of a message on a topic.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SubjectRole {
+    Key,
+    #[default]
+    Value,
+}
+
+impl SubjectRole {
+    /// The suffix `TopicNameStrategy` uses: `"key"` or `"value"`.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SubjectRole::Key => "key",
+            SubjectRole::Value => "value",
+        }
+    }
+}
+
+/// Decides the registry subject a schema is filed under.
+///
+/// Implemented by [`TopicNameStrategy`], [`RecordNameStrategy`], and
+/// [`TopicRecordNameStrategy`], and by any closure
+/// `Fn(&str, SubjectRole, &NamedSchema) -> String`.
+///
+/// # Examples
+///
+/// ```
+/// use avdl::{Idl2Schemata, SubjectRole, SubjectStrategy, TopicRecordNameStrategy};
+///
+/// let output = Idl2Schemata::new()
+///     .extract_str(r#"@namespace("com.example") protocol P { record User { string name; } }"#)?;
+/// let user = &output.schemas[0];
+/// assert_eq!(
+///     TopicRecordNameStrategy.subject("users", SubjectRole::Value, user),
+///     "users-com.example.User"
+/// );
+///
+/// // A custom strategy.
+/// let lowercase = |_: &str, _: SubjectRole, schema: &avdl::NamedSchema| {
+///     schema.full_name().to_lowercase()
+/// };
+/// assert_eq!(lowercase.subject("users", SubjectRole::Value, user), "com.example.user");
+/// # Ok::<(), miette::Report>(())
+/// ```
+pub trait SubjectStrategy {
+    /// The subject for `schema` when used as the `role` of messages on
+    /// `topic`. Strategies that do not depend on the topic ignore it.
+    fn subject(&self, topic: &str, role: SubjectRole, schema: &NamedSchema) -> String;
+}
+
+impl<F> SubjectStrategy for F
+where
+    F: Fn(&str, SubjectRole, &NamedSchema) -> String,
+{
+    fn subject(&self, topic: &str, role: SubjectRole, schema: &NamedSchema) -> String {
+        self(topic, role, schema)
+    }
+}
+
+/// `<topic>-key` or `<topic>-value`, regardless of the schema. The default
+/// strategy of Confluent's serializers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TopicNameStrategy;
+
+impl SubjectStrategy for TopicNameStrategy {
+    fn subject(&self, topic: &str, role: SubjectRole, _schema: &NamedSchema) -> String {
+        format!("{topic}-{}", role.as_str())
+    }
+}
+
+/// The schema's full name, regardless of the topic.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RecordNameStrategy;
+
+impl SubjectStrategy for RecordNameStrategy {
+    fn subject(&self, _topic: &str, _role: SubjectRole, schema: &NamedSchema) -> String {
+        schema.full_name()
+    }
+}
+
+/// `<topic>-<full name>`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TopicRecordNameStrategy;
+
+impl SubjectStrategy for TopicRecordNameStrategy {
+    fn subject(&self, topic: &str, _role: SubjectRole, schema: &NamedSchema) -> String {
+        format!("{topic}-{}", schema.full_name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Idl2Schemata;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn builtin_strategies_match_confluent_naming() {
+        let output = Idl2Schemata::new()
+            .extract_str(r#"@namespace("com.example") protocol P { record User { string name; } }"#)
+            .expect("should compile");
+        let user = &output.schemas[0];
+
+        let strategies: [&dyn SubjectStrategy; 3] = [
+            &TopicNameStrategy,
+            &RecordNameStrategy,
+            &TopicRecordNameStrategy,
+        ];
+        let subjects: Vec<_> = strategies
+            .iter()
+            .flat_map(|s| {
+                [
+                    s.subject("users", SubjectRole::Key, user),
+                    s.subject("users", SubjectRole::Value, user),
+                ]
+            })
+            .collect();
+        assert_eq!(
+            subjects,
+            vec![
+                "users-key",
+                "users-value",
+                "com.example.User",
+                "com.example.User",
+                "users-com.example.User",
+                "users-com.example.User",
+            ]
+        );
+    }
+}
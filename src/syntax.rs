@@ -0,0 +1,267 @@
+// ==============================================================================
+// Lossless Syntax Tree
+// ==============================================================================
+//
+// A concrete syntax tree for tools that rewrite source, such as formatters and
+// refactorings: one node per grammar rule the parser matched, and every token
+// of the source, whitespace and comments included, as leaves. Concatenating
+// the leaves gives back the source exactly.
+//
+// The tree is assembled from the parse tree, which knows the token range
+// each rule matched, and the lexer's full token list, which has the
+// whitespace and comments the parser skips. Walking the token list in order
+// and opening a node wherever a rule starts puts every token in the
+// innermost rule that contains it. Whitespace and comments between two rules
+// belong to the rule around both, so a doc comment is a sibling of the
+// declaration it documents, right before it. Characters the lexer skips (it
+// reports them as errors) become `Unknown` tokens, so nothing is lost either.
+
+use std::ops::Range;
+
+use crate::lexer::Token;
+use crate::parse_tree::{self, Node};
+
+/// A grammar rule matched in the source, with everything it was parsed
+/// from. See [`syntax_tree`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxNode {
+    /// The grammar rule, e.g. `recordDeclaration` or `fieldDeclaration`.
+    pub kind: &'static str,
+    /// The byte range of the node's tokens.
+    pub span: Range<usize>,
+    pub children: Vec<SyntaxElement>,
+}
+
+/// A child of a [`SyntaxNode`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+/// A token of the source, including whitespace and comments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxToken {
+    /// The lexer's name for the token type, e.g. `Record`, `LBrace`,
+    /// `IdentifierToken`, `WS`, or `SingleLineComment`. Characters the lexer
+    /// does not recognize are `Unknown`.
+    pub kind: &'static str,
+    pub span: Range<usize>,
+}
+
+impl SyntaxToken {
+    /// Whether the token is whitespace or a comment, which the parser skips.
+    #[must_use]
+    pub fn is_trivia(&self) -> bool {
+        matches!(
+            self.kind,
+            "WS" | "DocComment" | "EmptyComment" | "MultiLineComment" | "SingleLineComment"
+        )
+    }
+}
+
+impl SyntaxNode {
+    /// The first child node of the given kind.
+    #[must_use]
+    pub fn child(&self, kind: &str) -> Option<&SyntaxNode> {
+        self.children.iter().find_map(|child| match child {
+            SyntaxElement::Node(child) if child.kind == kind => Some(child),
+            _ => None,
+        })
+    }
+
+    /// This node and every node under it, in source order, outer nodes
+    /// before the nodes inside them.
+    #[must_use]
+    pub fn descendants(&self) -> Vec<&SyntaxNode> {
+        let mut nodes = vec![self];
+        let mut i = 0;
+        while i < nodes.len() {
+            let node = nodes[i];
+            let children = node.children.iter().filter_map(|child| match child {
+                SyntaxElement::Node(child) => Some(child),
+                SyntaxElement::Token(_) => None,
+            });
+            nodes.splice(i + 1..i + 1, children);
+            i += 1;
+        }
+        nodes
+    }
+
+    /// Every token under this node, in source order.
+    #[must_use]
+    pub fn tokens(&self) -> Vec<&SyntaxToken> {
+        let mut tokens = Vec::new();
+        let mut stack: Vec<std::slice::Iter<'_, SyntaxElement>> = vec![self.children.iter()];
+        while let Some(children) = stack.last_mut() {
+            match children.next() {
+                Some(SyntaxElement::Token(token)) => tokens.push(token),
+                Some(SyntaxElement::Node(node)) => stack.push(node.children.iter()),
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+        tokens
+    }
+}
+
+/// Parse `source` into a lossless syntax tree, rooted at an `idlFile` node
+/// that spans all of it.
+///
+/// This never fails. For source with syntax errors, the tree has whatever
+/// shape the parser's error recovery gave it, and tokens outside every rule
+/// it matched are children of the nearest node around them; the tree is
+/// still lossless.
+#[must_use]
+pub fn syntax_tree(source: &str) -> SyntaxNode {
+    let parse = parse_tree::parse(source);
+    let mut builder = Builder {
+        tokens: &parse.tokens,
+        next: 0,
+        offset: 0,
+    };
+    // Error recovery may stop short of the end; the rest still belongs in
+    // the tree.
+    let mut tree = builder.node(&parse.tree, parse.tokens.len());
+    if builder.offset < source.len() {
+        tree.children.push(SyntaxElement::Token(SyntaxToken {
+            kind: "Unknown",
+            span: builder.offset..source.len(),
+        }));
+    }
+    tree.span = 0..source.len();
+    tree
+}
+
+/// Emits tokens in order into the nodes of a parse tree.
+struct Builder<'a> {
+    tokens: &'a [Token],
+    /// Index of the next token to emit.
+    next: usize,
+    /// Byte offset up to which the source has been emitted.
+    offset: usize,
+}
+
+impl Builder<'_> {
+    /// Build the node for `rule`, with its tokens up to index `end`.
+    /// Rules that matched nothing are dropped, and their tokens left to
+    /// the node around them.
+    fn node(&mut self, rule: &Node, end: usize) -> SyntaxNode {
+        let mut children = Vec::new();
+        for child in rule.nodes().filter(|child| !child.tokens.is_empty()) {
+            while self.next < child.tokens.start {
+                self.token(&mut children);
+            }
+            // Overlapping rules do not occur; if one did, its tokens have
+            // already been emitted.
+            if self.next == child.tokens.start {
+                children.push(SyntaxElement::Node(self.node(child, child.tokens.end)));
+            }
+        }
+        while self.next < end {
+            self.token(&mut children);
+        }
+        let span = match (children.first(), children.last()) {
+            (Some(first), Some(last)) => element_span(first).start..element_span(last).end,
+            _ => self.offset..self.offset,
+        };
+        SyntaxNode {
+            kind: rule.rule.name(),
+            span,
+            children,
+        }
+    }
+
+    /// Emit the next token, preceded by any characters the lexer skipped.
+    fn token(&mut self, children: &mut Vec<SyntaxElement>) {
+        let token = &self.tokens[self.next];
+        self.next += 1;
+        if token.span.start > self.offset {
+            children.push(SyntaxElement::Token(SyntaxToken {
+                kind: "Unknown",
+                span: self.offset..token.span.start,
+            }));
+        }
+        children.push(SyntaxElement::Token(SyntaxToken {
+            kind: token.token_type.name(),
+            span: token.span.clone(),
+        }));
+        self.offset = token.span.end;
+    }
+}
+
+fn element_span(element: &SyntaxElement) -> &Range<usize> {
+    match element {
+        SyntaxElement::Node(node) => &node.span,
+        SyntaxElement::Token(token) => &token.span,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn text(source: &str, tree: &SyntaxNode) -> String {
+        tree.tokens()
+            .iter()
+            .map(|token| &source[token.span.clone()])
+            .collect()
+    }
+
+    /// The first node of the given kind, depth first.
+    fn find<'t>(node: &'t SyntaxNode, kind: &str) -> Option<&'t SyntaxNode> {
+        if node.kind == kind {
+            return Some(node);
+        }
+        node.children.iter().find_map(|child| match child {
+            SyntaxElement::Node(child) => find(child, kind),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    #[test]
+    fn tree_is_lossless_and_keeps_comments() {
+        let source = "// header\n@namespace(\"org.example\")\nprotocol P {\n  \
+                      /** A user. */\n  record User {\n    string name; // trailing\n    \
+                      /* block */ int age = 1;\n  }\n}\n";
+        let tree = syntax_tree(source);
+        assert_eq!(tree.kind, "idlFile");
+        assert_eq!(tree.span, 0..source.len());
+        assert_eq!(text(source, &tree), source);
+
+        let record = find(&tree, "recordDeclaration").unwrap();
+        assert_eq!(
+            &source[record.span.clone()],
+            "record User {\n    string name; // trailing\n    /* block */ int age = 1;\n  }"
+        );
+        let kinds: Vec<_> = record
+            .tokens()
+            .iter()
+            .filter(|token| !token.is_trivia())
+            .map(|token| token.kind)
+            .take(3)
+            .collect();
+        assert_eq!(kinds, ["Record", "IdentifierToken", "LBrace"]);
+        assert!(
+            record
+                .tokens()
+                .iter()
+                .any(|token| token.kind == "SingleLineComment")
+        );
+    }
+
+    #[test]
+    fn broken_source_is_still_lossless() {
+        for source in [
+            "protocol P { record R { int x } ",
+            "protocol P { record R { int # x; } }",
+            "",
+            "   ",
+        ] {
+            let tree = syntax_tree(source);
+            assert_eq!(text(source, &tree), source, "{source:?}");
+        }
+    }
+}
@@ -0,0 +1,238 @@
+// ==============================================================================
+// Output Filename Templates
+// ==============================================================================
+//
+// Where `avdl idl --out-dir` and `avdl idl2schemata` write each output file,
+// relative to the output directory, for repositories whose layout is neither
+// the flat default nor one directory per namespace. A template is a relative
+// path with placeholders in braces, such as `{namespace_path}/{name}.avsc`.
+
+use std::path::PathBuf;
+
+/// The placeholders an [`OutputTemplate`] may contain.
+const PLACEHOLDERS: [&str; 6] = [
+    "stem",
+    "name",
+    "namespace",
+    "namespace_path",
+    "full_name",
+    "ext",
+];
+
+/// A relative output path with placeholders, such as
+/// `{namespace_path}/{name}.avsc`:
+///
+/// - `{stem}`: the file name of the source file, without its extension
+/// - `{name}`: the simple name of the schema or protocol
+/// - `{namespace}`: its namespace, such as `com.example`
+/// - `{namespace_path}`: its namespace as directories, such as `com/example`
+/// - `{full_name}`: its namespace and name joined with a dot
+/// - `{ext}`: `avsc` for a schema, `avpr` for a protocol
+///
+/// `{{` and `}}` stand for literal braces. Placeholders for a missing
+/// namespace expand to nothing, and the empty path components that leaves
+/// behind are dropped, so `{namespace_path}/{name}.avsc` is `User.avsc` for
+/// a schema without a namespace.
+///
+/// ```
+/// use avdl::{OutputTemplate, TemplateValues};
+///
+/// let template = OutputTemplate::parse("{namespace_path}/{name}.{ext}")?;
+/// let values = TemplateValues {
+///     stem: "users",
+///     name: "User",
+///     namespace: Some("com.example"),
+///     ext: "avsc",
+/// };
+/// assert_eq!(template.expand(&values), std::path::Path::new("com/example/User.avsc"));
+/// # Ok::<(), miette::Report>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputTemplate {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    /// One of [`PLACEHOLDERS`].
+    Placeholder(&'static str),
+}
+
+/// What an [`OutputTemplate`]'s placeholders expand to.
+#[derive(Debug, Clone, Copy)]
+pub struct TemplateValues<'a> {
+    /// The source file name without its extension.
+    pub stem: &'a str,
+    /// The simple name of the schema or protocol.
+    pub name: &'a str,
+    /// The namespace, if any.
+    pub namespace: Option<&'a str>,
+    /// The output file extension, without the dot.
+    pub ext: &'a str,
+}
+
+impl OutputTemplate {
+    /// Parse a template. Fails on an unknown placeholder, an unmatched
+    /// brace, or a path that could leave the output directory.
+    pub fn parse(template: &str) -> miette::Result<Self> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let Some(end) = rest.find('}') else {
+                        return Err(miette::miette!(
+                            "unclosed `{{` in output template `{template}`"
+                        ));
+                    };
+                    let name = &rest[..end];
+                    let Some(&placeholder) = PLACEHOLDERS.iter().find(|p| **p == name) else {
+                        return Err(miette::miette!(
+                            help = format!(
+                                "the placeholders are {}",
+                                PLACEHOLDERS.map(|p| format!("{{{p}}}")).join(", ")
+                            ),
+                            "unknown placeholder `{{{name}}}` in output template `{template}`"
+                        ));
+                    };
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Placeholder(placeholder));
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => {
+                    return Err(miette::miette!(
+                        "unmatched `}}` in output template `{template}`"
+                    ));
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        if parts.is_empty() {
+            return Err(miette::miette!("output template is empty"));
+        }
+        // Placeholders never expand to `/` or `..`, so only the literal text
+        // can point outside the output directory.
+        let escapes = template.starts_with('/')
+            || template.starts_with('\\')
+            || std::path::Path::new(template).is_absolute()
+            || template
+                .split(['/', '\\'])
+                .any(|component| component == "..");
+        if escapes {
+            return Err(miette::miette!(
+                "output template `{template}` must be a path inside the output directory"
+            ));
+        }
+        Ok(OutputTemplate { parts })
+    }
+
+    /// The relative path the template expands to for `values`.
+    #[must_use]
+    pub fn expand(&self, values: &TemplateValues<'_>) -> PathBuf {
+        let mut expanded = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => expanded.push_str(text),
+                Part::Placeholder(placeholder) => match *placeholder {
+                    "stem" => expanded.push_str(values.stem),
+                    "name" => expanded.push_str(values.name),
+                    "namespace" => expanded.push_str(values.namespace.unwrap_or_default()),
+                    "namespace_path" => {
+                        expanded.push_str(&values.namespace.unwrap_or_default().replace('.', "/"));
+                    }
+                    "full_name" => {
+                        if let Some(namespace) = values.namespace {
+                            expanded.push_str(namespace);
+                            expanded.push('.');
+                        }
+                        expanded.push_str(values.name);
+                    }
+                    "ext" => expanded.push_str(values.ext),
+                    _ => unreachable!("placeholders are checked by `parse`"),
+                },
+            }
+        }
+        expanded
+            .split('/')
+            .filter(|component| !component.is_empty() && *component != ".")
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::path::Path;
+
+    const USER: TemplateValues<'static> = TemplateValues {
+        stem: "users",
+        name: "User",
+        namespace: Some("com.example"),
+        ext: "avsc",
+    };
+
+    fn expand(template: &str, values: &TemplateValues<'_>) -> PathBuf {
+        OutputTemplate::parse(template)
+            .expect("template parses")
+            .expand(values)
+    }
+
+    #[test]
+    fn placeholders_expand() {
+        assert_eq!(
+            expand("{namespace_path}/{name}.{ext}", &USER),
+            Path::new("com/example/User.avsc")
+        );
+        assert_eq!(
+            expand("{stem}/{full_name}.json", &USER),
+            Path::new("users/com.example.User.json")
+        );
+        assert_eq!(expand("{{{name}}}.avsc", &USER), Path::new("{User}.avsc"));
+    }
+
+    #[test]
+    fn missing_namespaces_leave_no_empty_directories() {
+        let values = TemplateValues {
+            namespace: None,
+            ..USER
+        };
+        assert_eq!(
+            expand("{namespace_path}/{name}.avsc", &values),
+            Path::new("User.avsc")
+        );
+        assert_eq!(expand("{full_name}.avsc", &values), Path::new("User.avsc"));
+    }
+
+    #[test]
+    fn invalid_templates_are_rejected() {
+        for (template, message) in [
+            ("{nam}.avsc", "unknown placeholder `{nam}`"),
+            ("{name.avsc", "unclosed `{`"),
+            ("name}.avsc", "unmatched `}`"),
+            ("/abs/{name}.avsc", "inside the output directory"),
+            ("../{name}.avsc", "inside the output directory"),
+            ("", "empty"),
+        ] {
+            let err = OutputTemplate::parse(template).expect_err(template);
+            assert!(err.to_string().contains(message), "{template}: {err}");
+        }
+    }
+}
@@ -0,0 +1,282 @@
+// ==============================================================================
+// Semantic Tokens
+// ==============================================================================
+//
+// Classify the tokens of an `.avdl` source for syntax highlighting. This only
+// lexes, so it works on files that do not parse or whose imports are missing,
+// which is the usual state of a file being edited.
+//
+// The lexer cannot tell a type name from any other identifier, so that is
+// decided from the neighboring tokens: a name is a type if it follows a
+// declaration keyword or `<`, appears in a `union { ... }` or `throws` list,
+// or is followed by another name (`Order order;`, `Order get(...)`). A name
+// followed by `;`, `=`, `,`, or `)` is the name of a field, parameter, or
+// symbol. Since IDL keywords may also be used as names, the same rules apply
+// to keywords.
+
+use std::ops::Range;
+
+use crate::lexer::{self, TokenType};
+
+/// What a [`SemanticToken`] is, for choosing its highlight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// A keyword such as `protocol`, `record`, `import`, or `throws`, or a
+    /// `true`, `false`, or `null` default value.
+    Keyword,
+    /// A primitive or logical type (`int`, `decimal`, `void`, ...), or the
+    /// name of a named type where it is declared or referenced.
+    TypeName,
+    /// Any other name: fields, parameters, messages, and enum symbols.
+    Identifier,
+    /// An annotation's `@` and name, such as `@namespace`.
+    Annotation,
+    String,
+    Number,
+    /// A `/** ... */` comment.
+    DocComment,
+    /// Any other comment.
+    Comment,
+}
+
+/// A classified token and its byte range in the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemanticToken {
+    pub kind: TokenKind,
+    pub span: Range<usize>,
+}
+
+/// Lex `source` and classify its tokens for syntax highlighting, in source
+/// order. Whitespace and punctuation are omitted.
+///
+/// This never fails: characters the lexer does not recognize are skipped,
+/// and the source does not need to parse.
+#[must_use]
+pub fn semantic_tokens(source: &str) -> Vec<SemanticToken> {
+    let (tokens, _) = lexer::lex(source);
+    let tokens: Vec<_> = tokens
+        .into_iter()
+        .filter(|token| token.token_type != TokenType::WS)
+        .map(|token| (token.token_type, token.span))
+        .collect();
+    classify(&tokens)
+}
+
+/// Classify lexed `(token type, byte range)` pairs, whitespace excluded.
+fn classify(raw: &[(TokenType, Range<usize>)]) -> Vec<SemanticToken> {
+    let is_comment = |token_type: TokenType| token_type.is_trivia();
+    // The neighbors that matter are the code tokens around a name, so
+    // comments between them are looked through.
+    let code: Vec<usize> = (0..raw.len()).filter(|&i| !is_comment(raw[i].0)).collect();
+    let token_type =
+        |position: Option<usize>| position.and_then(|p| code.get(p)).map(|&i| raw[i].0);
+
+    let mut kinds = vec![None; raw.len()];
+    let mut union_depth = 0;
+    let mut in_throws = false;
+    for (position, &i) in code.iter().enumerate() {
+        let current = raw[i].0;
+        let previous = token_type(position.checked_sub(1));
+        let mut next = token_type(Some(position + 1));
+        if next == Some(TokenType::QuestionMark) {
+            next = token_type(Some(position + 2));
+        }
+
+        kinds[i] = match current {
+            TokenType::StringLiteral => Some(TokenKind::String),
+            TokenType::IntegerLiteral | TokenType::FloatingPointLiteral => Some(TokenKind::Number),
+            TokenType::At => Some(TokenKind::Annotation),
+            _ if previous == Some(TokenType::At) && is_name(current) => Some(TokenKind::Annotation),
+            _ if is_name(current) => Some(name_kind(
+                current,
+                previous,
+                next,
+                union_depth > 0 || in_throws,
+            )),
+            _ => None,
+        };
+
+        match current {
+            TokenType::Union => union_depth += 1,
+            TokenType::RBrace if union_depth > 0 => union_depth -= 1,
+            TokenType::Throws => in_throws = true,
+            TokenType::Semicolon => in_throws = false,
+            _ => {}
+        }
+    }
+
+    let mut tokens: Vec<SemanticToken> = Vec::new();
+    for ((token_type, span), kind) in raw.iter().zip(kinds) {
+        let kind = match *token_type {
+            TokenType::DocComment => TokenKind::DocComment,
+            t if is_comment(t) => TokenKind::Comment,
+            _ => match kind {
+                Some(kind) => kind,
+                None => continue,
+            },
+        };
+        // Merge an annotation's `@` with its name.
+        if let Some(last) = tokens.last_mut()
+            && kind == TokenKind::Annotation
+            && last.kind == TokenKind::Annotation
+            && last.span.end == span.start
+        {
+            last.span.end = span.end;
+            continue;
+        }
+        tokens.push(SemanticToken {
+            kind,
+            span: span.clone(),
+        });
+    }
+    tokens
+}
+
+/// Whether a token can be used as a name: an identifier, or a keyword.
+fn is_name(token_type: TokenType) -> bool {
+    token_type == TokenType::IdentifierToken || token_type.is_keyword()
+}
+
+/// Classify a name (identifier or keyword) from the code tokens around it.
+fn name_kind(
+    current: TokenType,
+    previous: Option<TokenType>,
+    next: Option<TokenType>,
+    in_type_list: bool,
+) -> TokenKind {
+    let declares = matches!(
+        previous,
+        Some(
+            TokenType::Protocol
+                | TokenType::Record
+                | TokenType::Error
+                | TokenType::Enum
+                | TokenType::Fixed
+                | TokenType::Schema
+                | TokenType::LT
+        )
+    );
+    if declares
+        || (in_type_list
+            && matches!(
+                previous,
+                Some(TokenType::LBrace | TokenType::Comma | TokenType::Throws)
+            ))
+    {
+        return TokenKind::TypeName;
+    }
+    // A default value.
+    if previous == Some(TokenType::Equals) {
+        return match current {
+            TokenType::Null | TokenType::BTrue | TokenType::BFalse => TokenKind::Keyword,
+            _ => TokenKind::Identifier,
+        };
+    }
+    if matches!(
+        next,
+        Some(
+            TokenType::Semicolon
+                | TokenType::Equals
+                | TokenType::Comma
+                | TokenType::RParen
+                | TokenType::RBrace
+        )
+    ) {
+        return TokenKind::Identifier;
+    }
+    match current {
+        TokenType::Boolean
+        | TokenType::Int
+        | TokenType::Long
+        | TokenType::Float
+        | TokenType::Double
+        | TokenType::String
+        | TokenType::Bytes
+        | TokenType::Null
+        | TokenType::Decimal
+        | TokenType::Date
+        | TokenType::Time
+        | TokenType::Timestamp
+        | TokenType::LocalTimestamp
+        | TokenType::Uuid
+        | TokenType::Void => TokenKind::TypeName,
+        TokenType::IdentifierToken if next.is_some_and(is_name) => TokenKind::TypeName,
+        TokenType::IdentifierToken => TokenKind::Identifier,
+        _ => TokenKind::Keyword,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    /// Render each token as `kind:text`, for readable assertions.
+    fn render(source: &str) -> Vec<String> {
+        semantic_tokens(source)
+            .into_iter()
+            .map(|token| format!("{:?}:{}", token.kind, &source[token.span]))
+            .collect()
+    }
+
+    #[test]
+    fn classifies_keywords_types_names_and_literals() {
+        let source = r#"/** Shop. */
+@namespace("ex") protocol Shop {
+    /* Statuses. */
+    enum Status { NEW, SHIPPED } = NEW;
+    record Order { ex.Status status; union { null, Status } previous = null; decimal(9, 2) total; }
+    Order get(string id) throws NotFound;
+}"#;
+        assert_eq!(
+            render(source),
+            vec![
+                "DocComment:/** Shop. */",
+                "Annotation:@namespace",
+                "String:\"ex\"",
+                "Keyword:protocol",
+                "TypeName:Shop",
+                "Comment:/* Statuses. */",
+                "Keyword:enum",
+                "TypeName:Status",
+                "Identifier:NEW",
+                "Identifier:SHIPPED",
+                "Identifier:NEW",
+                "Keyword:record",
+                "TypeName:Order",
+                "TypeName:ex.Status",
+                "Identifier:status",
+                "Keyword:union",
+                "TypeName:null",
+                "TypeName:Status",
+                "Identifier:previous",
+                "Keyword:null",
+                "TypeName:decimal",
+                "Number:9",
+                "Number:2",
+                "Identifier:total",
+                "TypeName:Order",
+                "Identifier:get",
+                "TypeName:string",
+                "Identifier:id",
+                "Keyword:throws",
+                "TypeName:NotFound",
+            ]
+        );
+    }
+
+    #[test]
+    fn keywords_used_as_names_are_identifiers() {
+        assert_eq!(
+            render("record R { string error; date `date`; }"),
+            vec![
+                "Keyword:record",
+                "TypeName:R",
+                "TypeName:string",
+                "Identifier:error",
+                "TypeName:date",
+                "Identifier:`date`",
+            ]
+        );
+    }
+}
@@ -0,0 +1,248 @@
+// ==============================================================================
+// Tolerant Parsing
+// ==============================================================================
+//
+// The public face of `reader::parse_idl_tolerant`: parse a file that may not
+// be valid, for editors that need an outline or completions while the user
+// is typing. Only the file itself is parsed; imports are not read, and
+// references to other types are not resolved.
+
+use std::ops::Range;
+
+use crate::compiler::DeclaredName;
+use crate::model::protocol::Message;
+use crate::model::schema::AvroSchema;
+use crate::reader::{DeclItem, IdlFile, ReaderOptions, parse_idl_tolerant as parse_tolerant};
+
+/// A top-level declaration found by [`parse_idl_tolerant`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Declaration {
+    /// A named type that parsed.
+    Type {
+        name: DeclaredName,
+        /// The type as declared. References to other types are left
+        /// unresolved.
+        schema: AvroSchema,
+        /// The byte range of the declaration in the source.
+        span: Range<usize>,
+    },
+    /// A protocol message that parsed.
+    Message {
+        name: String,
+        message: Message,
+        /// The byte range of the declaration in the source.
+        span: Range<usize>,
+    },
+    /// A placeholder for a declaration with errors, which was left out of
+    /// the model.
+    Broken {
+        /// The byte range of the declaration in the source, from its first
+        /// annotation through its closing `}` or `;`.
+        span: Range<usize>,
+    },
+}
+
+impl Declaration {
+    /// The byte range of the declaration in the source.
+    #[must_use]
+    pub fn span(&self) -> &Range<usize> {
+        match self {
+            Declaration::Type { span, .. }
+            | Declaration::Message { span, .. }
+            | Declaration::Broken { span } => span,
+        }
+    }
+}
+
+/// What [`parse_idl_tolerant`] recovered from a source.
+#[derive(Debug)]
+pub struct TolerantParse {
+    /// The named types and messages that parsed and placeholders for the
+    /// declarations that did not, in source order. Empty if nothing could
+    /// be recovered, e.g. when the protocol header itself is broken.
+    pub declarations: Vec<Declaration>,
+    /// Every syntax and semantic error found, in the order found. Empty if
+    /// the source parsed cleanly.
+    pub errors: Vec<miette::Report>,
+}
+
+/// Parse an IDL source without giving up at the first error. Declarations
+/// with errors are left out and reported as [`Declaration::Broken`]; the
+/// rest of the file is parsed as if they were not there.
+///
+/// This never fails. Imports are not read and references are not resolved,
+/// so a source that parses here may still fail to compile.
+#[must_use]
+pub fn parse_idl_tolerant(source: &str) -> TolerantParse {
+    // Errors point into the source, so it is kept like any compiled source.
+    let source = crate::error::keep_source(source.to_owned());
+    let result = parse_tolerant(source, "<input>", &ReaderOptions::default());
+    let mut declarations: Vec<Declaration> = result
+        .broken
+        .iter()
+        .map(|span| Declaration::Broken { span: span.clone() })
+        .collect();
+    let Some((idl_file, decl_items, _)) = result.parsed else {
+        return TolerantParse {
+            declarations,
+            errors: result.errors,
+        };
+    };
+
+    // The declarations the scan found that are neither broken nor an
+    // import or a type are, in a protocol that parsed, its messages.
+    let mut rest: Vec<&Range<usize>> = result
+        .declarations
+        .iter()
+        .filter(|range| !result.broken.contains(range))
+        .collect();
+    for item in decl_items {
+        let offset = match &item {
+            DeclItem::Import(import) => import.span.as_ref().map(|span| span.offset),
+            DeclItem::Type(_, span, _) => span.as_ref().map(|span| span.offset),
+        };
+        // The item's own span only covers the keyword.
+        let range = offset.and_then(|offset| {
+            let index = rest.iter().position(|range| range.contains(&offset))?;
+            Some(rest.remove(index).clone())
+        });
+        if let DeclItem::Type(schema, Some(span), _) = item
+            && let Some(name) = DeclaredName::new(&schema, "<input>")
+        {
+            declarations.push(Declaration::Type {
+                name,
+                schema: *schema,
+                span: range.unwrap_or(span.offset..span.offset + span.length),
+            });
+        }
+    }
+    if let IdlFile::Protocol(protocol) = idl_file {
+        for ((name, message), span) in protocol.messages.into_iter().zip(rest) {
+            declarations.push(Declaration::Message {
+                name,
+                message,
+                span: span.clone(),
+            });
+        }
+    }
+    declarations.sort_by_key(|declaration| declaration.span().start);
+    TolerantParse {
+        declarations,
+        errors: result.errors,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NameKind;
+    use pretty_assertions::assert_eq;
+
+    /// Describe declarations as `(kind or "broken", source text)`.
+    fn outline(source: &str, parsed: &TolerantParse) -> Vec<(String, String)> {
+        parsed
+            .declarations
+            .iter()
+            .map(|declaration| {
+                let kind = match declaration {
+                    Declaration::Type { name, .. } => format!("{:?} {}", name.kind, name.full_name),
+                    Declaration::Message { name, .. } => format!("Message {name}"),
+                    Declaration::Broken { .. } => "broken".to_string(),
+                };
+                (kind, source[declaration.span().clone()].to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn broken_declarations_become_placeholders() {
+        let source = "@namespace(\"org.example\")\nprotocol P {\n  \
+                      record Good { int a; }\n  \
+                      record Bad { int b }\n  \
+                      enum Color { RED, GREEN } = RED;\n  \
+                      record AlsoBad { string s; string s; }\n  \
+                      fixed Hash(16);\n  \
+                      Good fetch(Hash id);\n  \
+                      void broken(int a;\n}\n";
+        let parsed = parse_idl_tolerant(source);
+        let outline = outline(source, &parsed);
+        assert_eq!(outline[1], ("broken".into(), "record Bad { int b }".into()));
+        assert_eq!(
+            outline[3],
+            (
+                "broken".into(),
+                "record AlsoBad { string s; string s; }".into()
+            )
+        );
+        let kinds: Vec<_> = outline.iter().map(|(kind, _)| kind.as_str()).collect();
+        assert_eq!(
+            kinds,
+            [
+                "Record org.example.Good",
+                "broken",
+                "Enum org.example.Color",
+                "broken",
+                "Fixed org.example.Hash",
+                "Message fetch",
+                "broken",
+            ]
+        );
+        assert_eq!(outline[5].1, "Good fetch(Hash id);");
+        // The syntax errors, then the duplicate field found on the retry.
+        assert_eq!(parsed.errors.len(), 2);
+        assert!(format!("{:?}", parsed.errors[1]).contains("duplicate field 's'"));
+    }
+
+    #[test]
+    fn recovered_declarations_carry_their_model() {
+        let parsed = parse_idl_tolerant(
+            "protocol P {\n  record R { int a; }\n  record Bad { int b }\n  R get(int id);\n}\n",
+        );
+        let [
+            Declaration::Type { schema, .. },
+            Declaration::Broken { .. },
+            Declaration::Message { name, message, .. },
+        ] = parsed.declarations.as_slice()
+        else {
+            panic!("unexpected outline: {:?}", parsed.declarations);
+        };
+        assert!(
+            matches!(schema, AvroSchema::Record { name, fields, .. } if name == "R" && fields.len() == 1)
+        );
+        assert_eq!(name, "get");
+        assert_eq!(message.request.len(), 1);
+        assert_eq!(message.request[0].name, "id");
+    }
+
+    #[test]
+    fn unclosed_record_does_not_take_the_protocol_with_it() {
+        let source = "protocol P {\n  record Good { int a; }\n  record Open { int b;\n}\n";
+        let parsed = parse_idl_tolerant(source);
+        assert_eq!(
+            outline(source, &parsed),
+            [
+                (
+                    "Record Good".to_string(),
+                    "record Good { int a; }".to_string()
+                ),
+                ("broken".to_string(), "record Open { int b;".to_string()),
+            ]
+        );
+        assert_eq!(parsed.errors.len(), 1);
+        assert!(matches!(
+            &parsed.declarations[0],
+            Declaration::Type { name, .. } if name.kind == NameKind::Record
+        ));
+    }
+
+    #[test]
+    fn valid_source_has_no_errors_and_broken_header_recovers_nothing() {
+        let parsed = parse_idl_tolerant("protocol P { record R { int a; } }");
+        assert!(parsed.errors.is_empty());
+        assert_eq!(parsed.declarations.len(), 1);
+
+        let parsed = parse_idl_tolerant("protocol { record R { int a; } }");
+        assert!(parsed.declarations.is_empty());
+        assert_eq!(parsed.errors.len(), 1);
+    }
+}
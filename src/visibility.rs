@@ -0,0 +1,290 @@
+// ==============================================================================
+// Visibility Enforcement
+// ==============================================================================
+//
+// `@visibility("internal")` on a named type or message marks it as outside the
+// public API; `@visibility("public")`, or no annotation, marks it as public.
+// Public types and messages must not reference internal types, directly or
+// through arrays, maps, and unions, because doing so would expose the
+// internal type to API consumers. A public type that reaches an internal one
+// only through another public type is not reported separately: the
+// intermediate type is already a violation.
+//
+// The annotation is an ordinary property, so it is still emitted in the JSON
+// output like any other.
+
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+use crate::error::{ErrorKind, ParseDiagnostic, SpanWithSource};
+use crate::model::schema::{AvroSchema, Properties, make_full_name};
+use crate::reader::IdlFile;
+use crate::resolve::SchemaRegistry;
+
+/// The property that carries a type's or message's visibility.
+const VISIBILITY_PROPERTY: &str = "visibility";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Visibility {
+    Public,
+    Internal,
+}
+
+/// A public type or message referencing an internal type.
+struct Leak {
+    message: String,
+    span: Option<SpanWithSource>,
+}
+
+/// Check that no public type or message references an internal type.
+///
+/// Fails with every leak found, pointing at the offending reference where the
+/// reference has a source span (i.e. it was written in IDL, not imported from
+/// JSON).
+pub(crate) fn validate_visibility(
+    idl_file: &IdlFile,
+    registry: &SchemaRegistry,
+    source: &'static str,
+    source_name: &'static str,
+) -> miette::Result<()> {
+    let mut internal = HashSet::new();
+    for schema in registry.schemas() {
+        let (Some(full_name), Some(properties)) = (schema.full_name(), named_properties(schema))
+        else {
+            continue;
+        };
+        if visibility_of(properties, &format!("type `{full_name}`"))? == Visibility::Internal {
+            internal.insert(full_name.into_owned());
+        }
+    }
+    if internal.is_empty() {
+        // Still validate message annotations below, but nothing can leak.
+        if let IdlFile::Protocol(protocol) = idl_file {
+            for (name, message) in &protocol.messages {
+                visibility_of(&message.properties, &format!("message `{name}`"))?;
+            }
+        }
+        return Ok(());
+    }
+
+    let mut leaks = Vec::new();
+    let mut check = |owner: &str, location: &str, schema: &AvroSchema| {
+        let mut refs = Vec::new();
+        collect_refs(schema, &mut refs);
+        for (target, span) in refs {
+            if internal.contains(&target) {
+                leaks.push(Leak {
+                    message: format!(
+                        "public {owner} references internal type `{target}` through {location}"
+                    ),
+                    span,
+                });
+            }
+        }
+    };
+
+    for schema in registry.schemas() {
+        let AvroSchema::Record { fields, .. } = schema else {
+            continue;
+        };
+        let full_name = schema.full_name().expect("records are named");
+        if internal.contains(full_name.as_ref()) {
+            continue;
+        }
+        for field in fields {
+            check(
+                &format!("type `{full_name}`"),
+                &format!("field `{}`", field.name),
+                &field.schema,
+            );
+        }
+    }
+
+    match idl_file {
+        IdlFile::Protocol(protocol) => {
+            for (name, message) in &protocol.messages {
+                let owner = format!("message `{name}`");
+                if visibility_of(&message.properties, &owner)? == Visibility::Internal {
+                    continue;
+                }
+                for param in &message.request {
+                    check(
+                        &owner,
+                        &format!("parameter `{}`", param.name),
+                        &param.schema,
+                    );
+                }
+                check(&owner, "its response", &message.response);
+                for error in message.errors.iter().flatten() {
+                    check(&owner, "its errors", error);
+                }
+            }
+        }
+        IdlFile::Schema(schema) => check("schema declaration", "its type", schema),
+        IdlFile::NamedSchemas(_) => {}
+    }
+
+    report(leaks, source, source_name)
+}
+
+/// The properties of a named type, or `None` for other schemas.
+fn named_properties(schema: &AvroSchema) -> Option<&Properties> {
+    match schema {
+        AvroSchema::Record { properties, .. }
+        | AvroSchema::Enum { properties, .. }
+        | AvroSchema::Fixed { properties, .. } => Some(properties),
+        _ => None,
+    }
+}
+
+/// Read the visibility annotation from `properties`. `owner` names the
+/// annotated type or message in the error for an unrecognized value.
+fn visibility_of(properties: &Properties, owner: &str) -> miette::Result<Visibility> {
+    match properties.get(VISIBILITY_PROPERTY) {
+        None => Ok(Visibility::Public),
+        Some(Value::String(s)) if s == "public" => Ok(Visibility::Public),
+        Some(Value::String(s)) if s == "internal" => Ok(Visibility::Internal),
+        Some(other) => Err(miette::miette!(
+            code = ErrorKind::InvalidAnnotation.code(),
+            help = "use @visibility(\"public\") or @visibility(\"internal\")",
+            "invalid @visibility value {other} on {owner}"
+        )),
+    }
+}
+
+/// Collect the full names of named types referenced by `schema`, with the
+/// span of each reference where known. Looks through arrays, maps, and
+/// unions; an inline named type counts as a reference to itself.
+fn collect_refs(schema: &AvroSchema, out: &mut Vec<(String, Option<SpanWithSource>)>) {
+    match schema {
+        AvroSchema::Reference {
+            name,
+            namespace,
+            span,
+            ..
+        } => out.push((
+            make_full_name(name, namespace.as_deref()).into_owned(),
+            *span,
+        )),
+        AvroSchema::Record { .. } | AvroSchema::Enum { .. } | AvroSchema::Fixed { .. } => {
+            if let Some(full_name) = schema.full_name() {
+                out.push((full_name.into_owned(), None));
+            }
+        }
+        AvroSchema::Array { items, .. } => collect_refs(items, out),
+        AvroSchema::Map { values, .. } => collect_refs(values, out),
+        AvroSchema::Union { types, .. } => {
+            for t in types {
+                collect_refs(t, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Turn the collected leaks into a single error, or `Ok` if there are none.
+/// The first leak with a span is the primary diagnostic; the rest are
+/// attached as related diagnostics so all of them are fixed in one pass.
+fn report(
+    mut leaks: Vec<Leak>,
+    source: &'static str,
+    source_name: &'static str,
+) -> miette::Result<()> {
+    if leaks.is_empty() {
+        return Ok(());
+    }
+    leaks.sort_by_key(|leak| leak.span.is_none());
+    if leaks.iter().all(|leak| leak.span.is_none()) {
+        let messages: Vec<_> = leaks.into_iter().map(|leak| leak.message).collect();
+        miette::bail!(
+            code = ErrorKind::InternalTypeLeak.code(),
+            "{}",
+            messages.join("\n")
+        );
+    }
+
+    let fallback_span = SpanWithSource::new(0, 0, source_name, source);
+    let mut diagnostics = leaks.into_iter().map(|leak| ParseDiagnostic {
+        kind: ErrorKind::InternalTypeLeak,
+        span: leak.span.unwrap_or(fallback_span),
+        message: leak.message,
+        label: Some("internal type referenced here".to_string()),
+        help: None,
+        related: Vec::new(),
+        name: None,
+        replacement: None,
+    });
+    let mut primary = diagnostics.next().expect("leaks is non-empty");
+    primary.help = Some(
+        "mark the referencing type or message @visibility(\"internal\"), \
+         or make the referenced type public"
+            .to_string(),
+    );
+    primary.related = diagnostics.collect();
+    Err(primary.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Idl;
+    use crate::error::ParseDiagnostic;
+
+    #[test]
+    fn public_types_and_messages_may_not_reference_internal_types() {
+        let err = Idl::new()
+            .convert_str(
+                r#"
+                @namespace("ex")
+                protocol P {
+                    @visibility("internal") record Secret { string key; }
+                    @visibility("internal") record Helper { Secret secret; }
+                    record Order { array<union { null, Secret }> secrets; }
+                    @visibility("internal") void peek(Secret secret);
+                    Order get(Secret filter);
+                }
+                "#,
+            )
+            .expect_err("public types reference an internal one");
+        let diagnostic = err
+            .chain()
+            .find_map(|e| e.downcast_ref::<ParseDiagnostic>())
+            .expect("a spanned diagnostic");
+        let messages: Vec<&str> = std::iter::once(diagnostic)
+            .chain(&diagnostic.related)
+            .map(|d| d.message.as_str())
+            .collect();
+        // Internal types and messages may reference internal types.
+        assert_eq!(
+            messages,
+            [
+                "public type `ex.Order` references internal type `ex.Secret` through field `secrets`",
+                "public message `get` references internal type `ex.Secret` through parameter `filter`",
+            ]
+        );
+    }
+
+    #[test]
+    fn visibility_annotations_are_validated() {
+        Idl::new()
+            .convert_str(
+                r#"
+                protocol P {
+                    @visibility("internal") record Secret { string key; }
+                    @visibility("public") record Order { int id; }
+                    @visibility("internal") void reset(Secret secret);
+                }
+                "#,
+            )
+            .expect("no public type references an internal one");
+
+        let err = Idl::new()
+            .convert_str(r#"protocol P { @visibility("private") record R { int x; } }"#)
+            .expect_err("unknown visibility");
+        assert!(
+            err.to_string()
+                .contains("invalid @visibility value \"private\" on type `R`"),
+            "{err}"
+        );
+    }
+}
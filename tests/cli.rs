@@ -10,7 +10,7 @@
 mod common;
 
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use assert_cmd::Command;
 use common::normalize_crlf;
@@ -22,7 +22,10 @@ const OUTPUT_DIR: &str = "avro/lang/java/idl/src/test/idl/output";
 const CLASSPATH_DIR: &str = "avro/lang/java/idl/src/test/idl/putOnClassPath";
 
 /// Helper to construct a `Command` for the `avdl` binary built by this crate.
-#[expect(deprecated, reason = "cargo_bin() warns about custom build-dir")]
+#[allow(
+    deprecated,
+    reason = "cargo_bin() warns about custom build-dir in some assert_cmd versions"
+)]
 fn avdl_cmd() -> Command {
     Command::cargo_bin("avdl").expect("avdl binary should be built by cargo")
 }
@@ -120,6 +123,56 @@ fn test_cli_idl_import_dir() {
     );
 }
 
+/// `--import-map-file` maps a logical prefix to a directory relative to the
+/// map file.
+#[test]
+fn test_cli_idl_import_map_file() {
+    let test_dir = PathBuf::from("tmp/cli-test-import-map");
+    let _ = fs::remove_dir_all(&test_dir);
+    fs::create_dir_all(test_dir.join("shared")).expect("create test directory");
+    fs::create_dir_all(test_dir.join("billing/invoices")).expect("create test directory");
+    fs::write(
+        test_dir.join("shared/money.avdl"),
+        "protocol Shared { record Money { long cents; } }",
+    )
+    .expect("write money.avdl");
+    fs::write(
+        test_dir.join("billing/invoices/invoice.avdl"),
+        "protocol Invoices { import idl \"@shared/money.avdl\"; \
+         record Invoice { Money total; } }",
+    )
+    .expect("write invoice.avdl");
+    fs::write(
+        test_dir.join("imports.map"),
+        "# logical import prefixes\n@shared/ = shared/\n",
+    )
+    .expect("write imports.map");
+
+    let output = avdl_cmd()
+        .args([
+            "idl",
+            "--import-map-file",
+            test_dir
+                .join("imports.map")
+                .to_str()
+                .expect("valid UTF-8 path"),
+            test_dir
+                .join("billing/invoices/invoice.avdl")
+                .to_str()
+                .expect("valid UTF-8 path"),
+        ])
+        .output()
+        .expect("run avdl idl with --import-map-file");
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let actual: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(actual["types"][0]["name"], "Money");
+}
+
 /// Run `avdl idl` on a nonexistent file and verify a non-zero exit code with
 /// a useful error message on stderr.
 #[test]
@@ -155,127 +208,1477 @@ fn test_cli_idl_stdin_to_stdout() {
     assert_eq!(types[0]["name"], "R");
 }
 
-/// Pass `-` as the output path and verify that JSON is written to stdout
-/// (same as omitting the output path).
+/// `idl2schemata -` reads stdin and resolves imports against `--import-base`.
 #[test]
-fn test_cli_idl_explicit_dash_output() {
+fn test_cli_idl2schemata_stdin_with_import_base() {
+    let test_dir = PathBuf::from("tmp/cli-test-idl2schemata-stdin");
+    let out_dir = test_dir.join("out");
+    fs::create_dir_all(&test_dir).expect("create test directory");
+    fs::write(
+        test_dir.join("common.avdl"),
+        "protocol Common { record Id { string value; } }",
+    )
+    .expect("write common.avdl");
+
+    avdl_cmd()
+        .args([
+            "idl2schemata",
+            "--import-base",
+            test_dir.to_str().expect("valid UTF-8 path"),
+            "-",
+            out_dir.to_str().expect("valid UTF-8 path"),
+        ])
+        .write_stdin(r#"protocol P { import idl "common.avdl"; record R { Id id; } }"#)
+        .assert()
+        .success();
+
+    assert!(out_dir.join("Id.avsc").is_file());
+    assert!(out_dir.join("R.avsc").is_file());
+}
+
+/// `--protocol` writes the protocol next to the extracted schemas.
+#[test]
+fn test_cli_idl2schemata_protocol() {
+    let out_dir = PathBuf::from("tmp/cli-test-idl2schemata-protocol");
+    let _ = fs::remove_dir_all(&out_dir);
+
+    avdl_cmd()
+        .args([
+            "idl2schemata",
+            "--protocol",
+            "-",
+            out_dir.to_str().expect("valid UTF-8 path"),
+        ])
+        .write_stdin("protocol Shop { record Order { string id; } }")
+        .assert()
+        .success();
+
+    assert!(out_dir.join("Order.avsc").is_file());
+    let protocol: Value = serde_json::from_str(
+        &fs::read_to_string(out_dir.join("Shop.avpr")).expect("read Shop.avpr"),
+    )
+    .expect("valid JSON");
+    assert_eq!(protocol["protocol"], "Shop");
+}
+
+/// `--stdout` prints all schemas as one JSON object keyed by full name.
+#[test]
+fn test_cli_idl2schemata_stdout() {
     let output = avdl_cmd()
-        .args(["idl", &format!("{INPUT_DIR}/simple.avdl"), "-"])
+        .args(["idl2schemata", "--stdout", "-"])
+        .write_stdin(
+            "@namespace(\"ex\") protocol Shop { enum Status { OPEN } \
+             record Order { Status status; } }",
+        )
         .output()
-        .expect("run avdl idl with - output");
-    assert!(
-        output.status.success(),
-        "avdl idl with - output should exit 0, stderr: {}",
-        String::from_utf8_lossy(&output.stderr)
-    );
+        .expect("run avdl");
+    assert!(output.status.success());
+    let schemas: Value = serde_json::from_slice(&output.stdout).expect("valid JSON");
+    let names: Vec<_> = schemas.as_object().expect("object").keys().collect();
+    assert_eq!(names, ["ex.Order", "ex.Status"]);
+    assert_eq!(schemas["ex.Order"]["fields"][0]["type"]["type"], "enum");
 
-    let actual: Value =
-        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
-    let expected = load_golden("simple.avpr");
+    avdl_cmd()
+        .args(["idl2schemata", "--stdout", "-", "out"])
+        .write_stdin("protocol Shop { }")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("--stdout cannot be used"));
+}
+
+/// `--output-archive` writes the schemas into a zip, in namespace directories.
+#[test]
+fn test_cli_idl2schemata_output_archive() {
+    let test_dir = PathBuf::from("tmp/cli-test-idl2schemata-archive");
+    let _ = fs::remove_dir_all(&test_dir);
+    fs::create_dir_all(&test_dir).expect("create test directory");
+    let archive_path = test_dir.join("schemas.zip");
+
+    avdl_cmd()
+        .args([
+            "idl2schemata",
+            "--output-archive",
+            archive_path.to_str().expect("valid UTF-8 path"),
+            "-",
+        ])
+        .write_stdin(r#"@namespace("com.example") protocol P { record R { int x; } enum E { A } }"#)
+        .assert()
+        .success();
+
+    let file = fs::File::open(&archive_path).expect("open archive");
+    let mut archive = zip::ZipArchive::new(file).expect("read archive");
+    let mut names: Vec<String> = archive.file_names().map(str::to_string).collect();
+    names.sort();
+    assert_eq!(names, vec!["com/example/E.avsc", "com/example/R.avsc"]);
+
+    let mut content = String::new();
+    std::io::Read::read_to_string(
+        &mut archive.by_name("com/example/R.avsc").expect("R.avsc entry"),
+        &mut content,
+    )
+    .expect("read R.avsc");
+    let schema: Value = serde_json::from_str(&content).expect("R.avsc should be JSON");
+    assert_eq!(schema["name"], "R");
+
+    // An archive with other content is only replaced with `--force`.
+    let archive_arg = archive_path.to_str().expect("valid UTF-8 path");
+    let other = r#"@namespace("com.example") protocol P { record R { long x; } }"#;
+    avdl_cmd()
+        .args(["idl2schemata", "--output-archive", archive_arg, "-"])
+        .write_stdin(other)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("pass --force to overwrite them"));
+    avdl_cmd()
+        .args([
+            "idl2schemata",
+            "--force",
+            "--output-archive",
+            archive_arg,
+            "-",
+        ])
+        .write_stdin(other)
+        .assert()
+        .success();
+    let file = fs::File::open(&archive_path).expect("open archive");
+    let archive = zip::ZipArchive::new(file).expect("read archive");
     assert_eq!(
-        normalize_crlf(actual),
-        normalize_crlf(expected),
-        "CLI stdout with `-` output should match golden simple.avpr"
+        archive.file_names().collect::<Vec<_>>(),
+        vec!["com/example/R.avsc"]
     );
 }
 
-// ==============================================================================
-// `idl2schemata` Subcommand Tests
-// ==============================================================================
-
-/// Run `avdl idl2schemata` on `simple.avdl` and verify that `.avsc` files are
-/// created in the output directory with valid JSON content.
+/// `--out-dir` compiles every file a glob matches, naming each output after
+/// its input.
 #[test]
-fn test_cli_idl2schemata() {
-    let out_dir = PathBuf::from("tmp/cli-test-idl2schemata");
-    // Clean up from any previous run to avoid stale files.
-    let _ = fs::remove_dir_all(&out_dir);
-    fs::create_dir_all(&out_dir).expect("create test output directory");
+fn test_cli_idl_out_dir_with_glob() {
+    let test_dir = PathBuf::from("tmp/cli-test-idl-out-dir");
+    let src_dir = test_dir.join("schemas");
+    let out_dir = test_dir.join("out");
+    fs::create_dir_all(src_dir.join("nested")).expect("create test directories");
+    fs::write(src_dir.join("a.avdl"), "protocol A { record R { int x; } }").expect("write a.avdl");
+    fs::write(
+        src_dir.join("nested/b.avdl"),
+        "schema S; record S { string s; }",
+    )
+    .expect("write b.avdl");
+    fs::write(src_dir.join("notes.txt"), "not IDL").expect("write notes.txt");
 
+    let pattern = format!("{}/**/*.avdl", src_dir.to_str().expect("valid UTF-8 path"));
     avdl_cmd()
         .args([
-            "idl2schemata",
-            &format!("{INPUT_DIR}/simple.avdl"),
+            "idl",
+            "--out-dir",
             out_dir.to_str().expect("valid UTF-8 path"),
+            &pattern,
         ])
         .assert()
         .success();
 
-    // simple.avdl defines Kind (enum), Status (enum), TestRecord (record),
-    // MD5 (fixed), and TestError (error). Each should produce a .avsc file.
-    let expected_files = [
-        "Kind.avsc",
-        "Status.avsc",
-        "TestRecord.avsc",
-        "MD5.avsc",
-        "TestError.avsc",
-    ];
-    for filename in &expected_files {
-        let path = out_dir.join(filename);
-        assert!(
-            path.exists(),
-            "expected {filename} to exist in output directory, got: {:?}",
-            fs::read_dir(&out_dir).map(|entries| entries
-                .filter_map(|e| e.ok())
-                .map(|e| e.file_name().to_string_lossy().to_string())
-                .collect::<Vec<_>>())
-        );
-        // Each file should contain valid JSON.
-        let content =
-            fs::read_to_string(&path).unwrap_or_else(|e| panic!("failed to read {filename}: {e}"));
-        let json: Value = serde_json::from_str(&content)
-            .unwrap_or_else(|e| panic!("{filename} should be valid JSON: {e}"));
-        // Every schema file should have a "type" key and a "name" key.
-        assert!(
-            json.get("type").is_some(),
-            "{filename} should have a \"type\" key"
-        );
-        assert!(
-            json.get("name").is_some(),
-            "{filename} should have a \"name\" key"
-        );
-    }
+    let protocol: Value =
+        serde_json::from_str(&fs::read_to_string(out_dir.join("a.avpr")).expect("read a.avpr"))
+            .expect("parse a.avpr");
+    assert_eq!(protocol["protocol"], "A");
+    let schema: Value =
+        serde_json::from_str(&fs::read_to_string(out_dir.join("b.avsc")).expect("read b.avsc"))
+            .expect("parse b.avsc");
+    assert_eq!(schema["name"], "S");
+    assert!(!out_dir.join("notes.avpr").exists());
 }
 
-/// Run `avdl idl2schemata` with an existing file as the output directory and
-/// verify that the error message clearly explains the path is not a directory.
+/// `--input-list -` reads the inputs to compile from stdin, one per line.
 #[test]
-fn test_cli_idl2schemata_outdir_is_file() {
-    let blocker = PathBuf::from("tmp/cli-test-idl2schemata-outdir-is-file");
-    // Create a regular file where the output directory should go.
-    fs::create_dir_all(blocker.parent().expect("tmp/ exists")).expect("create parent dir");
-    fs::write(&blocker, "not a directory").expect("create blocker file");
+fn test_cli_idl_input_list_from_stdin() {
+    let test_dir = PathBuf::from("tmp/cli-test-idl-input-list");
+    let _ = fs::remove_dir_all(&test_dir);
+    let out_dir = test_dir.join("out");
+    fs::create_dir_all(&test_dir).expect("create test directory");
+    let a = test_dir.join("a.avdl");
+    let b = test_dir.join("b.avdl");
+    fs::write(&a, "protocol A { record R { int x; } }").expect("write a.avdl");
+    fs::write(&b, "protocol B { record S { int y; } }").expect("write b.avdl");
 
-    let output = avdl_cmd()
+    avdl_cmd()
         .args([
-            "idl2schemata",
-            &format!("{INPUT_DIR}/simple.avdl"),
-            blocker.to_str().expect("valid UTF-8 path"),
+            "idl",
+            "--out-dir",
+            out_dir.to_str().expect("valid UTF-8 path"),
+            "--input-list",
+            "-",
         ])
+        .write_stdin(format!("{}\n\n{}\n", a.display(), b.display()))
+        .assert()
+        .success();
+
+    assert!(out_dir.join("a.avpr").exists());
+    assert!(out_dir.join("b.avpr").exists());
+}
+
+/// `--multiple-protocols` compiles each protocol in the input on its own and
+/// writes them as a JSON array.
+#[test]
+fn test_cli_idl_multiple_protocols() {
+    let output = avdl_cmd()
+        .args(["idl", "--multiple-protocols"])
+        .write_stdin(
+            "@namespace(\"shop\") protocol Orders { record Order { string id; } }\n\
+             /** Accounts. */\n\
+             protocol Users { record User { string name; } }\n",
+        )
         .output()
-        .expect("run avdl idl2schemata");
+        .expect("run avdl");
+    assert!(output.status.success(), "{output:?}");
+    assert!(output.stderr.is_empty(), "no out-of-place doc warning");
+    let protocols: Value = serde_json::from_slice(&output.stdout).expect("valid JSON");
+    assert_eq!(protocols[0]["protocol"], "Orders");
+    assert_eq!(protocols[0]["types"][0]["name"], "Order");
+    assert_eq!(protocols[1]["protocol"], "Users");
+    assert_eq!(protocols[1]["doc"], "Accounts.");
+    assert_eq!(protocols[1]["types"].as_array().map(Vec::len), Some(1));
+}
+
+/// `--recursive` mirrors the input tree, keeps going past a failure, and
+/// summarizes the results.
+#[test]
+fn test_cli_idl_recursive_mirrors_tree() {
+    let test_dir = PathBuf::from("tmp/cli-test-idl-recursive");
+    let src_dir = test_dir.join("schemas");
+    let out_dir = test_dir.join("out");
+    fs::create_dir_all(src_dir.join("billing")).expect("create test directories");
+    fs::write(src_dir.join("a.avdl"), "protocol A { record R { int x; } }").expect("write a.avdl");
+    fs::write(
+        src_dir.join("billing/a.avdl"),
+        "protocol B { record R { int x; } }",
+    )
+    .expect("write billing/a.avdl");
+    fs::write(
+        src_dir.join("billing/broken.avdl"),
+        "protocol C { record R { Missing m; } }",
+    )
+    .expect("write billing/broken.avdl");
+
+    avdl_cmd()
+        .args([
+            "idl",
+            "--recursive",
+            "--out-dir",
+            out_dir.to_str().expect("valid UTF-8 path"),
+            src_dir.to_str().expect("valid UTF-8 path"),
+        ])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("compiled 2 of 3 files"))
+        .stderr(predicates::str::contains("broken.avdl"));
+
+    assert!(out_dir.join("a.avpr").is_file());
+    assert!(out_dir.join("billing/a.avpr").is_file());
+    assert!(!out_dir.join("billing/broken.avpr").exists());
+}
+
+/// `--cache` skips inputs whose sources and imports are unchanged, and
+/// recompiles an input when a file it imports changes, or when its output
+/// would go somewhere else.
+#[test]
+fn test_cli_idl_cache_skips_unchanged_inputs() {
+    let test_dir = PathBuf::from("tmp/cli-test-idl-cache");
+    let src_dir = test_dir.join("schemas");
+    let out_dir = test_dir.join("out");
+    let cache = test_dir.join("cache.json");
+    let _ = fs::remove_dir_all(&test_dir);
+    fs::create_dir_all(src_dir.join("shared")).expect("create test directories");
+    fs::write(
+        src_dir.join("a.avdl"),
+        r#"protocol A { import idl "shared/common.avdl"; record R { Id id; } }"#,
+    )
+    .expect("write a.avdl");
+    fs::write(src_dir.join("b.avdl"), "protocol B { record S { int x; } }").expect("write b.avdl");
+    fs::write(
+        src_dir.join("shared/common.avdl"),
+        "protocol Common { record Id { string value; } }",
+    )
+    .expect("write common.avdl");
+
+    let build_into = |out_dir: &Path| {
+        let mut cmd = avdl_cmd();
+        cmd.args([
+            "idl",
+            "--recursive",
+            "--cache",
+            cache.to_str().expect("valid UTF-8 path"),
+            "--out-dir",
+            out_dir.to_str().expect("valid UTF-8 path"),
+            src_dir.to_str().expect("valid UTF-8 path"),
+        ]);
+        cmd
+    };
+    let build = || build_into(out_dir.as_path());
+
+    build()
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("compiled 3 of 3 files\n"));
+    build()
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("(3 already up to date)"));
 
+    fs::write(
+        src_dir.join("shared/common.avdl"),
+        "protocol Common { record Id { long value; } }",
+    )
+    .expect("rewrite common.avdl");
+    build()
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("(1 already up to date)"));
+    let protocol = fs::read_to_string(out_dir.join("a.avpr")).expect("read a.avpr");
     assert!(
-        !output.status.success(),
-        "should fail when output path is a file"
+        protocol.contains(r#""long""#),
+        "a.avpr was not rebuilt: {protocol}"
     );
-    let stderr = String::from_utf8_lossy(&output.stderr);
-    // Miette wraps long lines, so "is not a directory" may be split across
-    // lines with box-drawing characters. Check for each key fragment separately.
+
+    // The old outputs are still there, but not where the new ones go, even
+    // though that is a parent directory.
+    build_into(test_dir.as_path())
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("compiled 3 of 3 files\n"));
+    assert!(test_dir.join("a.avpr").is_file());
+
+    fs::write(&cache, "not a cache").expect("corrupt cache.json");
+    build()
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("corrupt build cache"));
+}
+
+/// `--cache` rebuilds an input when a new file shadows one of its imports,
+/// and never skips an input that imports from an archive.
+#[test]
+fn test_cli_idl_cache_notices_shadowed_imports() {
+    let test_dir = PathBuf::from("tmp/cli-test-idl-cache-shadow");
+    let src_dir = test_dir.join("schemas");
+    let lib_dir = test_dir.join("lib");
+    let cache = test_dir.join("cache.json");
+    let _ = fs::remove_dir_all(&test_dir);
+    fs::create_dir_all(&src_dir).expect("create test directories");
+    fs::create_dir_all(&lib_dir).expect("create test directories");
+    fs::write(
+        src_dir.join("a.avdl"),
+        r#"protocol A { import idl "common.avdl"; record R { Id id; } }"#,
+    )
+    .expect("write a.avdl");
+    fs::write(
+        lib_dir.join("common.avdl"),
+        "protocol Common { record Id { string value; } }",
+    )
+    .expect("write common.avdl");
+
+    let build = |import_dir: &Path| {
+        let mut cmd = avdl_cmd();
+        cmd.args([
+            "idl",
+            "--recursive",
+            "--import-dir",
+            import_dir.to_str().expect("valid UTF-8 path"),
+            "--cache",
+            cache.to_str().expect("valid UTF-8 path"),
+            "--out-dir",
+            test_dir.join("out").to_str().expect("valid UTF-8 path"),
+            src_dir.to_str().expect("valid UTF-8 path"),
+        ]);
+        cmd
+    };
+
+    build(&lib_dir).assert().success();
+    build(&lib_dir)
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("(1 already up to date)"));
+
+    // Next to the importing file, the new file is found before the import
+    // directory is searched, so both are compiled.
+    fs::write(
+        src_dir.join("common.avdl"),
+        "protocol Common { record Id { long value; } }",
+    )
+    .expect("write shadowing common.avdl");
+    build(&lib_dir)
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("compiled 2 of 2 files\n"));
+    let protocol = fs::read_to_string(test_dir.join("out/a.avpr")).expect("read a.avpr");
     assert!(
-        stderr.contains("not a") && stderr.contains("directory"),
-        "stderr should mention 'not a directory', got:\n{stderr}"
+        protocol.contains(r#""long""#),
+        "a.avpr was not rebuilt: {protocol}"
     );
 
-    // Clean up.
-    let _ = fs::remove_file(&blocker);
+    fs::remove_file(src_dir.join("common.avdl")).expect("remove shadowing common.avdl");
+    let jar = test_dir.join("lib.jar");
+    let mut archive = zip::ZipWriter::new(fs::File::create(&jar).expect("create lib.jar"));
+    archive
+        .start_file("common.avdl", zip::write::SimpleFileOptions::default())
+        .expect("add common.avdl");
+    std::io::Write::write_all(
+        &mut archive,
+        b"protocol Common { record Id { string value; } }",
+    )
+    .expect("write common.avdl");
+    archive.finish().expect("write lib.jar");
+    for _ in 0..2 {
+        build(&jar)
+            .assert()
+            .success()
+            .stderr(predicates::str::contains("compiled 1 of 1 files\n"));
+    }
 }
 
-/// Run `avdl idl2schemata` with no arguments and verify a non-zero exit code,
-/// since the `idl2schemata` subcommand requires an input argument.
+/// `--deny-warnings` prints the warnings and then fails without output.
 #[test]
-fn test_cli_idl2schemata_missing_input() {
-    avdl_cmd().args(["idl2schemata"]).assert().failure();
+fn test_cli_idl_deny_warnings() {
+    let input = "protocol P { record R { int x; /** orphan */ } }";
+    avdl_cmd()
+        .args(["idl", "--deny-warnings"])
+        .write_stdin(input)
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr(predicates::str::contains(
+            "out-of-place documentation comment",
+        ))
+        .stderr(predicates::str::contains("warnings are denied"));
+}
+
+/// `--allow` drops warnings of one kind, so `--deny-warnings` passes.
+#[test]
+fn test_cli_idl_allow_warning_kind() {
+    let input = "protocol P { record R { int x; /** orphan */ } }";
+    avdl_cmd()
+        .args(["idl", "--deny-warnings", "--allow", "out-of-place-doc"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stderr("");
+}
+
+/// Pass `-` as the output path and verify that JSON is written to stdout
+/// (same as omitting the output path).
+#[test]
+fn test_cli_idl_explicit_dash_output() {
+    let output = avdl_cmd()
+        .args(["idl", &format!("{INPUT_DIR}/simple.avdl"), "-"])
+        .output()
+        .expect("run avdl idl with - output");
+    assert!(
+        output.status.success(),
+        "avdl idl with - output should exit 0, stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let actual: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    let expected = load_golden("simple.avpr");
+    assert_eq!(
+        normalize_crlf(actual),
+        normalize_crlf(expected),
+        "CLI stdout with `-` output should match golden simple.avpr"
+    );
+}
+
+/// Pass `--param-defaults unchecked` and verify that an invalid message
+/// parameter default is emitted instead of rejected.
+#[test]
+fn test_cli_idl_param_defaults_unchecked() {
+    let input = r#"protocol P { void ping(int count = "many"); }"#;
+    avdl_cmd()
+        .args(["idl"])
+        .write_stdin(input)
+        .assert()
+        .failure();
+
+    let output = avdl_cmd()
+        .args(["idl", "--param-defaults", "unchecked"])
+        .write_stdin(input)
+        .output()
+        .expect("run avdl idl --param-defaults unchecked");
+    assert!(
+        output.status.success(),
+        "avdl idl --param-defaults unchecked should exit 0, stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let actual: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(actual["messages"]["ping"]["request"][0]["default"], "many");
+}
+
+/// Run `avdl idl --strip-docs` and verify doc comments are dropped from the
+/// protocol, its types, and their fields.
+#[test]
+fn test_cli_idl_strip_docs() {
+    let output = avdl_cmd()
+        .args(["idl", "--strip-docs"])
+        .write_stdin("/** P. */ protocol P { /** R. */ record R { /** x. */ int x; } }")
+        .output()
+        .expect("run avdl idl --strip-docs");
+    assert!(
+        output.status.success(),
+        "avdl idl --strip-docs should exit 0, stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout should be valid UTF-8");
+    assert!(!stdout.contains("\"doc\""), "got:\n{stdout}");
+    let actual: Value = serde_json::from_str(&stdout).expect("stdout should be valid JSON");
+    assert_eq!(actual["types"][0]["fields"][0]["name"], "x");
+}
+
+/// `--set-property` adds or replaces protocol properties, parsing values as
+/// JSON and falling back to a string.
+#[test]
+fn test_cli_idl_set_property() {
+    let output = avdl_cmd()
+        .args([
+            "idl",
+            "--set-property",
+            "version=2",
+            "--set-property",
+            "git-sha=3f2a9c1",
+        ])
+        .write_stdin("@version(1) protocol P { }")
+        .output()
+        .expect("run avdl idl --set-property");
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let actual: Value = serde_json::from_slice(&output.stdout).expect("valid JSON");
+    assert_eq!(actual["version"], 2);
+    assert_eq!(actual["git-sha"], "3f2a9c1");
+
+    avdl_cmd()
+        .args(["idl", "--set-property", "types=[]"])
+        .write_stdin("protocol P { }")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "cannot set protocol property `types`",
+        ));
+}
+
+#[test]
+fn test_cli_idl_require_namespace_prefix() {
+    avdl_cmd()
+        .args(["idl", "--require-namespace-prefix", "com.mycorp"])
+        .write_stdin(r#"@namespace("com.mycorp.billing") protocol P { @namespace("org.rogue") record R { int x; } }"#)
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(
+            "namespace `org.rogue` is outside the required prefix `com.mycorp`",
+        ));
+
+    avdl_cmd()
+        .args([
+            "idl",
+            "--require-namespace-prefix",
+            "com.mycorp",
+            "--deny-warnings",
+        ])
+        .write_stdin("protocol P { }")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "protocol `P` has no namespace; the required prefix is `com.mycorp`",
+        ));
+}
+
+#[test]
+fn test_cli_idl_require_docs() {
+    let source = "/** Docs. */ protocol P { /** A user. */ record User { string name; } }";
+    avdl_cmd()
+        .args(["idl", "--require-docs", "public", "--deny-warnings"])
+        .write_stdin(source)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "field `User.name` has no doc comment",
+        ));
+
+    avdl_cmd()
+        .args(["idl", "--require-docs", "public", "--allow", "missing-doc"])
+        .arg("--deny-warnings")
+        .write_stdin(source)
+        .assert()
+        .success();
+}
+
+// ==============================================================================
+// `idl2schemata` Subcommand Tests
+// ==============================================================================
+
+/// Run `avdl idl2schemata` on `simple.avdl` and verify that `.avsc` files are
+/// created in the output directory with valid JSON content.
+#[test]
+fn test_cli_idl2schemata() {
+    let out_dir = PathBuf::from("tmp/cli-test-idl2schemata");
+    // Clean up from any previous run to avoid stale files.
+    let _ = fs::remove_dir_all(&out_dir);
+    fs::create_dir_all(&out_dir).expect("create test output directory");
+
+    avdl_cmd()
+        .args([
+            "idl2schemata",
+            &format!("{INPUT_DIR}/simple.avdl"),
+            out_dir.to_str().expect("valid UTF-8 path"),
+        ])
+        .assert()
+        .success();
+
+    // simple.avdl defines Kind (enum), Status (enum), TestRecord (record),
+    // MD5 (fixed), and TestError (error). Each should produce a .avsc file.
+    let expected_files = [
+        "Kind.avsc",
+        "Status.avsc",
+        "TestRecord.avsc",
+        "MD5.avsc",
+        "TestError.avsc",
+    ];
+    for filename in &expected_files {
+        let path = out_dir.join(filename);
+        assert!(
+            path.exists(),
+            "expected {filename} to exist in output directory, got: {:?}",
+            fs::read_dir(&out_dir).map(|entries| entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.file_name().to_string_lossy().to_string())
+                .collect::<Vec<_>>())
+        );
+        // Each file should contain valid JSON.
+        let content =
+            fs::read_to_string(&path).unwrap_or_else(|e| panic!("failed to read {filename}: {e}"));
+        let json: Value = serde_json::from_str(&content)
+            .unwrap_or_else(|e| panic!("{filename} should be valid JSON: {e}"));
+        // Every schema file should have a "type" key and a "name" key.
+        assert!(
+            json.get("type").is_some(),
+            "{filename} should have a \"type\" key"
+        );
+        assert!(
+            json.get("name").is_some(),
+            "{filename} should have a \"name\" key"
+        );
+    }
+}
+
+/// Run `avdl idl2schemata` with an existing file as the output directory and
+/// verify that the error message clearly explains the path is not a directory.
+#[test]
+fn test_cli_idl2schemata_outdir_is_file() {
+    let blocker = PathBuf::from("tmp/cli-test-idl2schemata-outdir-is-file");
+    // Create a regular file where the output directory should go.
+    fs::create_dir_all(blocker.parent().expect("tmp/ exists")).expect("create parent dir");
+    fs::write(&blocker, "not a directory").expect("create blocker file");
+
+    let output = avdl_cmd()
+        .args([
+            "idl2schemata",
+            &format!("{INPUT_DIR}/simple.avdl"),
+            blocker.to_str().expect("valid UTF-8 path"),
+        ])
+        .output()
+        .expect("run avdl idl2schemata");
+
+    assert!(
+        !output.status.success(),
+        "should fail when output path is a file"
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    // Miette wraps long lines, so "is not a directory" may be split across
+    // lines with box-drawing characters. Check for each key fragment separately.
+    assert!(
+        stderr.contains("not a") && stderr.contains("directory"),
+        "stderr should mention 'not a directory', got:\n{stderr}"
+    );
+
+    // Clean up.
+    let _ = fs::remove_file(&blocker);
+}
+
+/// Run `avdl idl2schemata --namespace-dirs` on a protocol that declares two
+/// types with the same simple name in different namespaces, and verify each
+/// lands in its own namespace directory instead of overwriting the other.
+#[test]
+fn test_cli_idl2schemata_namespace_dirs() {
+    let test_dir = PathBuf::from("tmp/cli-test-idl2schemata-namespace-dirs");
+    let _ = fs::remove_dir_all(&test_dir);
+    fs::create_dir_all(&test_dir).expect("create test directory");
+    let avdl_path = test_dir.join("dup.avdl");
+    fs::write(
+        &avdl_path,
+        r#"@namespace("com.example.a") protocol P {
+            record Event { int x; }
+            @namespace("com.example.b") record Event { string y; }
+        }"#,
+    )
+    .expect("write dup.avdl");
+    let out_dir = test_dir.join("out");
+
+    avdl_cmd()
+        .args([
+            "idl2schemata",
+            "--namespace-dirs",
+            avdl_path.to_str().expect("valid UTF-8 path"),
+            out_dir.to_str().expect("valid UTF-8 path"),
+        ])
+        .assert()
+        .success();
+
+    for (ns_dir, field) in [("a", "x"), ("b", "y")] {
+        let path = out_dir.join("com/example").join(ns_dir).join("Event.avsc");
+        let content = fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("failed to read {}: {e}", path.display()));
+        let json: Value = serde_json::from_str(&content).expect("valid JSON");
+        assert_eq!(json["fields"][0]["name"], field);
+    }
+    assert!(!out_dir.join("Event.avsc").exists());
+}
+
+/// Run `avdl idl2schemata` for two protocols that both declare `Metadata`
+/// into the same directory, and verify the second refuses to overwrite the
+/// first's different file, writing nothing at all, unless `--force` is
+/// passed.
+#[test]
+fn test_cli_idl2schemata_refuses_to_overwrite() {
+    let test_dir = PathBuf::from("tmp/cli-test-idl2schemata-overwrite");
+    let _ = fs::remove_dir_all(&test_dir);
+    fs::create_dir_all(&test_dir).expect("create test directory");
+    let orders = test_dir.join("orders.avdl");
+    let users = test_dir.join("users.avdl");
+    fs::write(
+        &orders,
+        "protocol Orders { record Metadata { string order; } }",
+    )
+    .expect("write orders.avdl");
+    fs::write(
+        &users,
+        "protocol Users { record Metadata { string user; } record User { string name; } }",
+    )
+    .expect("write users.avdl");
+    let out_dir = test_dir.join("out");
+    let run = |input: &PathBuf, force: bool| {
+        let mut cmd = avdl_cmd();
+        cmd.arg("idl2schemata");
+        if force {
+            cmd.arg("--force");
+        }
+        cmd.args([
+            input.to_str().expect("valid UTF-8 path"),
+            out_dir.to_str().expect("valid UTF-8 path"),
+        ]);
+        cmd.assert()
+    };
+
+    run(&orders, false).success();
+    // Rewriting identical files is not overwriting anything.
+    run(&orders, false).success();
+    let metadata = out_dir.join("Metadata.avsc");
+    let written = fs::read_to_string(&metadata).expect("read Metadata.avsc");
+
+    run(&users, false)
+        .failure()
+        .stderr(predicates::str::contains("would overwrite:"))
+        .stderr(predicates::str::contains("Metadata.avsc"))
+        .stderr(predicates::str::contains("--force"));
+    assert_eq!(
+        fs::read_to_string(&metadata).expect("read Metadata.avsc"),
+        written
+    );
+    assert!(!out_dir.join("User.avsc").exists());
+
+    run(&users, true).success();
+    assert!(
+        fs::read_to_string(&metadata)
+            .expect("read Metadata.avsc")
+            .contains("\"user\"")
+    );
+}
+
+/// Run `avdl idl2schemata --output-template` and verify each schema is
+/// written where the template says, and that `avdl idl --out-dir` takes the
+/// same option.
+#[test]
+fn test_cli_output_template() {
+    let test_dir = PathBuf::from("tmp/cli-test-output-template");
+    let _ = fs::remove_dir_all(&test_dir);
+    fs::create_dir_all(&test_dir).expect("create test directory");
+    let avdl_path = test_dir.join("events.avdl");
+    fs::write(
+        &avdl_path,
+        r#"@namespace("com.example") protocol Events {
+            record Event { int x; }
+            @namespace("") record Plain { int y; }
+        }"#,
+    )
+    .expect("write events.avdl");
+    let out_dir = test_dir.join("out");
+
+    avdl_cmd()
+        .args([
+            "idl2schemata",
+            "--output-template",
+            "{stem}/{namespace_path}/{name}.schema.json",
+            avdl_path.to_str().expect("valid UTF-8 path"),
+            out_dir.to_str().expect("valid UTF-8 path"),
+        ])
+        .assert()
+        .success();
+    assert!(
+        out_dir
+            .join("events/com/example/Event.schema.json")
+            .exists()
+    );
+    assert!(out_dir.join("events/Plain.schema.json").exists());
+
+    avdl_cmd()
+        .args([
+            "idl",
+            "--out-dir",
+            out_dir.to_str().expect("valid UTF-8 path"),
+            "--output-template",
+            "{namespace}/{name}.{ext}",
+            avdl_path.to_str().expect("valid UTF-8 path"),
+        ])
+        .assert()
+        .success();
+    assert!(out_dir.join("com.example/Events.avpr").exists());
+
+    avdl_cmd()
+        .args([
+            "idl2schemata",
+            "--output-template",
+            "{nam}.avsc",
+            avdl_path.to_str().expect("valid UTF-8 path"),
+        ])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("unknown placeholder `{nam}`"));
+}
+
+/// Run `avdl idl2schemata --include/--exclude` and verify only the selected
+/// schemas are written.
+#[test]
+fn test_cli_idl2schemata_include_exclude() {
+    let test_dir = PathBuf::from("tmp/cli-test-idl2schemata-include-exclude");
+    let _ = fs::remove_dir_all(&test_dir);
+    fs::create_dir_all(&test_dir).expect("create test directory");
+    let avdl_path = test_dir.join("filter.avdl");
+    fs::write(
+        &avdl_path,
+        r#"@namespace("com.example") protocol P {
+            record User { string name; }
+            record Account { User owner; }
+            @namespace("com.other") enum Kind { A }
+        }"#,
+    )
+    .expect("write filter.avdl");
+    let out_dir = test_dir.join("out");
+
+    avdl_cmd()
+        .args([
+            "idl2schemata",
+            "--include",
+            "com.example.*",
+            "--exclude",
+            "*.User",
+            avdl_path.to_str().expect("valid UTF-8 path"),
+            out_dir.to_str().expect("valid UTF-8 path"),
+        ])
+        .assert()
+        .success();
+
+    assert!(out_dir.join("Account.avsc").exists());
+    assert!(!out_dir.join("User.avsc").exists());
+    assert!(!out_dir.join("Kind.avsc").exists());
+}
+
+/// Run `avdl idl2schemata` with no arguments and verify a non-zero exit code,
+/// since the `idl2schemata` subcommand requires an input argument.
+#[test]
+fn test_cli_idl2schemata_missing_input() {
+    avdl_cmd().args(["idl2schemata"]).assert().failure();
+}
+
+// ==============================================================================
+// `deps` Subcommand Tests
+// ==============================================================================
+
+/// Run `avdl deps --format json` on a file with an IDL import that itself
+/// imports a schema, and verify both edges are reported.
+#[test]
+fn test_cli_deps_json() {
+    let test_dir = PathBuf::from("tmp/cli-test-deps-json");
+    let _ = fs::remove_dir_all(&test_dir);
+    fs::create_dir_all(&test_dir).expect("create test directory");
+    fs::write(
+        test_dir.join("shared.avsc"),
+        r#"{"type": "enum", "name": "Kind", "symbols": ["A"]}"#,
+    )
+    .expect("write shared.avsc");
+    fs::write(
+        test_dir.join("common.avdl"),
+        "protocol Common { import schema \"shared.avsc\"; }",
+    )
+    .expect("write common.avdl");
+    let main_path = test_dir.join("main.avdl");
+    fs::write(&main_path, "protocol P { import idl \"common.avdl\"; }").expect("write main.avdl");
+
+    let output = avdl_cmd()
+        .args([
+            "deps",
+            "--format",
+            "json",
+            main_path.to_str().expect("valid UTF-8 path"),
+        ])
+        .output()
+        .expect("run avdl deps");
+    assert!(
+        output.status.success(),
+        "avdl deps should exit 0, stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let edges: Value = serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    let summary: Vec<(&str, &str)> = edges
+        .as_array()
+        .expect("edges should be an array")
+        .iter()
+        .map(|e| {
+            (
+                e["kind"].as_str().expect("kind is a string"),
+                e["path"].as_str().expect("path is a string"),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![("idl", "common.avdl"), ("schema", "shared.avsc")]
+    );
+}
+
+/// Run `avdl deps --overlay-dir` with a patched file shadowing one in the
+/// shared tree, and verify each import reports the overlay that satisfied it.
+#[test]
+fn test_cli_deps_overlay_dir() {
+    let test_dir = PathBuf::from("tmp/cli-test-deps-overlay-dir");
+    let _ = fs::remove_dir_all(&test_dir);
+    let patches = test_dir.join("patches");
+    let shared = test_dir.join("shared");
+    fs::create_dir_all(&patches).expect("create patches directory");
+    fs::create_dir_all(&shared).expect("create shared directory");
+    fs::write(
+        shared.join("common.avdl"),
+        "protocol Common { import schema \"kind.avsc\"; }",
+    )
+    .expect("write common.avdl");
+    fs::write(
+        shared.join("kind.avsc"),
+        r#"{"type": "enum", "name": "Kind", "symbols": ["A"]}"#,
+    )
+    .expect("write shared kind.avsc");
+    fs::write(
+        patches.join("kind.avsc"),
+        r#"{"type": "enum", "name": "Kind", "symbols": ["A", "B"]}"#,
+    )
+    .expect("write patched kind.avsc");
+    let main_path = test_dir.join("main.avdl");
+    fs::write(&main_path, "protocol P { import idl \"common.avdl\"; }").expect("write main.avdl");
+
+    let output = avdl_cmd()
+        .args([
+            "deps",
+            "--overlay-dir",
+            patches.to_str().expect("valid UTF-8 path"),
+            "--overlay-dir",
+            shared.to_str().expect("valid UTF-8 path"),
+            main_path.to_str().expect("valid UTF-8 path"),
+        ])
+        .output()
+        .expect("run avdl deps");
+    assert!(
+        output.status.success(),
+        "avdl deps should exit 0, stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().filter(|l| l.starts_with("  ")).collect();
+    assert_eq!(lines.len(), 2, "{stdout}");
+    assert!(
+        lines[0].ends_with(&format!("(from {})", shared.display())),
+        "{stdout}"
+    );
+    assert!(
+        lines[1].ends_with(&format!("(from {})", patches.display())),
+        "{stdout}"
+    );
+}
+
+// ==============================================================================
+// `graph` Subcommand Tests
+// ==============================================================================
+
+/// Run `avdl graph --format dot` and verify the output is a DOT digraph with
+/// an edge from the referencing record to the referenced type.
+#[test]
+fn test_cli_graph_dot() {
+    let test_dir = PathBuf::from("tmp/cli-test-graph-dot");
+    fs::create_dir_all(&test_dir).expect("create test directory");
+    let avdl_path = test_dir.join("graph.avdl");
+    fs::write(&avdl_path, "protocol G { enum E { A } record R { E e; } }")
+        .expect("write graph.avdl");
+
+    let output = avdl_cmd()
+        .args([
+            "graph",
+            "--format",
+            "dot",
+            avdl_path.to_str().expect("valid UTF-8 path"),
+        ])
+        .output()
+        .expect("run avdl graph");
+    assert!(
+        output.status.success(),
+        "avdl graph should exit 0, stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout should be valid UTF-8");
+    assert!(stdout.starts_with("digraph \"G\" {"), "got:\n{stdout}");
+    assert!(stdout.contains("\"R\" -> \"E\";"), "got:\n{stdout}");
+}
+
+// ==============================================================================
+// `check` Subcommand Tests
+// ==============================================================================
+
+/// Run `avdl check` on a directory where two files define the same record
+/// differently, and verify it fails naming the type and both files.
+#[test]
+fn test_cli_check_reports_conflicts() {
+    let test_dir = PathBuf::from("tmp/cli-test-check-conflicts");
+    let _ = fs::remove_dir_all(&test_dir);
+    fs::create_dir_all(&test_dir).expect("create test directory");
+    fs::write(
+        test_dir.join("billing.avdl"),
+        "@namespace(\"com.company\") protocol Billing { record Money { long cents; } }",
+    )
+    .expect("write billing.avdl");
+    fs::write(
+        test_dir.join("orders.avdl"),
+        "@namespace(\"com.company\") protocol Orders { record Money { double amount; } }",
+    )
+    .expect("write orders.avdl");
+
+    avdl_cmd()
+        .args(["check", test_dir.to_str().expect("valid UTF-8 path")])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("com.company.Money"))
+        .stderr(predicates::str::contains("billing.avdl"))
+        .stderr(predicates::str::contains("orders.avdl"));
+
+    fs::remove_file(test_dir.join("orders.avdl")).expect("remove orders.avdl");
+    avdl_cmd()
+        .args(["check", test_dir.to_str().expect("valid UTF-8 path")])
+        .assert()
+        .success();
+}
+
+/// `avdl fix` rewrites the input with its fixes and reports each one.
+#[test]
+fn test_cli_fix_rewrites_input() {
+    let test_dir = PathBuf::from("tmp/cli-test-fix");
+    let _ = fs::remove_dir_all(&test_dir);
+    fs::create_dir_all(test_dir.join("shared")).expect("create test directory");
+    fs::write(
+        test_dir.join("shared/money.avdl"),
+        "@namespace(\"com.company\") protocol Shared { record Money { long cents; } }",
+    )
+    .expect("write money.avdl");
+    let avdl_path = test_dir.join("orders.avdl");
+    fs::write(
+        &avdl_path,
+        "@namespace(\"com.company\") protocol Orders {\n  enum Status { NEW, DONE }\n  \
+         record Order { Money total; union { null, string, null } note; Status s = NEW; }\n}\n",
+    )
+    .expect("write orders.avdl");
+
+    avdl_cmd()
+        .args([
+            "fix",
+            "--import-dir",
+            test_dir.join("shared").to_str().expect("valid UTF-8 path"),
+            avdl_path.to_str().expect("valid UTF-8 path"),
+        ])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("replace `NEW` with `\"NEW\"`"))
+        .stderr(predicates::str::contains("duplicate `null`"))
+        .stderr(predicates::str::contains("import idl \"money.avdl\";"));
+    assert_eq!(
+        fs::read_to_string(&avdl_path).expect("read orders.avdl"),
+        "@namespace(\"com.company\") protocol Orders {\n  import idl \"money.avdl\";\n  \
+         enum Status { NEW, DONE }\n  \
+         record Order { Money total; union { null, string } note; Status s = \"NEW\"; }\n}\n"
+    );
+}
+
+/// `avdl defaults` prints each field's default with nested record defaults
+/// filled in.
+#[test]
+fn test_cli_defaults_expands_nested_records() {
+    let test_dir = PathBuf::from("tmp/cli-test-defaults");
+    fs::create_dir_all(&test_dir).expect("create test directory");
+    let avdl_path = test_dir.join("defaults.avdl");
+    fs::write(
+        &avdl_path,
+        "protocol P { record Money { long cents = 0; string currency = \"USD\"; } \
+         record Order { string id; Money total = {\"cents\": 5}; } }",
+    )
+    .expect("write defaults.avdl");
+
+    avdl_cmd()
+        .args(["defaults", avdl_path.to_str().expect("valid UTF-8 path")])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "Order\n  id (no default)\n  total = {\"cents\":5,\"currency\":\"USD\"}\n",
+        ));
+}
+
+/// `avdl diff` matches types by name, so only real changes are printed.
+#[test]
+fn test_cli_diff_ignores_reordering() {
+    let test_dir = PathBuf::from("tmp/cli-test-diff");
+    fs::create_dir_all(&test_dir).expect("create test directory");
+    let old_path = test_dir.join("old.avdl");
+    let new_path = test_dir.join("new.avdl");
+    fs::write(
+        &old_path,
+        "protocol P { record A { int x = 1; } enum E { X, Y } }",
+    )
+    .expect("write old.avdl");
+    fs::write(
+        &new_path,
+        "protocol P { enum E { X, Y } record A { int x = 2; } }",
+    )
+    .expect("write new.avdl");
+
+    avdl_cmd()
+        .args([
+            "diff",
+            old_path.to_str().expect("valid UTF-8 path"),
+            new_path.to_str().expect("valid UTF-8 path"),
+        ])
+        .assert()
+        .success()
+        .stdout("~ field A.x: default 1 -> 2\n");
+}
+
+/// `avdl equal` compares an .avdl schema with an .avsc file by Parsing
+/// Canonical Form, so docs do not matter but field types do.
+#[test]
+fn test_cli_equal_compares_canonical_forms() {
+    let test_dir = PathBuf::from("tmp/cli-test-equal");
+    fs::create_dir_all(&test_dir).expect("create test directory");
+    let idl_path = test_dir.join("user.avdl");
+    let same_path = test_dir.join("same.avsc");
+    let changed_path = test_dir.join("changed.avsc");
+    fs::write(
+        &idl_path,
+        "namespace ex; schema User; /** A user. */ record User { long id; }",
+    )
+    .expect("write user.avdl");
+    fs::write(
+        &same_path,
+        r#"{"type": "record", "name": "ex.User", "fields": [{"name": "id", "type": "long"}]}"#,
+    )
+    .expect("write same.avsc");
+    fs::write(
+        &changed_path,
+        r#"{"type": "record", "name": "ex.User", "fields": [{"name": "id", "type": "int"}]}"#,
+    )
+    .expect("write changed.avsc");
+
+    let idl = idl_path.to_str().expect("valid UTF-8 path");
+    avdl_cmd()
+        .args(["equal", idl, same_path.to_str().expect("valid UTF-8 path")])
+        .assert()
+        .success();
+    avdl_cmd()
+        .args([
+            "equal",
+            idl,
+            changed_path.to_str().expect("valid UTF-8 path"),
+        ])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("the same schema"));
+}
+
+/// `avdl doc` writes an index and one linked page per named type, or a
+/// single Markdown or JSON file with `--format markdown` or `--format json`.
+#[test]
+fn test_cli_doc_writes_linked_pages() {
+    let test_dir = PathBuf::from("tmp/cli-test-doc");
+    let out_dir = test_dir.join("site");
+    fs::create_dir_all(&test_dir).expect("create test directory");
+    let input_path = test_dir.join("shop.avdl");
+    fs::write(
+        &input_path,
+        r#"@namespace("ex") protocol Shop {
+            enum Status { NEW, DONE }
+            record Order { /** The order's state. */ Status status; }
+        }"#,
+    )
+    .expect("write shop.avdl");
+
+    avdl_cmd()
+        .args([
+            "doc",
+            input_path.to_str().expect("valid UTF-8 path"),
+            out_dir.to_str().expect("valid UTF-8 path"),
+        ])
+        .assert()
+        .success();
+
+    let index = fs::read_to_string(out_dir.join("index.html")).expect("read index.html");
+    assert!(index.contains("<a href=\"ex.Order.html\">"));
+    let order = fs::read_to_string(out_dir.join("ex.Order.html")).expect("read ex.Order.html");
+    assert!(order.contains("<a href=\"ex.Status.html\">ex.Status</a>"));
+    assert!(out_dir.join("ex.Status.html").is_file());
+
+    avdl_cmd()
+        .args([
+            "doc",
+            "--format",
+            "markdown",
+            input_path.to_str().expect("valid UTF-8 path"),
+            out_dir.to_str().expect("valid UTF-8 path"),
+        ])
+        .assert()
+        .success();
+    let markdown = fs::read_to_string(out_dir.join("ex.Shop.md")).expect("read ex.Shop.md");
+    assert!(markdown.contains("| `status` | [ex.Status](#ex.Status) |  | The order's state. |"));
+
+    avdl_cmd()
+        .args([
+            "doc",
+            "--format",
+            "json",
+            input_path.to_str().expect("valid UTF-8 path"),
+            out_dir.to_str().expect("valid UTF-8 path"),
+        ])
+        .assert()
+        .success();
+    let docs: Value = serde_json::from_str(
+        &fs::read_to_string(out_dir.join("ex.Shop.docs.json")).expect("read ex.Shop.docs.json"),
+    )
+    .expect("valid JSON");
+    assert_eq!(docs["ex.Order.status"], "The order's state.");
+}
+
+/// `avdl verify` needs the avro-tools JAR to compare against.
+#[test]
+fn test_cli_verify_requires_against() {
+    avdl_cmd()
+        .args(["verify", &format!("{INPUT_DIR}/simple.avdl")])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("--against"));
+}
+
+/// `--color` overrides color detection, and `NO_COLOR` disables it.
+#[test]
+fn test_cli_color_choice() {
+    let input = "protocol P { record R { int x; long x; } }";
+    avdl_cmd()
+        .args(["--color", "always", "idl"])
+        .write_stdin(input)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("\x1b["));
+    let output = avdl_cmd()
+        .args(["--color=never", "--diagnostic-width", "0", "idl"])
+        .env("NO_COLOR", "1")
+        .write_stdin(input)
+        .output()
+        .expect("run avdl");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("duplicate field"), "{stderr}");
+    assert!(!stderr.contains('\x1b'), "{stderr}");
+    avdl_cmd()
+        .args(["--color", "sometimes", "idl"])
+        .assert()
+        .code(2)
+        .stderr(predicates::str::contains("--color"));
+}
+
+/// `-q` drops warnings, and `-v` says which import brought in a warning.
+#[test]
+fn test_cli_quiet_and_verbose() {
+    let test_dir = PathBuf::from("tmp/cli-test-quiet-and-verbose");
+    fs::create_dir_all(&test_dir).expect("create test directory");
+    fs::write(
+        test_dir.join("common.avdl"),
+        "protocol Common { record Id { string value; /** orphan */ } }",
+    )
+    .expect("write common.avdl");
+    let main = test_dir.join("main.avdl");
+    fs::write(
+        &main,
+        r#"protocol P { import idl "common.avdl"; record R { Id id; } }"#,
+    )
+    .expect("write main.avdl");
+    let main = main.to_str().expect("valid UTF-8 path");
+
+    avdl_cmd()
+        .args(["-q", "idl", main])
+        .assert()
+        .success()
+        .stderr("");
+    avdl_cmd()
+        .args(["-v", "idl", main])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains("resolved to "))
+        .stderr(predicates::str::contains("imported by "))
+        .stderr(predicates::str::contains(
+            r#"with `import idl "common.avdl"`"#,
+        ));
+}
+
+/// `--warnings-format ndjson` prints each warning as one JSON line.
+#[test]
+fn test_cli_warnings_format_ndjson() {
+    let input = "protocol P {\n  record R { int x; /** orphan */ }\n}";
+    let output = avdl_cmd()
+        .args(["--warnings-format", "ndjson", "idl"])
+        .write_stdin(input)
+        .output()
+        .expect("run avdl idl");
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).expect("valid UTF-8");
+    let lines: Vec<&str> = stderr.lines().collect();
+    assert_eq!(lines.len(), 1, "{stderr}");
+    let warning: Value = serde_json::from_str(lines[0]).expect("valid JSON");
+    assert_eq!(warning["code"], "AVDL0101");
+    assert_eq!(warning["category"], "out-of-place-doc");
+    assert_eq!(warning["span"]["line"], 2);
+    assert_eq!(warning["span"]["column"], 21);
+    assert!(
+        warning["message"]
+            .as_str()
+            .expect("message is a string")
+            .contains("out-of-place documentation comment")
+    );
+}
+
+/// `--detailed-exit-codes` tells an invalid file, an undefined name, a
+/// missing import, and denied warnings apart.
+#[test]
+fn test_cli_detailed_exit_codes() {
+    for (input, code) in [
+        ("protocol P { record R { int x; long x; } }", 3),
+        ("protocol P { record R { Missing x; } }", 4),
+        (r#"protocol P { import idl "missing.avdl"; }"#, 5),
+        ("protocol P { record R { int x; /** orphan */ } }", 6),
+    ] {
+        avdl_cmd()
+            .args(["--detailed-exit-codes", "idl", "--deny-warnings"])
+            .write_stdin(input)
+            .assert()
+            .code(code);
+    }
+    avdl_cmd()
+        .args(["--detailed-exit-codes", "idl", "--no-such-option"])
+        .assert()
+        .code(2);
+    avdl_cmd()
+        .args(["idl"])
+        .write_stdin("protocol P { record R { Missing x; } }")
+        .assert()
+        .code(1);
+}
+
+/// Errors show their code, and `avdl explain` describes it.
+#[test]
+fn test_cli_explain_error_code() {
+    avdl_cmd()
+        .arg("idl")
+        .write_stdin("protocol P { record R { int x; long x; } }")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("AVDL0004"));
+    avdl_cmd()
+        .args(["explain", "avdl0004"])
+        .assert()
+        .success()
+        .stdout(predicates::str::starts_with(
+            "AVDL0004: duplicate field or parameter\n",
+        ));
+    avdl_cmd()
+        .args(["explain", "AVDL9999"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("avdl explain"));
+}
+
+// ==============================================================================
+// `tool` Drop-in Mode Tests
+// ==============================================================================
+
+/// `avdl tool idl` with no arguments reads stdin and writes stdout, like
+/// `avro-tools idl`.
+#[test]
+fn test_cli_tool_idl_stdin_to_stdout() {
+    let output = avdl_cmd()
+        .args(["tool", "idl"])
+        .write_stdin("protocol P { record R { int x; } }")
+        .output()
+        .expect("run avdl tool idl");
+    assert!(
+        output.status.success(),
+        "avdl tool idl should exit 0, stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let actual: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be valid JSON");
+    assert_eq!(actual["protocol"], "P");
+}
+
+/// `avdl tool` rejects avdl-only options and extra positionals with the
+/// `avro-tools` usage line and exit status 255.
+#[test]
+fn test_cli_tool_usage_errors() {
+    avdl_cmd()
+        .args(["tool", "idl", "a.avdl", "b.avpr", "extra"])
+        .assert()
+        .code(255)
+        .stderr(predicates::str::contains("Usage: idl [in] [out]"));
+    avdl_cmd()
+        .args(["tool", "idl2schemata"])
+        .assert()
+        .code(255)
+        .stderr(predicates::str::contains(
+            "Usage: idl2schemata [idl] [outdir]",
+        ));
+    avdl_cmd()
+        .args(["tool", "compile"])
+        .assert()
+        .code(255)
+        .stderr(predicates::str::contains("Unknown tool: compile"));
 }
 
 // ==============================================================================
@@ -0,0 +1,90 @@
+//! WebAssembly bindings for the `avdl` Avro IDL compiler.
+//!
+//! Exposes a single `compile` function to JavaScript. There is no filesystem
+//! in the browser, so every file the source imports has to be passed in
+//! alongside it.
+//!
+//! ```js
+//! import init, { compile } from "avdl-wasm";
+//!
+//! await init();
+//! const { output, warnings } = compile(
+//!   `protocol P { import idl "common.avdl"; record R { Id id; } }`,
+//!   { "common.avdl": "protocol Common { record Id { string value; } }" },
+//! );
+//! ```
+
+use avdl::Idl;
+use miette::{GraphicalReportHandler, GraphicalTheme};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPES: &str = r#"
+/** Imported files, by the path they are imported as. */
+export type ImportMap = Record<string, string>;
+
+export interface CompileResult {
+  /** The `.avpr` protocol object or `.avsc` schema value. */
+  output: unknown;
+  /** Non-fatal warnings, rendered as text. */
+  warnings: string[];
+}
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "ImportMap")]
+    pub type ImportMap;
+
+    #[wasm_bindgen(typescript_type = "CompileResult")]
+    pub type CompileResult;
+}
+
+/// Compile Avro IDL `source`, resolving `import` statements against
+/// `imports`. Throws an `Error` whose message is the rendered diagnostic if
+/// the source does not compile.
+///
+/// The source and imported files are kept for the lifetime of the module,
+/// since compiled schemas and diagnostics refer back into them, but each
+/// distinct text only once, so recompiling unchanged files costs no memory.
+#[wasm_bindgen]
+pub fn compile(source: String, imports: Option<ImportMap>) -> Result<CompileResult, JsError> {
+    let mut idl = Idl::new();
+    if let Some(imports) = imports {
+        let imports: &js_sys::Object = imports.unchecked_ref();
+        for entry in js_sys::Object::entries(imports).iter() {
+            let entry: js_sys::Array = entry.unchecked_into();
+            let (Some(path), Some(contents)) = (entry.get(0).as_string(), entry.get(1).as_string())
+            else {
+                return Err(JsError::new("import map values must be strings"));
+            };
+            idl.import_source(path, contents);
+        }
+    }
+
+    let output = idl
+        .convert_string(source)
+        .map_err(|e| JsError::new(&render(&e)))?;
+
+    // Serialized by hand rather than through a `serde_json::Value`, which
+    // would sort a protocol's messages by name.
+    let warnings: Vec<_> = output.warnings.iter().map(render).collect();
+    let (Ok(json), Ok(warnings)) = (
+        serde_json::to_string(&output.ordered_json()),
+        serde_json::to_string(&warnings),
+    ) else {
+        return Err(JsError::new("serialize compiler output"));
+    };
+    let result = js_sys::JSON::parse(&format!(r#"{{"output":{json},"warnings":{warnings}}}"#))
+        .map_err(|_| JsError::new("serialize compiler output"))?;
+    Ok(result.unchecked_into())
+}
+
+/// Render a diagnostic with its source snippet, without terminal colors.
+fn render(report: &miette::Report) -> String {
+    let mut rendered = String::new();
+    GraphicalReportHandler::new_themed(GraphicalTheme::unicode_nocolor())
+        .render_report(&mut rendered, report.as_ref())
+        .expect("render to String is infallible");
+    rendered
+}